@@ -0,0 +1,89 @@
+#ifndef A2LTOOL_H
+#define A2LTOOL_H
+
+/* Generated with cbindgen --config cbindgen.toml --output include/a2ltool.h */
+
+#include <stdbool.h>
+#include <stddef.h>
+
+// opaque handle to a loaded a2l file
+typedef struct A2lHandle A2lHandle;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Get the error message of the last call that failed on the current thread.
+//
+// Returns NULL if no error has occurred. The string remains valid until the next failing call on this thread.
+const char *a2ltool_last_error(void);
+
+// Load an a2l file.
+//
+// Returns NULL if the file could not be loaded. The handle must be released with [`a2ltool_free`].
+//
+// # Safety
+//
+// `filename` must be a valid pointer to a nul-terminated string
+A2lHandle *a2ltool_load(const char *filename, bool strict);
+
+// Create a new, empty a2l file.
+//
+// The handle must be released with [`a2ltool_free`].
+A2lHandle *a2ltool_create(void);
+
+// Update the addresses of all objects in the a2l file using the debug info of an elf file.
+//
+// If `preserve_unknown` is true, then objects that are not found in the elf file are kept and their address is set to zero.
+// Otherwise these objects are removed.
+//
+// # Safety
+//
+// `handle` must be a handle returned by [`a2ltool_load`] or [`a2ltool_create`] that has not been freed.
+// `elf_filename` must be a valid pointer to a nul-terminated string
+int a2ltool_update(A2lHandle *handle, const char *elf_filename, bool preserve_unknown);
+
+// Merge the content of another a2l file into the modules of this file.
+//
+// # Safety
+//
+// `handle` must be a handle returned by [`a2ltool_load`] or [`a2ltool_create`] that has not been freed.
+// `filename` must be a valid pointer to a nul-terminated string
+int a2ltool_merge(A2lHandle *handle, const char *filename);
+
+// Write the a2l file.
+//
+// # Safety
+//
+// `handle` must be a handle returned by [`a2ltool_load`] or [`a2ltool_create`] that has not been freed.
+// `filename` must be a valid pointer to a nul-terminated string
+int a2ltool_write(A2lHandle *handle, const char *filename);
+
+// Get the number of messages that were generated by the operations on this handle.
+//
+// # Safety
+//
+// `handle` must be a handle returned by [`a2ltool_load`] or [`a2ltool_create`] that has not been freed.
+size_t a2ltool_message_count(const A2lHandle *handle);
+
+// Get a message that was generated by an operation on this handle.
+//
+// Returns NULL if the index is out of range. The string remains valid until the handle is freed.
+//
+// # Safety
+//
+// `handle` must be a handle returned by [`a2ltool_load`] or [`a2ltool_create`] that has not been freed.
+const char *a2ltool_message(const A2lHandle *handle, size_t index);
+
+// Release a handle. Passing NULL is allowed and has no effect.
+//
+// # Safety
+//
+// `handle` must be NULL or a handle returned by [`a2ltool_load`] or [`a2ltool_create`] that has not been freed yet.
+void a2ltool_free(A2lHandle *handle);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* A2LTOOL_H */
@@ -0,0 +1,149 @@
+//! Python bindings for a2ltool
+//!
+//! The module pya2ltool provides the class A2lFile and the functions load() and new().
+//! All operations that can fail raise a RuntimeError.
+
+use a2lfile::{A2lError, A2lObject};
+use a2ltool::{check, insert, merge, update, DebugData};
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+use std::ffi::OsStr;
+
+/// An a2l file, which has been loaded or newly created
+#[pyclass(name = "A2lFile")]
+struct PyA2lFile {
+    a2l_file: a2lfile::A2lFile,
+}
+
+fn to_pyerr(err: String) -> PyErr {
+    PyRuntimeError::new_err(err)
+}
+
+#[pymethods]
+impl PyA2lFile {
+    /// Merge the content of another a2l file into the modules of this file
+    fn merge(&mut self, filename: &str) -> PyResult<()> {
+        merge::merge_module_file(&mut self.a2l_file, OsStr::new(filename), false).map_err(to_pyerr)
+    }
+
+    /// Merge another a2l file at the project level: all of its modules are added to this file
+    fn merge_project(&mut self, filename: &str) -> PyResult<()> {
+        merge::merge_project_file(&mut self.a2l_file, OsStr::new(filename), false).map_err(to_pyerr)
+    }
+
+    /// Merge all /include files into this file
+    fn merge_includes(&mut self) {
+        self.a2l_file.merge_includes();
+    }
+
+    /// Update the addresses of all objects based on the debug info in an elf file.
+    /// Returns the list of messages generated during the update.
+    #[pyo3(signature = (elf_filename, preserve_unknown=false, enable_structures=true))]
+    fn update(
+        &mut self,
+        elf_filename: &str,
+        preserve_unknown: bool,
+        enable_structures: bool,
+    ) -> PyResult<Vec<String>> {
+        let debug_data = DebugData::load(OsStr::new(elf_filename), false).map_err(to_pyerr)?;
+        let mut log_msgs = Vec::new();
+        update::update_addresses(
+            &mut self.a2l_file,
+            &debug_data,
+            &mut log_msgs,
+            preserve_unknown,
+            enable_structures,
+        );
+        Ok(log_msgs)
+    }
+
+    /// Insert new MEASUREMENTs and CHARACTERISTICs for the given symbols of an elf file.
+    /// Returns the list of messages generated while inserting.
+    #[pyo3(signature = (elf_filename, measurements=Vec::new(), characteristics=Vec::new(), target_group=None, enable_structures=true))]
+    fn insert(
+        &mut self,
+        elf_filename: &str,
+        measurements: Vec<String>,
+        characteristics: Vec<String>,
+        target_group: Option<&str>,
+        enable_structures: bool,
+    ) -> PyResult<Vec<String>> {
+        let debug_data = DebugData::load(OsStr::new(elf_filename), false).map_err(to_pyerr)?;
+        let mut log_msgs = Vec::new();
+        insert::insert_items(
+            &mut self.a2l_file,
+            &debug_data,
+            measurements.iter().map(String::as_str).collect(),
+            characteristics.iter().map(String::as_str).collect(),
+            target_group,
+            None,
+            &mut log_msgs,
+            enable_structures,
+        );
+        Ok(log_msgs)
+    }
+
+    /// Perform a consistency check, optionally with additional rules from a rules file.
+    /// Returns the list of problems; an empty list means that no problems were found.
+    #[pyo3(signature = (rules_filename=None))]
+    fn check(&self, rules_filename: Option<&str>) -> PyResult<Vec<String>> {
+        let mut log_msgs = Vec::new();
+        self.a2l_file.check(&mut log_msgs);
+        if let Some(rules_filename) = rules_filename {
+            let rules = check::CheckRules::load(OsStr::new(rules_filename)).map_err(to_pyerr)?;
+            check::check_rules(&self.a2l_file, &rules, &mut log_msgs);
+        }
+        Ok(log_msgs)
+    }
+
+    /// Sort all elements of the file
+    fn sort(&mut self) {
+        self.a2l_file.sort();
+    }
+
+    /// Remove unreferenced items and empty groups
+    fn cleanup(&mut self) {
+        self.a2l_file.cleanup();
+    }
+
+    /// Write the a2l file
+    fn write(&mut self, filename: &str) -> PyResult<()> {
+        self.a2l_file.sort_new_items();
+        let banner = format!("pya2ltool {}", env!("CARGO_PKG_VERSION"));
+        self.a2l_file
+            .write(filename, Some(&banner))
+            .map_err(|err| to_pyerr(err.to_string()))
+    }
+
+    /// Get the content of the a2l file as a string
+    fn to_string(&mut self) -> String {
+        self.a2l_file.sort_new_items();
+        self.a2l_file.write_to_string()
+    }
+}
+
+/// Load an a2l file
+#[pyfunction]
+#[pyo3(signature = (filename, strict=false))]
+fn load(filename: &str, strict: bool) -> PyResult<PyA2lFile> {
+    let mut log_msgs = Vec::<A2lError>::new();
+    let a2l_file = a2ltool::load::load_a2l(OsStr::new(filename), None, strict, &mut log_msgs)
+        .map_err(to_pyerr)?;
+    Ok(PyA2lFile { a2l_file })
+}
+
+/// Create a new, empty a2l file
+#[pyfunction]
+fn new() -> PyA2lFile {
+    PyA2lFile {
+        a2l_file: a2ltool::load::new_a2l(),
+    }
+}
+
+#[pymodule]
+fn pya2ltool(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyA2lFile>()?;
+    m.add_function(wrap_pyfunction!(load, m)?)?;
+    m.add_function(wrap_pyfunction!(new, m)?)?;
+    Ok(())
+}
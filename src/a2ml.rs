@@ -0,0 +1,561 @@
+//! Handling of A2ML specifications
+
+use a2lfile::{A2lFile, A2ml};
+use std::ffi::OsStr;
+
+/// load an A2ML specification from a file.
+/// The file may either contain the bare A2ML text, or a complete "/begin A2ML ... /end A2ML" block
+pub fn load_a2ml_file(filename: &OsStr) -> Result<String, String> {
+    let text = std::fs::read_to_string(filename).map_err(|err| {
+        format!(
+            "Error: could not read A2ML file {}: {err}",
+            filename.to_string_lossy()
+        )
+    })?;
+    Ok(strip_a2ml_block(&text).to_string())
+}
+
+/// write the A2ML of the first module that contains one to a file
+pub fn extract_a2ml(a2l_file: &A2lFile, filename: &OsStr) -> Result<(), String> {
+    let a2ml = a2l_file
+        .project
+        .module
+        .iter()
+        .find_map(|module| module.a2ml.as_ref())
+        .ok_or_else(|| "Error: the a2l file does not contain an A2ML block".to_string())?;
+    std::fs::write(filename, a2ml.a2ml_text.trim_start_matches(['\r', '\n'])).map_err(|err| {
+        format!(
+            "Error: could not write A2ML file {}: {err}",
+            filename.to_string_lossy()
+        )
+    })
+}
+
+/// replace the A2ML in all modules with the given text. Modules without A2ML get a new A2ML block
+pub fn replace_a2ml(a2l_file: &mut A2lFile, a2ml_text: &str) -> Result<(), String> {
+    validate_a2ml(a2ml_text)?;
+
+    // the text should start on a new line after "/begin A2ML"; "/end A2ML" is always placed on a new line
+    let a2ml_text = format!(
+        "\n{}",
+        a2ml_text.trim_start_matches(['\r', '\n']).trim_end()
+    );
+    for module in &mut a2l_file.project.module {
+        if let Some(a2ml) = &mut module.a2ml {
+            a2ml.a2ml_text.clone_from(&a2ml_text);
+        } else {
+            module.a2ml = Some(A2ml::new(a2ml_text.clone()));
+        }
+    }
+    Ok(())
+}
+
+// a2lfile does not offer a way to parse A2ML directly, but an invalid
+// A2ML specification that is passed to the loader causes an error
+fn validate_a2ml(a2ml_text: &str) -> Result<(), String> {
+    let mut log_msgs = Vec::new();
+    a2lfile::load_from_string(
+        r#"/begin PROJECT p "" /begin MODULE m "" /end MODULE /end PROJECT"#,
+        Some(a2ml_text.to_string()),
+        &mut log_msgs,
+        false,
+    )
+    .map(|_| ())
+    .map_err(|err| format!("Error: invalid A2ML: {err}"))
+}
+
+// remove the enclosing "/begin A2ML" and "/end A2ML" from the text, if they are present
+fn strip_a2ml_block(text: &str) -> &str {
+    let trimmed = text.trim();
+    if let Some(inner) = trimmed
+        .strip_prefix("/begin")
+        .map(str::trim_start)
+        .and_then(|rest| rest.strip_prefix("A2ML"))
+    {
+        if let Some(inner) = inner
+            .trim_end()
+            .strip_suffix("A2ML")
+            .map(str::trim_end)
+            .and_then(|rest| rest.strip_suffix("/end"))
+        {
+            return inner;
+        }
+    }
+    text
+}
+
+/// combine the built-in A2ML specification with an additional specification.
+/// Both must contain a block "IF_DATA" taggedunion. The items of the additional IF_DATA are appended
+/// to the items of the built-in IF_DATA, so that they override built-in items that use the same tag.
+/// Type definitions in the additional specification are placed in front of the built-in specification.
+pub fn merge_a2ml_spec(builtin: &str, additional: &str) -> Result<String, String> {
+    let additional_block = find_ifdata_block(additional).ok_or_else(|| {
+        "Error: the A2ML specification does not contain a block \"IF_DATA\" taggedunion { ... }"
+            .to_string()
+    })?;
+    let builtin_block = find_ifdata_block(builtin)
+        .ok_or_else(|| "impossible: the built-in A2ML has no IF_DATA block".to_string())?;
+
+    let mut merged = String::with_capacity(builtin.len() + additional.len() + 2);
+    merged.push_str(&additional[..additional_block.start]);
+    merged.push_str(&builtin[..builtin_block.body_end]);
+    merged.push('\n');
+    merged.push_str(&additional[additional_block.body_start..additional_block.body_end]);
+    merged.push('\n');
+    merged.push_str(&builtin[builtin_block.body_end..]);
+    merged.push_str(&additional[additional_block.end..]);
+
+    Ok(merged)
+}
+
+// byte offsets of the parts of the block "IF_DATA" in an A2ML specification
+struct IfDataBlock {
+    // start of the keyword "block"
+    start: usize,
+    // first character after the opening brace of the taggedunion
+    body_start: usize,
+    // position of the closing brace of the taggedunion
+    body_end: usize,
+    // first character after the closing brace and the optional semicolon
+    end: usize,
+}
+
+fn find_ifdata_block(text: &str) -> Option<IfDataBlock> {
+    let tokens = tokenize(text);
+
+    let mut idx = 0;
+    while idx + 3 < tokens.len() {
+        if tokens[idx].1 == "block"
+            && tokens[idx + 1].1 == "\"IF_DATA\""
+            && tokens[idx + 2].1 == "taggedunion"
+        {
+            let start = tokens[idx].0;
+            // the taggedunion may optionally have a name
+            let mut open_idx = idx + 3;
+            if tokens[open_idx].1 != "{" {
+                open_idx += 1;
+            }
+            if tokens.get(open_idx)?.1 != "{" {
+                return None;
+            }
+            let body_start = tokens[open_idx].0 + 1;
+
+            let mut depth = 0;
+            for (close_idx, (pos, tok)) in tokens.iter().enumerate().skip(open_idx) {
+                match *tok {
+                    "{" => depth += 1,
+                    "}" => {
+                        depth -= 1;
+                        if depth == 0 {
+                            let end = match tokens.get(close_idx + 1) {
+                                Some((semicolon_pos, ";")) => semicolon_pos + 1,
+                                _ => pos + 1,
+                            };
+                            return Some(IfDataBlock {
+                                start,
+                                body_start,
+                                body_end: *pos,
+                                end,
+                            });
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            return None;
+        }
+        idx += 1;
+    }
+
+    None
+}
+
+/// reformat an A2ML specification, so that every declaration and every member is on a line of its own
+/// and is indented according to its nesting depth. Comments are kept.
+/// If sort is set, the members of each taggedstruct and taggedunion are sorted by their tags; unlike the
+/// members of a struct, the order of these members has no meaning.
+pub fn format_a2ml(a2ml_text: &str, sort: bool) -> Result<String, String> {
+    let tokens = tokenize_with_comments(a2ml_text);
+    let mut pos = 0;
+    let mut items = parse_a2ml_items(a2ml_text, &tokens, &mut pos, false)?;
+    if sort {
+        sort_tagged_members(&mut items);
+    }
+
+    let mut writer = A2mlWriter::default();
+    writer.write_items(&items);
+    validate_a2ml(&writer.text)?;
+    Ok(writer.text)
+}
+
+/// reformat the A2ML of all modules with format_a2ml. Returns the number of modules whose A2ML was changed
+pub fn format_module_a2ml(a2l_file: &mut A2lFile, sort: bool) -> Result<usize, String> {
+    let mut count = 0;
+    for module in &mut a2l_file.project.module {
+        if let Some(a2ml) = &mut module.a2ml {
+            // same layout as in replace_a2ml: the text starts on a new line after "/begin A2ML"
+            let formatted = format!("\n{}", format_a2ml(&a2ml.a2ml_text, sort)?.trim_end());
+            if formatted != a2ml.a2ml_text {
+                a2ml.a2ml_text = formatted;
+                count += 1;
+            }
+        }
+    }
+    Ok(count)
+}
+
+// a declaration or a member in an A2ML specification. It ends with ";", or with "," inside an enum
+struct A2mlItem<'a> {
+    parts: Vec<A2mlPart<'a>>,
+    terminator: Option<&'a str>,
+    // a line comment on the same line after the terminator
+    trailing_comment: Option<&'a str>,
+}
+
+enum A2mlPart<'a> {
+    Token(&'a str),
+    Comment(&'a str),
+    // the items between "{" and "}"
+    Body(Vec<A2mlItem<'a>>),
+}
+
+impl<'a> A2mlItem<'a> {
+    // the tag of a member of a taggedstruct or taggedunion is the first string
+    fn tag(&self) -> Option<&'a str> {
+        self.parts.iter().find_map(|part| match part {
+            A2mlPart::Token(token) if token.starts_with('"') => Some(*token),
+            _ => None,
+        })
+    }
+}
+
+fn parse_a2ml_items<'a>(
+    text: &'a str,
+    tokens: &[(usize, &'a str)],
+    pos: &mut usize,
+    nested: bool,
+) -> Result<Vec<A2mlItem<'a>>, String> {
+    let mut items = Vec::new();
+    let mut parts = Vec::new();
+
+    while let Some(&(offset, token)) = tokens.get(*pos) {
+        *pos += 1;
+        match token {
+            "{" => parts.push(A2mlPart::Body(parse_a2ml_items(text, tokens, pos, true)?)),
+            "}" => {
+                if !nested {
+                    return Err(format!(
+                        "Error: invalid A2ML: unexpected \"}}\" at offset {offset}"
+                    ));
+                }
+                if !parts.is_empty() {
+                    items.push(A2mlItem {
+                        parts,
+                        terminator: None,
+                        trailing_comment: None,
+                    });
+                }
+                return Ok(items);
+            }
+            ";" | "," => {
+                let mut trailing_comment = None;
+                if let Some(&(comment_offset, comment)) = tokens.get(*pos) {
+                    if comment.starts_with("//") && !text[offset..comment_offset].contains('\n') {
+                        trailing_comment = Some(comment);
+                        *pos += 1;
+                    }
+                }
+                items.push(A2mlItem {
+                    parts: std::mem::take(&mut parts),
+                    terminator: Some(token),
+                    trailing_comment,
+                });
+            }
+            _ if is_comment(token) => parts.push(A2mlPart::Comment(token)),
+            _ => parts.push(A2mlPart::Token(token)),
+        }
+    }
+
+    if nested {
+        return Err("Error: invalid A2ML: missing \"}\" at the end of the text".to_string());
+    }
+    if !parts.is_empty() {
+        items.push(A2mlItem {
+            parts,
+            terminator: None,
+            trailing_comment: None,
+        });
+    }
+    Ok(items)
+}
+
+fn sort_tagged_members(items: &mut [A2mlItem]) {
+    for item in items {
+        let mut keyword = "";
+        for part in &mut item.parts {
+            match part {
+                A2mlPart::Token(token) => {
+                    if matches!(*token, "struct" | "taggedstruct" | "taggedunion" | "enum") {
+                        keyword = token;
+                    }
+                }
+                A2mlPart::Body(members) => {
+                    sort_tagged_members(members);
+                    if keyword == "taggedstruct" || keyword == "taggedunion" {
+                        // items without a tag, e.g. comments at the end of the body, stay at the end
+                        members.sort_by_key(|member| (member.tag().is_none(), member.tag()));
+                    }
+                }
+                A2mlPart::Comment(_) => {}
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct A2mlWriter {
+    text: String,
+    depth: usize,
+    line_start: bool,
+    // no space is needed after "(" and "["
+    attach_next: bool,
+}
+
+impl A2mlWriter {
+    fn write_items(&mut self, items: &[A2mlItem]) {
+        for (idx, item) in items.iter().enumerate() {
+            // top level declarations are separated by an empty line
+            if self.depth == 0 && idx > 0 {
+                self.text.push('\n');
+            }
+            self.write_item(item);
+        }
+    }
+
+    fn write_item(&mut self, item: &A2mlItem) {
+        self.line_start = true;
+        for part in &item.parts {
+            match part {
+                A2mlPart::Token(token) => self.write_token(token),
+                A2mlPart::Comment(comment) => {
+                    self.write_token(comment);
+                    self.newline();
+                }
+                A2mlPart::Body(members) => {
+                    self.write_token("{");
+                    self.newline();
+                    self.depth += 1;
+                    self.write_items(members);
+                    self.depth -= 1;
+                    self.line_start = true;
+                    self.write_token("}");
+                }
+            }
+        }
+        if let Some(terminator) = item.terminator {
+            self.write_token(terminator);
+        }
+        if let Some(comment) = item.trailing_comment {
+            self.write_token(comment);
+        }
+        if !self.line_start {
+            self.newline();
+        }
+    }
+
+    fn write_token(&mut self, token: &str) {
+        if self.line_start {
+            self.text.push_str(&"  ".repeat(self.depth));
+            self.line_start = false;
+        } else if !self.attach_next && !matches!(token, ")" | "[" | "]" | "*" | ";" | ",") {
+            self.text.push(' ');
+        }
+        self.text.push_str(token);
+        self.attach_next = matches!(token, "(" | "[");
+    }
+
+    fn newline(&mut self) {
+        self.text.push('\n');
+        self.line_start = true;
+    }
+}
+
+fn is_comment(token: &str) -> bool {
+    token.starts_with("//") || token.starts_with("/*")
+}
+
+// minimal A2ML tokenizer: it only needs to identify words, strings and punctuation.
+// Comments are skipped, so that braces or keywords inside them are ignored.
+pub(crate) fn tokenize(text: &str) -> Vec<(usize, &str)> {
+    tokenize_impl(text, false)
+}
+
+// the formatter needs the comments in order to keep them
+fn tokenize_with_comments(text: &str) -> Vec<(usize, &str)> {
+    tokenize_impl(text, true)
+}
+
+fn tokenize_impl(text: &str, keep_comments: bool) -> Vec<(usize, &str)> {
+    let bytes = text.as_bytes();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let c = bytes[pos];
+        if c.is_ascii_whitespace() {
+            pos += 1;
+        } else if text[pos..].starts_with("//") {
+            let end = text[pos..].find('\n').map_or(bytes.len(), |len| pos + len);
+            if keep_comments {
+                tokens.push((pos, text[pos..end].trim_end()));
+            }
+            pos = end;
+        } else if text[pos..].starts_with("/*") {
+            let end = text[pos + 2..]
+                .find("*/")
+                .map_or(bytes.len(), |len| pos + len + 4);
+            if keep_comments {
+                tokens.push((pos, &text[pos..end]));
+            }
+            pos = end;
+        } else if c == b'"' {
+            let end = text[pos + 1..]
+                .find('"')
+                .map_or(bytes.len(), |len| pos + len + 2);
+            tokens.push((pos, &text[pos..end]));
+            pos = end;
+        } else if c.is_ascii_alphanumeric() || c == b'_' {
+            let start = pos;
+            while pos < bytes.len() && (bytes[pos].is_ascii_alphanumeric() || bytes[pos] == b'_') {
+                pos += 1;
+            }
+            tokens.push((start, &text[start..pos]));
+        } else {
+            // punctuation and any other characters are single character tokens
+            let len = text[pos..].chars().next().map_or(1, char::len_utf8);
+            tokens.push((pos, &text[pos..pos + len]));
+            pos += len;
+        }
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_merge_a2ml_spec() {
+        let vendor_a2ml = r#"
+            /begin A2ML
+                struct Vendor_Info {
+                    uint;
+                };
+                block "IF_DATA" taggedunion {
+                    /* a comment containing { braces */
+                    "VENDOR_EXT" struct Vendor_Info;
+                };
+            /end A2ML
+        "#;
+        let vendor_a2ml = strip_a2ml_block(vendor_a2ml);
+        assert!(vendor_a2ml.trim().starts_with("struct Vendor_Info"));
+
+        let merged = merge_a2ml_spec(crate::ifdata::A2MLVECTOR_TEXT, vendor_a2ml).unwrap();
+        assert!(merged.trim().starts_with("struct Vendor_Info"));
+        let block = find_ifdata_block(&merged).unwrap();
+        assert!(merged[block.body_start..block.body_end].contains("\"VENDOR_EXT\""));
+        assert!(merged[block.body_start..block.body_end].contains("\"XCP\""));
+
+        let a2l_text = r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin IF_DATA VENDOR_EXT 3 /end IF_DATA
+            /end MODULE
+        /end PROJECT"#;
+        let mut log_msgs = Vec::new();
+        let a2l_file =
+            a2lfile::load_from_string(a2l_text, Some(merged), &mut log_msgs, false).unwrap();
+        assert!(a2l_file.project.module[0].if_data[0].ifdata_valid);
+        let a2l_file = a2lfile::load_from_string(
+            a2l_text,
+            Some(crate::ifdata::A2MLVECTOR_TEXT.to_string()),
+            &mut log_msgs,
+            false,
+        )
+        .unwrap();
+        assert!(!a2l_file.project.module[0].if_data[0].ifdata_valid);
+
+        assert!(merge_a2ml_spec(crate::ifdata::A2MLVECTOR_TEXT, "struct { uint; };").is_err());
+    }
+
+    #[test]
+    fn test_format_a2ml() {
+        let a2ml_text = r#"struct Info { uint; char[16]; }; /* the vendor block */ block "IF_DATA" taggedunion { "VENDOR_Z" taggedstruct { "B" uint; ("A" enum { "ON" = 1, "OFF" = 0 })*; }; "VENDOR_A" struct Info; // first
+            };"#;
+        let formatted = format_a2ml(a2ml_text, false).unwrap();
+        let expected = r#"struct Info {
+  uint;
+  char[16];
+};
+
+/* the vendor block */
+block "IF_DATA" taggedunion {
+  "VENDOR_Z" taggedstruct {
+    "B" uint;
+    ("A" enum {
+      "ON" = 1,
+      "OFF" = 0
+    })*;
+  };
+  "VENDOR_A" struct Info; // first
+};
+"#;
+        assert_eq!(formatted, expected);
+        // formatting is stable
+        assert_eq!(format_a2ml(&formatted, false).unwrap(), expected);
+
+        // only the members of taggedstructs and taggedunions are sorted
+        let sorted = format_a2ml(a2ml_text, true).unwrap();
+        assert!(sorted.contains("  \"VENDOR_A\" struct Info; // first\n  \"VENDOR_Z\""));
+        assert!(sorted.contains("    (\"A\" enum {"));
+        assert!(sorted.find("(\"A\"") < sorted.find("\"B\" uint"));
+        assert!(sorted.contains("\"ON\" = 1,\n      \"OFF\" = 0"));
+        assert!(sorted.contains("  uint;\n  char[16];"));
+
+        assert!(format_a2ml("struct { uint; };};", false).is_err());
+        assert!(format_a2ml("struct { uint;", false).is_err());
+
+        let mut a2l_file = a2lfile::new();
+        assert_eq!(format_module_a2ml(&mut a2l_file, false).unwrap(), 0);
+        replace_a2ml(&mut a2l_file, a2ml_text).unwrap();
+        assert_eq!(format_module_a2ml(&mut a2l_file, false).unwrap(), 1);
+        let a2ml = a2l_file.project.module[0].a2ml.as_ref().unwrap();
+        assert_eq!(a2ml.a2ml_text, format!("\n{}", expected.trim_end()));
+        assert_eq!(format_module_a2ml(&mut a2l_file, false).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_extract_replace_a2ml() {
+        let mut a2l_file = a2lfile::new();
+        let a2ml_filename = std::env::temp_dir().join("a2ltool_test_extract_replace.aml");
+        assert!(extract_a2ml(&a2l_file, a2ml_filename.as_os_str()).is_err());
+
+        let a2ml_text = r#"block "IF_DATA" taggedunion { "VENDOR_EXT" uint; };"#;
+        replace_a2ml(&mut a2l_file, a2ml_text).unwrap();
+        extract_a2ml(&a2l_file, a2ml_filename.as_os_str()).unwrap();
+        let loaded = load_a2ml_file(a2ml_filename.as_os_str()).unwrap();
+        std::fs::remove_file(&a2ml_filename).unwrap();
+        assert_eq!(loaded.trim(), a2ml_text);
+
+        // the replacement A2ML is written to the file and can be used to parse IF_DATA
+        a2l_file.project.module[0]
+            .if_data
+            .push(a2lfile::IfData::new());
+        let text = a2l_file.write_to_string();
+        assert!(text.contains("VENDOR_EXT"));
+
+        assert!(replace_a2ml(&mut a2l_file, "block \"IF_DATA\" taggedunion {").is_err());
+    }
+}
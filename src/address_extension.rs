@@ -0,0 +1,238 @@
+//! Assignment of ECU_ADDRESS_EXTENSIONs based on the addresses of objects
+
+use crate::parse_number;
+use a2lfile::{A2lFile, EcuAddressExtension};
+use std::str::FromStr;
+
+/// The memory area that a rule applies to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressArea {
+    /// an address range; the start is inclusive and the end is exclusive
+    Range(u64, u64),
+    /// an elf section
+    Section(String),
+}
+
+/// A rule that assigns an ECU_ADDRESS_EXTENSION to all objects in a memory area
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressExtensionRule {
+    pub area: AddressArea,
+    pub extension: i16,
+}
+
+impl FromStr for AddressExtensionRule {
+    type Err = String;
+
+    /// Parse a rule of the form `START-END=EXTENSION` (the END address is included), or `SECTION=EXTENSION`.
+    /// Numbers can be given in decimal or as hex with the prefix 0x.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Error: invalid address extension rule \"{text}\", expected START-END=EXTENSION or SECTION=EXTENSION"
+            )
+        };
+        let (area_text, extension_text) = text.rsplit_once('=').ok_or_else(invalid)?;
+        let extension = parse_number(extension_text.trim())
+            .and_then(|ext| i16::try_from(ext).ok())
+            .ok_or_else(invalid)?;
+        let area_text = area_text.trim();
+        let area = if let Some((start, end)) = area_text.split_once('-').and_then(|(start, end)| {
+            let start = u64::try_from(parse_number(start.trim())?).ok()?;
+            let end = u64::try_from(parse_number(end.trim())?).ok()?;
+            Some((start, end))
+        }) {
+            if start > end {
+                return Err(format!(
+                    "Error: invalid address extension rule \"{text}\": the start address is greater than the end address"
+                ));
+            }
+            AddressArea::Range(start, end + 1)
+        } else if !area_text.is_empty() && !area_text.contains(char::is_whitespace) {
+            AddressArea::Section(area_text.to_string())
+        } else {
+            return Err(invalid());
+        };
+        Ok(Self { area, extension })
+    }
+}
+
+/// Set the ECU_ADDRESS_EXTENSION of all MEASUREMENTs, CHARACTERISTICs, AXIS_PTS, BLOBs and INSTANCEs
+/// whose address is inside the area of one of the rules.
+///
+/// The first matching rule is used, and objects that are not covered by any rule are not changed.
+/// `section_range` provides the address ranges of elf sections; rules for unknown sections are reported in `log_msgs`.
+/// Objects with the address 0 have not been located and are skipped.
+///
+/// Returns the number of objects whose ECU_ADDRESS_EXTENSION was changed.
+pub fn apply_address_extensions(
+    a2l_file: &mut A2lFile,
+    rules: &[AddressExtensionRule],
+    section_range: impl Fn(&str) -> Option<(u64, u64)>,
+    log_msgs: &mut Vec<String>,
+) -> usize {
+    let mut ranges = Vec::new();
+    for rule in rules {
+        match &rule.area {
+            AddressArea::Range(start, end) => ranges.push((*start, *end, rule.extension)),
+            AddressArea::Section(name) => {
+                if let Some((start, end)) = section_range(name) {
+                    ranges.push((start, end, rule.extension));
+                } else {
+                    log_msgs.push(format!(
+                        "The section {name} of an address extension rule does not exist"
+                    ));
+                }
+            }
+        }
+    }
+    let find_extension = |address: u32| {
+        if address == 0 {
+            return None;
+        }
+        let address = u64::from(address);
+        ranges
+            .iter()
+            .find(|(start, end, _)| *start <= address && address < *end)
+            .map(|(_, _, extension)| *extension)
+    };
+
+    let mut count = 0;
+    for module in &mut a2l_file.project.module {
+        for measurement in &mut module.measurement {
+            if let Some(ecu_address) = &measurement.ecu_address {
+                count += set_extension(
+                    &mut measurement.ecu_address_extension,
+                    find_extension(ecu_address.address),
+                );
+            }
+        }
+        for characteristic in &mut module.characteristic {
+            count += set_extension(
+                &mut characteristic.ecu_address_extension,
+                find_extension(characteristic.address),
+            );
+        }
+        for axis_pts in &mut module.axis_pts {
+            count += set_extension(
+                &mut axis_pts.ecu_address_extension,
+                find_extension(axis_pts.address),
+            );
+        }
+        for blob in &mut module.blob {
+            count += set_extension(
+                &mut blob.ecu_address_extension,
+                find_extension(blob.start_address),
+            );
+        }
+        for instance in &mut module.instance {
+            count += set_extension(
+                &mut instance.ecu_address_extension,
+                find_extension(instance.start_address),
+            );
+        }
+    }
+    count
+}
+
+fn set_extension(
+    ecu_address_extension: &mut Option<EcuAddressExtension>,
+    new_extension: Option<i16>,
+) -> usize {
+    match (ecu_address_extension.as_mut(), new_extension) {
+        (Some(eae), Some(extension)) if eae.extension != extension => {
+            eae.extension = extension;
+            1
+        }
+        // a missing ECU_ADDRESS_EXTENSION is equivalent to 0
+        (None, Some(extension)) if extension != 0 => {
+            *ecu_address_extension = Some(EcuAddressExtension::new(extension));
+            1
+        }
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_apply_address_extensions() {
+        assert!("0x1000-0x2000".parse::<AddressExtensionRule>().is_err());
+        assert!("0x2000-0x1000=1".parse::<AddressExtensionRule>().is_err());
+        assert!("0x1000-0x2000=100000"
+            .parse::<AddressExtensionRule>()
+            .is_err());
+        assert!("=1".parse::<AddressExtensionRule>().is_err());
+        assert!("0x1000-0x1FFF=0x-1"
+            .parse::<AddressExtensionRule>()
+            .is_err());
+        assert_eq!(
+            "0x1000-0x1FFF=1".parse::<AddressExtensionRule>().unwrap(),
+            AddressExtensionRule {
+                area: AddressArea::Range(0x1000, 0x2000),
+                extension: 1
+            }
+        );
+        let rules: Vec<AddressExtensionRule> = ["0x1000-0x1FFF=1", ".rodata=0", ".missing=2"]
+            .iter()
+            .map(|rule| rule.parse().unwrap())
+            .collect();
+
+        let mut load_msgs = Vec::new();
+        let mut a2l_file = a2lfile::load_from_string(
+            r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin MEASUREMENT ram_value "" UBYTE NO_COMPU_METHOD 0 0 0 255
+                    ECU_ADDRESS 0x1004
+                /end MEASUREMENT
+                /begin CHARACTERISTIC flash_value "" VALUE 0x8000 RL 0 NO_COMPU_METHOD 0 100
+                    ECU_ADDRESS_EXTENSION 1
+                /end CHARACTERISTIC
+                /begin CHARACTERISTIC other_value "" VALUE 0x9000 RL 0 NO_COMPU_METHOD 0 100
+                    ECU_ADDRESS_EXTENSION 3
+                /end CHARACTERISTIC
+                /begin BLOB not_located "" 0 8
+                /end BLOB
+            /end MODULE
+        /end PROJECT"#,
+            None,
+            &mut load_msgs,
+            true,
+        )
+        .unwrap();
+        let mut log_msgs = Vec::new();
+        let section_range = |name: &str| (name == ".rodata").then_some((0x8000, 0x8100));
+        let count = apply_address_extensions(&mut a2l_file, &rules, section_range, &mut log_msgs);
+        assert_eq!(count, 2);
+        assert_eq!(log_msgs.len(), 1);
+        let module = &a2l_file.project.module[0];
+        assert_eq!(
+            module.measurement[0]
+                .ecu_address_extension
+                .as_ref()
+                .unwrap()
+                .extension,
+            1
+        );
+        assert_eq!(
+            module.characteristic[0]
+                .ecu_address_extension
+                .as_ref()
+                .unwrap()
+                .extension,
+            0
+        );
+        assert_eq!(
+            module.characteristic[1]
+                .ecu_address_extension
+                .as_ref()
+                .unwrap()
+                .extension,
+            3
+        );
+        assert!(module.blob[0].ecu_address_extension.is_none());
+    }
+}
@@ -0,0 +1,350 @@
+//! Shifting all addresses of a file by a constant offset, and translating addresses from one memory window to another
+
+use crate::ifdata::A2mlVector;
+use crate::parse_number;
+use a2lfile::{A2lFile, IfData};
+use std::str::FromStr;
+
+/// A memory window whose addresses are translated to another window, e.g. a calibration flash range that
+/// is mirrored into RAM
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressWindow {
+    /// the first address of the window
+    pub start: u32,
+    /// the last address of the window (inclusive)
+    pub end: u32,
+    /// the address that `start` is translated to
+    pub target: u32,
+}
+
+impl FromStr for AddressWindow {
+    type Err = String;
+
+    /// Parse a window of the form `START-END=TARGET`, e.g. `0x08010000-0x0801FFFF=0x20010000`. The END address is included.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Error: invalid address window \"{text}\", expected START-END=TARGET, e.g. 0x08010000-0x0801FFFF=0x20010000"
+            )
+        };
+        let parse = |value: &str| {
+            parse_address_offset(value.trim())
+                .ok()
+                .and_then(|value| u32::try_from(value).ok())
+                .ok_or_else(invalid)
+        };
+        let (range, target) = text.split_once('=').ok_or_else(invalid)?;
+        let (start, end) = range.split_once('-').ok_or_else(invalid)?;
+        let window = Self {
+            start: parse(start)?,
+            end: parse(end)?,
+            target: parse(target)?,
+        };
+        if window.start > window.end {
+            return Err(format!(
+                "Error: invalid address window \"{text}\": the start address is greater than the end address"
+            ));
+        }
+        if u64::from(window.target) + u64::from(window.end - window.start) > u64::from(u32::MAX) {
+            return Err(format!(
+                "Error: invalid address window \"{text}\": the target window exceeds the 32-bit address space"
+            ));
+        }
+        Ok(window)
+    }
+}
+
+/// Add an offset to every address in the file, e.g. when the same software is linked to a different memory bank.
+///
+/// The addresses of all MEASUREMENTs, CHARACTERISTICs, AXIS_PTS, BLOBs and INSTANCEs are shifted, as well as
+/// the MEMORY_SEGMENTs and ADDR_EPK in MOD_PAR. In the IF_DATA, the CANAPE_EXT LINK_MAP, the CCP DP_BLOB and the
+/// XCP ADDRESS_MAPPINGs of memory segments are shifted.
+/// Objects with the address 0 have not been located and are skipped.
+/// An address that would leave the 32-bit address space is not changed and is reported in `log_msgs`.
+///
+/// Returns the number of addresses that were changed.
+pub fn apply_address_offset(
+    a2l_file: &mut A2lFile,
+    offset: i64,
+    log_msgs: &mut Vec<String>,
+) -> usize {
+    let mut count = 0;
+    let mut shift = |address: &mut u32, description: &dyn Fn() -> String| {
+        if *address == 0 || offset == 0 {
+            return;
+        }
+        if let Ok(new_address) = u32::try_from(i64::from(*address) + offset) {
+            *address = new_address;
+            count += 1;
+        } else {
+            log_msgs.push(format!(
+                "The address 0x{:X} of {} can't be shifted by {}",
+                *address,
+                description(),
+                format_address_offset(offset)
+            ));
+        }
+    };
+    for_each_address(a2l_file, true, &mut shift);
+    count
+}
+
+/// Translate the addresses of all objects that are inside one of the windows to the target of the window.
+///
+/// The addresses of MEASUREMENTs, CHARACTERISTICs, AXIS_PTS, BLOBs and INSTANCEs, and the addresses in their
+/// CANAPE_EXT and CCP IF_DATA, are translated. The MEMORY_SEGMENTs and ADDR_EPK in MOD_PAR still describe the
+/// original memory and are not changed. If the windows overlap, the first matching window is used.
+///
+/// Returns the number of addresses that were translated.
+pub fn apply_address_translation(a2l_file: &mut A2lFile, windows: &[AddressWindow]) -> usize {
+    let mut count = 0;
+    let mut translate = |address: &mut u32, _: &dyn Fn() -> String| {
+        if let Some(window) = windows
+            .iter()
+            .find(|window| window.start <= *address && *address <= window.end)
+        {
+            *address = window.target + (*address - window.start);
+            count += 1;
+        }
+    };
+    for_each_address(a2l_file, false, &mut translate);
+    count
+}
+
+// call the visitor for each address in the file. The description of the address is only created when it is needed
+fn for_each_address(
+    a2l_file: &mut A2lFile,
+    include_mod_par: bool,
+    shift: &mut impl FnMut(&mut u32, &dyn Fn() -> String),
+) {
+    for module in &mut a2l_file.project.module {
+        for measurement in &mut module.measurement {
+            if let Some(ecu_address) = &mut measurement.ecu_address {
+                shift(&mut ecu_address.address, &|| {
+                    format!("MEASUREMENT {}", measurement.name)
+                });
+            }
+            shift_ifdata(&mut measurement.if_data, shift);
+        }
+        for characteristic in &mut module.characteristic {
+            let name = &characteristic.name;
+            shift(&mut characteristic.address, &|| {
+                format!("CHARACTERISTIC {name}")
+            });
+            shift_ifdata(&mut characteristic.if_data, shift);
+        }
+        for axis_pts in &mut module.axis_pts {
+            let name = &axis_pts.name;
+            shift(&mut axis_pts.address, &|| format!("AXIS_PTS {name}"));
+            shift_ifdata(&mut axis_pts.if_data, shift);
+        }
+        for blob in &mut module.blob {
+            let name = &blob.name;
+            shift(&mut blob.start_address, &|| format!("BLOB {name}"));
+            shift_ifdata(&mut blob.if_data, shift);
+        }
+        for instance in &mut module.instance {
+            let name = &instance.name;
+            shift(&mut instance.start_address, &|| format!("INSTANCE {name}"));
+            shift_ifdata(&mut instance.if_data, shift);
+        }
+        if let Some(mod_par) = module.mod_par.as_mut().filter(|_| include_mod_par) {
+            for memory_segment in &mut mod_par.memory_segment {
+                let name = &memory_segment.name;
+                shift(&mut memory_segment.address, &|| {
+                    format!("MEMORY_SEGMENT {name}")
+                });
+                shift_ifdata(&mut memory_segment.if_data, shift);
+            }
+            for addr_epk in &mut mod_par.addr_epk {
+                shift(&mut addr_epk.address, &|| "ADDR_EPK".to_string());
+            }
+        }
+    }
+}
+
+fn shift_ifdata(if_data_vec: &mut [IfData], shift: &mut impl FnMut(&mut u32, &dyn Fn() -> String)) {
+    for if_data in if_data_vec {
+        let Some(mut decoded) = A2mlVector::load_from_ifdata(if_data) else {
+            continue;
+        };
+        let mut changed = false;
+        if let Some(link_map) = decoded
+            .canape_ext
+            .as_mut()
+            .and_then(|canape_ext| canape_ext.link_map.as_mut())
+        {
+            // the CANape address is stored as a signed value
+            let mut address = link_map.address.cast_unsigned();
+            shift(&mut address, &|| {
+                format!("LINK_MAP {}", link_map.symbol_name)
+            });
+            link_map.address = address.cast_signed();
+            changed = true;
+        }
+        if let Some(dp_blob) = decoded
+            .asap1b_ccp
+            .as_mut()
+            .and_then(|asap1b_ccp| asap1b_ccp.dp_blob.as_mut())
+        {
+            shift(&mut dp_blob.base_address, &|| "DP_BLOB".to_string());
+            changed = true;
+        }
+        if let Some(segment) = decoded.xcp.as_mut().and_then(|xcp| xcp.segment.as_mut()) {
+            for address_mapping in &mut segment.address_mapping {
+                shift(&mut address_mapping.source_address, &|| {
+                    "ADDRESS_MAPPING".to_string()
+                });
+                shift(&mut address_mapping.dest_address, &|| {
+                    "ADDRESS_MAPPING".to_string()
+                });
+                changed = true;
+            }
+        }
+        if changed {
+            decoded.store_to_ifdata(if_data);
+        }
+    }
+}
+
+/// Parse an address offset, which is given as a decimal or hex number with an optional sign, e.g. `-0x20000`
+///
+/// # Errors
+///
+/// Returns an error message if the text is not a valid number
+pub fn parse_address_offset(text: &str) -> Result<i64, String> {
+    parse_number(text).ok_or_else(|| format!("invalid address offset \"{text}\""))
+}
+
+/// Format an address offset as a hex number with a sign, e.g. `-0x20000`
+#[must_use]
+pub fn format_address_offset(offset: i64) -> String {
+    if offset < 0 {
+        format!("-0x{:X}", offset.unsigned_abs())
+    } else {
+        format!("0x{offset:X}")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_apply_address_offset() {
+        assert_eq!(parse_address_offset("0x20000"), Ok(0x20000));
+        assert_eq!(parse_address_offset("-0x20000"), Ok(-0x20000));
+        assert_eq!(parse_address_offset("100"), Ok(100));
+        assert!(parse_address_offset("0xg").is_err());
+        assert!(parse_address_offset("0x-1").is_err());
+        assert!(parse_address_offset("--1").is_err());
+        assert_eq!(format_address_offset(-0x20000), "-0x20000");
+        assert!("0x2000-0x1000=0".parse::<AddressWindow>().is_err());
+        assert!("0x1000-0x2000".parse::<AddressWindow>().is_err());
+        assert!("0x1000-0x2000=0xFFFFF000".parse::<AddressWindow>().is_err());
+
+        let mut load_msgs = Vec::new();
+        let mut a2l_file = a2lfile::load_from_string(
+            r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin MOD_PAR ""
+                    ADDR_EPK 0x8000
+                    /begin MEMORY_SEGMENT cal "" DATA FLASH INTERN 0x8000 0x1000 -1 -1 -1 -1 -1 /end MEMORY_SEGMENT
+                /end MOD_PAR
+                /begin MEASUREMENT m1 "" UBYTE NO_COMPU_METHOD 0 0 0 255
+                    ECU_ADDRESS 0x1004
+                    /begin IF_DATA CANAPE_EXT 100 LINK_MAP "m1" 0x1004 0 0 0 1 0x87 0 /end IF_DATA
+                /end MEASUREMENT
+                /begin MEASUREMENT unlocated "" UBYTE NO_COMPU_METHOD 0 0 0 255
+                    ECU_ADDRESS 0
+                /end MEASUREMENT
+                /begin CHARACTERISTIC c1 "" VALUE 0x8010 RL 0 NO_COMPU_METHOD 0 100 /end CHARACTERISTIC
+                /begin CHARACTERISTIC low "" VALUE 0x10 RL 0 NO_COMPU_METHOD 0 100 /end CHARACTERISTIC
+                /begin RECORD_LAYOUT RL FNC_VALUES 1 UBYTE ROW_DIR DIRECT /end RECORD_LAYOUT
+            /end MODULE
+        /end PROJECT"#,
+            Some(crate::ifdata::A2MLVECTOR_TEXT.to_string()),
+            &mut load_msgs,
+            true,
+        )
+        .unwrap();
+
+        let mut log_msgs = Vec::new();
+        let count = apply_address_offset(&mut a2l_file, -0x1000, &mut log_msgs);
+        // the address of "low" would be negative
+        assert_eq!(count, 5);
+        assert_eq!(log_msgs.len(), 1);
+        let module = &a2l_file.project.module[0];
+        assert_eq!(
+            module.measurement[0].ecu_address.as_ref().unwrap().address,
+            0x0004
+        );
+        assert_eq!(
+            module.measurement[1].ecu_address.as_ref().unwrap().address,
+            0
+        );
+        assert_eq!(module.characteristic[0].address, 0x7010);
+        assert_eq!(module.characteristic[1].address, 0x10);
+        let mod_par = module.mod_par.as_ref().unwrap();
+        assert_eq!(mod_par.addr_epk[0].address, 0x7000);
+        assert_eq!(mod_par.memory_segment[0].address, 0x7000);
+        let decoded = A2mlVector::load_from_ifdata(&module.measurement[0].if_data[0]).unwrap();
+        assert_eq!(
+            decoded.canape_ext.unwrap().link_map.unwrap().address,
+            0x0004
+        );
+    }
+
+    #[test]
+    fn test_apply_address_translation() {
+        let window: AddressWindow = "0x08010000-0x0801FFFF=0x20010000".parse().unwrap();
+        assert_eq!(
+            window,
+            AddressWindow {
+                start: 0x0801_0000,
+                end: 0x0801_FFFF,
+                target: 0x2001_0000
+            }
+        );
+
+        let mut load_msgs = Vec::new();
+        let mut a2l_file = a2lfile::load_from_string(
+            r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin MOD_PAR ""
+                    /begin MEMORY_SEGMENT cal "" DATA FLASH INTERN 0x08010000 0x10000 -1 -1 -1 -1 -1 /end MEMORY_SEGMENT
+                /end MOD_PAR
+                /begin CHARACTERISTIC inside "" VALUE 0x0801FFFF RL 0 NO_COMPU_METHOD 0 100
+                    /begin IF_DATA CANAPE_EXT 100 LINK_MAP "inside" 0x0801FFFF 0 0 0 1 0x87 0 /end IF_DATA
+                /end CHARACTERISTIC
+                /begin CHARACTERISTIC outside "" VALUE 0x08020000 RL 0 NO_COMPU_METHOD 0 100 /end CHARACTERISTIC
+                /begin RECORD_LAYOUT RL FNC_VALUES 1 UBYTE ROW_DIR DIRECT /end RECORD_LAYOUT
+            /end MODULE
+        /end PROJECT"#,
+            Some(crate::ifdata::A2MLVECTOR_TEXT.to_string()),
+            &mut load_msgs,
+            true,
+        )
+        .unwrap();
+
+        let count = apply_address_translation(&mut a2l_file, &[window]);
+        assert_eq!(count, 2);
+        let module = &a2l_file.project.module[0];
+        assert_eq!(module.characteristic[0].address, 0x2001_FFFF);
+        assert_eq!(module.characteristic[1].address, 0x0802_0000);
+        let decoded = A2mlVector::load_from_ifdata(&module.characteristic[0].if_data[0]).unwrap();
+        assert_eq!(
+            decoded.canape_ext.unwrap().link_map.unwrap().address,
+            0x2001_FFFF
+        );
+        // the memory segment still describes the flash
+        assert_eq!(
+            module.mod_par.as_ref().unwrap().memory_segment[0].address,
+            0x0801_0000
+        );
+    }
+}
@@ -0,0 +1,216 @@
+//! Validation of object addresses against the alignment requirements of their data types
+
+use crate::dwarf::{DebugData, DwarfDataType, TypeInfo};
+use crate::update::get_symbol_info;
+use a2lfile::{A2lFile, A2lObject, A2lObjectName, IfData, SymbolLink};
+use std::collections::HashMap;
+
+// nested types deeper than this are not examined further
+const MAX_DEPTH: usize = 32;
+
+/// Check that the address of each MEASUREMENT, CHARACTERISTIC, AXIS_PTS, BLOB and INSTANCE is
+/// a multiple of the alignment of its data type in the debug info.
+///
+/// The alignment of a basic type is its size, the alignment of an array is the alignment of its
+/// elements, and the alignment of a struct is the largest alignment of its members.
+/// Structs whose members are placed at misaligned offsets are treated as packed and have an alignment of 1.
+/// Objects whose symbol cannot be found in the debug info are not checked.
+///
+/// A message is added to `log_msgs` for each misaligned object; the number of misaligned objects is returned.
+pub fn check_alignment(
+    a2l_file: &A2lFile,
+    debug_data: &DebugData,
+    log_msgs: &mut Vec<String>,
+) -> usize {
+    let mut count = 0;
+    for module in &a2l_file.project.module {
+        for measurement in &module.measurement {
+            if let Some(address) = measurement.ecu_address.as_ref().map(|ea| ea.address) {
+                count += check_object(
+                    "MEASUREMENT",
+                    measurement,
+                    u64::from(address),
+                    &measurement.symbol_link,
+                    &measurement.if_data,
+                    debug_data,
+                    log_msgs,
+                );
+            }
+        }
+        for characteristic in &module.characteristic {
+            count += check_object(
+                "CHARACTERISTIC",
+                characteristic,
+                u64::from(characteristic.address),
+                &characteristic.symbol_link,
+                &characteristic.if_data,
+                debug_data,
+                log_msgs,
+            );
+        }
+        for axis_pts in &module.axis_pts {
+            count += check_object(
+                "AXIS_PTS",
+                axis_pts,
+                u64::from(axis_pts.address),
+                &axis_pts.symbol_link,
+                &axis_pts.if_data,
+                debug_data,
+                log_msgs,
+            );
+        }
+        for blob in &module.blob {
+            count += check_object(
+                "BLOB",
+                blob,
+                u64::from(blob.start_address),
+                &blob.symbol_link,
+                &blob.if_data,
+                debug_data,
+                log_msgs,
+            );
+        }
+        for instance in &module.instance {
+            count += check_object(
+                "INSTANCE",
+                instance,
+                u64::from(instance.start_address),
+                &instance.symbol_link,
+                &instance.if_data,
+                debug_data,
+                log_msgs,
+            );
+        }
+    }
+    count
+}
+
+fn check_object<T: A2lObject<L> + A2lObjectName, L>(
+    blockname: &str,
+    object: &T,
+    address: u64,
+    symbol_link: &Option<SymbolLink>,
+    if_data: &[IfData],
+    debug_data: &DebugData,
+    log_msgs: &mut Vec<String>,
+) -> usize {
+    // address 0 is used by objects that have not been located yet
+    if address == 0 {
+        return 0;
+    }
+    let Ok(sym_info) = get_symbol_info(object.get_name(), symbol_link, if_data, debug_data) else {
+        return 0;
+    };
+    let alignment = type_alignment(sym_info.typeinfo, &debug_data.types, 0);
+    if !address.is_multiple_of(alignment) {
+        log_msgs.push(format!(
+            "{blockname} {} on line {}: address {address:#x} is not aligned to the {alignment}-byte alignment of its type {}",
+            object.get_name(),
+            object.get_line(),
+            sym_info.typeinfo
+        ));
+        1
+    } else {
+        0
+    }
+}
+
+// the natural alignment of a type, which is at least 1
+fn type_alignment(typeinfo: &TypeInfo, types: &HashMap<usize, TypeInfo>, depth: usize) -> u64 {
+    if depth > MAX_DEPTH {
+        return 1;
+    }
+    match &typeinfo.datatype {
+        DwarfDataType::Bitfield { basetype, .. } => type_alignment(basetype, types, depth + 1),
+        DwarfDataType::Array { arraytype, .. } => type_alignment(arraytype, types, depth + 1),
+        DwarfDataType::Struct { members, .. } | DwarfDataType::Union { members, .. } => {
+            members_alignment(members.values(), types, depth)
+        }
+        DwarfDataType::Class {
+            members,
+            inheritance,
+            ..
+        } => members_alignment(inheritance.values().chain(members.values()), types, depth),
+        DwarfDataType::TypeRef(..) => {
+            let reftype = typeinfo.get_reference(types);
+            if std::ptr::eq(reftype, typeinfo) {
+                1
+            } else {
+                type_alignment(reftype, types, depth + 1)
+            }
+        }
+        DwarfDataType::Other(_) => 1,
+        _ => typeinfo.get_size().max(1),
+    }
+}
+
+fn members_alignment<'a>(
+    members: impl Iterator<Item = &'a (TypeInfo, u64)>,
+    types: &HashMap<usize, TypeInfo>,
+    depth: usize,
+) -> u64 {
+    let mut alignment = 1;
+    for (membertype, offset) in members {
+        let member_alignment = type_alignment(membertype, types, depth + 1);
+        if !offset.is_multiple_of(member_alignment) {
+            // a misaligned member means that the struct is packed
+            return 1;
+        }
+        alignment = alignment.max(member_alignment);
+    }
+    alignment
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::symbol::find_symbol;
+
+    #[test]
+    fn test_check_alignment() {
+        let debug_data = crate::DebugData::load(
+            std::ffi::OsStr::new("tests/elffiles/update_test.elf"),
+            false,
+        )
+        .unwrap();
+        let u8_addr = find_symbol("val_u8", &debug_data).unwrap().address;
+        let u32_addr = find_symbol("val_u32", &debug_data).unwrap().address;
+        let struct_addr = find_symbol("struct_b", &debug_data).unwrap().address;
+        let a2l_text = format!(
+            r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin MEASUREMENT val_u8 "" UBYTE NO_COMPU_METHOD 0 0 0 255
+                    ECU_ADDRESS {:#x}
+                /end MEASUREMENT
+                /begin MEASUREMENT val_u32 "" ULONG NO_COMPU_METHOD 0 0 0 255
+                    ECU_ADDRESS {:#x}
+                /end MEASUREMENT
+                /begin MEASUREMENT misaligned "" ULONG NO_COMPU_METHOD 0 0 0 255
+                    ECU_ADDRESS {:#x}
+                    SYMBOL_LINK "val_u32" 0
+                /end MEASUREMENT
+                /begin BLOB struct_b "" {:#x} 8
+                /end BLOB
+                /begin BLOB unknown_symbol "" 0x1001 8
+                /end BLOB
+            /end MODULE
+        /end PROJECT"#,
+            u8_addr + 1,
+            u32_addr,
+            u32_addr + 2,
+            struct_addr + 1
+        );
+        let mut load_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(&a2l_text, None, &mut load_msgs, true).unwrap();
+
+        let mut log_msgs = Vec::new();
+        let count = check_alignment(&a2l_file, &debug_data, &mut log_msgs);
+        assert_eq!(count, 2);
+        assert!(log_msgs[0].starts_with("MEASUREMENT misaligned on line"));
+        assert!(log_msgs[0].contains("4-byte alignment"));
+        assert!(log_msgs[1].starts_with("BLOB struct_b on line"));
+        assert!(log_msgs[1].contains("8-byte alignment"));
+    }
+}
@@ -0,0 +1,302 @@
+//! Removal of confidential information, so that an a2l file can be shared with third parties
+
+use crate::rename::{apply_renames, plan_renames, NameKind};
+use a2lfile::A2lFile;
+use std::collections::HashMap;
+
+/// Remove all descriptions, annotations and project identifiers from the file.
+///
+/// The PROJECT is renamed to `project`, the long identifiers of all objects and the comments of HEADER, MOD_PAR and MOD_COMMON are emptied,
+/// all ANNOTATIONs and MODEL_LINKs are removed, and so are PROJECT_NO, VERSION and the supplier, customer,
+/// user, phone and ECU information in MOD_PAR. The names, the structure and the addresses are not changed.
+///
+/// Returns the number of entries that were removed or emptied.
+pub fn anonymize(a2l_file: &mut A2lFile) -> usize {
+    let mut count = 0;
+    let mut clear = |text: &mut String| {
+        if !text.is_empty() {
+            text.clear();
+            count += 1;
+        }
+    };
+
+    let mut removed = 0;
+    if a2l_file.project.name != "project" {
+        a2l_file.project.name = "project".to_string();
+        removed += 1;
+    }
+    clear(&mut a2l_file.project.long_identifier);
+    if let Some(header) = &mut a2l_file.project.header {
+        clear(&mut header.comment);
+    }
+
+    for module in &mut a2l_file.project.module {
+        clear(&mut module.long_identifier);
+        if let Some(mod_common) = &mut module.mod_common {
+            clear(&mut mod_common.comment);
+        }
+        if let Some(mod_par) = &mut module.mod_par {
+            clear(&mut mod_par.comment);
+        }
+
+        for measurement in &mut module.measurement {
+            clear(&mut measurement.long_identifier);
+        }
+        for characteristic in &mut module.characteristic {
+            clear(&mut characteristic.long_identifier);
+        }
+        for axis_pts in &mut module.axis_pts {
+            clear(&mut axis_pts.long_identifier);
+        }
+        for blob in &mut module.blob {
+            clear(&mut blob.long_identifier);
+        }
+        for instance in &mut module.instance {
+            clear(&mut instance.long_identifier);
+        }
+        for compu_method in &mut module.compu_method {
+            clear(&mut compu_method.long_identifier);
+        }
+        for compu_tab in &mut module.compu_tab {
+            clear(&mut compu_tab.long_identifier);
+        }
+        for compu_vtab in &mut module.compu_vtab {
+            clear(&mut compu_vtab.long_identifier);
+        }
+        for compu_vtab_range in &mut module.compu_vtab_range {
+            clear(&mut compu_vtab_range.long_identifier);
+        }
+        for function in &mut module.function {
+            clear(&mut function.long_identifier);
+        }
+        for group in &mut module.group {
+            clear(&mut group.long_identifier);
+        }
+        for unit in &mut module.unit {
+            clear(&mut unit.long_identifier);
+        }
+        for frame in &mut module.frame {
+            clear(&mut frame.long_identifier);
+        }
+        for typedef in &mut module.typedef_measurement {
+            clear(&mut typedef.long_identifier);
+        }
+        for typedef in &mut module.typedef_characteristic {
+            clear(&mut typedef.long_identifier);
+        }
+        for typedef in &mut module.typedef_axis {
+            clear(&mut typedef.long_identifier);
+        }
+        for typedef in &mut module.typedef_blob {
+            clear(&mut typedef.long_identifier);
+        }
+        for typedef in &mut module.typedef_structure {
+            clear(&mut typedef.long_identifier);
+        }
+        if let Some(mod_par) = &mut module.mod_par {
+            for memory_segment in &mut mod_par.memory_segment {
+                clear(&mut memory_segment.long_identifier);
+            }
+        }
+        if let Some(variant_coding) = &mut module.variant_coding {
+            for var_criterion in &mut variant_coding.var_criterion {
+                clear(&mut var_criterion.long_identifier);
+            }
+        }
+
+        // annotations and model links are removed completely
+        for measurement in &mut module.measurement {
+            removed += measurement.annotation.len();
+            measurement.annotation.clear();
+            removed += usize::from(measurement.model_link.take().is_some());
+        }
+        for characteristic in &mut module.characteristic {
+            removed += characteristic.annotation.len();
+            characteristic.annotation.clear();
+            removed += usize::from(characteristic.model_link.take().is_some());
+        }
+        for axis_pts in &mut module.axis_pts {
+            removed += axis_pts.annotation.len();
+            axis_pts.annotation.clear();
+            removed += usize::from(axis_pts.model_link.take().is_some());
+        }
+        for blob in &mut module.blob {
+            removed += blob.annotation.len();
+            blob.annotation.clear();
+            removed += usize::from(blob.model_link.take().is_some());
+        }
+        for instance in &mut module.instance {
+            removed += instance.annotation.len();
+            instance.annotation.clear();
+            removed += usize::from(instance.model_link.take().is_some());
+        }
+        for function in &mut module.function {
+            removed += function.annotation.len();
+            function.annotation.clear();
+        }
+        for group in &mut module.group {
+            removed += group.annotation.len();
+            group.annotation.clear();
+        }
+
+        if let Some(mod_par) = &mut module.mod_par {
+            removed += usize::from(mod_par.supplier.take().is_some());
+            removed += usize::from(mod_par.customer.take().is_some());
+            removed += usize::from(mod_par.customer_no.take().is_some());
+            removed += usize::from(mod_par.user.take().is_some());
+            removed += usize::from(mod_par.phone_no.take().is_some());
+            removed += usize::from(mod_par.ecu.take().is_some());
+            removed += usize::from(mod_par.version.take().is_some());
+        }
+    }
+    if let Some(header) = &mut a2l_file.project.header {
+        removed += usize::from(header.project_no.take().is_some());
+        removed += usize::from(header.version.take().is_some());
+    }
+
+    count + removed
+}
+
+/// Rename all items of the file to generated names, e.g. `OBJ_1` or `CM_1`, and update all references to them.
+///
+/// SYMBOL_LINKs and DISPLAY_IDENTIFIERs are removed, because they contain the original names.
+/// The returned mapping contains pairs of (generated name, original name). It can be used as a rename map
+/// to restore the original names.
+///
+/// # Errors
+///
+/// Returns an error message if a generated name collides with an existing name
+pub fn anonymize_names(a2l_file: &mut A2lFile) -> Result<Vec<(String, String)>, String> {
+    let mut counters: HashMap<NameKind, usize> = HashMap::new();
+    let mut mapping = Vec::new();
+    for module in &mut a2l_file.project.module {
+        let plan = plan_renames(module, |kind, name| {
+            let counter = counters.entry(kind).or_default();
+            *counter += 1;
+            let new_name = format!("{}_{counter}", name_prefix(kind));
+            mapping.push((new_name.clone(), name.to_string()));
+            Some(new_name)
+        });
+        if !plan.rejected.is_empty() {
+            return Err(format!(
+                "Error: the objects could not be anonymized:\n  {}",
+                plan.rejected.join("\n  ")
+            ));
+        }
+        apply_renames(module, &plan, false);
+
+        for measurement in &mut module.measurement {
+            measurement.symbol_link = None;
+            measurement.display_identifier = None;
+        }
+        for characteristic in &mut module.characteristic {
+            characteristic.symbol_link = None;
+            characteristic.display_identifier = None;
+        }
+        for axis_pts in &mut module.axis_pts {
+            axis_pts.symbol_link = None;
+            axis_pts.display_identifier = None;
+        }
+        for blob in &mut module.blob {
+            blob.symbol_link = None;
+            blob.display_identifier = None;
+        }
+        for instance in &mut module.instance {
+            instance.symbol_link = None;
+            instance.display_identifier = None;
+        }
+    }
+    Ok(mapping)
+}
+
+fn name_prefix(kind: NameKind) -> &'static str {
+    match kind {
+        NameKind::Object => "OBJ",
+        NameKind::CompuMethod => "CM",
+        NameKind::ConversionTable => "CT",
+        NameKind::RecordLayout => "RL",
+        NameKind::Unit => "UNIT",
+        NameKind::Group => "GRP",
+        NameKind::Function => "FNC",
+        NameKind::Typedef => "TD",
+        NameKind::Frame => "FRM",
+        NameKind::Transformer => "TR",
+        NameKind::MemorySegment => "SEG",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_anonymize() {
+        let text = r#"ASAP2_VERSION 1 71
+        /begin PROJECT secret_project "secret project"
+          /begin HEADER "confidential" PROJECT_NO P123 VERSION "1.2" /end HEADER
+          /begin MODULE m "secret module"
+            /begin MOD_PAR "" SUPPLIER "supplier" ECU "ecu" /end MOD_PAR
+            /begin MEASUREMENT EngineSpeed "engine speed" UWORD rpm_conv 0 0 0 8000
+              ECU_ADDRESS 0x1000
+              SYMBOL_LINK "EngineSpeed" 0
+              DISPLAY_IDENTIFIER EngSpd
+              /begin ANNOTATION /begin ANNOTATION_TEXT "calculated by the secret algorithm" /end ANNOTATION_TEXT /end ANNOTATION
+            /end MEASUREMENT
+            /begin COMPU_METHOD rpm_conv "rpm" IDENTICAL "%6.0" "rpm" /end COMPU_METHOD
+            /begin GROUP Engine "engine" ROOT /begin REF_MEASUREMENT EngineSpeed /end REF_MEASUREMENT /end GROUP
+          /end MODULE
+        /end PROJECT"#;
+        let mut log_msgs = Vec::new();
+        let mut a2l_file = a2lfile::load_from_string(text, None, &mut log_msgs, true).unwrap();
+
+        let count = anonymize(&mut a2l_file);
+        // the project name, 6 descriptions and comments, 1 annotation, 2 MOD_PAR entries, PROJECT_NO and VERSION
+        assert_eq!(count, 12);
+        assert!(a2l_file
+            .project
+            .header
+            .as_ref()
+            .unwrap()
+            .project_no
+            .is_none());
+        let module = &a2l_file.project.module[0];
+        assert!(module.measurement[0].long_identifier.is_empty());
+        assert!(module.measurement[0].annotation.is_empty());
+        assert!(module.mod_par.as_ref().unwrap().supplier.is_none());
+
+        let mapping = anonymize_names(&mut a2l_file).unwrap();
+        assert_eq!(mapping.len(), 3);
+        let module = &a2l_file.project.module[0];
+        assert_eq!(module.measurement[0].name, "OBJ_1");
+        assert_eq!(module.measurement[0].conversion, "CM_1");
+        assert!(module.measurement[0].symbol_link.is_none());
+        assert!(module.measurement[0].display_identifier.is_none());
+        assert_eq!(module.group[0].name, "GRP_1");
+        assert_eq!(
+            module.group[0]
+                .ref_measurement
+                .as_ref()
+                .unwrap()
+                .identifier_list,
+            vec!["OBJ_1"]
+        );
+        // the address is unchanged
+        assert_eq!(
+            module.measurement[0].ecu_address.as_ref().unwrap().address,
+            0x1000
+        );
+
+        // the mapping restores the original names
+        let text = a2l_file.write_to_string();
+        assert!(!text.contains("EngineSpeed") && !text.contains("secret"));
+        let mut log_msgs = Vec::new();
+        let mapping =
+            crate::rename::parse_rename_map(&crate::rename::format_rename_map(&mapping)).unwrap();
+        let count = crate::rename::rename_from_map(&mut a2l_file, &mapping, &mut log_msgs);
+        assert_eq!(count, 3);
+        assert_eq!(
+            a2l_file.project.module[0].measurement[0].name,
+            "EngineSpeed"
+        );
+    }
+}
@@ -0,0 +1,131 @@
+//! Helpers for processing multiple a2l files with the same operations
+
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+/// Expand a file pattern into a sorted list of matching files.
+///
+/// The wildcards `*` (any number of characters) and `?` (exactly one character) may only be used
+/// in the file name, not in the directory part of the pattern.
+/// A pattern without wildcards is returned unchanged, even if the file does not exist.
+///
+/// # Errors
+///
+/// Returns an error message if the directory cannot be read
+pub fn expand_file_pattern(pattern: &str) -> Result<Vec<PathBuf>, String> {
+    let path = Path::new(pattern);
+    let Some(file_pattern) = path.file_name().and_then(OsStr::to_str) else {
+        return Ok(vec![path.to_path_buf()]);
+    };
+    if !file_pattern.contains(['*', '?']) {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let entries = std::fs::read_dir(dir)
+        .map_err(|err| format!("Error: could not read directory {}: {err}", dir.display()))?;
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|ftype| ftype.is_file()))
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| wildcard_match(file_pattern, name))
+        })
+        .map(|entry| match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.join(entry.file_name()),
+            _ => PathBuf::from(entry.file_name()),
+        })
+        .collect();
+    files.sort();
+
+    Ok(files)
+}
+
+// match a name against a pattern containing the wildcards '*' and '?'
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    // iterative matching with backtracking to the most recent '*'
+    let (mut p_idx, mut n_idx) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n_idx < name.len() {
+        if p_idx < pattern.len() && (pattern[p_idx] == '?' || pattern[p_idx] == name[n_idx]) {
+            p_idx += 1;
+            n_idx += 1;
+        } else if p_idx < pattern.len() && pattern[p_idx] == '*' {
+            star = Some((p_idx, n_idx));
+            p_idx += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p_idx = star_p + 1;
+            n_idx = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p_idx..].iter().all(|c| *c == '*')
+}
+
+/// Build the output file name for an input file from a pattern.
+///
+/// The placeholders `{stem}` (file name without extension), `{name}` (complete file name) and
+/// `{ext}` (extension) are replaced using the input file name.
+/// A relative output path is placed in the directory of the input file.
+#[must_use]
+pub fn output_filename(pattern: &str, input: &Path) -> PathBuf {
+    let stem = input
+        .file_stem()
+        .map(OsStr::to_string_lossy)
+        .unwrap_or_default();
+    let name = input
+        .file_name()
+        .map(OsStr::to_string_lossy)
+        .unwrap_or_default();
+    let ext = input
+        .extension()
+        .map(OsStr::to_string_lossy)
+        .unwrap_or_default();
+    let filename = pattern
+        .replace("{stem}", &stem)
+        .replace("{name}", &name)
+        .replace("{ext}", &ext);
+
+    let output = PathBuf::from(filename);
+    match input.parent() {
+        Some(dir) if output.is_relative() => dir.join(output),
+        _ => output,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_expand_file_pattern() {
+        assert!(wildcard_match("*.a2l", "variant_1.a2l"));
+        assert!(wildcard_match("variant_?.a2l", "variant_1.a2l"));
+        assert!(wildcard_match("*_*.a2l", "variant_1.a2l"));
+        assert!(!wildcard_match("*.a2l", "variant_1.a2l.bak"));
+        assert!(!wildcard_match("variant_?.a2l", "variant_10.a2l"));
+
+        let files = expand_file_pattern("tests/update_test?.a2l").unwrap();
+        assert_eq!(files.len(), 4);
+        assert_eq!(files[0], Path::new("tests/update_test1.a2l"));
+        let files = expand_file_pattern("tests/no_match_*.a2l").unwrap();
+        assert!(files.is_empty());
+        let files = expand_file_pattern("single.a2l").unwrap();
+        assert_eq!(files, [Path::new("single.a2l")]);
+
+        let output = output_filename("{stem}_updated.{ext}", Path::new("variants/v1.a2l"));
+        assert_eq!(output, Path::new("variants/v1_updated.a2l"));
+        let output = output_filename("/out/{name}", Path::new("variants/v1.a2l"));
+        assert_eq!(output, Path::new("/out/v1.a2l"));
+    }
+}
@@ -0,0 +1,105 @@
+//! Record which elf file the addresses of an a2l file were taken from
+
+use crate::modpar::get_or_create_mod_par;
+use a2lfile::{A2lFile, SystemConstant};
+use std::ffi::OsStr;
+
+/// The name of the SYSTEM_CONSTANT in MOD_PAR that stores the build-id
+pub const BUILD_ID_CONSTANT: &str = "BUILD_ID";
+
+/// Read the identification of a build from an elf file.
+///
+/// Without a symbol name this is the GNU build-id note as a hex string. With a symbol name it is the content of
+/// the symbol, e.g. a version string. The content is returned as text if it is a string, and as hex otherwise.
+///
+/// # Errors
+///
+/// Returns an error message if the file can't be read, if it has no build-id, or if the symbol doesn't exist
+/// or has no initial value
+pub fn read_build_id(elf_filename: &OsStr, symbol_name: Option<&str>) -> Result<String, String> {
+    crate::dwarf::read_build_id(elf_filename, symbol_name)
+}
+
+/// Store the build-id in the SYSTEM_CONSTANT `BUILD_ID` in the MOD_PAR of every module.
+///
+/// MOD_PAR and the SYSTEM_CONSTANT are created if they don't exist.
+pub fn store_build_id(a2l_file: &mut A2lFile, build_id: &str) {
+    for module in &mut a2l_file.project.module {
+        let mod_par = get_or_create_mod_par(module);
+        if let Some(constant) = mod_par
+            .system_constant
+            .iter_mut()
+            .find(|constant| constant.name == BUILD_ID_CONSTANT)
+        {
+            constant.value = build_id.to_string();
+        } else {
+            mod_par.system_constant.push(SystemConstant::new(
+                BUILD_ID_CONSTANT.to_string(),
+                build_id.to_string(),
+            ));
+        }
+    }
+}
+
+/// Check that the build-id stored in every module matches the given build-id
+///
+/// # Errors
+///
+/// Returns an error message for the first module where the build-id is missing or different
+pub fn verify_build_id(a2l_file: &A2lFile, build_id: &str) -> Result<(), String> {
+    for module in &a2l_file.project.module {
+        let stored = module.mod_par.as_ref().and_then(|mod_par| {
+            mod_par
+                .system_constant
+                .iter()
+                .find(|constant| constant.name == BUILD_ID_CONSTANT)
+        });
+        match stored {
+            Some(constant) if constant.value == build_id => {}
+            Some(constant) => {
+                return Err(format!(
+                    "Error: the build-id \"{}\" in module {} does not match the build-id \"{build_id}\" of the elf file",
+                    constant.value, module.name
+                ))
+            }
+            None => {
+                return Err(format!(
+                    "Error: module {} does not contain the SYSTEM_CONSTANT {BUILD_ID_CONSTANT}",
+                    module.name
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_read_build_id() {
+        let elf_filename = OsStr::new("tests/elffiles/build_id.elf");
+        let build_id = read_build_id(elf_filename, None).unwrap();
+        assert_eq!(build_id, "4b9e4544506ea33532e82b5dccbb723528509edb");
+        let version = read_build_id(elf_filename, Some("sw_version")).unwrap();
+        assert_eq!(version, "SW 1.2.3");
+        // the symbol is in .bss
+        assert!(read_build_id(elf_filename, Some("counter")).is_err());
+        assert!(read_build_id(elf_filename, Some("missing")).is_err());
+        // this file was built without a build-id
+        assert!(read_build_id(OsStr::new("tests/elffiles/update_test.elf"), None).is_err());
+    }
+
+    #[test]
+    fn test_store_build_id() {
+        let mut a2l_file = crate::load::new_a2l();
+        assert!(verify_build_id(&a2l_file, "1234").is_err());
+        store_build_id(&mut a2l_file, "abcd");
+        store_build_id(&mut a2l_file, "1234");
+        let mod_par = a2l_file.project.module[0].mod_par.as_ref().unwrap();
+        assert_eq!(mod_par.system_constant.len(), 1);
+        assert!(verify_build_id(&a2l_file, "1234").is_ok());
+        assert!(verify_build_id(&a2l_file, "abcd").is_err());
+    }
+}
@@ -0,0 +1,270 @@
+//! Creation of CALIBRATION_METHODs and the calibration RAM in MOD_PAR from a config file
+
+use crate::modpar::get_or_create_mod_par;
+use crate::parse_number;
+use a2lfile::{
+    A2lFile, CalibrationHandle, CalibrationHandleText, CalibrationMethod, MemoryAttribute,
+    MemorySegment, MemoryType, PrgType,
+};
+use std::ffi::OsStr;
+
+#[derive(Debug, Clone, PartialEq)]
+struct MethodConfig {
+    method: String,
+    version: u32,
+    handles: Vec<(Vec<i32>, Option<String>)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct RamSegmentConfig {
+    name: String,
+    address: u32,
+    size: u32,
+}
+
+/// The calibration concept of an ECU, as read from a config file
+#[derive(Debug, Default)]
+pub struct CalibrationConfig {
+    methods: Vec<MethodConfig>,
+    ram_segments: Vec<RamSegmentConfig>,
+}
+
+impl CalibrationConfig {
+    /// load the calibration config from a file.
+    /// The file contains one entry per line, lines starting with '#' are comments:
+    /// - `METHOD <NAME> <VERSION>` starts a CALIBRATION_METHOD, e.g. `METHOD InCircuit2 1`
+    /// - `HANDLE <VALUE> ...` adds values to the CALIBRATION_HANDLE of the preceding METHOD
+    /// - `HANDLE_TEXT <TEXT>` sets the CALIBRATION_HANDLE_TEXT of the preceding METHOD
+    /// - `RAM_SEGMENT <NAME> <ADDRESS> <SIZE>` declares a calibration RAM window as MEMORY_SEGMENT
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the file cannot be read or contains an invalid entry
+    pub fn load(filename: &OsStr) -> Result<Self, String> {
+        let text = std::fs::read_to_string(filename).map_err(|err| {
+            format!(
+                "Error: could not read calibration config {}: {err}",
+                filename.to_string_lossy()
+            )
+        })?;
+        Self::parse(&text).map_err(|err| {
+            format!(
+                "Error: invalid calibration config {}: {err}",
+                filename.to_string_lossy()
+            )
+        })
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut config = CalibrationConfig::default();
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let rest = rest.trim();
+            let args: Vec<&str> = rest.split_whitespace().collect();
+            match keyword.to_ascii_uppercase().as_str() {
+                "METHOD" => {
+                    let [method, version] = args[..] else {
+                        return Err(format!(
+                            "line {}: expected METHOD <NAME> <VERSION>",
+                            idx + 1
+                        ));
+                    };
+                    let version = parse_number(version)
+                        .and_then(|version| u32::try_from(version).ok())
+                        .ok_or_else(|| format!("line {}: invalid version {version}", idx + 1))?;
+                    config.methods.push(MethodConfig {
+                        method: method.to_string(),
+                        version,
+                        handles: Vec::new(),
+                    });
+                }
+                "HANDLE" => {
+                    let Some(method) = config.methods.last_mut() else {
+                        return Err(format!("line {}: HANDLE without a METHOD", idx + 1));
+                    };
+                    let handle_list = args
+                        .iter()
+                        .map(|value| {
+                            parse_handle_value(value).ok_or_else(|| {
+                                format!("line {}: invalid handle value {value}", idx + 1)
+                            })
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    method.handles.push((handle_list, None));
+                }
+                "HANDLE_TEXT" => {
+                    let Some((_, text)) = config
+                        .methods
+                        .last_mut()
+                        .and_then(|method| method.handles.last_mut())
+                    else {
+                        return Err(format!("line {}: HANDLE_TEXT without a HANDLE", idx + 1));
+                    };
+                    *text = Some(rest.trim_matches('"').to_string());
+                }
+                "RAM_SEGMENT" => {
+                    let [name, address, size] = args[..] else {
+                        return Err(format!(
+                            "line {}: expected RAM_SEGMENT <NAME> <ADDRESS> <SIZE>",
+                            idx + 1
+                        ));
+                    };
+                    if !crate::rename::is_valid_identifier(name) {
+                        return Err(format!(
+                            "line {}: {name} is not a valid MEMORY_SEGMENT name",
+                            idx + 1
+                        ));
+                    }
+                    let parse_u32 = |value: &str| {
+                        parse_number(value)
+                            .and_then(|num| u32::try_from(num).ok())
+                            .ok_or_else(|| format!("line {}: invalid number {value}", idx + 1))
+                    };
+                    config.ram_segments.push(RamSegmentConfig {
+                        name: name.to_string(),
+                        address: parse_u32(address)?,
+                        size: parse_u32(size)?,
+                    });
+                }
+                _ => return Err(format!("line {}: unknown entry {keyword}", idx + 1)),
+            }
+        }
+        Ok(config)
+    }
+}
+
+// handles are often addresses, so values up to 0xFFFFFFFF are accepted and stored as i32
+fn parse_handle_value(text: &str) -> Option<i32> {
+    let value = parse_number(text)?;
+    i32::try_from(value)
+        .ok()
+        .or_else(|| u32::try_from(value).ok().map(u32::cast_signed))
+}
+
+/// Insert the CALIBRATION_METHODs and the calibration RAM MEMORY_SEGMENTs of the config into the MOD_PAR of each module.
+///
+/// An existing CALIBRATION_METHOD with the same name is replaced, and an existing MEMORY_SEGMENT with the same name is
+/// updated to describe the calibration RAM.
+///
+/// Returns the number of entries that were created or replaced.
+pub fn apply_calibration_config(
+    a2l_file: &mut A2lFile,
+    config: &CalibrationConfig,
+    log_msgs: &mut Vec<String>,
+) -> usize {
+    let mut count = 0;
+    for module in &mut a2l_file.project.module {
+        let module_name = module.name.clone();
+        let mod_par = get_or_create_mod_par(module);
+        for method_config in &config.methods {
+            let mut method =
+                CalibrationMethod::new(method_config.method.clone(), method_config.version);
+            // a CALIBRATION_METHOD has at most one CALIBRATION_HANDLE block, which can contain several handles
+            for (handle_list, text) in &method_config.handles {
+                let handle = method
+                    .calibration_handle
+                    .get_or_insert_with(CalibrationHandle::new);
+                handle.handle_list.extend(handle_list);
+                if let Some(text) = text {
+                    handle.calibration_handle_text = Some(CalibrationHandleText::new(text.clone()));
+                }
+            }
+            if let Some(existing) = mod_par
+                .calibration_method
+                .iter_mut()
+                .find(|existing| existing.method == method.method)
+            {
+                log_msgs.push(format!(
+                    "Replaced CALIBRATION_METHOD {} in module {}",
+                    method.method, module_name
+                ));
+                *existing = method;
+            } else {
+                mod_par.calibration_method.push(method);
+            }
+            count += 1;
+        }
+
+        for ram in &config.ram_segments {
+            if let Some(segment) = mod_par
+                .memory_segment
+                .iter_mut()
+                .find(|segment| segment.name == ram.name)
+            {
+                segment.prg_type = PrgType::CalibrationVariables;
+                segment.memory_type = MemoryType::Ram;
+                segment.address = ram.address;
+                segment.size = ram.size;
+            } else {
+                mod_par.memory_segment.push(MemorySegment::new(
+                    ram.name.clone(),
+                    "calibration RAM".to_string(),
+                    PrgType::CalibrationVariables,
+                    MemoryType::Ram,
+                    MemoryAttribute::Intern,
+                    ram.address,
+                    ram.size,
+                    [-1; 5],
+                ));
+            }
+            count += 1;
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_apply_calibration_config() {
+        assert!(CalibrationConfig::parse("HANDLE 1").is_err());
+        assert!(CalibrationConfig::parse("METHOD InCircuit2").is_err());
+        assert!(CalibrationConfig::parse("METHOD InCircuit2 1\nHANDLE x").is_err());
+        assert!(CalibrationConfig::parse("RAM_SEGMENT cal 0x100000000 16").is_err());
+        assert!(CalibrationConfig::parse("COLOR blue").is_err());
+
+        let config = CalibrationConfig::parse(
+            r#"
+            # working page calibration
+            METHOD InCircuit2 1
+            HANDLE 0x70000000 0x8000 0x80000000
+            HANDLE_TEXT "working page"
+            RAM_SEGMENT CalRam 0x70000000 0x8000
+            "#,
+        )
+        .unwrap();
+
+        let mut a2l_file = crate::load::new_a2l();
+        let mut log_msgs = Vec::new();
+        let count = apply_calibration_config(&mut a2l_file, &config, &mut log_msgs);
+        assert_eq!(count, 2);
+        assert!(log_msgs.is_empty());
+        // applying the config again replaces the entries instead of duplicating them
+        let count = apply_calibration_config(&mut a2l_file, &config, &mut log_msgs);
+        assert_eq!(count, 2);
+        assert_eq!(log_msgs.len(), 1);
+
+        let mod_par = a2l_file.project.module[0].mod_par.as_ref().unwrap();
+        assert_eq!(mod_par.calibration_method.len(), 1);
+        let method = &mod_par.calibration_method[0];
+        assert_eq!(method.method, "InCircuit2");
+        let handle = method.calibration_handle.as_ref().unwrap();
+        assert_eq!(handle.handle_list, vec![0x7000_0000, 0x8000, i32::MIN]);
+        assert_eq!(
+            handle.calibration_handle_text.as_ref().unwrap().text,
+            "working page"
+        );
+        assert_eq!(mod_par.memory_segment.len(), 1);
+        assert_eq!(
+            mod_par.memory_segment[0].prg_type,
+            PrgType::CalibrationVariables
+        );
+        assert_eq!(mod_par.memory_segment[0].size, 0x8000);
+    }
+}
@@ -0,0 +1,1150 @@
+//! Decoding of calibration values (CHARACTERISTICs and AXIS_PTS) from a memory image
+
+use crate::cdfx;
+use crate::compu::{phys_to_raw, raw_to_phys, PhysValue};
+use crate::dcm;
+use crate::hexfile::HexImage;
+use crate::memmap::datatype_size;
+use a2lfile::{
+    A2lFile, AxisDescr, AxisDescrAttribute, AxisPts, ByteOrder, ByteOrderEnum, Characteristic,
+    CharacteristicType, DataType, DataTypeSize, IndexMode, Module, RecordLayout,
+};
+use std::ffi::OsStr;
+use std::fmt::Display;
+
+/// The decoded value of a CHARACTERISTIC or AXIS_PTS
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalibrationValue {
+    pub name: String,
+    /// CHARACTERISTIC or AXIS_PTS
+    pub blocktype: &'static str,
+    pub unit: String,
+    /// the physical values of each axis. The values of CURVE_AXIS and RES_AXIS are not decoded and remain empty.
+    pub axes: Vec<Vec<PhysValue>>,
+    /// the dimensions of the values; empty for a single value
+    pub dims: Vec<usize>,
+    /// the physical values. For MAPs the values are ordered by x, i.e. values[x * y_count + y]
+    pub values: Vec<PhysValue>,
+}
+
+// the part of the memory layout of an object that holds values, found by walking its record layout
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ItemKind {
+    NoAxisPts(usize),
+    AxisPts(usize),
+    FncValues,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LayoutItem {
+    pub(crate) kind: ItemKind,
+    pub(crate) address: u32,
+    pub(crate) datatype: DataType,
+    pub(crate) count: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Endian {
+    Little,
+    Big,
+}
+
+/// Decode the values of all CHARACTERISTICs and AXIS_PTS using the data in the memory image.
+///
+/// Objects whose values can't be decoded are skipped, and the reason is added to `log_msgs`.
+#[must_use]
+pub fn read_calibration_values(
+    a2l_file: &A2lFile,
+    image: &HexImage,
+    log_msgs: &mut Vec<String>,
+) -> Vec<CalibrationValue> {
+    let mut result = Vec::new();
+    for module in &a2l_file.project.module {
+        for axis_pts in &module.axis_pts {
+            match read_axis_pts(module, axis_pts, image) {
+                Ok(value) => result.push(value),
+                Err(err) => log_msgs.push(format!("AXIS_PTS {}: {err}", axis_pts.name)),
+            }
+        }
+        for characteristic in &module.characteristic {
+            match read_characteristic(module, characteristic, image) {
+                Ok(value) => result.push(value),
+                Err(err) => log_msgs.push(format!("CHARACTERISTIC {}: {err}", characteristic.name)),
+            }
+        }
+    }
+    result
+}
+
+fn read_axis_pts(
+    module: &Module,
+    axis_pts: &AxisPts,
+    image: &HexImage,
+) -> Result<CalibrationValue, String> {
+    let record_layout = find_record_layout(module, &axis_pts.deposit_record)?;
+    let endian = get_endian(module, axis_pts.byte_order.as_ref())?;
+    let mut axis_counts = [usize::from(axis_pts.max_axis_points)];
+    let items = layout_items(
+        record_layout,
+        axis_pts.address,
+        &mut axis_counts,
+        0,
+        Some(image),
+        endian,
+    )?;
+    let raw = read_item(&items, ItemKind::AxisPts(0), image, endian, None)?;
+    let values = convert_values(module, &axis_pts.conversion, &raw)?;
+    Ok(CalibrationValue {
+        name: axis_pts.name.clone(),
+        blocktype: "AXIS_PTS",
+        unit: unit_of(module, &axis_pts.conversion),
+        axes: Vec::new(),
+        dims: vec![values.len()],
+        values,
+    })
+}
+
+fn read_characteristic(
+    module: &Module,
+    characteristic: &Characteristic,
+    image: &HexImage,
+) -> Result<CalibrationValue, String> {
+    let unit = unit_of(module, &characteristic.conversion);
+    let block_dims = block_dims(characteristic);
+
+    if characteristic.characteristic_type == CharacteristicType::Ascii {
+        let len = block_dims.iter().product();
+        let bytes = image
+            .read(characteristic.address, len)
+            .ok_or_else(|| no_data_msg(characteristic.address))?;
+        let text = String::from_utf8_lossy(bytes)
+            .trim_end_matches('\0')
+            .to_string();
+        return Ok(CalibrationValue {
+            name: characteristic.name.clone(),
+            blocktype: "CHARACTERISTIC",
+            unit,
+            axes: Vec::new(),
+            dims: Vec::new(),
+            values: vec![PhysValue::Text(text)],
+        });
+    }
+
+    let record_layout = find_record_layout(module, &characteristic.deposit)?;
+    let endian = get_endian(module, characteristic.byte_order.as_ref())?;
+    let mut axis_counts: Vec<usize> = characteristic
+        .axis_descr
+        .iter()
+        .map(|axis_descr| usize::from(axis_descr.max_axis_points))
+        .collect();
+
+    // axes which are not stored in the record layout of the characteristic itself
+    let mut axes = Vec::new();
+    for (idx, axis_descr) in characteristic.axis_descr.iter().enumerate() {
+        let axis = external_axis_values(module, axis_descr, image)?;
+        if let Some(values) = &axis {
+            axis_counts[idx] = values.len();
+        }
+        axes.push(axis);
+    }
+
+    let (fnc_count, dims) = match characteristic.characteristic_type {
+        CharacteristicType::Value => (1, Vec::new()),
+        CharacteristicType::ValBlk => (block_dims.iter().product(), block_dims),
+        _ => (0, Vec::new()),
+    };
+    let items = layout_items(
+        record_layout,
+        characteristic.address,
+        &mut axis_counts,
+        fnc_count,
+        Some(image),
+        endian,
+    )?;
+    let dims = if dims.is_empty() && !axis_counts.is_empty() {
+        axis_counts.clone()
+    } else {
+        dims
+    };
+
+    let mut axis_values = Vec::new();
+    for (idx, (axis_descr, axis)) in characteristic.axis_descr.iter().zip(axes).enumerate() {
+        if let Some(axis) = axis {
+            axis_values.push(axis);
+        } else if axis_descr.attribute == AxisDescrAttribute::StdAxis {
+            let raw = read_item(&items, ItemKind::AxisPts(idx), image, endian, None)?;
+            axis_values.push(convert_values(module, &axis_descr.conversion, &raw)?);
+        } else {
+            axis_values.push(Vec::new());
+        }
+    }
+
+    let bit_mask = characteristic.bit_mask.as_ref().map(|bm| bm.mask);
+    let mut raw = read_item(&items, ItemKind::FncValues, image, endian, bit_mask)?;
+    if let Some(fnc_values) = &record_layout.fnc_values {
+        if dims.len() == 2 {
+            match fnc_values.index_mode {
+                IndexMode::ColumnDir => {}
+                IndexMode::RowDir => raw = transpose(&raw, dims[1], dims[0]),
+                _ => {
+                    return Err(format!(
+                        "the index mode {} is not supported",
+                        fnc_values.index_mode
+                    ))
+                }
+            }
+        }
+    }
+    let values = convert_values(module, &characteristic.conversion, &raw)?;
+
+    Ok(CalibrationValue {
+        name: characteristic.name.clone(),
+        blocktype: "CHARACTERISTIC",
+        unit,
+        axes: axis_values,
+        dims,
+        values,
+    })
+}
+
+// the dimensions of a VAL_BLK or ASCII characteristic
+pub(crate) fn block_dims(characteristic: &Characteristic) -> Vec<usize> {
+    if let Some(matrix_dim) = &characteristic.matrix_dim {
+        matrix_dim
+            .dim_list
+            .iter()
+            .map(|dim| usize::from(*dim))
+            .collect()
+    } else if let Some(number) = &characteristic.number {
+        vec![usize::from(number.number)]
+    } else {
+        vec![1]
+    }
+}
+
+/// Load a calibration data file in DCM or CDFx format. The format is detected from the content of the file.
+///
+/// # Errors
+///
+/// Returns an error message if the file cannot be read or parsed
+pub fn load_calibration_file(filename: &OsStr) -> Result<Vec<CalibrationValue>, String> {
+    let data = std::fs::read(filename).map_err(|err| {
+        format!(
+            "Error: could not read \"{}\": {err}",
+            filename.to_string_lossy()
+        )
+    })?;
+    // DCM files are often encoded in Latin-1, which is handled well enough by a lossy conversion
+    let text = String::from_utf8_lossy(&data);
+    let result = if text.trim_start().starts_with('<') {
+        cdfx::parse_cdfx(&text)
+    } else {
+        dcm::parse_dcm(&text)
+    };
+    result.map_err(|err| format!("{err} in \"{}\"", filename.to_string_lossy()))
+}
+
+/// Write calibration values into a memory image. The values are matched to the CHARACTERISTICs and AXIS_PTS by name.
+///
+/// Values that can't be written are skipped, and the reason is added to `log_msgs`.
+/// Returns the number of values that were written.
+pub fn write_calibration_values(
+    a2l_file: &A2lFile,
+    image: &mut HexImage,
+    values: &[CalibrationValue],
+    log_msgs: &mut Vec<String>,
+) -> usize {
+    let mut count = 0;
+    for value in values {
+        let result = a2l_file.project.module.iter().find_map(|module| {
+            if let Some(characteristic) =
+                module.characteristic.iter().find(|c| c.name == value.name)
+            {
+                Some(encode_characteristic(module, characteristic, image, value))
+            } else {
+                module
+                    .axis_pts
+                    .iter()
+                    .find(|ap| ap.name == value.name)
+                    .map(|axis_pts| encode_axis_pts(module, axis_pts, image, value))
+            }
+        });
+        match result {
+            Some(Ok(writes)) => {
+                for (address, bytes) in writes {
+                    // all addresses were checked during encoding
+                    let _ = image.write(address, &bytes);
+                }
+                count += 1;
+            }
+            Some(Err(err)) => log_msgs.push(format!("{}: {err}", value.name)),
+            None => log_msgs.push(format!(
+                "{}: there is no CHARACTERISTIC or AXIS_PTS with this name",
+                value.name
+            )),
+        }
+    }
+    count
+}
+
+fn encode_axis_pts(
+    module: &Module,
+    axis_pts: &AxisPts,
+    image: &HexImage,
+    value: &CalibrationValue,
+) -> Result<Vec<(u32, Vec<u8>)>, String> {
+    let record_layout = find_record_layout(module, &axis_pts.deposit_record)?;
+    let endian = get_endian(module, axis_pts.byte_order.as_ref())?;
+    let max_count = usize::from(axis_pts.max_axis_points);
+    let mut axis_counts = [max_count];
+    layout_items(
+        record_layout,
+        axis_pts.address,
+        &mut axis_counts,
+        0,
+        Some(image),
+        endian,
+    )?;
+    let new_counts = new_axis_counts(record_layout, &axis_counts, &[max_count], &[&value.values])?;
+    let mut axis_counts = [new_counts[0]];
+    let items = layout_items(
+        record_layout,
+        axis_pts.address,
+        &mut axis_counts,
+        0,
+        None,
+        endian,
+    )?;
+    let mut writes = Vec::new();
+    encode_no_axis_pts(&items, &axis_counts, endian, &mut writes)?;
+    let raw = convert_to_raw(module, &axis_pts.conversion, &value.values)?;
+    encode_item(
+        &items,
+        ItemKind::AxisPts(0),
+        &raw,
+        image,
+        endian,
+        None,
+        &mut writes,
+    )?;
+    Ok(writes)
+}
+
+fn encode_characteristic(
+    module: &Module,
+    characteristic: &Characteristic,
+    image: &HexImage,
+    value: &CalibrationValue,
+) -> Result<Vec<(u32, Vec<u8>)>, String> {
+    let block_dims = block_dims(characteristic);
+    if characteristic.characteristic_type == CharacteristicType::Ascii {
+        let len = block_dims.iter().product();
+        let Some(PhysValue::Text(text)) = value.values.first() else {
+            return Err("the value of an ASCII characteristic must be a text".to_string());
+        };
+        if text.len() > len {
+            return Err(format!("the text is longer than {len} characters"));
+        }
+        let mut bytes = text.as_bytes().to_vec();
+        bytes.resize(len, 0);
+        if image.read(characteristic.address, len).is_none() {
+            return Err(no_data_msg(characteristic.address));
+        }
+        return Ok(vec![(characteristic.address, bytes)]);
+    }
+
+    let record_layout = find_record_layout(module, &characteristic.deposit)?;
+    let endian = get_endian(module, characteristic.byte_order.as_ref())?;
+    let max_counts: Vec<usize> = characteristic
+        .axis_descr
+        .iter()
+        .map(|axis_descr| usize::from(axis_descr.max_axis_points))
+        .collect();
+    let mut axis_counts = max_counts.clone();
+    for (idx, axis_descr) in characteristic.axis_descr.iter().enumerate() {
+        if let Some(axis) = external_axis_values(module, axis_descr, image)? {
+            axis_counts[idx] = axis.len();
+        }
+    }
+    let fnc_count = match characteristic.characteristic_type {
+        CharacteristicType::Value => 1,
+        CharacteristicType::ValBlk => block_dims.iter().product(),
+        _ => 0,
+    };
+    layout_items(
+        record_layout,
+        characteristic.address,
+        &mut axis_counts,
+        fnc_count,
+        Some(image),
+        endian,
+    )?;
+
+    // only the points of standard axes are stored in the characteristic
+    let std_axes: Vec<&[PhysValue]> = characteristic
+        .axis_descr
+        .iter()
+        .enumerate()
+        .map(|(idx, axis_descr)| {
+            if axis_descr.attribute == AxisDescrAttribute::StdAxis {
+                value.axes.get(idx).map_or(&[][..], Vec::as_slice)
+            } else {
+                &[][..]
+            }
+        })
+        .collect();
+    let mut axis_counts = new_axis_counts(record_layout, &axis_counts, &max_counts, &std_axes)?;
+    let items = layout_items(
+        record_layout,
+        characteristic.address,
+        &mut axis_counts,
+        fnc_count,
+        None,
+        endian,
+    )?;
+
+    let mut writes = Vec::new();
+    encode_no_axis_pts(&items, &axis_counts, endian, &mut writes)?;
+    for (idx, axis) in std_axes.iter().enumerate() {
+        if !axis.is_empty() {
+            let conversion = &characteristic.axis_descr[idx].conversion;
+            let raw = convert_to_raw(module, conversion, axis)?;
+            encode_item(
+                &items,
+                ItemKind::AxisPts(idx),
+                &raw,
+                image,
+                endian,
+                None,
+                &mut writes,
+            )?;
+        }
+    }
+
+    let mut raw = convert_to_raw(module, &characteristic.conversion, &value.values)?;
+    if let Some(fnc_values) = &record_layout.fnc_values {
+        if axis_counts.len() == 2 && fnc_values.index_mode == IndexMode::RowDir {
+            raw = transpose(&raw, axis_counts[0], axis_counts[1]);
+        }
+    }
+    let bit_mask = characteristic.bit_mask.as_ref().map(|bm| bm.mask);
+    encode_item(
+        &items,
+        ItemKind::FncValues,
+        &raw,
+        image,
+        endian,
+        bit_mask,
+        &mut writes,
+    )?;
+    Ok(writes)
+}
+
+// get the number of axis points after writing the new axes. The number can only change if it is stored in the record layout
+fn new_axis_counts(
+    record_layout: &RecordLayout,
+    current_counts: &[usize],
+    max_counts: &[usize],
+    new_axes: &[&[PhysValue]],
+) -> Result<Vec<usize>, String> {
+    let no_axis_pts = [
+        record_layout.no_axis_pts_x.is_some(),
+        record_layout.no_axis_pts_y.is_some(),
+        record_layout.no_axis_pts_z.is_some(),
+        record_layout.no_axis_pts_4.is_some(),
+        record_layout.no_axis_pts_5.is_some(),
+    ];
+    let mut counts = current_counts.to_vec();
+    for (idx, axis) in new_axes.iter().enumerate() {
+        if axis.is_empty() || axis.len() == counts[idx] {
+            continue;
+        }
+        if !no_axis_pts.get(idx).copied().unwrap_or(false) {
+            return Err(format!(
+                "axis {idx} has {} points, but {} are required",
+                axis.len(),
+                counts[idx]
+            ));
+        }
+        if axis.len() > max_counts[idx] {
+            return Err(format!(
+                "axis {idx} has {} points, but the maximum is {}",
+                axis.len(),
+                max_counts[idx]
+            ));
+        }
+        counts[idx] = axis.len();
+    }
+    Ok(counts)
+}
+
+fn encode_no_axis_pts(
+    items: &[LayoutItem],
+    axis_counts: &[usize],
+    endian: Endian,
+    writes: &mut Vec<(u32, Vec<u8>)>,
+) -> Result<(), String> {
+    for item in items {
+        if let ItemKind::NoAxisPts(idx) = item.kind {
+            let count = axis_counts.get(idx).copied().unwrap_or(0) as f64;
+            let bytes = encode_raw(count, item.datatype, endian).ok_or_else(|| {
+                format!("{count} axis points can't be stored as {}", item.datatype)
+            })?;
+            writes.push((item.address, bytes));
+        }
+    }
+    Ok(())
+}
+
+fn encode_item(
+    items: &[LayoutItem],
+    kind: ItemKind,
+    raw: &[f64],
+    image: &HexImage,
+    endian: Endian,
+    bit_mask: Option<u32>,
+    writes: &mut Vec<(u32, Vec<u8>)>,
+) -> Result<(), String> {
+    let Some(item) = items.iter().find(|item| item.kind == kind) else {
+        return Err(match kind {
+            ItemKind::FncValues => "the record layout has no FNC_VALUES".to_string(),
+            _ => "the record layout does not contain the axis points".to_string(),
+        });
+    };
+    if raw.len() != item.count {
+        return Err(format!(
+            "{} values are required, but {} were given",
+            item.count,
+            raw.len()
+        ));
+    }
+    let size = datatype_size(item.datatype);
+    for (idx, value) in raw.iter().enumerate() {
+        let address = item.address.wrapping_add(idx as u32 * size);
+        let mut value = *value;
+        if let Some(mask) = bit_mask {
+            // keep the bits outside of the mask unchanged
+            let current = read_raw(image, address, item.datatype, endian, None)
+                .ok_or_else(|| no_data_msg(address))?;
+            let mask = u64::from(mask);
+            let shifted = (value.round() as u64) << mask.trailing_zeros();
+            value = (((current as u64) & !mask) | (shifted & mask)) as f64;
+        }
+        let bytes = encode_raw(value, item.datatype, endian).ok_or_else(|| {
+            format!(
+                "the value {value} at 0x{address:08X} is out of range for {}",
+                item.datatype
+            )
+        })?;
+        if image.read(address, bytes.len()).is_none() {
+            return Err(no_data_msg(address));
+        }
+        writes.push((address, bytes));
+    }
+    Ok(())
+}
+
+// encode a raw value according to its data type. Integers are rounded; None is returned if the value is out of range
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub(crate) fn encode_raw(value: f64, datatype: DataType, endian: Endian) -> Option<Vec<u8>> {
+    let rounded = value.round();
+    let in_range = |min: f64, max: f64| (min..=max).contains(&rounded);
+    let le_bytes: Vec<u8> = match datatype {
+        DataType::Ubyte if in_range(0.0, 255.0) => vec![rounded as u8],
+        DataType::Sbyte if in_range(-128.0, 127.0) => (rounded as i8).to_le_bytes().to_vec(),
+        DataType::Uword if in_range(0.0, 65535.0) => (rounded as u16).to_le_bytes().to_vec(),
+        DataType::Sword if in_range(-32768.0, 32767.0) => (rounded as i16).to_le_bytes().to_vec(),
+        DataType::Ulong if in_range(0.0, 4_294_967_295.0) => {
+            (rounded as u32).to_le_bytes().to_vec()
+        }
+        DataType::Slong if in_range(-2_147_483_648.0, 2_147_483_647.0) => {
+            (rounded as i32).to_le_bytes().to_vec()
+        }
+        DataType::AUint64 if in_range(0.0, u64::MAX as f64) => {
+            (rounded as u64).to_le_bytes().to_vec()
+        }
+        DataType::AInt64 if in_range(i64::MIN as f64, i64::MAX as f64) => {
+            (rounded as i64).to_le_bytes().to_vec()
+        }
+        DataType::Float16Ieee => f64_to_f16(value).to_le_bytes().to_vec(),
+        DataType::Float32Ieee => (value as f32).to_le_bytes().to_vec(),
+        DataType::Float64Ieee => value.to_le_bytes().to_vec(),
+        _ => return None,
+    };
+    Some(match endian {
+        Endian::Little => le_bytes,
+        Endian::Big => le_bytes.into_iter().rev().collect(),
+    })
+}
+
+fn convert_to_raw(
+    module: &Module,
+    conversion: &str,
+    values: &[PhysValue],
+) -> Result<Vec<f64>, String> {
+    values
+        .iter()
+        .map(|value| {
+            phys_to_raw(module, conversion, value)
+                .ok_or_else(|| format!("the value {value} cannot be converted with {conversion}"))
+        })
+        .collect()
+}
+
+// get the values of an axis that is not part of the characteristic: COM_AXIS and FIX_AXIS
+fn external_axis_values(
+    module: &Module,
+    axis_descr: &AxisDescr,
+    image: &HexImage,
+) -> Result<Option<Vec<PhysValue>>, String> {
+    let raw: Vec<f64> = match axis_descr.attribute {
+        AxisDescrAttribute::ComAxis => {
+            let Some(axis_pts_ref) = &axis_descr.axis_pts_ref else {
+                return Err("COM_AXIS without AXIS_PTS_REF".to_string());
+            };
+            let Some(axis_pts) = module
+                .axis_pts
+                .iter()
+                .find(|ap| ap.name == axis_pts_ref.axis_points)
+            else {
+                return Err(format!(
+                    "the AXIS_PTS {} does not exist",
+                    axis_pts_ref.axis_points
+                ));
+            };
+            return read_axis_pts(module, axis_pts, image).map(|value| Some(value.values));
+        }
+        AxisDescrAttribute::FixAxis => {
+            if let Some(par) = &axis_descr.fix_axis_par {
+                (0..par.number_apo)
+                    .map(|i| f64::from(par.offset) + f64::from(i) * 2f64.powi(par.shift.into()))
+                    .collect()
+            } else if let Some(par) = &axis_descr.fix_axis_par_dist {
+                (0..par.number_apo)
+                    .map(|i| f64::from(par.offset) + f64::from(i) * f64::from(par.distance))
+                    .collect()
+            } else if let Some(par) = &axis_descr.fix_axis_par_list {
+                par.axis_pts_value_list.clone()
+            } else {
+                return Err("FIX_AXIS without FIX_AXIS_PAR".to_string());
+            }
+        }
+        _ => return Ok(None),
+    };
+    convert_values(module, &axis_descr.conversion, &raw).map(Some)
+}
+
+// walk through the elements of a record layout in the order of their positions, and calculate the address of each.
+// If an image is given, the number of axis points is read from it if the record layout contains NO_AXIS_PTS_x,
+// and axis_counts is updated. Otherwise axis_counts is used as-is. Alignment is not taken into account.
+pub(crate) fn layout_items(
+    record_layout: &RecordLayout,
+    address: u32,
+    axis_counts: &mut [usize],
+    fnc_count: usize,
+    image: Option<&HexImage>,
+    endian: Endian,
+) -> Result<Vec<LayoutItem>, String> {
+    let fix_no_axis_pts = [
+        &record_layout.fix_no_axis_pts_x,
+        &record_layout.fix_no_axis_pts_y,
+        &record_layout.fix_no_axis_pts_z,
+        &record_layout.fix_no_axis_pts_4,
+        &record_layout.fix_no_axis_pts_5,
+    ];
+    for (idx, fix_no) in fix_no_axis_pts.iter().enumerate() {
+        if let (Some(fix_no), Some(count)) = (fix_no, axis_counts.get_mut(idx)) {
+            *count = usize::from(fix_no.number_of_axis_points);
+        }
+    }
+
+    // (position, kind, datatype, size of elements that are skipped)
+    let mut elements: Vec<(u16, Option<ItemKind>, DataType, u32)> = Vec::new();
+    let no_axis_pts = [
+        &record_layout.no_axis_pts_x,
+        &record_layout.no_axis_pts_y,
+        &record_layout.no_axis_pts_z,
+        &record_layout.no_axis_pts_4,
+        &record_layout.no_axis_pts_5,
+    ];
+    for (idx, dim) in no_axis_pts.iter().enumerate() {
+        if let Some(dim) = dim {
+            elements.push((
+                dim.position,
+                Some(ItemKind::NoAxisPts(idx)),
+                dim.datatype,
+                0,
+            ));
+        }
+    }
+    let axis_pts = [
+        &record_layout.axis_pts_x,
+        &record_layout.axis_pts_y,
+        &record_layout.axis_pts_z,
+        &record_layout.axis_pts_4,
+        &record_layout.axis_pts_5,
+    ];
+    for (idx, dim) in axis_pts.iter().enumerate() {
+        if let Some(dim) = dim {
+            elements.push((dim.position, Some(ItemKind::AxisPts(idx)), dim.datatype, 0));
+        }
+    }
+    if let Some(fnc_values) = &record_layout.fnc_values {
+        elements.push((
+            fnc_values.position,
+            Some(ItemKind::FncValues),
+            fnc_values.datatype,
+            0,
+        ));
+    }
+    for reserved in &record_layout.reserved {
+        let size = match reserved.data_size {
+            DataTypeSize::Byte => 1,
+            DataTypeSize::Word => 2,
+            DataTypeSize::Long => 4,
+        };
+        elements.push((reserved.position, None, DataType::Ubyte, size));
+    }
+    let single_elements = [
+        record_layout
+            .identification
+            .as_ref()
+            .map(|e| (e.position, e.datatype)),
+        record_layout
+            .offset_x
+            .as_ref()
+            .map(|e| (e.position, e.datatype)),
+        record_layout
+            .offset_y
+            .as_ref()
+            .map(|e| (e.position, e.datatype)),
+        record_layout
+            .offset_z
+            .as_ref()
+            .map(|e| (e.position, e.datatype)),
+        record_layout
+            .dist_op_x
+            .as_ref()
+            .map(|e| (e.position, e.datatype)),
+        record_layout
+            .dist_op_y
+            .as_ref()
+            .map(|e| (e.position, e.datatype)),
+        record_layout
+            .dist_op_z
+            .as_ref()
+            .map(|e| (e.position, e.datatype)),
+        record_layout
+            .shift_op_x
+            .as_ref()
+            .map(|e| (e.position, e.datatype)),
+        record_layout
+            .shift_op_y
+            .as_ref()
+            .map(|e| (e.position, e.datatype)),
+        record_layout
+            .shift_op_z
+            .as_ref()
+            .map(|e| (e.position, e.datatype)),
+        record_layout
+            .src_addr_x
+            .as_ref()
+            .map(|e| (e.position, e.datatype)),
+        record_layout
+            .src_addr_y
+            .as_ref()
+            .map(|e| (e.position, e.datatype)),
+        record_layout
+            .src_addr_z
+            .as_ref()
+            .map(|e| (e.position, e.datatype)),
+        record_layout
+            .rip_addr_w
+            .as_ref()
+            .map(|e| (e.position, e.datatype)),
+        record_layout
+            .rip_addr_x
+            .as_ref()
+            .map(|e| (e.position, e.datatype)),
+        record_layout
+            .rip_addr_y
+            .as_ref()
+            .map(|e| (e.position, e.datatype)),
+        record_layout
+            .rip_addr_z
+            .as_ref()
+            .map(|e| (e.position, e.datatype)),
+    ];
+    for (position, datatype) in single_elements.into_iter().flatten() {
+        elements.push((position, None, datatype, datatype_size(datatype)));
+    }
+    elements.sort_by_key(|(position, ..)| *position);
+
+    let mut items = Vec::new();
+    let mut offset = 0u32;
+    for (_, kind, datatype, skip_size) in elements {
+        let item_address = address.wrapping_add(offset);
+        let count = match kind {
+            None => {
+                offset += skip_size;
+                continue;
+            }
+            Some(ItemKind::NoAxisPts(idx)) => {
+                if let Some(image) = image {
+                    let value = read_raw(image, item_address, datatype, endian, None)
+                        .ok_or_else(|| no_data_msg(item_address))?;
+                    if let Some(max_count) = axis_counts.get_mut(idx) {
+                        // the value can't be trusted if it is larger than the maximum
+                        if value < 0.0 || value > *max_count as f64 {
+                            return Err(format!(
+                                "the number of axis points ({value}) at 0x{item_address:08X} is invalid"
+                            ));
+                        }
+                        *max_count = value as usize;
+                    }
+                }
+                1
+            }
+            Some(ItemKind::AxisPts(idx)) => axis_counts.get(idx).copied().unwrap_or(1),
+            Some(ItemKind::FncValues) => {
+                if fnc_count > 0 {
+                    fnc_count
+                } else {
+                    axis_counts.iter().product()
+                }
+            }
+        };
+        items.push(LayoutItem {
+            kind: kind.unwrap(),
+            address: item_address,
+            datatype,
+            count,
+        });
+        offset += datatype_size(datatype) * count as u32;
+    }
+    Ok(items)
+}
+
+fn read_item(
+    items: &[LayoutItem],
+    kind: ItemKind,
+    image: &HexImage,
+    endian: Endian,
+    bit_mask: Option<u32>,
+) -> Result<Vec<f64>, String> {
+    let Some(item) = items.iter().find(|item| item.kind == kind) else {
+        return Err(match kind {
+            ItemKind::FncValues => "the record layout has no FNC_VALUES".to_string(),
+            _ => "the record layout does not contain the axis points".to_string(),
+        });
+    };
+    let size = datatype_size(item.datatype);
+    (0..item.count)
+        .map(|idx| {
+            let address = item.address.wrapping_add(idx as u32 * size);
+            read_raw(image, address, item.datatype, endian, bit_mask)
+                .ok_or_else(|| no_data_msg(address))
+        })
+        .collect()
+}
+
+// read a single raw value from the image
+pub(crate) fn read_raw(
+    image: &HexImage,
+    address: u32,
+    datatype: DataType,
+    endian: Endian,
+    bit_mask: Option<u32>,
+) -> Option<f64> {
+    let size = datatype_size(datatype) as usize;
+    let bytes = image.read(address, size)?;
+    let mut buffer = [0u8; 8];
+    match endian {
+        Endian::Little => buffer[..size].copy_from_slice(bytes),
+        Endian::Big => {
+            for (idx, byte) in bytes.iter().rev().enumerate() {
+                buffer[idx] = *byte;
+            }
+        }
+    }
+    let mut raw = u64::from_le_bytes(buffer);
+    if let Some(mask) = bit_mask {
+        let mask = u64::from(mask);
+        raw = (raw & mask) >> mask.trailing_zeros();
+    }
+    // truncation is intended: the value is reinterpreted according to its data type
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    let value = match datatype {
+        DataType::Ubyte | DataType::Uword | DataType::Ulong => raw as f64,
+        DataType::AUint64 => raw as f64,
+        DataType::Sbyte => f64::from(raw as u8 as i8),
+        DataType::Sword => f64::from(raw as u16 as i16),
+        DataType::Slong => f64::from(raw as u32 as i32),
+        DataType::AInt64 => raw as i64 as f64,
+        DataType::Float16Ieee => f16_to_f64(raw as u16),
+        DataType::Float32Ieee => f64::from(f32::from_bits(raw as u32)),
+        DataType::Float64Ieee => f64::from_bits(raw),
+    };
+    Some(value)
+}
+
+// convert to half precision, rounding towards zero
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn f64_to_f16(value: f64) -> u16 {
+    let bits = (value as f32).to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = bits & 0x7f_ffff;
+    if value.is_nan() {
+        sign | 0x7e00
+    } else if exponent >= 0x1f {
+        sign | 0x7c00
+    } else if exponent <= 0 {
+        // subnormal or zero
+        if exponent < -10 {
+            sign
+        } else {
+            sign | (((mantissa | 0x80_0000) >> (14 - exponent)) as u16)
+        }
+    } else {
+        sign | ((exponent as u16) << 10) | ((mantissa >> 13) as u16)
+    }
+}
+
+fn f16_to_f64(bits: u16) -> f64 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = i32::from((bits >> 10) & 0x1f);
+    let mantissa = f64::from(bits & 0x3ff);
+    match exponent {
+        0 => sign * mantissa * 2f64.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f64::INFINITY,
+        0x1f => f64::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f64.powi(exponent - 15),
+    }
+}
+
+// the byte order of an object, which defaults to the byte order in MOD_COMMON
+pub(crate) fn get_endian(
+    module: &Module,
+    byte_order: Option<&ByteOrder>,
+) -> Result<Endian, String> {
+    let byte_order = byte_order.or_else(|| {
+        module
+            .mod_common
+            .as_ref()
+            .and_then(|mod_common| mod_common.byte_order.as_ref())
+    });
+    match byte_order.map(|bo| bo.byte_order) {
+        None | Some(ByteOrderEnum::LittleEndian | ByteOrderEnum::MsbLast) => Ok(Endian::Little),
+        Some(ByteOrderEnum::BigEndian | ByteOrderEnum::MsbFirst) => Ok(Endian::Big),
+        Some(other) => Err(format!("the byte order {other} is not supported")),
+    }
+}
+
+pub(crate) fn find_record_layout<'a>(
+    module: &'a Module,
+    name: &str,
+) -> Result<&'a RecordLayout, String> {
+    module
+        .record_layout
+        .iter()
+        .find(|rl| rl.name == name)
+        .ok_or_else(|| format!("the RECORD_LAYOUT {name} does not exist"))
+}
+
+fn convert_values(
+    module: &Module,
+    conversion: &str,
+    raw: &[f64],
+) -> Result<Vec<PhysValue>, String> {
+    raw.iter()
+        .map(|value| {
+            raw_to_phys(module, conversion, *value)
+                .ok_or_else(|| format!("the conversion {conversion} cannot be evaluated"))
+        })
+        .collect()
+}
+
+// convert values from row-major to column-major order or vice versa
+pub(crate) fn transpose<T: Clone>(values: &[T], rows: usize, columns: usize) -> Vec<T> {
+    let mut result = Vec::with_capacity(values.len());
+    for column in 0..columns {
+        for row in 0..rows {
+            if let Some(value) = values.get(row * columns + column) {
+                result.push(value.clone());
+            }
+        }
+    }
+    result
+}
+
+pub(crate) fn unit_of(module: &Module, conversion: &str) -> String {
+    module
+        .compu_method
+        .iter()
+        .find(|cm| cm.name == conversion)
+        .map(|cm| cm.unit.clone())
+        .unwrap_or_default()
+}
+
+fn no_data_msg(address: u32) -> String {
+    format!("the memory image contains no data at 0x{address:08X}")
+}
+
+fn format_list(values: &[PhysValue]) -> String {
+    values
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl Display for CalibrationValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let unit = if self.unit.is_empty() {
+            String::new()
+        } else {
+            format!(" [{}]", self.unit)
+        };
+        if self.dims.is_empty() {
+            return write!(f, "{} = {}{unit}", self.name, format_list(&self.values));
+        }
+        let dims: Vec<String> = self.dims.iter().map(ToString::to_string).collect();
+        write!(
+            f,
+            "{} {} ({}){unit}",
+            self.blocktype,
+            self.name,
+            dims.join("x")
+        )?;
+        for (axis_name, axis) in ["x", "y", "z", "4", "5"].iter().zip(&self.axes) {
+            write!(f, "\n  {axis_name} axis: {}", format_list(axis))?;
+        }
+        if self.dims.len() == 2 && self.dims[1] > 0 {
+            for (x, row) in self.values.chunks(self.dims[1]).enumerate() {
+                write!(f, "\n  values[{x}]: {}", format_list(row))?;
+            }
+            Ok(())
+        } else {
+            write!(f, "\n  values: {}", format_list(&self.values))
+        }
+    }
+}
+
+/// Convert a list of calibration values to JSON
+#[must_use]
+pub fn values_to_json(values: &[CalibrationValue]) -> String {
+    let to_json = |values: &[PhysValue]| -> Vec<serde_json::Value> {
+        values
+            .iter()
+            .map(|value| match value {
+                PhysValue::Number(number) => serde_json::json!(number),
+                PhysValue::Text(text) => serde_json::json!(text),
+            })
+            .collect()
+    };
+    let list: Vec<serde_json::Value> = values
+        .iter()
+        .map(|value| {
+            let axes: Vec<_> = value.axes.iter().map(|axis| to_json(axis)).collect();
+            serde_json::json!({
+                "name": value.name,
+                "type": value.blocktype,
+                "unit": value.unit,
+                "dims": value.dims,
+                "axes": axes,
+                "values": to_json(&value.values),
+            })
+        })
+        .collect();
+    serde_json::to_string_pretty(&list).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_read_values() {
+        let a2l_text = r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin CHARACTERISTIC value "" VALUE 0x1000 RL_UWORD 0 cm_lin 0 1000
+                /end CHARACTERISTIC
+                /begin CHARACTERISTIC curve "" CURVE 0x1002 RL_CURVE 0 NO_COMPU_METHOD 0 255
+                    /begin AXIS_DESCR STD_AXIS NO_INPUT_QUANTITY NO_COMPU_METHOD 4 0 255
+                    /end AXIS_DESCR
+                /end CHARACTERISTIC
+                /begin CHARACTERISTIC text "" ASCII 0x1010 RL_UWORD 0 NO_COMPU_METHOD 0 255
+                    NUMBER 4
+                /end CHARACTERISTIC
+                /begin CHARACTERISTIC big "" VALUE 0x1000 RL_UWORD 0 NO_COMPU_METHOD 0 65535
+                    BYTE_ORDER MSB_FIRST
+                /end CHARACTERISTIC
+                /begin COMPU_METHOD cm_lin "" LINEAR "%6.2" "rpm"
+                    COEFFS_LINEAR 2 0
+                /end COMPU_METHOD
+                /begin RECORD_LAYOUT RL_UWORD
+                    FNC_VALUES 1 UWORD COLUMN_DIR DIRECT
+                /end RECORD_LAYOUT
+                /begin RECORD_LAYOUT RL_CURVE
+                    NO_AXIS_PTS_X 1 UBYTE
+                    AXIS_PTS_X 2 UBYTE INDEX_INCR DIRECT
+                    FNC_VALUES 3 UBYTE COLUMN_DIR DIRECT
+                /end RECORD_LAYOUT
+            /end MODULE
+        /end PROJECT"#;
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(a2l_text, None, &mut log_msgs, false).unwrap();
+        // 0x1000: 0x0102; 0x1002: curve with 2 axis points (10, 20) and values (5, 6); 0x1010: "ab"
+        let image =
+            HexImage::parse(":0A1000000201020A140506000000B8\n:041010006162000019\n:00000001FF\n")
+                .unwrap();
+
+        let mut log_msgs = Vec::new();
+        let values = read_calibration_values(&a2l_file, &image, &mut log_msgs);
+        assert!(log_msgs.is_empty());
+        assert_eq!(values[0].values, vec![PhysValue::Number(516.0)]);
+        assert_eq!(values[0].to_string(), "value = 516 [rpm]");
+        assert_eq!(values[1].dims, vec![2]);
+        assert_eq!(
+            values[1].axes[0],
+            vec![PhysValue::Number(10.0), PhysValue::Number(20.0)]
+        );
+        assert_eq!(
+            values[1].values,
+            vec![PhysValue::Number(5.0), PhysValue::Number(6.0)]
+        );
+        assert_eq!(values[2].values, vec![PhysValue::Text("ab".to_string())]);
+        assert_eq!(values[3].values, vec![PhysValue::Number(513.0)]);
+        assert!(values_to_json(&values).contains("\"name\": \"curve\""));
+
+        // write new values, including a different number of axis points
+        let dcm_text = r#"
+FESTWERT value
+  WERT 600
+END
+KENNLINIE curve 3
+  ST/X 1 2 3
+  WERT 7 8 9
+END
+TEXTSTRING text
+  TEXT "xyz"
+END
+FESTWERT unknown
+  WERT 1
+END
+"#;
+        let new_values = crate::dcm::parse_dcm(dcm_text).unwrap();
+        let mut image = image;
+        let count = write_calibration_values(&a2l_file, &mut image, &new_values, &mut log_msgs);
+        assert_eq!(count, 3);
+        assert_eq!(log_msgs.len(), 1);
+        let values = read_calibration_values(&a2l_file, &image, &mut log_msgs);
+        assert_eq!(values[0].values, vec![PhysValue::Number(600.0)]);
+        assert_eq!(values[1].axes[0].len(), 3);
+        assert_eq!(values[1].values[2], PhysValue::Number(9.0));
+        assert_eq!(values[2].values, vec![PhysValue::Text("xyz".to_string())]);
+    }
+}
@@ -0,0 +1,245 @@
+//! Display and edit the CCP settings in the IF_DATA ASAP1B_CCP
+
+use std::ffi::OsStr;
+
+use crate::ifdata::{A2mlVector, Asap1bCcp, Raster, SeedKey, TpBlob, A2MLVECTOR_TEXT};
+use a2lfile::{A2lFile, A2lObject, A2ml, IfData, Module};
+
+/// CCP settings that can be changed from the command line
+#[derive(Debug, Default)]
+pub struct CcpSettings {
+    pub can_id_send: Option<u32>,
+    pub can_id_receive: Option<u32>,
+    pub station_address: Option<u16>,
+}
+
+// default values used when a new TP_BLOB is created: CCP 2.1, Intel byte order
+const CCP_VERSION: u16 = 0x0201;
+const CCP_BLOB_VERSION: u16 = 0x0100;
+const CCP_BYTE_ORDER_INTEL: u16 = 2;
+
+/// print the CCP settings of all modules
+pub fn show_settings(a2l_file: &A2lFile, filename: &OsStr) {
+    let multi_module = a2l_file.project.module.len() > 1;
+
+    println!("CCP settings in {}:", filename.to_string_lossy());
+
+    for module in &a2l_file.project.module {
+        if multi_module {
+            println!("CCP settings for module {}", module.name);
+        }
+
+        let mut found = false;
+        for ifdata in &module.if_data {
+            if let Some(decoded_ifdata) = A2mlVector::load_from_ifdata(ifdata) {
+                if let Some(asap1b_ccp) = &decoded_ifdata.asap1b_ccp {
+                    if is_module_ccp(asap1b_ccp) {
+                        print_ccp(asap1b_ccp);
+                        found = true;
+                    }
+                }
+            }
+        }
+
+        if !found {
+            println!("No CCP settings found in module {}", module.name);
+        }
+    }
+    println!();
+}
+
+// the IF_DATA ASAP1B_CCP is also used in calibration objects, where it only contains a DP_BLOB
+fn is_module_ccp(asap1b_ccp: &Asap1bCcp) -> bool {
+    asap1b_ccp.tp_blob.is_some() || asap1b_ccp.seed_key.is_some() || !asap1b_ccp.raster.is_empty()
+}
+
+fn print_ccp(asap1b_ccp: &Asap1bCcp) {
+    if let Some(tp_blob) = &asap1b_ccp.tp_blob {
+        print_tp_blob(tp_blob);
+    }
+
+    if let Some(seed_key) = &asap1b_ccp.seed_key {
+        println!("  Seed & Key:");
+        println!("    CAL: {}", seed_key.cal_dll);
+        println!("    DAQ: {}", seed_key.daq_dll);
+        println!("    PGM: {}", seed_key.pgm_dll);
+    }
+
+    for raster in &asap1b_ccp.raster {
+        print_raster(raster);
+    }
+}
+
+fn print_tp_blob(tp_blob: &TpBlob) {
+    println!(
+        "  CCP version: {}.{}",
+        tp_blob.ccp_version >> 8,
+        tp_blob.ccp_version & 0xff
+    );
+    println!(
+        "    CAN id send (CRO): {}",
+        format_can_id(tp_blob.can_id_send)
+    );
+    println!(
+        "    CAN id receive (DTO): {}",
+        format_can_id(tp_blob.can_id_receive)
+    );
+    println!("    station address: 0x{:X}", tp_blob.station_address);
+    match tp_blob.byte_order {
+        1 => println!("    byte order: Motorola (MSB first)"),
+        2 => println!("    byte order: Intel (MSB last)"),
+        other => println!("    byte order: invalid ({other})"),
+    }
+    if let Some(baudrate) = &tp_blob.baudrate {
+        println!("    CAN baudrate: {} kbps", baudrate.baudrate / 1000);
+    }
+    if let Some(sample_point) = &tp_blob.sample_point {
+        println!("    CAN sample point: {}%", sample_point.sample_point);
+    }
+}
+
+// bit 31 of a CAN id in the TP_BLOB marks an extended identifier
+fn format_can_id(can_id: u32) -> String {
+    if can_id & 0x8000_0000 != 0 {
+        format!("0x{:X} (extended)", can_id & 0x1fff_ffff)
+    } else {
+        format!("0x{can_id:X} (standard)")
+    }
+}
+
+fn print_raster(raster: &Raster) {
+    let unit = match raster.cycle_time_unit {
+        0 => "us",
+        1 => "* 10us",
+        2 => "* 100us",
+        3 => "ms",
+        4 => "* 10ms",
+        5 => "* 100ms",
+        6 => "s",
+        _ => "(unknown unit)",
+    };
+    println!(
+        "  Raster {} ({}): event channel {}, cycle time {} {unit}",
+        raster.raster_name, raster.raster_short_name, raster.event_channel, raster.cycle_time
+    );
+}
+
+/// set the CCP parameters in all modules
+/// the ASAP1B_CCP IF_DATA and the TP_BLOB inside it are created if they don't exist yet
+pub fn edit_ccp(a2l_file: &mut A2lFile, settings: &CcpSettings) {
+    for module in &mut a2l_file.project.module {
+        edit_module_ccp(module, |asap1b_ccp| {
+            let tp_blob = asap1b_ccp.tp_blob.get_or_insert_with(|| {
+                let mut tp_blob =
+                    TpBlob::new(CCP_VERSION, CCP_BLOB_VERSION, 0, 0, 0, CCP_BYTE_ORDER_INTEL);
+                // CAN ids and the station address are conventionally written as hex numbers
+                let layout = &mut tp_blob.get_layout_mut().item_location;
+                layout.2 .1 = true;
+                layout.3 .1 = true;
+                layout.4 .1 = true;
+                tp_blob
+            });
+            if let Some(can_id_send) = settings.can_id_send {
+                tp_blob.can_id_send = can_id_send;
+            }
+            if let Some(can_id_receive) = settings.can_id_receive {
+                tp_blob.can_id_receive = can_id_receive;
+            }
+            if let Some(station_address) = settings.station_address {
+                tp_blob.station_address = station_address;
+            }
+        });
+    }
+}
+
+/// set (Some) or clear (None) the seed & key DLL in all modules that contain CCP settings
+/// the same DLL is used for all privilege levels (CAL, DAQ, PGM)
+pub fn edit_seed_key(a2l_file: &mut A2lFile, seed_key_dll: Option<&str>) {
+    for module in &mut a2l_file.project.module {
+        let has_ccp = module.if_data.iter().any(|ifdata| {
+            A2mlVector::load_from_ifdata(ifdata)
+                .and_then(|decoded_ifdata| decoded_ifdata.asap1b_ccp)
+                .is_some_and(|asap1b_ccp| is_module_ccp(&asap1b_ccp))
+        });
+        if has_ccp {
+            edit_module_ccp(module, |asap1b_ccp| {
+                asap1b_ccp.seed_key = seed_key_dll
+                    .map(|dll| SeedKey::new(dll.to_string(), dll.to_string(), dll.to_string()));
+            });
+        }
+    }
+}
+
+/// decode the module level ASAP1B_CCP IF_DATA, modify it and store the result
+/// if the module doesn't have one yet, then a new one is created
+pub fn edit_module_ccp<F>(module: &mut Module, edit_fn: F)
+where
+    F: FnOnce(&mut Asap1bCcp),
+{
+    let existing = module.if_data.iter().enumerate().find_map(|(idx, ifdata)| {
+        A2mlVector::load_from_ifdata(ifdata)
+            .filter(|decoded_ifdata| decoded_ifdata.asap1b_ccp.is_some())
+            .map(|decoded_ifdata| (idx, decoded_ifdata))
+    });
+    let (idx, mut decoded_ifdata) = existing.unwrap_or_else(|| {
+        let mut decoded_ifdata = A2mlVector::new();
+        decoded_ifdata.asap1b_ccp = Some(Asap1bCcp::new());
+        module.if_data.push(IfData::new());
+        (module.if_data.len() - 1, decoded_ifdata)
+    });
+
+    if let Some(asap1b_ccp) = &mut decoded_ifdata.asap1b_ccp {
+        edit_fn(asap1b_ccp);
+    }
+    decoded_ifdata.store_to_ifdata(&mut module.if_data[idx]);
+
+    // other tools can only interpret the new IF_DATA if the file contains an A2ML specification
+    if module.a2ml.is_none() {
+        module.a2ml = Some(A2ml::new(A2MLVECTOR_TEXT.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_edit_ccp() {
+        let mut a2l_file = a2lfile::new();
+        let settings = CcpSettings {
+            can_id_send: Some(0x600),
+            can_id_receive: Some(0x601),
+            station_address: None,
+        };
+        edit_ccp(&mut a2l_file, &settings);
+        let settings = CcpSettings {
+            station_address: Some(0x39),
+            ..Default::default()
+        };
+        edit_ccp(&mut a2l_file, &settings);
+        assert_eq!(a2l_file.project.module[0].if_data.len(), 1);
+
+        let text = a2l_file.write_to_string();
+        assert!(text.contains("0x600"));
+        let mut log_msgs = Vec::new();
+        let reloaded = a2lfile::load_from_string(&text, None, &mut log_msgs, true).unwrap();
+        let decoded = A2mlVector::load_from_ifdata(&reloaded.project.module[0].if_data[0]).unwrap();
+        let asap1b_ccp = decoded.asap1b_ccp.unwrap();
+        assert!(is_module_ccp(&asap1b_ccp));
+        let tp_blob = asap1b_ccp.tp_blob.unwrap();
+        assert_eq!(tp_blob.can_id_send, 0x600);
+        assert_eq!(tp_blob.can_id_receive, 0x601);
+        assert_eq!(tp_blob.station_address, 0x39);
+        assert_eq!(tp_blob.byte_order, CCP_BYTE_ORDER_INTEL);
+        assert_eq!(format_can_id(tp_blob.can_id_send), "0x600 (standard)");
+        assert_eq!(format_can_id(0x8000_0601), "0x601 (extended)");
+
+        edit_seed_key(&mut a2l_file, Some("ccp_seed.dll"));
+        let decoded = A2mlVector::load_from_ifdata(&a2l_file.project.module[0].if_data[0]).unwrap();
+        let seed_key = decoded.asap1b_ccp.unwrap().seed_key.unwrap();
+        assert_eq!(seed_key.cal_dll, "ccp_seed.dll");
+        edit_seed_key(&mut a2l_file, None);
+        let decoded = A2mlVector::load_from_ifdata(&a2l_file.project.module[0].if_data[0]).unwrap();
+        assert!(decoded.asap1b_ccp.unwrap().seed_key.is_none());
+    }
+}
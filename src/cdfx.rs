@@ -0,0 +1,152 @@
+//! Reading calibration data files in CDFx (ASAM CDF 2.0) format
+
+use crate::calvalues::CalibrationValue;
+use crate::compu::PhysValue;
+use regex::Regex;
+
+/// Parse the text of a CDFx file.
+///
+/// Only the physical values are read; the content of the file is not validated against the CDF schema.
+///
+/// # Errors
+///
+/// Returns an error message if the text is not a CDFx file or an instance has no name
+pub fn parse_cdfx(text: &str) -> Result<Vec<CalibrationValue>, String> {
+    let instance_regex = Regex::new(r"(?s)<SW-INSTANCE>(.*?)</SW-INSTANCE>").unwrap();
+    let axis_regex = Regex::new(r"(?s)<SW-AXIS-CONT>(.*?)</SW-AXIS-CONT>").unwrap();
+    let value_cont_regex = Regex::new(r"(?s)<SW-VALUE-CONT>(.*?)</SW-VALUE-CONT>").unwrap();
+
+    if !text.contains("<MSRSW") {
+        return Err("Error: the file is not a CDFx file".to_string());
+    }
+    let mut values = Vec::new();
+    for instance in instance_regex.captures_iter(text) {
+        let content = &instance[1];
+        // the axes are removed first, so that their elements are not mistaken for elements of the instance
+        let without_axes = axis_regex.replace_all(content, "");
+        let Some(name) = element_text(&without_axes, "SHORT-NAME") else {
+            return Err("Error: found a SW-INSTANCE without SHORT-NAME".to_string());
+        };
+        let category = element_text(&without_axes, "CATEGORY").unwrap_or_default();
+        let value_cont = value_cont_regex
+            .captures(&without_axes)
+            .map(|captures| captures[1].to_string())
+            .unwrap_or_default();
+        let unit = element_text(&value_cont, "UNIT-DISPLAY-NAME").unwrap_or_default();
+        let phys_values = values_phys(&value_cont);
+        let axes: Vec<Vec<PhysValue>> = axis_regex
+            .captures_iter(content)
+            .map(|axis| values_phys(&axis[1]))
+            .collect();
+
+        // the points of an AXIS_PTS object are its values
+        let is_axis = matches!(category.as_str(), "COM_AXIS" | "RES_AXIS" | "CURVE_AXIS");
+        let (blocktype, axes) = if is_axis {
+            ("AXIS_PTS", Vec::new())
+        } else {
+            ("CHARACTERISTIC", axes)
+        };
+        let dims = match category.as_str() {
+            "VALUE" | "BOOLEAN" | "ASCII" | "DEPENDENT_VALUE" => Vec::new(),
+            "VAL_BLK" | "COM_AXIS" | "RES_AXIS" | "CURVE_AXIS" => vec![phys_values.len()],
+            _ => axes.iter().map(Vec::len).collect(),
+        };
+        values.push(CalibrationValue {
+            name,
+            blocktype,
+            unit,
+            axes,
+            dims,
+            values: phys_values,
+        });
+    }
+    Ok(values)
+}
+
+// get the values from all V (numeric) and VT (text) elements inside of SW-VALUES-PHYS
+fn values_phys(text: &str) -> Vec<PhysValue> {
+    let phys_regex = Regex::new(r"(?s)<SW-VALUES-PHYS>(.*?)</SW-VALUES-PHYS>").unwrap();
+    let value_regex = Regex::new(r"(?s)<V>(.*?)</V>|<VT>(.*?)</VT>").unwrap();
+    let Some(phys) = phys_regex.captures(text) else {
+        return Vec::new();
+    };
+    value_regex
+        .captures_iter(&phys[1])
+        .map(|captures| {
+            if let Some(number) = captures.get(1) {
+                let number = number.as_str().trim();
+                number
+                    .parse::<f64>()
+                    .map_or_else(|_| PhysValue::Text(unescape(number)), PhysValue::Number)
+            } else {
+                PhysValue::Text(unescape(&captures[2]))
+            }
+        })
+        .collect()
+}
+
+// get the text of the first element with the given tag
+fn element_text(text: &str, tag: &str) -> Option<String> {
+    let start_tag = format!("<{tag}>");
+    let end_tag = format!("</{tag}>");
+    let start = text.find(&start_tag)? + start_tag.len();
+    let len = text[start..].find(&end_tag)?;
+    Some(unescape(text[start..start + len].trim()))
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_cdfx() {
+        let text = r#"<?xml version="1.0" encoding="UTF-8"?>
+<MSRSW>
+  <SW-SYSTEMS><SW-SYSTEM><SW-INSTANCE-SPEC><SW-INSTANCE-TREE><SW-INSTANCE-TREE-ORIGIN/>
+    <SW-INSTANCE>
+      <SHORT-NAME>value</SHORT-NAME>
+      <CATEGORY>VALUE</CATEGORY>
+      <SW-VALUE-CONT>
+        <UNIT-DISPLAY-NAME>rpm</UNIT-DISPLAY-NAME>
+        <SW-VALUES-PHYS><V>2.5</V></SW-VALUES-PHYS>
+      </SW-VALUE-CONT>
+    </SW-INSTANCE>
+    <SW-INSTANCE>
+      <SHORT-NAME>curve</SHORT-NAME>
+      <CATEGORY>CURVE</CATEGORY>
+      <SW-VALUE-CONT>
+        <SW-VALUES-PHYS><VT>on</VT><VT>off &amp; out</VT></SW-VALUES-PHYS>
+      </SW-VALUE-CONT>
+      <SW-AXIS-CONTS>
+        <SW-AXIS-CONT>
+          <CATEGORY>STD_AXIS</CATEGORY>
+          <SW-VALUES-PHYS><V>1</V><V>2</V></SW-VALUES-PHYS>
+        </SW-AXIS-CONT>
+      </SW-AXIS-CONTS>
+    </SW-INSTANCE>
+  </SW-INSTANCE-TREE></SW-INSTANCE-SPEC></SW-SYSTEM></SW-SYSTEMS>
+</MSRSW>"#;
+        let values = parse_cdfx(text).unwrap();
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[0].name, "value");
+        assert_eq!(values[0].unit, "rpm");
+        assert_eq!(values[0].values, vec![PhysValue::Number(2.5)]);
+        assert_eq!(values[1].dims, vec![2]);
+        assert_eq!(
+            values[1].axes[0],
+            vec![PhysValue::Number(1.0), PhysValue::Number(2.0)]
+        );
+        assert_eq!(
+            values[1].values[1],
+            PhysValue::Text("off & out".to_string())
+        );
+    }
+}
@@ -0,0 +1,1123 @@
+//! User configurable consistency checks
+
+use crate::formula::{parse_formula, Expression};
+use crate::memmap::datatype_size;
+use a2lfile::{
+    A2lFile, A2lObject, A2lObjectName, ByteOrder, ByteOrderEnum, CompuMethod, CompuTab,
+    CompuVtabRange, ConversionType, DataType, Group, Module, RecordLayout, ValueTriplesStruct,
+};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+
+// the block types for which naming rules can be configured
+const NAMED_BLOCK_TYPES: [&str; 20] = [
+    "AXIS_PTS",
+    "BLOB",
+    "CHARACTERISTIC",
+    "COMPU_METHOD",
+    "COMPU_TAB",
+    "COMPU_VTAB",
+    "COMPU_VTAB_RANGE",
+    "FUNCTION",
+    "GROUP",
+    "INSTANCE",
+    "MEASUREMENT",
+    "RECORD_LAYOUT",
+    "TRANSFORMER",
+    "TYPEDEF_AXIS",
+    "TYPEDEF_BLOB",
+    "TYPEDEF_CHARACTERISTIC",
+    "TYPEDEF_MEASUREMENT",
+    "TYPEDEF_STRUCTURE",
+    "UNIT",
+    "FRAME",
+];
+
+/// user configurable rules, which are applied in addition to the standard consistency check
+#[derive(Debug, Default)]
+pub struct CheckRules {
+    naming: Vec<(String, Regex)>,
+    aliases: Vec<Regex>,
+}
+
+impl CheckRules {
+    /// load the rules from a rules file.
+    /// The file contains one rule per line, lines starting with '#' are comments.
+    /// Supported rules:
+    /// - `NAMING <BLOCK_TYPE> <REGEX>`: the names of all objects of BLOCK_TYPE must match REGEX
+    /// - `ALIAS <REGEX>`: objects matching REGEX may intentionally share their address with other objects
+    pub fn load(filename: &OsStr) -> Result<Self, String> {
+        let text = std::fs::read_to_string(filename).map_err(|err| {
+            format!(
+                "Error: could not read rules file {}: {err}",
+                filename.to_string_lossy()
+            )
+        })?;
+        Self::parse(&text).map_err(|err| {
+            format!(
+                "Error: invalid rules file {}: {err}",
+                filename.to_string_lossy()
+            )
+        })
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut rules = CheckRules::default();
+
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (keyword, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            match keyword {
+                "NAMING" => {
+                    let args = args.trim_start();
+                    let Some((blocktype, regex_text)) = args.split_once(char::is_whitespace) else {
+                        return Err(format!(
+                            "line {}: expected NAMING <BLOCK_TYPE> <REGEX>",
+                            idx + 1
+                        ));
+                    };
+                    if !NAMED_BLOCK_TYPES.contains(&blocktype) {
+                        return Err(format!(
+                            "line {}: naming rules cannot be applied to {blocktype}",
+                            idx + 1
+                        ));
+                    }
+                    let regex = Regex::new(regex_text.trim())
+                        .map_err(|err| format!("line {}: {err}", idx + 1))?;
+                    rules.naming.push((blocktype.to_string(), regex));
+                }
+                "ALIAS" => {
+                    let regex_text = args.trim();
+                    if regex_text.is_empty() {
+                        return Err(format!("line {}: expected ALIAS <REGEX>", idx + 1));
+                    }
+                    let regex =
+                        Regex::new(regex_text).map_err(|err| format!("line {}: {err}", idx + 1))?;
+                    rules.aliases.push(regex);
+                }
+                _ => return Err(format!("line {}: unknown rule {keyword}", idx + 1)),
+            }
+        }
+
+        Ok(rules)
+    }
+}
+
+/// run the consistency check of a2lfile followed by all checks of this module that only need the a2l file:
+/// the user configured rules, duplicate addresses, formulas, conversion tables and the GROUP tree
+pub fn check_all(
+    a2l_file: &A2lFile,
+    rules: &CheckRules,
+    verify_formula_inverse: bool,
+    log_msgs: &mut Vec<String>,
+) {
+    a2l_file.check(log_msgs);
+    check_rules(a2l_file, rules, log_msgs);
+    check_duplicate_addresses(a2l_file, rules, log_msgs);
+    check_formulas(a2l_file, verify_formula_inverse, log_msgs);
+    check_conversion_tables(a2l_file, log_msgs);
+    check_groups(a2l_file, log_msgs);
+}
+
+/// check the a2l file using the user configured rules
+pub fn check_rules(a2l_file: &A2lFile, rules: &CheckRules, log_msgs: &mut Vec<String>) {
+    for module in &a2l_file.project.module {
+        check_naming(module, &rules.naming, log_msgs);
+    }
+}
+
+// every object name must match all naming rules configured for its block type
+fn check_naming(module: &Module, naming: &[(String, Regex)], log_msgs: &mut Vec<String>) {
+    for (blocktype, regex) in naming {
+        let names: Vec<(&str, u32)> = match blocktype.as_str() {
+            "AXIS_PTS" => get_names(&module.axis_pts),
+            "BLOB" => get_names(&module.blob),
+            "CHARACTERISTIC" => get_names(&module.characteristic),
+            "COMPU_METHOD" => get_names(&module.compu_method),
+            "COMPU_TAB" => get_names(&module.compu_tab),
+            "COMPU_VTAB" => get_names(&module.compu_vtab),
+            "COMPU_VTAB_RANGE" => get_names(&module.compu_vtab_range),
+            "FRAME" => get_names(&module.frame),
+            "FUNCTION" => get_names(&module.function),
+            "GROUP" => get_names(&module.group),
+            "INSTANCE" => get_names(&module.instance),
+            "MEASUREMENT" => get_names(&module.measurement),
+            "RECORD_LAYOUT" => get_names(&module.record_layout),
+            "TRANSFORMER" => get_names(&module.transformer),
+            "TYPEDEF_AXIS" => get_names(&module.typedef_axis),
+            "TYPEDEF_BLOB" => get_names(&module.typedef_blob),
+            "TYPEDEF_CHARACTERISTIC" => get_names(&module.typedef_characteristic),
+            "TYPEDEF_MEASUREMENT" => get_names(&module.typedef_measurement),
+            "TYPEDEF_STRUCTURE" => get_names(&module.typedef_structure),
+            "UNIT" => get_names(&module.unit),
+            _ => Vec::new(),
+        };
+
+        for (name, line) in names {
+            if !regex.is_match(name) {
+                log_msgs.push(format!(
+                    "In {blocktype} {name} on line {line}: The name does not match the naming rule \"{regex}\""
+                ));
+            }
+        }
+    }
+}
+
+// block type, name and line of an object that has an address
+type AddressUser<'a> = (&'a str, &'a str, u32);
+
+/// check that no two MEASUREMENTs, CHARACTERISTICs or AXIS_PTS have the same address
+///
+/// Objects with different BIT_MASKs or address extensions may share an address, and so may objects that match one of
+/// the ALIAS rules. Objects with the address 0 have not been located and are ignored.
+pub fn check_duplicate_addresses(
+    a2l_file: &A2lFile,
+    rules: &CheckRules,
+    log_msgs: &mut Vec<String>,
+) {
+    for module in &a2l_file.project.module {
+        let mut objects: HashMap<(u32, i16, u32), Vec<AddressUser>> = HashMap::new();
+        for measurement in &module.measurement {
+            if let Some(ecu_address) = &measurement.ecu_address {
+                let key = (
+                    ecu_address.address,
+                    measurement
+                        .ecu_address_extension
+                        .as_ref()
+                        .map_or(0, |ext| ext.extension),
+                    measurement.bit_mask.as_ref().map_or(0, |bm| bm.mask),
+                );
+                objects.entry(key).or_default().push((
+                    "MEASUREMENT",
+                    &measurement.name,
+                    measurement.get_line(),
+                ));
+            }
+        }
+        for characteristic in &module.characteristic {
+            let key = (
+                characteristic.address,
+                characteristic
+                    .ecu_address_extension
+                    .as_ref()
+                    .map_or(0, |ext| ext.extension),
+                characteristic.bit_mask.as_ref().map_or(0, |bm| bm.mask),
+            );
+            objects.entry(key).or_default().push((
+                "CHARACTERISTIC",
+                &characteristic.name,
+                characteristic.get_line(),
+            ));
+        }
+        for axis_pts in &module.axis_pts {
+            let key = (
+                axis_pts.address,
+                axis_pts
+                    .ecu_address_extension
+                    .as_ref()
+                    .map_or(0, |ext| ext.extension),
+                0,
+            );
+            objects
+                .entry(key)
+                .or_default()
+                .push(("AXIS_PTS", &axis_pts.name, axis_pts.get_line()));
+        }
+
+        let mut duplicates: Vec<(u32, Vec<AddressUser>)> = objects
+            .into_iter()
+            .filter(|((address, _, _), _)| *address != 0)
+            .map(|((address, _, _), items)| {
+                let mut items: Vec<_> = items
+                    .into_iter()
+                    .filter(|(_, name, _)| !rules.aliases.iter().any(|regex| regex.is_match(name)))
+                    .collect();
+                items.sort_by_key(|(_, _, line)| *line);
+                (address, items)
+            })
+            .filter(|(_, items)| items.len() > 1)
+            .collect();
+        // report in the order of the file
+        duplicates.sort_by_key(|(_, items)| items[0].2);
+        for (address, items) in duplicates {
+            let (blocktype, name, line) = items[0];
+            let others: Vec<String> = items[1..]
+                .iter()
+                .map(|(blocktype, name, _)| format!("{blocktype} {name}"))
+                .collect();
+            log_msgs.push(format!(
+                "In {blocktype} {name} on line {line}: The address 0x{address:X} is also used by {}",
+                others.join(", ")
+            ));
+        }
+    }
+}
+
+/// check the syntax of every FORMULA and FORMULA_INV, and the references to system constants
+///
+/// A FORMULA_INV is required if the COMPU_METHOD is used by a CHARACTERISTIC or AXIS_PTS, because the values can't be calibrated otherwise.
+/// If `verify_inverse` is set, FORMULA_INV is evaluated for sample values between the limits of each object that uses
+/// the COMPU_METHOD, and FORMULA must convert the result back to the original value.
+pub fn check_formulas(a2l_file: &A2lFile, verify_inverse: bool, log_msgs: &mut Vec<String>) {
+    for module in &a2l_file.project.module {
+        let system_constants: Vec<&str> = module
+            .mod_par
+            .iter()
+            .flat_map(|mod_par| &mod_par.system_constant)
+            .map(|sc| sc.name.as_str())
+            .collect();
+        for compu_method in &module.compu_method {
+            if compu_method.conversion_type != ConversionType::Form {
+                continue;
+            }
+            let name = &compu_method.name;
+            let line = compu_method.get_line();
+            let Some(formula) = &compu_method.formula else {
+                log_msgs.push(format!(
+                    "In COMPU_METHOD {name} on line {line}: The conversion type is FORM, but there is no FORMULA"
+                ));
+                continue;
+            };
+            let parse = |text: &str, keyword: &str, log_msgs: &mut Vec<String>| {
+                let expression = parse_formula(text)
+                    .map_err(|err| {
+                        log_msgs.push(format!(
+                            "In COMPU_METHOD {name} on line {line}: The {keyword} \"{text}\" is invalid: {err}"
+                        ));
+                    })
+                    .ok()?;
+                for sysc in expression.system_constants() {
+                    if !system_constants.contains(&sysc) {
+                        log_msgs.push(format!(
+                            "In COMPU_METHOD {name} on line {line}: The {keyword} references the unknown SYSTEM_CONSTANT \"{sysc}\""
+                        ));
+                    }
+                }
+                Some(expression)
+            };
+            let fx = parse(&formula.fx, "FORMULA", log_msgs);
+            let gx = if let Some(formula_inv) = &formula.formula_inv {
+                parse(&formula_inv.gx, "FORMULA_INV", log_msgs)
+            } else {
+                let first_calibration_object = module
+                    .characteristic
+                    .iter()
+                    .filter(|item| &item.conversion == name)
+                    .map(|item| &item.name)
+                    .chain(
+                        module
+                            .axis_pts
+                            .iter()
+                            .filter(|item| &item.conversion == name)
+                            .map(|item| &item.name),
+                    )
+                    .next();
+                if let Some(object_name) = first_calibration_object {
+                    log_msgs.push(format!(
+                        "In COMPU_METHOD {name} on line {line}: There is no FORMULA_INV, but {object_name} can't be calibrated without it"
+                    ));
+                }
+                None
+            };
+            if let (true, Some(fx), Some(gx)) = (verify_inverse, fx, gx) {
+                verify_formula_inverse(module, compu_method, &fx, &gx, log_msgs);
+            }
+        }
+    }
+}
+
+// FORMULA(FORMULA_INV(x)) must be x for all physical values within the limits of each object
+fn verify_formula_inverse(
+    module: &Module,
+    compu_method: &CompuMethod,
+    fx: &Expression,
+    gx: &Expression,
+    log_msgs: &mut Vec<String>,
+) {
+    const SAMPLES: usize = 10;
+    // formulas of virtual measurements have several inputs and can't be inverted
+    if fx.input_count() > 1 {
+        return;
+    }
+    let conversion = &compu_method.name;
+    for (object_name, lower, upper) in objects_using_conversion(module, conversion) {
+        for step in 0..=SAMPLES {
+            let phys = lower + (upper - lower) * step as f64 / SAMPLES as f64;
+            // values where one of the formulas is undefined, e.g. because it uses a system constant, can't be verified
+            let Some(raw) = gx.evaluate(&[phys]) else {
+                continue;
+            };
+            let Some(result) = fx.evaluate(&[raw]) else {
+                continue;
+            };
+            if (result - phys).abs() > 1e-6 * phys.abs().max(1.0) {
+                log_msgs.push(format!(
+                    "In COMPU_METHOD {conversion} on line {}: FORMULA_INV is not the inverse of FORMULA in the range of {object_name}: the value {phys} is converted to {raw} and back to {result}",
+                    compu_method.get_line()
+                ));
+                break;
+            }
+        }
+    }
+}
+
+/// check that the value pairs of each COMPU_TAB are ordered, that the ranges of each COMPU_VTAB_RANGE do not
+/// overlap, and that the limits of the objects using a table are covered by it
+pub fn check_conversion_tables(a2l_file: &A2lFile, log_msgs: &mut Vec<String>) {
+    for module in &a2l_file.project.module {
+        for compu_tab in &module.compu_tab {
+            check_compu_tab_order(compu_tab, log_msgs);
+        }
+        for vtab_range in &module.compu_vtab_range {
+            check_vtab_range_overlap(vtab_range, log_msgs);
+        }
+
+        for compu_method in &module.compu_method {
+            let Some(tab_ref) = &compu_method.compu_tab_ref else {
+                continue;
+            };
+            let table_name = &tab_ref.conversion_table;
+            let objects = objects_using_conversion(module, &compu_method.name);
+            match compu_method.conversion_type {
+                ConversionType::TabIntp | ConversionType::TabNointp => {
+                    let Some(compu_tab) =
+                        module.compu_tab.iter().find(|tab| &tab.name == table_name)
+                    else {
+                        continue;
+                    };
+                    // values outside of the table are acceptable if there is a default value
+                    if compu_tab.tab_entry.is_empty() || compu_tab.default_value_numeric.is_some() {
+                        continue;
+                    }
+                    let (min, max) = compu_tab
+                        .tab_entry
+                        .iter()
+                        .fold((f64::MAX, f64::MIN), |(min, max), entry| {
+                            (min.min(entry.out_val), max.max(entry.out_val))
+                        });
+                    for (object_name, lower, upper) in objects {
+                        if lower < min || upper > max {
+                            log_msgs.push(format!(
+                                "In COMPU_TAB {table_name} on line {}: The limits {lower} .. {upper} of {object_name} are not covered by the table values {min} .. {max}",
+                                compu_tab.get_line()
+                            ));
+                        }
+                    }
+                }
+                ConversionType::TabVerb => {
+                    let Some(vtab_range) = module
+                        .compu_vtab_range
+                        .iter()
+                        .find(|tab| &tab.name == table_name)
+                    else {
+                        continue;
+                    };
+                    if vtab_range.value_triples.is_empty() || vtab_range.default_value.is_some() {
+                        continue;
+                    }
+                    for (object_name, lower, upper) in objects {
+                        if let Some(gap) = find_range_gap(&vtab_range.value_triples, lower, upper) {
+                            log_msgs.push(format!(
+                                "In COMPU_VTAB_RANGE {table_name} on line {}: The value {gap} within the limits {lower} .. {upper} of {object_name} is not covered by any range",
+                                vtab_range.get_line()
+                            ));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+// the input values must be strictly increasing, and the output values of an interpolated table must be
+// monotonic, otherwise the table can't be inverted
+fn check_compu_tab_order(compu_tab: &CompuTab, log_msgs: &mut Vec<String>) {
+    let name = &compu_tab.name;
+    let line = compu_tab.get_line();
+    if let Some(pair) = compu_tab
+        .tab_entry
+        .windows(2)
+        .find(|pair| pair[0].in_val >= pair[1].in_val)
+    {
+        log_msgs.push(format!(
+            "In COMPU_TAB {name} on line {line}: The input values are not strictly increasing: {} is followed by {}",
+            pair[0].in_val, pair[1].in_val
+        ));
+    }
+    if compu_tab.conversion_type == ConversionType::TabIntp {
+        let increasing = compu_tab
+            .tab_entry
+            .windows(2)
+            .all(|pair| pair[0].out_val <= pair[1].out_val);
+        let decreasing = compu_tab
+            .tab_entry
+            .windows(2)
+            .all(|pair| pair[0].out_val >= pair[1].out_val);
+        if !increasing && !decreasing {
+            log_msgs.push(format!(
+                "In COMPU_TAB {name} on line {line}: The output values of the interpolation table are not monotonic"
+            ));
+        }
+    }
+}
+
+fn check_vtab_range_overlap(vtab_range: &CompuVtabRange, log_msgs: &mut Vec<String>) {
+    let name = &vtab_range.name;
+    let line = vtab_range.get_line();
+    for triple in &vtab_range.value_triples {
+        if triple.in_val_min > triple.in_val_max {
+            log_msgs.push(format!(
+                "In COMPU_VTAB_RANGE {name} on line {line}: The range {} .. {} of \"{}\" is empty",
+                triple.in_val_min, triple.in_val_max, triple.out_val
+            ));
+        }
+    }
+    let mut ranges: Vec<&ValueTriplesStruct> = vtab_range.value_triples.iter().collect();
+    ranges.sort_by(|a, b| a.in_val_min.total_cmp(&b.in_val_min));
+    // ranges may share a boundary, because the upper limit is exclusive for floating point values
+    for pair in ranges.windows(2) {
+        if pair[1].in_val_min < pair[0].in_val_max {
+            log_msgs.push(format!(
+                "In COMPU_VTAB_RANGE {name} on line {line}: The ranges of \"{}\" and \"{}\" overlap",
+                pair[0].out_val, pair[1].out_val
+            ));
+        }
+    }
+}
+
+// find the first value in lower..upper that is not inside any range
+// the gap between ranges with integer limits like 0..9 and 10..19 is not counted
+fn find_range_gap(triples: &[ValueTriplesStruct], lower: f64, upper: f64) -> Option<f64> {
+    let mut ranges: Vec<(f64, f64)> = triples
+        .iter()
+        .map(|triple| (triple.in_val_min, triple.in_val_max))
+        .collect();
+    ranges.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut covered_to = lower;
+    let mut integer_step = 0.0;
+    for (min, max) in ranges {
+        if max < covered_to {
+            continue;
+        }
+        if min - covered_to > integer_step {
+            return Some(covered_to + integer_step);
+        }
+        covered_to = covered_to.max(max);
+        if covered_to >= upper {
+            return None;
+        }
+        integer_step = if covered_to.fract() == 0.0 { 1.0 } else { 0.0 };
+    }
+    Some(covered_to + integer_step)
+}
+
+/// check the structure of the GROUP tree
+///
+/// The SUB_GROUP references must not form a cycle, every GROUP can only have one parent, and a ROOT GROUP must not
+/// be a SUB_GROUP. If a module contains any GROUPs, all objects that are not referenced by a GROUP are reported as well.
+pub fn check_groups(a2l_file: &A2lFile, log_msgs: &mut Vec<String>) {
+    for module in &a2l_file.project.module {
+        let groups: HashMap<&str, &Group> = module
+            .group
+            .iter()
+            .map(|group| (group.name.as_str(), group))
+            .collect();
+        let mut parents: HashMap<&str, Vec<&str>> = HashMap::new();
+        for group in &module.group {
+            for sub_group in group.sub_group.iter().flat_map(|sg| &sg.identifier_list) {
+                parents.entry(sub_group).or_default().push(&group.name);
+            }
+        }
+
+        for group in &module.group {
+            let name = &group.name;
+            let line = group.get_line();
+            let group_parents = parents.get(name.as_str()).map_or(&[][..], Vec::as_slice);
+            if group.root.is_some() && !group_parents.is_empty() {
+                log_msgs.push(format!(
+                    "In GROUP {name} on line {line}: The GROUP is declared as ROOT, but it is also a SUB_GROUP of {}",
+                    group_parents.join(", ")
+                ));
+            }
+            if group_parents.len() > 1 {
+                log_msgs.push(format!(
+                    "In GROUP {name} on line {line}: The GROUP is a SUB_GROUP of several parents: {}",
+                    group_parents.join(", ")
+                ));
+            }
+        }
+
+        let mut visit_state = HashMap::new();
+        for group in &module.group {
+            let mut path = Vec::new();
+            find_group_cycles(&group.name, &groups, &mut visit_state, &mut path, log_msgs);
+        }
+
+        if !module.group.is_empty() {
+            check_orphans(module, log_msgs);
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum VisitState {
+    InProgress,
+    Done,
+}
+
+// depth-first search through the SUB_GROUPs; a group that is reached again while it is still in progress is part of a cycle
+fn find_group_cycles<'a>(
+    name: &'a str,
+    groups: &HashMap<&'a str, &'a Group>,
+    visit_state: &mut HashMap<&'a str, VisitState>,
+    path: &mut Vec<&'a str>,
+    log_msgs: &mut Vec<String>,
+) {
+    match visit_state.get(name) {
+        Some(VisitState::Done) => return,
+        Some(VisitState::InProgress) => {
+            let start = path.iter().position(|item| *item == name).unwrap_or(0);
+            let mut cycle = path[start..].to_vec();
+            cycle.push(name);
+            let line = groups.get(name).map_or(0, |group| group.get_line());
+            log_msgs.push(format!(
+                "In GROUP {name} on line {line}: The SUB_GROUPs form a cycle: {}",
+                cycle.join(" -> ")
+            ));
+            return;
+        }
+        None => {}
+    }
+    let Some(group) = groups.get(name) else {
+        return;
+    };
+    visit_state.insert(name, VisitState::InProgress);
+    path.push(name);
+    for sub_group in group.sub_group.iter().flat_map(|sg| &sg.identifier_list) {
+        find_group_cycles(sub_group, groups, visit_state, path, log_msgs);
+    }
+    path.pop();
+    visit_state.insert(name, VisitState::Done);
+}
+
+// every MEASUREMENT, CHARACTERISTIC, AXIS_PTS, BLOB and INSTANCE should be referenced by at least one GROUP
+fn check_orphans(module: &Module, log_msgs: &mut Vec<String>) {
+    let referenced: HashSet<&str> = module
+        .group
+        .iter()
+        .flat_map(|group| {
+            let characteristics = group
+                .ref_characteristic
+                .iter()
+                .flat_map(|rc| &rc.identifier_list);
+            let measurements = group
+                .ref_measurement
+                .iter()
+                .flat_map(|rm| &rm.identifier_list);
+            characteristics.chain(measurements)
+        })
+        .map(String::as_str)
+        .collect();
+    let mut report = |blocktype: &str, names: Vec<(&str, u32)>| {
+        for (name, line) in names {
+            if !referenced.contains(name) {
+                log_msgs.push(format!(
+                    "In {blocktype} {name} on line {line}: The object is not referenced by any GROUP"
+                ));
+            }
+        }
+    };
+    report("MEASUREMENT", get_names(&module.measurement));
+    report("CHARACTERISTIC", get_names(&module.characteristic));
+    report("AXIS_PTS", get_names(&module.axis_pts));
+    report("BLOB", get_names(&module.blob));
+    report("INSTANCE", get_names(&module.instance));
+}
+
+// all MEASUREMENTs, CHARACTERISTICs and AXIS_PTS that use the named COMPU_METHOD, with their limits
+fn objects_using_conversion<'a>(
+    module: &'a Module,
+    conversion: &'a str,
+) -> impl Iterator<Item = (&'a String, f64, f64)> {
+    module
+        .measurement
+        .iter()
+        .filter(move |item| item.conversion == conversion)
+        .map(|item| (&item.name, item.lower_limit, item.upper_limit))
+        .chain(
+            module
+                .characteristic
+                .iter()
+                .filter(move |item| item.conversion == conversion)
+                .map(|item| (&item.name, item.lower_limit, item.upper_limit)),
+        )
+        .chain(
+            module
+                .axis_pts
+                .iter()
+                .filter(move |item| item.conversion == conversion)
+                .map(|item| (&item.name, item.lower_limit, item.upper_limit)),
+        )
+}
+
+/// check that the byte order of the objects matches the byte order of the target
+///
+/// The target byte order is the byte order of the elf file if `elf_little_endian` is given, and the BYTE_ORDER in
+/// MOD_COMMON otherwise. A BYTE_ORDER in MOD_COMMON that conflicts with the elf file is reported, and so is every
+/// MEASUREMENT, CHARACTERISTIC, AXIS_DESCR, AXIS_PTS and TYPEDEF with an explicit BYTE_ORDER that conflicts with the
+/// target, unless its values are single bytes.
+///
+/// Returns the number of conflicts.
+pub fn check_byte_order(
+    a2l_file: &A2lFile,
+    elf_little_endian: Option<bool>,
+    log_msgs: &mut Vec<String>,
+) -> usize {
+    let count_before = log_msgs.len();
+    for module in &a2l_file.project.module {
+        let module_byte_order = module
+            .mod_common
+            .as_ref()
+            .and_then(|mod_common| mod_common.byte_order.as_ref());
+        let elf_byte_order = elf_little_endian.map(|little_endian| {
+            if little_endian {
+                ByteOrderEnum::MsbLast
+            } else {
+                ByteOrderEnum::MsbFirst
+            }
+        });
+        if let (Some(byte_order), Some(elf_byte_order)) = (module_byte_order, elf_byte_order) {
+            if normalize_byte_order(byte_order.byte_order) != elf_byte_order {
+                log_msgs.push(format!(
+                    "In MOD_COMMON of module {} on line {}: The BYTE_ORDER {} conflicts with the {} byte order of the elf file",
+                    module.name,
+                    byte_order.get_line(),
+                    byte_order.byte_order,
+                    if elf_byte_order == ByteOrderEnum::MsbLast { "little endian" } else { "big endian" }
+                ));
+            }
+        }
+        let Some(target) = elf_byte_order.or_else(|| {
+            module_byte_order.map(|byte_order| normalize_byte_order(byte_order.byte_order))
+        }) else {
+            continue;
+        };
+
+        let mut check_object = |blocktype: &str,
+                                name: &str,
+                                byte_order: &Option<ByteOrder>,
+                                multi_byte: bool| {
+            if let Some(byte_order) = byte_order {
+                if multi_byte && normalize_byte_order(byte_order.byte_order) != target {
+                    log_msgs.push(format!(
+                            "In {blocktype} {name} on line {}: The BYTE_ORDER {} conflicts with the byte order {target} of the target",
+                            byte_order.get_line(),
+                            byte_order.byte_order
+                        ));
+                }
+            }
+        };
+        let record_layout = |name: &str| module.record_layout.iter().find(|rl| rl.name == name);
+
+        for measurement in &module.measurement {
+            let multi_byte = datatype_size(measurement.datatype) > 1;
+            check_object(
+                "MEASUREMENT",
+                &measurement.name,
+                &measurement.byte_order,
+                multi_byte,
+            );
+        }
+        for characteristic in &module.characteristic {
+            let rl = record_layout(&characteristic.deposit);
+            let multi_byte = is_multi_byte(
+                rl.and_then(|rl| rl.fnc_values.as_ref().map(|fv| fv.datatype)),
+                rl,
+            );
+            check_object(
+                "CHARACTERISTIC",
+                &characteristic.name,
+                &characteristic.byte_order,
+                multi_byte,
+            );
+            for (idx, axis_descr) in characteristic.axis_descr.iter().enumerate() {
+                let multi_byte = is_multi_byte(rl.and_then(|rl| axis_datatype(rl, idx)), rl);
+                let name = format!("{} (axis {})", characteristic.name, idx + 1);
+                check_object(
+                    "AXIS_DESCR of CHARACTERISTIC",
+                    &name,
+                    &axis_descr.byte_order,
+                    multi_byte,
+                );
+            }
+        }
+        for axis_pts in &module.axis_pts {
+            let rl = record_layout(&axis_pts.deposit_record);
+            let multi_byte = is_multi_byte(rl.and_then(|rl| axis_datatype(rl, 0)), rl);
+            check_object("AXIS_PTS", &axis_pts.name, &axis_pts.byte_order, multi_byte);
+        }
+        for typedef in &module.typedef_measurement {
+            let multi_byte = datatype_size(typedef.datatype) > 1;
+            check_object(
+                "TYPEDEF_MEASUREMENT",
+                &typedef.name,
+                &typedef.byte_order,
+                multi_byte,
+            );
+        }
+        for typedef in &module.typedef_characteristic {
+            let rl = record_layout(&typedef.record_layout);
+            let multi_byte = is_multi_byte(
+                rl.and_then(|rl| rl.fnc_values.as_ref().map(|fv| fv.datatype)),
+                rl,
+            );
+            check_object(
+                "TYPEDEF_CHARACTERISTIC",
+                &typedef.name,
+                &typedef.byte_order,
+                multi_byte,
+            );
+        }
+        for typedef in &module.typedef_axis {
+            let rl = record_layout(&typedef.record_layout);
+            let multi_byte = is_multi_byte(rl.and_then(|rl| axis_datatype(rl, 0)), rl);
+            check_object(
+                "TYPEDEF_AXIS",
+                &typedef.name,
+                &typedef.byte_order,
+                multi_byte,
+            );
+        }
+    }
+    log_msgs.len() - count_before
+}
+
+// LITTLE_ENDIAN and BIG_ENDIAN are the deprecated names of MSB_LAST and MSB_FIRST
+fn normalize_byte_order(byte_order: ByteOrderEnum) -> ByteOrderEnum {
+    match byte_order {
+        ByteOrderEnum::LittleEndian => ByteOrderEnum::MsbLast,
+        ByteOrderEnum::BigEndian => ByteOrderEnum::MsbFirst,
+        other => other,
+    }
+}
+
+// the byte order only matters for values that are larger than one byte.
+// If the RECORD_LAYOUT is missing, the size is unknown and the object is checked
+fn is_multi_byte(datatype: Option<DataType>, record_layout: Option<&RecordLayout>) -> bool {
+    match datatype {
+        Some(datatype) => datatype_size(datatype) > 1,
+        None => record_layout.is_none(),
+    }
+}
+
+fn axis_datatype(record_layout: &RecordLayout, axis_idx: usize) -> Option<DataType> {
+    let axis_pts = match axis_idx {
+        0 => &record_layout.axis_pts_x,
+        1 => &record_layout.axis_pts_y,
+        2 => &record_layout.axis_pts_z,
+        3 => &record_layout.axis_pts_4,
+        _ => &record_layout.axis_pts_5,
+    };
+    axis_pts.as_ref().map(|axis_pts| axis_pts.datatype)
+}
+
+fn get_names<T, L>(items: &[T]) -> Vec<(&str, u32)>
+where
+    T: A2lObject<L> + A2lObjectName,
+{
+    items
+        .iter()
+        .map(|item| (item.get_name(), item.get_line()))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_check_naming() {
+        let rules = CheckRules::parse(
+            r#"
+            # measurements and characteristics need a prefix
+            NAMING MEASUREMENT ^M_
+            NAMING CHARACTERISTIC ^C_
+            "#,
+        )
+        .unwrap();
+        assert_eq!(rules.naming.len(), 2);
+
+        let mut a2l_file = a2lfile::new();
+        let module = &mut a2l_file.project.module[0];
+        for name in ["M_good", "bad_meas"] {
+            module.measurement.push(a2lfile::Measurement::new(
+                name.to_string(),
+                String::new(),
+                a2lfile::DataType::Ubyte,
+                "NO_COMPU_METHOD".to_string(),
+                0,
+                0.0,
+                0.0,
+                255.0,
+            ));
+        }
+
+        let mut log_msgs = Vec::new();
+        check_rules(&a2l_file, &rules, &mut log_msgs);
+        assert_eq!(log_msgs.len(), 1);
+        assert!(log_msgs[0].contains("bad_meas"));
+
+        assert!(CheckRules::parse("NAMING MEASUREMENT").is_err());
+        assert!(CheckRules::parse("NAMING NOT_A_BLOCK ^x").is_err());
+        assert!(CheckRules::parse("NAMING MEASUREMENT ([").is_err());
+        assert!(CheckRules::parse("UNKNOWN_RULE").is_err());
+    }
+
+    #[test]
+    fn test_check_byte_order() {
+        let a2l_text = r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin MOD_COMMON "" BYTE_ORDER MSB_LAST /end MOD_COMMON
+                /begin MEASUREMENT m_ok "" UWORD NO_COMPU_METHOD 0 0 0 255
+                    BYTE_ORDER LITTLE_ENDIAN
+                /end MEASUREMENT
+                /begin MEASUREMENT m_swapped "" UWORD NO_COMPU_METHOD 0 0 0 255
+                    BYTE_ORDER MSB_FIRST
+                /end MEASUREMENT
+                /begin MEASUREMENT m_byte "" UBYTE NO_COMPU_METHOD 0 0 0 255
+                    BYTE_ORDER MSB_FIRST
+                /end MEASUREMENT
+                /begin CHARACTERISTIC c_swapped "" CURVE 0x1000 RL_UWORD 0 NO_COMPU_METHOD 0 255
+                    BYTE_ORDER MSB_FIRST
+                    /begin AXIS_DESCR STD_AXIS NO_INPUT_QUANTITY NO_COMPU_METHOD 4 0 255
+                        BYTE_ORDER BIG_ENDIAN
+                    /end AXIS_DESCR
+                /end CHARACTERISTIC
+                /begin CHARACTERISTIC c_byte "" VALUE 0x2000 RL_UBYTE 0 NO_COMPU_METHOD 0 255
+                    BYTE_ORDER MSB_FIRST
+                /end CHARACTERISTIC
+                /begin RECORD_LAYOUT RL_UWORD
+                    FNC_VALUES 1 UWORD COLUMN_DIR DIRECT
+                    AXIS_PTS_X 2 UWORD INDEX_INCR DIRECT
+                /end RECORD_LAYOUT
+                /begin RECORD_LAYOUT RL_UBYTE
+                    FNC_VALUES 1 UBYTE COLUMN_DIR DIRECT
+                /end RECORD_LAYOUT
+            /end MODULE
+        /end PROJECT"#;
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(a2l_text, None, &mut log_msgs, false).unwrap();
+
+        // the target byte order is taken from MOD_COMMON; single byte objects are not reported
+        let mut log_msgs = Vec::new();
+        let count = check_byte_order(&a2l_file, None, &mut log_msgs);
+        assert_eq!(count, 3);
+        assert!(log_msgs[0].contains("m_swapped"));
+        assert!(log_msgs[1].contains("c_swapped"));
+        assert!(log_msgs[2].contains("AXIS_DESCR"));
+
+        // a big endian elf file conflicts with MOD_COMMON and with the little endian measurement
+        let mut log_msgs = Vec::new();
+        let count = check_byte_order(&a2l_file, Some(false), &mut log_msgs);
+        assert_eq!(count, 2);
+        assert!(log_msgs[0].contains("MOD_COMMON"));
+        assert!(log_msgs[1].contains("m_ok"));
+    }
+
+    #[test]
+    fn test_check_conversion_tables() {
+        let mut load_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(
+            r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin COMPU_METHOD cm_tab "" TAB_INTP "%6.2" ""
+                    COMPU_TAB_REF tab
+                /end COMPU_METHOD
+                /begin COMPU_TAB tab "" TAB_INTP 4
+                    0 0  10 5  20 2  20 30
+                /end COMPU_TAB
+                /begin COMPU_METHOD cm_range "" TAB_VERB "%6.2" ""
+                    COMPU_TAB_REF range
+                /end COMPU_METHOD
+                /begin COMPU_VTAB_RANGE range "" 3
+                    0 9 "low"  10 19 "mid"  15 30 "high"
+                /end COMPU_VTAB_RANGE
+                /begin COMPU_METHOD cm_gap "" TAB_VERB "%6.2" ""
+                    COMPU_TAB_REF gap
+                /end COMPU_METHOD
+                /begin COMPU_VTAB_RANGE gap "" 2
+                    0 9 "low"  12 19 "high"
+                /end COMPU_VTAB_RANGE
+                /begin MEASUREMENT m_tab "" UBYTE cm_tab 0 0 0 40 /end MEASUREMENT
+                /begin MEASUREMENT m_range "" UBYTE cm_range 0 0 0 30 /end MEASUREMENT
+                /begin MEASUREMENT m_gap "" UBYTE cm_gap 0 0 0 19 /end MEASUREMENT
+                /begin MEASUREMENT m_gap_small "" UBYTE cm_gap 0 0 0 9 /end MEASUREMENT
+            /end MODULE
+        /end PROJECT"#,
+            None,
+            &mut load_msgs,
+            true,
+        )
+        .unwrap();
+
+        let mut log_msgs = Vec::new();
+        check_conversion_tables(&a2l_file, &mut log_msgs);
+        assert_eq!(log_msgs.len(), 5);
+        assert!(log_msgs[0].contains("not strictly increasing"));
+        assert!(log_msgs[1].contains("not monotonic"));
+        assert!(log_msgs[2].contains("\"mid\" and \"high\" overlap"));
+        assert!(log_msgs[3].contains("m_tab"));
+        assert!(log_msgs[4].contains("The value 10") && log_msgs[4].contains("m_gap"));
+
+        let triples = &a2l_file.project.module[0].compu_vtab_range[0].value_triples;
+        assert_eq!(find_range_gap(triples, 0.0, 30.0), None);
+        assert_eq!(find_range_gap(triples, -1.0, 30.0), Some(-1.0));
+        assert_eq!(find_range_gap(triples, 0.0, 40.0), Some(31.0));
+    }
+
+    #[test]
+    fn test_check_duplicate_addresses() {
+        let mut load_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(
+            r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin MEASUREMENT m1 "" UBYTE NO_COMPU_METHOD 0 0 0 255 ECU_ADDRESS 0x1000 /end MEASUREMENT
+                /begin MEASUREMENT m1_copy "" UBYTE NO_COMPU_METHOD 0 0 0 255 ECU_ADDRESS 0x1000 /end MEASUREMENT
+                /begin MEASUREMENT bit0 "" UBYTE NO_COMPU_METHOD 0 0 0 1 ECU_ADDRESS 0x2000 BIT_MASK 0x01 /end MEASUREMENT
+                /begin MEASUREMENT bit1 "" UBYTE NO_COMPU_METHOD 0 0 0 1 ECU_ADDRESS 0x2000 BIT_MASK 0x02 /end MEASUREMENT
+                /begin MEASUREMENT unlocated "" UBYTE NO_COMPU_METHOD 0 0 0 255 ECU_ADDRESS 0 /end MEASUREMENT
+                /begin CHARACTERISTIC c_unlocated "" VALUE 0 RL 0 NO_COMPU_METHOD 0 100 /end CHARACTERISTIC
+                /begin CHARACTERISTIC c1 "" VALUE 0x3000 RL 0 NO_COMPU_METHOD 0 100 /end CHARACTERISTIC
+                /begin MEASUREMENT c1_alias "" UBYTE NO_COMPU_METHOD 0 0 0 255 ECU_ADDRESS 0x3000 /end MEASUREMENT
+            /end MODULE
+        /end PROJECT"#,
+            None,
+            &mut load_msgs,
+            true,
+        )
+        .unwrap();
+
+        let mut log_msgs = Vec::new();
+        check_duplicate_addresses(&a2l_file, &CheckRules::default(), &mut log_msgs);
+        assert_eq!(log_msgs.len(), 2);
+        assert!(
+            log_msgs[0].contains("MEASUREMENT m1 ") && log_msgs[0].contains("MEASUREMENT m1_copy")
+        );
+        assert!(log_msgs[1].contains("CHARACTERISTIC c1 ") && log_msgs[1].contains("0x3000"));
+
+        let rules = CheckRules::parse("ALIAS .*_alias").unwrap();
+        let mut log_msgs = Vec::new();
+        check_duplicate_addresses(&a2l_file, &rules, &mut log_msgs);
+        assert_eq!(log_msgs.len(), 1);
+        assert!(CheckRules::parse("ALIAS").is_err());
+    }
+
+    #[test]
+    fn test_check_groups() {
+        let mut load_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(
+            r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin GROUP top "" ROOT
+                    /begin SUB_GROUP a shared /end SUB_GROUP
+                /end GROUP
+                /begin GROUP a ""
+                    /begin SUB_GROUP b /end SUB_GROUP
+                    /begin REF_MEASUREMENT m1 /end REF_MEASUREMENT
+                /end GROUP
+                /begin GROUP b ""
+                    /begin SUB_GROUP a /end SUB_GROUP
+                /end GROUP
+                /begin GROUP shared "" ROOT
+                    /begin REF_CHARACTERISTIC c1 /end REF_CHARACTERISTIC
+                /end GROUP
+                /begin MEASUREMENT m1 "" UBYTE NO_COMPU_METHOD 0 0 0 255 /end MEASUREMENT
+                /begin MEASUREMENT orphan "" UBYTE NO_COMPU_METHOD 0 0 0 255 /end MEASUREMENT
+                /begin CHARACTERISTIC c1 "" VALUE 0x1000 RL 0 NO_COMPU_METHOD 0 100 /end CHARACTERISTIC
+            /end MODULE
+        /end PROJECT"#,
+            None,
+            &mut load_msgs,
+            true,
+        )
+        .unwrap();
+
+        let mut log_msgs = Vec::new();
+        check_groups(&a2l_file, &mut log_msgs);
+        assert_eq!(log_msgs.len(), 4);
+        assert!(
+            log_msgs[0].contains("GROUP a ") && log_msgs[0].contains("several parents: top, b")
+        );
+        assert!(log_msgs[1].contains("GROUP shared ") && log_msgs[1].contains("declared as ROOT"));
+        assert!(log_msgs[2].contains("cycle: a -> b -> a"));
+        assert!(log_msgs[3].contains("MEASUREMENT orphan"));
+
+        // a module without groups has no orphans
+        let mut a2l_file = a2l_file;
+        a2l_file.project.module[0].group.clear();
+        let mut log_msgs = Vec::new();
+        check_groups(&a2l_file, &mut log_msgs);
+        assert!(log_msgs.is_empty());
+    }
+
+    #[test]
+    fn test_check_formulas() {
+        let mut load_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(
+            r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin MOD_PAR ""
+                    SYSTEM_CONSTANT "factor" "2"
+                /end MOD_PAR
+                /begin COMPU_METHOD good "" FORM "%6.2" ""
+                    /begin FORMULA "X1 * 2 + 1" FORMULA_INV "(X1 - 1) / 2" /end FORMULA
+                /end COMPU_METHOD
+                /begin COMPU_METHOD wrong_inverse "" FORM "%6.2" ""
+                    /begin FORMULA "X1 * 2" FORMULA_INV "X1 * 2" /end FORMULA
+                /end COMPU_METHOD
+                /begin COMPU_METHOD syntax_error "" FORM "%6.2" ""
+                    /begin FORMULA "sqr(X1" /end FORMULA
+                /end COMPU_METHOD
+                /begin COMPU_METHOD no_inverse "" FORM "%6.2" ""
+                    /begin FORMULA "X1 * sysc(factor) + sysc(offset)" /end FORMULA
+                /end COMPU_METHOD
+                /begin MEASUREMENT m1 "" UBYTE good 0 0 0 255 /end MEASUREMENT
+                /begin MEASUREMENT m2 "" UBYTE wrong_inverse 0 0 0 100 /end MEASUREMENT
+                /begin CHARACTERISTIC c1 "" VALUE 0x1000 RL 0 no_inverse 0 100 /end CHARACTERISTIC
+            /end MODULE
+        /end PROJECT"#,
+            None,
+            &mut load_msgs,
+            true,
+        )
+        .unwrap();
+
+        let mut log_msgs = Vec::new();
+        check_formulas(&a2l_file, false, &mut log_msgs);
+        // the syntax error, the unknown system constant and the missing FORMULA_INV
+        assert_eq!(log_msgs.len(), 3);
+        assert!(log_msgs[0].contains("syntax_error"));
+        assert!(log_msgs[1].contains("offset"));
+        assert!(log_msgs[2].contains("c1"));
+
+        let mut log_msgs = Vec::new();
+        check_formulas(&a2l_file, true, &mut log_msgs);
+        assert_eq!(log_msgs.len(), 4);
+        assert!(log_msgs[0].contains("wrong_inverse") && log_msgs[0].contains("m2"));
+    }
+}
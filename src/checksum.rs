@@ -0,0 +1,405 @@
+//! Calculation and verification of the checksums of MEMORY_SEGMENTs
+
+use crate::hexfile::HexImage;
+use crate::ifdata::{A2mlVector, XcpChecksumType};
+use crate::modpar::get_or_create_mod_par;
+use a2lfile::{A2lFile, ByteOrderEnum, MemorySegment, Module, SystemConstant};
+use std::str::FromStr;
+
+/// The checksum algorithms defined by XCP
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumType {
+    /// add the bytes into a byte
+    Add11,
+    /// add the bytes into a word
+    Add12,
+    /// add the bytes into a dword
+    Add14,
+    /// add the words into a word
+    Add22,
+    /// add the words into a dword
+    Add24,
+    /// add the dwords into a dword
+    Add44,
+    /// CRC-16 with the polynomial 0x8005
+    Crc16,
+    /// CRC-16 CCITT with the polynomial 0x1021
+    Crc16Ccitt,
+    /// CRC-32 with the polynomial 0x04C11DB7
+    Crc32,
+}
+
+impl FromStr for ChecksumType {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.to_ascii_lowercase().as_str() {
+            "add11" => Ok(Self::Add11),
+            "add12" => Ok(Self::Add12),
+            "add14" => Ok(Self::Add14),
+            "add22" => Ok(Self::Add22),
+            "add24" => Ok(Self::Add24),
+            "add44" => Ok(Self::Add44),
+            "crc16" => Ok(Self::Crc16),
+            "crc16-ccitt" => Ok(Self::Crc16Ccitt),
+            "crc32" => Ok(Self::Crc32),
+            _ => Err(format!(
+                "Error: unknown checksum type \"{text}\". Valid types are add11, add12, add14, add22, add24, add44, crc16, crc16-ccitt and crc32"
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for ChecksumType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Add11 => "XCP_ADD_11",
+            Self::Add12 => "XCP_ADD_12",
+            Self::Add14 => "XCP_ADD_14",
+            Self::Add22 => "XCP_ADD_22",
+            Self::Add24 => "XCP_ADD_24",
+            Self::Add44 => "XCP_ADD_44",
+            Self::Crc16 => "XCP_CRC_16",
+            Self::Crc16Ccitt => "XCP_CRC_16_CITT",
+            Self::Crc32 => "XCP_CRC_32",
+        };
+        f.write_str(name)
+    }
+}
+
+impl ChecksumType {
+    fn from_xcp(xcp_type: &XcpChecksumType) -> Option<Self> {
+        match xcp_type {
+            XcpChecksumType::XcpAdd11 => Some(Self::Add11),
+            XcpChecksumType::XcpAdd12 => Some(Self::Add12),
+            XcpChecksumType::XcpAdd14 => Some(Self::Add14),
+            XcpChecksumType::XcpAdd22 => Some(Self::Add22),
+            XcpChecksumType::XcpAdd24 => Some(Self::Add24),
+            XcpChecksumType::XcpAdd44 => Some(Self::Add44),
+            XcpChecksumType::XcpCrc16 => Some(Self::Crc16),
+            XcpChecksumType::XcpCrc16Citt => Some(Self::Crc16Ccitt),
+            XcpChecksumType::XcpCrc32 => Some(Self::Crc32),
+            XcpChecksumType::XcpUserDefined => None,
+        }
+    }
+
+    // the number of hex digits needed to display the checksum
+    fn digits(self) -> usize {
+        match self {
+            Self::Add11 => 2,
+            Self::Add12 | Self::Add22 | Self::Crc16 | Self::Crc16Ccitt => 4,
+            Self::Add14 | Self::Add24 | Self::Add44 | Self::Crc32 => 8,
+        }
+    }
+}
+
+/// Calculate the checksum of a block of data.
+///
+/// The words and dwords of the ADD_2x and ADD_4x checksums are read with the given byte order.
+/// If the length of the data is not a multiple of their size, the data is padded with zeros.
+#[must_use]
+pub fn calculate_checksum(checksum_type: ChecksumType, data: &[u8], msb_first: bool) -> u32 {
+    let read_dword = |chunk: &[u8]| {
+        let mut bytes = [0u8; 4];
+        bytes[..chunk.len()].copy_from_slice(chunk);
+        if msb_first {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    };
+    match checksum_type {
+        ChecksumType::Add11 => data.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)).into(),
+        ChecksumType::Add12 => data
+            .iter()
+            .fold(0u16, |sum, b| sum.wrapping_add(u16::from(*b)))
+            .into(),
+        ChecksumType::Add14 => data
+            .iter()
+            .fold(0u32, |sum, b| sum.wrapping_add(u32::from(*b))),
+        ChecksumType::Add22 => data
+            .chunks(2)
+            .map(|chunk| read_word(chunk, msb_first))
+            .fold(0u16, u16::wrapping_add)
+            .into(),
+        ChecksumType::Add24 => data
+            .chunks(2)
+            .map(|chunk| u32::from(read_word(chunk, msb_first)))
+            .fold(0u32, u32::wrapping_add),
+        ChecksumType::Add44 => data.chunks(4).map(read_dword).fold(0u32, u32::wrapping_add),
+        ChecksumType::Crc16 => crc16_arc(data).into(),
+        ChecksumType::Crc16Ccitt => crc16_ccitt(data).into(),
+        ChecksumType::Crc32 => crc32(data),
+    }
+}
+
+fn read_word(chunk: &[u8], msb_first: bool) -> u16 {
+    let bytes = [chunk[0], chunk.get(1).copied().unwrap_or(0)];
+    if msb_first {
+        u16::from_be_bytes(bytes)
+    } else {
+        u16::from_le_bytes(bytes)
+    }
+}
+
+// CRC-16/ARC: reflected polynomial 0x8005, initial value 0
+fn crc16_arc(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for byte in data {
+        crc ^= u16::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xA001
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+// CRC-16/CCITT-FALSE: polynomial 0x1021, initial value 0xFFFF
+fn crc16_ccitt(data: &[u8]) -> u16 {
+    let mut crc = 0xFFFFu16;
+    for byte in data {
+        crc ^= u16::from(*byte) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+// CRC-32: reflected polynomial 0x04C11DB7, initial value and final xor 0xFFFFFFFF
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in data {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// result of [`update_checksums`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ChecksumResult {
+    /// number of MEMORY_SEGMENTs whose checksum was calculated
+    pub calculated: usize,
+    /// number of MEMORY_SEGMENTs whose checksum was missing or did not match, or could not be calculated
+    pub mismatched: usize,
+}
+
+/// Calculate the checksum of every MEMORY_SEGMENT over the content of the memory image.
+///
+/// The checksum type is taken from the CHECKSUM block in the XCP IF_DATA of the segment; `default_type`
+/// is used for segments without one. The checksums are stored in MOD_PAR as SYSTEM_CONSTANTs named
+/// `CHECKSUM.<segment name>`. If `verify_only` is set, the stored values are compared with the calculated
+/// values instead, and nothing is changed.
+/// Segments which are not completely contained in the image are reported in `log_msgs`.
+pub fn update_checksums(
+    a2l_file: &mut A2lFile,
+    image: &HexImage,
+    default_type: ChecksumType,
+    verify_only: bool,
+    log_msgs: &mut Vec<String>,
+) -> ChecksumResult {
+    let mut result = ChecksumResult::default();
+    for module in &mut a2l_file.project.module {
+        let msb_first = is_msb_first(module);
+        let Some(mod_par) = &module.mod_par else {
+            continue;
+        };
+        let mut checksums = Vec::new();
+        for segment in &mod_par.memory_segment {
+            let checksum_type = segment_checksum_type(segment).unwrap_or(default_type);
+            let Some(data) = image.read(segment.address, segment.size as usize) else {
+                log_msgs.push(format!(
+                    "MEMORY_SEGMENT {} (0x{:08X}, size 0x{:X}) is not completely contained in the memory image",
+                    segment.name, segment.address, segment.size
+                ));
+                result.mismatched += 1;
+                continue;
+            };
+            let value = calculate_checksum(checksum_type, data, msb_first);
+            result.calculated += 1;
+            checksums.push((
+                format!("CHECKSUM.{}", segment.name),
+                format!("0x{value:0width$X}", width = checksum_type.digits()),
+                value,
+                checksum_type,
+            ));
+        }
+
+        let mod_par = get_or_create_mod_par(module);
+        for (name, text, value, checksum_type) in checksums {
+            let existing = mod_par
+                .system_constant
+                .iter_mut()
+                .find(|constant| constant.name == name);
+            if verify_only {
+                match existing
+                    .as_ref()
+                    .map(|constant| parse_checksum(&constant.value))
+                {
+                    Some(Some(stored)) if stored == value => {}
+                    Some(_) => {
+                        log_msgs.push(format!(
+                            "SYSTEM_CONSTANT {name}: the stored value {} does not match the calculated {checksum_type} checksum {text}",
+                            existing.map(|constant| constant.value.as_str()).unwrap_or_default()
+                        ));
+                        result.mismatched += 1;
+                    }
+                    None => {
+                        log_msgs.push(format!(
+                            "SYSTEM_CONSTANT {name} does not exist; the calculated {checksum_type} checksum is {text}"
+                        ));
+                        result.mismatched += 1;
+                    }
+                }
+            } else if let Some(constant) = existing {
+                constant.value = text;
+            } else {
+                mod_par
+                    .system_constant
+                    .push(SystemConstant::new(name, text));
+            }
+        }
+    }
+    result
+}
+
+fn segment_checksum_type(segment: &MemorySegment) -> Option<ChecksumType> {
+    segment.if_data.iter().find_map(|ifdata| {
+        let checksum = A2mlVector::load_from_ifdata(ifdata)?
+            .xcp?
+            .segment?
+            .checksum?;
+        ChecksumType::from_xcp(&checksum.xcp_checksum_type)
+    })
+}
+
+fn is_msb_first(module: &Module) -> bool {
+    module
+        .mod_common
+        .as_ref()
+        .and_then(|mod_common| mod_common.byte_order.as_ref())
+        .is_some_and(|byte_order| {
+            matches!(
+                byte_order.byte_order,
+                ByteOrderEnum::MsbFirst | ByteOrderEnum::BigEndian | ByteOrderEnum::MsbFirstMswLast
+            )
+        })
+}
+
+fn parse_checksum(text: &str) -> Option<u32> {
+    let text = text.trim();
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        u32::from_str_radix(hex, 16).ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_checksums() {
+        let data = b"123456789";
+        assert_eq!(calculate_checksum(ChecksumType::Crc16, data, false), 0xBB3D);
+        assert_eq!(
+            calculate_checksum(ChecksumType::Crc16Ccitt, data, false),
+            0x29B1
+        );
+        assert_eq!(
+            calculate_checksum(ChecksumType::Crc32, data, false),
+            0xCBF4_3926
+        );
+        assert_eq!(
+            calculate_checksum(ChecksumType::Add11, &[0xFF, 0x02], false),
+            0x01
+        );
+        assert_eq!(
+            calculate_checksum(ChecksumType::Add12, &[0xFF, 0x02], false),
+            0x101
+        );
+        assert_eq!(
+            calculate_checksum(ChecksumType::Add22, &[1, 2, 3], false),
+            0x0204
+        );
+        assert_eq!(
+            calculate_checksum(ChecksumType::Add22, &[1, 2, 3], true),
+            0x0402
+        );
+        assert_eq!(
+            calculate_checksum(ChecksumType::Add44, &[1, 0, 0, 0, 2], true),
+            0x0300_0000
+        );
+        assert!("crc64".parse::<ChecksumType>().is_err());
+
+        let image = HexImage::parse(":0400100001020304E2\n:00000001FF\n").unwrap();
+        let mut load_msgs = Vec::new();
+        let mut a2l_file = a2lfile::load_from_string(
+            r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin MOD_PAR ""
+                    /begin MEMORY_SEGMENT data "" DATA FLASH INTERN 0x10 4 -1 -1 -1 -1 -1
+                    /end MEMORY_SEGMENT
+                    /begin MEMORY_SEGMENT missing "" DATA FLASH INTERN 0x1000 4 -1 -1 -1 -1 -1
+                    /end MEMORY_SEGMENT
+                /end MOD_PAR
+            /end MODULE
+        /end PROJECT"#,
+            None,
+            &mut load_msgs,
+            true,
+        )
+        .unwrap();
+        let mut log_msgs = Vec::new();
+        let result = update_checksums(
+            &mut a2l_file,
+            &image,
+            ChecksumType::Add11,
+            true,
+            &mut log_msgs,
+        );
+        assert_eq!(result.calculated, 1);
+        assert_eq!(result.mismatched, 2);
+        assert_eq!(log_msgs.len(), 2);
+
+        log_msgs.clear();
+        update_checksums(
+            &mut a2l_file,
+            &image,
+            ChecksumType::Add11,
+            false,
+            &mut log_msgs,
+        );
+        let mod_par = a2l_file.project.module[0].mod_par.as_ref().unwrap();
+        assert_eq!(mod_par.system_constant[0].name, "CHECKSUM.data");
+        assert_eq!(mod_par.system_constant[0].value, "0x0A");
+
+        log_msgs.clear();
+        let result = update_checksums(
+            &mut a2l_file,
+            &image,
+            ChecksumType::Add11,
+            true,
+            &mut log_msgs,
+        );
+        assert_eq!(result.mismatched, 1);
+    }
+}
@@ -0,0 +1,567 @@
+//! Generation of C and Rust struct definitions from TYPEDEF_STRUCTUREs
+//!
+//! The generated structs mirror the memory layout of the a2l file: each STRUCTURE_COMPONENT is placed at its
+//! ADDRESS_OFFSET, and gaps are filled with reserved bytes. The structs are packed, so that the compiler
+//! cannot insert any padding of its own.
+
+use crate::calvalues::find_record_layout;
+use a2lfile::{
+    A2lFile, CharacteristicType, DataType, Module, StructureComponent, TypedefStructure,
+};
+use std::collections::HashSet;
+use std::fmt::Write;
+
+/// The language of the generated struct definitions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StructLanguage {
+    C,
+    Rust,
+}
+
+// a member of a generated struct
+struct Member {
+    name: String,
+    type_name: String,
+    // array dimensions, outermost first
+    dims: Vec<u32>,
+    comment: String,
+}
+
+// the native type of a component: type name, array dimensions and size in bytes
+struct NativeType {
+    type_name: String,
+    dims: Vec<u32>,
+    size: u32,
+}
+
+const C_KEYWORDS: &[&str] = &[
+    "auto", "break", "case", "char", "const", "continue", "default", "do", "double", "else",
+    "enum", "extern", "float", "for", "goto", "if", "inline", "int", "long", "register",
+    "restrict", "return", "short", "signed", "sizeof", "static", "struct", "switch", "typedef",
+    "union", "unsigned", "void", "volatile", "while",
+];
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while",
+];
+
+/// Generate struct definitions for all TYPEDEF_STRUCTUREs in the a2l file.
+///
+/// Components of type TYPEDEF_MEASUREMENT, TYPEDEF_BLOB and TYPEDEF_STRUCTURE, as well as TYPEDEF_CHARACTERISTICs
+/// of the types VALUE, VAL_BLK and ASCII and TYPEDEF_AXIS without additional record layout entries are
+/// generated with native types. All other components are generated as byte arrays that fill the space up to
+/// the next component. Components that overlap a previous component, e.g. bit fields that share their
+/// storage, are skipped and reported in log_msgs.
+#[must_use]
+pub fn generate_structs(
+    a2l_file: &A2lFile,
+    language: StructLanguage,
+    log_msgs: &mut Vec<String>,
+) -> String {
+    let mut text = String::new();
+    match language {
+        StructLanguage::C => {
+            text.push_str(
+                "/* generated by a2ltool from the TYPEDEF_STRUCTUREs in the a2l file */\n\n",
+            );
+            text.push_str("#ifndef A2L_TYPEDEF_STRUCTS_H\n#define A2L_TYPEDEF_STRUCTS_H\n\n");
+            text.push_str("#include <stdint.h>\n\n#pragma pack(push, 1)\n");
+        }
+        StructLanguage::Rust => {
+            text.push_str("// generated by a2ltool from the TYPEDEF_STRUCTUREs in the a2l file\n");
+        }
+    }
+
+    for module in &a2l_file.project.module {
+        let mut done = HashSet::new();
+        let mut in_progress = HashSet::new();
+        for typedef in &module.typedef_structure {
+            generate_with_dependencies(
+                module,
+                typedef,
+                language,
+                &mut done,
+                &mut in_progress,
+                &mut text,
+                log_msgs,
+            );
+        }
+    }
+
+    if language == StructLanguage::C {
+        text.push_str("\n#pragma pack(pop)\n\n#endif\n");
+    }
+    text
+}
+
+// structs that are used as components must be defined before the struct that uses them
+fn generate_with_dependencies<'a>(
+    module: &'a Module,
+    typedef: &'a TypedefStructure,
+    language: StructLanguage,
+    done: &mut HashSet<&'a str>,
+    in_progress: &mut HashSet<&'a str>,
+    text: &mut String,
+    log_msgs: &mut Vec<String>,
+) {
+    if done.contains(typedef.name.as_str()) {
+        return;
+    }
+    if !in_progress.insert(&typedef.name) {
+        log_msgs.push(format!(
+            "TYPEDEF_STRUCTURE {} contains itself and cannot be generated",
+            typedef.name
+        ));
+        return;
+    }
+    for component in &typedef.structure_component {
+        if let Some(inner) = module
+            .typedef_structure
+            .iter()
+            .find(|inner| inner.name == component.component_type)
+        {
+            generate_with_dependencies(module, inner, language, done, in_progress, text, log_msgs);
+        }
+    }
+    in_progress.remove(typedef.name.as_str());
+    done.insert(&typedef.name);
+
+    let (members, size) = struct_members(module, typedef, language, log_msgs);
+    // a struct whose components do not fit into TOTAL_SIZE would fail the size assertion
+    let check_size = size == typedef.total_size;
+    match language {
+        StructLanguage::C => write_c_struct(text, typedef, &members, check_size),
+        StructLanguage::Rust => write_rust_struct(text, typedef, &members, check_size),
+    }
+}
+
+fn struct_members(
+    module: &Module,
+    typedef: &TypedefStructure,
+    language: StructLanguage,
+    log_msgs: &mut Vec<String>,
+) -> (Vec<Member>, u32) {
+    let mut components: Vec<&StructureComponent> = typedef.structure_component.iter().collect();
+    components.sort_by_key(|component| component.address_offset);
+
+    let byte_type = base_type(DataType::Ubyte, language);
+    let mut members = Vec::new();
+    let mut position = 0;
+    for (idx, component) in components.iter().enumerate() {
+        let offset = component.address_offset;
+        if offset < position {
+            log_msgs.push(format!(
+                "STRUCTURE_COMPONENT {} of TYPEDEF_STRUCTURE {} overlaps the previous component and is not generated",
+                component.component_name, typedef.name
+            ));
+            continue;
+        }
+        if offset > position {
+            members.push(reserved_member(position, offset - position, language));
+        }
+
+        // the space up to the next component, or up to the end of the struct
+        let available = components[idx + 1..]
+            .iter()
+            .map(|next| next.address_offset)
+            .find(|next_offset| *next_offset > offset)
+            .unwrap_or(typedef.total_size)
+            .saturating_sub(offset);
+        let component_dims = component
+            .matrix_dim
+            .as_ref()
+            .map(|matrix_dim| significant_dims(&matrix_dim.dim_list))
+            .unwrap_or_default();
+        let component_count: u32 = component_dims.iter().product();
+        let name = identifier(&component.component_name, language);
+        let comment = format!("offset 0x{offset:X}");
+
+        if let Some(native) = native_type(module, &component.component_type, language) {
+            let mut dims = component_dims;
+            dims.extend(native.dims);
+            members.push(Member {
+                name,
+                type_name: native.type_name,
+                dims,
+                comment,
+            });
+            position = offset + native.size * component_count;
+        } else {
+            log_msgs.push(format!(
+                "STRUCTURE_COMPONENT {} of TYPEDEF_STRUCTURE {} has no native representation and is generated as a byte array",
+                component.component_name, typedef.name
+            ));
+            members.push(Member {
+                name,
+                type_name: byte_type.to_string(),
+                dims: vec![available],
+                comment: format!("{comment}, {}", component.component_type),
+            });
+            position = offset + available;
+        }
+    }
+
+    if position < typedef.total_size {
+        members.push(reserved_member(
+            position,
+            typedef.total_size - position,
+            language,
+        ));
+        position = typedef.total_size;
+    } else if position > typedef.total_size {
+        log_msgs.push(format!(
+            "The components of TYPEDEF_STRUCTURE {} need {position} bytes, but its size is {}",
+            typedef.name, typedef.total_size
+        ));
+    }
+
+    (members, position)
+}
+
+fn reserved_member(offset: u32, size: u32, language: StructLanguage) -> Member {
+    Member {
+        name: format!("_reserved_{offset:X}"),
+        type_name: base_type(DataType::Ubyte, language).to_string(),
+        dims: vec![size],
+        comment: format!("offset 0x{offset:X}"),
+    }
+}
+
+fn native_type(module: &Module, type_name: &str, language: StructLanguage) -> Option<NativeType> {
+    if let Some(typedef) = module
+        .typedef_structure
+        .iter()
+        .find(|t| t.name == type_name)
+    {
+        return Some(NativeType {
+            type_name: identifier(&typedef.name, language),
+            dims: vec![],
+            size: typedef.total_size,
+        });
+    }
+
+    if let Some(typedef) = module
+        .typedef_measurement
+        .iter()
+        .find(|t| t.name == type_name)
+    {
+        let dims = typedef
+            .matrix_dim
+            .as_ref()
+            .map(|matrix_dim| significant_dims(&matrix_dim.dim_list))
+            .unwrap_or_default();
+        return Some(array_of(typedef.datatype, dims, language));
+    }
+
+    if let Some(typedef) = module.typedef_blob.iter().find(|t| t.name == type_name) {
+        return Some(array_of(DataType::Ubyte, vec![typedef.size], language));
+    }
+
+    if let Some(typedef) = module
+        .typedef_characteristic
+        .iter()
+        .find(|t| t.name == type_name)
+    {
+        let block_dims = if let Some(matrix_dim) = &typedef.matrix_dim {
+            significant_dims(&matrix_dim.dim_list)
+        } else if let Some(number) = &typedef.number {
+            vec![u32::from(number.number)]
+        } else {
+            vec![]
+        };
+        if typedef.characteristic_type == CharacteristicType::Ascii {
+            let mut native = array_of(DataType::Ubyte, block_dims, language);
+            if language == StructLanguage::C {
+                native.type_name = "char".to_string();
+            }
+            return Some(native);
+        }
+        let record_layout = find_record_layout(module, &typedef.record_layout).ok()?;
+        if !is_plain_record_layout(record_layout) {
+            return None;
+        }
+        let datatype = record_layout.fnc_values.as_ref()?.datatype;
+        return match typedef.characteristic_type {
+            CharacteristicType::Value => Some(array_of(datatype, vec![], language)),
+            CharacteristicType::ValBlk => Some(array_of(datatype, block_dims, language)),
+            _ => None,
+        };
+    }
+
+    if let Some(typedef) = module.typedef_axis.iter().find(|t| t.name == type_name) {
+        let record_layout = find_record_layout(module, &typedef.record_layout).ok()?;
+        if record_layout.fnc_values.is_some() || !is_plain_record_layout(record_layout) {
+            return None;
+        }
+        let datatype = record_layout.axis_pts_x.as_ref()?.datatype;
+        return Some(array_of(
+            datatype,
+            vec![u32::from(typedef.max_axis_points)],
+            language,
+        ));
+    }
+
+    None
+}
+
+// a record layout that only contains the values, without axis point counts, rescale axes or identification
+fn is_plain_record_layout(record_layout: &a2lfile::RecordLayout) -> bool {
+    record_layout.no_axis_pts_x.is_none()
+        && record_layout.axis_pts_y.is_none()
+        && record_layout.axis_rescale_x.is_none()
+        && record_layout.no_rescale_x.is_none()
+        && record_layout.identification.is_none()
+        && record_layout.src_addr_x.is_none()
+        && record_layout.rip_addr_x.is_none()
+        && record_layout.offset_x.is_none()
+        && record_layout.shift_op_x.is_none()
+        && record_layout.dist_op_x.is_none()
+}
+
+fn array_of(datatype: DataType, dims: Vec<u32>, language: StructLanguage) -> NativeType {
+    let count: u32 = dims.iter().product();
+    NativeType {
+        type_name: base_type(datatype, language).to_string(),
+        size: crate::memmap::datatype_size(datatype) * count,
+        dims,
+    }
+}
+
+// dimensions of size 1 do not change the layout; older files often use MATRIX_DIM 1 1 1 for scalars
+fn significant_dims(dim_list: &[u16]) -> Vec<u32> {
+    dim_list
+        .iter()
+        .filter(|dim| **dim > 1)
+        .map(|dim| u32::from(*dim))
+        .collect()
+}
+
+fn base_type(datatype: DataType, language: StructLanguage) -> &'static str {
+    match (language, datatype) {
+        (StructLanguage::C, DataType::Ubyte) => "uint8_t",
+        (StructLanguage::C, DataType::Sbyte) => "int8_t",
+        // C has no standard 16 bit float type, so the raw bits are used
+        (StructLanguage::C, DataType::Uword | DataType::Float16Ieee) => "uint16_t",
+        (StructLanguage::C, DataType::Sword) => "int16_t",
+        (StructLanguage::C, DataType::Ulong) => "uint32_t",
+        (StructLanguage::C, DataType::Slong) => "int32_t",
+        (StructLanguage::C, DataType::AUint64) => "uint64_t",
+        (StructLanguage::C, DataType::AInt64) => "int64_t",
+        (StructLanguage::C, DataType::Float32Ieee) => "float",
+        (StructLanguage::C, DataType::Float64Ieee) => "double",
+        (StructLanguage::Rust, DataType::Ubyte) => "u8",
+        (StructLanguage::Rust, DataType::Sbyte) => "i8",
+        (StructLanguage::Rust, DataType::Uword | DataType::Float16Ieee) => "u16",
+        (StructLanguage::Rust, DataType::Sword) => "i16",
+        (StructLanguage::Rust, DataType::Ulong) => "u32",
+        (StructLanguage::Rust, DataType::Slong) => "i32",
+        (StructLanguage::Rust, DataType::AUint64) => "u64",
+        (StructLanguage::Rust, DataType::AInt64) => "i64",
+        (StructLanguage::Rust, DataType::Float32Ieee) => "f32",
+        (StructLanguage::Rust, DataType::Float64Ieee) => "f64",
+    }
+}
+
+// a2l names may contain characters like '.' or '[' that are not allowed in identifiers
+fn identifier(name: &str, language: StructLanguage) -> String {
+    let mut ident: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    let keywords = match language {
+        StructLanguage::C => C_KEYWORDS,
+        StructLanguage::Rust => RUST_KEYWORDS,
+    };
+    if keywords.contains(&ident.as_str()) {
+        ident.push('_');
+    }
+    ident
+}
+
+fn write_c_struct(
+    text: &mut String,
+    typedef: &TypedefStructure,
+    members: &[Member],
+    check_size: bool,
+) {
+    let name = identifier(&typedef.name, StructLanguage::C);
+    text.push('\n');
+    if !typedef.long_identifier.is_empty() {
+        let _ = writeln!(
+            text,
+            "/* {} */",
+            typedef.long_identifier.replace("*/", "* /")
+        );
+    }
+    let _ = writeln!(text, "typedef struct {name} {{");
+    for member in members {
+        let dims: String = member.dims.iter().map(|dim| format!("[{dim}]")).collect();
+        let _ = writeln!(
+            text,
+            "    {} {}{dims}; /* {} */",
+            member.type_name, member.name, member.comment
+        );
+    }
+    let _ = writeln!(text, "}} {name};");
+    if check_size {
+        let _ = writeln!(
+            text,
+            "_Static_assert(sizeof({name}) == {}, \"size of {name}\");",
+            typedef.total_size
+        );
+    }
+}
+
+fn write_rust_struct(
+    text: &mut String,
+    typedef: &TypedefStructure,
+    members: &[Member],
+    check_size: bool,
+) {
+    let name = identifier(&typedef.name, StructLanguage::Rust);
+    text.push('\n');
+    if !typedef.long_identifier.is_empty() {
+        let _ = writeln!(text, "/// {}", typedef.long_identifier);
+    }
+    text.push_str("#[repr(C, packed)]\n#[derive(Debug, Clone, Copy)]\n");
+    text.push_str("#[allow(non_camel_case_types, non_snake_case)]\n");
+    let _ = writeln!(text, "pub struct {name} {{");
+    for member in members {
+        let type_name = member
+            .dims
+            .iter()
+            .rev()
+            .fold(member.type_name.clone(), |inner, dim| {
+                format!("[{inner}; {dim}]")
+            });
+        let _ = writeln!(text, "    /// {}", member.comment);
+        let _ = writeln!(text, "    pub {}: {type_name},", member.name);
+    }
+    text.push_str("}\n");
+    if check_size {
+        let _ = writeln!(
+            text,
+            "const _: () = assert!(std::mem::size_of::<{name}>() == {});",
+            typedef.total_size
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_generate_structs() {
+        let a2l_text = r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin RECORD_LAYOUT RL_WORD
+                    FNC_VALUES 1 UWORD ROW_DIR DIRECT
+                /end RECORD_LAYOUT
+                /begin RECORD_LAYOUT RL_MAP
+                    NO_AXIS_PTS_X 1 UBYTE
+                    AXIS_PTS_X 2 UWORD INDEX_INCR DIRECT
+                    FNC_VALUES 3 UWORD ROW_DIR DIRECT
+                /end RECORD_LAYOUT
+                /begin TYPEDEF_MEASUREMENT T_speed "" FLOAT32_IEEE NO_COMPU_METHOD 0 0 0 100
+                /end TYPEDEF_MEASUREMENT
+                /begin TYPEDEF_CHARACTERISTIC T_table "" VAL_BLK RL_WORD 0 NO_COMPU_METHOD 0 100
+                    MATRIX_DIM 4 1 1
+                /end TYPEDEF_CHARACTERISTIC
+                /begin TYPEDEF_CHARACTERISTIC T_curve "" CURVE RL_MAP 0 NO_COMPU_METHOD 0 100
+                    /begin AXIS_DESCR STD_AXIS NO_INPUT_QUANTITY NO_COMPU_METHOD 3 0 10
+                    /end AXIS_DESCR
+                /end TYPEDEF_CHARACTERISTIC
+                /begin TYPEDEF_BLOB T_blob "" 3 /end TYPEDEF_BLOB
+                /begin TYPEDEF_STRUCTURE Outer "outer struct" 40
+                    /begin STRUCTURE_COMPONENT inner Inner 0 /end STRUCTURE_COMPONENT
+                    /begin STRUCTURE_COMPONENT speeds T_speed 8
+                        MATRIX_DIM 2
+                    /end STRUCTURE_COMPONENT
+                    /begin STRUCTURE_COMPONENT curve T_curve 16 /end STRUCTURE_COMPONENT
+                    /begin STRUCTURE_COMPONENT type T_blob 32 /end STRUCTURE_COMPONENT
+                /end TYPEDEF_STRUCTURE
+                /begin TYPEDEF_STRUCTURE Inner "" 8
+                    /begin STRUCTURE_COMPONENT table T_table 0 /end STRUCTURE_COMPONENT
+                /end TYPEDEF_STRUCTURE
+            /end MODULE
+        /end PROJECT"#;
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(a2l_text, None, &mut log_msgs, false).unwrap();
+
+        let mut log_msgs = Vec::new();
+        let text = generate_structs(&a2l_file, StructLanguage::C, &mut log_msgs);
+        // Inner is used by Outer and must be defined first
+        let inner_pos = text.find("typedef struct Inner {").unwrap();
+        let outer_pos = text.find("typedef struct Outer {").unwrap();
+        assert!(inner_pos < outer_pos);
+        assert!(text.contains("    uint16_t table[4]; /* offset 0x0 */\n} Inner;\n"));
+        assert!(text.contains(
+            "/* outer struct */\ntypedef struct Outer {\n    Inner inner; /* offset 0x0 */\n"
+        ));
+        assert!(text.contains("    float speeds[2]; /* offset 0x8 */\n"));
+        assert!(text.contains("    uint8_t curve[16]; /* offset 0x10, T_curve */\n"));
+        assert!(text.contains("    uint8_t type[3]; /* offset 0x20 */\n"));
+        assert!(text.contains("    uint8_t _reserved_23[5]; /* offset 0x23 */\n} Outer;\n"));
+        assert!(text.contains("_Static_assert(sizeof(Outer) == 40, \"size of Outer\");"));
+        assert_eq!(log_msgs.len(), 1);
+        assert!(log_msgs[0].contains("curve"));
+
+        let mut log_msgs = Vec::new();
+        let text = generate_structs(&a2l_file, StructLanguage::Rust, &mut log_msgs);
+        assert!(text.contains("#[repr(C, packed)]"));
+        assert!(text.contains("    /// offset 0x8\n    pub speeds: [f32; 2],\n"));
+        assert!(text.contains("    pub type_: [u8; 3],\n"));
+        assert!(text.contains("const _: () = assert!(std::mem::size_of::<Outer>() == 40);"));
+    }
+
+    #[test]
+    fn test_overlapping_components() {
+        let mut module = Module::new("m".to_string(), String::new());
+        let mut typedef = TypedefStructure::new("S".to_string(), String::new(), 4);
+        typedef.structure_component.push(StructureComponent::new(
+            "a".to_string(),
+            "T_ulong".to_string(),
+            0,
+        ));
+        typedef.structure_component.push(StructureComponent::new(
+            "b".to_string(),
+            "T_ulong".to_string(),
+            2,
+        ));
+        module
+            .typedef_measurement
+            .push(a2lfile::TypedefMeasurement::new(
+                "T_ulong".to_string(),
+                String::new(),
+                DataType::Ulong,
+                "NO_COMPU_METHOD".to_string(),
+                0,
+                0.0,
+                0.0,
+                100.0,
+            ));
+        module.typedef_structure.push(typedef);
+
+        let mut log_msgs = Vec::new();
+        let (members, size) = struct_members(
+            &module,
+            &module.typedef_structure[0],
+            StructLanguage::C,
+            &mut log_msgs,
+        );
+        assert_eq!(members.len(), 1);
+        assert_eq!(size, 4);
+        assert_eq!(members[0].name, "a");
+        assert_eq!(log_msgs.len(), 1);
+        assert!(log_msgs[0].contains("overlaps"));
+    }
+}
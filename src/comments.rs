@@ -0,0 +1,324 @@
+//! Preservation of the comments of an a2l file
+//!
+//! The parser discards all comments, so they are collected from the text of the input file separately.
+//! Each comment is attached to the block that follows it. When the output is written, the comments are
+//! inserted in front of the same blocks again.
+
+use std::collections::HashMap;
+
+/// The comments of an a2l file, attached to the blocks that follow them
+#[derive(Debug, Default)]
+pub struct Comments {
+    // comments before the first element of the file
+    header: Vec<String>,
+    // comments in front of the /begin or the /end of a block; the key identifies the block by its path in the file
+    blocks: HashMap<String, Vec<String>>,
+    // comments that are not followed by any block
+    trailer: Vec<String>,
+}
+
+enum Item<'a> {
+    Token(usize, &'a str),
+    Text,
+    Comment(&'a str),
+}
+
+// the /begin or /end of a block in the text, with its key and the comments in front of it
+struct Anchor<'a> {
+    offset: usize,
+    key: String,
+    is_end: bool,
+    comments: Vec<&'a str>,
+}
+
+#[derive(Default)]
+struct ScanResult<'a> {
+    header: Vec<&'a str>,
+    anchors: Vec<Anchor<'a>>,
+    trailer: Vec<&'a str>,
+}
+
+impl Comments {
+    /// Collect the comments from the text of an a2l file.
+    ///
+    /// The banner comment that was written by a2ltool is not collected, since a new banner is written with the output.
+    #[must_use]
+    pub fn collect(text: &str) -> Self {
+        let scan = scan(text.trim_start_matches('\u{feff}'));
+        let header = scan
+            .header
+            .iter()
+            .enumerate()
+            .filter(|(idx, comment)| !(*idx == 0 && comment.starts_with("/* a2ltool ")))
+            .map(|(_, comment)| (*comment).to_string())
+            .collect();
+        let blocks = scan
+            .anchors
+            .into_iter()
+            .filter(|anchor| !anchor.comments.is_empty())
+            .map(|anchor| {
+                let comments = anchor.comments.iter().map(|c| (*c).to_string()).collect();
+                (anchor.key, comments)
+            })
+            .collect();
+        let trailer = scan.trailer.iter().map(|c| (*c).to_string()).collect();
+        Self {
+            header,
+            blocks,
+            trailer,
+        }
+    }
+
+    /// Returns true if no comments were collected
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.header.is_empty() && self.blocks.is_empty() && self.trailer.is_empty()
+    }
+
+    /// Insert the collected comments into the text created by the a2l writer.
+    ///
+    /// Each comment is placed on its own line in front of the block it was attached to.
+    /// Comments of blocks that no longer exist are dropped.
+    #[must_use]
+    pub fn insert(&self, text: &str) -> String {
+        let mut output = String::with_capacity(text.len());
+        let mut last = 0;
+        // the first line of the writer output is empty, it is reserved for the banner
+        if text.starts_with('\n') {
+            output.push('\n');
+            last = 1;
+        }
+        if !self.header.is_empty() {
+            // the lines of the comments were empty lines in front of the first element when the file was loaded
+            let mut line_count = line_count(&self.header);
+            while line_count > 0 && text[last..].starts_with('\n') {
+                last += 1;
+                line_count -= 1;
+            }
+            for comment in &self.header {
+                output.push_str(comment);
+                output.push('\n');
+            }
+        }
+
+        for anchor in scan(text).anchors {
+            if let Some(comments) = self.blocks.get(&anchor.key) {
+                let line_start = text[..anchor.offset].rfind('\n').map_or(0, |pos| pos + 1);
+                let line = &text[line_start..anchor.offset];
+                let indent = &line[..line.len() - line.trim_start().len()];
+                let mut before = &text[last..line_start];
+                let mut line_count = line_count(comments);
+                while line_count > 0 && before.ends_with("\n\n") {
+                    before = &before[..before.len() - 1];
+                    line_count -= 1;
+                }
+                output.push_str(before);
+                for comment in comments {
+                    output.push_str(indent);
+                    if anchor.is_end {
+                        // the comment was inside of the block
+                        output.push_str("  ");
+                    }
+                    output.push_str(comment);
+                    output.push('\n');
+                }
+                last = line_start;
+            }
+        }
+        output.push_str(&text[last..]);
+
+        if !self.trailer.is_empty() && !output.ends_with('\n') {
+            output.push('\n');
+        }
+        for comment in &self.trailer {
+            output.push_str(comment);
+            output.push('\n');
+        }
+        output
+    }
+}
+
+fn line_count(comments: &[String]) -> usize {
+    comments.iter().map(|comment| comment.lines().count()).sum()
+}
+
+// split the text into tokens, strings and comments
+fn tokenize(text: &str) -> Vec<Item<'_>> {
+    let bytes = text.as_bytes();
+    let mut items = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        if bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        } else if bytes[pos..].starts_with(b"/*") {
+            let end = text[pos + 2..]
+                .find("*/")
+                .map_or(bytes.len(), |end| pos + 2 + end + 2);
+            items.push(Item::Comment(&text[pos..end]));
+            pos = end;
+        } else if bytes[pos..].starts_with(b"//") {
+            let end = text[pos..]
+                .find(['\r', '\n'])
+                .map_or(bytes.len(), |end| pos + end);
+            items.push(Item::Comment(&text[pos..end]));
+            pos = end;
+        } else if bytes[pos] == b'"' {
+            // strings may contain escaped quotes \" and doubled quotes ""
+            pos += 1;
+            while pos < bytes.len() {
+                if bytes[pos] == b'\\' {
+                    pos += 2;
+                } else if bytes[pos] == b'"' {
+                    pos += 1;
+                    if pos < bytes.len() && bytes[pos] == b'"' {
+                        pos += 1;
+                    } else {
+                        break;
+                    }
+                } else {
+                    pos += 1;
+                }
+            }
+            pos = pos.min(bytes.len());
+            items.push(Item::Text);
+        } else {
+            let start = pos;
+            while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() && bytes[pos] != b'"' {
+                pos += 1;
+            }
+            items.push(Item::Token(start, &text[start..pos]));
+        }
+    }
+    items
+}
+
+// find the /begin and /end of all blocks of the text and the comments in front of them.
+// Each block is identified by the kinds and names of all enclosing blocks, and by the number of blocks
+// with the same path before it, e.g. "PROJECT p/MODULE m/MEASUREMENT x/IF_DATA CANAPE_EXT#0".
+// The key of the /end of a block is the key of the block followed by "/end"
+fn scan(text: &str) -> ScanResult<'_> {
+    let items = tokenize(text);
+    let mut result = ScanResult::default();
+    let mut path: Vec<String> = Vec::new();
+    let mut keys: Vec<String> = Vec::new();
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut pending = Vec::new();
+    let mut seen_token = false;
+    // the content of the A2ML block is kept as text by the parser, including its comments
+    let mut in_a2ml = false;
+
+    for (idx, item) in items.iter().enumerate() {
+        match item {
+            Item::Comment(comment) => {
+                if in_a2ml {
+                    // the comment is part of the A2ML text
+                } else if seen_token {
+                    pending.push(*comment);
+                } else {
+                    result.header.push(*comment);
+                }
+            }
+            Item::Text => seen_token = true,
+            Item::Token(offset, "/begin") => {
+                seen_token = true;
+                if in_a2ml {
+                    continue;
+                }
+                let kind = match items.get(idx + 1) {
+                    Some(Item::Token(_, kind)) => *kind,
+                    _ => "",
+                };
+                let element = match items.get(idx + 2) {
+                    Some(Item::Token(_, name)) if !name.starts_with('/') && kind != "A2ML" => {
+                        format!("{kind} {name}")
+                    }
+                    _ => kind.to_string(),
+                };
+                path.push(element);
+                let block_path = path.join("/");
+                let count = counts.entry(block_path.clone()).or_default();
+                let key = format!("{block_path}#{count}");
+                *count += 1;
+                result.anchors.push(Anchor {
+                    offset: *offset,
+                    key: key.clone(),
+                    is_end: false,
+                    comments: std::mem::take(&mut pending),
+                });
+                keys.push(key);
+                in_a2ml = kind == "A2ML";
+            }
+            Item::Token(offset, "/end") => {
+                in_a2ml = false;
+                path.pop();
+                if let Some(key) = keys.pop() {
+                    result.anchors.push(Anchor {
+                        offset: *offset,
+                        key: format!("{key}/end"),
+                        is_end: true,
+                        comments: std::mem::take(&mut pending),
+                    });
+                }
+            }
+            Item::Token(..) => seen_token = true,
+        }
+    }
+    result.trailer = pending;
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_comments() {
+        let input = r#"/* a2ltool 2.0.1 */
+// file comment
+ASAP2_VERSION 1 71
+/begin PROJECT p ""
+  /begin MODULE m ""
+    /begin A2ML
+      /* part of the A2ML */
+      block "IF_DATA" taggedstruct { "X" uint; };
+    /end A2ML
+    // measurement comment
+    /* with "quotes" */
+    /begin MEASUREMENT m1 "// not a comment" UBYTE NO_COMPU_METHOD 0 0 0 255
+      /begin IF_DATA X 1 /end IF_DATA
+      ECU_ADDRESS 0x1000 // end comment
+    /end MEASUREMENT
+    /begin MEASUREMENT m2 "" UBYTE NO_COMPU_METHOD 0 0 0 255
+      // if_data comment
+      /begin IF_DATA X 2 /end IF_DATA
+    /end MEASUREMENT
+  /end MODULE
+/end PROJECT
+// trailing comment
+"#;
+        let comments = Comments::collect(input);
+        assert!(!comments.is_empty());
+        assert_eq!(comments.header, vec!["// file comment"]);
+        assert_eq!(
+            comments.blocks["PROJECT p/MODULE m/MEASUREMENT m1#0"],
+            vec!["// measurement comment", "/* with \"quotes\" */"]
+        );
+        assert_eq!(
+            comments.blocks["PROJECT p/MODULE m/MEASUREMENT m2/IF_DATA X#0"],
+            vec!["// if_data comment"]
+        );
+        assert_eq!(
+            comments.blocks["PROJECT p/MODULE m/MEASUREMENT m1#0/end"],
+            vec!["// end comment"]
+        );
+        assert_eq!(comments.blocks.len(), 3);
+        assert_eq!(comments.trailer, vec!["// trailing comment"]);
+
+        // the comments are inserted into the writer output, even if the order of the blocks changed
+        let output = "\nASAP2_VERSION 1 71\n/begin PROJECT p \"\"\n  /begin MODULE m \"\"\n    /begin MEASUREMENT m2 \"\" UBYTE NO_COMPU_METHOD 0 0 0 255\n      /begin IF_DATA X 2\n      /end IF_DATA\n    /end MEASUREMENT\n    /begin MEASUREMENT m1 \"\" UBYTE NO_COMPU_METHOD 0 0 0 255\n    /end MEASUREMENT\n  /end MODULE\n/end PROJECT";
+        let expected = "\n// file comment\nASAP2_VERSION 1 71\n/begin PROJECT p \"\"\n  /begin MODULE m \"\"\n    /begin MEASUREMENT m2 \"\" UBYTE NO_COMPU_METHOD 0 0 0 255\n      // if_data comment\n      /begin IF_DATA X 2\n      /end IF_DATA\n    /end MEASUREMENT\n    // measurement comment\n    /* with \"quotes\" */\n    /begin MEASUREMENT m1 \"\" UBYTE NO_COMPU_METHOD 0 0 0 255\n      // end comment\n    /end MEASUREMENT\n  /end MODULE\n/end PROJECT\n// trailing comment\n";
+        assert_eq!(comments.insert(output), expected);
+
+        assert!(Comments::collect(output).is_empty());
+    }
+}
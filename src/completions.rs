@@ -0,0 +1,25 @@
+use clap::Command;
+use clap_complete::Shell;
+
+// generate a completion script for the given shell from the clap definition of the command line
+pub(crate) fn generate(shell: Shell, mut cmd: Command) -> String {
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut cmd, "a2ltool", &mut script);
+    String::from_utf8_lossy(&script).into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_generate_completions() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish, Shell::PowerShell] {
+            let script = generate(shell, crate::build_command());
+            assert!(script.contains("elffile"));
+            assert!(script.contains("completions"));
+        }
+        let script = generate(Shell::Bash, crate::build_command());
+        assert!(script.contains("_a2ltool"));
+    }
+}
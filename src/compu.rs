@@ -0,0 +1,350 @@
+//! Evaluation of COMPU_METHODs: conversion between raw ECU values and physical values
+
+use a2lfile::{CompuMethod, ConversionType, Module};
+use std::fmt::Display;
+
+/// A physical value, which is either a number or the text of a verbal conversion table
+#[derive(Debug, Clone, PartialEq)]
+pub enum PhysValue {
+    Number(f64),
+    Text(String),
+}
+
+impl Display for PhysValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PhysValue::Number(value) => write!(f, "{value}"),
+            PhysValue::Text(text) => write!(f, "\"{text}\""),
+        }
+    }
+}
+
+/// Convert a raw value to a physical value using the named COMPU_METHOD.
+///
+/// `NO_COMPU_METHOD` and unknown names use the identity conversion.
+/// Returns None if the conversion cannot be evaluated, e.g. for a FORMULA that is not valid.
+#[must_use]
+pub fn raw_to_phys(module: &Module, conversion: &str, raw: f64) -> Option<PhysValue> {
+    let Some(compu_method) = find_compu_method(module, conversion) else {
+        return Some(PhysValue::Number(raw));
+    };
+    match compu_method.conversion_type {
+        ConversionType::Identical => Some(PhysValue::Number(raw)),
+        ConversionType::Linear => {
+            let coeffs = compu_method.coeffs_linear.as_ref()?;
+            Some(PhysValue::Number(coeffs.a * raw + coeffs.b))
+        }
+        ConversionType::RatFunc => {
+            // RAT_FUNC describes phys -> raw: raw = (a*x^2 + b*x + c) / (d*x^2 + e*x + f)
+            // Only the common case without quadratic terms can be inverted
+            let c = compu_method.coeffs.as_ref()?;
+            if c.a != 0.0 || c.d != 0.0 {
+                return None;
+            }
+            let divisor = raw * c.e - c.b;
+            (divisor != 0.0).then(|| PhysValue::Number((c.c - raw * c.f) / divisor))
+        }
+        ConversionType::TabIntp | ConversionType::TabNointp => {
+            let tab_name = &compu_method.compu_tab_ref.as_ref()?.conversion_table;
+            let compu_tab = module.compu_tab.iter().find(|tab| &tab.name == tab_name)?;
+            let mut entries: Vec<(f64, f64)> = compu_tab
+                .tab_entry
+                .iter()
+                .map(|entry| (entry.in_val, entry.out_val))
+                .collect();
+            entries.sort_by(|a, b| a.0.total_cmp(&b.0));
+            let default = compu_tab
+                .default_value_numeric
+                .as_ref()
+                .map(|default| default.display_value);
+            let interpolate = compu_method.conversion_type == ConversionType::TabIntp;
+            lookup_table(&entries, raw, interpolate)
+                .or(default)
+                .map(PhysValue::Number)
+        }
+        ConversionType::TabVerb => {
+            let tab_name = &compu_method.compu_tab_ref.as_ref()?.conversion_table;
+            if let Some(vtab) = module.compu_vtab.iter().find(|tab| &tab.name == tab_name) {
+                vtab.value_pairs
+                    .iter()
+                    .find(|pair| pair.in_val == raw)
+                    .map(|pair| pair.out_val.clone())
+                    .or_else(|| {
+                        vtab.default_value
+                            .as_ref()
+                            .map(|d| d.display_string.clone())
+                    })
+                    .map(PhysValue::Text)
+            } else {
+                let vtab_range = module
+                    .compu_vtab_range
+                    .iter()
+                    .find(|tab| &tab.name == tab_name)?;
+                vtab_range
+                    .value_triples
+                    .iter()
+                    .find(|triple| triple.in_val_min <= raw && raw <= triple.in_val_max)
+                    .map(|triple| triple.out_val.clone())
+                    .or_else(|| {
+                        vtab_range
+                            .default_value
+                            .as_ref()
+                            .map(|d| d.display_string.clone())
+                    })
+                    .map(PhysValue::Text)
+            }
+        }
+        ConversionType::Form => {
+            let formula = compu_method.formula.as_ref()?;
+            let expression = crate::formula::parse_formula(&formula.fx).ok()?;
+            expression.evaluate(&[raw]).map(PhysValue::Number)
+        }
+    }
+}
+
+/// Convert a physical value to a raw value using the named COMPU_METHOD.
+///
+/// This is the inverse of [`raw_to_phys`]. Returns None if the conversion cannot be inverted.
+#[must_use]
+pub fn phys_to_raw(module: &Module, conversion: &str, phys: &PhysValue) -> Option<f64> {
+    let Some(compu_method) = find_compu_method(module, conversion) else {
+        return phys.as_number();
+    };
+    match compu_method.conversion_type {
+        ConversionType::Identical => phys.as_number(),
+        ConversionType::Linear => {
+            let coeffs = compu_method.coeffs_linear.as_ref()?;
+            let x = phys.as_number()?;
+            (coeffs.a != 0.0).then(|| (x - coeffs.b) / coeffs.a)
+        }
+        ConversionType::RatFunc => {
+            let c = compu_method.coeffs.as_ref()?;
+            let x = phys.as_number()?;
+            let divisor = c.d * x * x + c.e * x + c.f;
+            (divisor != 0.0).then(|| (c.a * x * x + c.b * x + c.c) / divisor)
+        }
+        ConversionType::TabIntp | ConversionType::TabNointp => {
+            let tab_name = &compu_method.compu_tab_ref.as_ref()?.conversion_table;
+            let compu_tab = module.compu_tab.iter().find(|tab| &tab.name == tab_name)?;
+            // swap in and out values to search the table in the reverse direction
+            let mut entries: Vec<(f64, f64)> = compu_tab
+                .tab_entry
+                .iter()
+                .map(|entry| (entry.out_val, entry.in_val))
+                .collect();
+            entries.sort_by(|a, b| a.0.total_cmp(&b.0));
+            let interpolate = compu_method.conversion_type == ConversionType::TabIntp;
+            lookup_table(&entries, phys.as_number()?, interpolate)
+        }
+        ConversionType::TabVerb => {
+            let tab_name = &compu_method.compu_tab_ref.as_ref()?.conversion_table;
+            match phys {
+                PhysValue::Text(text) => {
+                    if let Some(vtab) = module.compu_vtab.iter().find(|tab| &tab.name == tab_name) {
+                        vtab.value_pairs
+                            .iter()
+                            .find(|pair| &pair.out_val == text)
+                            .map(|pair| pair.in_val)
+                    } else {
+                        module
+                            .compu_vtab_range
+                            .iter()
+                            .find(|tab| &tab.name == tab_name)?
+                            .value_triples
+                            .iter()
+                            .find(|triple| &triple.out_val == text)
+                            .map(|triple| triple.in_val_min)
+                    }
+                }
+                // calibration data files sometimes contain the raw value instead of the text
+                PhysValue::Number(value) => Some(*value),
+            }
+        }
+        ConversionType::Form => {
+            // a FORMULA can only be inverted if FORMULA_INV is present
+            let formula_inv = compu_method.formula.as_ref()?.formula_inv.as_ref()?;
+            let expression = crate::formula::parse_formula(&formula_inv.gx).ok()?;
+            expression.evaluate(&[phys.as_number()?])
+        }
+    }
+}
+
+/// Find the COMPU_METHOD that is used by the named object.
+///
+/// The name can be a COMPU_METHOD, a MEASUREMENT, a CHARACTERISTIC or an AXIS_PTS.
+///
+/// # Errors
+///
+/// Returns an error message if no such object exists
+pub fn find_conversion<'a>(module: &'a Module, name: &str) -> Result<&'a str, String> {
+    if let Some(compu_method) = find_compu_method(module, name) {
+        Ok(&compu_method.name)
+    } else if let Some(measurement) = module.measurement.iter().find(|m| m.name == name) {
+        Ok(&measurement.conversion)
+    } else if let Some(characteristic) = module.characteristic.iter().find(|c| c.name == name) {
+        Ok(&characteristic.conversion)
+    } else if let Some(axis_pts) = module.axis_pts.iter().find(|a| a.name == name) {
+        Ok(&axis_pts.conversion)
+    } else {
+        Err(format!(
+            "Error: there is no COMPU_METHOD, MEASUREMENT, CHARACTERISTIC or AXIS_PTS named {name}"
+        ))
+    }
+}
+
+/// Convert the raw and physical values given as text using the COMPU_METHOD of the named object.
+///
+/// Raw values can be decimal or hex numbers. Physical values that are not numbers are treated as
+/// texts of a verbal conversion table. Returns one line of text for each value.
+///
+/// # Errors
+///
+/// Returns an error message if there is no such object, or if a raw value is not a number
+pub fn convert_values(
+    module: &Module,
+    name: &str,
+    raw_values: &[&str],
+    phys_values: &[&str],
+) -> Result<Vec<String>, String> {
+    let conversion = find_conversion(module, name)?;
+    let mut lines = Vec::new();
+    for raw_text in raw_values {
+        let raw = parse_raw_value(raw_text)?;
+        lines.push(match raw_to_phys(module, conversion, raw) {
+            Some(phys) => format!("{name}: raw {raw} -> phys {phys}"),
+            None => format!("{name}: raw {raw} cannot be converted by {conversion}"),
+        });
+    }
+    for phys_text in phys_values {
+        let phys = phys_text.trim().parse::<f64>().map_or_else(
+            |_| PhysValue::Text((*phys_text).to_string()),
+            PhysValue::Number,
+        );
+        lines.push(match phys_to_raw(module, conversion, &phys) {
+            Some(raw) => format!("{name}: phys {phys} -> raw {raw}"),
+            None => format!("{name}: phys {phys} cannot be converted by {conversion}"),
+        });
+    }
+    Ok(lines)
+}
+
+// parse a raw value as a decimal or hex number
+fn parse_raw_value(text: &str) -> Result<f64, String> {
+    let text = text.trim();
+    let value = if let Some(hex) = text.strip_prefix("0x") {
+        u64::from_str_radix(hex, 16).ok().map(|num| num as f64)
+    } else {
+        text.parse::<f64>().ok()
+    };
+    value.ok_or_else(|| format!("Error: \"{text}\" is not a valid raw value"))
+}
+
+impl PhysValue {
+    #[must_use]
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            PhysValue::Number(value) => Some(*value),
+            PhysValue::Text(_) => None,
+        }
+    }
+}
+
+fn find_compu_method<'a>(module: &'a Module, name: &str) -> Option<&'a CompuMethod> {
+    module.compu_method.iter().find(|cm| cm.name == name)
+}
+
+// look up a value in a table of (in, out) pairs that is sorted by the in values.
+// Without interpolation only exact matches are found. Values outside the table are clamped.
+fn lookup_table(entries: &[(f64, f64)], value: f64, interpolate: bool) -> Option<f64> {
+    if !interpolate {
+        return entries
+            .iter()
+            .find(|(in_val, _)| *in_val == value)
+            .map(|(_, out_val)| *out_val);
+    }
+    let first = entries.first()?;
+    let last = entries.last()?;
+    if value <= first.0 {
+        return Some(first.1);
+    }
+    if value >= last.0 {
+        return Some(last.1);
+    }
+    let idx = entries.partition_point(|(in_val, _)| *in_val <= value);
+    let (x0, y0) = entries[idx - 1];
+    let (x1, y1) = entries[idx];
+    Some(y0 + (y1 - y0) * (value - x0) / (x1 - x0))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        let a2l_text = r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin COMPU_METHOD cm_lin "" LINEAR "%6.2" "rpm"
+                    COEFFS_LINEAR 0.5 10
+                /end COMPU_METHOD
+                /begin COMPU_METHOD cm_rat "" RAT_FUNC "%6.2" "km/h"
+                    COEFFS 0 100 0 0 0 1
+                /end COMPU_METHOD
+                /begin COMPU_METHOD cm_tab "" TAB_INTP "%6.2" ""
+                    COMPU_TAB_REF tab
+                /end COMPU_METHOD
+                /begin COMPU_METHOD cm_verb "" TAB_VERB "%6.2" ""
+                    COMPU_TAB_REF vtab
+                /end COMPU_METHOD
+                /begin COMPU_METHOD cm_form "" FORM "%6.2" ""
+                    /begin FORMULA "X1 * 2 - 4"
+                        FORMULA_INV "(X1 + 4) / 2"
+                    /end FORMULA
+                /end COMPU_METHOD
+                /begin MEASUREMENT meas "" UBYTE cm_form 0 0 0 255
+                /end MEASUREMENT
+                /begin COMPU_TAB tab "" TAB_INTP 2 0 0 10 100
+                /end COMPU_TAB
+                /begin COMPU_VTAB vtab "" TAB_VERB 2 0 "off" 1 "on"
+                    DEFAULT_VALUE "invalid"
+                /end COMPU_VTAB
+            /end MODULE
+        /end PROJECT"#;
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(a2l_text, None, &mut log_msgs, false).unwrap();
+        let module = &a2l_file.project.module[0];
+
+        let number = PhysValue::Number;
+        assert_eq!(
+            raw_to_phys(module, "NO_COMPU_METHOD", 7.0),
+            Some(number(7.0))
+        );
+        assert_eq!(raw_to_phys(module, "cm_lin", 4.0), Some(number(12.0)));
+        assert_eq!(phys_to_raw(module, "cm_lin", &number(12.0)), Some(4.0));
+        assert_eq!(raw_to_phys(module, "cm_rat", 500.0), Some(number(5.0)));
+        assert_eq!(phys_to_raw(module, "cm_rat", &number(5.0)), Some(500.0));
+        assert_eq!(raw_to_phys(module, "cm_tab", 5.0), Some(number(50.0)));
+        assert_eq!(phys_to_raw(module, "cm_tab", &number(50.0)), Some(5.0));
+        let text = |t: &str| PhysValue::Text(t.to_string());
+        assert_eq!(raw_to_phys(module, "cm_verb", 1.0), Some(text("on")));
+        assert_eq!(raw_to_phys(module, "cm_verb", 5.0), Some(text("invalid")));
+        assert_eq!(phys_to_raw(module, "cm_verb", &text("off")), Some(0.0));
+        assert_eq!(raw_to_phys(module, "cm_form", 5.0), Some(number(6.0)));
+        assert_eq!(phys_to_raw(module, "cm_form", &number(6.0)), Some(5.0));
+        assert_eq!(find_conversion(module, "meas"), Ok("cm_form"));
+        assert!(find_conversion(module, "unknown").is_err());
+
+        let lines = convert_values(module, "meas", &["0x05"], &["6", "on"]).unwrap();
+        assert_eq!(
+            lines,
+            vec![
+                "meas: raw 5 -> phys 6".to_string(),
+                "meas: phys 6 -> raw 5".to_string(),
+                "meas: phys \"on\" cannot be converted by cm_form".to_string(),
+            ]
+        );
+        assert!(convert_values(module, "meas", &["five"], &[]).is_err());
+    }
+}
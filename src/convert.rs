@@ -0,0 +1,528 @@
+//! Conversion of objects between MEASUREMENT and CHARACTERISTIC
+
+use crate::query::compile_regex;
+use a2lfile::{
+    A2lFile, A2lObject, AddrType, Characteristic, CharacteristicType, DataType, DefCharacteristic,
+    EcuAddress, FncValues, InMeasurement, IndexMode, LocMeasurement, MatrixDim, Measurement,
+    Module, RecordLayout, RefCharacteristic, RefMeasurement,
+};
+use std::collections::HashSet;
+
+/// Convert all MEASUREMENTs whose name matches the regex into CHARACTERISTICs.
+///
+/// Scalar MEASUREMENTs become VALUE characteristics and arrays become VAL_BLK characteristics.
+/// A RECORD_LAYOUT for the data type is created if it doesn't exist yet, and the references in
+/// GROUPs and FUNCTIONs are moved to the lists for characteristics.
+/// Virtual MEASUREMENTs and MEASUREMENTs with an ADDRESS_TYPE are not converted.
+///
+/// Returns the number of converted objects.
+///
+/// # Errors
+///
+/// Returns an error message if the regex is invalid
+pub fn convert_to_characteristic(
+    a2l_file: &mut A2lFile,
+    regex: &str,
+    log_msgs: &mut Vec<String>,
+) -> Result<usize, String> {
+    let regex = compile_regex(regex)?;
+    let mut count = 0;
+    for module in &mut a2l_file.project.module {
+        let mut converted = HashSet::new();
+        let measurements = std::mem::take(&mut module.measurement);
+        for measurement in measurements {
+            if !regex.is_match(&measurement.name) {
+                module.measurement.push(measurement);
+            } else if measurement.var_virtual.is_some() || measurement.address_type.is_some() {
+                log_msgs.push(format!(
+                    "MEASUREMENT {} on line {} is virtual or a pointer and can't be converted to a CHARACTERISTIC",
+                    measurement.name,
+                    measurement.get_line()
+                ));
+                module.measurement.push(measurement);
+            } else {
+                converted.insert(measurement.name.clone());
+                let characteristic = measurement_to_characteristic(module, measurement);
+                module.characteristic.push(characteristic);
+            }
+        }
+        move_references_to_characteristic(module, &converted);
+        count += converted.len();
+    }
+    Ok(count)
+}
+
+/// Convert all CHARACTERISTICs whose name matches the regex into MEASUREMENTs.
+///
+/// Only VALUE and VAL_BLK characteristics can be converted. The data type is taken from the
+/// FNC_VALUES of the RECORD_LAYOUT, which is deleted if it is no longer used.
+/// The references in GROUPs and FUNCTIONs are moved to the lists for measurements.
+///
+/// Returns the number of converted objects.
+///
+/// # Errors
+///
+/// Returns an error message if the regex is invalid
+pub fn convert_to_measurement(
+    a2l_file: &mut A2lFile,
+    regex: &str,
+    log_msgs: &mut Vec<String>,
+) -> Result<usize, String> {
+    let regex = compile_regex(regex)?;
+    let mut count = 0;
+    for module in &mut a2l_file.project.module {
+        let mut converted = HashSet::new();
+        let mut deposits = HashSet::new();
+        let characteristics = std::mem::take(&mut module.characteristic);
+        for characteristic in characteristics {
+            if !regex.is_match(&characteristic.name) {
+                module.characteristic.push(characteristic);
+                continue;
+            }
+            let datatype = get_fnc_values_datatype(module, &characteristic.deposit);
+            match (characteristic.characteristic_type, datatype) {
+                (CharacteristicType::Value | CharacteristicType::ValBlk, Some(datatype)) => {
+                    converted.insert(characteristic.name.clone());
+                    deposits.insert(characteristic.deposit.clone());
+                    let measurement = characteristic_to_measurement(characteristic, datatype);
+                    module.measurement.push(measurement);
+                }
+                (CharacteristicType::Value | CharacteristicType::ValBlk, None) => {
+                    log_msgs.push(format!(
+                        "CHARACTERISTIC {} on line {} can't be converted to a MEASUREMENT: RECORD_LAYOUT {} has no FNC_VALUES",
+                        characteristic.name,
+                        characteristic.get_line(),
+                        characteristic.deposit
+                    ));
+                    module.characteristic.push(characteristic);
+                }
+                (ctype, _) => {
+                    log_msgs.push(format!(
+                        "CHARACTERISTIC {} on line {} can't be converted to a MEASUREMENT: type {ctype} is not supported",
+                        characteristic.name,
+                        characteristic.get_line()
+                    ));
+                    module.characteristic.push(characteristic);
+                }
+            }
+        }
+        remove_unused_record_layouts(module, &deposits);
+        move_references_to_measurement(module, &converted);
+        count += converted.len();
+    }
+    Ok(count)
+}
+
+fn measurement_to_characteristic(module: &mut Module, measurement: Measurement) -> Characteristic {
+    let mut matrix_dim = measurement.matrix_dim;
+    if matrix_dim.is_none() {
+        // ARRAY_SIZE is deprecated, and only MATRIX_DIM is available in a CHARACTERISTIC
+        if let Some(array_size) = &measurement.array_size {
+            let mut new_matrix_dim = MatrixDim::new();
+            new_matrix_dim.dim_list = vec![array_size.number];
+            matrix_dim = Some(new_matrix_dim);
+        }
+    }
+    let is_array = matrix_dim
+        .as_ref()
+        .is_some_and(|md| md.dim_list.iter().any(|dim| *dim > 1));
+    let ctype = if is_array {
+        CharacteristicType::ValBlk
+    } else {
+        CharacteristicType::Value
+    };
+    let deposit = get_or_create_record_layout(module, measurement.datatype);
+    let address = measurement.ecu_address.as_ref().map_or(0, |ea| ea.address);
+
+    let mut characteristic = Characteristic::new(
+        measurement.name,
+        measurement.long_identifier,
+        ctype,
+        address,
+        deposit,
+        0f64,
+        measurement.conversion,
+        measurement.lower_limit,
+        measurement.upper_limit,
+    );
+    // enable hex mode for the address (item 3 in the CHARACTERISTIC)
+    characteristic.get_layout_mut().item_location.3 .1 = true;
+    characteristic.annotation = measurement.annotation;
+    characteristic.bit_mask = measurement.bit_mask;
+    characteristic.byte_order = measurement.byte_order;
+    characteristic.discrete = measurement.discrete;
+    characteristic.display_identifier = measurement.display_identifier;
+    characteristic.ecu_address_extension = measurement.ecu_address_extension;
+    characteristic.format = measurement.format;
+    characteristic.function_list = measurement.function_list;
+    characteristic.if_data = measurement.if_data;
+    characteristic.matrix_dim = matrix_dim;
+    characteristic.max_refresh = measurement.max_refresh;
+    characteristic.model_link = measurement.model_link;
+    characteristic.phys_unit = measurement.phys_unit;
+    characteristic.ref_memory_segment = measurement.ref_memory_segment;
+    characteristic.symbol_link = measurement.symbol_link;
+    characteristic
+}
+
+fn characteristic_to_measurement(
+    characteristic: Characteristic,
+    datatype: DataType,
+) -> Measurement {
+    let mut matrix_dim = characteristic.matrix_dim;
+    if matrix_dim.is_none() {
+        // old files describe the size of a VAL_BLK with NUMBER
+        if let Some(number) = &characteristic.number {
+            let mut new_matrix_dim = MatrixDim::new();
+            new_matrix_dim.dim_list = vec![number.number];
+            matrix_dim = Some(new_matrix_dim);
+        }
+    }
+
+    let mut measurement = Measurement::new(
+        characteristic.name,
+        characteristic.long_identifier,
+        datatype,
+        characteristic.conversion,
+        0,
+        0f64,
+        characteristic.lower_limit,
+        characteristic.upper_limit,
+    );
+    // create an ECU_ADDRESS attribute, and set it to hex display mode
+    let mut ecu_address = EcuAddress::new(characteristic.address);
+    ecu_address.get_layout_mut().item_location.0 .1 = true;
+    measurement.ecu_address = Some(ecu_address);
+    measurement.annotation = characteristic.annotation;
+    measurement.bit_mask = characteristic.bit_mask;
+    measurement.byte_order = characteristic.byte_order;
+    measurement.discrete = characteristic.discrete;
+    measurement.display_identifier = characteristic.display_identifier;
+    measurement.ecu_address_extension = characteristic.ecu_address_extension;
+    measurement.format = characteristic.format;
+    measurement.function_list = characteristic.function_list;
+    measurement.if_data = characteristic.if_data;
+    measurement.matrix_dim = matrix_dim;
+    measurement.max_refresh = characteristic.max_refresh;
+    measurement.model_link = characteristic.model_link;
+    measurement.phys_unit = characteristic.phys_unit;
+    measurement.ref_memory_segment = characteristic.ref_memory_segment;
+    measurement.symbol_link = characteristic.symbol_link;
+    measurement
+}
+
+// find the data type of the FNC_VALUES in the named RECORD_LAYOUT
+fn get_fnc_values_datatype(module: &Module, deposit: &str) -> Option<DataType> {
+    module
+        .record_layout
+        .iter()
+        .find(|rl| rl.name == deposit)
+        .and_then(|rl| rl.fnc_values.as_ref())
+        .map(|fnc_values| fnc_values.datatype)
+}
+
+// use the RECORD_LAYOUT __<type>_Z, which is also used when inserting characteristics, and create it if needed
+fn get_or_create_record_layout(module: &mut Module, datatype: DataType) -> String {
+    let recordlayout_name = format!("__{datatype}_Z");
+    if !module
+        .record_layout
+        .iter()
+        .any(|rl| rl.name == recordlayout_name)
+    {
+        let mut recordlayout = RecordLayout::new(recordlayout_name.clone());
+        // set item 0 (name) to use an offset of 0 lines, i.e. no line break after /begin RECORD_LAYOUT
+        recordlayout.get_layout_mut().item_location.0 = 0;
+        recordlayout.fnc_values = Some(FncValues::new(
+            1,
+            datatype,
+            IndexMode::RowDir,
+            AddrType::Direct,
+        ));
+        module.record_layout.push(recordlayout);
+    }
+    recordlayout_name
+}
+
+// delete the RECORD_LAYOUTs that were used by converted characteristics, if nothing else uses them
+fn remove_unused_record_layouts(module: &mut Module, deposits: &HashSet<String>) {
+    let mut used: HashSet<&str> = module
+        .characteristic
+        .iter()
+        .map(|chr| chr.deposit.as_str())
+        .collect();
+    used.extend(module.axis_pts.iter().map(|ap| ap.deposit_record.as_str()));
+    used.extend(
+        module
+            .typedef_characteristic
+            .iter()
+            .map(|td| td.record_layout.as_str()),
+    );
+    used.extend(
+        module
+            .typedef_axis
+            .iter()
+            .map(|td| td.record_layout.as_str()),
+    );
+    used.extend(
+        module
+            .mod_common
+            .iter()
+            .filter_map(|mc| mc.s_rec_layout.as_ref())
+            .map(|srl| srl.name.as_str()),
+    );
+    let unused: HashSet<String> = deposits
+        .iter()
+        .filter(|name| !used.contains(name.as_str()))
+        .cloned()
+        .collect();
+    module.record_layout.retain(|rl| !unused.contains(&rl.name));
+}
+
+fn move_references_to_characteristic(module: &mut Module, converted: &HashSet<String>) {
+    if converted.is_empty() {
+        return;
+    }
+    for group in &mut module.group {
+        let moved = take_names(
+            group
+                .ref_measurement
+                .as_mut()
+                .map(|rm| &mut rm.identifier_list),
+            converted,
+        );
+        if !moved.is_empty() {
+            group
+                .ref_characteristic
+                .get_or_insert_with(RefCharacteristic::new)
+                .identifier_list
+                .extend(moved);
+        }
+        if group
+            .ref_measurement
+            .as_ref()
+            .is_some_and(|rm| rm.identifier_list.is_empty())
+        {
+            group.ref_measurement = None;
+        }
+    }
+    for function in &mut module.function {
+        // inputs of the function are referenced characteristics, all other measurements are defined by the function
+        let referenced = take_names(
+            function
+                .in_measurement
+                .as_mut()
+                .map(|im| &mut im.identifier_list),
+            converted,
+        );
+        let mut defined = take_names(
+            function
+                .out_measurement
+                .as_mut()
+                .map(|om| &mut om.identifier_list),
+            converted,
+        );
+        defined.extend(take_names(
+            function
+                .loc_measurement
+                .as_mut()
+                .map(|lm| &mut lm.identifier_list),
+            converted,
+        ));
+        if !referenced.is_empty() {
+            function
+                .ref_characteristic
+                .get_or_insert_with(RefCharacteristic::new)
+                .identifier_list
+                .extend(referenced);
+        }
+        if !defined.is_empty() {
+            function
+                .def_characteristic
+                .get_or_insert_with(DefCharacteristic::new)
+                .identifier_list
+                .extend(defined);
+        }
+        if function
+            .in_measurement
+            .as_ref()
+            .is_some_and(|im| im.identifier_list.is_empty())
+        {
+            function.in_measurement = None;
+        }
+        if function
+            .out_measurement
+            .as_ref()
+            .is_some_and(|om| om.identifier_list.is_empty())
+        {
+            function.out_measurement = None;
+        }
+        if function
+            .loc_measurement
+            .as_ref()
+            .is_some_and(|lm| lm.identifier_list.is_empty())
+        {
+            function.loc_measurement = None;
+        }
+    }
+}
+
+fn move_references_to_measurement(module: &mut Module, converted: &HashSet<String>) {
+    if converted.is_empty() {
+        return;
+    }
+    for group in &mut module.group {
+        let moved = take_names(
+            group
+                .ref_characteristic
+                .as_mut()
+                .map(|rc| &mut rc.identifier_list),
+            converted,
+        );
+        if !moved.is_empty() {
+            group
+                .ref_measurement
+                .get_or_insert_with(RefMeasurement::new)
+                .identifier_list
+                .extend(moved);
+        }
+        if group
+            .ref_characteristic
+            .as_ref()
+            .is_some_and(|rc| rc.identifier_list.is_empty())
+        {
+            group.ref_characteristic = None;
+        }
+    }
+    for function in &mut module.function {
+        // referenced characteristics are inputs of the function, defined characteristics are local
+        let referenced = take_names(
+            function
+                .ref_characteristic
+                .as_mut()
+                .map(|rc| &mut rc.identifier_list),
+            converted,
+        );
+        let defined = take_names(
+            function
+                .def_characteristic
+                .as_mut()
+                .map(|dc| &mut dc.identifier_list),
+            converted,
+        );
+        if !referenced.is_empty() {
+            function
+                .in_measurement
+                .get_or_insert_with(InMeasurement::new)
+                .identifier_list
+                .extend(referenced);
+        }
+        if !defined.is_empty() {
+            function
+                .loc_measurement
+                .get_or_insert_with(LocMeasurement::new)
+                .identifier_list
+                .extend(defined);
+        }
+        if function
+            .ref_characteristic
+            .as_ref()
+            .is_some_and(|rc| rc.identifier_list.is_empty())
+        {
+            function.ref_characteristic = None;
+        }
+        if function
+            .def_characteristic
+            .as_ref()
+            .is_some_and(|dc| dc.identifier_list.is_empty())
+        {
+            function.def_characteristic = None;
+        }
+    }
+}
+
+// remove all converted names from the list and return them
+fn take_names(list: Option<&mut Vec<String>>, converted: &HashSet<String>) -> Vec<String> {
+    let Some(list) = list else {
+        return Vec::new();
+    };
+    let (moved, kept) = std::mem::take(list)
+        .into_iter()
+        .partition(|name| converted.contains(name));
+    *list = kept;
+    moved
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_convert() {
+        let a2l_text = r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin MEASUREMENT speed "" UWORD NO_COMPU_METHOD 0 0 0 1000
+                    ECU_ADDRESS 0x1000
+                    MATRIX_DIM 4
+                /end MEASUREMENT
+                /begin MEASUREMENT other "" UBYTE NO_COMPU_METHOD 0 0 0 255
+                /end MEASUREMENT
+                /begin GROUP grp ""
+                    ROOT
+                    /begin REF_MEASUREMENT speed other /end REF_MEASUREMENT
+                /end GROUP
+                /begin FUNCTION fn ""
+                    /begin OUT_MEASUREMENT speed /end OUT_MEASUREMENT
+                /end FUNCTION
+            /end MODULE
+        /end PROJECT"#;
+        let mut load_msgs = Vec::new();
+        let mut a2l_file =
+            a2lfile::load_from_string(a2l_text, None, &mut load_msgs, false).unwrap();
+
+        let mut log_msgs = Vec::new();
+        let count = convert_to_characteristic(&mut a2l_file, "speed", &mut log_msgs).unwrap();
+        assert_eq!(count, 1);
+        let module = &a2l_file.project.module[0];
+        assert_eq!(module.measurement.len(), 1);
+        let characteristic = &module.characteristic[0];
+        assert_eq!(
+            characteristic.characteristic_type,
+            CharacteristicType::ValBlk
+        );
+        assert_eq!(characteristic.address, 0x1000);
+        assert_eq!(characteristic.deposit, "__UWORD_Z");
+        assert_eq!(module.record_layout.len(), 1);
+        let group = &module.group[0];
+        assert_eq!(
+            group.ref_characteristic.as_ref().unwrap().identifier_list,
+            vec!["speed"]
+        );
+        assert_eq!(
+            group.ref_measurement.as_ref().unwrap().identifier_list,
+            vec!["other"]
+        );
+        assert!(module.function[0].out_measurement.is_none());
+        assert!(module.function[0].def_characteristic.is_some());
+        let mut check_msgs = Vec::new();
+        a2l_file.check(&mut check_msgs);
+        assert!(check_msgs.is_empty(), "{check_msgs:?}");
+
+        // convert it back
+        let count = convert_to_measurement(&mut a2l_file, "speed|missing", &mut log_msgs).unwrap();
+        assert_eq!(count, 1);
+        let module = &a2l_file.project.module[0];
+        assert!(module.characteristic.is_empty());
+        assert!(module.record_layout.is_empty());
+        let measurement = &module.measurement[1];
+        assert_eq!(measurement.datatype, DataType::Uword);
+        assert_eq!(measurement.ecu_address.as_ref().unwrap().address, 0x1000);
+        assert_eq!(measurement.matrix_dim.as_ref().unwrap().dim_list, vec![4]);
+        assert!(module.group[0].ref_characteristic.is_none());
+        assert!(module.function[0].loc_measurement.is_some());
+        assert!(log_msgs.is_empty());
+
+        assert!(convert_to_measurement(&mut a2l_file, "(", &mut log_msgs).is_err());
+    }
+}
@@ -0,0 +1,611 @@
+//! Reading and writing calibration data files in DCM (DAMOS) format
+
+use crate::calvalues::{block_dims, transpose, unit_of, CalibrationValue};
+use crate::compu::PhysValue;
+use crate::rename::is_valid_identifier;
+use a2lfile::{
+    A2lFile, AxisDescr, AxisDescrAttribute, CharacteristicType, DisplayIdentifier, Module,
+};
+use std::fmt::Write;
+
+/// Metadata of a calibration object in a DCM file
+#[derive(Debug, Clone, PartialEq)]
+pub struct DcmMetadata {
+    pub name: String,
+    /// the description from LANGNAME
+    pub long_name: Option<String>,
+    /// the display name from DISPLAYNAME
+    pub display_name: Option<String>,
+}
+
+/// Parse the text of a DCM file.
+///
+/// # Errors
+///
+/// Returns an error message if the file contains an invalid or incomplete block
+pub fn parse_dcm(text: &str) -> Result<Vec<CalibrationValue>, String> {
+    read_blocks(text)?
+        .into_iter()
+        .map(|(line_num, header, block)| {
+            block
+                .into_value(&header)
+                .map_err(|err| format!("Error in the block on line {line_num}: {err}"))
+        })
+        .collect()
+}
+
+/// Read the descriptions and display names of all objects in a DCM file. The values are ignored.
+///
+/// # Errors
+///
+/// Returns an error message if the file contains an invalid or incomplete block
+pub fn parse_dcm_metadata(text: &str) -> Result<Vec<DcmMetadata>, String> {
+    read_blocks(text)?
+        .into_iter()
+        .map(|(line_num, header, block)| {
+            let name = header.get(1).ok_or_else(|| {
+                format!("Error in the block on line {line_num}: the name is missing")
+            })?;
+            Ok(DcmMetadata {
+                name: name.clone(),
+                long_name: block.long_name,
+                display_name: block.display_name,
+            })
+        })
+        .collect()
+}
+
+// split the file into blocks: (line number, tokens of the first line, content)
+fn read_blocks(text: &str) -> Result<Vec<(usize, Vec<String>, DcmBlock)>, String> {
+    let mut blocks = Vec::new();
+    let mut lines = text.lines().enumerate();
+    while let Some((idx, line)) = lines.next() {
+        let line_num = idx + 1;
+        let tokens = tokenize(line);
+        let Some(keyword) = tokens.first() else {
+            continue;
+        };
+        match keyword.as_str() {
+            "KONSERVIERUNG_FORMAT" => {}
+            // blocks that don't contain values
+            "FUNKTIONEN" | "VARIANTENKODIERUNG" | "MODULKOPF" => {
+                for (_, line) in lines.by_ref() {
+                    if tokenize(line).first().is_some_and(|token| token == "END") {
+                        break;
+                    }
+                }
+            }
+            "FESTWERT"
+            | "FESTWERTEBLOCK"
+            | "KENNLINIE"
+            | "FESTKENNLINIE"
+            | "GRUPPENKENNLINIE"
+            | "KENNFELD"
+            | "FESTKENNFELD"
+            | "GRUPPENKENNFELD"
+            | "STUETZSTELLENVERTEILUNG"
+            | "TEXTSTRING" => {
+                let mut block = DcmBlock::default();
+                let mut complete = false;
+                for (_, line) in lines.by_ref() {
+                    let tokens = tokenize(line);
+                    if tokens.first().is_some_and(|token| token == "END") {
+                        complete = true;
+                        break;
+                    }
+                    block.add_line(&tokens);
+                }
+                if !complete {
+                    return Err(format!(
+                        "Error: the block {keyword} on line {line_num} has no END"
+                    ));
+                }
+                blocks.push((line_num, tokens, block));
+            }
+            _ => {
+                return Err(format!(
+                    "Error: unexpected keyword \"{keyword}\" on line {line_num}"
+                ))
+            }
+        }
+    }
+    Ok(blocks)
+}
+
+// the content of a block, collected line by line
+#[derive(Default)]
+struct DcmBlock {
+    long_name: Option<String>,
+    display_name: Option<String>,
+    unit: String,
+    values: Vec<PhysValue>,
+    x_axis: Vec<PhysValue>,
+    y_axis: Vec<PhysValue>,
+}
+
+impl DcmBlock {
+    fn add_line(&mut self, tokens: &[String]) {
+        let Some((keyword, args)) = tokens.split_first() else {
+            return;
+        };
+        let target = match keyword.as_str() {
+            "WERT" | "TEXT" => &mut self.values,
+            "ST/X" | "ST_TX/X" => &mut self.x_axis,
+            "ST/Y" | "ST_TX/Y" => &mut self.y_axis,
+            "EINHEIT_W" => {
+                if let Some(unit) = args.first() {
+                    self.unit = unquote(unit);
+                }
+                return;
+            }
+            "LANGNAME" => {
+                self.long_name = Some(
+                    args.iter()
+                        .map(|arg| unquote(arg))
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                );
+                return;
+            }
+            "DISPLAYNAME" => {
+                self.display_name = args.first().map(|arg| unquote(arg));
+                return;
+            }
+            // FUNKTION, EINHEIT_X, EINHEIT_Y, VAR, ...
+            _ => return,
+        };
+        for arg in args {
+            if arg.starts_with('"') {
+                target.push(PhysValue::Text(unquote(arg)));
+            } else if let Ok(number) = arg.parse::<f64>() {
+                target.push(PhysValue::Number(number));
+            } else {
+                target.push(PhysValue::Text(arg.clone()));
+            }
+        }
+    }
+
+    // convert the block to a calibration value. The header contains the keyword, the name and the dimensions
+    fn into_value(self, header: &[String]) -> Result<CalibrationValue, String> {
+        let keyword = header[0].as_str();
+        let name = header
+            .get(1)
+            .ok_or_else(|| "the name is missing".to_string())?
+            .clone();
+        let dims: Vec<usize> = header[2..]
+            .iter()
+            .filter(|token| *token != "@")
+            .map(|token| {
+                token
+                    .parse::<usize>()
+                    .map_err(|_| format!("invalid dimension \"{token}\""))
+            })
+            .collect::<Result<_, _>>()?;
+
+        let mut value = CalibrationValue {
+            name,
+            blocktype: "CHARACTERISTIC",
+            unit: self.unit,
+            axes: Vec::new(),
+            dims: dims.clone(),
+            values: self.values,
+        };
+        match keyword {
+            "FESTWERT" | "TEXTSTRING" => {
+                value.dims = Vec::new();
+                check_count(&value.values, 1, "values")?;
+            }
+            "STUETZSTELLENVERTEILUNG" => {
+                value.blocktype = "AXIS_PTS";
+                check_count(&self.x_axis, dims_product(&dims), "axis points")?;
+                value.values = self.x_axis;
+            }
+            "FESTWERTEBLOCK" => {
+                check_count(&value.values, dims_product(&dims), "values")?;
+                // the values of a two-dimensional block are stored line by line
+                if let [x_count, y_count] = dims[..] {
+                    value.values = transpose(&value.values, y_count, x_count);
+                }
+            }
+            "KENNLINIE" | "FESTKENNLINIE" | "GRUPPENKENNLINIE" => {
+                check_dims(&dims, 1)?;
+                check_count(&self.x_axis, dims[0], "x axis points")?;
+                check_count(&value.values, dims[0], "values")?;
+                value.axes = vec![self.x_axis];
+            }
+            _ => {
+                // KENNFELD, FESTKENNFELD, GRUPPENKENNFELD: each line starts with a y axis point
+                check_dims(&dims, 2)?;
+                check_count(&self.x_axis, dims[0], "x axis points")?;
+                check_count(&self.y_axis, dims[1], "y axis points")?;
+                check_count(&value.values, dims[0] * dims[1], "values")?;
+                value.values = transpose(&value.values, dims[1], dims[0]);
+                value.axes = vec![self.x_axis, self.y_axis];
+            }
+        }
+        Ok(value)
+    }
+}
+
+/// Create a DCM file containing all CHARACTERISTICs and AXIS_PTS with their descriptions, display names and units.
+///
+/// All values are set to zero, so the file can be used as a template for a calibration partner.
+#[must_use]
+pub fn export_dcm(a2l_file: &A2lFile) -> String {
+    let mut text = format!(
+        "* DCM file created by a2ltool {}\n\nKONSERVIERUNG_FORMAT 2.0\n",
+        env!("CARGO_PKG_VERSION")
+    );
+    for module in &a2l_file.project.module {
+        for axis_pts in &module.axis_pts {
+            let count = usize::from(axis_pts.max_axis_points);
+            let _ = writeln!(text, "\nSTUETZSTELLENVERTEILUNG {} {count}", axis_pts.name);
+            write_metadata(
+                &mut text,
+                &axis_pts.long_identifier,
+                axis_pts.display_identifier.as_ref(),
+            );
+            let _ = writeln!(
+                text,
+                "   EINHEIT_X \"{}\"",
+                escape(&unit_of(module, &axis_pts.conversion))
+            );
+            write_numbers(&mut text, "ST/X", count);
+            text.push_str("END\n");
+        }
+        for characteristic in &module.characteristic {
+            let name = &characteristic.name;
+            let counts: Vec<usize> = characteristic
+                .axis_descr
+                .iter()
+                .map(|axis_descr| axis_point_count(module, axis_descr))
+                .collect();
+            let axis_prefix = match characteristic.axis_descr.first().map(|ad| ad.attribute) {
+                Some(AxisDescrAttribute::FixAxis) => "FEST",
+                Some(AxisDescrAttribute::ComAxis) => "GRUPPEN",
+                _ => "",
+            };
+            let header = match (characteristic.characteristic_type, &counts[..]) {
+                (CharacteristicType::Value, _) => format!("FESTWERT {name}"),
+                (CharacteristicType::Ascii, _) => format!("TEXTSTRING {name}"),
+                (CharacteristicType::ValBlk, _) => {
+                    let dims: Vec<String> = block_dims(characteristic)
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect();
+                    if dims.len() > 2 {
+                        let _ = writeln!(
+                            text,
+                            "\n* {name}: VAL_BLK with more than 2 dimensions is not supported"
+                        );
+                        continue;
+                    }
+                    format!("FESTWERTEBLOCK {name} {}", dims.join(" @ "))
+                }
+                (CharacteristicType::Curve, [x_count]) => {
+                    format!("{axis_prefix}KENNLINIE {name} {x_count}")
+                }
+                (CharacteristicType::Map, [x_count, y_count]) => {
+                    format!("{axis_prefix}KENNFELD {name} {x_count} {y_count}")
+                }
+                (other, _) => {
+                    let _ = writeln!(text, "\n* {name}: {other} is not supported");
+                    continue;
+                }
+            };
+            let _ = writeln!(text, "\n{header}");
+            write_metadata(
+                &mut text,
+                &characteristic.long_identifier,
+                characteristic.display_identifier.as_ref(),
+            );
+            for (axis_descr, axis_name) in characteristic.axis_descr.iter().zip(["X", "Y"]) {
+                let unit = unit_of(module, &axis_descr.conversion);
+                let _ = writeln!(text, "   EINHEIT_{axis_name} \"{}\"", escape(&unit));
+            }
+            let unit = unit_of(module, &characteristic.conversion);
+            let _ = writeln!(text, "   EINHEIT_W \"{}\"", escape(&unit));
+            match (characteristic.characteristic_type, &counts[..]) {
+                (CharacteristicType::Value, _) => write_numbers(&mut text, "WERT", 1),
+                (CharacteristicType::Ascii, _) => text.push_str("   TEXT \"\"\n"),
+                (CharacteristicType::ValBlk, _) => {
+                    let dims = block_dims(characteristic);
+                    for _ in 0..dims.get(1).copied().unwrap_or(1) {
+                        write_numbers(&mut text, "WERT", dims[0]);
+                    }
+                }
+                (CharacteristicType::Curve, [x_count]) => {
+                    write_numbers(&mut text, "ST/X", *x_count);
+                    write_numbers(&mut text, "WERT", *x_count);
+                }
+                (_, [x_count, y_count]) => {
+                    write_numbers(&mut text, "ST/X", *x_count);
+                    for _ in 0..*y_count {
+                        write_numbers(&mut text, "ST/Y", 1);
+                        write_numbers(&mut text, "WERT", *x_count);
+                    }
+                }
+                _ => {}
+            }
+            text.push_str("END\n");
+        }
+    }
+    text
+}
+
+/// Set the descriptions and display names of CHARACTERISTICs and AXIS_PTS from the metadata of a DCM file.
+///
+/// Problems, like entries without a matching object, are added to `log_msgs`.
+/// Returns the number of objects that were updated.
+pub fn apply_dcm_metadata(
+    a2l_file: &mut A2lFile,
+    metadata: &[DcmMetadata],
+    log_msgs: &mut Vec<String>,
+) -> usize {
+    let mut count = 0;
+    for entry in metadata {
+        if entry.long_name.is_none() && entry.display_name.is_none() {
+            continue;
+        }
+        if let Some(display_name) = &entry.display_name {
+            if !is_valid_identifier(display_name) {
+                log_msgs.push(format!(
+                    "{}: the display name \"{display_name}\" is not a valid identifier",
+                    entry.name
+                ));
+                continue;
+            }
+        }
+        let mut found = false;
+        for module in &mut a2l_file.project.module {
+            if let Some(characteristic) = module
+                .characteristic
+                .iter_mut()
+                .find(|c| c.name == entry.name)
+            {
+                apply_metadata(
+                    entry,
+                    &mut characteristic.long_identifier,
+                    &mut characteristic.display_identifier,
+                );
+                found = true;
+            } else if let Some(axis_pts) =
+                module.axis_pts.iter_mut().find(|ap| ap.name == entry.name)
+            {
+                apply_metadata(
+                    entry,
+                    &mut axis_pts.long_identifier,
+                    &mut axis_pts.display_identifier,
+                );
+                found = true;
+            }
+        }
+        if found {
+            count += 1;
+        } else {
+            log_msgs.push(format!(
+                "{}: there is no CHARACTERISTIC or AXIS_PTS with this name",
+                entry.name
+            ));
+        }
+    }
+    count
+}
+
+fn apply_metadata(
+    entry: &DcmMetadata,
+    long_identifier: &mut String,
+    display_identifier: &mut Option<DisplayIdentifier>,
+) {
+    if let Some(long_name) = &entry.long_name {
+        long_identifier.clone_from(long_name);
+    }
+    if let Some(display_name) = &entry.display_name {
+        *display_identifier = Some(DisplayIdentifier::new(display_name.clone()));
+    }
+}
+
+fn write_metadata(
+    text: &mut String,
+    long_identifier: &str,
+    display_identifier: Option<&DisplayIdentifier>,
+) {
+    let _ = writeln!(text, "   LANGNAME \"{}\"", escape(long_identifier));
+    if let Some(display_identifier) = display_identifier {
+        let _ = writeln!(text, "   DISPLAYNAME {}", display_identifier.display_name);
+    }
+}
+
+fn write_numbers(text: &mut String, keyword: &str, count: usize) {
+    let _ = writeln!(text, "   {keyword:<6}{}", " 0".repeat(count));
+}
+
+// the number of points of an axis, which for fixed axes is given by the axis parameters
+fn axis_point_count(module: &Module, axis_descr: &AxisDescr) -> usize {
+    if let Some(par) = &axis_descr.fix_axis_par {
+        usize::from(par.number_apo)
+    } else if let Some(par) = &axis_descr.fix_axis_par_dist {
+        usize::from(par.number_apo)
+    } else if let Some(par) = &axis_descr.fix_axis_par_list {
+        par.axis_pts_value_list.len()
+    } else if let Some(axis_pts) = axis_descr.axis_pts_ref.as_ref().and_then(|axis_pts_ref| {
+        module
+            .axis_pts
+            .iter()
+            .find(|ap| ap.name == axis_pts_ref.axis_points)
+    }) {
+        usize::from(axis_pts.max_axis_points)
+    } else {
+        usize::from(axis_descr.max_axis_points)
+    }
+}
+
+// DCM strings can't contain line breaks or quotes
+fn escape(text: &str) -> String {
+    text.replace(['\r', '\n'], " ").replace('"', "'")
+}
+
+fn dims_product(dims: &[usize]) -> usize {
+    dims.iter().product()
+}
+
+fn check_dims(dims: &[usize], expected: usize) -> Result<(), String> {
+    if dims.len() == expected {
+        Ok(())
+    } else {
+        Err(format!(
+            "expected {expected} dimensions, found {}",
+            dims.len()
+        ))
+    }
+}
+
+fn check_count<T>(items: &[T], expected: usize, what: &str) -> Result<(), String> {
+    if items.len() == expected {
+        Ok(())
+    } else {
+        Err(format!("expected {expected} {what}, found {}", items.len()))
+    }
+}
+
+// split a line into tokens. Quoted strings are single tokens, and comments starting with * or ! are removed
+fn tokenize(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.trim().chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if (c == '*' || c == '!') && tokens.is_empty() {
+            break;
+        } else if c == '"' {
+            let mut token = String::from(chars.next().unwrap());
+            for c in chars.by_ref() {
+                token.push(c);
+                if c == '"' {
+                    break;
+                }
+            }
+            tokens.push(token);
+        } else {
+            let mut token = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                token.push(c);
+                chars.next();
+            }
+            tokens.push(token);
+        }
+    }
+    tokens
+}
+
+fn unquote(token: &str) -> String {
+    token
+        .strip_prefix('"')
+        .map(|t| t.strip_suffix('"').unwrap_or(t))
+        .unwrap_or(token)
+        .to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_dcm() {
+        let text = r#"
+* comment
+KONSERVIERUNG_FORMAT 2.0
+
+FESTWERT value
+   LANGNAME "a value"
+   EINHEIT_W "rpm"
+   WERT 1.5
+END
+
+TEXTSTRING text
+   TEXT "hello world"
+END
+
+KENNFELD map 3 2
+   ST/X   1 2 3
+   ST/Y   10
+   WERT   1 2 3
+   ST/Y   20
+   WERT   4 5 6
+END
+
+STUETZSTELLENVERTEILUNG axis 2
+   ST/X 0 100
+END
+"#;
+        let values = parse_dcm(text).unwrap();
+        assert_eq!(values.len(), 4);
+        assert_eq!(values[0].values, vec![PhysValue::Number(1.5)]);
+        assert_eq!(values[0].unit, "rpm");
+        assert_eq!(
+            values[1].values,
+            vec![PhysValue::Text("hello world".to_string())]
+        );
+        assert_eq!(values[2].dims, vec![3, 2]);
+        let map_values: Vec<f64> = values[2]
+            .values
+            .iter()
+            .filter_map(PhysValue::as_number)
+            .collect();
+        assert_eq!(map_values, vec![1.0, 4.0, 2.0, 5.0, 3.0, 6.0]);
+        assert_eq!(values[3].blocktype, "AXIS_PTS");
+
+        assert!(parse_dcm("FESTWERT x\n WERT 1\n").is_err());
+        assert!(parse_dcm("KENNLINIE c 3\n ST/X 1 2 3\n WERT 1 2\nEND\n").is_err());
+    }
+
+    #[test]
+    fn test_export_import() {
+        let a2l_text = r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin CHARACTERISTIC value "a value" VALUE 0x1000 RL 0 NO_COMPU_METHOD 0 255
+                /end CHARACTERISTIC
+                /begin CHARACTERISTIC map "" MAP 0x1000 RL 0 NO_COMPU_METHOD 0 255
+                    /begin AXIS_DESCR STD_AXIS NO_INPUT_QUANTITY NO_COMPU_METHOD 3 0 255
+                    /end AXIS_DESCR
+                    /begin AXIS_DESCR STD_AXIS NO_INPUT_QUANTITY NO_COMPU_METHOD 2 0 255
+                    /end AXIS_DESCR
+                /end CHARACTERISTIC
+                /begin RECORD_LAYOUT RL
+                    FNC_VALUES 1 UBYTE COLUMN_DIR DIRECT
+                /end RECORD_LAYOUT
+            /end MODULE
+        /end PROJECT"#;
+        let mut log_msgs = Vec::new();
+        let mut a2l_file = a2lfile::load_from_string(a2l_text, None, &mut log_msgs, false).unwrap();
+
+        let dcm_text = export_dcm(&a2l_file);
+        assert!(dcm_text.contains("LANGNAME \"a value\""));
+        let values = parse_dcm(&dcm_text).unwrap();
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[1].dims, vec![3, 2]);
+
+        let metadata_text = "FESTWERT value\n LANGNAME \"new description\"\n DISPLAYNAME disp.value\n WERT 0\nEND\n\
+            FESTWERT other\n LANGNAME \"x\"\n WERT 0\nEND\n";
+        let metadata = parse_dcm_metadata(metadata_text).unwrap();
+        let mut log_msgs = Vec::new();
+        let count = apply_dcm_metadata(&mut a2l_file, &metadata, &mut log_msgs);
+        assert_eq!(count, 1);
+        assert_eq!(log_msgs.len(), 1);
+        let characteristic = &a2l_file.project.module[0].characteristic[0];
+        assert_eq!(characteristic.long_identifier, "new description");
+        assert_eq!(
+            characteristic
+                .display_identifier
+                .as_ref()
+                .unwrap()
+                .display_name,
+            "disp.value"
+        );
+    }
+}
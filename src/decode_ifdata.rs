@@ -0,0 +1,188 @@
+//! Readable display of the content of IF_DATA blocks
+//!
+//! The IF_DATA blocks are decoded by a2lfile according to the A2ML specification when the file is loaded.
+//! The decoded items are shown as a tree, where each value is labeled with its A2ML data type.
+
+use crate::validate_ifdata::ifdata_owners;
+use a2lfile::{A2lFile, A2lObject, GenericIfData, IfData};
+use std::fmt::Write;
+
+/// Describe the content of the IF_DATA blocks of all objects as an indented tree.
+///
+/// If a name is given, only the IF_DATA blocks of the object with this name, or the IF_DATA blocks with this tag, are shown.
+/// Blocks that do not match the A2ML specification are shown as well, but their values can only be shown as generic
+/// numbers, strings and identifiers.
+#[must_use]
+pub fn decode_ifdata(a2l_file: &A2lFile, name: Option<&str>) -> String {
+    let mut text = String::new();
+    for module in &a2l_file.project.module {
+        for (owner, ifdata_list) in ifdata_owners(module) {
+            let owner_name = owner
+                .split_once(' ')
+                .map_or("", |(_, owner_name)| owner_name);
+            for ifdata in ifdata_list {
+                let tag = crate::remove_ifdata::ifdata_tag(ifdata).unwrap_or("");
+                if name.is_some_and(|name| name != owner_name && name != tag) {
+                    continue;
+                }
+                decode_block(&mut text, ifdata, &owner, &module.name);
+            }
+        }
+    }
+    text
+}
+
+fn decode_block(text: &mut String, ifdata: &IfData, owner: &str, module_name: &str) {
+    let _ = write!(
+        text,
+        "{owner} in module {module_name}, line {}",
+        ifdata.get_line()
+    );
+    if !ifdata.ifdata_valid {
+        text.push_str(" (does not match the A2ML specification)");
+    }
+    text.push('\n');
+    if let Some(items) = &ifdata.ifdata_items {
+        decode_item(text, items, 1);
+    }
+}
+
+fn decode_item(text: &mut String, item: &GenericIfData, depth: usize) {
+    let indent = "  ".repeat(depth);
+    match item {
+        GenericIfData::None => {}
+        GenericIfData::Char(_, (value, is_hex)) => {
+            push_integer(text, &indent, "char", i64::from(*value), *is_hex);
+        }
+        GenericIfData::Int(_, (value, is_hex)) => {
+            push_integer(text, &indent, "int", i64::from(*value), *is_hex);
+        }
+        GenericIfData::Long(_, (value, is_hex)) => {
+            push_integer(text, &indent, "long", i64::from(*value), *is_hex);
+        }
+        GenericIfData::Int64(_, (value, is_hex)) => {
+            push_integer(text, &indent, "int64", *value, *is_hex);
+        }
+        GenericIfData::UChar(_, (value, is_hex)) => {
+            push_unsigned(text, &indent, "uchar", u64::from(*value), *is_hex);
+        }
+        GenericIfData::UInt(_, (value, is_hex)) => {
+            push_unsigned(text, &indent, "uint", u64::from(*value), *is_hex);
+        }
+        GenericIfData::ULong(_, (value, is_hex)) => {
+            push_unsigned(text, &indent, "ulong", u64::from(*value), *is_hex);
+        }
+        GenericIfData::UInt64(_, (value, is_hex)) => {
+            push_unsigned(text, &indent, "uint64", *value, *is_hex);
+        }
+        GenericIfData::Float(_, value) => {
+            let _ = writeln!(text, "{indent}float {value}");
+        }
+        GenericIfData::Double(_, value) => {
+            let _ = writeln!(text, "{indent}double {value}");
+        }
+        GenericIfData::String(_, value) => {
+            let _ = writeln!(text, "{indent}char[] \"{value}\"");
+        }
+        GenericIfData::EnumItem(_, value) => {
+            let _ = writeln!(text, "{indent}enum {value}");
+        }
+        GenericIfData::Array(items) => {
+            let _ = writeln!(text, "{indent}array [{}]", items.len());
+            for item in items {
+                decode_item(text, item, depth + 1);
+            }
+        }
+        GenericIfData::Sequence(items) => {
+            let _ = writeln!(text, "{indent}sequence [{}]", items.len());
+            for item in items {
+                decode_item(text, item, depth + 1);
+            }
+        }
+        // the members of a struct are shown at the level of the struct, since they have no names
+        GenericIfData::Struct(_, _, items) | GenericIfData::Block { items, .. } => {
+            for item in items {
+                decode_item(text, item, depth);
+            }
+        }
+        GenericIfData::TaggedStruct(taggeditems) | GenericIfData::TaggedUnion(taggeditems) => {
+            // the items are stored in a HashMap; the uid restores the original order
+            let mut sorted: Vec<_> = taggeditems.values().flatten().collect();
+            sorted.sort_by_key(|item| item.uid);
+            for item in sorted {
+                let _ = writeln!(text, "{indent}{}", item.tag);
+                decode_item(text, &item.data, depth + 1);
+            }
+        }
+    }
+}
+
+fn push_integer(text: &mut String, indent: &str, typename: &str, value: i64, is_hex: bool) {
+    if is_hex {
+        let _ = writeln!(text, "{indent}{typename} 0x{value:X}");
+    } else {
+        let _ = writeln!(text, "{indent}{typename} {value}");
+    }
+}
+
+fn push_unsigned(text: &mut String, indent: &str, typename: &str, value: u64, is_hex: bool) {
+    if is_hex {
+        let _ = writeln!(text, "{indent}{typename} 0x{value:X}");
+    } else {
+        let _ = writeln!(text, "{indent}{typename} {value}");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decode_ifdata() {
+        let a2l_text = r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin MEASUREMENT speed "" UWORD NO_COMPU_METHOD 0 0 0 255
+                    ECU_ADDRESS 0x1234
+                    /begin IF_DATA CANAPE_EXT 100
+                        LINK_MAP "speed" 0x1234 0 0 0 1 0x0 0
+                        DISPLAY 0 0 255
+                    /end IF_DATA
+                /end MEASUREMENT
+                /begin MEASUREMENT temp "" UBYTE NO_COMPU_METHOD 0 0 0 255
+                    /begin IF_DATA VENDOR_X 1 "text" /end IF_DATA
+                /end MEASUREMENT
+            /end MODULE
+        /end PROJECT"#;
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(
+            a2l_text,
+            Some(crate::ifdata::A2MLVECTOR_TEXT.to_string()),
+            &mut log_msgs,
+            false,
+        )
+        .unwrap();
+
+        let text = decode_ifdata(&a2l_file, None);
+        let expected = r#"MEASUREMENT speed in module m, line 7
+  CANAPE_EXT
+    int 100
+    LINK_MAP
+      char[] "speed"
+      long 0x1234
+"#;
+        assert!(text.starts_with(expected), "{text}");
+        assert!(text.contains("    DISPLAY\n      long 0\n"));
+        assert!(text.contains(
+            "MEASUREMENT temp in module m, line 13 (does not match the A2ML specification)\n"
+        ));
+
+        // filter by object name or by tag
+        let text = decode_ifdata(&a2l_file, Some("temp"));
+        assert!(!text.contains("speed"));
+        let text = decode_ifdata(&a2l_file, Some("CANAPE_EXT"));
+        assert!(text.contains("speed") && !text.contains("temp"));
+        assert!(decode_ifdata(&a2l_file, Some("missing")).is_empty());
+    }
+}
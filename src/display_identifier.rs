@@ -0,0 +1,295 @@
+//! Generation of DISPLAY_IDENTIFIERs from the names of objects
+
+use a2lfile::{A2lFile, A2lObject, DisplayIdentifier, SymbolLink};
+use regex::Regex;
+
+/// A rule that derives the DISPLAY_IDENTIFIER of an object from its name
+#[derive(Debug, Clone)]
+pub struct DisplayIdentifierRule {
+    template: String,
+    strip_prefix: Option<Regex>,
+    max_length: Option<usize>,
+}
+
+impl DisplayIdentifierRule {
+    /// Create a new rule.
+    ///
+    /// The placeholders `{name}` (name of the object) and `{symbol}` (the symbol name in the SYMBOL_LINK,
+    /// or the name of the object if there is no SYMBOL_LINK) are replaced in the template.
+    /// A prefix matching `strip_prefix` is removed from both values before they are inserted.
+    /// The result is truncated to `max_length` characters.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the template contains an unknown placeholder, if the template
+    /// contains no placeholder at all, or if the regex is invalid
+    pub fn new(
+        template: &str,
+        strip_prefix: Option<&str>,
+        max_length: Option<usize>,
+    ) -> Result<Self, String> {
+        let mut remainder = template;
+        let mut placeholder_count = 0;
+        while let Some(start) = remainder.find('{') {
+            let Some(end) = remainder[start..].find('}') else {
+                return Err(format!(
+                    "Error: unterminated placeholder in the display identifier template \"{template}\""
+                ));
+            };
+            let placeholder = &remainder[start..start + end + 1];
+            if placeholder != "{name}" && placeholder != "{symbol}" {
+                return Err(format!(
+                    "Error: unknown placeholder {placeholder} in the display identifier template \"{template}\". Valid placeholders are {{name}} and {{symbol}}"
+                ));
+            }
+            placeholder_count += 1;
+            remainder = &remainder[start + end + 1..];
+        }
+        if placeholder_count == 0 {
+            return Err(format!(
+                "Error: the display identifier template \"{template}\" must contain {{name}} or {{symbol}}"
+            ));
+        }
+        let strip_prefix = strip_prefix
+            .map(|regex| {
+                Regex::new(&format!("^(?:{regex})"))
+                    .map_err(|err| format!("Error: invalid regex \"{regex}\": {err}"))
+            })
+            .transpose()?;
+        Ok(Self {
+            template: template.to_string(),
+            strip_prefix,
+            max_length,
+        })
+    }
+
+    /// Make the DISPLAY_IDENTIFIER for an object
+    ///
+    /// Characters that are not allowed in an identifier are replaced by `_`.
+    #[must_use]
+    pub fn make_display_identifier(&self, name: &str, symbol: &str) -> String {
+        let name = self.strip(name);
+        let symbol = self.strip(symbol);
+        let text = self
+            .template
+            .replace("{name}", name)
+            .replace("{symbol}", symbol);
+        let mut display_name: String = text
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '[' | ']') {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        if let Some(max_length) = self.max_length {
+            display_name.truncate(max_length);
+        }
+        display_name
+    }
+
+    fn strip<'a>(&self, value: &'a str) -> &'a str {
+        if let Some(regex) = &self.strip_prefix {
+            if let Some(m) = regex.find(value) {
+                // never strip the whole value
+                if m.end() < value.len() {
+                    return &value[m.end()..];
+                }
+            }
+        }
+        value
+    }
+}
+
+/// Set the DISPLAY_IDENTIFIER of all MEASUREMENTs, CHARACTERISTICs, AXIS_PTS, BLOBs and INSTANCEs
+/// according to the rule.
+///
+/// If `only_missing` is set, existing DISPLAY_IDENTIFIERs are not changed.
+/// Objects whose generated DISPLAY_IDENTIFIER is empty are reported in `log_msgs`.
+///
+/// Returns the number of objects whose DISPLAY_IDENTIFIER was created or changed.
+pub fn update_display_identifiers(
+    a2l_file: &mut A2lFile,
+    rule: &DisplayIdentifierRule,
+    only_missing: bool,
+    log_msgs: &mut Vec<String>,
+) -> usize {
+    let mut count = 0;
+    for module in &mut a2l_file.project.module {
+        for measurement in &mut module.measurement {
+            count += update_item(
+                "MEASUREMENT",
+                measurement,
+                |m| (&m.name, &m.symbol_link, &mut m.display_identifier),
+                rule,
+                only_missing,
+                log_msgs,
+            );
+        }
+        for characteristic in &mut module.characteristic {
+            count += update_item(
+                "CHARACTERISTIC",
+                characteristic,
+                |c| (&c.name, &c.symbol_link, &mut c.display_identifier),
+                rule,
+                only_missing,
+                log_msgs,
+            );
+        }
+        for axis_pts in &mut module.axis_pts {
+            count += update_item(
+                "AXIS_PTS",
+                axis_pts,
+                |a| (&a.name, &a.symbol_link, &mut a.display_identifier),
+                rule,
+                only_missing,
+                log_msgs,
+            );
+        }
+        for blob in &mut module.blob {
+            count += update_item(
+                "BLOB",
+                blob,
+                |b| (&b.name, &b.symbol_link, &mut b.display_identifier),
+                rule,
+                only_missing,
+                log_msgs,
+            );
+        }
+        for instance in &mut module.instance {
+            count += update_item(
+                "INSTANCE",
+                instance,
+                |i| (&i.name, &i.symbol_link, &mut i.display_identifier),
+                rule,
+                only_missing,
+                log_msgs,
+            );
+        }
+    }
+    count
+}
+
+fn update_item<T: A2lObject<L>, L>(
+    blockname: &str,
+    item: &mut T,
+    fields: impl Fn(&mut T) -> (&String, &Option<SymbolLink>, &mut Option<DisplayIdentifier>),
+    rule: &DisplayIdentifierRule,
+    only_missing: bool,
+    log_msgs: &mut Vec<String>,
+) -> usize {
+    let line = item.get_line();
+    let (name, symbol_link, display_identifier) = fields(item);
+    if only_missing && display_identifier.is_some() {
+        return 0;
+    }
+    let symbol = symbol_link
+        .as_ref()
+        .map_or(name.as_str(), |sl| sl.symbol_name.as_str());
+    let display_name = rule.make_display_identifier(name, symbol);
+    if display_name.is_empty() {
+        log_msgs.push(format!(
+            "{blockname} {name} on line {line}: the generated DISPLAY_IDENTIFIER is empty"
+        ));
+        return 0;
+    }
+    match display_identifier {
+        Some(di) if di.display_name == display_name => 0,
+        Some(di) => {
+            di.display_name = display_name;
+            1
+        }
+        None => {
+            *display_identifier = Some(DisplayIdentifier::new(display_name));
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_update_display_identifiers() {
+        assert!(DisplayIdentifierRule::new("{nme}", None, None).is_err());
+        assert!(DisplayIdentifierRule::new("fixed", None, None).is_err());
+        assert!(DisplayIdentifierRule::new("{name", None, None).is_err());
+        assert!(DisplayIdentifierRule::new("{name}", Some("("), None).is_err());
+
+        let rule = DisplayIdentifierRule::new("{name}", Some("c_|m_"), Some(10)).unwrap();
+        assert_eq!(rule.make_display_identifier("c_gain", "x"), "gain");
+        assert_eq!(rule.make_display_identifier("m_", "x"), "m_");
+        assert_eq!(
+            rule.make_display_identifier("m_engine_speed_filtered", "x"),
+            "engine_spe"
+        );
+        let rule = DisplayIdentifierRule::new("DI_{symbol}", None, None).unwrap();
+        assert_eq!(rule.make_display_identifier("n", "a::b"), "DI_a__b");
+
+        let mut load_msgs = Vec::new();
+        let mut a2l_file = a2lfile::load_from_string(
+            r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin MEASUREMENT m_speed "" UBYTE NO_COMPU_METHOD 0 0 0 255
+                    DISPLAY_IDENTIFIER old
+                /end MEASUREMENT
+                /begin MEASUREMENT m_temp "" UBYTE NO_COMPU_METHOD 0 0 0 255
+                    SYMBOL_LINK "temperature" 0
+                /end MEASUREMENT
+                /begin BLOB b "" 0x1000 8
+                /end BLOB
+            /end MODULE
+        /end PROJECT"#,
+            None,
+            &mut load_msgs,
+            true,
+        )
+        .unwrap();
+        let mut log_msgs = Vec::new();
+
+        let rule = DisplayIdentifierRule::new("{symbol}", Some("m_"), None).unwrap();
+        let count = update_display_identifiers(&mut a2l_file, &rule, true, &mut log_msgs);
+        assert_eq!(count, 2);
+        let module = &a2l_file.project.module[0];
+        assert_eq!(
+            module.measurement[0]
+                .display_identifier
+                .as_ref()
+                .unwrap()
+                .display_name,
+            "old"
+        );
+        assert_eq!(
+            module.measurement[1]
+                .display_identifier
+                .as_ref()
+                .unwrap()
+                .display_name,
+            "temperature"
+        );
+        assert_eq!(
+            module.blob[0]
+                .display_identifier
+                .as_ref()
+                .unwrap()
+                .display_name,
+            "b"
+        );
+
+        let count = update_display_identifiers(&mut a2l_file, &rule, false, &mut log_msgs);
+        assert_eq!(count, 1);
+        assert_eq!(
+            a2l_file.project.module[0].measurement[0]
+                .display_identifier
+                .as_ref()
+                .unwrap()
+                .display_name,
+            "speed"
+        );
+    }
+}
@@ -142,6 +142,38 @@ pub(crate) fn get_byte_size_attribute(
     }
 }
 
+// get the index of the declaring source file from the DW_AT_decl_file attribute
+pub(crate) fn get_decl_file_attribute(
+    entry: &DebuggingInformationEntry<SliceType, usize>,
+) -> Option<u64> {
+    let decl_file_attr = get_attr_value(entry, gimli::constants::DW_AT_decl_file)?;
+    match decl_file_attr {
+        gimli::AttributeValue::FileIndex(file_index) => Some(file_index),
+        gimli::AttributeValue::Udata(file_index) => Some(file_index),
+        gimli::AttributeValue::Data1(file_index) => Some(u64::from(file_index)),
+        gimli::AttributeValue::Data2(file_index) => Some(u64::from(file_index)),
+        gimli::AttributeValue::Data4(file_index) => Some(u64::from(file_index)),
+        _ => None,
+    }
+}
+
+// get the scale of a fixed-point type from the DW_AT_binary_scale or DW_AT_decimal_scale attribute
+pub(crate) fn get_scale_attribute(
+    entry: &DebuggingInformationEntry<SliceType, usize>,
+    attrtype: gimli::DwAt,
+) -> Option<i64> {
+    let scale_attr = get_attr_value(entry, attrtype)?;
+    match scale_attr {
+        gimli::AttributeValue::Sdata(scale) => Some(scale),
+        gimli::AttributeValue::Udata(scale) => Some(scale as i64),
+        gimli::AttributeValue::Data1(scale) => Some(i64::from(scale as i8)),
+        gimli::AttributeValue::Data2(scale) => Some(i64::from(scale as i16)),
+        gimli::AttributeValue::Data4(scale) => Some(i64::from(scale as i32)),
+        gimli::AttributeValue::Data8(scale) => Some(scale as i64),
+        _ => None,
+    }
+}
+
 // get the encoding of a variable from the DW_AT_encoding attribute
 pub(crate) fn get_encoding_attribute(
     entry: &DebuggingInformationEntry<SliceType, usize>,
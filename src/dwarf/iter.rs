@@ -171,6 +171,7 @@ impl<'dbg> Iterator for VariablesIterator<'dbg> {
                         address: varinfo.address,
                         typeinfo,
                         unit_idx: varinfo.unit_idx,
+                        decl_file: varinfo.decl_file,
                         function_name: &varinfo.function,
                         namespaces: &varinfo.namespaces,
                         is_unique,
@@ -183,6 +184,7 @@ impl<'dbg> Iterator for VariablesIterator<'dbg> {
                         address: varinfo.address + offset,
                         typeinfo,
                         unit_idx: varinfo.unit_idx,
+                        decl_file: varinfo.decl_file,
                         function_name: &varinfo.function,
                         namespaces: &varinfo.namespaces,
                         is_unique,
@@ -244,6 +246,7 @@ impl<'dbg> VariablesIterator<'dbg> {
 mod test {
     use super::*;
     use indexmap::IndexMap;
+    use std::sync::Arc;
 
     const DEFAULT_TYPEINFO: TypeInfo = TypeInfo {
         name: None,
@@ -275,12 +278,12 @@ mod test {
             datatype: DwarfDataType::Uint64,
             ..DEFAULT_TYPEINFO.clone()
         };
-        let mut structmembers_a: IndexMap<String, (TypeInfo, u64)> = IndexMap::new();
-        structmembers_a.insert("structmember_1".to_string(), (t_uint64.clone(), 0));
-        structmembers_a.insert("structmember_2".to_string(), (t_uint64.clone(), 0));
-        structmembers_a.insert("structmember_3".to_string(), (t_uint64.clone(), 0));
-        structmembers_a.insert("structmember_4".to_string(), (t_uint64.clone(), 0));
-        structmembers_a.insert("structmember_5".to_string(), (t_uint64.clone(), 0));
+        let mut structmembers_a: IndexMap<Arc<str>, (TypeInfo, u64)> = IndexMap::new();
+        structmembers_a.insert("structmember_1".into(), (t_uint64.clone(), 0));
+        structmembers_a.insert("structmember_2".into(), (t_uint64.clone(), 0));
+        structmembers_a.insert("structmember_3".into(), (t_uint64.clone(), 0));
+        structmembers_a.insert("structmember_4".into(), (t_uint64.clone(), 0));
+        structmembers_a.insert("structmember_5".into(), (t_uint64.clone(), 0));
         let typeinfo_inner_1 = TypeInfo {
             datatype: DwarfDataType::Struct {
                 size: 64,
@@ -288,10 +291,10 @@ mod test {
             },
             ..DEFAULT_TYPEINFO.clone()
         };
-        let mut structmembers_b: IndexMap<String, (TypeInfo, u64)> = IndexMap::new();
-        structmembers_b.insert("foobar_1".to_string(), (t_sint8.clone(), 0));
-        structmembers_b.insert("foobar_2".to_string(), (t_sint8.clone(), 0));
-        structmembers_b.insert("foobar_3".to_string(), (t_sint8.clone(), 0));
+        let mut structmembers_b: IndexMap<Arc<str>, (TypeInfo, u64)> = IndexMap::new();
+        structmembers_b.insert("foobar_1".into(), (t_sint8.clone(), 0));
+        structmembers_b.insert("foobar_2".into(), (t_sint8.clone(), 0));
+        structmembers_b.insert("foobar_3".into(), (t_sint8.clone(), 0));
         let typeinfo_inner_2 = TypeInfo {
             datatype: DwarfDataType::Struct {
                 size: 64,
@@ -309,9 +312,9 @@ mod test {
             datatype: DwarfDataType::TypeRef(101, 0),
             ..DEFAULT_TYPEINFO.clone()
         };
-        let mut structmembers: IndexMap<String, (TypeInfo, u64)> = IndexMap::new();
-        structmembers.insert("inner_a".to_string(), (typeref_inner_1, 0));
-        structmembers.insert("inner_b".to_string(), (typeref_inner_2, 0));
+        let mut structmembers: IndexMap<Arc<str>, (TypeInfo, u64)> = IndexMap::new();
+        structmembers.insert("inner_a".into(), (typeref_inner_1, 0));
+        structmembers.insert("inner_b".into(), (typeref_inner_2, 0));
         let typeinfo = TypeInfo {
             datatype: DwarfDataType::Struct {
                 size: 64,
@@ -333,6 +336,7 @@ mod test {
                 typeref: 0,
                 unit_idx: 0,
                 function: None,
+                decl_file: None,
                 namespaces: vec![],
             }],
         );
@@ -343,6 +347,7 @@ mod test {
                 typeref: 0,
                 unit_idx: 0,
                 function: None,
+                decl_file: None,
                 namespaces: vec![],
             }],
         );
@@ -354,6 +359,7 @@ mod test {
                     typeref: 1,
                     unit_idx: 0,
                     function: None,
+                    decl_file: None,
                     namespaces: vec![],
                 },
                 VarInfo {
@@ -361,6 +367,7 @@ mod test {
                     typeref: 1,
                     unit_idx: 1,
                     function: None,
+                    decl_file: None,
                     namespaces: vec![],
                 },
             ],
@@ -372,6 +379,7 @@ mod test {
                 typeref: 404, // some number with no correspondence in the types hash map
                 unit_idx: 0,
                 function: None,
+                decl_file: None,
                 namespaces: vec![],
             }],
         );
@@ -381,9 +389,9 @@ mod test {
             datatype: DwarfDataType::Uint8,
             ..DEFAULT_TYPEINFO.clone()
         };
-        let mut structmembers: IndexMap<String, (TypeInfo, u64)> = IndexMap::new();
-        structmembers.insert("member_1".to_string(), (t_uint8.clone(), 0));
-        structmembers.insert("member_2".to_string(), (t_uint8.clone(), 1));
+        let mut structmembers: IndexMap<Arc<str>, (TypeInfo, u64)> = IndexMap::new();
+        structmembers.insert("member_1".into(), (t_uint8.clone(), 0));
+        structmembers.insert("member_2".into(), (t_uint8.clone(), 1));
         let structtype = TypeInfo {
             datatype: DwarfDataType::Struct {
                 size: 64,
@@ -399,7 +407,16 @@ mod test {
             typenames: HashMap::new(),
             demangled_names,
             unit_names: vec![Some("file_a.c".to_string()), Some("file_b.c".to_string())],
+            source_files: Vec::new(),
             sections: HashMap::new(),
+            section_flags: HashMap::new(),
+            load_segments: Vec::new(),
+            symbol_sizes: HashMap::new(),
+            fixed_point: HashMap::new(),
+            little_endian: true,
+            case_folded: None,
+            ignore_leading_underscore: false,
+            duplicate_symbol_policy: DuplicateSymbolPolicy::First,
         };
 
         // test iter.next_sibling()
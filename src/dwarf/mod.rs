@@ -1,19 +1,26 @@
 use gimli::{Abbreviations, DebugInfoOffset, DebuggingInformationEntry, Dwarf, UnitHeader};
 use gimli::{EndianSlice, RunTimeEndian};
-use indexmap::IndexMap;
-use object::read::ObjectSection;
-use object::{Endianness, Object};
+use indexmap::{IndexMap, IndexSet};
+use object::read::{ObjectSection, ObjectSegment};
+use object::{Endianness, Object, ObjectSymbol, SectionFlags, SegmentFlags, SymbolKind};
 use std::ffi::OsStr;
 use std::fmt::Display;
+use std::hash::{Hash, Hasher};
+use std::io::Read;
 use std::ops::Index;
+use std::str::FromStr;
+use std::sync::Arc;
 use std::{collections::HashMap, fs::File};
 
 type SliceType<'a> = EndianSlice<'a, RunTimeEndian>;
+// name, typeref, address and source file index of a global variable
+type GlobalVariable = (String, usize, u64, Option<u64>);
 
 mod attributes;
+use crate::intern::StringInterner;
 use attributes::{
-    get_abstract_origin_attribute, get_location_attribute, get_name_attribute,
-    get_specification_attribute, get_typeref_attribute,
+    get_abstract_origin_attribute, get_decl_file_attribute, get_location_attribute,
+    get_name_attribute, get_specification_attribute, get_typeref_attribute,
 };
 mod iter;
 mod typereader;
@@ -23,13 +30,14 @@ pub(crate) struct VarInfo {
     pub(crate) address: u64,
     pub(crate) typeref: usize,
     pub(crate) unit_idx: usize,
-    pub(crate) function: Option<String>,
-    pub(crate) namespaces: Vec<String>,
+    pub(crate) decl_file: Option<usize>, // index into DebugData.source_files
+    pub(crate) function: Option<Arc<str>>,
+    pub(crate) namespaces: Vec<Arc<str>>,
 }
 
 #[derive(Debug, Clone)]
 pub(crate) struct TypeInfo {
-    pub(crate) name: Option<String>, // not all types have a name
+    pub(crate) name: Option<Arc<str>>, // not all types have a name
     pub(crate) unit_idx: usize,
     pub(crate) datatype: DwarfDataType,
     pub(crate) dbginfo_offset: usize,
@@ -55,20 +63,20 @@ pub(crate) enum DwarfDataType {
     Pointer(u64, DebugInfoOffset),
     Struct {
         size: u64,
-        members: IndexMap<String, (TypeInfo, u64)>,
+        members: IndexMap<Arc<str>, (TypeInfo, u64)>,
     },
     Class {
         size: u64,
-        inheritance: IndexMap<String, (TypeInfo, u64)>,
-        members: IndexMap<String, (TypeInfo, u64)>,
+        inheritance: IndexMap<Arc<str>, (TypeInfo, u64)>,
+        members: IndexMap<Arc<str>, (TypeInfo, u64)>,
     },
     Union {
         size: u64,
-        members: IndexMap<String, (TypeInfo, u64)>,
+        members: IndexMap<Arc<str>, (TypeInfo, u64)>,
     },
     Enum {
         size: u64,
-        enumerators: Vec<(String, i64)>,
+        enumerators: Vec<(Arc<str>, i64)>,
     },
     Array {
         size: u64,
@@ -85,14 +93,61 @@ pub(crate) struct UnitList<'a> {
     list: Vec<(UnitHeader<SliceType<'a>>, gimli::Abbreviations)>,
 }
 
+/// Variables and types read from the DWARF debug information of an elf file
 #[derive(Debug)]
-pub(crate) struct DebugData {
+pub struct DebugData {
     pub(crate) variables: IndexMap<String, Vec<VarInfo>>,
     pub(crate) types: HashMap<usize, TypeInfo>,
-    pub(crate) typenames: HashMap<String, Vec<usize>>,
+    pub(crate) typenames: HashMap<Arc<str>, Vec<usize>>,
     pub(crate) demangled_names: HashMap<String, String>,
     pub(crate) unit_names: Vec<Option<String>>,
+    pub(crate) source_files: Vec<String>,
     pub(crate) sections: HashMap<String, (u64, u64)>,
+    pub(crate) section_flags: HashMap<String, MemoryFlags>,
+    // the loadable program headers: (start, end, flags)
+    pub(crate) load_segments: Vec<(u64, u64, MemoryFlags)>,
+    pub(crate) symbol_sizes: HashMap<String, u64>,
+    // scaling factors of fixed-point types, by the offset of the type
+    pub(crate) fixed_point: HashMap<usize, f64>,
+    pub(crate) little_endian: bool,
+    // only set if symbol names should also be matched without regard to case
+    pub(crate) case_folded: Option<CaseFoldedNames>,
+    // symbol names may differ from the variable names by a leading underscore
+    pub(crate) ignore_leading_underscore: bool,
+    // how a symbol name that matches several variables is resolved
+    pub(crate) duplicate_symbol_policy: DuplicateSymbolPolicy,
+}
+
+/// How a symbol name is resolved if it matches several variables or struct members whose names
+/// differ only by case, e.g. `SPEED` matching both `Speed` and `speed`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CaseTieBreak {
+    /// use the first of the matching names in alphabetical order
+    #[default]
+    First,
+    /// do not resolve the symbol name
+    Reject,
+}
+
+/// How a symbol name is resolved if there are several variables with this name, e.g. static variables
+/// in different compile units, and the SYMBOL_LINK does not select one of them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateSymbolPolicy {
+    /// do not resolve the symbol name
+    Error,
+    /// use the first of the variables in the debug info
+    #[default]
+    First,
+    /// use the variable from the compile unit whose file name comes first in alphabetical order
+    ByFile,
+}
+
+// lookup of the variable names without regard to case
+#[derive(Debug)]
+pub(crate) struct CaseFoldedNames {
+    pub(crate) tie_break: CaseTieBreak,
+    // lowercase name -> all variable names with this lowercase form, sorted alphabetically
+    pub(crate) variables: HashMap<String, Vec<String>>,
 }
 
 struct DebugDataReader<'elffile> {
@@ -100,38 +155,237 @@ struct DebugDataReader<'elffile> {
     verbose: bool,
     units: UnitList<'elffile>,
     unit_names: Vec<Option<String>>,
+    source_files: IndexSet<String>,
     endian: Endianness,
     sections: HashMap<String, (u64, u64)>,
+    section_flags: HashMap<String, MemoryFlags>,
+    load_segments: Vec<(u64, u64, MemoryFlags)>,
+    symbol_sizes: HashMap<String, u64>,
+    names: StringInterner,
+}
+
+/// access flags of an elf section or program header
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct MemoryFlags {
+    pub(crate) writable: bool,
+    pub(crate) executable: bool,
 }
 
 impl DebugData {
-    // load the debug info from an elf file
-    pub(crate) fn load(filename: &OsStr, verbose: bool) -> Result<Self, String> {
+    /// load the debug info from an elf file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the file cannot be read or if it does not contain DWARF debug info
+    pub fn load(filename: &OsStr, verbose: bool) -> Result<Self, String> {
         let filedata = load_filedata(filename)?;
         let elffile = load_elf_file(&filename.to_string_lossy(), &filedata)?;
         let dwarf = load_dwarf(&elffile)?;
 
         let sections = get_elf_sections(&elffile);
+        let section_flags = get_elf_section_flags(&elffile);
+        let load_segments = get_load_segments(&elffile);
+        let symbol_sizes = get_symbol_sizes(&elffile);
 
         let dbg_reader = DebugDataReader {
             dwarf,
             verbose,
             units: UnitList::new(),
             unit_names: Vec::new(),
+            source_files: IndexSet::new(),
             endian: elffile.endianness(),
             sections,
+            section_flags,
+            load_segments,
+            symbol_sizes,
+            names: StringInterner::new(),
         };
 
         Ok(dbg_reader.read_debug_info_entries())
     }
 
+    /// the number of distinct variable names in the debug info
+    #[must_use]
+    pub fn variable_count(&self) -> usize {
+        self.variables.len()
+    }
+
+    /// the address range (start, end) of the elf section with the given name
+    #[must_use]
+    pub fn section_range(&self, section_name: &str) -> Option<(u64, u64)> {
+        self.sections.get(section_name).copied()
+    }
+
+    /// true if the program in the elf file uses little endian byte order
+    #[must_use]
+    pub fn is_little_endian(&self) -> bool {
+        self.little_endian
+    }
+
+    /// match symbol names to variables and struct members without regard to case, if there is no exact match.
+    /// A warning is logged for each group of variables or struct members whose names differ only by case;
+    /// symbol names that match such a group are resolved according to `tie_break`.
+    pub fn set_case_insensitive(&mut self, tie_break: CaseTieBreak, log_msgs: &mut Vec<String>) {
+        let mut variables = HashMap::<String, Vec<String>>::new();
+        for name in self.variables.keys() {
+            variables
+                .entry(name.to_ascii_lowercase())
+                .or_default()
+                .push(name.clone());
+        }
+        // names is sorted, so the first entry is the one that is selected by CaseTieBreak::First
+        let resolution = |names: &[&str]| match tie_break {
+            CaseTieBreak::First => format!(
+                "{} is used for names that match them without case",
+                names[0]
+            ),
+            CaseTieBreak::Reject => {
+                "names that match them without case are not resolved".to_string()
+            }
+        };
+        let mut warnings = std::collections::BTreeSet::new();
+        for names in variables.values_mut() {
+            names.sort();
+            if names.len() > 1 {
+                let names: Vec<&str> = names.iter().map(String::as_str).collect();
+                warnings.insert(format!(
+                    "Warning: the variables {} differ only by case; {}",
+                    names.join(", "),
+                    resolution(&names)
+                ));
+            }
+        }
+        // struct types are repeated in every compilation unit that uses them, but each type is only reported once
+        for typeinfo in self.types.values() {
+            let (DwarfDataType::Struct { members, .. }
+            | DwarfDataType::Union { members, .. }
+            | DwarfDataType::Class { members, .. }) = &typeinfo.datatype
+            else {
+                continue;
+            };
+            let mut member_names = HashMap::<String, Vec<&str>>::new();
+            for name in members.keys() {
+                member_names
+                    .entry(name.to_ascii_lowercase())
+                    .or_default()
+                    .push(name);
+            }
+            for mut names in member_names.into_values().filter(|names| names.len() > 1) {
+                names.sort_unstable();
+                warnings.insert(format!(
+                    "Warning: the members {} of {} differ only by case; {}",
+                    names.join(", "),
+                    typeinfo.name.as_deref().unwrap_or("<anonymous>"),
+                    resolution(&names)
+                ));
+            }
+        }
+        log_msgs.extend(warnings);
+
+        self.case_folded = Some(CaseFoldedNames {
+            tie_break,
+            variables,
+        });
+    }
+
+    /// also match symbol names to variables whose names differ by a leading underscore, e.g. `EngSpd` to `_EngSpd`
+    /// or `_EngSpd` to `EngSpd`. An exact match is always preferred.
+    pub fn set_ignore_leading_underscore(&mut self, ignore: bool) {
+        self.ignore_leading_underscore = ignore;
+    }
+
+    /// set how symbol names that match several variables with the same name are resolved, if the SYMBOL_LINK
+    /// does not select one of them with {CompileUnit:...}, {Function:...} or {Namespace:...}
+    pub fn set_duplicate_symbol_policy(&mut self, policy: DuplicateSymbolPolicy) {
+        self.duplicate_symbol_policy = policy;
+    }
+
     pub(crate) fn iter(&self, use_new_arrays: bool) -> iter::VariablesIterator {
         iter::VariablesIterator::new(self, use_new_arrays)
     }
+
+    // the scaling factor (physical value = factor * raw value) of a fixed-point type
+    pub(crate) fn fixed_point_factor(&self, typeinfo: &TypeInfo) -> Option<f64> {
+        self.fixed_point.get(&typeinfo.dbginfo_offset).copied()
+    }
+}
+
+// read an identification of the build from an elf file: either the content of the GNU build-id note
+// as a hex string, or the content of the given symbol, e.g. a version string
+pub(crate) fn read_build_id(filename: &OsStr, symbol_name: Option<&str>) -> Result<String, String> {
+    let filedata = load_filedata(filename)?;
+    let elffile = load_elf_file(&filename.to_string_lossy(), &filedata)?;
+
+    let Some(symbol_name) = symbol_name else {
+        return match elffile.build_id() {
+            Ok(Some(build_id)) => Ok(build_id.iter().map(|byte| format!("{byte:02x}")).collect()),
+            Ok(None) => Err(format!(
+                "Error: the file {} does not contain a build-id",
+                filename.to_string_lossy()
+            )),
+            Err(err) => Err(format!(
+                "Error: could not read the build-id of {}: {err}",
+                filename.to_string_lossy()
+            )),
+        };
+    };
+
+    let Some(symbol) = elffile
+        .symbols()
+        .find(|symbol| symbol.name() == Ok(symbol_name))
+    else {
+        return Err(format!(
+            "Error: the symbol {symbol_name} does not exist in {}",
+            filename.to_string_lossy()
+        ));
+    };
+    // uninitialized symbols in .bss have no content in the file
+    let data = symbol
+        .section_index()
+        .and_then(|index| elffile.section_by_index(index).ok())
+        .and_then(|section| {
+            let data = section.data().ok()?;
+            let offset = usize::try_from(symbol.address().checked_sub(section.address())?).ok()?;
+            data.get(offset..)
+        })
+        .filter(|data| !data.is_empty())
+        .ok_or_else(|| format!("Error: the symbol {symbol_name} has no initial value"))?;
+    let size = match usize::try_from(symbol.size()) {
+        Ok(size) if size > 0 => size.min(data.len()),
+        _ => data.len(),
+    };
+    let data = &data[..size];
+    // a version string ends at the first zero byte, other data is shown as hex
+    let text_len = data
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(data.len());
+    match std::str::from_utf8(&data[..text_len]) {
+        Ok(text) if !text.is_empty() && !text.chars().any(char::is_control) => Ok(text.to_string()),
+        _ => Ok(data.iter().map(|byte| format!("{byte:02x}")).collect()),
+    }
+}
+
+// the content of the elf file: usually memory mapped, but read into memory if mapping is not possible
+enum FileData {
+    Mapped(memmap2::Mmap),
+    Loaded(Vec<u8>),
+}
+
+impl std::ops::Deref for FileData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            FileData::Mapped(mmap) => mmap,
+            FileData::Loaded(data) => data,
+        }
+    }
 }
 
 // open a file and mmap its content
-fn load_filedata(filename: &OsStr) -> Result<memmap2::Mmap, String> {
+// Mapping fails on some file systems and for special files like pipes; then the file is read instead
+fn load_filedata(filename: &OsStr) -> Result<FileData, String> {
     let file = match File::open(filename) {
         Ok(file) => file,
         Err(error) => {
@@ -143,11 +397,17 @@ fn load_filedata(filename: &OsStr) -> Result<memmap2::Mmap, String> {
     };
 
     match unsafe { memmap2::Mmap::map(&file) } {
-        Ok(mmap) => Ok(mmap),
-        Err(err) => Err(format!(
-            "Error: Failed to map file '{}': {err}",
-            filename.to_string_lossy()
-        )),
+        Ok(mmap) => Ok(FileData::Mapped(mmap)),
+        Err(map_err) => {
+            let mut data = Vec::new();
+            match (&file).read_to_end(&mut data) {
+                Ok(_) => Ok(FileData::Loaded(data)),
+                Err(err) => Err(format!(
+                    "Error: Failed to map file '{}': {map_err}; reading it also failed: {err}",
+                    filename.to_string_lossy()
+                )),
+            }
+        }
     }
 }
 
@@ -178,6 +438,53 @@ fn get_elf_sections(elffile: &object::read::File) -> HashMap<String, (u64, u64)>
     map
 }
 
+// get the access flags of all allocated sections
+fn get_elf_section_flags(elffile: &object::read::File) -> HashMap<String, MemoryFlags> {
+    elffile
+        .sections()
+        .filter(|section| section.address() != 0 && section.size() != 0)
+        .filter_map(|section| {
+            let SectionFlags::Elf { sh_flags } = section.flags() else {
+                return None;
+            };
+            let flags = MemoryFlags {
+                writable: sh_flags & u64::from(object::elf::SHF_WRITE) != 0,
+                executable: sh_flags & u64::from(object::elf::SHF_EXECINSTR) != 0,
+            };
+            Some((section.name().ok()?.to_string(), flags))
+        })
+        .collect()
+}
+
+// get the address ranges and flags of all loadable program headers
+fn get_load_segments(elffile: &object::read::File) -> Vec<(u64, u64, MemoryFlags)> {
+    elffile
+        .segments()
+        .filter(|segment| segment.size() != 0)
+        .map(|segment| {
+            let flags = if let SegmentFlags::Elf { p_flags } = segment.flags() {
+                MemoryFlags {
+                    writable: p_flags & object::elf::PF_W != 0,
+                    executable: p_flags & object::elf::PF_X != 0,
+                }
+            } else {
+                MemoryFlags::default()
+            };
+            let start = segment.address();
+            (start, start + segment.size(), flags)
+        })
+        .collect()
+}
+
+// get the sizes of all data objects from the symbol table
+fn get_symbol_sizes(elffile: &object::read::File) -> HashMap<String, u64> {
+    elffile
+        .symbols()
+        .filter(|symbol| symbol.kind() == SymbolKind::Data && symbol.size() != 0)
+        .filter_map(|symbol| Some((symbol.name().ok()?.to_string(), symbol.size())))
+        .collect()
+}
+
 // load the SWARF debug info from the .debug_<xyz> sections
 fn load_dwarf<'data>(
     elffile: &object::read::File<'data>,
@@ -216,7 +523,7 @@ impl<'elffile> DebugDataReader<'elffile> {
     // read the debug information entries in the DWAF data to get all the global variables and their types
     fn read_debug_info_entries(mut self) -> DebugData {
         let variables = self.load_variables();
-        let (types, typenames) = self.load_types(&variables);
+        let typereader_data = self.load_types(&variables);
         let varname_list: Vec<&String> = variables.keys().collect();
         let demangled_names = demangle_cpp_varnames(&varname_list);
 
@@ -225,11 +532,20 @@ impl<'elffile> DebugDataReader<'elffile> {
 
         DebugData {
             variables,
-            types,
-            typenames,
+            types: typereader_data.types,
+            typenames: typereader_data.typenames,
             demangled_names,
             unit_names,
+            source_files: self.source_files.into_iter().collect(),
             sections: self.sections,
+            section_flags: self.section_flags,
+            load_segments: self.load_segments,
+            symbol_sizes: self.symbol_sizes,
+            fixed_point: typereader_data.fixed_point,
+            little_endian: self.endian == Endianness::Little,
+            case_folded: None,
+            ignore_leading_underscore: false,
+            duplicate_symbol_policy: DuplicateSymbolPolicy::First,
         }
     }
 
@@ -258,8 +574,11 @@ impl<'elffile> DebugDataReader<'elffile> {
                 }
             }
 
+            // the table of source files is only read if the unit contains global variables
+            let mut file_table: Option<Vec<Option<usize>>> = None;
+
             let mut depth = 0;
-            let mut context: Vec<(gimli::DwTag, Option<String>)> = Vec::new();
+            let mut context: Vec<(gimli::DwTag, Option<Arc<str>>)> = Vec::new();
             while let Ok(Some((depth_delta, entry))) = entries_cursor.next_dfs() {
                 depth += depth_delta;
                 debug_assert!(depth >= 1);
@@ -271,7 +590,8 @@ impl<'elffile> DebugDataReader<'elffile> {
                 if tag == gimli::constants::DW_TAG_namespace
                     || tag == gimli::constants::DW_TAG_subprogram
                 {
-                    context.push((tag, get_name_attribute(entry, &self.dwarf, unit).ok()));
+                    let name = get_name_attribute(entry, &self.dwarf, unit).ok();
+                    context.push((tag, name.map(|name| self.names.intern(&name))));
                 } else {
                     context.push((tag, None));
                 }
@@ -279,12 +599,19 @@ impl<'elffile> DebugDataReader<'elffile> {
 
                 if entry.tag() == gimli::constants::DW_TAG_variable {
                     match self.get_global_variable(entry, unit, abbreviations) {
-                        Ok(Some((name, typeref, address))) => {
+                        Ok(Some((name, typeref, address, file_index))) => {
                             let (function, namespaces) = get_varinfo_from_context(&context);
+                            let decl_file = file_index.and_then(|file_index| {
+                                let table = file_table.get_or_insert_with(|| {
+                                    read_file_table(&self.dwarf, unit, &mut self.source_files)
+                                });
+                                table.get(file_index as usize).copied().flatten()
+                            });
                             variables.entry(name).or_default().push(VarInfo {
                                 address,
                                 typeref,
                                 unit_idx,
+                                decl_file,
                                 function,
                                 namespaces,
                             });
@@ -311,13 +638,13 @@ impl<'elffile> DebugDataReader<'elffile> {
     }
 
     // an entry of the type DW_TAG_variable only describes a global variable if there is a name, a type and an address
-    // this function tries to get all three and returns them
+    // this function tries to get all three and returns them, together with the index of the declaring source file
     fn get_global_variable(
         &self,
         entry: &DebuggingInformationEntry<SliceType, usize>,
         unit: &UnitHeader<SliceType>,
         abbrev: &gimli::Abbreviations,
-    ) -> Result<Option<(String, usize, u64)>, String> {
+    ) -> Result<Option<GlobalVariable>, String> {
         match get_location_attribute(self, entry, unit.encoding(), &self.units.list.len() - 1) {
             Some(address) => {
                 // if debugging information entry A has a DW_AT_specification or DW_AT_abstract_origin attribute
@@ -327,8 +654,10 @@ impl<'elffile> DebugDataReader<'elffile> {
                     // the entry refers to a specification, which contains the name and type reference
                     let name = get_name_attribute(&specification_entry, &self.dwarf, unit)?;
                     let typeref = get_typeref_attribute(&specification_entry, unit)?;
+                    let decl_file = get_decl_file_attribute(entry)
+                        .or_else(|| get_decl_file_attribute(&specification_entry));
 
-                    Ok(Some((name, typeref, address)))
+                    Ok(Some((name, typeref, address, decl_file)))
                 } else if let Some(abstract_origin_entry) =
                     get_abstract_origin_attribute(entry, unit, abbrev)
                 {
@@ -338,14 +667,17 @@ impl<'elffile> DebugDataReader<'elffile> {
                     })?;
                     let typeref = get_typeref_attribute(entry, unit)
                         .or_else(|_| get_typeref_attribute(&abstract_origin_entry, unit))?;
+                    let decl_file = get_decl_file_attribute(entry)
+                        .or_else(|| get_decl_file_attribute(&abstract_origin_entry));
 
-                    Ok(Some((name, typeref, address)))
+                    Ok(Some((name, typeref, address, decl_file)))
                 } else {
                     // usual case: there is no specification or abstract origin and all info is part of this entry
                     let name = get_name_attribute(entry, &self.dwarf, unit)?;
                     let typeref = get_typeref_attribute(entry, unit)?;
+                    let decl_file = get_decl_file_attribute(entry);
 
-                    Ok(Some((name, typeref, address)))
+                    Ok(Some((name, typeref, address, decl_file)))
                 }
             }
             None => {
@@ -357,14 +689,14 @@ impl<'elffile> DebugDataReader<'elffile> {
 }
 
 fn get_varinfo_from_context(
-    context: &[(gimli::DwTag, Option<String>)],
-) -> (Option<String>, Vec<String>) {
+    context: &[(gimli::DwTag, Option<Arc<str>>)],
+) -> (Option<Arc<str>>, Vec<Arc<str>>) {
     let function = context
         .iter()
         .rev()
         .find(|(tag, _)| *tag == gimli::constants::DW_TAG_subprogram)
         .and_then(|(_, name)| name.clone());
-    let namespaces: Vec<String> = context
+    let namespaces: Vec<Arc<str>> = context
         .iter()
         .rev()
         .filter_map(|(tag, ns)| {
@@ -414,6 +746,44 @@ pub(crate) fn make_simple_unit_name(debug_data: &DebugData, unit_idx: usize) ->
     Some(file_name.replace('.', "_"))
 }
 
+// read the file name table from the line program of a unit. The table maps the values of
+// DW_AT_decl_file to indices in the list of all source files.
+fn read_file_table(
+    dwarf: &Dwarf<SliceType>,
+    unit_header: &UnitHeader<SliceType>,
+    source_files: &mut IndexSet<String>,
+) -> Vec<Option<usize>> {
+    let Ok(unit) = dwarf.unit(*unit_header) else {
+        return Vec::new();
+    };
+    let Some(line_program) = &unit.line_program else {
+        return Vec::new();
+    };
+    let header = line_program.header();
+    // before DWARF 5 the file names are numbered starting from 1
+    let count = header.file_names().len() as u64 + 1;
+    (0..count)
+        .map(|file_index| {
+            let file = header.file(file_index)?;
+            let file_name = dwarf.attr_string(&unit, file.path_name()).ok()?;
+            let file_name = file_name.to_string_lossy().into_owned();
+            let directory = file
+                .directory(header)
+                .and_then(|dir| dwarf.attr_string(&unit, dir).ok())
+                .map(|dir| dir.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let is_absolute =
+                file_name.starts_with(['/', '\\']) || file_name.get(1..2) == Some(":");
+            let path = if is_absolute || directory.is_empty() {
+                file_name
+            } else {
+                format!("{}/{file_name}", directory.trim_end_matches(['/', '\\']))
+            };
+            Some(source_files.insert_full(path).0)
+        })
+        .collect()
+}
+
 // UnitList holds a list of all UnitHeaders in the Dwarf data for convenient access
 impl<'a> UnitList<'a> {
     fn new() -> Self {
@@ -444,6 +814,55 @@ impl<'a> Index<usize> for UnitList<'a> {
     }
 }
 
+impl FromStr for CaseTieBreak {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "first" => Ok(Self::First),
+            "reject" => Ok(Self::Reject),
+            _ => Err(format!(
+                "invalid tie-break policy \"{s}\", expected first or reject"
+            )),
+        }
+    }
+}
+
+impl FromStr for DuplicateSymbolPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(Self::Error),
+            "first" => Ok(Self::First),
+            "by-file" => Ok(Self::ByFile),
+            _ => Err(format!(
+                "invalid duplicate symbol policy \"{s}\", expected error, first or by-file"
+            )),
+        }
+    }
+}
+
+impl CaseFoldedNames {
+    // select one of several names that match `name` without regard to case; `what` describes the names in error messages
+    pub(crate) fn select<'a>(
+        &self,
+        name: &str,
+        mut candidates: Vec<&'a str>,
+        what: &str,
+    ) -> Result<Option<&'a str>, String> {
+        candidates.sort_unstable();
+        match (candidates.len(), self.tie_break) {
+            (0, _) => Ok(None),
+            (1, _) | (_, CaseTieBreak::First) => Ok(Some(candidates[0])),
+            (_, CaseTieBreak::Reject) => Err(format!(
+                "\"{name}\" matches several {what} that differ only by case: {}",
+                candidates.join(", ")
+            )),
+        }
+    }
+}
+
 impl TypeInfo {
     const MAX_RECURSION_DEPTH: usize = 5;
 
@@ -472,7 +891,7 @@ impl TypeInfo {
         }
     }
 
-    pub(crate) fn get_members(&self) -> Option<&IndexMap<String, (TypeInfo, u64)>> {
+    pub(crate) fn get_members(&self) -> Option<&IndexMap<Arc<str>, (TypeInfo, u64)>> {
         match &self.datatype {
             DwarfDataType::Struct { members, .. }
             | DwarfDataType::Class { members, .. }
@@ -644,8 +1063,8 @@ impl TypeInfo {
     }
 
     fn compare_members(
-        members1: &IndexMap<String, (TypeInfo, u64)>,
-        members2: &IndexMap<String, (TypeInfo, u64)>,
+        members1: &IndexMap<Arc<str>, (TypeInfo, u64)>,
+        members2: &IndexMap<Arc<str>, (TypeInfo, u64)>,
         types: &HashMap<usize, TypeInfo>,
         depth: usize,
     ) -> bool {
@@ -676,6 +1095,80 @@ impl TypeInfo {
         }
         true
     }
+
+    // hash the content of the type, like compare() ignoring the debug info offsets,
+    // which change whenever anything else in the elf file changes
+    pub(crate) fn hash_content<H: Hasher>(&self, types: &HashMap<usize, TypeInfo>, state: &mut H) {
+        self.hash_content_internal(types, state, 0);
+    }
+
+    fn hash_content_internal<H: Hasher>(
+        &self,
+        types: &HashMap<usize, TypeInfo>,
+        state: &mut H,
+        depth: usize,
+    ) {
+        let typeinfo = self.get_reference(types);
+        typeinfo.name.hash(state);
+        std::mem::discriminant(&typeinfo.datatype).hash(state);
+        typeinfo.get_size().hash(state);
+        if depth >= Self::MAX_RECURSION_DEPTH {
+            return;
+        }
+        match &typeinfo.datatype {
+            DwarfDataType::Bitfield {
+                basetype,
+                bit_offset,
+                bit_size,
+            } => {
+                bit_offset.hash(state);
+                bit_size.hash(state);
+                basetype.hash_content_internal(types, state, depth + 1);
+            }
+            DwarfDataType::Pointer(_, dest_offset) => {
+                if let Some(dest_type) = types.get(&dest_offset.0) {
+                    dest_type.hash_content_internal(types, state, depth + 1);
+                }
+            }
+            DwarfDataType::Struct { members, .. } | DwarfDataType::Union { members, .. } => {
+                Self::hash_members(members, types, state, depth);
+            }
+            DwarfDataType::Class {
+                inheritance,
+                members,
+                ..
+            } => {
+                Self::hash_members(inheritance, types, state, depth);
+                Self::hash_members(members, types, state, depth);
+            }
+            DwarfDataType::Enum { enumerators, .. } => enumerators.hash(state),
+            DwarfDataType::Array {
+                dim,
+                stride,
+                arraytype,
+                ..
+            } => {
+                dim.hash(state);
+                stride.hash(state);
+                arraytype.hash_content_internal(types, state, depth + 1);
+            }
+            _ => {}
+        }
+    }
+
+    fn hash_members<H: Hasher>(
+        members: &IndexMap<Arc<str>, (TypeInfo, u64)>,
+        types: &HashMap<usize, TypeInfo>,
+        state: &mut H,
+        depth: usize,
+    ) {
+        members.len().hash(state);
+        for (member_name, (member_type, member_offset)) in members {
+            member_name.hash(state);
+            member_offset.hash(state);
+            member_type.hash_content_internal(types, state, depth + 1);
+        }
+    }
 }
 
 impl Display for TypeInfo {
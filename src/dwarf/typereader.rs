@@ -1,20 +1,25 @@
 use super::{attributes::*, DebugDataReader};
 use super::{DwarfDataType, TypeInfo, VarInfo};
+use crate::intern::StringInterner;
 use gimli::{DebugInfoOffset, DwTag, EndianSlice, EntriesTreeNode, RunTimeEndian, UnitOffset};
 use indexmap::IndexMap;
 use object::Endianness;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 #[derive(Debug)]
 struct WipItemInfo {
     offset: usize,
-    name: Option<String>,
+    name: Option<Arc<str>>,
     tag: DwTag,
 }
 
-struct TypeReaderData {
-    types: HashMap<usize, TypeInfo>,
-    typenames: HashMap<String, Vec<usize>>,
+pub(crate) struct TypeReaderData {
+    pub(crate) types: HashMap<usize, TypeInfo>,
+    pub(crate) typenames: HashMap<Arc<str>, Vec<usize>>,
+    pub(crate) fixed_point: HashMap<usize, f64>,
+    // all names of types, members and enumerators, which are repeated in every unit that uses a type
+    pub(crate) names: StringInterner,
     wip_items: Vec<WipItemInfo>,
 }
 
@@ -23,10 +28,12 @@ impl<'elffile> DebugDataReader<'elffile> {
     pub(crate) fn load_types(
         &mut self,
         variables: &IndexMap<String, Vec<VarInfo>>,
-    ) -> (HashMap<usize, TypeInfo>, HashMap<String, Vec<usize>>) {
+    ) -> TypeReaderData {
         let mut typereader_data = TypeReaderData {
             types: HashMap::<usize, TypeInfo>::new(),
-            typenames: HashMap::<String, Vec<usize>>::new(),
+            typenames: HashMap::<Arc<str>, Vec<usize>>::new(),
+            fixed_point: HashMap::new(),
+            names: std::mem::take(&mut self.names),
             wip_items: Vec::new(),
         };
         // for each variable
@@ -51,7 +58,7 @@ impl<'elffile> DebugDataReader<'elffile> {
             }
         }
 
-        (typereader_data.types, typereader_data.typenames)
+        typereader_data
     }
 
     fn get_type(
@@ -106,6 +113,7 @@ impl<'elffile> DebugDataReader<'elffile> {
             return Ok(t.clone());
         }
 
+        let type_offset = dbginfo_offset.0;
         let (unit, abbrev) = &self.units[current_unit];
         let offset = dbginfo_offset.to_unit_offset(unit).unwrap();
         let mut entries_tree = unit
@@ -113,7 +121,9 @@ impl<'elffile> DebugDataReader<'elffile> {
             .map_err(|err| err.to_string())?;
         let entries_tree_node = entries_tree.root().map_err(|err| err.to_string())?;
         let entry = entries_tree_node.entry();
-        let typename = get_name_attribute(entry, &self.dwarf, unit).ok();
+        let typename = get_name_attribute(entry, &self.dwarf, unit)
+            .ok()
+            .map(|name| typereader_data.names.intern(&name));
         typereader_data.wip_items.push(WipItemInfo::new(
             dbginfo_offset.0,
             typename.clone(),
@@ -123,7 +133,10 @@ impl<'elffile> DebugDataReader<'elffile> {
         let (datatype, inner_name) = match entry.tag() {
             gimli::constants::DW_TAG_base_type => {
                 let (datatype, name) = get_base_type(entry, &self.units[current_unit].0);
-                (datatype, Some(name))
+                if let Some(factor) = get_fixed_point_factor(entry) {
+                    typereader_data.fixed_point.insert(type_offset, factor);
+                }
+                (datatype, Some(typereader_data.names.intern(name)))
             }
             gimli::constants::DW_TAG_pointer_type => {
                 let (unit, _) = &self.units[current_unit];
@@ -166,7 +179,7 @@ impl<'elffile> DebugDataReader<'elffile> {
                             u64::from(unit.encoding().address_size),
                             DebugInfoOffset(0),
                         ),
-                        Some("void".to_string()),
+                        Some(typereader_data.names.intern("void")),
                     )
                 }
                 //DwarfDataType::Pointer(u64::from(unit.encoding().address_size), dest_type)
@@ -174,9 +187,10 @@ impl<'elffile> DebugDataReader<'elffile> {
             gimli::constants::DW_TAG_array_type => {
                 self.get_array_type(entry, current_unit, offset, typereader_data)?
             }
-            gimli::constants::DW_TAG_enumeration_type => {
-                (self.get_enumeration_type(current_unit, offset)?, None)
-            }
+            gimli::constants::DW_TAG_enumeration_type => (
+                self.get_enumeration_type(current_unit, offset, typereader_data)?,
+                None,
+            ),
             gimli::constants::DW_TAG_structure_type => {
                 let size = get_byte_size_attribute(entry)
                     .ok_or_else(|| "missing struct byte size attribute".to_string())?;
@@ -205,6 +219,15 @@ impl<'elffile> DebugDataReader<'elffile> {
                 let (new_cur_unit, dbginfo_offset) =
                     get_type_attribute(entry, &self.units, current_unit)?;
                 let reftype = self.get_type(new_cur_unit, dbginfo_offset, typereader_data)?;
+                // the typedef inherits the scaling of a fixed-point type, or defines it by its name
+                let factor = typereader_data
+                    .fixed_point
+                    .get(&reftype.dbginfo_offset)
+                    .copied()
+                    .or_else(|| get_q_format_factor(typename.as_deref()?, &reftype.datatype));
+                if let Some(factor) = factor {
+                    typereader_data.fixed_point.insert(type_offset, factor);
+                }
                 (reftype.datatype, None)
             }
             gimli::constants::DW_TAG_const_type | gimli::constants::DW_TAG_volatile_type => {
@@ -212,6 +235,10 @@ impl<'elffile> DebugDataReader<'elffile> {
                     get_type_attribute(entry, &self.units, current_unit)
                 {
                     let typeinfo = self.get_type(new_cur_unit, dbginfo_offset, typereader_data)?;
+                    if let Some(factor) = typereader_data.fixed_point.get(&typeinfo.dbginfo_offset)
+                    {
+                        typereader_data.fixed_point.insert(type_offset, *factor);
+                    }
                     (typeinfo.datatype, typeinfo.name)
                 } else {
                     // const void*
@@ -225,7 +252,7 @@ impl<'elffile> DebugDataReader<'elffile> {
                 // function pointer
                 (
                     DwarfDataType::FuncPtr(u64::from(unit.encoding().address_size)),
-                    Some("p_function".to_string()),
+                    Some(typereader_data.names.intern("p_function")),
                 )
             }
             gimli::constants::DW_TAG_unspecified_type => {
@@ -279,7 +306,7 @@ impl<'elffile> DebugDataReader<'elffile> {
         current_unit: usize,
         offset: UnitOffset,
         typereader_data: &mut TypeReaderData,
-    ) -> Result<(DwarfDataType, Option<String>), String> {
+    ) -> Result<(DwarfDataType, Option<Arc<str>>), String> {
         let (unit, abbrev) = &self.units[current_unit];
         let mut entries_tree = unit
             .entries_tree(abbrev, Some(offset))
@@ -354,6 +381,7 @@ impl<'elffile> DebugDataReader<'elffile> {
         &self,
         current_unit: usize,
         offset: UnitOffset,
+        typereader_data: &mut TypeReaderData,
     ) -> Result<DwarfDataType, String> {
         let (unit, abbrev) = &self.units[current_unit];
         let mut entries_tree = unit
@@ -375,7 +403,7 @@ impl<'elffile> DebugDataReader<'elffile> {
                     .map_err(|_| "missing enum item name".to_string())?;
                 let value = get_const_value_attribute(child_entry)
                     .ok_or_else(|| "missing enum item value".to_string())?;
-                enumerators.push((name, value));
+                enumerators.push((typereader_data.names.intern(&name), value));
             }
         }
         Ok(DwarfDataType::Enum { size, enumerators })
@@ -413,10 +441,7 @@ impl<'elffile> DebugDataReader<'elffile> {
             } = &baseclass_type.datatype
             {
                 for (name, (m_type, m_offset)) in baseclass_members {
-                    members.insert(
-                        name.to_owned(),
-                        (m_type.clone(), m_offset + baseclass_offset),
-                    );
+                    members.insert(name.clone(), (m_type.clone(), m_offset + baseclass_offset));
                 }
             }
         }
@@ -433,9 +458,9 @@ impl<'elffile> DebugDataReader<'elffile> {
         entries_tree: EntriesTreeNode<EndianSlice<RunTimeEndian>>,
         current_unit: usize,
         typereader_data: &mut TypeReaderData,
-    ) -> Result<IndexMap<String, (TypeInfo, u64)>, String> {
+    ) -> Result<IndexMap<Arc<str>, (TypeInfo, u64)>, String> {
         let (unit, _) = &self.units[current_unit];
-        let mut members = IndexMap::<String, (TypeInfo, u64)>::new();
+        let mut members = IndexMap::<Arc<str>, (TypeInfo, u64)>::new();
         let mut iter = entries_tree.children();
         while let Ok(Some(child_node)) = iter.next() {
             let child_entry = child_node.entry();
@@ -521,6 +546,7 @@ impl<'elffile> DebugDataReader<'elffile> {
                                     membertype.get_size(),
                                 );
                             }
+                            let name = typereader_data.names.intern(&name);
                             members.insert(name, (membertype, offset));
                         }
                     } else {
@@ -558,9 +584,9 @@ impl<'elffile> DebugDataReader<'elffile> {
         entries_tree: EntriesTreeNode<EndianSlice<RunTimeEndian>>,
         current_unit: usize,
         typereader_data: &mut TypeReaderData,
-    ) -> Result<IndexMap<String, (TypeInfo, u64)>, String> {
+    ) -> Result<IndexMap<Arc<str>, (TypeInfo, u64)>, String> {
         let (unit, _) = &self.units[current_unit];
-        let mut inheritance = IndexMap::<String, (TypeInfo, u64)>::new();
+        let mut inheritance = IndexMap::<Arc<str>, (TypeInfo, u64)>::new();
         let mut iter = entries_tree.children();
         while let Ok(Some(child_node)) = iter.next() {
             let child_entry = child_node.entry();
@@ -587,6 +613,7 @@ impl<'elffile> DebugDataReader<'elffile> {
                 let baseclass_type =
                     self.get_type(new_cur_unit, new_dbginfo_offset, typereader_data)?;
 
+                let baseclass_name = typereader_data.names.intern(&baseclass_name);
                 inheritance.insert(baseclass_name, (baseclass_type, data_location));
             }
         }
@@ -597,7 +624,7 @@ impl<'elffile> DebugDataReader<'elffile> {
 fn get_base_type(
     entry: &gimli::DebuggingInformationEntry<EndianSlice<RunTimeEndian>, usize>,
     unit: &gimli::UnitHeader<EndianSlice<RunTimeEndian>>,
-) -> (DwarfDataType, String) {
+) -> (DwarfDataType, &'static str) {
     let byte_size = get_byte_size_attribute(entry).unwrap_or(1u64);
     let encoding = get_encoding_attribute(entry).unwrap_or(gimli::constants::DW_ATE_unsigned);
     match encoding {
@@ -606,38 +633,86 @@ fn get_base_type(
             // in all other cases DW_AT_pointer is used
             (
                 DwarfDataType::Pointer(u64::from(unit.encoding().address_size), DebugInfoOffset(0)),
-                "unknown".to_string(),
+                "unknown",
             )
         }
         gimli::constants::DW_ATE_float => {
             if byte_size == 8 {
-                (DwarfDataType::Double, "double".to_string())
+                (DwarfDataType::Double, "double")
             } else {
-                (DwarfDataType::Float, "float".to_string())
+                (DwarfDataType::Float, "float")
             }
         }
-        gimli::constants::DW_ATE_signed | gimli::constants::DW_ATE_signed_char => match byte_size {
-            1 => (DwarfDataType::Sint8, "sint8".to_string()),
-            2 => (DwarfDataType::Sint16, "sint16".to_string()),
-            4 => (DwarfDataType::Sint32, "sint32".to_string()),
-            8 => (DwarfDataType::Sint64, "sint64".to_string()),
-            _ => (DwarfDataType::Other(byte_size), "double".to_string()),
+        gimli::constants::DW_ATE_signed
+        | gimli::constants::DW_ATE_signed_char
+        | gimli::constants::DW_ATE_signed_fixed => match byte_size {
+            1 => (DwarfDataType::Sint8, "sint8"),
+            2 => (DwarfDataType::Sint16, "sint16"),
+            4 => (DwarfDataType::Sint32, "sint32"),
+            8 => (DwarfDataType::Sint64, "sint64"),
+            _ => (DwarfDataType::Other(byte_size), "double"),
         },
         gimli::constants::DW_ATE_boolean
         | gimli::constants::DW_ATE_unsigned
-        | gimli::constants::DW_ATE_unsigned_char => match byte_size {
-            1 => (DwarfDataType::Uint8, "uint8".to_string()),
-            2 => (DwarfDataType::Uint16, "uint16".to_string()),
-            4 => (DwarfDataType::Uint32, "uint32".to_string()),
-            8 => (DwarfDataType::Uint64, "uint64".to_string()),
-            _ => (DwarfDataType::Other(byte_size), "other".to_string()),
+        | gimli::constants::DW_ATE_unsigned_char
+        | gimli::constants::DW_ATE_unsigned_fixed => match byte_size {
+            1 => (DwarfDataType::Uint8, "uint8"),
+            2 => (DwarfDataType::Uint16, "uint16"),
+            4 => (DwarfDataType::Uint32, "uint32"),
+            8 => (DwarfDataType::Uint64, "uint64"),
+            _ => (DwarfDataType::Other(byte_size), "other"),
         },
-        _other => (DwarfDataType::Other(byte_size), "other".to_string()),
+        _other => (DwarfDataType::Other(byte_size), "other"),
+    }
+}
+
+// get the scaling factor of a fixed-point base type from DW_AT_binary_scale or DW_AT_decimal_scale
+fn get_fixed_point_factor(
+    entry: &gimli::DebuggingInformationEntry<EndianSlice<RunTimeEndian>, usize>,
+) -> Option<f64> {
+    let encoding = get_encoding_attribute(entry)?;
+    if encoding != gimli::constants::DW_ATE_signed_fixed
+        && encoding != gimli::constants::DW_ATE_unsigned_fixed
+    {
+        return None;
+    }
+    if let Some(scale) = get_scale_attribute(entry, gimli::constants::DW_AT_binary_scale) {
+        Some(2f64.powi(i32::try_from(scale).ok()?))
+    } else if let Some(scale) = get_scale_attribute(entry, gimli::constants::DW_AT_decimal_scale) {
+        Some(10f64.powi(i32::try_from(scale).ok()?))
+    } else {
+        None
+    }
+}
+
+// get the scaling factor of a typedef that follows the naming of Q-formats, e.g. _q15, _iq24 or q31_t
+// Compilers for DSPs (e.g. TI C2000 with IQmath) don't describe these types as fixed-point types in the
+// debug info, so the name is the only indication of the scaling
+fn get_q_format_factor(typename: &str, datatype: &DwarfDataType) -> Option<f64> {
+    let bits = match datatype {
+        DwarfDataType::Sint8 | DwarfDataType::Uint8 => 8,
+        DwarfDataType::Sint16 | DwarfDataType::Uint16 => 16,
+        DwarfDataType::Sint32 | DwarfDataType::Uint32 => 32,
+        DwarfDataType::Sint64 | DwarfDataType::Uint64 => 64,
+        _ => return None,
+    };
+    let name = typename.to_ascii_lowercase();
+    let name = name.strip_prefix('_').unwrap_or(&name);
+    let name = name.strip_prefix('i').unwrap_or(name);
+    let name = name.strip_suffix("_t").unwrap_or(name);
+    let digits = name.strip_prefix('q')?;
+    if digits.is_empty() || digits.len() > 2 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let fractional_bits: i32 = digits.parse().ok()?;
+    if fractional_bits == 0 || fractional_bits >= bits {
+        return None;
     }
+    Some(2f64.powi(-fractional_bits))
 }
 
 impl WipItemInfo {
-    fn new(offset: usize, name: Option<String>, tag: DwTag) -> Self {
+    fn new(offset: usize, name: Option<Arc<str>>, tag: DwTag) -> Self {
         Self { offset, name, tag }
     }
 }
@@ -649,7 +724,7 @@ impl TypeReaderData {
     // The fix is to keep track of in-progress types in self.wip_items, and break the recursion if needed.
     // Now pointers have a new problem: they normally get their names from the pointed-to child type, whose info is not available yet
     // Here we try to recover a name from the wip_items stack
-    fn get_pointer_name(&self, idx: usize) -> Option<String> {
+    fn get_pointer_name(&self, idx: usize) -> Option<Arc<str>> {
         let mut nameidx = idx;
         while nameidx < self.wip_items.len() {
             if self.wip_items[nameidx].name.is_some() {
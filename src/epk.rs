@@ -0,0 +1,99 @@
+//! Management of the EPK (EPROM identifier) in MOD_PAR
+
+use crate::hexfile::HexImage;
+use crate::modpar::get_or_create_mod_par;
+use a2lfile::{A2lFile, AddrEpk, Epk};
+
+/// Set the EPK string in the MOD_PAR of every module. MOD_PAR is created if it does not exist.
+pub fn set_epk(a2l_file: &mut A2lFile, epk: &str) {
+    for module in &mut a2l_file.project.module {
+        get_or_create_mod_par(module).epk = Some(Epk::new(epk.to_string()));
+    }
+}
+
+/// Set the address of the EPK in the MOD_PAR of every module. Any existing ADDR_EPK entries are replaced.
+pub fn set_addr_epk(a2l_file: &mut A2lFile, address: u32) {
+    for module in &mut a2l_file.project.module {
+        get_or_create_mod_par(module).addr_epk = vec![AddrEpk::new(address)];
+    }
+}
+
+/// Check that the EPK string is stored in the memory image at every ADDR_EPK.
+///
+/// On success a description of the verified EPK is returned.
+///
+/// # Errors
+///
+/// Returns an error message if the file has no EPK or ADDR_EPK, or if the content of the image does not match
+pub fn verify_epk(a2l_file: &A2lFile, image: &HexImage) -> Result<String, String> {
+    let mut verified = Vec::new();
+    for module in &a2l_file.project.module {
+        let Some(mod_par) = &module.mod_par else {
+            continue;
+        };
+        let Some(epk) = &mod_par.epk else {
+            continue;
+        };
+        if mod_par.addr_epk.is_empty() {
+            return Err(format!(
+                "Error: MODULE {} has an EPK, but no ADDR_EPK",
+                module.name
+            ));
+        }
+        let expected = epk.identifier.as_bytes();
+        for addr_epk in &mod_par.addr_epk {
+            let Some(actual) = image.read(addr_epk.address, expected.len()) else {
+                return Err(format!(
+                    "Error: the memory image contains no data at ADDR_EPK 0x{:08X}",
+                    addr_epk.address
+                ));
+            };
+            if actual != expected {
+                return Err(format!(
+                    "Error: EPK mismatch at 0x{:08X}: expected \"{}\", found \"{}\"",
+                    addr_epk.address,
+                    epk.identifier,
+                    String::from_utf8_lossy(actual).escape_debug()
+                ));
+            }
+            verified.push(format!(
+                "EPK \"{}\" verified at 0x{:08X}",
+                epk.identifier, addr_epk.address
+            ));
+        }
+    }
+    if verified.is_empty() {
+        Err(
+            "Error: the EPK cannot be verified, because the file does not contain an EPK"
+                .to_string(),
+        )
+    } else {
+        Ok(verified.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_epk() {
+        let mut a2l_file = crate::load::new_a2l();
+        assert!(a2l_file.project.module[0].mod_par.is_none());
+        set_epk(&mut a2l_file, "ABC");
+        set_addr_epk(&mut a2l_file, 0x10010);
+        let mod_par = a2l_file.project.module[0].mod_par.as_ref().unwrap();
+        assert_eq!(mod_par.epk.as_ref().unwrap().identifier, "ABC");
+        assert_eq!(mod_par.addr_epk[0].address, 0x10010);
+
+        let image = HexImage::parse(":020000040001F9\n:0400100041424344E2\n:00000001FF\n").unwrap();
+        assert!(verify_epk(&a2l_file, &image).is_ok());
+
+        set_epk(&mut a2l_file, "ABX");
+        let result = verify_epk(&a2l_file, &image);
+        assert!(result.unwrap_err().contains("mismatch"));
+
+        set_addr_epk(&mut a2l_file, 0x2000);
+        assert!(verify_epk(&a2l_file, &image).is_err());
+    }
+}
@@ -0,0 +1,310 @@
+//! C API for a2ltool
+//!
+//! The functions in this module can be called from C or C++ when a2ltool is built as a cdylib.
+//! The matching header file is include/a2ltool.h, which is generated with
+//! `cbindgen --config cbindgen.toml --output include/a2ltool.h`.
+//!
+//! All functions that can fail return 0 on success and -1 on failure.
+//! The error message of the last failed call on the current thread can be retrieved with [`a2ltool_last_error`].
+//! A panic inside of a function is reported in the same way. This requires a library that was built with
+//! `panic = "unwind"`, e.g. with the profile `release-lib`; with `panic = "abort"` the process is terminated.
+
+use crate::{load, merge, update, DebugData};
+use a2lfile::{A2lError, A2lFile};
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString, OsStr};
+use std::ptr;
+
+/// opaque handle to a loaded a2l file
+pub struct A2lHandle {
+    a2l_file: A2lFile,
+    messages: Vec<CString>,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(msg: &str) {
+    // interior nul bytes cannot be represented in a C string
+    let msg = CString::new(msg.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(msg));
+}
+
+// convert a C string argument to an OsStr. On failure the last error is set
+unsafe fn arg_to_str<'a>(arg: *const c_char, arg_name: &str) -> Option<&'a OsStr> {
+    if arg.is_null() {
+        set_last_error(&format!("{arg_name} must not be NULL"));
+        return None;
+    }
+    match CStr::from_ptr(arg).to_str() {
+        Ok(text) => Some(OsStr::new(text)),
+        Err(_) => {
+            set_last_error(&format!("{arg_name} is not valid UTF-8"));
+            None
+        }
+    }
+}
+
+// run the body of an API function. A panic must not unwind into the caller, so it is caught and
+// reported through the last error, and `error_value` is returned instead
+fn catch_panic<T>(error_value: T, body: impl FnOnce() -> T) -> T {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(body)).unwrap_or_else(|payload| {
+        let msg = payload
+            .downcast_ref::<&str>()
+            .map(ToString::to_string)
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        set_last_error(&format!("internal error: {msg}"));
+        error_value
+    })
+}
+
+fn add_messages(handle: &mut A2lHandle, msgs: impl IntoIterator<Item = String>) {
+    for msg in msgs {
+        handle
+            .messages
+            .push(CString::new(msg.replace('\0', " ")).unwrap_or_default());
+    }
+}
+
+/// Get the error message of the last call that failed on the current thread.
+///
+/// Returns NULL if no error has occurred. The string remains valid until the next failing call on this thread.
+#[no_mangle]
+pub extern "C" fn a2ltool_last_error() -> *const c_char {
+    catch_panic(ptr::null(), || {
+        LAST_ERROR.with(|last_error| {
+            last_error
+                .borrow()
+                .as_ref()
+                .map_or(ptr::null(), |msg| msg.as_ptr())
+        })
+    })
+}
+
+/// Load an a2l file.
+///
+/// Returns NULL if the file could not be loaded. The handle must be released with [`a2ltool_free`].
+///
+/// # Safety
+///
+/// `filename` must be a valid pointer to a nul-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn a2ltool_load(filename: *const c_char, strict: bool) -> *mut A2lHandle {
+    catch_panic(ptr::null_mut(), || {
+        let Some(filename) = arg_to_str(filename, "filename") else {
+            return ptr::null_mut();
+        };
+        let mut log_msgs = Vec::<A2lError>::new();
+        match load::load_a2l(filename, None, strict, &mut log_msgs) {
+            Ok(a2l_file) => {
+                let mut handle = A2lHandle {
+                    a2l_file,
+                    messages: Vec::new(),
+                };
+                add_messages(&mut handle, log_msgs.iter().map(ToString::to_string));
+                Box::into_raw(Box::new(handle))
+            }
+            Err(err) => {
+                set_last_error(&err);
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Create a new, empty a2l file.
+///
+/// The handle must be released with [`a2ltool_free`].
+#[no_mangle]
+pub extern "C" fn a2ltool_create() -> *mut A2lHandle {
+    catch_panic(ptr::null_mut(), || {
+        Box::into_raw(Box::new(A2lHandle {
+            a2l_file: load::new_a2l(),
+            messages: Vec::new(),
+        }))
+    })
+}
+
+/// Update the addresses of all objects in the a2l file using the debug info of an elf file.
+///
+/// If `preserve_unknown` is true, then objects that are not found in the elf file are kept and their address is set to zero.
+/// Otherwise these objects are removed.
+///
+/// # Safety
+///
+/// `handle` must be a handle returned by [`a2ltool_load`] or [`a2ltool_create`] that has not been freed.
+/// `elf_filename` must be a valid pointer to a nul-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn a2ltool_update(
+    handle: *mut A2lHandle,
+    elf_filename: *const c_char,
+    preserve_unknown: bool,
+) -> c_int {
+    catch_panic(-1, || {
+        let Some(handle) = handle.as_mut() else {
+            set_last_error("handle must not be NULL");
+            return -1;
+        };
+        let Some(elf_filename) = arg_to_str(elf_filename, "elf_filename") else {
+            return -1;
+        };
+        match DebugData::load(elf_filename, false) {
+            Ok(debug_data) => {
+                let mut log_msgs = Vec::new();
+                update::update_addresses(
+                    &mut handle.a2l_file,
+                    &debug_data,
+                    &mut log_msgs,
+                    preserve_unknown,
+                    true,
+                );
+                add_messages(handle, log_msgs);
+                0
+            }
+            Err(err) => {
+                set_last_error(&err);
+                -1
+            }
+        }
+    })
+}
+
+/// Merge the content of another a2l file into the modules of this file.
+///
+/// # Safety
+///
+/// `handle` must be a handle returned by [`a2ltool_load`] or [`a2ltool_create`] that has not been freed.
+/// `filename` must be a valid pointer to a nul-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn a2ltool_merge(handle: *mut A2lHandle, filename: *const c_char) -> c_int {
+    catch_panic(-1, || {
+        let Some(handle) = handle.as_mut() else {
+            set_last_error("handle must not be NULL");
+            return -1;
+        };
+        let Some(filename) = arg_to_str(filename, "filename") else {
+            return -1;
+        };
+        match merge::merge_module_file(&mut handle.a2l_file, filename, false) {
+            Ok(()) => 0,
+            Err(err) => {
+                set_last_error(&err);
+                -1
+            }
+        }
+    })
+}
+
+/// Write the a2l file.
+///
+/// # Safety
+///
+/// `handle` must be a handle returned by [`a2ltool_load`] or [`a2ltool_create`] that has not been freed.
+/// `filename` must be a valid pointer to a nul-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn a2ltool_write(handle: *mut A2lHandle, filename: *const c_char) -> c_int {
+    catch_panic(-1, || {
+        let Some(handle) = handle.as_mut() else {
+            set_last_error("handle must not be NULL");
+            return -1;
+        };
+        let Some(filename) = arg_to_str(filename, "filename") else {
+            return -1;
+        };
+        handle.a2l_file.sort_new_items();
+        let banner = format!("a2ltool {}", env!("CARGO_PKG_VERSION"));
+        match handle.a2l_file.write(filename, Some(&banner)) {
+            Ok(()) => 0,
+            Err(err) => {
+                set_last_error(&err.to_string());
+                -1
+            }
+        }
+    })
+}
+
+/// Get the number of messages that were generated by the operations on this handle.
+///
+/// # Safety
+///
+/// `handle` must be a handle returned by [`a2ltool_load`] or [`a2ltool_create`] that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn a2ltool_message_count(handle: *const A2lHandle) -> usize {
+    catch_panic(0, || {
+        handle.as_ref().map_or(0, |handle| handle.messages.len())
+    })
+}
+
+/// Get a message that was generated by an operation on this handle.
+///
+/// Returns NULL if the index is out of range. The string remains valid until the handle is freed.
+///
+/// # Safety
+///
+/// `handle` must be a handle returned by [`a2ltool_load`] or [`a2ltool_create`] that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn a2ltool_message(handle: *const A2lHandle, index: usize) -> *const c_char {
+    catch_panic(ptr::null(), || {
+        handle
+            .as_ref()
+            .and_then(|handle| handle.messages.get(index))
+            .map_or(ptr::null(), |msg| msg.as_ptr())
+    })
+}
+
+/// Release a handle. Passing NULL is allowed and has no effect.
+///
+/// # Safety
+///
+/// `handle` must be NULL or a handle returned by [`a2ltool_load`] or [`a2ltool_create`] that has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn a2ltool_free(handle: *mut A2lHandle) {
+    catch_panic((), || {
+        if !handle.is_null() {
+            drop(Box::from_raw(handle));
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ffi() {
+        unsafe {
+            let handle = a2ltool_load(c"does_not_exist.a2l".as_ptr(), false);
+            assert!(handle.is_null());
+            assert!(!a2ltool_last_error().is_null());
+
+            let handle = a2ltool_load(c"tests/update_test1.a2l".as_ptr(), false);
+            assert!(!handle.is_null());
+            let result = a2ltool_update(handle, c"tests/elffiles/update_test.elf".as_ptr(), true);
+            assert_eq!(result, 0);
+            assert_eq!(a2ltool_update(handle, ptr::null(), true), -1);
+            assert_eq!(
+                a2ltool_message(handle, a2ltool_message_count(handle)),
+                ptr::null()
+            );
+
+            let created = a2ltool_create();
+            assert_eq!(
+                a2ltool_merge(created, c"tests/update_test1.a2l".as_ptr()),
+                0
+            );
+            let created_ref = &*created;
+            assert!(!created_ref.a2l_file.project.module[0].instance.is_empty());
+
+            a2ltool_free(handle);
+            a2ltool_free(created);
+            a2ltool_free(ptr::null_mut());
+
+            // a panic is reported as an error
+            assert_eq!(catch_panic(-1, || -> c_int { panic!("test panic") }), -1);
+            let last_error = CStr::from_ptr(a2ltool_last_error()).to_string_lossy();
+            assert!(last_error.contains("test panic"));
+        }
+    }
+}
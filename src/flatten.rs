@@ -0,0 +1,717 @@
+//! Expansion of INSTANCEs into classic MEASUREMENTs, CHARACTERISTICs, AXIS_PTS and BLOBs
+
+use crate::memmap::{axis_pts_size, characteristic_size};
+use a2lfile::{
+    A2lFile, A2lObject, AxisPts, Blob, Characteristic, CharacteristicType, EcuAddress, Instance,
+    MatrixDim, Measurement, Module, Overwrite, SymbolLink, TypedefAxis, TypedefCharacteristic,
+    TypedefMeasurement, TypedefStructure,
+};
+use std::collections::HashSet;
+
+// nesting limit for TYPEDEF_STRUCTUREs, which protects against circular references
+const MAX_DEPTH: usize = 32;
+
+/// The result of [`flatten_typedefs`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FlattenSummary {
+    /// number of INSTANCEs that were expanded
+    pub instances_flattened: usize,
+    /// number of MEASUREMENTs, CHARACTERISTICs, AXIS_PTS and BLOBs that were created
+    pub objects_created: usize,
+    /// number of TYPEDEF_* blocks that were removed
+    pub typedefs_removed: usize,
+}
+
+// the objects created for one INSTANCE
+#[derive(Default)]
+struct FlatObjects {
+    axis_pts: Vec<AxisPts>,
+    blob: Vec<Blob>,
+    characteristic: Vec<Characteristic>,
+    measurement: Vec<Measurement>,
+}
+
+struct Flattener<'a> {
+    module: &'a Module,
+    instance: &'a Instance,
+    objects: FlatObjects,
+}
+
+/// Expand every INSTANCE into equivalent classic MEASUREMENTs, CHARACTERISTICs, AXIS_PTS and BLOBs.
+///
+/// The objects are named after the elements of the INSTANCE, e.g. `inst.component` or `inst[2].component`,
+/// so existing references to INSTANCE elements remain valid. Their addresses are calculated from the
+/// STRUCTURE_COMPONENT offsets. OVERWRITE settings of the INSTANCE are applied to the new objects.
+/// References to a complete INSTANCE in GROUPs are replaced by references to all of its objects.
+///
+/// INSTANCEs that can't be expanded, e.g. because they contain pointers, are kept and reported.
+/// All TYPEDEF_* blocks that are not used by the remaining INSTANCEs are removed.
+pub fn flatten_typedefs(a2l_file: &mut A2lFile, log_msgs: &mut Vec<String>) -> FlattenSummary {
+    let mut summary = FlattenSummary::default();
+    for module in &mut a2l_file.project.module {
+        let instances = std::mem::take(&mut module.instance);
+        let mut kept_instances = Vec::new();
+        let mut all_objects = FlatObjects::default();
+        for instance in instances {
+            let mut flattener = Flattener {
+                module: &*module,
+                instance: &instance,
+                objects: FlatObjects::default(),
+            };
+            match flattener.flatten() {
+                Ok(()) => {
+                    let objects = flattener.objects;
+                    let count = objects.axis_pts.len()
+                        + objects.blob.len()
+                        + objects.characteristic.len()
+                        + objects.measurement.len();
+                    log_msgs.push(format!(
+                        "INSTANCE {} was expanded into {count} objects",
+                        instance.name
+                    ));
+                    summary.instances_flattened += 1;
+                    summary.objects_created += count;
+                    replace_group_references(module, &instance.name, &objects);
+                    all_objects.axis_pts.extend(objects.axis_pts);
+                    all_objects.blob.extend(objects.blob);
+                    all_objects.characteristic.extend(objects.characteristic);
+                    all_objects.measurement.extend(objects.measurement);
+                }
+                Err(errmsg) => {
+                    log_msgs.push(format!(
+                        "INSTANCE {} on line {} was not expanded: {errmsg}",
+                        instance.name,
+                        instance.get_line()
+                    ));
+                    kept_instances.push(instance);
+                }
+            }
+        }
+        module.instance = kept_instances;
+        module.axis_pts.extend(all_objects.axis_pts);
+        module.blob.extend(all_objects.blob);
+        module.characteristic.extend(all_objects.characteristic);
+        module.measurement.extend(all_objects.measurement);
+
+        summary.typedefs_removed += remove_unused_typedefs(module);
+    }
+    summary
+}
+
+impl Flattener<'_> {
+    fn flatten(&mut self) -> Result<(), String> {
+        let instance = self.instance;
+        if instance.address_type.is_some() {
+            return Err(
+                "the INSTANCE is a pointer, so the addresses of its elements are not known"
+                    .to_string(),
+            );
+        }
+        let dims = instance
+            .matrix_dim
+            .as_ref()
+            .map_or(Vec::new(), |md| md.dim_list.clone());
+        let symbol = instance
+            .symbol_link
+            .as_ref()
+            .filter(|sl| sl.offset == 0)
+            .map(|sl| sl.symbol_name.clone());
+        self.expand(
+            &instance.name,
+            &instance.type_ref,
+            instance.start_address,
+            &dims,
+            symbol.as_deref(),
+            0,
+        )
+    }
+
+    // create the objects for an element of the INSTANCE, which has the type type_ref
+    fn expand(
+        &mut self,
+        name: &str,
+        type_ref: &str,
+        address: u32,
+        dims: &[u16],
+        symbol: Option<&str>,
+        depth: usize,
+    ) -> Result<(), String> {
+        if depth > MAX_DEPTH {
+            return Err(format!(
+                "the TYPEDEF_STRUCTUREs are nested more than {MAX_DEPTH} levels deep"
+            ));
+        }
+        let module = self.module;
+        if let Some(td_struct) = module
+            .typedef_structure
+            .iter()
+            .find(|td| td.name == type_ref)
+        {
+            for (idx, suffix) in element_indices(dims) {
+                let elem_address = address + idx * td_struct.total_size;
+                let elem_name = format!("{name}{suffix}");
+                let elem_symbol = symbol.map(|sym| append_to_symbol(sym, &suffix));
+                self.expand_struct(
+                    &elem_name,
+                    td_struct,
+                    elem_address,
+                    elem_symbol.as_deref(),
+                    depth,
+                )?;
+            }
+        } else if let Some(td_meas) = module
+            .typedef_measurement
+            .iter()
+            .find(|td| td.name == type_ref)
+        {
+            // arrays of measurements are represented by a single MEASUREMENT with a larger MATRIX_DIM
+            let measurement = self.make_measurement(name, td_meas, address, dims, symbol);
+            self.objects.measurement.push(measurement);
+        } else if let Some(td_char) = module
+            .typedef_characteristic
+            .iter()
+            .find(|td| td.name == type_ref)
+        {
+            if matches!(
+                td_char.characteristic_type,
+                CharacteristicType::Value | CharacteristicType::ValBlk
+            ) {
+                let characteristic = self.make_characteristic(name, td_char, address, dims, symbol);
+                self.objects.characteristic.push(characteristic);
+            } else {
+                // curves, maps etc. can't be combined into one object; each array element becomes a separate CHARACTERISTIC
+                let stride = characteristic_size(
+                    module,
+                    &self.make_characteristic(name, td_char, address, &[], symbol),
+                );
+                for (idx, suffix) in element_indices(dims) {
+                    let elem_symbol = symbol.map(|sym| append_to_symbol(sym, &suffix));
+                    let characteristic = self.make_characteristic(
+                        &format!("{name}{suffix}"),
+                        td_char,
+                        address + idx * stride,
+                        &[],
+                        elem_symbol.as_deref(),
+                    );
+                    self.objects.characteristic.push(characteristic);
+                }
+            }
+        } else if let Some(td_axis) = module.typedef_axis.iter().find(|td| td.name == type_ref) {
+            let stride = axis_pts_size(module, &self.make_axis_pts(name, td_axis, address, symbol));
+            for (idx, suffix) in element_indices(dims) {
+                let elem_symbol = symbol.map(|sym| append_to_symbol(sym, &suffix));
+                let axis_pts = self.make_axis_pts(
+                    &format!("{name}{suffix}"),
+                    td_axis,
+                    address + idx * stride,
+                    elem_symbol.as_deref(),
+                );
+                self.objects.axis_pts.push(axis_pts);
+            }
+        } else if let Some(td_blob) = module.typedef_blob.iter().find(|td| td.name == type_ref) {
+            if td_blob.address_type.is_some() {
+                return Err(format!("the element {name} is a pointer"));
+            }
+            for (idx, suffix) in element_indices(dims) {
+                let mut blob = Blob::new(
+                    format!("{name}{suffix}"),
+                    long_identifier(&td_blob.long_identifier, self.instance),
+                    address + idx * td_blob.size,
+                    td_blob.size,
+                );
+                blob.calibration_access = self.instance.calibration_access.clone();
+                blob.ecu_address_extension = self.instance.ecu_address_extension.clone();
+                blob.max_refresh = self.instance.max_refresh.clone();
+                blob.symbol_link =
+                    symbol.map(|sym| SymbolLink::new(append_to_symbol(sym, &suffix), 0));
+                // display the address as hex
+                blob.get_layout_mut().item_location.2 .1 = true;
+                self.objects.blob.push(blob);
+            }
+        } else {
+            return Err(format!("{type_ref} does not refer to any TYPEDEF_*"));
+        }
+        Ok(())
+    }
+
+    fn expand_struct(
+        &mut self,
+        name: &str,
+        td_struct: &TypedefStructure,
+        address: u32,
+        symbol: Option<&str>,
+        depth: usize,
+    ) -> Result<(), String> {
+        if td_struct.address_type.is_some() {
+            return Err(format!("the element {name} is a pointer"));
+        }
+        for component in &td_struct.structure_component {
+            let comp_name = format!("{name}.{}", component.component_name);
+            if component.address_type.is_some() {
+                return Err(format!("the element {comp_name} is a pointer"));
+            }
+            let dims = component
+                .matrix_dim
+                .as_ref()
+                .map_or(Vec::new(), |md| md.dim_list.clone());
+            let comp_symbol =
+                symbol.map(|sym| append_to_symbol(sym, &format!(".{}", component.component_name)));
+            self.expand(
+                &comp_name,
+                &component.component_type,
+                address + component.address_offset,
+                &dims,
+                comp_symbol.as_deref(),
+                depth + 1,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn make_measurement(
+        &self,
+        name: &str,
+        td_meas: &TypedefMeasurement,
+        address: u32,
+        dims: &[u16],
+        symbol: Option<&str>,
+    ) -> Measurement {
+        let mut measurement = Measurement::new(
+            name.to_string(),
+            long_identifier(&td_meas.long_identifier, self.instance),
+            td_meas.datatype,
+            td_meas.conversion.clone(),
+            td_meas.resolution,
+            td_meas.accuracy,
+            td_meas.lower_limit,
+            td_meas.upper_limit,
+        );
+        // create an ECU_ADDRESS attribute, and set it to hex display mode
+        let mut ecu_address = EcuAddress::new(address);
+        ecu_address.get_layout_mut().item_location.0 .1 = true;
+        measurement.ecu_address = Some(ecu_address);
+        measurement.address_type = td_meas.address_type.clone();
+        measurement.bit_mask = td_meas.bit_mask.clone();
+        measurement.bit_operation = td_meas.bit_operation.clone();
+        measurement.byte_order = td_meas.byte_order.clone();
+        measurement.discrete = td_meas.discrete.clone();
+        measurement.error_mask = td_meas.error_mask.clone();
+        measurement.format = td_meas.format.clone();
+        measurement.layout = td_meas.layout.clone();
+        measurement.matrix_dim = combine_dims(dims, td_meas.matrix_dim.as_ref(), None);
+        measurement.phys_unit = td_meas.phys_unit.clone();
+        measurement.ecu_address_extension = self.instance.ecu_address_extension.clone();
+        measurement.max_refresh = self.instance.max_refresh.clone();
+        measurement.symbol_link = symbol.map(|sym| SymbolLink::new(sym.to_string(), 0));
+
+        if let Some(overwrite) = self.find_overwrite(name, 0) {
+            if let Some(conversion) = &overwrite.conversion {
+                measurement.conversion.clone_from(&conversion.name);
+            }
+            if let Some(limits) = &overwrite.limits {
+                measurement.lower_limit = limits.lower_limit;
+                measurement.upper_limit = limits.upper_limit;
+            }
+            if overwrite.format.is_some() {
+                measurement.format.clone_from(&overwrite.format);
+            }
+            if overwrite.phys_unit.is_some() {
+                measurement.phys_unit.clone_from(&overwrite.phys_unit);
+            }
+        }
+        measurement
+    }
+
+    fn make_characteristic(
+        &self,
+        name: &str,
+        td_char: &TypedefCharacteristic,
+        address: u32,
+        dims: &[u16],
+        symbol: Option<&str>,
+    ) -> Characteristic {
+        let matrix_dim = combine_dims(
+            dims,
+            td_char.matrix_dim.as_ref(),
+            td_char.number.as_ref().map(|number| number.number),
+        );
+        let ctype =
+            if matrix_dim.is_some() && td_char.characteristic_type == CharacteristicType::Value {
+                CharacteristicType::ValBlk
+            } else {
+                td_char.characteristic_type
+            };
+        let mut characteristic = Characteristic::new(
+            name.to_string(),
+            long_identifier(&td_char.long_identifier, self.instance),
+            ctype,
+            address,
+            td_char.record_layout.clone(),
+            td_char.max_diff,
+            td_char.conversion.clone(),
+            td_char.lower_limit,
+            td_char.upper_limit,
+        );
+        // enable hex mode for the address (item 3 in the CHARACTERISTIC)
+        characteristic.get_layout_mut().item_location.3 .1 = true;
+        characteristic.axis_descr.clone_from(&td_char.axis_descr);
+        characteristic.bit_mask = td_char.bit_mask.clone();
+        characteristic.byte_order = td_char.byte_order.clone();
+        characteristic.discrete = td_char.discrete.clone();
+        characteristic.encoding = td_char.encoding.clone();
+        characteristic.extended_limits = td_char.extended_limits.clone();
+        characteristic.format = td_char.format.clone();
+        characteristic.phys_unit = td_char.phys_unit.clone();
+        characteristic.step_size = td_char.step_size.clone();
+        if matches!(ctype, CharacteristicType::ValBlk) {
+            characteristic.matrix_dim = matrix_dim;
+        } else {
+            characteristic.matrix_dim = td_char.matrix_dim.clone();
+            characteristic.number = td_char.number.clone();
+        }
+        characteristic.calibration_access = self.instance.calibration_access.clone();
+        characteristic.ecu_address_extension = self.instance.ecu_address_extension.clone();
+        characteristic.max_refresh = self.instance.max_refresh.clone();
+        characteristic.read_only = self.instance.read_only.clone();
+        characteristic.symbol_link = symbol.map(|sym| SymbolLink::new(sym.to_string(), 0));
+
+        if let Some(overwrite) = self.find_overwrite(name, 0) {
+            if let Some(conversion) = &overwrite.conversion {
+                characteristic.conversion.clone_from(&conversion.name);
+            }
+            if let Some(limits) = &overwrite.limits {
+                characteristic.lower_limit = limits.lower_limit;
+                characteristic.upper_limit = limits.upper_limit;
+            }
+            if overwrite.extended_limits.is_some() {
+                characteristic
+                    .extended_limits
+                    .clone_from(&overwrite.extended_limits);
+            }
+            if overwrite.format.is_some() {
+                characteristic.format.clone_from(&overwrite.format);
+            }
+            if overwrite.phys_unit.is_some() {
+                characteristic.phys_unit.clone_from(&overwrite.phys_unit);
+            }
+        }
+        for (idx, axis_descr) in characteristic.axis_descr.iter_mut().enumerate() {
+            if let Some(overwrite) = self.find_overwrite(name, idx as u32 + 1) {
+                if let Some(conversion) = &overwrite.conversion {
+                    axis_descr.conversion.clone_from(&conversion.name);
+                }
+                if let Some(input_quantity) = &overwrite.input_quantity {
+                    axis_descr.input_quantity.clone_from(&input_quantity.name);
+                }
+                if let Some(limits) = &overwrite.limits {
+                    axis_descr.lower_limit = limits.lower_limit;
+                    axis_descr.upper_limit = limits.upper_limit;
+                }
+                if overwrite.extended_limits.is_some() {
+                    axis_descr
+                        .extended_limits
+                        .clone_from(&overwrite.extended_limits);
+                }
+                if overwrite.format.is_some() {
+                    axis_descr.format.clone_from(&overwrite.format);
+                }
+                if overwrite.monotony.is_some() {
+                    axis_descr.monotony.clone_from(&overwrite.monotony);
+                }
+                if overwrite.phys_unit.is_some() {
+                    axis_descr.phys_unit.clone_from(&overwrite.phys_unit);
+                }
+            }
+        }
+        characteristic
+    }
+
+    fn make_axis_pts(
+        &self,
+        name: &str,
+        td_axis: &TypedefAxis,
+        address: u32,
+        symbol: Option<&str>,
+    ) -> AxisPts {
+        let mut axis_pts = AxisPts::new(
+            name.to_string(),
+            long_identifier(&td_axis.long_identifier, self.instance),
+            address,
+            td_axis.input_quantity.clone(),
+            td_axis.record_layout.clone(),
+            td_axis.max_diff,
+            td_axis.conversion.clone(),
+            td_axis.max_axis_points,
+            td_axis.lower_limit,
+            td_axis.upper_limit,
+        );
+        // display the address as hex
+        axis_pts.get_layout_mut().item_location.2 .1 = true;
+        axis_pts.byte_order = td_axis.byte_order.clone();
+        axis_pts.deposit = td_axis.deposit.clone();
+        axis_pts.extended_limits = td_axis.extended_limits.clone();
+        axis_pts.format = td_axis.format.clone();
+        axis_pts.monotony = td_axis.monotony.clone();
+        axis_pts.phys_unit = td_axis.phys_unit.clone();
+        axis_pts.step_size = td_axis.step_size.clone();
+        axis_pts.calibration_access = self.instance.calibration_access.clone();
+        axis_pts.ecu_address_extension = self.instance.ecu_address_extension.clone();
+        axis_pts.max_refresh = self.instance.max_refresh.clone();
+        axis_pts.read_only = self.instance.read_only.clone();
+        axis_pts.symbol_link = symbol.map(|sym| SymbolLink::new(sym.to_string(), 0));
+
+        if let Some(overwrite) = self.find_overwrite(name, 0) {
+            if let Some(conversion) = &overwrite.conversion {
+                axis_pts.conversion.clone_from(&conversion.name);
+            }
+            if let Some(input_quantity) = &overwrite.input_quantity {
+                axis_pts.input_quantity.clone_from(&input_quantity.name);
+            }
+            if let Some(limits) = &overwrite.limits {
+                axis_pts.lower_limit = limits.lower_limit;
+                axis_pts.upper_limit = limits.upper_limit;
+            }
+            if overwrite.extended_limits.is_some() {
+                axis_pts
+                    .extended_limits
+                    .clone_from(&overwrite.extended_limits);
+            }
+            if overwrite.format.is_some() {
+                axis_pts.format.clone_from(&overwrite.format);
+            }
+            if overwrite.monotony.is_some() {
+                axis_pts.monotony.clone_from(&overwrite.monotony);
+            }
+            if overwrite.phys_unit.is_some() {
+                axis_pts.phys_unit.clone_from(&overwrite.phys_unit);
+            }
+        }
+        axis_pts
+    }
+
+    // an OVERWRITE can name the element either with or without the name of the INSTANCE
+    fn find_overwrite(&self, name: &str, axis_number: u32) -> Option<&Overwrite> {
+        let relative_name = name
+            .strip_prefix(&self.instance.name)
+            .map(|rest| rest.trim_start_matches('.'));
+        self.instance.overwrite.iter().find(|overwrite| {
+            overwrite.axis_number == axis_number
+                && (overwrite.name == name || Some(overwrite.name.as_str()) == relative_name)
+        })
+    }
+}
+
+// the flat index and the name suffix (e.g. "[1][2]") of each element of an array with the given dimensions
+// a scalar is represented by a single element with an empty suffix
+fn element_indices(dims: &[u16]) -> Vec<(u32, String)> {
+    let mut elements = vec![(0, String::new())];
+    for dim in dims {
+        elements = elements
+            .into_iter()
+            .flat_map(|(idx, suffix)| {
+                (0..u32::from(*dim))
+                    .map(move |pos| (idx * u32::from(*dim) + pos, format!("{suffix}[{pos}]")))
+            })
+            .collect();
+    }
+    elements
+}
+
+// the MATRIX_DIM of an array of elements, which may be arrays themselves
+fn combine_dims(
+    outer: &[u16],
+    inner: Option<&MatrixDim>,
+    number: Option<u16>,
+) -> Option<MatrixDim> {
+    let mut dim_list = outer.to_vec();
+    if let Some(inner) = inner {
+        dim_list.extend(inner.dim_list.iter().filter(|dim| **dim > 1));
+    } else if let Some(number) = number {
+        dim_list.push(number);
+    }
+    if dim_list.iter().all(|dim| *dim <= 1) {
+        None
+    } else {
+        let mut matrix_dim = MatrixDim::new();
+        matrix_dim.dim_list = dim_list;
+        Some(matrix_dim)
+    }
+}
+
+// insert the element path before any additional information like {CompileUnit:...}
+fn append_to_symbol(symbol: &str, path: &str) -> String {
+    let (base, extra) = symbol.split_at(symbol.find('{').unwrap_or(symbol.len()));
+    format!("{base}{path}{extra}")
+}
+
+fn long_identifier(typedef_long_identifier: &str, instance: &Instance) -> String {
+    if typedef_long_identifier.is_empty() {
+        instance.long_identifier.clone()
+    } else {
+        typedef_long_identifier.to_string()
+    }
+}
+
+// a GROUP that references the complete INSTANCE now references all of its objects
+fn replace_group_references(module: &mut Module, instance_name: &str, objects: &FlatObjects) {
+    let characteristic_names: Vec<String> = objects
+        .characteristic
+        .iter()
+        .map(|chr| chr.name.clone())
+        .chain(objects.axis_pts.iter().map(|ap| ap.name.clone()))
+        .chain(objects.blob.iter().map(|blob| blob.name.clone()))
+        .collect();
+    let measurement_names: Vec<String> = objects
+        .measurement
+        .iter()
+        .map(|meas| meas.name.clone())
+        .collect();
+    for group in &mut module.group {
+        if let Some(ref_characteristic) = &mut group.ref_characteristic {
+            replace_name(
+                &mut ref_characteristic.identifier_list,
+                instance_name,
+                &characteristic_names,
+            );
+        }
+        if let Some(ref_measurement) = &mut group.ref_measurement {
+            replace_name(
+                &mut ref_measurement.identifier_list,
+                instance_name,
+                &measurement_names,
+            );
+        }
+    }
+}
+
+fn replace_name(list: &mut Vec<String>, name: &str, new_names: &[String]) {
+    if let Some(pos) = list.iter().position(|item| item == name) {
+        list.splice(pos..=pos, new_names.iter().cloned());
+    }
+}
+
+// remove all TYPEDEF_* blocks that are not used by the remaining INSTANCEs
+fn remove_unused_typedefs(module: &mut Module) -> usize {
+    let mut used: HashSet<&str> = HashSet::new();
+    let mut pending: Vec<&str> = module
+        .instance
+        .iter()
+        .map(|inst| inst.type_ref.as_str())
+        .collect();
+    while let Some(name) = pending.pop() {
+        if used.insert(name) {
+            if let Some(td_struct) = module.typedef_structure.iter().find(|td| td.name == name) {
+                pending.extend(
+                    td_struct
+                        .structure_component
+                        .iter()
+                        .map(|sc| sc.component_type.as_str()),
+                );
+            }
+        }
+    }
+    let used: HashSet<String> = used.into_iter().map(ToString::to_string).collect();
+
+    let count_before = module.typedef_axis.len()
+        + module.typedef_blob.len()
+        + module.typedef_characteristic.len()
+        + module.typedef_measurement.len()
+        + module.typedef_structure.len();
+    module.typedef_axis.retain(|td| used.contains(&td.name));
+    module.typedef_blob.retain(|td| used.contains(&td.name));
+    module
+        .typedef_characteristic
+        .retain(|td| used.contains(&td.name));
+    module
+        .typedef_measurement
+        .retain(|td| used.contains(&td.name));
+    module
+        .typedef_structure
+        .retain(|td| used.contains(&td.name));
+    count_before
+        - (module.typedef_axis.len()
+            + module.typedef_blob.len()
+            + module.typedef_characteristic.len()
+            + module.typedef_measurement.len()
+            + module.typedef_structure.len())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_flatten_typedefs() {
+        let a2l_text = r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin INSTANCE params "" Params 0x1000
+                    MATRIX_DIM 2
+                    SYMBOL_LINK "params" 0
+                    /begin OVERWRITE params[1].gain 0
+                        CONVERSION CM
+                    /end OVERWRITE
+                /end INSTANCE
+                /begin INSTANCE ptr "" Params 0x2000
+                    ADDRESS_TYPE PLONG
+                /end INSTANCE
+                /begin TYPEDEF_STRUCTURE Params "" 0x8
+                    /begin STRUCTURE_COMPONENT gain Gain 0 /end STRUCTURE_COMPONENT
+                    /begin STRUCTURE_COMPONENT values Value 4
+                        MATRIX_DIM 2
+                    /end STRUCTURE_COMPONENT
+                /end TYPEDEF_STRUCTURE
+                /begin TYPEDEF_CHARACTERISTIC Gain "" VALUE RL 0 NO_COMPU_METHOD 0 100 /end TYPEDEF_CHARACTERISTIC
+                /begin TYPEDEF_MEASUREMENT Value "" UWORD NO_COMPU_METHOD 0 0 0 100 /end TYPEDEF_MEASUREMENT
+                /begin TYPEDEF_BLOB Unused "" 4 /end TYPEDEF_BLOB
+                /begin COMPU_METHOD CM "" IDENTICAL "%4.2" "" /end COMPU_METHOD
+                /begin RECORD_LAYOUT RL FNC_VALUES 1 ULONG ROW_DIR DIRECT /end RECORD_LAYOUT
+                /begin GROUP grp "" ROOT
+                    /begin REF_CHARACTERISTIC params /end REF_CHARACTERISTIC
+                /end GROUP
+            /end MODULE
+        /end PROJECT"#;
+        let mut load_msgs = Vec::new();
+        let mut a2l_file =
+            a2lfile::load_from_string(a2l_text, None, &mut load_msgs, false).unwrap();
+
+        let mut log_msgs = Vec::new();
+        let summary = flatten_typedefs(&mut a2l_file, &mut log_msgs);
+        assert_eq!(summary.instances_flattened, 1);
+        assert_eq!(summary.objects_created, 4);
+        // TYPEDEF_BLOB Unused is removed; the others are still needed by the pointer INSTANCE
+        assert_eq!(summary.typedefs_removed, 1);
+        assert!(log_msgs[1].contains("ptr"));
+
+        let module = &a2l_file.project.module[0];
+        assert_eq!(module.instance.len(), 1);
+        let chr = &module.characteristic[1];
+        assert_eq!(chr.name, "params[1].gain");
+        assert_eq!(chr.address, 0x1008);
+        assert_eq!(chr.conversion, "CM");
+        assert_eq!(
+            chr.symbol_link.as_ref().unwrap().symbol_name,
+            "params[1].gain"
+        );
+        assert_eq!(module.characteristic[0].conversion, "NO_COMPU_METHOD");
+        let meas = &module.measurement[0];
+        assert_eq!(meas.name, "params[0].values");
+        assert_eq!(meas.ecu_address.as_ref().unwrap().address, 0x1004);
+        assert_eq!(meas.matrix_dim.as_ref().unwrap().dim_list, vec![2]);
+        assert_eq!(
+            module.group[0]
+                .ref_characteristic
+                .as_ref()
+                .unwrap()
+                .identifier_list,
+            vec!["params[0].gain", "params[1].gain"]
+        );
+
+        assert_eq!(element_indices(&[2, 3])[4], (4, "[1][1]".to_string()));
+        assert_eq!(
+            append_to_symbol("a{CompileUnit:x}", ".b"),
+            "a.b{CompileUnit:x}"
+        );
+    }
+}
@@ -0,0 +1,247 @@
+//! Formatting of the a2l output text according to a style profile
+
+use serde_json::Value;
+use std::ffi::OsStr;
+
+/// The names of the built-in style profiles
+pub const BUILTIN_PROFILES: &[&str] = &["default", "sorted", "compact", "tabs"];
+
+/// A style profile, which controls the layout of the output file
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatProfile {
+    /// the text used for each level of indentation
+    pub indent: String,
+    /// sort all elements of the file
+    pub sort: bool,
+    /// write the digits of hex numbers in upper case (0x1A) or in lower case (0x1a)
+    pub hex_uppercase: bool,
+    /// the maximum number of consecutive empty lines
+    pub max_blank_lines: usize,
+    /// use CRLF line endings instead of LF
+    pub crlf: bool,
+}
+
+impl Default for FormatProfile {
+    fn default() -> Self {
+        Self {
+            indent: "  ".to_string(),
+            sort: false,
+            hex_uppercase: true,
+            max_blank_lines: 1,
+            crlf: false,
+        }
+    }
+}
+
+impl FormatProfile {
+    /// Get a built-in profile by name, or load a profile from a JSON file.
+    ///
+    /// The JSON file can contain the keys "indent" (number of spaces or "tab"), "sort" (bool),
+    /// "hex_case" ("upper" or "lower"), "max_blank_lines" (number) and "line_ending" ("lf" or "crlf").
+    /// Missing keys keep the values of the default profile.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the name is not a built-in profile and the file cannot be loaded
+    pub fn load(name: &OsStr) -> Result<Self, String> {
+        let default = Self::default();
+        match name.to_str() {
+            Some("default") => return Ok(default),
+            Some("sorted") => {
+                return Ok(Self {
+                    sort: true,
+                    ..default
+                })
+            }
+            Some("compact") => {
+                return Ok(Self {
+                    sort: true,
+                    max_blank_lines: 0,
+                    ..default
+                })
+            }
+            Some("tabs") => {
+                return Ok(Self {
+                    indent: "\t".to_string(),
+                    ..default
+                })
+            }
+            _ => {}
+        }
+
+        let text = std::fs::read_to_string(name).map_err(|err| {
+            format!(
+                "Error: \"{}\" is neither a built-in format profile ({}) nor a readable profile file: {err}",
+                name.to_string_lossy(),
+                BUILTIN_PROFILES.join(", ")
+            )
+        })?;
+        Self::from_json(&text).map_err(|err| {
+            format!(
+                "Error: invalid format profile \"{}\": {err}",
+                name.to_string_lossy()
+            )
+        })
+    }
+
+    fn from_json(text: &str) -> Result<Self, String> {
+        let value: Value = serde_json::from_str(text).map_err(|err| err.to_string())?;
+        let Some(object) = value.as_object() else {
+            return Err("the profile must be a JSON object".to_string());
+        };
+        let mut profile = Self::default();
+        for (key, value) in object {
+            match (key.as_str(), value) {
+                ("indent", Value::String(text)) if text == "tab" => {
+                    profile.indent = "\t".to_string();
+                }
+                ("indent", Value::Number(num)) if num.as_u64().is_some_and(|n| n <= 16) => {
+                    profile.indent = " ".repeat(num.as_u64().unwrap_or(2) as usize);
+                }
+                ("sort", Value::Bool(sort)) => profile.sort = *sort,
+                ("hex_case", Value::String(case)) if case == "upper" || case == "lower" => {
+                    profile.hex_uppercase = case == "upper";
+                }
+                ("max_blank_lines", Value::Number(num)) if num.as_u64().is_some() => {
+                    profile.max_blank_lines = num.as_u64().unwrap_or(1) as usize;
+                }
+                ("line_ending", Value::String(ending)) if ending == "lf" || ending == "crlf" => {
+                    profile.crlf = ending == "crlf";
+                }
+                _ => return Err(format!("invalid setting \"{key}\": {value}")),
+            }
+        }
+        Ok(profile)
+    }
+}
+
+/// Apply a style profile to a2l text that was created by the a2l writer.
+///
+/// The content of quoted strings is never changed.
+#[must_use]
+pub fn format_text(text: &str, profile: &FormatProfile) -> String {
+    let line_ending = if profile.crlf { "\r\n" } else { "\n" };
+    let mut output = String::with_capacity(text.len());
+    let mut in_string = false;
+    let mut blank_lines = 0;
+    let mut lines = text.split('\n').peekable();
+    while let Some(line) = lines.next() {
+        let is_last = lines.peek().is_none();
+        if in_string {
+            // the line continues a string from the previous line, so it is copied unchanged
+            in_string = format_line_content(line, profile, true, &mut output);
+        } else {
+            let line = line.trim_end();
+            let content = line.trim_start_matches(' ');
+            if content.is_empty() {
+                if is_last {
+                    break;
+                }
+                blank_lines += 1;
+                if blank_lines <= profile.max_blank_lines {
+                    output.push_str(line_ending);
+                }
+                continue;
+            }
+            blank_lines = 0;
+            // the writer indents with two spaces per level
+            let spaces = line.len() - content.len();
+            for _ in 0..spaces / 2 {
+                output.push_str(&profile.indent);
+            }
+            if spaces % 2 == 1 {
+                output.push(' ');
+            }
+            in_string = format_line_content(content, profile, false, &mut output);
+        }
+        if in_string {
+            // a line break inside of a string is part of the string
+            output.push('\n');
+        } else if !is_last {
+            output.push_str(line_ending);
+        }
+    }
+    output
+}
+
+// copy one line to the output and change the case of hex numbers outside of strings.
+// Returns true if the line ends inside of a string
+fn format_line_content(
+    line: &str,
+    profile: &FormatProfile,
+    mut in_string: bool,
+    output: &mut String,
+) -> bool {
+    let mut escaped = false;
+    let mut in_hex_number = false;
+    let mut prev = ' ';
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            output.push(c);
+        } else if c == '"' {
+            in_string = true;
+            in_hex_number = false;
+            output.push(c);
+        } else if in_hex_number && c.is_ascii_hexdigit() {
+            if profile.hex_uppercase {
+                output.push(c.to_ascii_uppercase());
+            } else {
+                output.push(c.to_ascii_lowercase());
+            }
+        } else {
+            let starts_number = !(prev.is_ascii_alphanumeric() || prev == '_' || prev == '.');
+            in_hex_number = (c == '0' && starts_number && matches!(chars.peek(), Some('x' | 'X')))
+                || (in_hex_number && prev == '0' && (c == 'x' || c == 'X'));
+            output.push(c);
+        }
+        prev = c;
+    }
+    in_string
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format_text() {
+        let text = "\nASAP2_VERSION 1 71\n/begin PROJECT p \"\"\n\n\n  /begin MODULE m \"a 0xAB\\\n  b\"\n    ECU_ADDRESS 0xAB1F   \n  /end MODULE\n/end PROJECT\n";
+        let profile = FormatProfile {
+            indent: "\t".to_string(),
+            hex_uppercase: false,
+            max_blank_lines: 0,
+            crlf: true,
+            ..FormatProfile::default()
+        };
+        assert_eq!(
+            format_text(text, &profile),
+            "ASAP2_VERSION 1 71\r\n/begin PROJECT p \"\"\r\n\t/begin MODULE m \"a 0xAB\\\n  b\"\r\n\t\tECU_ADDRESS 0xab1f\r\n\t/end MODULE\r\n/end PROJECT\r\n"
+        );
+        assert_eq!(
+            format_text(text, &FormatProfile::default()).len(),
+            text.len() - 4
+        );
+
+        let profile =
+            FormatProfile::from_json(r#"{"indent": 4, "sort": true, "hex_case": "lower"}"#);
+        assert_eq!(
+            profile,
+            Ok(FormatProfile {
+                indent: "    ".to_string(),
+                sort: true,
+                hex_uppercase: false,
+                ..FormatProfile::default()
+            })
+        );
+        assert!(FormatProfile::from_json(r#"{"indent": "x"}"#).is_err());
+        assert!(FormatProfile::load(OsStr::new("compact")).unwrap().sort);
+    }
+}
@@ -0,0 +1,435 @@
+//! Parsing and evaluation of the expressions used in FORMULA and FORMULA_INV
+
+use std::fmt::Display;
+
+/// A parsed formula expression
+///
+/// The input values are named X1, X2, etc.; X is an alias of X1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression(Expr);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Number(f64),
+    // zero based index of the input value
+    Variable(usize),
+    Unary(&'static str, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    Function(&'static str, Vec<Expr>),
+    // sysc(NAME): the value of a SYSTEM_CONSTANT in MOD_PAR
+    SystemConstant(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    SystemConstant(String),
+    Operator(&'static str),
+    LeftParen,
+    RightParen,
+    Comma,
+}
+
+// the supported functions and the number of their arguments
+const FUNCTIONS: &[(&str, usize)] = &[
+    ("abs", 1),
+    ("acos", 1),
+    ("asin", 1),
+    ("atan", 1),
+    ("ceil", 1),
+    ("cos", 1),
+    ("cosh", 1),
+    ("exp", 1),
+    ("floor", 1),
+    ("log", 1),
+    ("log10", 1),
+    ("mod", 2),
+    ("pow", 2),
+    ("sin", 1),
+    ("sinh", 1),
+    ("sqrt", 1),
+    ("tan", 1),
+    ("tanh", 1),
+];
+
+// binary operators ordered by increasing precedence
+const BINARY_OPERATORS: &[&[&str]] = &[
+    &["||"],
+    &["&&"],
+    &["|"],
+    &["^"],
+    &["&"],
+    &["==", "!="],
+    &["<", "<=", ">", ">="],
+    &["<<", ">>"],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+// operators are matched greedily, so the longer ones must come first
+const OPERATORS: &[&str] = &[
+    "||", "&&", "==", "!=", "<=", ">=", "<<", ">>", "|", "^", "&", "<", ">", "+", "-", "*", "/",
+    "%", "!", "~",
+];
+
+/// Parse the text of a FORMULA or FORMULA_INV
+///
+/// # Errors
+///
+/// Returns an error message if the text is not a valid expression, e.g. because of unbalanced
+/// parentheses or an unknown function
+pub fn parse_formula(text: &str) -> Result<Expression, String> {
+    let tokens = tokenize(text)?;
+    let mut pos = 0;
+    let expr = parse_binary(&tokens, &mut pos, 0)?;
+    if pos < tokens.len() {
+        return Err(format!("unexpected {} in formula \"{text}\"", tokens[pos]));
+    }
+    Ok(Expression(expr))
+}
+
+impl Expression {
+    /// Evaluate the expression with the given input values X1, X2, etc.
+    ///
+    /// Returns None if an input value is missing or if the result is not a finite number
+    #[must_use]
+    pub fn evaluate(&self, inputs: &[f64]) -> Option<f64> {
+        let result = evaluate(&self.0, inputs)?;
+        result.is_finite().then_some(result)
+    }
+
+    /// The names of the system constants referenced with sysc(NAME)
+    #[must_use]
+    pub fn system_constants(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        collect_system_constants(&self.0, &mut names);
+        names
+    }
+
+    /// The number of input values used by the expression, i.e. the highest index n of any Xn
+    #[must_use]
+    pub fn input_count(&self) -> usize {
+        input_count(&self.0)
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut end = start;
+            let mut prev = ' ';
+            while let Some(&(idx, c)) = chars.peek() {
+                // the sign of an exponent belongs to the number, e.g. 1e-3
+                let is_exp_sign = (c == '+' || c == '-') && (prev == 'e' || prev == 'E');
+                if c.is_ascii_alphanumeric() || c == '.' || is_exp_sign {
+                    end = idx + c.len_utf8();
+                    prev = c;
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            let number_text = &text[start..end];
+            let number = if let Some(hex) = number_text
+                .strip_prefix("0x")
+                .or_else(|| number_text.strip_prefix("0X"))
+            {
+                u64::from_str_radix(hex, 16).ok().map(|num| num as f64)
+            } else if number_text.contains(['x', 'X']) {
+                None
+            } else {
+                number_text.parse::<f64>().ok()
+            };
+            let Some(number) = number else {
+                return Err(format!("invalid number \"{number_text}\""));
+            };
+            tokens.push(Token::Number(number));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut end = start;
+            while let Some(&(idx, c)) = chars.peek() {
+                if c.is_ascii_alphanumeric() || c == '_' {
+                    end = idx + 1;
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            let ident = &text[start..end];
+            // the name of a system constant is an arbitrary string, so it is read up to the closing parenthesis
+            if ident.eq_ignore_ascii_case("sysc") && text[end..].trim_start().starts_with('(') {
+                let args_start = end + text[end..].find('(').unwrap_or(0) + 1;
+                let Some(len) = text[args_start..].find(')') else {
+                    return Err("missing ')' after sysc".to_string());
+                };
+                let name = text[args_start..args_start + len].trim();
+                if name.is_empty() {
+                    return Err("missing system constant name in sysc()".to_string());
+                }
+                tokens.push(Token::SystemConstant(name.to_string()));
+                while chars
+                    .next_if(|(idx, _)| *idx < args_start + len + 1)
+                    .is_some()
+                {}
+            } else {
+                tokens.push(Token::Ident(ident.to_string()));
+            }
+        } else if c == '(' {
+            chars.next();
+            tokens.push(Token::LeftParen);
+        } else if c == ')' {
+            chars.next();
+            tokens.push(Token::RightParen);
+        } else if c == ',' {
+            chars.next();
+            tokens.push(Token::Comma);
+        } else if let Some(op) = OPERATORS.iter().find(|op| text[start..].starts_with(*op)) {
+            for _ in 0..op.len() {
+                chars.next();
+            }
+            tokens.push(Token::Operator(op));
+        } else {
+            return Err(format!("invalid character '{c}' in formula"));
+        }
+    }
+    Ok(tokens)
+}
+
+// parse a sequence of binary operations with the given precedence level or higher
+fn parse_binary(tokens: &[Token], pos: &mut usize, level: usize) -> Result<Expr, String> {
+    if level >= BINARY_OPERATORS.len() {
+        return parse_unary(tokens, pos);
+    }
+    let mut lhs = parse_binary(tokens, pos, level + 1)?;
+    while let Some(Token::Operator(op)) = tokens.get(*pos) {
+        if !BINARY_OPERATORS[level].contains(op) {
+            break;
+        }
+        *pos += 1;
+        let rhs = parse_binary(tokens, pos, level + 1)?;
+        lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+    }
+    Ok(lhs)
+}
+
+fn parse_unary(tokens: &[Token], pos: &mut usize) -> Result<Expr, String> {
+    match tokens.get(*pos) {
+        Some(Token::Operator(op @ ("-" | "+" | "!" | "~"))) => {
+            *pos += 1;
+            let operand = parse_unary(tokens, pos)?;
+            Ok(Expr::Unary(op, Box::new(operand)))
+        }
+        _ => parse_primary(tokens, pos),
+    }
+}
+
+fn parse_primary(tokens: &[Token], pos: &mut usize) -> Result<Expr, String> {
+    let Some(token) = tokens.get(*pos) else {
+        return Err("unexpected end of formula".to_string());
+    };
+    *pos += 1;
+    match token {
+        Token::Number(number) => Ok(Expr::Number(*number)),
+        Token::SystemConstant(name) => Ok(Expr::SystemConstant(name.clone())),
+        Token::LeftParen => {
+            let expr = parse_binary(tokens, pos, 0)?;
+            expect_right_paren(tokens, pos)?;
+            Ok(expr)
+        }
+        Token::Ident(ident) => {
+            if let Some(index) = parse_variable(ident) {
+                return Ok(Expr::Variable(index));
+            }
+            let lower = ident.to_ascii_lowercase();
+            // "ln" is accepted as an alternative name of the natural logarithm
+            let lookup_name = if lower == "ln" { "log" } else { &lower };
+            let Some((name, arg_count)) = FUNCTIONS.iter().find(|(name, _)| *name == lookup_name)
+            else {
+                return Err(format!("unknown identifier \"{ident}\""));
+            };
+            if tokens.get(*pos) != Some(&Token::LeftParen) {
+                return Err(format!("missing '(' after function {ident}"));
+            }
+            *pos += 1;
+            let mut args = vec![parse_binary(tokens, pos, 0)?];
+            while tokens.get(*pos) == Some(&Token::Comma) {
+                *pos += 1;
+                args.push(parse_binary(tokens, pos, 0)?);
+            }
+            expect_right_paren(tokens, pos)?;
+            if args.len() != *arg_count {
+                return Err(format!(
+                    "function {ident} expects {arg_count} arguments, but got {}",
+                    args.len()
+                ));
+            }
+            Ok(Expr::Function(name, args))
+        }
+        _ => Err(format!("unexpected {token} in formula")),
+    }
+}
+
+fn expect_right_paren(tokens: &[Token], pos: &mut usize) -> Result<(), String> {
+    if tokens.get(*pos) == Some(&Token::RightParen) {
+        *pos += 1;
+        Ok(())
+    } else {
+        Err("missing ')' in formula".to_string())
+    }
+}
+
+// X and X1 refer to the first input value, X2 to the second, etc.
+fn parse_variable(ident: &str) -> Option<usize> {
+    let digits = ident
+        .strip_prefix('X')
+        .or_else(|| ident.strip_prefix('x'))?;
+    if digits.is_empty() {
+        return Some(0);
+    }
+    let index = digits.parse::<usize>().ok()?;
+    index.checked_sub(1)
+}
+
+fn evaluate(expr: &Expr, inputs: &[f64]) -> Option<f64> {
+    let bool_to_f64 = |value: bool| if value { 1.0 } else { 0.0 };
+    let value = match expr {
+        Expr::Number(number) => *number,
+        Expr::Variable(index) => *inputs.get(*index)?,
+        // the values of system constants are not known here
+        Expr::SystemConstant(_) => return None,
+        Expr::Unary(op, operand) => {
+            let operand = evaluate(operand, inputs)?;
+            match *op {
+                "-" => -operand,
+                "!" => bool_to_f64(operand == 0.0),
+                "~" => !(operand as i64) as f64,
+                _ => operand,
+            }
+        }
+        Expr::Binary(op, lhs, rhs) => {
+            let lhs = evaluate(lhs, inputs)?;
+            let rhs = evaluate(rhs, inputs)?;
+            match *op {
+                "||" => bool_to_f64(lhs != 0.0 || rhs != 0.0),
+                "&&" => bool_to_f64(lhs != 0.0 && rhs != 0.0),
+                "|" => ((lhs as i64) | (rhs as i64)) as f64,
+                "^" => ((lhs as i64) ^ (rhs as i64)) as f64,
+                "&" => ((lhs as i64) & (rhs as i64)) as f64,
+                "==" => bool_to_f64(lhs == rhs),
+                "!=" => bool_to_f64(lhs != rhs),
+                "<" => bool_to_f64(lhs < rhs),
+                "<=" => bool_to_f64(lhs <= rhs),
+                ">" => bool_to_f64(lhs > rhs),
+                ">=" => bool_to_f64(lhs >= rhs),
+                "<<" => ((lhs as i64).checked_shl(rhs as u32)?) as f64,
+                ">>" => ((lhs as i64).checked_shr(rhs as u32)?) as f64,
+                "+" => lhs + rhs,
+                "-" => lhs - rhs,
+                "*" => lhs * rhs,
+                "/" => lhs / rhs,
+                _ => lhs % rhs,
+            }
+        }
+        Expr::Function(name, args) => {
+            let x = evaluate(&args[0], inputs)?;
+            match *name {
+                "abs" => x.abs(),
+                "acos" => x.acos(),
+                "asin" => x.asin(),
+                "atan" => x.atan(),
+                "ceil" => x.ceil(),
+                "cos" => x.cos(),
+                "cosh" => x.cosh(),
+                "exp" => x.exp(),
+                "floor" => x.floor(),
+                "log" => x.ln(),
+                "log10" => x.log10(),
+                "mod" => x % evaluate(&args[1], inputs)?,
+                "pow" => x.powf(evaluate(&args[1], inputs)?),
+                "sin" => x.sin(),
+                "sinh" => x.sinh(),
+                "sqrt" => x.sqrt(),
+                "tan" => x.tan(),
+                _ => x.tanh(),
+            }
+        }
+    };
+    Some(value)
+}
+
+fn input_count(expr: &Expr) -> usize {
+    match expr {
+        Expr::Number(_) | Expr::SystemConstant(_) => 0,
+        Expr::Variable(index) => index + 1,
+        Expr::Unary(_, operand) => input_count(operand),
+        Expr::Binary(_, lhs, rhs) => input_count(lhs).max(input_count(rhs)),
+        Expr::Function(_, args) => args.iter().map(input_count).max().unwrap_or(0),
+    }
+}
+
+fn collect_system_constants<'a>(expr: &'a Expr, names: &mut Vec<&'a str>) {
+    match expr {
+        Expr::Number(_) | Expr::Variable(_) => {}
+        Expr::SystemConstant(name) => names.push(name),
+        Expr::Unary(_, operand) => collect_system_constants(operand, names),
+        Expr::Binary(_, lhs, rhs) => {
+            collect_system_constants(lhs, names);
+            collect_system_constants(rhs, names);
+        }
+        Expr::Function(_, args) => {
+            for arg in args {
+                collect_system_constants(arg, names);
+            }
+        }
+    }
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Number(number) => write!(f, "number {number}"),
+            Token::Ident(ident) => write!(f, "identifier \"{ident}\""),
+            Token::SystemConstant(name) => write!(f, "sysc({name})"),
+            Token::Operator(op) => write!(f, "operator '{op}'"),
+            Token::LeftParen => write!(f, "'('"),
+            Token::RightParen => write!(f, "')'"),
+            Token::Comma => write!(f, "','"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_evaluate_formula() {
+        let eval = |text: &str, inputs: &[f64]| parse_formula(text).unwrap().evaluate(inputs);
+        assert_eq!(eval("X1 * 2 + 1", &[3.0]), Some(7.0));
+        assert_eq!(eval("(x + 1) * 2", &[3.0]), Some(8.0));
+        assert_eq!(eval("-X1 - -2", &[3.0]), Some(-1.0));
+        assert_eq!(eval("X1 / X2", &[1.0, 4.0]), Some(0.25));
+        assert_eq!(eval("sqrt(X1) + pow(2, 3)", &[16.0]), Some(12.0));
+        assert_eq!(eval("(X1 >> 4) & 0x0F", &[0xAB as f64]), Some(10.0));
+        assert_eq!(eval("X1 > 5 && X1 < 10", &[7.0]), Some(1.0));
+        assert_eq!(eval("1.5e-1 * 10", &[]), Some(1.5));
+        assert_eq!(eval("X1 / 0", &[1.0]), None);
+        assert_eq!(eval("X2", &[1.0]), None);
+        assert_eq!(parse_formula("X1 + X3").unwrap().input_count(), 3);
+        let with_sysc = parse_formula("X1 * sysc(CONV.Factor) + sysc( offset )").unwrap();
+        assert_eq!(with_sysc.system_constants(), vec!["CONV.Factor", "offset"]);
+        assert_eq!(with_sysc.evaluate(&[1.0]), None);
+        assert!(parse_formula("sysc(X").is_err());
+
+        assert!(parse_formula("(X1 + 1").is_err());
+        assert!(parse_formula("X1 + 1)").is_err());
+        assert!(parse_formula("sqr(X1)").is_err());
+        assert!(parse_formula("pow(X1)").is_err());
+        assert!(parse_formula("X1 +").is_err());
+        assert!(parse_formula("X1 $ 2").is_err());
+    }
+}
@@ -0,0 +1,356 @@
+//! Reading memory images in Intel HEX and Motorola S-record format
+
+use std::ffi::OsStr;
+
+/// The file formats of memory images
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum HexFormat {
+    #[default]
+    IntelHex,
+    SRecord,
+}
+
+/// A memory image, consisting of blocks of contiguous data
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HexImage {
+    // sorted by address, non-overlapping and non-adjacent
+    blocks: Vec<(u32, Vec<u8>)>,
+    /// the format of the file the image was loaded from
+    pub format: HexFormat,
+}
+
+impl HexImage {
+    /// Load an image from a file. The format is detected from the content of the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the file cannot be read or is not a valid Intel HEX or S-record file
+    pub fn load(filename: &OsStr) -> Result<Self, String> {
+        let text = std::fs::read_to_string(filename).map_err(|err| {
+            format!(
+                "Error: could not read \"{}\": {err}",
+                filename.to_string_lossy()
+            )
+        })?;
+        Self::parse(&text).map_err(|err| format!("{err} in \"{}\"", filename.to_string_lossy()))
+    }
+
+    /// Parse the text of an Intel HEX or S-record file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the text is neither a valid Intel HEX file nor a valid S-record file
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let first_line = text.lines().map(str::trim).find(|line| !line.is_empty());
+        match first_line.and_then(|line| line.chars().next()) {
+            Some(':') => Self::parse_intel_hex(text),
+            Some('S') => Self::parse_srecord(text),
+            _ => Err("Error: unknown file format, expected Intel HEX or S-record".to_string()),
+        }
+    }
+
+    fn parse_intel_hex(text: &str) -> Result<Self, String> {
+        let mut image = HexImage::default();
+        let mut base_address = 0u32;
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let line_num = idx + 1;
+            let bytes = line
+                .strip_prefix(':')
+                .and_then(decode_hex)
+                .ok_or_else(|| format!("Error: invalid record on line {line_num}"))?;
+            if bytes.len() < 5 || bytes.len() != usize::from(bytes[0]) + 5 {
+                return Err(format!("Error: invalid record length on line {line_num}"));
+            }
+            if bytes.iter().fold(0u8, |acc, b| acc.wrapping_add(*b)) != 0 {
+                return Err(format!("Error: checksum error on line {line_num}"));
+            }
+            let offset = u32::from(u16::from_be_bytes([bytes[1], bytes[2]]));
+            let data = &bytes[4..bytes.len() - 1];
+            match bytes[3] {
+                0 => image.insert(base_address.wrapping_add(offset), data),
+                1 => break,
+                2 if data.len() == 2 => {
+                    base_address = u32::from(u16::from_be_bytes([data[0], data[1]])) << 4;
+                }
+                4 if data.len() == 2 => {
+                    base_address = u32::from(u16::from_be_bytes([data[0], data[1]])) << 16;
+                }
+                // start addresses are not relevant for the memory content
+                3 | 5 => {}
+                _ => return Err(format!("Error: invalid record type on line {line_num}")),
+            }
+        }
+        image.normalize();
+        Ok(image)
+    }
+
+    fn parse_srecord(text: &str) -> Result<Self, String> {
+        let mut image = HexImage {
+            format: HexFormat::SRecord,
+            ..Default::default()
+        };
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let line_num = idx + 1;
+            let mut chars = line.chars();
+            let (Some('S'), Some(record_type)) = (chars.next(), chars.next()) else {
+                return Err(format!("Error: invalid record on line {line_num}"));
+            };
+            let bytes = decode_hex(chars.as_str())
+                .ok_or_else(|| format!("Error: invalid record on line {line_num}"))?;
+            if bytes.is_empty() || bytes.len() != usize::from(bytes[0]) + 1 {
+                return Err(format!("Error: invalid record length on line {line_num}"));
+            }
+            if bytes.iter().fold(0u8, |acc, b| acc.wrapping_add(*b)) != 0xff {
+                return Err(format!("Error: checksum error on line {line_num}"));
+            }
+            let address_len = match record_type {
+                '1' => 2,
+                '2' => 3,
+                '3' => 4,
+                // header, record count and start address records
+                '0' | '5' | '6' | '7' | '8' | '9' => continue,
+                _ => return Err(format!("Error: invalid record type on line {line_num}")),
+            };
+            if bytes.len() < address_len + 2 {
+                return Err(format!("Error: invalid record length on line {line_num}"));
+            }
+            let address = bytes[1..=address_len]
+                .iter()
+                .fold(0u32, |acc, b| (acc << 8) | u32::from(*b));
+            image.insert(address, &bytes[address_len + 1..bytes.len() - 1]);
+        }
+        image.normalize();
+        Ok(image)
+    }
+
+    // add data to the image. Usually the data directly follows the previous data, so it can simply be appended
+    fn insert(&mut self, address: u32, data: &[u8]) {
+        if let Some((start, block)) = self.blocks.last_mut() {
+            if u64::from(*start) + block.len() as u64 == u64::from(address) {
+                block.extend_from_slice(data);
+                return;
+            }
+        }
+        self.blocks.push((address, data.to_vec()));
+    }
+
+    // sort the blocks and merge blocks that are adjacent or overlapping.
+    // Where blocks overlap, the data of the block with the higher start address is used
+    fn normalize(&mut self) {
+        let mut blocks = std::mem::take(&mut self.blocks);
+        // stable sort: for blocks with the same start address the later one stays later and wins
+        blocks.sort_by_key(|(start, _)| *start);
+        for (start, data) in blocks {
+            if let Some((prev_start, prev_data)) = self.blocks.last_mut() {
+                let prev_end = u64::from(*prev_start) + prev_data.len() as u64;
+                if u64::from(start) <= prev_end {
+                    let offset = (start - *prev_start) as usize;
+                    let overlap = (prev_data.len() - offset).min(data.len());
+                    prev_data[offset..offset + overlap].copy_from_slice(&data[..overlap]);
+                    prev_data.extend_from_slice(&data[overlap..]);
+                    continue;
+                }
+            }
+            self.blocks.push((start, data));
+        }
+    }
+
+    /// Read `len` bytes starting at `address`. Returns None if any of the bytes is not part of the image.
+    #[must_use]
+    pub fn read(&self, address: u32, len: usize) -> Option<&[u8]> {
+        // find the last block that starts at or before the address
+        let idx = self
+            .blocks
+            .partition_point(|(start, _)| *start <= address)
+            .checked_sub(1)?;
+        let (start, data) = &self.blocks[idx];
+        let offset = (address - start) as usize;
+        data.get(offset..offset.checked_add(len)?)
+    }
+
+    /// Overwrite the data at `address`. Returns false if any of the bytes is not part of the image; nothing is written in this case.
+    #[must_use]
+    pub fn write(&mut self, address: u32, data: &[u8]) -> bool {
+        let Some(idx) = self
+            .blocks
+            .partition_point(|(start, _)| *start <= address)
+            .checked_sub(1)
+        else {
+            return false;
+        };
+        let (start, block) = &mut self.blocks[idx];
+        let offset = (address - *start) as usize;
+        if let Some(target) = block.get_mut(offset..offset + data.len()) {
+            target.copy_from_slice(data);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Save the image in the same format it was loaded from
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the file cannot be written
+    pub fn save(&self, filename: &OsStr) -> Result<(), String> {
+        let text = match self.format {
+            HexFormat::IntelHex => self.to_intel_hex(),
+            HexFormat::SRecord => self.to_srecord(),
+        };
+        std::fs::write(filename, text).map_err(|err| {
+            format!(
+                "Error: could not write \"{}\": {err}",
+                filename.to_string_lossy()
+            )
+        })
+    }
+
+    /// Convert the image to Intel HEX format
+    #[must_use]
+    pub fn to_intel_hex(&self) -> String {
+        let mut text = String::new();
+        let mut upper_address = 0u16;
+        for (address, data) in self.data_records() {
+            let [upper_hi, upper_lo, addr_hi, addr_lo] = address.to_be_bytes();
+            let upper = u16::from_be_bytes([upper_hi, upper_lo]);
+            if upper != upper_address {
+                text.push_str(&intel_hex_record(0, 4, &upper.to_be_bytes()));
+                upper_address = upper;
+            }
+            text.push_str(&intel_hex_record(
+                u16::from_be_bytes([addr_hi, addr_lo]),
+                0,
+                data,
+            ));
+        }
+        text.push_str(":00000001FF\n");
+        text
+    }
+
+    /// Convert the image to Motorola S-record format, using S3 records with 32-bit addresses
+    #[must_use]
+    pub fn to_srecord(&self) -> String {
+        let mut text = String::new();
+        for (address, data) in self.data_records() {
+            let mut bytes = vec![(data.len() + 5) as u8];
+            bytes.extend_from_slice(&address.to_be_bytes());
+            bytes.extend_from_slice(data);
+            let checksum = !bytes.iter().fold(0u8, |acc, b| acc.wrapping_add(*b));
+            bytes.push(checksum);
+            text.push_str(&format!("S3{}\n", encode_hex(&bytes)));
+        }
+        text.push_str("S70500000000FA\n");
+        text
+    }
+
+    // split the data into records of up to 16 bytes. Records don't cross 64k boundaries, which is needed for Intel HEX
+    fn data_records(&self) -> Vec<(u32, &[u8])> {
+        let mut records = Vec::new();
+        for (start, data) in &self.blocks {
+            let mut pos = 0;
+            while pos < data.len() {
+                let address = start.wrapping_add(pos as u32);
+                let to_boundary = 0x10000 - (address & 0xffff) as usize;
+                let len = (data.len() - pos).min(16).min(to_boundary);
+                records.push((address, &data[pos..pos + len]));
+                pos += len;
+            }
+        }
+        records
+    }
+
+    /// The address ranges (start, length) that contain data
+    pub fn ranges(&self) -> impl Iterator<Item = (u32, usize)> + '_ {
+        self.blocks.iter().map(|(start, data)| (*start, data.len()))
+    }
+}
+
+fn intel_hex_record(address: u16, record_type: u8, data: &[u8]) -> String {
+    let mut bytes = vec![data.len() as u8];
+    bytes.extend_from_slice(&address.to_be_bytes());
+    bytes.push(record_type);
+    bytes.extend_from_slice(data);
+    let checksum = bytes
+        .iter()
+        .fold(0u8, |acc, b| acc.wrapping_add(*b))
+        .wrapping_neg();
+    bytes.push(checksum);
+    format!(":{}\n", encode_hex(&bytes))
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02X}")).collect()
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) || !text.is_ascii() {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|pos| u8::from_str_radix(&text[pos..pos + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_intel_hex() {
+        let text = ":020000040001F9\n:0400100041424344E2\n:0200140045465F\n:00000001FF\n";
+        let image = HexImage::parse(text).unwrap();
+        assert_eq!(image.read(0x10010, 6), Some(&b"ABCDEF"[..]));
+        assert_eq!(image.read(0x10012, 2), Some(&b"CD"[..]));
+        assert_eq!(image.read(0x10014, 4), None);
+        assert_eq!(image.read(0x1000F, 1), None);
+        assert_eq!(image.ranges().collect::<Vec<_>>(), vec![(0x10010, 6)]);
+
+        assert!(HexImage::parse(":0400100041424344EB\n").is_err());
+        assert!(HexImage::parse(":04001000414243\n").is_err());
+    }
+
+    #[test]
+    fn test_srecord() {
+        let text = "S00600004844521B\nS106001041424323\nS3080000100044454618\nS9030000FC\n";
+        let image = HexImage::parse(text).unwrap();
+        assert_eq!(image.read(0x10, 3), Some(&b"ABC"[..]));
+        assert_eq!(image.read(0x1000, 3), Some(&b"DEF"[..]));
+        assert!(HexImage::parse("S1060010414243B3\n").is_err());
+        assert!(HexImage::parse("hello").is_err());
+    }
+
+    #[test]
+    fn test_write_image() {
+        let text = ":020000040001F9\n:0400100041424344E2\n:00000001FF\n";
+        let mut image = HexImage::parse(text).unwrap();
+        assert_eq!(image.to_intel_hex(), text);
+        assert!(image.write(0x10011, b"XY"));
+        assert!(!image.write(0x10013, b"XY"));
+        assert_eq!(image.read(0x10010, 4), Some(&b"AXYD"[..]));
+
+        let srecord = image.to_srecord();
+        let image2 = HexImage::parse(&srecord).unwrap();
+        assert_eq!(image2.format, HexFormat::SRecord);
+        assert_eq!(image2.read(0x10010, 4), Some(&b"AXYD"[..]));
+    }
+
+    #[test]
+    fn test_overlapping_data() {
+        let mut image = HexImage::default();
+        image.insert(0x100, &[1, 2, 3, 4]);
+        image.insert(0x0FE, &[5, 6, 7]);
+        image.insert(0x104, &[8]);
+        image.normalize();
+        assert_eq!(image.read(0xFE, 7), Some(&[5, 6, 1, 2, 3, 4, 8][..]));
+    }
+}
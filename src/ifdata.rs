@@ -1,3 +1,5 @@
+//! Data structures for the IF_DATA blocks XCP, ASAP1B_CCP and CANAPE_EXT, generated from the built-in A2ML
+
 #![allow(clippy::all)] // don't care about mesages in generated code
 
 use a2lfile::a2ml_specification;
@@ -1058,6 +1060,48 @@ a2ml_specification! {
         };
 
         "ASAP1B_CCP" taggedstruct {
+            (block "RASTER" struct {
+                char raster_name[101];  /// name of the DAQ list / measurement source
+                char raster_short_name[9];  /// short name of the DAQ list
+                uchar event_channel;  /// event channel number, used for START_STOP
+                int cycle_time;  /// cycle time in units of cycle_time_unit
+                int cycle_time_unit;  /// 0 = 1us, 1 = 10us, 2 = 100us, 3 = 1ms, 4 = 10ms, 5 = 100ms, 6 = 1s
+                taggedstruct {
+                    ("EXCLUSIVE" uchar raster_id)*;  /// rasters which cannot be active at the same time
+                };
+            })*;
+            block "SEED_KEY" struct {
+                char cal_dll[256];  /// Name of the Seed&Key DLL for CAL privilege, including file extension without path
+                char daq_dll[256];  /// Name of the Seed&Key DLL for DAQ privilege, including file extension without path
+                char pgm_dll[256];  /// Name of the Seed&Key DLL for PGM privilege, including file extension without path
+            };
+            block "TP_BLOB" struct {
+                uint ccp_version;  /// CCP version, high byte: version, low byte: subversion
+                uint blob_version;  /// blob version, high byte: version, low byte: subversion
+                ulong can_id_send;  /// CAN id for transmitting to the ECU (CRO). Bit 31 = 1: extended identifier
+                ulong can_id_receive;  /// CAN id for receiving from the ECU (DTO). Bit 31 = 1: extended identifier
+                uint station_address;  /// logical CCP address of the ECU
+                uint byte_order;  /// byte order of multi-byte items: 1 = high byte first, 2 = low byte first
+                taggedstruct {
+                    "BAUDRATE" ulong baudrate;  /// Baudrate in Hz
+                    "SAMPLE_POINT" uchar sample_point;  /// sample point in % of bit time
+                    "SAMPLE_RATE" uchar sample_rate;  /// samples per bit: 1 = single, 3 = triple
+                    "BTL_CYCLES" uchar btl_cycles;  /// number of BTL cycles
+                    "SJW" uchar sjw;  /// SJW length synchronisation segment in BTL cycles
+                    "SYNC_EDGE" enum {
+                        "SINGLE" = 0,
+                        "DUAL" = 1
+                    };
+                    "DAQ_MODE" enum {
+                        "ALTERNATING" = 0,
+                        "BURST" = 1
+                    };
+                    "BYTES_ONLY";
+                    "RESUME_SUPPORTED";
+                    "STORE_SUPPORTED";
+                    ("OPTIONAL_CMD" uint command_code)*;
+                };
+            };
             "DP_BLOB" struct {
                 uint address_extension;  /// Address extension of the calibration data
                 ulong base_address;  /// Base address of the calibration data
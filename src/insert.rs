@@ -1,19 +1,50 @@
+//! Create new MEASUREMENTs, CHARACTERISTICs and INSTANCEs based on the debug info
+
 use a2lfile::{
     A2lFile, A2lObject, AddrType, Characteristic, CharacteristicType, EcuAddress, FncValues, Group,
     IndexMode, Instance, Measurement, Module, RecordLayout, RefCharacteristic, RefMeasurement,
-    Root, SymbolLink,
+    Root, SubGroup, SymbolLink,
 };
+use indexmap::IndexMap;
 use std::collections::HashMap;
+use std::str::FromStr;
 
 use crate::datatype::{get_a2l_datatype, get_type_limits};
 use crate::dwarf::{DebugData, DwarfDataType, TypeInfo};
 use crate::symbol::SymbolInfo;
 use crate::update::{
-    self, enums, make_symbol_link_string, set_address_type, set_bitmask, set_matrix_dim,
+    self, enums, fixed_point, make_symbol_link_string, set_address_type, set_bitmask,
+    set_matrix_dim,
 };
 use crate::A2lVersion;
 use regex::Regex;
 
+/// How new items are assigned to groups derived from their source files
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GroupBy {
+    /// one group per source file, e.g. "ignition_c"
+    SourceFile,
+    /// one group per directory containing source files
+    Directory,
+    /// nested groups following the struct members, e.g. "EngineCal" -> "EngineCal.Ignition"
+    Struct,
+}
+
+impl FromStr for GroupBy {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "source-file" => Ok(Self::SourceFile),
+            "directory" => Ok(Self::Directory),
+            "struct" => Ok(Self::Struct),
+            _ => Err(format!(
+                "Error: unknown grouping \"{text}\". Valid values are source-file, directory and struct"
+            )),
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 enum ItemType {
     Measurement(usize),
@@ -34,6 +65,7 @@ struct InsertSupport<'a2l, 'dbg, 'param> {
     sym_map: HashMap<String, ItemType>,
     characteristic_list: Vec<String>,
     measurement_list: Vec<String>,
+    item_source_files: HashMap<String, usize>,
     meas_count: u32,
     chara_count: u32,
     instance_count: u32,
@@ -41,12 +73,17 @@ struct InsertSupport<'a2l, 'dbg, 'param> {
     create_typedef: Vec<(&'dbg TypeInfo, usize)>,
 }
 
-pub(crate) fn insert_items(
+/// insert MEASUREMENTs and CHARACTERISTICs for the given symbols into the first module
+/// if a target group is given, then all new items are added to this group
+/// if `group_by` is set, then the new items are also added to groups based on their source files
+#[allow(clippy::too_many_arguments)]
+pub fn insert_items(
     a2l_file: &mut A2lFile,
     debug_data: &DebugData,
     measurement_symbols: Vec<&str>,
     characteristic_symbols: Vec<&str>,
     target_group: Option<&str>,
+    group_by: Option<GroupBy>,
     log_msgs: &mut Vec<String>,
     enable_structures: bool,
 ) {
@@ -55,6 +92,7 @@ pub(crate) fn insert_items(
     let (mut name_map, mut sym_map) = build_maps(&module);
     let mut characteristic_list = vec![];
     let mut measurement_list = vec![];
+    let mut item_source_files = HashMap::new();
 
     let mut insert_list: Vec<(&str, SymbolInfo, bool)> = Vec::new();
 
@@ -91,6 +129,9 @@ pub(crate) fn insert_items(
                     Ok(characteristic_name) => {
                         log_msgs.push(format!("Inserted CHARACTERISTIC {characteristic_name}"));
                         characteristic_list.push(characteristic_name.clone());
+                        if let Some(decl_file) = sym_info.decl_file {
+                            item_source_files.insert(characteristic_name.clone(), decl_file);
+                        }
 
                         let it = ItemType::Characteristic(module.characteristic.len() - 1);
                         name_map.insert(characteristic_name, it);
@@ -107,6 +148,9 @@ pub(crate) fn insert_items(
                     Ok(measure_name) => {
                         log_msgs.push(format!("Inserted MEASUREMENT {measure_name}"));
                         measurement_list.push(measure_name.clone());
+                        if let Some(decl_file) = sym_info.decl_file {
+                            item_source_files.insert(measure_name.clone(), decl_file);
+                        }
 
                         let it = ItemType::Measurement(module.measurement.len() - 1);
                         name_map.insert(measure_name, it);
@@ -132,6 +176,9 @@ pub(crate) fn insert_items(
                         measurement_list.push(instance_name.clone());
                     }
 
+                    if let Some(decl_file) = sym_info.decl_file {
+                        item_source_files.insert(instance_name.clone(), decl_file);
+                    }
                     create_typedef.push((typedef_typeinfo, module.instance.len() - 1));
 
                     let it = ItemType::Instance(module.instance.len() - 1);
@@ -152,6 +199,21 @@ pub(crate) fn insert_items(
 
     update::typedef::create_new_typedefs(module, debug_data, log_msgs, &create_typedef);
 
+    match group_by {
+        Some(GroupBy::Struct) => {
+            create_struct_groups(module, &characteristic_list, &measurement_list, log_msgs);
+        }
+        Some(group_by) => create_source_file_groups(
+            module,
+            debug_data,
+            group_by == GroupBy::Directory,
+            &characteristic_list,
+            &measurement_list,
+            &item_source_files,
+            log_msgs,
+        ),
+        None => {}
+    }
     if let Some(group_name) = target_group {
         create_or_update_group(module, group_name, characteristic_list, measurement_list);
     }
@@ -209,14 +271,23 @@ fn insert_measurement_sym(
 
     if let DwarfDataType::Enum { enumerators, .. } = &typeinfo.datatype {
         // create a conversion table for enums
-        let enum_name = typeinfo
-            .name
-            .clone()
-            .unwrap_or_else(|| format!("{}_compu_method", new_measurement.name));
+        let enum_name = typeinfo.name.as_deref().map_or_else(
+            || format!("{}_compu_method", new_measurement.name),
+            ToString::to_string,
+        );
         enums::cond_create_enum_conversion(module, &enum_name, enumerators);
         new_measurement.conversion = enum_name;
     } else {
         update::set_bitmask(&mut new_measurement.bit_mask, typeinfo);
+        if let Some(name) = fixed_point::cond_create_fixed_point_conversion(
+            module, debug_data, typeinfo, &item_name, version,
+        ) {
+            // the limits are physical values
+            let factor = debug_data.fixed_point_factor(typeinfo).unwrap_or(1.0);
+            new_measurement.lower_limit *= factor;
+            new_measurement.upper_limit *= factor;
+            new_measurement.conversion = name;
+        }
     }
     module.measurement.push(new_measurement);
 
@@ -269,10 +340,18 @@ fn insert_characteristic_sym(
     if let DwarfDataType::Enum { enumerators, .. } = &typeinfo.datatype {
         let enum_name = typeinfo
             .name
-            .clone()
-            .unwrap_or_else(|| format!("{item_name}_compu_method"));
+            .as_deref()
+            .map_or_else(|| format!("{item_name}_compu_method"), ToString::to_string);
         enums::cond_create_enum_conversion(module, &enum_name, enumerators);
         new_characteristic.conversion = enum_name;
+    } else if let Some(name) = fixed_point::cond_create_fixed_point_conversion(
+        module, debug_data, typeinfo, &item_name, version,
+    ) {
+        // the limits are physical values
+        let factor = debug_data.fixed_point_factor(typeinfo).unwrap_or(1.0);
+        new_characteristic.lower_limit *= factor;
+        new_characteristic.upper_limit *= factor;
+        new_characteristic.conversion = name;
     }
 
     // enable hex mode for the address (item 3 in the CHARACTERISTIC)
@@ -448,8 +527,30 @@ fn build_maps(module: &&mut Module) -> (HashMap<String, ItemType>, HashMap<Strin
     (name_map, sym_map)
 }
 
+/// get the address ranges of the named elf sections, e.g. to pass them to [`insert_many`]
+/// a message is logged for each section that does not exist
+pub fn section_ranges(
+    debugdata: &DebugData,
+    section_names: &[&str],
+    log_msgs: &mut Vec<String>,
+) -> Vec<(u64, u64)> {
+    let mut ranges = Vec::new();
+    for section in section_names {
+        if let Some(range) = debugdata.section_range(section) {
+            ranges.push(range);
+        } else {
+            log_msgs.push(format!(
+                "Cannot insert items from non-existent section {section}!"
+            ));
+        }
+    }
+    ranges
+}
+
+/// insert MEASUREMENTs and CHARACTERISTICs for all variables in the debug info
+/// whose address is inside one of the ranges, or whose name matches one of the regexes
 #[allow(clippy::too_many_arguments)]
-pub(crate) fn insert_many<'param>(
+pub fn insert_many<'param>(
     a2l_file: &mut A2lFile,
     debugdata: &DebugData,
     measurement_ranges: &'param [(u64, u64)],
@@ -457,6 +558,7 @@ pub(crate) fn insert_many<'param>(
     measurement_regexes: Vec<&str>,
     characteristic_regexes: Vec<&str>,
     target_group: Option<&str>,
+    group_by: Option<GroupBy>,
     log_msgs: &mut Vec<String>,
     enable_structures: bool,
 ) {
@@ -475,6 +577,7 @@ pub(crate) fn insert_many<'param>(
         sym_map,
         characteristic_list: Vec::new(),
         measurement_list: Vec::new(),
+        item_source_files: HashMap::new(),
         meas_count: 0u32,
         chara_count: 0u32,
         instance_count: 0u32,
@@ -545,6 +648,24 @@ pub(crate) fn insert_many<'param>(
         }
     }
 
+    match group_by {
+        Some(GroupBy::Struct) => create_struct_groups(
+            isupp.module,
+            &isupp.characteristic_list,
+            &isupp.measurement_list,
+            log_msgs,
+        ),
+        Some(group_by) => create_source_file_groups(
+            isupp.module,
+            isupp.debug_data,
+            group_by == GroupBy::Directory,
+            &isupp.characteristic_list,
+            &isupp.measurement_list,
+            &isupp.item_source_files,
+            log_msgs,
+        ),
+        None => {}
+    }
     if let Some(group_name) = target_group {
         create_or_update_group(
             isupp.module,
@@ -616,6 +737,11 @@ fn check_and_insert_simple_type(
                     sym_info.address
                 ));
                 isupp.measurement_list.push(measurement_name.clone());
+                if let Some(decl_file) = sym_info.decl_file {
+                    isupp
+                        .item_source_files
+                        .insert(measurement_name.clone(), decl_file);
+                }
                 isupp.meas_count += 1;
 
                 // update mappings to prevent the creation of duplicates
@@ -653,6 +779,11 @@ fn check_and_insert_simple_type(
                     sym_info.address
                 ));
                 isupp.characteristic_list.push(characteristic_name.clone());
+                if let Some(decl_file) = sym_info.decl_file {
+                    isupp
+                        .item_source_files
+                        .insert(characteristic_name.clone(), decl_file);
+                }
                 isupp.chara_count += 1;
 
                 // update mappings to prevent the creation of duplicates
@@ -700,6 +831,11 @@ fn check_and_insert_instance<'dbg>(
                     sym_info.address
                 ));
                 isupp.measurement_list.push(instance_name.clone());
+                if let Some(decl_file) = sym_info.decl_file {
+                    isupp
+                        .item_source_files
+                        .insert(instance_name.clone(), decl_file);
+                }
                 isupp.instance_count += 1;
 
                 // update mappings to prevent the creation of duplicates
@@ -740,6 +876,11 @@ fn check_and_insert_instance<'dbg>(
                     sym_info.address
                 ));
                 isupp.measurement_list.push(instance_name.clone());
+                if let Some(decl_file) = sym_info.decl_file {
+                    isupp
+                        .item_source_files
+                        .insert(instance_name.clone(), decl_file);
+                }
                 isupp.instance_count += 1;
 
                 // update mappings to prevent the creation of duplicates
@@ -823,6 +964,215 @@ fn create_or_update_group(
     }
 }
 
+// add the new items to one group per source file or per directory.
+// Items whose source file is unknown are not added to any of these groups
+fn create_source_file_groups(
+    module: &mut Module,
+    debug_data: &DebugData,
+    by_directory: bool,
+    characteristic_list: &[String],
+    measurement_list: &[String],
+    item_source_files: &HashMap<String, usize>,
+    log_msgs: &mut Vec<String>,
+) {
+    let mut groups: IndexMap<String, (Vec<String>, Vec<String>)> = IndexMap::new();
+    let group_name_of = |item_name: &String| {
+        let source_file = debug_data
+            .source_files
+            .get(*item_source_files.get(item_name)?)?;
+        make_source_group_name(source_file, by_directory)
+    };
+    for characteristic_name in characteristic_list {
+        if let Some(group_name) = group_name_of(characteristic_name) {
+            let (characteristics, _) = groups.entry(group_name).or_default();
+            characteristics.push(characteristic_name.clone());
+        }
+    }
+    for measurement_name in measurement_list {
+        if let Some(group_name) = group_name_of(measurement_name) {
+            let (_, measurements) = groups.entry(group_name).or_default();
+            measurements.push(measurement_name.clone());
+        }
+    }
+
+    for (group_name, (characteristics, measurements)) in groups {
+        log_msgs.push(format!(
+            "Added {} items to GROUP {group_name}",
+            characteristics.len() + measurements.len()
+        ));
+        create_or_update_group(module, &group_name, characteristics, measurements);
+    }
+}
+
+/// Add each MEASUREMENT, CHARACTERISTIC and AXIS_PTS to a GROUP named after the elf section that contains its address,
+/// e.g. objects in the section ".cal_engine" are added to the group "cal_engine".
+///
+/// Groups are created as needed, and objects that are already part of their section group are not added again.
+/// Objects with the address 0 and objects outside of all sections are not added to any group.
+///
+/// Returns the number of objects that were added to a group.
+pub fn create_section_groups(
+    a2l_file: &mut A2lFile,
+    debug_data: &DebugData,
+    log_msgs: &mut Vec<String>,
+) -> usize {
+    // if sections overlap, then the smallest containing section is used
+    let mut sections: Vec<(&String, u64, u64)> = debug_data
+        .sections
+        .iter()
+        .map(|(name, (start, end))| (name, *start, *end))
+        .collect();
+    sections.sort_by_key(|(name, start, end)| (end - start, *start, *name));
+    let section_of = |address: u32| {
+        let address = u64::from(address);
+        if address == 0 {
+            return None;
+        }
+        sections
+            .iter()
+            .find(|(_, start, end)| *start <= address && address < *end)
+            .map(|(name, _, _)| *name)
+    };
+
+    let mut count = 0;
+    for module in &mut a2l_file.project.module {
+        let mut groups: IndexMap<String, (Vec<String>, Vec<String>)> = IndexMap::new();
+        for characteristic in &module.characteristic {
+            if let Some(section) = section_of(characteristic.address) {
+                let (characteristics, _) =
+                    groups.entry(make_section_group_name(section)).or_default();
+                characteristics.push(characteristic.name.clone());
+            }
+        }
+        for axis_pts in &module.axis_pts {
+            if let Some(section) = section_of(axis_pts.address) {
+                let (characteristics, _) =
+                    groups.entry(make_section_group_name(section)).or_default();
+                characteristics.push(axis_pts.name.clone());
+            }
+        }
+        for measurement in &module.measurement {
+            if let Some(section) = measurement
+                .ecu_address
+                .as_ref()
+                .and_then(|ecu_address| section_of(ecu_address.address))
+            {
+                let (_, measurements) = groups.entry(make_section_group_name(section)).or_default();
+                measurements.push(measurement.name.clone());
+            }
+        }
+
+        for (group_name, (mut characteristics, mut measurements)) in groups {
+            // skip the objects that are already in the group, so that repeated runs don't create duplicates
+            if let Some(group) = module.group.iter().find(|grp| grp.name == group_name) {
+                if let Some(ref_characteristic) = &group.ref_characteristic {
+                    characteristics
+                        .retain(|name| !ref_characteristic.identifier_list.contains(name));
+                }
+                if let Some(ref_measurement) = &group.ref_measurement {
+                    measurements.retain(|name| !ref_measurement.identifier_list.contains(name));
+                }
+            }
+            let added = characteristics.len() + measurements.len();
+            if added > 0 {
+                log_msgs.push(format!("Added {added} items to GROUP {group_name}"));
+                create_or_update_group(module, &group_name, characteristics, measurements);
+                count += added;
+            }
+        }
+    }
+    count
+}
+
+// derive a valid group name from the name of an elf section: ".cal_engine" becomes "cal_engine"
+fn make_section_group_name(section_name: &str) -> String {
+    let mut group_name: String = section_name
+        .trim_start_matches('.')
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if group_name.is_empty() || group_name.starts_with(|c: char| c.is_ascii_digit()) {
+        group_name.insert(0, '_');
+    }
+    group_name
+}
+
+// derive a valid group name from the path of a source file: "src/engine/ignition.c" becomes
+// "ignition_c" when grouping by source file, or "engine" when grouping by directory
+fn make_source_group_name(source_file: &str, by_directory: bool) -> Option<String> {
+    let mut components = source_file
+        .split(['/', '\\'])
+        .filter(|component| !component.is_empty() && *component != ".");
+    let name = if by_directory {
+        components.rev().nth(1)?
+    } else {
+        components.next_back()?
+    };
+    let mut group_name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if group_name.starts_with(|c: char| c.is_ascii_digit()) {
+        group_name.insert(0, '_');
+    }
+    Some(group_name)
+}
+
+// add the new items to nested groups that follow the struct hierarchy: the item "cal.ignition.map"
+// is added to the group "cal.ignition", which is a sub-group of the root group "cal".
+// Items that are not struct members are not added to any of these groups
+fn create_struct_groups(
+    module: &mut Module,
+    characteristic_list: &[String],
+    measurement_list: &[String],
+    log_msgs: &mut Vec<String>,
+) {
+    let mut groups: IndexMap<&str, (Vec<String>, Vec<String>)> = IndexMap::new();
+    for characteristic_name in characteristic_list {
+        if let Some((parent, _)) = characteristic_name.rsplit_once('.') {
+            let (characteristics, _) = groups.entry(parent).or_default();
+            characteristics.push(characteristic_name.clone());
+        }
+    }
+    for measurement_name in measurement_list {
+        if let Some((parent, _)) = measurement_name.rsplit_once('.') {
+            let (_, measurements) = groups.entry(parent).or_default();
+            measurements.push(measurement_name.clone());
+        }
+    }
+
+    for (group_name, (characteristics, measurements)) in groups {
+        log_msgs.push(format!(
+            "Added {} items to GROUP {group_name}",
+            characteristics.len() + measurements.len()
+        ));
+        create_group_path(module, group_name);
+        create_or_update_group(module, group_name, characteristics, measurements);
+    }
+}
+
+// create a group and all of its parent groups, if they don't exist yet.
+// Only the top-level group gets the ROOT attribute, all others are referenced as SUB_GROUPs
+fn create_group_path(module: &mut Module, group_name: &str) {
+    if module.group.iter().any(|grp| grp.name == group_name) {
+        return;
+    }
+    let mut group = Group::new(group_name.to_string(), String::new());
+    if let Some((parent_name, _)) = group_name.rsplit_once('.') {
+        create_group_path(module, parent_name);
+        if let Some(parent) = module.group.iter_mut().find(|grp| grp.name == parent_name) {
+            parent
+                .sub_group
+                .get_or_insert_with(SubGroup::new)
+                .identifier_list
+                .push(group_name.to_string());
+        }
+    } else {
+        group.root = Some(Root::new());
+    }
+    module.group.push(group);
+}
+
 fn insert_instance_sym<'dbg>(
     module: &mut Module,
     debug_data: &'dbg DebugData,
@@ -876,3 +1226,139 @@ fn insert_instance_sym<'dbg>(
         ))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::ffi::OsStr;
+
+    #[test]
+    fn test_insert_group_by_source_file() {
+        let debug_data =
+            DebugData::load(OsStr::new("tests/elffiles/update_test.elf"), false).unwrap();
+        let mut a2l_file = crate::load::new_a2l();
+        let mut log_msgs = Vec::new();
+        insert_items(
+            &mut a2l_file,
+            &debug_data,
+            vec!["val_u8"],
+            vec!["val_f"],
+            None,
+            Some(GroupBy::SourceFile),
+            &mut log_msgs,
+            false,
+        );
+        let module = &a2l_file.project.module[0];
+        assert_eq!(module.group.len(), 1);
+        let group = &module.group[0];
+        assert_eq!(group.name, "update_test_c");
+        assert!(group.root.is_some());
+        assert_eq!(
+            group.ref_measurement.as_ref().unwrap().identifier_list,
+            vec!["val_u8".to_string()]
+        );
+        assert_eq!(
+            group.ref_characteristic.as_ref().unwrap().identifier_list,
+            vec!["val_f".to_string()]
+        );
+
+        assert_eq!(
+            make_source_group_name("/home/user/src/engine/ignition.c", true),
+            Some("engine".to_string())
+        );
+        assert_eq!(
+            make_source_group_name("C:\\src\\2d-map.c", false),
+            Some("_2d_map_c".to_string())
+        );
+        assert_eq!(make_source_group_name("main.c", true), None);
+    }
+
+    #[test]
+    fn test_create_section_groups() {
+        let debug_data =
+            DebugData::load(OsStr::new("tests/elffiles/update_test.elf"), false).unwrap();
+        let mut a2l_file = crate::load::new_a2l();
+        let mut log_msgs = Vec::new();
+        insert_items(
+            &mut a2l_file,
+            &debug_data,
+            vec!["val_u8"],
+            vec!["val_f"],
+            None,
+            None,
+            &mut log_msgs,
+            false,
+        );
+        let count = create_section_groups(&mut a2l_file, &debug_data, &mut log_msgs);
+        assert_eq!(count, 2);
+        let module = &a2l_file.project.module[0];
+        assert_eq!(module.group.len(), 1);
+        let group = &module.group[0];
+        assert_eq!(group.name, "bss");
+        assert_eq!(
+            group.ref_measurement.as_ref().unwrap().identifier_list,
+            vec!["val_u8".to_string()]
+        );
+        assert_eq!(
+            group.ref_characteristic.as_ref().unwrap().identifier_list,
+            vec!["val_f".to_string()]
+        );
+
+        // nothing is added a second time
+        let count = create_section_groups(&mut a2l_file, &debug_data, &mut log_msgs);
+        assert_eq!(count, 0);
+
+        assert_eq!(make_section_group_name(".cal_engine"), "cal_engine");
+        assert_eq!(make_section_group_name(".1st-stage"), "_1st_stage");
+
+        let mut log_msgs = Vec::new();
+        let ranges = section_ranges(&debug_data, &[".bss", ".no_such_section"], &mut log_msgs);
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(Some(ranges[0]), debug_data.section_range(".bss"));
+        assert_eq!(log_msgs.len(), 1);
+    }
+
+    #[test]
+    fn test_insert_group_by_struct() {
+        let debug_data =
+            DebugData::load(OsStr::new("tests/elffiles/update_test.elf"), false).unwrap();
+        let mut a2l_file = crate::load::new_a2l();
+        let mut log_msgs = Vec::new();
+        insert_many(
+            &mut a2l_file,
+            &debug_data,
+            &[],
+            &[],
+            vec![],
+            vec!["^struct_b\\.s1\\."],
+            None,
+            Some(GroupBy::Struct),
+            &mut log_msgs,
+            false,
+        );
+        let module = &a2l_file.project.module[0];
+        let root = module
+            .group
+            .iter()
+            .find(|grp| grp.name == "struct_b")
+            .unwrap();
+        assert!(root.root.is_some());
+        assert_eq!(
+            root.sub_group.as_ref().unwrap().identifier_list,
+            vec!["struct_b.s1".to_string()]
+        );
+        let sub_group = module
+            .group
+            .iter()
+            .find(|grp| grp.name == "struct_b.s1")
+            .unwrap();
+        assert!(sub_group.root.is_none());
+        let items = &sub_group
+            .ref_characteristic
+            .as_ref()
+            .unwrap()
+            .identifier_list;
+        assert_eq!(items.len(), 4);
+        assert!(items.contains(&"struct_b.s1.val_f32".to_string()));
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+// Debug info and large a2l files repeat the same names many times: every compilation unit that includes
+// a header contains a copy of its types, and every variable in a namespace repeats the namespace name.
+// The interner makes sure that all copies of a name share one allocation.
+#[derive(Debug, Default)]
+pub(crate) struct StringInterner {
+    strings: HashSet<Arc<str>>,
+}
+
+impl StringInterner {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    // get the shared copy of a string, creating it if it doesn't exist yet
+    pub(crate) fn intern(&mut self, text: &str) -> Arc<str> {
+        if let Some(existing) = self.strings.get(text) {
+            existing.clone()
+        } else {
+            let new: Arc<str> = Arc::from(text);
+            self.strings.insert(new.clone());
+            new
+        }
+    }
+
+    // get the shared copy of a string only if it was interned before
+    pub(crate) fn get(&self, text: &str) -> Option<Arc<str>> {
+        self.strings.get(text).cloned()
+    }
+
+    pub(crate) fn contains(&self, text: &str) -> bool {
+        self.strings.contains(text)
+    }
+
+    pub(crate) fn insert(&mut self, text: &str) {
+        self.intern(text);
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
+impl<S: AsRef<str>> Extend<S> for StringInterner {
+    fn extend<I: IntoIterator<Item = S>>(&mut self, iter: I) {
+        for text in iter {
+            self.insert(text.as_ref());
+        }
+    }
+}
+
+impl<S: AsRef<str>> FromIterator<S> for StringInterner {
+    fn from_iter<I: IntoIterator<Item = S>>(iter: I) -> Self {
+        let mut interner = Self::new();
+        interner.extend(iter);
+        interner
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_intern() {
+        let mut interner = StringInterner::new();
+        let first = interner.intern("name");
+        let second = interner.intern(&String::from("name"));
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(interner.strings.len(), 1);
+        assert!(interner.contains("name"));
+        assert!(interner.get("other").is_none());
+
+        let collected: StringInterner = ["a", "b", "a"].into_iter().collect();
+        assert_eq!(collected.strings.len(), 2);
+        assert!(!collected.is_empty());
+    }
+}
@@ -0,0 +1,154 @@
+//! a2ltool library
+//!
+//! This crate contains the functionality of the a2ltool command line program, so that it can be used
+//! directly from other Rust programs.
+//!
+//! The typical workflow is:
+//! 1. load an a2l file with [`load::load_a2l`] or create one with [`load::new_a2l`]
+//! 2. load the debug information of the compiled program using [`DebugData::load`]
+//! 3. modify the file, e.g. with [`merge`], [`update::update_addresses`], [`insert`], [`xcp`] or [`ccp`]
+//! 4. run the consistency checks with [`check`]
+//! 5. write the file using the methods of [`a2lfile::A2lFile`]
+//!
+//! All operations report problems by adding messages to a `Vec<String>` log instead of printing them.
+//!
+//! Features:
+//! - `elf` (enabled by `cli`): reading debug info from elf files, which is needed for [`update`] and [`insert`]
+//! - `cli` (default): the command line program
+//!
+//! Without default features the library can be compiled for wasm32-unknown-unknown; see [`wasm`].
+
+use a2lfile::A2lFile;
+use std::fmt::Display;
+
+pub mod a2ml;
+pub mod address_extension;
+pub mod address_offset;
+#[cfg(feature = "elf")]
+pub mod alignment;
+pub mod anonymize;
+pub mod batch;
+#[cfg(feature = "elf")]
+pub mod build_id;
+pub mod calibration_method;
+pub mod calvalues;
+pub mod ccp;
+pub mod cdfx;
+pub mod check;
+pub mod checksum;
+pub mod codegen;
+pub mod comments;
+pub mod compu;
+pub mod convert;
+#[cfg(feature = "elf")]
+mod datatype;
+pub mod dcm;
+pub mod decode_ifdata;
+pub mod display_identifier;
+#[cfg(feature = "elf")]
+mod dwarf;
+pub mod epk;
+#[cfg(feature = "elf")]
+pub mod ffi;
+pub mod flatten;
+pub mod format;
+pub mod formula;
+pub mod hexfile;
+pub mod ifdata;
+#[cfg(feature = "elf")]
+pub mod insert;
+#[cfg(feature = "elf")]
+mod intern;
+pub mod load;
+pub mod memmap;
+#[cfg(feature = "elf")]
+pub mod memory_segments;
+pub mod merge;
+pub mod metadata;
+#[cfg(feature = "elf")]
+pub mod modernize;
+pub mod modpar;
+pub mod module_scope;
+pub mod plugin;
+pub mod query;
+pub mod remove_ifdata;
+pub mod rename;
+pub mod split;
+pub mod statistics;
+#[cfg(feature = "elf")]
+mod symbol;
+#[cfg(feature = "elf")]
+pub mod update;
+pub mod validate_ifdata;
+pub mod variant_coding;
+pub mod version;
+pub mod virtual_measurement;
+pub mod wasm;
+pub mod xcp;
+pub mod zip;
+
+#[cfg(feature = "elf")]
+pub use dwarf::{CaseTieBreak, DebugData, DuplicateSymbolPolicy};
+
+// parse an integer given on the command line or in a config file: decimal, or hex with the prefix 0x,
+// optionally preceded by a sign
+pub(crate) fn parse_number(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let (digits, radix) = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => (hex, 16),
+        None => (digits, 10),
+    };
+    // from_str_radix would accept another sign after the prefix
+    if digits.starts_with(['+', '-']) {
+        return None;
+    }
+    let value = i64::from_str_radix(digits, radix).ok()?;
+    Some(if negative { -value } else { value })
+}
+
+/// The versions of the a2l file format that are supported by a2ltool
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum A2lVersion {
+    V1_5_0,
+    V1_5_1,
+    V1_6_0,
+    V1_6_1,
+    V1_7_0,
+    V1_7_1,
+}
+
+impl From<&A2lFile> for A2lVersion {
+    fn from(a2l_file: &A2lFile) -> Self {
+        if let Some(asap2_version) = &a2l_file.asap2_version {
+            match (asap2_version.version_no, asap2_version.upgrade_no) {
+                (1, 51) => A2lVersion::V1_5_1,
+                (1, 60) => A2lVersion::V1_6_0,
+                (1, 61) => A2lVersion::V1_6_1,
+                (1, 70) => A2lVersion::V1_7_0,
+                (1, 71) => A2lVersion::V1_7_1,
+                _ => A2lVersion::V1_5_0,
+            }
+        } else {
+            A2lVersion::V1_5_0
+        }
+    }
+}
+
+impl Display for A2lVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            A2lVersion::V1_5_0 => f.write_str("1.5.0"),
+            A2lVersion::V1_5_1 => f.write_str("1.5.1"),
+            A2lVersion::V1_6_0 => f.write_str("1.6.0"),
+            A2lVersion::V1_6_1 => f.write_str("1.6.1"),
+            A2lVersion::V1_7_0 => f.write_str("1.7.0"),
+            A2lVersion::V1_7_1 => f.write_str("1.7.1"),
+        }
+    }
+}
@@ -0,0 +1,1025 @@
+//! Loading and creating a2l files
+
+use crate::ifdata;
+use a2lfile::{A2lError, A2lFile, A2lObject, Module};
+use regex::bytes::Regex;
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Load an a2l file.
+///
+/// The built-in A2ML specification for XCP, CCP and CANape IF_DATA is always used while parsing.
+/// If `additional_a2ml` is given, its IF_DATA definitions are merged into the built-in specification.
+///
+/// If the file turns out to be an a2l fragment that only contains the content of a MODULE,
+/// then a new file is created around it.
+///
+/// The file can also be read from a ZIP archive by giving its name as `archive.zip!path/inner.a2l`, or just
+/// `archive.zip` if the archive contains only one complete a2l file. The /include directives are resolved
+/// inside the archive, and the included files are merged into the loaded file, because they don't exist
+/// outside of the archive.
+///
+/// # Errors
+///
+/// Returns an error message if the file cannot be loaded or parsed
+pub fn load_a2l(
+    filename: &OsStr,
+    additional_a2ml: Option<&str>,
+    strict: bool,
+    log_msgs: &mut Vec<A2lError>,
+) -> Result<A2lFile, String> {
+    load_a2l_with_include_dirs(filename, additional_a2ml, &[], strict, log_msgs)
+}
+
+/// Load an a2l file, like [`load_a2l`], and search for included files in additional directories.
+///
+/// Environment variables in /include directives are expanded, e.g. `/include "$(PROJECT_ROOT)/common.a2l"`.
+/// Both `$(NAME)` and `${NAME}` are supported.
+/// An included file is searched relative to the including file first, then in each of the `include_dirs`,
+/// and finally relative to the current directory.
+/// Includes that refer to an environment variable which is not set are not resolved, so the parser reports them as missing.
+/// Commented out includes are ignored.
+/// The /include directives are written to the output unchanged.
+///
+/// If all included files are included directly inside of a MODULE and don't include further files, then
+/// they are parsed in parallel. The result is the same as if the files were parsed one after the other.
+///
+/// # Errors
+///
+/// Returns an error message if the file cannot be loaded or parsed
+pub fn load_a2l_with_include_dirs(
+    filename: &OsStr,
+    additional_a2ml: Option<&str>,
+    include_dirs: &[PathBuf],
+    strict: bool,
+    log_msgs: &mut Vec<A2lError>,
+) -> Result<A2lFile, String> {
+    let a2ml_spec = if let Some(additional_a2ml) = additional_a2ml {
+        crate::a2ml::merge_a2ml_spec(ifdata::A2MLVECTOR_TEXT, additional_a2ml)?
+    } else {
+        ifdata::A2MLVECTOR_TEXT.to_string()
+    };
+
+    if let Some((archive, inner_name)) = crate::zip::split_archive_path(filename) {
+        return load_a2l_from_zip(&archive, inner_name, a2ml_spec, strict, log_msgs);
+    }
+
+    let tree = read_include_tree(Path::new(filename), include_dirs);
+    let thread_count = std::thread::available_parallelism().map_or(4, usize::from);
+    if let Some(a2l_file) = load_parallel(&tree, &a2ml_spec, strict, log_msgs, thread_count) {
+        return Ok(a2l_file);
+    }
+
+    // the parser can only find included files relative to the including file or the current directory.
+    // Otherwise the include tree is copied to a temporary directory, where each file is placed at the
+    // location that the parser expects
+    let mirror = IncludeMirror::create(Path::new(filename), &tree)?;
+    let filename = mirror
+        .as_ref()
+        .map_or(filename, |mirror| mirror.main_file.as_os_str());
+
+    let result = match a2lfile::load(filename, Some(a2ml_spec), log_msgs, strict) {
+        Ok(a2l_file) => Ok(a2l_file),
+        Err(
+            ref error @ A2lError::ParserError {
+                parser_error: a2lfile::ParserError::InvalidMultiplicityNotPresent { ref block, .. },
+            },
+        ) if block == "A2L_FILE" => {
+            // parse error in the outermost block "A2L_FILE" could indicate that this is an a2l fragment containing only the content of a MODULE
+            if let Ok(module) = a2lfile::load_fragment_file(filename) {
+                // successfully loaded a module, now upgrade it to a full file
+                let mut a2l_file = a2lfile::new();
+                a2l_file.project.module[0] = module;
+                a2l_file.project.module[0].get_layout_mut().start_offset = 1;
+                Ok(a2l_file)
+            } else {
+                Err(error.to_string())
+            }
+        }
+        Err(error) => Err(error.to_string()),
+    };
+    // error messages should refer to the original files instead of their copies
+    match &mirror {
+        Some(mirror) => result.map_err(|errmsg| mirror.restore_paths(errmsg)),
+        None => result,
+    }
+}
+
+// the archive is extracted into a temporary directory, where the parser can resolve the /include directives
+fn load_a2l_from_zip(
+    archive_path: &Path,
+    inner_name: Option<String>,
+    a2ml_spec: String,
+    strict: bool,
+    log_msgs: &mut Vec<A2lError>,
+) -> Result<A2lFile, String> {
+    let archive = crate::zip::ZipArchive::load(archive_path)?;
+    if let Some(inner_name) = &inner_name {
+        if !archive.file_names().any(|name| name == inner_name) {
+            return Err(format!(
+                "Error: the archive {} does not contain {inner_name}",
+                archive_path.to_string_lossy()
+            ));
+        }
+    }
+
+    let extract_dir = std::env::temp_dir().join(format!(
+        "a2ltool-{}-{}",
+        std::process::id(),
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_nanos())
+    ));
+    let result = archive.extract_all(&extract_dir).and_then(|()| {
+        let inner_name = match inner_name {
+            Some(inner_name) => inner_name,
+            None => find_main_a2l_file(&archive, archive_path, &extract_dir)?,
+        };
+        a2lfile::load(
+            extract_dir.join(&inner_name),
+            Some(a2ml_spec),
+            log_msgs,
+            strict,
+        )
+        .map_err(|err| err.to_string())
+    });
+    let _ = std::fs::remove_dir_all(&extract_dir);
+    let mut a2l_file = result?;
+    a2l_file.merge_includes();
+    Ok(a2l_file)
+}
+
+// find the main a2l file among the extracted files of the archive.
+// Included files don't contain a PROJECT, so only the main file of the delivery is found
+fn find_main_a2l_file(
+    archive: &crate::zip::ZipArchive,
+    archive_path: &Path,
+    extract_dir: &Path,
+) -> Result<String, String> {
+    let a2l_names: Vec<&str> = archive
+        .file_names()
+        .filter(|name| name.to_ascii_lowercase().ends_with(".a2l"))
+        .filter(|name| {
+            std::fs::read(extract_dir.join(name))
+                .is_ok_and(|content| String::from_utf8_lossy(&content).contains("/begin PROJECT"))
+        })
+        .collect();
+    match a2l_names[..] {
+        [name] => Ok(name.to_string()),
+        [] => Err(format!(
+            "Error: the archive {} does not contain an a2l file",
+            archive_path.to_string_lossy()
+        )),
+        _ => Err(format!(
+            "Error: the archive {} contains several a2l files, select one with {}!<name>. Available files: {}",
+            archive_path.to_string_lossy(),
+            archive_path.to_string_lossy(),
+            a2l_names.join(", ")
+        )),
+    }
+}
+
+// a copy of an include tree in a temporary directory. The directory is removed when the mirror is dropped
+struct IncludeMirror {
+    root: PathBuf,
+    main_file: PathBuf,
+    // each copied file and its original
+    files: Vec<(PathBuf, PathBuf)>,
+}
+
+impl IncludeMirror {
+    // returns None if no include needs to be resolved by a2ltool
+    fn create(filename: &Path, tree: &[TreeFile]) -> Result<Option<Self>, String> {
+        let mut needed = false;
+        let mut max_depth = 0;
+        for TreeFile { path, includes, .. } in tree {
+            let base_dir = path.parent().unwrap_or(Path::new(""));
+            for (name, _) in includes {
+                needed |= !(base_dir.join(name).exists() || Path::new(name).exists());
+                max_depth += Path::new(name)
+                    .components()
+                    .filter(|component| *component == Component::ParentDir)
+                    .count();
+            }
+        }
+        if !needed {
+            return Ok(None);
+        }
+
+        let root = std::env::temp_dir().join(format!(
+            "a2ltool-include-{}-{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_nanos())
+        ));
+        // the main file is placed deep enough that includes with ".." stay inside of the mirror
+        let mut main_dir = root.clone();
+        for _ in 0..max_depth {
+            main_dir.push("d");
+        }
+        let mut mirror = Self {
+            main_file: main_dir.join(filename.file_name().unwrap_or(OsStr::new("main.a2l"))),
+            root,
+            files: Vec::new(),
+        };
+
+        let mut placed = HashSet::new();
+        let mut queue = vec![(filename.to_path_buf(), mirror.main_file.clone())];
+        while let Some((file, mirror_file)) = queue.pop() {
+            if !placed.insert(mirror_file.clone()) {
+                continue;
+            }
+            let write_error = |err: std::io::Error| {
+                format!(
+                    "Error: could not copy \"{}\" to a temporary directory: {err}",
+                    file.to_string_lossy()
+                )
+            };
+            if let Some(parent) = mirror_file.parent() {
+                std::fs::create_dir_all(parent).map_err(write_error)?;
+            }
+            std::fs::copy(&file, &mirror_file).map_err(write_error)?;
+            mirror.files.push((mirror_file.clone(), file.clone()));
+
+            let mirror_dir = mirror_file.parent().unwrap_or(Path::new(""));
+            let includes = tree
+                .iter()
+                .find(|tree_file| tree_file.path == file)
+                .map(|tree_file| tree_file.includes.as_slice())
+                .unwrap_or_default();
+            for (name, resolved) in includes {
+                // absolute names are found by the parser without help
+                if !Path::new(name).is_absolute() {
+                    let target = normalize_path(&mirror_dir.join(name));
+                    if target.starts_with(&mirror.root) {
+                        queue.push((resolved.clone(), target));
+                    }
+                }
+            }
+        }
+        Ok(Some(mirror))
+    }
+
+    // replace the names of the copied files in a message with the names of the original files
+    fn restore_paths(&self, message: String) -> String {
+        let mut files: Vec<_> = self.files.iter().collect();
+        // a longer name could contain a shorter one
+        files.sort_by_key(|(mirror_file, _)| std::cmp::Reverse(mirror_file.as_os_str().len()));
+        files
+            .into_iter()
+            .fold(message, |message, (mirror_file, file)| {
+                message.replace(&*mirror_file.to_string_lossy(), &file.to_string_lossy())
+            })
+    }
+}
+
+impl Drop for IncludeMirror {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+// a file of an include tree
+struct TreeFile {
+    path: PathBuf,
+    content: Vec<u8>,
+    // the names in the /include directives of the file and the files they refer to
+    includes: Vec<(String, PathBuf)>,
+}
+
+// an /include directive in the text of a file
+#[derive(Debug, PartialEq)]
+struct IncludeDirective {
+    range: Range<usize>,
+    name: String,
+    // the index of the MODULE, if the directive is placed directly inside of a MODULE
+    module: Option<usize>,
+}
+
+// find the include tree of a file: for each file that could be read, the names in its /include directives
+// and the files they refer to. The file itself is the first entry.
+// Includes that can't be resolved are left out; the parser reports them when the file is loaded.
+pub(crate) fn include_tree(
+    filename: &Path,
+    include_dirs: &[PathBuf],
+) -> Vec<(PathBuf, Vec<(String, PathBuf)>)> {
+    read_include_tree(filename, include_dirs)
+        .into_iter()
+        .map(|file| (file.path, file.includes))
+        .collect()
+}
+
+// read all files of the include tree. Each file is only read once
+fn read_include_tree(filename: &Path, include_dirs: &[PathBuf]) -> Vec<TreeFile> {
+    let mut tree = Vec::new();
+    let mut visited = HashSet::from([filename.to_path_buf()]);
+    let mut queue = vec![filename.to_path_buf()];
+    while let Some(path) = queue.pop() {
+        let Ok(content) = std::fs::read(&path) else {
+            continue;
+        };
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        let mut includes = Vec::new();
+        for name in find_includes(&content) {
+            let Some(resolved) = resolve_include(&name, base_dir, include_dirs) else {
+                continue;
+            };
+            if visited.insert(resolved.clone()) {
+                queue.push(resolved.clone());
+            }
+            includes.push((name, resolved));
+        }
+        tree.push(TreeFile {
+            path,
+            content,
+            includes,
+        });
+    }
+    tree
+}
+
+// Load a file whose included files contain the content of a MODULE, by parsing the included files in parallel.
+// Each included file is parsed inside of an empty MODULE, and its objects are then merged into the MODULE
+// that contains the /include directive, in the order of the directives. The objects are renumbered
+// in the order in which the parser would have found them, so that the file is written in the same way.
+// Returns None if this is not possible; in this case the file must be loaded by the parser, which also
+// reports any errors.
+fn load_parallel(
+    tree: &[TreeFile],
+    a2ml_spec: &str,
+    strict: bool,
+    log_msgs: &mut Vec<A2lError>,
+    thread_count: usize,
+) -> Option<A2lFile> {
+    let main_file = tree.first()?;
+    let directives = include_directives(&main_file.content);
+    // every directive must be resolved, and the included files must not include further files
+    if directives.is_empty() || directives.len() != main_file.includes.len() {
+        return None;
+    }
+    let mut texts = Vec::with_capacity(directives.len());
+    for (directive, (name, resolved)) in directives.iter().zip(&main_file.includes) {
+        let included = tree.iter().find(|file| file.path == *resolved)?;
+        if directive.module.is_none() || directive.name != *name || !included.includes.is_empty() {
+            return None;
+        }
+        texts.push(std::str::from_utf8(&included.content).ok()?);
+    }
+
+    // parse the main file without the /include directives; the line numbers stay the same
+    let mut main_content = main_file.content.clone();
+    for directive in &directives {
+        for c in &mut main_content[directive.range.clone()] {
+            if !c.is_ascii_whitespace() {
+                *c = b' ';
+            }
+        }
+    }
+    let mut main_log_msgs = Vec::new();
+    let mut a2l_file = a2lfile::load_from_string(
+        std::str::from_utf8(&main_content).ok()?,
+        Some(a2ml_spec.to_string()),
+        &mut main_log_msgs,
+        strict,
+    )
+    .ok()?;
+
+    // the included files are parsed with the version and A2ML of the file that includes them
+    let version = a2l_file
+        .asap2_version
+        .as_ref()
+        .map(|version| {
+            format!(
+                "ASAP2_VERSION {} {} ",
+                version.version_no, version.upgrade_no
+            )
+        })
+        .unwrap_or_default();
+    let headers: Vec<String> = a2l_file
+        .project
+        .module
+        .iter()
+        .map(|module| {
+            let a2ml = module
+                .a2ml
+                .as_ref()
+                .map(|a2ml| {
+                    format!(
+                        "/begin A2ML {} /end A2ML ",
+                        single_line_a2ml(&a2ml.a2ml_text)
+                    )
+                })
+                .unwrap_or_default();
+            format!("{version}/begin PROJECT p \"\" /begin MODULE m \"\" {a2ml}")
+        })
+        .collect();
+    let mut jobs = Vec::with_capacity(directives.len());
+    for (directive, text) in directives.iter().zip(texts) {
+        jobs.push((headers.get(directive.module?)?, text));
+    }
+
+    // the threads take the next unparsed file until all are done. Each result is stored at the
+    // index of its directive, so the merge does not depend on the order in which the threads finish
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<Option<(Module, Vec<A2lError>)>> = Vec::new();
+    results.resize_with(jobs.len(), || None);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..thread_count.clamp(1, jobs.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut parsed = Vec::new();
+                    loop {
+                        let idx = next.fetch_add(1, Ordering::Relaxed);
+                        let Some((header, text)) = jobs.get(idx) else {
+                            break;
+                        };
+                        parsed.push((idx, parse_included(header, text, a2ml_spec, strict)));
+                    }
+                    parsed
+                })
+            })
+            .collect();
+        for handle in handles {
+            for (idx, result) in handle.join().expect("parser thread panicked") {
+                results[idx] = result;
+            }
+        }
+    });
+
+    // the objects of each included file are placed at the line of its /include directive
+    let mut included_modules: Vec<Vec<(u32, Module)>> =
+        a2l_file.project.module.iter().map(|_| Vec::new()).collect();
+    let mut fragment_log_msgs = Vec::new();
+    for (directive, result) in directives.iter().zip(results) {
+        let (mut module, msgs) = result?;
+        let module_idx = directive.module?;
+        // the A2ML of the main file was only added to parse the included file
+        if a2l_file.project.module[module_idx].a2ml.is_some() {
+            module.a2ml = None;
+        }
+        set_incfile(&mut module, &directive.name);
+        let line = main_file.content[..directive.range.start]
+            .iter()
+            .filter(|c| **c == b'\n')
+            .count()
+            + 1;
+        included_modules[module_idx].push((u32::try_from(line).unwrap_or(u32::MAX), module));
+        fragment_log_msgs.extend(msgs);
+    }
+    for (module, included) in a2l_file.project.module.iter_mut().zip(included_modules) {
+        if !included.is_empty() {
+            merge_included(module, included)?;
+        }
+    }
+    log_msgs.extend(main_log_msgs);
+    log_msgs.extend(fragment_log_msgs);
+    Some(a2l_file)
+}
+
+// parse the content of an included file inside of an otherwise empty MODULE
+fn parse_included(
+    header: &str,
+    text: &str,
+    a2ml_spec: &str,
+    strict: bool,
+) -> Option<(Module, Vec<A2lError>)> {
+    let text = format!("{header}{text}\n/end MODULE /end PROJECT\n");
+    let mut log_msgs = Vec::new();
+    let mut a2l_file =
+        a2lfile::load_from_string(&text, Some(a2ml_spec.to_string()), &mut log_msgs, strict)
+            .ok()?;
+    let module = a2l_file.project.module.pop()?;
+    Some((module, log_msgs))
+}
+
+// Generate the functions that handle all objects of a module. Optional objects are treated as lists
+// with at most one element.
+macro_rules! module_items {
+    (lists: [$($list:ident),*], options: [$($opt:ident),*]) => {
+        // mark the objects of a module as loaded from an included file
+        fn set_incfile(module: &mut Module, incfile: &str) {
+            $(for item in &mut module.$list {
+                item.get_layout_mut().incfile = Some(incfile.to_string());
+            })*
+            $(if let Some(item) = &mut module.$opt {
+                item.get_layout_mut().incfile = Some(incfile.to_string());
+            })*
+        }
+
+        // Merge the objects of the included files into the module. Each included module is placed at the
+        // line of its /include directive. Returns None if an optional object exists more than once.
+        fn merge_included(module: &mut Module, mut included: Vec<(u32, Module)>) -> Option<()> {
+            // renumber the objects in the order in which the parser would have found them
+            {
+                let mut keys = Vec::new();
+                uid_keys(module, None, 0, &mut keys);
+                for (idx, (line, included_module)) in included.iter_mut().enumerate() {
+                    uid_keys(included_module, Some(*line), idx + 1, &mut keys);
+                }
+                keys.sort_by_key(|(key, _)| *key);
+                for (uid, (_, item_uid)) in (1..).zip(keys) {
+                    *item_uid = uid;
+                }
+            }
+
+            for (_, included_module) in &mut included {
+                $(module.$list.append(&mut included_module.$list);)*
+                $(if let Some(item) = included_module.$opt.take() {
+                    if module.$opt.is_some() {
+                        return None;
+                    }
+                    module.$opt = Some(item);
+                })*
+            }
+            Some(())
+        }
+
+        // get the uid of each object, and the key by which it is sorted: objects of the main file are sorted
+        // by their line, and the objects of an included file by the line of its /include directive
+        fn uid_keys<'a>(
+            module: &'a mut Module,
+            line: Option<u32>,
+            source: usize,
+            keys: &mut Vec<((u32, usize, u32), &'a mut u32)>,
+        ) {
+            $(for item in &mut module.$list {
+                let layout = item.get_layout_mut();
+                keys.push(((line.unwrap_or(layout.line), source, layout.uid), &mut layout.uid));
+            })*
+            $(if let Some(item) = &mut module.$opt {
+                let layout = item.get_layout_mut();
+                keys.push(((line.unwrap_or(layout.line), source, layout.uid), &mut layout.uid));
+            })*
+        }
+    };
+}
+
+module_items!(
+    lists: [
+        axis_pts,
+        blob,
+        characteristic,
+        compu_method,
+        compu_tab,
+        compu_vtab,
+        compu_vtab_range,
+        frame,
+        function,
+        group,
+        if_data,
+        instance,
+        measurement,
+        record_layout,
+        transformer,
+        typedef_axis,
+        typedef_blob,
+        typedef_characteristic,
+        typedef_measurement,
+        typedef_structure,
+        unit,
+        user_rights
+    ],
+    options: [a2ml, mod_common, mod_par, variant_coding]
+);
+
+// write the text of an A2ML block on a single line, so that the line numbers of the text after it don't change.
+// Comments are removed, because a line comment would hide the rest of the line
+fn single_line_a2ml(a2ml_text: &str) -> String {
+    let content = a2ml_text.as_bytes();
+    let mut text = Vec::with_capacity(content.len());
+    let mut pos = 0;
+    while pos < content.len() {
+        let rest = &content[pos..];
+        if rest.starts_with(b"/*") {
+            pos = rest[2..]
+                .windows(2)
+                .position(|window| window == b"*/")
+                .map_or(content.len(), |end| pos + end + 4);
+            text.push(b' ');
+        } else if rest.starts_with(b"//") {
+            pos = rest
+                .iter()
+                .position(|c| *c == b'\n')
+                .map_or(content.len(), |end| pos + end);
+        } else if rest[0] == b'"' {
+            let end = (string_end(content, pos) + 1).min(content.len());
+            text.extend_from_slice(&content[pos..end]);
+            pos = end;
+        } else {
+            text.push(if rest[0] == b'\r' || rest[0] == b'\n' {
+                b' '
+            } else {
+                rest[0]
+            });
+            pos += 1;
+        }
+    }
+    String::from_utf8_lossy(&text).into_owned()
+}
+
+// find the names in all /include directives of a file.
+fn find_includes(content: &[u8]) -> Vec<String> {
+    include_directives(content)
+        .into_iter()
+        .map(|directive| directive.name)
+        .collect()
+}
+
+// find all /include directives of a file, and the MODULE that contains each of them.
+// Comments and strings are skipped, so that commented out includes are not mistaken for real ones
+fn include_directives(content: &[u8]) -> Vec<IncludeDirective> {
+    let mut includes = Vec::new();
+    // the names of the open blocks, and the index of each MODULE among all MODULEs
+    let mut blocks: Vec<(&[u8], Option<usize>)> = Vec::new();
+    let mut module_count = 0;
+    let mut pos = 0;
+    while pos < content.len() {
+        let rest = &content[pos..];
+        if rest.starts_with(b"/*") {
+            pos = rest[2..]
+                .windows(2)
+                .position(|window| window == b"*/")
+                .map_or(content.len(), |end| pos + end + 4);
+        } else if rest.starts_with(b"//") {
+            pos = rest
+                .iter()
+                .position(|c| *c == b'\n')
+                .map_or(content.len(), |end| pos + end);
+        } else if rest[0] == b'"' {
+            pos = string_end(content, pos) + 1;
+        } else if rest.starts_with(b"/include") && rest.get(8).is_some_and(u8::is_ascii_whitespace)
+        {
+            let mut start = pos + 8;
+            while content.get(start).is_some_and(u8::is_ascii_whitespace) {
+                start += 1;
+            }
+            let (name, end) = if content.get(start) == Some(&b'"') {
+                let end = string_end(content, start);
+                (&content[start + 1..end], (end + 1).min(content.len()))
+            } else {
+                let end = word_end(content, start);
+                (&content[start..end], end)
+            };
+            includes.push(IncludeDirective {
+                range: pos..end,
+                name: String::from_utf8_lossy(name).into_owned(),
+                module: blocks.last().and_then(|(_, module_idx)| *module_idx),
+            });
+            pos = end;
+        } else if rest.starts_with(b"/begin") && rest.get(6).is_some_and(u8::is_ascii_whitespace) {
+            let mut start = pos + 6;
+            while content.get(start).is_some_and(u8::is_ascii_whitespace) {
+                start += 1;
+            }
+            let end = word_end(content, start);
+            let block = &content[start..end];
+            let module_idx = (block == b"MODULE").then(|| {
+                module_count += 1;
+                module_count - 1
+            });
+            blocks.push((block, module_idx));
+            pos = end;
+        } else if rest.starts_with(b"/end") && rest.get(4).is_some_and(u8::is_ascii_whitespace) {
+            blocks.pop();
+            pos += 4;
+        } else {
+            pos += 1;
+        }
+    }
+    includes
+}
+
+// find the end of the word that starts at the given position
+fn word_end(content: &[u8], start: usize) -> usize {
+    content[start..]
+        .iter()
+        .position(u8::is_ascii_whitespace)
+        .map_or(content.len(), |len| start + len)
+}
+
+// find the closing quote of the string that starts at the given position.
+// Quotes inside of the string are escaped as \" or ""
+fn string_end(content: &[u8], start: usize) -> usize {
+    let mut pos = start + 1;
+    while pos < content.len() {
+        match content[pos] {
+            b'\\' => pos += 2,
+            b'"' if content.get(pos + 1) == Some(&b'"') => pos += 2,
+            b'"' => return pos,
+            _ => pos += 1,
+        }
+    }
+    content.len()
+}
+
+// find the file of an /include directive. Returns None if the file does not exist,
+// or if the name refers to an environment variable that is not set
+fn resolve_include(name: &str, base_dir: &Path, include_dirs: &[PathBuf]) -> Option<PathBuf> {
+    let expanded = PathBuf::from(expand_env_vars(name)?);
+    if expanded.is_absolute() {
+        return Some(expanded).filter(|path| path.exists());
+    }
+    let candidates = std::iter::once(base_dir.join(&expanded))
+        .chain(include_dirs.iter().map(|dir| dir.join(&expanded)))
+        .chain(std::iter::once(expanded.clone()));
+    candidates.into_iter().find(|path| path.is_file())
+}
+
+// expand environment variables written as $(NAME) or ${NAME}. Returns None if a variable is not set
+fn expand_env_vars(text: &str) -> Option<String> {
+    let var_regex =
+        Regex::new(r"\$(?:\(([A-Za-z_][A-Za-z0-9_]*)\)|\{([A-Za-z_][A-Za-z0-9_]*)\})").unwrap();
+    let mut result = Vec::with_capacity(text.len());
+    let mut last = 0;
+    for captures in var_regex.captures_iter(text.as_bytes()) {
+        let full_match = captures.get(0).unwrap();
+        let name = captures.get(1).or(captures.get(2)).unwrap();
+        let name = String::from_utf8_lossy(name.as_bytes());
+        let value = std::env::var(&*name).ok()?;
+        result.extend_from_slice(&text.as_bytes()[last..full_match.start()]);
+        result.extend_from_slice(value.as_bytes());
+        last = full_match.end();
+    }
+    result.extend_from_slice(&text.as_bytes()[last..]);
+    Some(String::from_utf8_lossy(&result).into_owned())
+}
+
+// remove "." and ".." from a path without accessing the file system
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Create a new, minimal a2l file, which contains a PROJECT with a single MODULE.
+///
+/// The new file uses a2l version 1.71.
+#[must_use]
+pub fn new_a2l() -> A2lFile {
+    // a minimal a2l file needs only a PROJECT containing a MODULE
+    let mut project = a2lfile::Project::new(
+        "new_project".to_string(),
+        "description of project".to_string(),
+    );
+    project.module = vec![a2lfile::Module::new(
+        "new_module".to_string(),
+        String::new(),
+    )];
+    let mut a2l_file = a2lfile::A2lFile::new(project);
+    // only one line break for PROJECT (after ASAP2_VERSION) instead of the default 2
+    a2l_file.project.get_layout_mut().start_offset = 1;
+    // only one line break for MODULE [0] instead of the default 2
+    a2l_file.project.module[0].get_layout_mut().start_offset = 1;
+    // also set ASAP2_VERSION 1.71
+    a2l_file.asap2_version = Some(a2lfile::Asap2Version::new(1, 71));
+    a2l_file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::A2lVersion;
+
+    #[test]
+    fn test_load_from_zip() {
+        let mut log_msgs = Vec::new();
+        let a2l_file = load_a2l(
+            OsStr::new("tests/test_delivery.zip"),
+            None,
+            true,
+            &mut log_msgs,
+        )
+        .unwrap();
+        // the included file was merged
+        assert_eq!(a2l_file.project.module[0].measurement.len(), 21);
+        assert!(a2l_file.project.module[0].measurement[20]
+            .get_layout()
+            .incfile
+            .is_none());
+
+        let a2l_file = load_a2l(
+            OsStr::new("tests/test_delivery.zip!dir/b.a2l"),
+            None,
+            true,
+            &mut log_msgs,
+        )
+        .unwrap();
+        assert_eq!(a2l_file.project.name, "delivery");
+        assert!(load_a2l(
+            OsStr::new("tests/test_delivery.zip!dir/missing.a2l"),
+            None,
+            true,
+            &mut log_msgs
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_load_a2l() {
+        let a2l_file = new_a2l();
+        assert_eq!(A2lVersion::from(&a2l_file), A2lVersion::V1_7_1);
+
+        let mut log_msgs = Vec::new();
+        let a2l_file = load_a2l(
+            OsStr::new("tests/update_test1.a2l"),
+            None,
+            false,
+            &mut log_msgs,
+        )
+        .unwrap();
+        assert_eq!(a2l_file.project.module.len(), 1);
+
+        assert!(load_a2l(OsStr::new("does_not_exist.a2l"), None, false, &mut log_msgs).is_err());
+    }
+
+    #[test]
+    fn test_load_with_include_dirs() {
+        let dir = std::env::temp_dir().join(format!("a2ltool_include_dirs_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("project/a2l")).unwrap();
+        std::fs::create_dir_all(dir.join("common")).unwrap();
+        let main_file = dir.join("project/a2l/main.a2l");
+        std::fs::write(
+            &main_file,
+            r#"ASAP2_VERSION 1 71
+            /begin PROJECT p ""
+              /begin MODULE m ""
+                /include "$(A2LTOOL_TEST_ROOT)/project/meas.a2l"
+                /include "shared.a2l"
+                // /include "$(A2LTOOL_TEST_UNSET_VAR)/old.a2l"
+              /end MODULE
+            /end PROJECT"#,
+        )
+        .unwrap();
+        // a relative include with .. inside of a file that was found through a variable
+        std::fs::write(dir.join("project/meas.a2l"), "/include \"../common/m2.a2l\"\n/begin MEASUREMENT m1 \"\" UBYTE NO_COMPU_METHOD 0 0 0 255 /end MEASUREMENT").unwrap();
+        std::fs::write(
+            dir.join("common/m2.a2l"),
+            "/begin MEASUREMENT m2 \"\" UBYTE NO_COMPU_METHOD 0 0 0 255 /end MEASUREMENT",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("common/shared.a2l"),
+            "/begin MEASUREMENT m3 \"\" UBYTE NO_COMPU_METHOD 0 0 0 255 /end MEASUREMENT",
+        )
+        .unwrap();
+
+        let mut log_msgs = Vec::new();
+        // the variable is not set
+        assert!(load_a2l(main_file.as_os_str(), None, true, &mut log_msgs).is_err());
+        std::env::set_var("A2LTOOL_TEST_ROOT", &dir);
+        // shared.a2l is only found in the include directory
+        assert!(load_a2l(main_file.as_os_str(), None, true, &mut log_msgs).is_err());
+        let result = load_a2l_with_include_dirs(
+            main_file.as_os_str(),
+            None,
+            &[dir.join("common")],
+            true,
+            &mut log_msgs,
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let a2l_file = result.unwrap();
+        assert_eq!(a2l_file.project.module[0].measurement.len(), 3);
+        // the include directives are unchanged
+        let text = a2l_file.write_to_string();
+        assert!(text.contains("/include \"$(A2LTOOL_TEST_ROOT)/project/meas.a2l\""));
+        assert!(text.contains("/include \"shared.a2l\""));
+    }
+
+    #[test]
+    fn test_load_parallel() {
+        let dir = std::env::temp_dir().join(format!("a2ltool_parallel_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut main_text = String::from("ASAP2_VERSION 1 71\n/begin PROJECT p \"\"\n");
+        for module_idx in 0..2 {
+            main_text.push_str(&format!("  /begin MODULE m{module_idx} \"\"\n"));
+            for file_idx in 0..10 {
+                let name = format!("inc_{module_idx}_{file_idx}.a2l");
+                let mut text = String::new();
+                for item_idx in 0..3 {
+                    text.push_str(&format!(
+                        "/begin MEASUREMENT m_{module_idx}_{file_idx}_{item_idx} \"\" UBYTE NO_COMPU_METHOD 0 0 0 255 /end MEASUREMENT\n"
+                    ));
+                }
+                std::fs::write(dir.join(&name), text).unwrap();
+                main_text.push_str(&format!(
+                    "    /begin MEASUREMENT main_{module_idx}_{file_idx} \"\" UBYTE NO_COMPU_METHOD 0 0 0 255 /end MEASUREMENT\n    /include \"{name}\"\n"
+                ));
+            }
+            main_text.push_str("  /end MODULE\n");
+        }
+        main_text.push_str("/end PROJECT\n");
+        let main_file = dir.join("main.a2l");
+        std::fs::write(&main_file, &main_text).unwrap();
+
+        // the result is the same for any number of threads
+        let tree = read_include_tree(&main_file, &[]);
+        let mut results = Vec::new();
+        for thread_count in [1, 2, 3, 8, 32] {
+            let mut log_msgs = Vec::new();
+            let a2l_file = load_parallel(
+                &tree,
+                ifdata::A2MLVECTOR_TEXT,
+                true,
+                &mut log_msgs,
+                thread_count,
+            )
+            .unwrap();
+            results.push((a2l_file.clone(), a2l_file.write_to_string()));
+        }
+        for result in &results[1..] {
+            assert_eq!(result, &results[0]);
+        }
+
+        // the objects have the same order and include files as if the parser had loaded the included files
+        let mut log_msgs = Vec::new();
+        let reference = a2lfile::load(&main_file, None, &mut log_msgs, true).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let object_order = |a2l_file: &A2lFile| {
+            let mut objects: Vec<_> = a2l_file
+                .project
+                .module
+                .iter()
+                .flat_map(|module| &module.measurement)
+                .map(|item| {
+                    let layout = item.get_layout();
+                    (layout.uid, item.name.clone(), layout.incfile.clone())
+                })
+                .collect();
+            objects.sort();
+            objects
+                .into_iter()
+                .map(|(_, name, incfile)| (name, incfile))
+                .collect::<Vec<_>>()
+        };
+        let (a2l_file, _) = &results[0];
+        assert_eq!(a2l_file.project.module[1].measurement.len(), 40);
+        assert_eq!(object_order(a2l_file), object_order(&reference));
+        assert_eq!(
+            a2l_file.project.module[0].measurement[10]
+                .get_layout()
+                .incfile,
+            Some("inc_0_0.a2l".to_string())
+        );
+
+        // included files are parsed by the parser if they include further files
+        let mut tree = tree;
+        tree[1]
+            .includes
+            .push(("nested.a2l".to_string(), dir.join("nested.a2l")));
+        assert!(load_parallel(&tree, ifdata::A2MLVECTOR_TEXT, true, &mut log_msgs, 2).is_none());
+    }
+
+    #[test]
+    fn test_find_includes() {
+        let content = br#"/include "a.a2l"
+            /* /include "b.a2l" */
+            // /include "c.a2l"
+            /begin MEASUREMENT m "text with \" /include d.a2l" UBYTE NO_COMPU_METHOD 0 0 0 255 /end MEASUREMENT
+            /include e.a2l
+            /include "f g.a2l""#;
+        assert_eq!(find_includes(content), vec!["a.a2l", "e.a2l", "f g.a2l"]);
+
+        let content = br#"/begin PROJECT p ""
+            /begin MODULE m1 "" /include "a.a2l" /end MODULE
+            /begin MODULE m2 ""
+                /begin MOD_COMMON "" /include "b.a2l" /end MOD_COMMON
+                /include c.a2l
+            /end MODULE
+        /end PROJECT"#;
+        let directives = include_directives(content);
+        let modules: Vec<_> = directives
+            .iter()
+            .map(|directive| directive.module)
+            .collect();
+        assert_eq!(modules, vec![Some(0), None, Some(1)]);
+        assert_eq!(&content[directives[2].range.clone()], b"/include c.a2l");
+    }
+
+    #[test]
+    fn test_expand_env_vars() {
+        std::env::set_var("A2LTOOL_TEST_VAR", "value");
+        assert_eq!(
+            expand_env_vars("$(A2LTOOL_TEST_VAR)/a/${A2LTOOL_TEST_VAR}.a2l").unwrap(),
+            "value/a/value.a2l"
+        );
+        assert_eq!(expand_env_vars("$a/b.a2l").unwrap(), "$a/b.a2l");
+        assert!(expand_env_vars("$(A2LTOOL_TEST_UNSET_VAR)/b.a2l").is_none());
+        assert_eq!(
+            normalize_path(Path::new("/tmp/a/./b/../c")),
+            PathBuf::from("/tmp/a/c")
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{LineWriter, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+// all messages are copied to the log file, if one was given with --logfile
+static LOG_FILE: Mutex<Option<LineWriter<File>>> = Mutex::new(None);
+// set by --quiet: nothing is printed on stdout
+static QUIET: AtomicBool = AtomicBool::new(false);
+// the timestamps in the log file are relative to this point in time
+static START: OnceLock<Instant> = OnceLock::new();
+
+// set up quiet mode and the log file. This must be called before any messages are printed
+pub(crate) fn init(logfile: Option<&OsStr>, quiet: bool) -> Result<(), String> {
+    QUIET.store(quiet, Ordering::Relaxed);
+    START.get_or_init(Instant::now);
+    if let Some(logfile) = logfile {
+        let file = File::create(logfile).map_err(|err| {
+            format!(
+                "Error: could not create log file \"{}\": {err}",
+                logfile.to_string_lossy()
+            )
+        })?;
+        let mut writer = LineWriter::new(file);
+        let _ = writeln!(
+            writer,
+            "a2ltool {} log, started {}",
+            env!("CARGO_PKG_VERSION"),
+            utc_timestamp(SystemTime::now())
+        );
+        let _ = writeln!(
+            writer,
+            "command line: {}",
+            std::env::args_os()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+        *LOG_FILE.lock().unwrap() = Some(writer);
+    }
+    Ok(())
+}
+
+pub(crate) fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+pub(crate) fn is_active() -> bool {
+    LOG_FILE.lock().unwrap().is_some()
+}
+
+// write a message to the log file. Each line is prefixed with the elapsed time
+pub(crate) fn log(msg: &str) {
+    if let Some(writer) = LOG_FILE.lock().unwrap().as_mut() {
+        let elapsed = START.get_or_init(Instant::now).elapsed().as_secs_f64() * 1000.0;
+        for line in msg.split('\n') {
+            if line.is_empty() {
+                let _ = writeln!(writer);
+            } else {
+                let _ = writeln!(writer, "[{elapsed:11.4}ms] {line}");
+            }
+        }
+    }
+}
+
+// write a message to the log file, and print it unless quiet mode is active
+pub(crate) fn output(msg: &str) {
+    log(msg);
+    if !is_quiet() {
+        println!("{msg}");
+    }
+}
+
+// format a point in time as "YYYY-MM-DD hh:mm:ss UTC"
+pub(crate) fn utc_timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let days = (secs / 86400) as i64;
+    let secs_of_day = secs % 86400;
+
+    // convert the number of days since 1970-01-01 to a civil date in the proleptic gregorian calendar
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        secs_of_day / 3600,
+        (secs_of_day / 60) % 60,
+        secs_of_day % 60
+    )
+}
+
+// compact UTC timestamp for file names, e.g. 20261016_093000
+pub(crate) fn file_timestamp(time: SystemTime) -> String {
+    let digits: String = utc_timestamp(time)
+        .chars()
+        .filter(char::is_ascii_digit)
+        .collect();
+    format!("{}_{}", &digits[..8], &digits[8..])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_utc_timestamp() {
+        assert_eq!(utc_timestamp(UNIX_EPOCH), "1970-01-01 00:00:00 UTC");
+        let time = UNIX_EPOCH + Duration::from_secs(951_782_400 + 3661);
+        assert_eq!(utc_timestamp(time), "2000-02-29 01:01:01 UTC");
+        let time = UNIX_EPOCH + Duration::from_secs(1_792_108_800);
+        assert_eq!(utc_timestamp(time), "2026-10-16 00:00:00 UTC");
+    }
+
+    #[test]
+    fn test_file_timestamp() {
+        let time = UNIX_EPOCH + Duration::from_secs(951_782_400 + 3661);
+        assert_eq!(file_timestamp(time), "20000229_010101");
+    }
+}
@@ -1,42 +1,47 @@
-use clap::{builder::ValueParser, parser::ValuesRef, Arg, ArgGroup, ArgMatches, Command};
+use clap::{builder::ValueParser, Arg, ArgGroup, ArgMatches, Command, ValueHint};
 
 use a2lfile::{A2lError, A2lFile, A2lObject};
-use dwarf::DebugData;
+use a2ltool::{
+    a2ml, address_extension, address_offset, alignment, anonymize, batch, build_id,
+    calibration_method, calvalues, ccp, check, checksum, codegen, comments, compu, convert, dcm,
+    decode_ifdata, display_identifier, epk, flatten, format, hexfile, insert, load, memmap,
+    memory_segments, merge, metadata, modernize, modpar, module_scope::ModuleScope, plugin, query,
+    remove_ifdata, rename, split, statistics, update, validate_ifdata, variant_coding, version,
+    virtual_measurement, xcp, zip, A2lVersion, CaseTieBreak, DebugData, DuplicateSymbolPolicy,
+};
 use std::{
     ffi::{OsStr, OsString},
-    fmt::Display,
+    io::Write,
+    process::ExitCode,
     time::Instant,
 };
 
-mod datatype;
-mod dwarf;
-mod ifdata;
-mod insert;
-mod symbol;
-mod update;
-mod version;
-mod xcp;
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-pub enum A2lVersion {
-    V1_5_0,
-    V1_5_1,
-    V1_6_0,
-    V1_6_1,
-    V1_7_0,
-    V1_7_1,
-}
+mod completions;
+mod logging;
+mod output;
+mod progress;
+mod server;
+mod shell;
+mod summary;
+mod watch;
 
+// print a message if verbose output is enabled. All messages are also written to the log file
 macro_rules! cond_print {
     ($verbose:ident, $now:ident, $formatexp:expr) => {
-        if $verbose == 1 {
-            println!("{}", $formatexp);
-        } else if $verbose >= 2 {
-            for line in $formatexp.split('\n') {
-                if line == "" {
-                    println!("");
-                } else {
-                    println!("[{:9.4}ms] {}", $now.elapsed().as_secs_f64() * 1000.0, line);
+        if $verbose > 0 || logging::is_active() {
+            let text: &str = &$formatexp;
+            logging::log(text);
+            if logging::is_quiet() {
+                // no output on stdout
+            } else if $verbose == 1 {
+                println!("{}", text);
+            } else if $verbose >= 2 {
+                for line in text.split('\n') {
+                    if line == "" {
+                        println!("");
+                    } else {
+                        println!("[{:9.4}ms] {}", $now.elapsed().as_secs_f64() * 1000.0, line);
+                    }
                 }
             }
         }
@@ -45,10 +50,14 @@ macro_rules! cond_print {
 
 macro_rules! ext_println {
     ($verbose:ident, $now:ident, $formatexp:expr) => {
-        if $verbose <= 1 {
-            println!("{}", $formatexp);
+        let text: &str = &$formatexp;
+        logging::log(text);
+        if logging::is_quiet() {
+            // no output on stdout
+        } else if $verbose <= 1 {
+            println!("{}", text);
         } else {
-            for line in $formatexp.split('\n') {
+            for line in text.split('\n') {
                 if line == "" {
                     println!("");
                 } else {
@@ -59,62 +68,331 @@ macro_rules! ext_println {
     };
 }
 
-fn main() {
+// exit codes, which allow scripts to distinguish the different kinds of failures.
+// Exit code 2 is used by clap if the command line is invalid
+const EXIT_ERROR: u8 = 1;
+const EXIT_LOAD_ERROR: u8 = 3;
+const EXIT_ELF_ERROR: u8 = 4;
+const EXIT_CHECK_FAILED: u8 = 5;
+const EXIT_UPDATE_FAILED: u8 = 6;
+const EXIT_IO_ERROR: u8 = 7;
+const EXIT_VERIFY_FAILED: u8 = 8;
+
+// an error message together with the exit code of the process
+#[derive(Debug)]
+struct RunError {
+    exit_code: u8,
+    message: String,
+}
+
+impl RunError {
+    fn new(exit_code: u8, message: impl Into<String>) -> Self {
+        Self {
+            exit_code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<String> for RunError {
+    fn from(message: String) -> Self {
+        Self::new(EXIT_ERROR, message)
+    }
+}
+
+impl std::fmt::Display for RunError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+fn main() -> ExitCode {
     match core() {
-        Ok(()) => {}
-        Err(err) => println!("{err}"),
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            logging::log(&err.message);
+            if logging::is_quiet() {
+                eprintln!("{err}");
+            } else {
+                println!("{err}");
+            }
+            ExitCode::from(err.exit_code)
+        }
     }
 }
 
 // Implement all the operations supported by a2ltool
-// They will always be performed in this order:
-//  1) load input
-//  2) additional consistency checks
-//  3) load elf
-//  4) merge at the module level
-//  5) merge at the project level
-//  6) merge includes (flatten)
-//  7) update addresses
-//  8) clean up ifdata
-//  9) sort the file
-// 10) output
-fn core() -> Result<(), String> {
-    let arg_matches = get_args();
+// They will always be performed in this order, see process_a2l():
+//  1) skip the run if the update cache shows that nothing has changed
+//  2) load input
+//  3) show information
+//  4) additional consistency checks
+//  5) flatten typedefs, convert the version and replace the A2ML
+//  6) create and edit XCP and CCP settings
+//  7) load elf
+//  8) merge at the module level, at the project level and merge includes
+//  9) update addresses and the build id
+// 10) insert new items from the elf file
+// 11) modernize, group and check the items based on the elf file
+// 12) edit the address offset, address map and address extensions
+// 13) create virtual measurements
+// 14) clean up unused items and ifdata
+// 15) convert, rename and annotate objects
+// 16) edit MOD_PAR, variant coding and versions
+// 17) run plugins
+// 18) verify the EPK and the checksums
+// 19) sort the file
+// 20) export other files
+// 21) anonymize
+// 22) output
+fn core() -> Result<(), RunError> {
+    let args = expand_response_files(std::env::args_os(), 0)?;
+    let arg_matches = get_args(args);
+
+    if let Some(("shell", shell_matches)) = arg_matches.subcommand() {
+        let input_filename = shell_matches
+            .get_one::<OsString>("INPUT")
+            .expect("the input file is required");
+        let strict = *shell_matches
+            .get_one::<bool>("STRICT")
+            .expect("option strict must always exist");
+        return Ok(shell::run_shell(input_filename, strict)?);
+    }
+    if let Some(("server", server_matches)) = arg_matches.subcommand() {
+        let port = *server_matches
+            .get_one::<u16>("PORT")
+            .expect("option port has a default value");
+        let strict = *server_matches
+            .get_one::<bool>("STRICT")
+            .expect("option strict must always exist");
+        let token_file = server_matches.get_one::<OsString>("TOKEN_FILE");
+        let output_dir = server_matches
+            .get_one::<OsString>("OUTPUT_DIR")
+            .expect("option output-dir has a default value");
+        return Ok(server::run_server(
+            port,
+            strict,
+            token_file.map(std::path::Path::new),
+            std::path::PathBuf::from(output_dir),
+        )?);
+    }
+    if let Some(("completions", completions_matches)) = arg_matches.subcommand() {
+        let shell = completions_matches
+            .get_one::<clap_complete::Shell>("SHELL")
+            .expect("the shell is required");
+        print!("{}", completions::generate(*shell, build_command()));
+        return Ok(());
+    }
+
+    let quiet = *arg_matches
+        .get_one::<bool>("QUIET")
+        .expect("option quiet must always exist");
+    logging::init(
+        arg_matches
+            .get_one::<OsString>("LOGFILE")
+            .map(OsString::as_os_str),
+        quiet,
+    )
+    .map_err(|err| RunError::new(EXIT_IO_ERROR, err))?;
+
+    let watch = *arg_matches
+        .get_one::<bool>("WATCH")
+        .expect("option watch must always exist");
+    if watch {
+        return run_watch(&arg_matches);
+    }
+
+    // the elf file is only loaded once, even if multiple a2l files are processed in batch mode
+    let mut elf_cache: Option<DebugData> = None;
+    let mut file_summaries = Vec::new();
+    let result = run_once(&arg_matches, &mut elf_cache, &mut file_summaries);
+
+    // the summary is also written if processing failed, so that the error is recorded
+    if let Some(summary_filename) = arg_matches.get_one::<OsString>("SUMMARY_JSON") {
+        summary::write_summary(summary_filename, &file_summaries)
+            .map_err(|err| RunError::new(EXIT_IO_ERROR, err))?;
+    }
+    result
+}
+
+// process the input file, or all input files in batch mode
+fn run_once(
+    arg_matches: &ArgMatches,
+    elf_cache: &mut Option<DebugData>,
+    file_summaries: &mut Vec<summary::FileSummary>,
+) -> Result<(), RunError> {
+    if let Some(patterns) = arg_matches.get_many::<String>("BATCH") {
+        run_batch(arg_matches, patterns, elf_cache, file_summaries)
+    } else {
+        let input_filename = arg_matches
+            .get_one::<OsString>("INPUT")
+            .map(OsString::as_os_str);
+        let output_filename = arg_matches
+            .get_one::<OsString>("OUTPUT")
+            .map(OsString::as_os_str);
+        let mut file_summary = summary::FileSummary::new(input_filename);
+        let result = process_a2l(
+            arg_matches,
+            input_filename,
+            output_filename,
+            elf_cache,
+            &mut file_summary,
+        );
+        file_summary.set_result(&result);
+        file_summaries.push(file_summary);
+        result
+    }
+}
+
+// run all operations, then wait until the elf file or one of the merge inputs changes and run them again.
+// Errors are reported, but they do not end the watch mode. It only ends when the process is terminated.
+fn run_watch(arg_matches: &ArgMatches) -> Result<(), RunError> {
+    let mut watched_files = Vec::new();
+    watched_files.extend(arg_matches.get_one::<OsString>("ELFFILE").cloned());
+    for id in ["MERGEMODULE", "MERGEPROJECT"] {
+        if let Some(files) = arg_matches.get_many::<OsString>(id) {
+            watched_files.extend(files.cloned());
+        }
+    }
+    let mut watcher = watch::FileWatcher::new(
+        watched_files
+            .into_iter()
+            .map(std::path::PathBuf::from)
+            .collect(),
+    );
+
+    loop {
+        // the elf file has to be loaded again in each run, since it has probably changed
+        let mut elf_cache: Option<DebugData> = None;
+        let mut file_summaries = Vec::new();
+        let result = run_once(arg_matches, &mut elf_cache, &mut file_summaries);
+        if let Some(summary_filename) = arg_matches.get_one::<OsString>("SUMMARY_JSON") {
+            summary::write_summary(summary_filename, &file_summaries)
+                .map_err(|err| RunError::new(EXIT_IO_ERROR, err))?;
+        }
+        let status = match result {
+            Ok(()) => "OK".to_string(),
+            Err(err) => format!("FAILED: {}", err.message.trim_end()),
+        };
+        logging::output(&format!(
+            "[{}] {status}\nWaiting for changes of the input files...",
+            logging::utc_timestamp(std::time::SystemTime::now())
+        ));
+
+        let changed = watcher.wait_for_change();
+        let names: Vec<String> = changed
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        logging::output(&format!("\nChanged: {}", names.join(", ")));
+    }
+}
+
+// process several a2l files independently, using the same options for each of them.
+// A failure only stops the processing of the current file; a summary is printed at the end
+fn run_batch<'a>(
+    arg_matches: &ArgMatches,
+    patterns: impl Iterator<Item = &'a String>,
+    elf_cache: &mut Option<DebugData>,
+    file_summaries: &mut Vec<summary::FileSummary>,
+) -> Result<(), RunError> {
+    let mut input_files = Vec::new();
+    for pattern in patterns {
+        input_files.extend(batch::expand_file_pattern(pattern)?);
+    }
+    if input_files.is_empty() {
+        return Err(RunError::new(
+            EXIT_LOAD_ERROR,
+            "Error: no input files match the --batch pattern",
+        ));
+    }
+    let output_pattern = arg_matches.get_one::<String>("OUTPUT_PATTERN");
+
+    let mut failed = 0;
+    let mut first_exit_code = None;
+    for input_file in &input_files {
+        let output_file = output_pattern.map(|pattern| batch::output_filename(pattern, input_file));
+        let mut file_summary = summary::FileSummary::new(Some(input_file.as_os_str()));
+        let result = process_a2l(
+            arg_matches,
+            Some(input_file.as_os_str()),
+            output_file.as_deref().map(std::path::Path::as_os_str),
+            elf_cache,
+            &mut file_summary,
+        );
+        file_summary.set_result(&result);
+        file_summaries.push(file_summary);
+        match (result, output_file) {
+            (Ok(()), Some(output_file)) => logging::output(&format!(
+                "OK      {} -> {}",
+                input_file.display(),
+                output_file.display()
+            )),
+            (Ok(()), None) => logging::output(&format!("OK      {}", input_file.display())),
+            (Err(err), _) => {
+                failed += 1;
+                first_exit_code.get_or_insert(err.exit_code);
+                logging::output(&format!(
+                    "FAILED  {}: {}",
+                    input_file.display(),
+                    err.message.trim_end()
+                ));
+            }
+        }
+    }
+
+    logging::output(&format!(
+        "\nBatch complete: {} files processed, {} succeeded, {failed} failed",
+        input_files.len(),
+        input_files.len() - failed
+    ));
+    // the exit code of the first failure is used for the whole batch
+    match first_exit_code {
+        Some(exit_code) => Err(RunError::new(
+            exit_code,
+            format!("Error: processing failed for {failed} files"),
+        )),
+        None => Ok(()),
+    }
+}
 
+// run all operations for a single input file, in the order that is described at core()
+fn process_a2l(
+    arg_matches: &ArgMatches,
+    input_filename: Option<&OsStr>,
+    output_filename: Option<&OsStr>,
+    elf_cache: &mut Option<DebugData>,
+    summary: &mut summary::FileSummary,
+) -> Result<(), RunError> {
     let strict = *arg_matches
         .get_one::<bool>("STRICT")
         .expect("option strict must always exist");
-    let check = *arg_matches
-        .get_one::<bool>("CHECK")
-        .expect("option check must always exist");
     let debugprint = *arg_matches
         .get_one::<bool>("DEBUGPRINT")
         .expect("option debugprint must always exist");
-    let show_xcp = *arg_matches
-        .get_one::<bool>("SHOW_XCP")
-        .expect("option show-xcp must always exist");
-    let update = *arg_matches
-        .get_one::<bool>("UPDATE")
-        .expect("option update must always exist");
-    let update_preserve = *arg_matches
-        .get_one::<bool>("SAFE_UPDATE")
-        .expect("option update-preserve must always exist");
     let enable_structures = *arg_matches
         .get_one::<bool>("ENABLE_STRUCTURES")
         .expect("option enable-structures must always exist");
     let cleanup = *arg_matches
         .get_one::<bool>("CLEANUP")
         .expect("option cleanup must always exist");
-    let ifdata_cleanup = *arg_matches
-        .get_one::<bool>("IFDATA_CLEANUP")
-        .expect("option ifdata-cleanup must always exist");
     let sort = *arg_matches
         .get_one::<bool>("SORT")
         .expect("option sort must always exist");
-    let merge_includes = *arg_matches
-        .get_one::<bool>("MERGEINCLUDES")
-        .expect("option merge-includes must always exist");
+    let strip_comments = *arg_matches
+        .get_one::<bool>("STRIP_COMMENTS")
+        .expect("option strip-comments must always exist");
+    let anonymize = *arg_matches
+        .get_one::<bool>("ANONYMIZE")
+        .expect("option anonymize must always exist");
+    let show_progress = *arg_matches
+        .get_one::<bool>("PROGRESS")
+        .expect("option progress must always exist");
     let verbose = arg_matches.get_count("VERBOSE");
+    let format_profile = arg_matches
+        .get_one::<OsString>("FORMAT")
+        .map(|name| format::FormatProfile::load(name))
+        .transpose()?;
 
     let now = Instant::now();
     cond_print!(
@@ -122,21 +400,456 @@ fn core() -> Result<(), String> {
         now,
         format!("\na2ltool {}\n", env!("CARGO_PKG_VERSION"))
     );
+    let progress = progress::Progress::new(show_progress);
+
+    // The update cache allows work to be skipped if the elf file, the command line and all other input files
+    // are unchanged since the last run:
+    // - if the input is the output of the last run, e.g. because the file is updated in place, the update is skipped
+    // - if the input is unchanged and the output of the last run has not been modified, the whole run is skipped
+    // - if only the elf file has changed, the objects whose symbols are unchanged are not updated again
+    // Files that are loaded from an archive are not cached
+    let update_cache_file = arg_matches.get_one::<OsString>("UPDATE_CACHE");
+    let mut update_cache_state = None;
+    let mut cache_status = update::cache::CacheStatus::default();
+    if let (Some(cache_file), Some(elffile), Some(input_path)) = (
+        update_cache_file,
+        arg_matches.get_one::<OsString>("ELFFILE"),
+        input_filename.filter(|path| zip::split_archive_path(path).is_none()),
+    ) {
+        let current = update::cache::UpdateCache::current(
+            input_path,
+            elffile,
+            other_input_files(arg_matches),
+            format!(
+                "a2ltool {} {:?}",
+                env!("CARGO_PKG_VERSION"),
+                std::env::args_os().skip(1).collect::<Vec<_>>()
+            ),
+        )?;
+        let output_hash =
+            output_filename.and_then(|out_filename| update::cache::hash_file(out_filename).ok());
+        cache_status = current.compare(update::cache::UpdateCache::load(cache_file), output_hash);
+        update_cache_state = Some(current);
+    }
+    if cache_status.up_to_date {
+        summary.add_operation("up_to_date", &[], &[]);
+        cond_print!(
+            verbose,
+            now,
+            "The input files are unchanged since the last run and the output is up to date, nothing needs to be done"
+        );
+        return Ok(());
+    }
+    // the elf file is still needed if it is used by other operations than the update
+    let elf_needed_otherwise = arg_matches.contains_id("INSERT_ARGGROUP")
+        || arg_matches.contains_id("INFO")
+        || arg_matches.get_flag("CHECK_ALIGNMENT")
+        || arg_matches.get_flag("CHECK_BYTE_ORDER")
+        || arg_matches.get_flag("GROUP_BY_SECTION")
+        || arg_matches.get_flag("CREATE_MEMORY_SEGMENTS");
+    let skip_update = cache_status.skip_update && !elf_needed_otherwise;
 
     // load input
-    let (input_filename, mut a2l_file) = load_or_create_a2l(&arg_matches, strict, verbose, now)?;
+    if let Some(input_filename) = input_filename {
+        progress.phase(&format!("Loading \"{}\"", input_filename.to_string_lossy()));
+    }
+    let input_path = input_filename;
+    // the parser discards comments, so they are collected from the input text to be written to the output again
+    let input_comments = input_path
+        .filter(|_| !strip_comments && !anonymize && output_filename.is_some())
+        .and_then(|path| std::fs::read(path).ok())
+        .map(|data| comments::Comments::collect(&String::from_utf8_lossy(&data)))
+        .filter(|input_comments| !input_comments.is_empty());
+    let (input_filename, mut a2l_file) =
+        load_or_create_a2l(arg_matches, input_filename, strict, verbose, now, summary)
+            .map_err(|err| RunError::new(EXIT_LOAD_ERROR, err))?;
+    progress.finish();
     if debugprint {
         // why not cond_print? in that case the output string must always be
         // formatted before cond_print can decide whether to print it. This can take longer than parsing the file.
         println!("================\n{a2l_file:#?}\n================\n");
     }
 
+    show_information(
+        arg_matches,
+        &a2l_file,
+        input_filename,
+        summary,
+        verbose,
+        now,
+    )?;
+
+    let mut check_problems = run_checks(
+        arg_matches,
+        &a2l_file,
+        input_filename,
+        summary,
+        verbose,
+        now,
+    )?;
+
+    convert_file(arg_matches, &mut a2l_file, summary, verbose, now)?;
+
+    // the XCP, update, insert and IF_DATA removal operations can be restricted to one module
+    let module_name = arg_matches.get_one::<String>("MODULE");
+    let mut module_scope = module_name
+        .map(|name| ModuleScope::select(&mut a2l_file, name))
+        .transpose()?;
+
+    check_problems += edit_xcp_ccp(arg_matches, &mut a2l_file, summary, verbose, now)?;
+
+    if let Some(scope) = module_scope.take() {
+        scope.restore(&mut a2l_file);
+    }
+
+    let current_version = A2lVersion::from(&a2l_file);
+    if enable_structures && current_version < A2lVersion::V1_7_1 {
+        return Err(format!("Error: The option --enable-structures requires input file version 1.7.1, but the current version is {current_version}").into());
+    }
+
+    if skip_update {
+        summary.add_operation("update_skipped", &[], &[]);
+        cond_print!(
+            verbose,
+            now,
+            "The a2l file and the elf file are unchanged since the last update, the update is skipped"
+        );
+    } else {
+        load_elf(arg_matches, elf_cache, &progress, summary, verbose, now)?;
+    }
+    if let Some(debugdata) = elf_cache.as_ref() {
+        summary.add_operation(
+            "load_elf",
+            &[("variables", debugdata.variable_count())],
+            &[],
+        );
+    }
+
+    // show everything about one object, including the symbol it refers to
+    if let Some(name) = arg_matches.get_one::<String>("INFO") {
+        ext_println!(verbose, now, query::object_details(&a2l_file, name)?);
+        if let Some(debugdata) = elf_cache.as_ref() {
+            let symbol_text =
+                query::symbol_details(&a2l_file, name, debugdata).unwrap_or_else(|errmsg| errmsg);
+            ext_println!(verbose, now, format!("\n{symbol_text}"));
+        }
+        summary.add_operation("info", &[], &[]);
+    }
+
+    merge_inputs(arg_matches, &mut a2l_file, strict, summary, verbose, now)?;
+
+    module_scope = module_name
+        .map(|name| ModuleScope::select(&mut a2l_file, name))
+        .transpose()?;
+    if let Some(debugdata) = elf_cache.as_ref() {
+        if !skip_update {
+            update_addresses(
+                arg_matches,
+                &mut a2l_file,
+                debugdata,
+                cache_status.object_hashes.as_ref(),
+                &progress,
+                summary,
+                verbose,
+                now,
+            )?;
+        }
+        handle_build_id(arg_matches, &mut a2l_file, summary, verbose, now)?;
+        insert_objects(arg_matches, &mut a2l_file, debugdata, summary, verbose, now)?;
+        check_problems +=
+            organize_by_elf(arg_matches, &mut a2l_file, debugdata, summary, verbose, now)?;
+    }
+    check_problems += edit_addresses(
+        arg_matches,
+        &mut a2l_file,
+        elf_cache.as_ref(),
+        summary,
+        verbose,
+        now,
+    )?;
+    if let Some(scope) = module_scope.take() {
+        scope.restore(&mut a2l_file);
+    }
+
+    create_virtual_measurements(arg_matches, &mut a2l_file, summary, verbose, now)?;
+
+    // clean up unreferenced items
+    if cleanup {
+        let count_before = count_objects(&a2l_file);
+        a2l_file.cleanup();
+        summary.add_operation(
+            "cleanup",
+            &[("objects_removed", count_before - count_objects(&a2l_file))],
+            &[],
+        );
+        cond_print!(
+            verbose,
+            now,
+            "Cleanup of unused items and empty groups is complete"
+        );
+    }
+
+    module_scope = module_name
+        .map(|name| ModuleScope::select(&mut a2l_file, name))
+        .transpose()?;
+    clean_up_ifdata(arg_matches, &mut a2l_file, summary, verbose, now);
+    if let Some(scope) = module_scope.take() {
+        scope.restore(&mut a2l_file);
+    }
+
+    edit_objects(arg_matches, &mut a2l_file, summary, verbose, now)?;
+
+    edit_mod_par(arg_matches, &mut a2l_file, summary, verbose, now)?;
+
+    check_problems += run_plugins(arg_matches, &mut a2l_file, summary, verbose, now)?;
+
+    verify_memory_image(arg_matches, &mut a2l_file, summary, verbose, now)?;
+
+    // sort all elements in the file
+    if sort || format_profile.as_ref().is_some_and(|profile| profile.sort) {
+        progress.phase("Sorting");
+        a2l_file.sort();
+        progress.finish();
+        summary.add_operation("sort", &[], &[]);
+        cond_print!(verbose, now, "All objects have been sorted");
+    }
+
+    export_files(arg_matches, &a2l_file, summary, verbose, now)?;
+
+    if anonymize {
+        anonymize_file(arg_matches, &mut a2l_file, summary, verbose, now)?;
+    }
+
+    // output
+    if let Some(out_filename) = output_filename {
+        // A run that found problems is not cached, so that they are reported again.
+        // The object hashes are calculated before the file is consumed by writing it
+        let update_cache = update_cache_file
+            .zip(update_cache_state)
+            .filter(|_| check_problems == 0)
+            .map(|(cache_file, state)| {
+                // if the update was skipped, then the objects are the same as in the last run
+                let object_hashes = match elf_cache.as_ref() {
+                    Some(debugdata) => update::cache::object_hashes(&a2l_file, debugdata),
+                    None => cache_status.object_hashes.unwrap_or_default(),
+                };
+                let cache = update::cache::UpdateCache {
+                    object_hashes,
+                    ..state
+                };
+                (cache_file, cache)
+            });
+
+        write_output(
+            arg_matches,
+            a2l_file,
+            input_path,
+            out_filename,
+            input_comments.as_ref(),
+            format_profile.as_ref(),
+            &progress,
+            summary,
+            verbose,
+            now,
+        )?;
+
+        // remember the state of the input and output files, so that the next run can be skipped if nothing changes
+        if let Some((cache_file, cache)) = update_cache {
+            let cache = update::cache::UpdateCache {
+                a2l_hash: update::cache::hash_file(out_filename)?,
+                ..cache
+            };
+            cache.save(cache_file)?;
+            summary.add_written_file(cache_file);
+        }
+        cond_print!(
+            verbose,
+            now,
+            format!("Output written to \"{}\"", out_filename.to_string_lossy())
+        );
+    }
+
+    cond_print!(verbose, now, "\nRun complete. Have a nice day!\n\n");
+
+    // problems found by the consistency check are reported after all other operations are complete
+    if check_problems > 0 {
+        return Err(RunError::new(
+            EXIT_CHECK_FAILED,
+            format!("Error: the consistency check reported {check_problems} problems"),
+        ));
+    }
+
+    Ok(())
+}
+
+// display information about the file; none of these operations modify it
+fn show_information(
+    arg_matches: &ArgMatches,
+    a2l_file: &A2lFile,
+    input_filename: &OsStr,
+    summary: &mut summary::FileSummary,
+    verbose: u8,
+    now: Instant,
+) -> Result<(), RunError> {
+    let show_xcp = *arg_matches
+        .get_one::<bool>("SHOW_XCP")
+        .expect("option show-xcp must always exist");
+    let show_ccp = *arg_matches
+        .get_one::<bool>("SHOW_CCP")
+        .expect("option show-ccp must always exist");
+    let show_variants = *arg_matches
+        .get_one::<bool>("SHOW_VARIANTS")
+        .expect("option show-variants must always exist");
+
     // show XCP settings
     if show_xcp {
-        xcp::show_settings(&a2l_file, input_filename);
+        let json = arg_matches
+            .get_one::<String>("SHOW_XCP_FORMAT")
+            .is_some_and(|format| format == "json");
+        xcp::show_settings(a2l_file, input_filename, json);
+    }
+
+    // show CCP settings
+    if show_ccp {
+        ccp::show_settings(a2l_file, input_filename);
+    }
+
+    // show the variant coding
+    if show_variants {
+        variant_coding::show_variants(a2l_file, input_filename);
+    }
+
+    // display the decoded IF_DATA blocks
+    if let Some(name) = arg_matches.get_one::<String>("DECODE_IFDATA") {
+        let name = Some(name.as_str()).filter(|name| !name.is_empty());
+        let text = decode_ifdata::decode_ifdata(a2l_file, name);
+        if text.is_empty() {
+            ext_println!(verbose, now, "No matching IF_DATA blocks found");
+        } else {
+            ext_println!(verbose, now, text.trim_end());
+        }
+        summary.add_operation("decode_ifdata", &[], &[]);
+    }
+
+    // search for objects by name
+    if let Some(regex) = arg_matches.get_one::<String>("SEARCH") {
+        let objects = query::search(a2l_file, regex)?;
+        for object in &objects {
+            ext_println!(verbose, now, object.to_string());
+        }
+        ext_println!(
+            verbose,
+            now,
+            format!("{} objects match \"{regex}\"", objects.len())
+        );
+        summary.add_operation("search", &[("matches", objects.len())], &[]);
+    }
+
+    // list all objects of one type
+    if let Some(list_type) = arg_matches.get_one::<String>("LIST") {
+        let regex = arg_matches.get_one::<String>("LIST_FILTER");
+        let objects = query::list(a2l_file, list_type, regex.map(String::as_str))?;
+        for object in &objects {
+            ext_println!(verbose, now, object.to_string());
+        }
+        ext_println!(
+            verbose,
+            now,
+            format!("{} {list_type} listed", objects.len())
+        );
+        summary.add_operation("list", &[("objects", objects.len())], &[]);
+    }
+
+    // list everything that is reachable from a group, including its sub groups
+    if let Some(group_name) = arg_matches.get_one::<String>("EXPAND_GROUP") {
+        let contents = query::expand_group(a2l_file, group_name)?;
+        if let Some(lab_filename) = arg_matches.get_one::<OsString>("EXPAND_GROUP_OUTPUT") {
+            std::fs::write(lab_filename, contents.to_lab()).map_err(|err| {
+                RunError::new(
+                    EXIT_IO_ERROR,
+                    format!(
+                        "Error: could not write the label list \"{}\": {err}",
+                        lab_filename.to_string_lossy()
+                    ),
+                )
+            })?;
+            summary.add_written_file(lab_filename);
+        } else {
+            for name in &contents.measurements {
+                ext_println!(verbose, now, format!("MEASUREMENT {name}"));
+            }
+            for name in &contents.characteristics {
+                ext_println!(verbose, now, format!("CHARACTERISTIC {name}"));
+            }
+        }
+        summary.add_operation(
+            "expand_group",
+            &[
+                ("measurements", contents.measurements.len()),
+                ("characteristics", contents.characteristics.len()),
+            ],
+            &[],
+        );
+        ext_println!(
+            verbose,
+            now,
+            format!(
+                "GROUP {group_name} contains {} measurements and {} characteristics",
+                contents.measurements.len(),
+                contents.characteristics.len()
+            )
+        );
+    }
+
+    // show statistics about the content of the file
+    if arg_matches.get_flag("STATISTICS") {
+        let include_dirs: Vec<std::path::PathBuf> = arg_matches
+            .get_many::<OsString>("INCLUDE_DIR")
+            .map(|dirs| dirs.map(std::path::PathBuf::from).collect())
+            .unwrap_or_default();
+        let stats = statistics::collect_statistics(a2l_file, Some(input_filename), &include_dirs);
+        ext_println!(verbose, now, stats.to_string());
+        summary.add_operation(
+            "statistics",
+            &[("calibration_bytes", stats.calibration_bytes as usize)],
+            &[],
+        );
+    }
+
+    // save the A2ML to a separate file
+    if let Some(a2ml_filename) = arg_matches.get_one::<OsString>("EXTRACT_A2ML") {
+        a2ml::extract_a2ml(a2l_file, a2ml_filename)
+            .map_err(|err| RunError::new(EXIT_IO_ERROR, err))?;
+        summary.add_operation("extract_a2ml", &[], &[]);
+        summary.add_written_file(a2ml_filename);
+        cond_print!(
+            verbose,
+            now,
+            format!("A2ML written to \"{}\"", a2ml_filename.to_string_lossy())
+        );
     }
 
+    Ok(())
+}
+
+// run the consistency checks and the IF_DATA validation. Returns the number of problems found
+fn run_checks(
+    arg_matches: &ArgMatches,
+    a2l_file: &A2lFile,
+    input_filename: &OsStr,
+    summary: &mut summary::FileSummary,
+    verbose: u8,
+    now: Instant,
+) -> Result<usize, RunError> {
+    let check = *arg_matches
+        .get_one::<bool>("CHECK")
+        .expect("option check must always exist");
+    let validate_ifdata = *arg_matches
+        .get_one::<bool>("VALIDATE_IFDATA")
+        .expect("option validate-ifdata must always exist");
+
     // additional consistency checks
+    let mut check_problems = 0;
     if check {
         cond_print!(
             verbose,
@@ -146,8 +859,18 @@ fn core() -> Result<(), String> {
                 input_filename.to_string_lossy()
             )
         );
+        let rules = arg_matches
+            .get_one::<OsString>("CHECK_RULES")
+            .map(|rules_filename| check::CheckRules::load(rules_filename))
+            .transpose()?
+            .unwrap_or_default();
+        let check_formula_inverse = *arg_matches
+            .get_one::<bool>("CHECK_FORMULA_INVERSE")
+            .expect("option check-formula-inverse must always exist");
         let mut log_msgs = Vec::<String>::new();
-        a2l_file.check(&mut log_msgs);
+        check::check_all(a2l_file, &rules, check_formula_inverse, &mut log_msgs);
+        summary.add_operation("check", &[("problems", log_msgs.len())], &log_msgs);
+        check_problems = log_msgs.len();
         if log_msgs.is_empty() {
             ext_println!(
                 verbose,
@@ -169,79 +892,422 @@ fn core() -> Result<(), String> {
         }
     }
 
-    // convert/downgrade the file to some version
-    if let Some(new_a2l_version) = arg_matches.get_one::<A2lVersion>("A2LVERSION") {
-        version::convert(&mut a2l_file, *new_a2l_version);
-    }
-
-    let current_version = A2lVersion::from(&a2l_file);
-    if enable_structures && current_version < A2lVersion::V1_7_1 {
-        return Err(format!("Error: The option --enable-structures requires input file version 1.7.1, but the current version is {current_version}"));
-    }
-
-    // load elf
-    let elf_info = if let Some(elffile) = arg_matches.get_one::<OsString>("ELFFILE") {
-        let elf_info = DebugData::load(elffile, verbose > 0)?;
-        cond_print!(
+    // strict validation of the IF_DATA blocks against the A2ML
+    if validate_ifdata {
+        let additional_a2ml = arg_matches
+            .get_one::<OsString>("A2ML_FILE")
+            .map(|a2ml_filename| a2ml::load_a2ml_file(a2ml_filename))
+            .transpose()?;
+        let mut log_msgs = Vec::<String>::new();
+        validate_ifdata::validate_ifdata(a2l_file, additional_a2ml.as_deref(), &mut log_msgs)?;
+        summary.add_operation(
+            "validate_ifdata",
+            &[("problems", log_msgs.len())],
+            &log_msgs,
+        );
+        check_problems += log_msgs.len();
+        for msg in &log_msgs {
+            ext_println!(verbose, now, format!("    {}", msg));
+        }
+        ext_println!(
             verbose,
             now,
             format!(
-                "Variables and types loaded from \"{}\": {} variables available",
-                elffile.to_string_lossy(),
-                elf_info.variables.len()
+                "IF_DATA validation complete. {} invalid IF_DATA blocks found.",
+                log_msgs.len()
             )
         );
-        if debugprint {
-            println!("================\n{elf_info:#?}\n================\n");
-        }
-        Some(elf_info)
-    } else {
-        None
-    };
+    }
+
+    Ok(check_problems)
+}
+
+// convert the file: expand the typedefs, change the version and replace or reformat the A2ML
+fn convert_file(
+    arg_matches: &ArgMatches,
+    a2l_file: &mut A2lFile,
+    summary: &mut summary::FileSummary,
+    verbose: u8,
+    now: Instant,
+) -> Result<(), RunError> {
+    // expand INSTANCEs into classic objects for tools that don't support TYPEDEF_*
+    if *arg_matches
+        .get_one::<bool>("FLATTEN_TYPEDEFS")
+        .expect("option flatten-typedefs must always exist")
+    {
+        let mut log_msgs = Vec::new();
+        let flatten_summary = flatten::flatten_typedefs(a2l_file, &mut log_msgs);
+        summary.add_operation(
+            "flatten_typedefs",
+            &[
+                ("instances_flattened", flatten_summary.instances_flattened),
+                ("objects_created", flatten_summary.objects_created),
+                ("typedefs_removed", flatten_summary.typedefs_removed),
+            ],
+            &log_msgs,
+        );
+        for msg in log_msgs {
+            cond_print!(verbose, now, msg);
+        }
+        cond_print!(
+            verbose,
+            now,
+            format!(
+                "Expanded {} INSTANCEs into {} objects and removed {} TYPEDEFs",
+                flatten_summary.instances_flattened,
+                flatten_summary.objects_created,
+                flatten_summary.typedefs_removed
+            )
+        );
+    }
+
+    // convert/downgrade the file to some version
+    if let Some(new_a2l_version) = arg_matches.get_one::<A2lVersion>("A2LVERSION") {
+        version::convert(a2l_file, *new_a2l_version);
+        summary.add_operation("convert_version", &[], &[]);
+    }
+
+    // replace the A2ML, e.g. with an updated vendor A2ML
+    if let Some(a2ml_filename) = arg_matches.get_one::<OsString>("REPLACE_A2ML") {
+        let a2ml_text = a2ml::load_a2ml_file(a2ml_filename)?;
+        a2ml::replace_a2ml(a2l_file, &a2ml_text)?;
+        summary.add_operation("replace_a2ml", &[], &[]);
+        cond_print!(
+            verbose,
+            now,
+            format!(
+                "A2ML replaced with the content of \"{}\"",
+                a2ml_filename.to_string_lossy()
+            )
+        );
+    }
+
+    // reformat the A2ML so that changes to it can be reviewed in a diff
+    if *arg_matches
+        .get_one::<bool>("FORMAT_A2ML")
+        .expect("option format-a2ml must always exist")
+    {
+        let sort = *arg_matches
+            .get_one::<bool>("SORT_A2ML")
+            .expect("option sort-a2ml must always exist");
+        let count = a2ml::format_module_a2ml(a2l_file, sort)?;
+        summary.add_operation("format_a2ml", &[("modules", count)], &[]);
+        cond_print!(verbose, now, format!("A2ML reformatted in {count} modules"));
+    }
+
+    Ok(())
+}
+
+// create and edit the XCP and CCP settings. Returns the number of problems found by the MAX_REFRESH check
+fn edit_xcp_ccp(
+    arg_matches: &ArgMatches,
+    a2l_file: &mut A2lFile,
+    summary: &mut summary::FileSummary,
+    verbose: u8,
+    now: Instant,
+) -> Result<usize, RunError> {
+    // create XCP settings
+    if let Some(transport) = arg_matches.get_one::<String>("CREATE_XCP") {
+        let mut template = xcp::XcpTemplate::new(transport.parse()?);
+        if let Some(config_filename) = arg_matches.get_one::<OsString>("XCP_CONFIG") {
+            template.load_config(config_filename)?;
+        }
+        if let Some(params) = arg_matches.get_many::<String>("XCP_PARAM") {
+            for param in params {
+                template.set_from_arg(param)?;
+            }
+        }
+        let mut log_msgs = Vec::<String>::new();
+        xcp::create_xcp(a2l_file, &template, &mut log_msgs);
+        summary.add_operation("create_xcp", &[], &log_msgs);
+        for msg in log_msgs {
+            cond_print!(verbose, now, msg);
+        }
+        cond_print!(verbose, now, "XCP settings have been created");
+    }
+
+    // edit the XCP settings
+    let can_id_master = arg_matches.get_one::<u64>("XCP_CAN_ID_MASTER");
+    let can_id_slave = arg_matches.get_one::<u64>("XCP_CAN_ID_SLAVE");
+    let can_baudrate = arg_matches.get_one::<u32>("XCP_CAN_BAUDRATE");
+    let can_sample_point = arg_matches.get_one::<u8>("XCP_CAN_SAMPLE_POINT");
+    if can_id_master.is_some()
+        || can_id_slave.is_some()
+        || can_baudrate.is_some()
+        || can_sample_point.is_some()
+    {
+        let settings = xcp::XcpOnCanSettings {
+            can_id_master: can_id_master.map(|id| *id as u32),
+            can_id_slave: can_id_slave.map(|id| *id as u32),
+            baudrate: can_baudrate.copied(),
+            sample_point: can_sample_point.copied(),
+        };
+        xcp::edit_xcp_on_can(a2l_file, &settings)?;
+        summary.add_operation("edit_xcp_on_can", &[], &[]);
+        cond_print!(verbose, now, "XCP on CAN settings have been updated");
+    }
+    let flx_fibex_file = arg_matches.get_one::<String>("XCP_FLX_FIBEX_FILE");
+    let flx_cluster_id = arg_matches.get_one::<String>("XCP_FLX_CLUSTER_ID");
+    let flx_nax = arg_matches.get_one::<u8>("XCP_FLX_NAX");
+    let flx_t1 = arg_matches.get_one::<u16>("XCP_FLX_T1");
+    if flx_fibex_file.is_some() || flx_cluster_id.is_some() || flx_nax.is_some() || flx_t1.is_some()
+    {
+        let settings = xcp::XcpOnFlxSettings {
+            fibex_file: flx_fibex_file.cloned(),
+            cluster_id: flx_cluster_id.cloned(),
+            nax: flx_nax.copied(),
+            t1: flx_t1.copied(),
+        };
+        xcp::edit_xcp_on_flx(a2l_file, &settings)?;
+        summary.add_operation("edit_xcp_on_flx", &[], &[]);
+        cond_print!(verbose, now, "XCP on FlexRay settings have been updated");
+    }
+    let daq_event_edits = xcp::DaqEventEdit::from_pairs(
+        &string_values(arg_matches, "XCP_ADD_EVENT"),
+        &string_values(arg_matches, "XCP_RENAME_EVENT"),
+        &string_values(arg_matches, "XCP_RETIME_EVENT"),
+    )?;
+    if let Some(config_filename) = arg_matches.get_one::<OsString>("XCP_EVENT_CONFIG") {
+        let definitions = xcp::load_daq_event_config(config_filename)?;
+        let mut log_msgs = Vec::<String>::new();
+        let changed = xcp::sync_daq_events(a2l_file, &definitions, &mut log_msgs);
+        summary.add_operation(
+            "sync_daq_events",
+            &[("events", definitions.len()), ("changed", changed)],
+            &log_msgs,
+        );
+        for msg in log_msgs {
+            cond_print!(verbose, now, msg);
+        }
+        cond_print!(
+            verbose,
+            now,
+            format!(
+                "XCP DAQ events have been synchronized with the config, {changed} events were changed"
+            )
+        );
+    }
+    if !daq_event_edits.is_empty() {
+        let mut log_msgs = Vec::<String>::new();
+        xcp::edit_daq_events(a2l_file, &daq_event_edits, &mut log_msgs);
+        summary.add_operation(
+            "edit_daq_events",
+            &[("edits", daq_event_edits.len())],
+            &log_msgs,
+        );
+        for msg in log_msgs {
+            cond_print!(verbose, now, msg);
+        }
+        cond_print!(verbose, now, "XCP DAQ events have been updated");
+    }
+    if let Some(params) = arg_matches.get_many::<String>("XCP_PROTOCOL_PARAM") {
+        let params: Vec<&str> = params.map(|param| &**param).collect();
+        let mut log_msgs = Vec::<String>::new();
+        xcp::edit_protocol_layer(a2l_file, &params, &mut log_msgs)?;
+        summary.add_operation(
+            "edit_protocol_layer",
+            &[("parameters", params.len())],
+            &log_msgs,
+        );
+        for msg in log_msgs {
+            cond_print!(verbose, now, msg);
+        }
+        cond_print!(
+            verbose,
+            now,
+            "XCP protocol layer settings have been updated"
+        );
+    }
+    let seed_key_dll = arg_matches.get_one::<String>("SET_SEED_KEY");
+    let clear_seed_key = *arg_matches
+        .get_one::<bool>("CLEAR_SEED_KEY")
+        .expect("option clear-seed-key must always exist");
+    if seed_key_dll.is_some() || clear_seed_key {
+        let transport = arg_matches
+            .get_one::<String>("SEED_KEY_TRANSPORT")
+            .map(|transport| transport.parse::<xcp::XcpTransport>())
+            .transpose()?;
+        let seed_key_dll = seed_key_dll.map(|dll| &**dll);
+        let mut log_msgs = Vec::<String>::new();
+        xcp::edit_seed_key(a2l_file, seed_key_dll, transport, &mut log_msgs);
+        if transport.is_none() {
+            ccp::edit_seed_key(a2l_file, seed_key_dll);
+        }
+        summary.add_operation("edit_seed_key", &[], &log_msgs);
+        for msg in log_msgs {
+            cond_print!(verbose, now, msg);
+        }
+        cond_print!(verbose, now, "Seed & Key settings have been updated");
+    }
+    let ccp_can_id_send = arg_matches.get_one::<u64>("CCP_CAN_ID_SEND");
+    let ccp_can_id_receive = arg_matches.get_one::<u64>("CCP_CAN_ID_RECEIVE");
+    let ccp_station_address = arg_matches.get_one::<u16>("CCP_STATION_ADDRESS");
+    if ccp_can_id_send.is_some() || ccp_can_id_receive.is_some() || ccp_station_address.is_some() {
+        let settings = ccp::CcpSettings {
+            can_id_send: ccp_can_id_send.map(|id| *id as u32),
+            can_id_receive: ccp_can_id_receive.map(|id| *id as u32),
+            station_address: ccp_station_address.copied(),
+        };
+        ccp::edit_ccp(a2l_file, &settings);
+        summary.add_operation("edit_ccp", &[], &[]);
+        cond_print!(verbose, now, "CCP settings have been updated");
+    }
+    let eth_protocol = arg_matches.get_one::<String>("XCP_ETH_PROTOCOL");
+    let eth_address = arg_matches.get_one::<String>("XCP_ETH_ADDRESS");
+    let eth_port = arg_matches.get_one::<u16>("XCP_ETH_PORT");
+    if eth_protocol.is_some() || eth_address.is_some() || eth_port.is_some() {
+        let settings = xcp::XcpOnEthSettings {
+            protocol: eth_protocol
+                .map(|protocol| protocol.parse::<xcp::XcpEthProtocol>())
+                .transpose()?,
+            address: eth_address.cloned(),
+            port: eth_port.copied(),
+        };
+        xcp::edit_xcp_on_eth(a2l_file, &settings)?;
+        summary.add_operation("edit_xcp_on_eth", &[], &[]);
+        cond_print!(verbose, now, "XCP on Ethernet settings have been updated");
+    }
+
+    // compare MAX_REFRESH with the (possibly edited) DAQ events
+    let mut check_problems = 0;
+    let check_max_refresh = *arg_matches
+        .get_one::<bool>("CHECK_MAX_REFRESH")
+        .expect("option check-max-refresh must always exist");
+    let fix_max_refresh = *arg_matches
+        .get_one::<bool>("FIX_MAX_REFRESH")
+        .expect("option fix-max-refresh must always exist");
+    if check_max_refresh || fix_max_refresh {
+        let mut log_msgs = Vec::new();
+        let result = xcp::check_max_refresh(a2l_file, fix_max_refresh, &mut log_msgs);
+        let problems = result.mismatched - result.fixed;
+        summary.add_operation(
+            "check_max_refresh",
+            &[("problems", problems), ("fixed", result.fixed)],
+            &log_msgs,
+        );
+        check_problems += problems;
+        for msg in &log_msgs {
+            ext_println!(verbose, now, format!("    {}", msg));
+        }
+        ext_println!(
+            verbose,
+            now,
+            format!(
+                "MAX_REFRESH check complete. {} objects do not match a DAQ event, {} were fixed.",
+                result.mismatched, result.fixed
+            )
+        );
+    }
+
+    Ok(check_problems)
+}
+
+// load the elf file, unless it is already loaded. In batch mode all files are processed with the same elf file
+fn load_elf(
+    arg_matches: &ArgMatches,
+    elf_cache: &mut Option<DebugData>,
+    progress: &progress::Progress,
+    summary: &mut summary::FileSummary,
+    verbose: u8,
+    now: Instant,
+) -> Result<(), RunError> {
+    let symbol_case_tie_break = arg_matches
+        .get_one::<CaseTieBreak>("SYMBOL_CASE_INSENSITIVE")
+        .copied();
+    let duplicate_symbol_policy = arg_matches
+        .get_one::<DuplicateSymbolPolicy>("DUPLICATE_SYMBOL_POLICY")
+        .copied()
+        .unwrap_or_default();
+    let ignore_leading_underscore = *arg_matches
+        .get_one::<bool>("IGNORE_LEADING_UNDERSCORE")
+        .expect("option ignore-leading-underscore must always exist");
+    let debugprint = *arg_matches
+        .get_one::<bool>("DEBUGPRINT")
+        .expect("option debugprint must always exist");
+
+    if let (None, Some(elffile)) = (&elf_cache, arg_matches.get_one::<OsString>("ELFFILE")) {
+        progress.phase(&format!("Loading \"{}\"", elffile.to_string_lossy()));
+        let mut elf_info = DebugData::load(elffile, verbose > 0)
+            .map_err(|err| RunError::new(EXIT_ELF_ERROR, err))?;
+        progress.finish();
+        cond_print!(
+            verbose,
+            now,
+            format!(
+                "Variables and types loaded from \"{}\": {} variables available",
+                elffile.to_string_lossy(),
+                elf_info.variable_count()
+            )
+        );
+        elf_info.set_ignore_leading_underscore(ignore_leading_underscore);
+        elf_info.set_duplicate_symbol_policy(duplicate_symbol_policy);
+        if let Some(tie_break) = symbol_case_tie_break {
+            let mut log_msgs = Vec::new();
+            elf_info.set_case_insensitive(tie_break, &mut log_msgs);
+            summary.add_operation(
+                "symbol_case_insensitive",
+                &[("ambiguous", log_msgs.len())],
+                &log_msgs,
+            );
+            for msg in log_msgs {
+                cond_print!(verbose, now, msg);
+            }
+        }
+        if debugprint {
+            println!("================\n{elf_info:#?}\n================\n");
+        }
+        *elf_cache = Some(elf_info);
+    }
+    Ok(())
+}
+
+// merge other files at the module and at the project level, and merge the include files
+fn merge_inputs(
+    arg_matches: &ArgMatches,
+    a2l_file: &mut A2lFile,
+    strict: bool,
+    summary: &mut summary::FileSummary,
+    verbose: u8,
+    now: Instant,
+) -> Result<(), RunError> {
+    let merge_includes = *arg_matches
+        .get_one::<bool>("MERGEINCLUDES")
+        .expect("option merge-includes must always exist");
 
     // merge at the module level
     if let Some(merge_modules) = arg_matches.get_many::<OsString>("MERGEMODULE") {
         for mergemodule in merge_modules {
-            let mut merge_log_msgs = Vec::<A2lError>::new();
-            let mergeresult = a2lfile::load(mergemodule, None, &mut merge_log_msgs, strict);
-            if let Ok(mut merge_a2l) = mergeresult {
-                a2l_file.merge_modules(&mut merge_a2l);
-                cond_print!(
-                    verbose,
-                    now,
-                    format!(
-                        "Merged A2l objects from \"{}\"\n",
-                        mergemodule.to_string_lossy()
-                    )
-                );
-            } else if let Ok(mut other_module) = a2lfile::load_fragment_file(mergemodule) {
-                a2l_file.project.module[0].merge(&mut other_module);
-                cond_print!(
-                    verbose,
-                    now,
-                    format!(
-                        "Merged A2l objects from \"{}\"\n",
-                        mergemodule.to_string_lossy()
-                    )
-                );
-            } else {
-                return Err(format!(
-                    "Failed to load \"{}\" for merging: {}\n",
-                    mergemodule.to_string_lossy(),
-                    mergeresult.unwrap_err()
-                ));
-            }
+            let count_before = count_objects(a2l_file);
+            merge::merge_module_file(a2l_file, mergemodule, strict)?;
+            summary.add_operation(
+                "merge",
+                &[("objects_added", count_objects(a2l_file) - count_before)],
+                &[],
+            );
+            cond_print!(
+                verbose,
+                now,
+                format!(
+                    "Merged A2l objects from \"{}\"\n",
+                    mergemodule.to_string_lossy()
+                )
+            );
         }
     }
 
     // merge at the project level
     if let Some(merge_projects) = arg_matches.get_many::<OsString>("MERGEPROJECT") {
         for mergeproject in merge_projects {
-            let mut merge_log_msgs = Vec::<A2lError>::new();
-            let merge_a2l = a2lfile::load(mergeproject, None, &mut merge_log_msgs, strict)
-                .map_err(|a2lerr| a2lerr.to_string())?;
-
-            a2l_file.project.module.extend(merge_a2l.project.module);
+            let modules_before = a2l_file.project.module.len();
+            merge::merge_project_file(a2l_file, mergeproject, strict)?;
+            summary.add_operation(
+                "merge_project",
+                &[(
+                    "modules_added",
+                    a2l_file.project.module.len() - modules_before,
+                )],
+                &[],
+            );
             cond_print!(
                 verbose,
                 now,
@@ -257,408 +1323,2188 @@ fn core() -> Result<(), String> {
     // merge includes
     if merge_includes {
         a2l_file.merge_includes();
+        summary.add_operation("merge_includes", &[], &[]);
         cond_print!(verbose, now, "Include directives have been merged\n");
     }
 
-    if let Some(debugdata) = &elf_info {
-        // update addresses
-        if update || update_preserve {
-            let mut log_msgs = Vec::<String>::new();
-            let summary = update::update_addresses(
-                &mut a2l_file,
-                debugdata,
-                &mut log_msgs,
-                update_preserve,
-                enable_structures,
-            );
+    Ok(())
+}
 
-            for msg in log_msgs {
-                cond_print!(verbose, now, msg);
-            }
+// update the addresses of all objects and of the variants of VAR_CHARACTERISTICs
+#[allow(clippy::too_many_arguments)]
+fn update_addresses(
+    arg_matches: &ArgMatches,
+    a2l_file: &mut A2lFile,
+    debugdata: &DebugData,
+    cached_object_hashes: Option<&update::cache::ObjectHashes>,
+    progress: &progress::Progress,
+    summary: &mut summary::FileSummary,
+    verbose: u8,
+    now: Instant,
+) -> Result<(), RunError> {
+    let update = *arg_matches
+        .get_one::<bool>("UPDATE")
+        .expect("option update must always exist");
+    let update_preserve = *arg_matches
+        .get_one::<bool>("SAFE_UPDATE")
+        .expect("option update-preserve must always exist");
+    let enable_structures = *arg_matches
+        .get_one::<bool>("ENABLE_STRUCTURES")
+        .expect("option enable-structures must always exist");
+    let pointer_policy = arg_matches
+        .get_one::<update::InstancePointerPolicy>("INSTANCE_POINTERS")
+        .copied()
+        .unwrap_or_default();
 
-            cond_print!(verbose, now, "Address update done\nSummary:");
-            cond_print!(
-                verbose,
-                now,
-                format!(
-                    "   characteristic: {} updated, {} not found",
-                    summary.characteristic_updated, summary.characteristic_not_updated
-                )
-            );
-            cond_print!(
-                verbose,
-                now,
-                format!(
-                    "   measurement: {} updated, {} not found",
-                    summary.measurement_updated, summary.measurement_not_updated
-                )
-            );
-            cond_print!(
-                verbose,
-                now,
-                format!(
-                    "   axis_pts: {} updated, {} not found",
-                    summary.axis_pts_updated, summary.axis_pts_not_updated
-                )
-            );
-            cond_print!(
-                verbose,
-                now,
-                format!(
-                    "   blob: {} updated, {} not found",
-                    summary.blob_updated, summary.blob_not_updated
-                )
+    // update addresses
+    if update || update_preserve {
+        let mut log_msgs = Vec::<String>::new();
+        let address_delta_warn = arg_matches.get_one::<u64>("ADDRESS_DELTA_WARN").copied();
+        let address_snapshot = address_delta_warn.map(|_| update::AddressSnapshot::new(a2l_file));
+        let unchanged = cached_object_hashes
+            .map(|cached| {
+                let current = update::cache::object_hashes(a2l_file, debugdata);
+                update::cache::unchanged_objects(cached, &current)
+            })
+            .unwrap_or_default();
+        let update_summary = update::update_addresses_incremental(
+            a2l_file,
+            debugdata,
+            &mut log_msgs,
+            update_preserve,
+            enable_structures,
+            pointer_policy,
+            &unchanged,
+            &mut |step| progress.phase(step),
+        );
+        progress.finish();
+        summary.add_operation("update", &update_summary.counts(), &log_msgs);
+
+        for msg in log_msgs {
+            cond_print!(verbose, now, msg);
+        }
+
+        cond_print!(verbose, now, "Address update done\nSummary:");
+        cond_print!(verbose, now, update_summary.to_string());
+
+        // objects whose address changed a lot were probably matched to the wrong variable
+        if let (Some(threshold), Some(snapshot)) = (address_delta_warn, &address_snapshot) {
+            let jump_msgs = snapshot.large_changes(a2l_file, threshold);
+            summary.add_operation(
+                "address_delta_warn",
+                &[("address_jumps", jump_msgs.len())],
+                &jump_msgs,
             );
             cond_print!(
                 verbose,
                 now,
                 format!(
-                    "   instance: {} updated, {} not found",
-                    summary.instance_updated, summary.instance_not_updated
+                    "   address changed by more than {threshold} bytes: {}",
+                    jump_msgs.len()
                 )
             );
-        }
-
-        // create new items
-        if arg_matches.contains_id("INSERT_CHARACTERISTIC")
-            || arg_matches.contains_id("INSERT_MEASUREMENT")
-        {
-            let target_group = arg_matches
-                .get_one::<String>("TARGET_GROUP")
-                .map(|group| &**group);
-
-            let measurement_symbols: Vec<&str> =
-                if let Some(values) = arg_matches.get_many::<String>("INSERT_MEASUREMENT") {
-                    values.into_iter().map(|x| &**x).collect()
-                } else {
-                    Vec::new()
-                };
-            let characteristic_symbols: Vec<&str> =
-                if let Some(values) = arg_matches.get_many::<String>("INSERT_CHARACTERISTIC") {
-                    values.into_iter().map(|x| &**x).collect()
-                } else {
-                    Vec::new()
-                };
-
-            let mut log_msgs: Vec<String> = Vec::new();
-            insert::insert_items(
-                &mut a2l_file,
-                debugdata,
-                measurement_symbols,
-                characteristic_symbols,
-                target_group,
-                &mut log_msgs,
-                enable_structures,
-            );
-            for msg in log_msgs {
+            for msg in jump_msgs {
                 cond_print!(verbose, now, msg);
             }
         }
 
-        if arg_matches.contains_id("INSERT_CHARACTERISTIC_RANGE")
-            || arg_matches.contains_id("INSERT_MEASUREMENT_RANGE")
-            || arg_matches.contains_id("INSERT_CHARACTERISTIC_REGEX")
-            || arg_matches.contains_id("INSERT_MEASUREMENT_REGEX")
-            || arg_matches.contains_id("INSERT_CHARACTERISTIC_SECTION")
-            || arg_matches.contains_id("INSERT_MEASUREMENT_SECTION")
-        {
-            cond_print!(
-                verbose,
-                now,
-                "Inserting new items from range, regex, or section"
-            );
-            let target_group = arg_matches
-                .get_one::<String>("TARGET_GROUP")
-                .map(|group| &**group);
-
-            let mut meas_ranges =
-                range_args_to_ranges(arg_matches.get_many::<u64>("INSERT_MEASUREMENT_RANGE"));
-            let mut char_ranges =
-                range_args_to_ranges(arg_matches.get_many::<u64>("INSERT_CHARACTERISTIC_RANGE"));
-
-            let mut meas_section_ranges = section_args_to_ranges(
-                arg_matches.get_many::<String>("INSERT_MEASUREMENT_SECTION"),
-                debugdata,
-                verbose,
-            );
-            let mut char_section_ranges = section_args_to_ranges(
-                arg_matches.get_many::<String>("INSERT_CHARACTERISTIC_SECTION"),
-                debugdata,
-                verbose,
-            );
-            meas_ranges.append(&mut meas_section_ranges);
-            char_ranges.append(&mut char_section_ranges);
-
-            let meas_regexes: Vec<&str> =
-                match arg_matches.get_many::<String>("INSERT_MEASUREMENT_REGEX") {
-                    Some(values) => values.map(|x| &**x).collect(),
-                    None => Vec::new(),
-                };
-            let char_regexes: Vec<&str> =
-                match arg_matches.get_many::<String>("INSERT_CHARACTERISTIC_REGEX") {
-                    Some(values) => values.map(|x| &**x).collect(),
-                    None => Vec::new(),
-                };
-
-            let mut log_msgs: Vec<String> = Vec::new();
-            insert::insert_many(
-                &mut a2l_file,
-                debugdata,
-                &meas_ranges,
-                &char_ranges,
-                meas_regexes,
-                char_regexes,
-                target_group,
-                &mut log_msgs,
-                enable_structures,
-            );
-            for msg in log_msgs {
-                cond_print!(verbose, now, msg);
+        // optionally treat the update as failed if too many objects could not be found
+        if let Some(max_failures) = arg_matches.get_one::<u32>("MAX_UPDATE_FAILURES") {
+            let failures = update_summary.failures();
+            if failures > *max_failures {
+                return Err(RunError::new(
+                    EXIT_UPDATE_FAILED,
+                    format!("Error: {failures} objects could not be updated, but at most {max_failures} are allowed by --max-update-failures"),
+                ));
             }
         }
     }
 
-    // clean up unreferenced items
-    if cleanup {
-        a2l_file.cleanup();
+    // update the addresses of the variants of VAR_CHARACTERISTICs
+    if let Some(suffix) = arg_matches.get_one::<String>("UPDATE_VAR_ADDRESS") {
+        let mut log_msgs = Vec::<String>::new();
+        let count =
+            variant_coding::update_var_addresses(a2l_file, debugdata, suffix, &mut log_msgs);
+        summary.add_operation(
+            "update_var_address",
+            &[("var_characteristics", count)],
+            &log_msgs,
+        );
+        for msg in log_msgs {
+            cond_print!(verbose, now, msg);
+        }
         cond_print!(
             verbose,
             now,
-            "Cleanup of unused items and empty groups is complete"
+            format!("Updated the VAR_ADDRESS of {count} variant characteristics")
         );
     }
 
-    // remove unknown IF_DATA
-    if ifdata_cleanup {
-        a2l_file.ifdata_cleanup();
-        cond_print!(verbose, now, "Unknown ifdata removal is done");
-    }
-
-    // sort all elements in the file
-    if sort {
-        a2l_file.sort();
-        cond_print!(verbose, now, "All objects have been sorted");
-    }
+    Ok(())
+}
 
-    // output
-    if arg_matches.contains_id("OUTPUT") {
-        a2l_file.sort_new_items();
-        if let Some(out_filename) = arg_matches.get_one::<OsString>("OUTPUT") {
-            let banner = &*format!("a2ltool {}", env!("CARGO_PKG_VERSION"));
-            a2l_file
-                .write(out_filename, Some(banner))
-                .map_err(|err| err.to_string())?;
-            cond_print!(
-                verbose,
-                now,
-                format!("Output written to \"{}\"", out_filename.to_string_lossy())
-            );
+// record which build the addresses were taken from, or check that they still belong to it
+fn handle_build_id(
+    arg_matches: &ArgMatches,
+    a2l_file: &mut A2lFile,
+    summary: &mut summary::FileSummary,
+    verbose: u8,
+    now: Instant,
+) -> Result<(), RunError> {
+    let store_build_id = *arg_matches
+        .get_one::<bool>("STORE_BUILD_ID")
+        .expect("option store-build-id must always exist");
+    let verify_build_id = *arg_matches
+        .get_one::<bool>("VERIFY_BUILD_ID")
+        .expect("option verify-build-id must always exist");
+    if let (true, Some(elffile)) = (
+        store_build_id || verify_build_id,
+        arg_matches.get_one::<OsString>("ELFFILE"),
+    ) {
+        let symbol_name = arg_matches.get_one::<String>("BUILD_ID_SYMBOL");
+        let build_id = build_id::read_build_id(elffile, symbol_name.map(String::as_str))
+            .map_err(|err| RunError::new(EXIT_ELF_ERROR, err))?;
+        if verify_build_id {
+            build_id::verify_build_id(a2l_file, &build_id)
+                .map_err(|err| RunError::new(EXIT_VERIFY_FAILED, err))?;
+            summary.add_operation("verify_build_id", &[], &[]);
+            cond_print!(verbose, now, format!("The build-id {build_id} matches"));
+        } else {
+            build_id::store_build_id(a2l_file, &build_id);
+            summary.add_operation("store_build_id", &[], &[]);
+            cond_print!(verbose, now, format!("Stored the build-id {build_id}"));
         }
     }
+    Ok(())
+}
 
-    cond_print!(verbose, now, "\nRun complete. Have a nice day!\n\n");
+// create new items for symbols, address ranges, regexes and elf sections
+fn insert_objects(
+    arg_matches: &ArgMatches,
+    a2l_file: &mut A2lFile,
+    debugdata: &DebugData,
+    summary: &mut summary::FileSummary,
+    verbose: u8,
+    now: Instant,
+) -> Result<(), RunError> {
+    let enable_structures = *arg_matches
+        .get_one::<bool>("ENABLE_STRUCTURES")
+        .expect("option enable-structures must always exist");
+    let group_by = arg_matches
+        .get_one::<String>("GROUP_BY")
+        .map(|group_by| group_by.parse::<insert::GroupBy>())
+        .transpose()?;
+    let target_group = arg_matches
+        .get_one::<String>("TARGET_GROUP")
+        .map(String::as_str);
+
+    if arg_matches.contains_id("INSERT_CHARACTERISTIC")
+        || arg_matches.contains_id("INSERT_MEASUREMENT")
+    {
+        let mut log_msgs: Vec<String> = Vec::new();
+        let count_before = count_objects(a2l_file);
+        insert::insert_items(
+            a2l_file,
+            debugdata,
+            string_values(arg_matches, "INSERT_MEASUREMENT"),
+            string_values(arg_matches, "INSERT_CHARACTERISTIC"),
+            target_group,
+            group_by,
+            &mut log_msgs,
+            enable_structures,
+        );
+        summary.add_operation(
+            "insert",
+            &[("objects_inserted", count_objects(a2l_file) - count_before)],
+            &log_msgs,
+        );
+        for msg in log_msgs {
+            cond_print!(verbose, now, msg);
+        }
+    }
+
+    if arg_matches.contains_id("INSERT_CHARACTERISTIC_RANGE")
+        || arg_matches.contains_id("INSERT_MEASUREMENT_RANGE")
+        || arg_matches.contains_id("INSERT_CHARACTERISTIC_REGEX")
+        || arg_matches.contains_id("INSERT_MEASUREMENT_REGEX")
+        || arg_matches.contains_id("INSERT_CHARACTERISTIC_SECTION")
+        || arg_matches.contains_id("INSERT_MEASUREMENT_SECTION")
+    {
+        cond_print!(
+            verbose,
+            now,
+            "Inserting new items from range, regex, or section"
+        );
+        let mut log_msgs: Vec<String> = Vec::new();
+        let mut meas_ranges = range_values(arg_matches, "INSERT_MEASUREMENT_RANGE");
+        meas_ranges.extend(insert::section_ranges(
+            debugdata,
+            &string_values(arg_matches, "INSERT_MEASUREMENT_SECTION"),
+            &mut log_msgs,
+        ));
+        let mut char_ranges = range_values(arg_matches, "INSERT_CHARACTERISTIC_RANGE");
+        char_ranges.extend(insert::section_ranges(
+            debugdata,
+            &string_values(arg_matches, "INSERT_CHARACTERISTIC_SECTION"),
+            &mut log_msgs,
+        ));
+
+        let count_before = count_objects(a2l_file);
+        insert::insert_many(
+            a2l_file,
+            debugdata,
+            &meas_ranges,
+            &char_ranges,
+            string_values(arg_matches, "INSERT_MEASUREMENT_REGEX"),
+            string_values(arg_matches, "INSERT_CHARACTERISTIC_REGEX"),
+            target_group,
+            group_by,
+            &mut log_msgs,
+            enable_structures,
+        );
+        summary.add_operation(
+            "insert_many",
+            &[("objects_inserted", count_objects(a2l_file) - count_before)],
+            &log_msgs,
+        );
+        for msg in log_msgs {
+            cond_print!(verbose, now, msg);
+        }
+    }
 
     Ok(())
 }
 
-// load or create an a2l file, depending on the command line
-// return the file name (a dummy value if it is created) as well as the a2l data
-fn load_or_create_a2l(
+// use the structs, sections and program headers of the elf file to organize and check the objects.
+// Returns the number of problems found by the alignment check
+fn organize_by_elf(
     arg_matches: &ArgMatches,
-    strict: bool,
+    a2l_file: &mut A2lFile,
+    debugdata: &DebugData,
+    summary: &mut summary::FileSummary,
     verbose: u8,
     now: Instant,
-) -> Result<(&std::ffi::OsStr, a2lfile::A2lFile), String> {
-    if let Some(input_filename) = arg_matches.get_one::<OsString>("INPUT") {
-        let mut log_msgs = Vec::<A2lError>::new();
-        let a2lresult = a2lfile::load(
-            input_filename,
-            Some(ifdata::A2MLVECTOR_TEXT.to_string()),
+) -> Result<usize, RunError> {
+    let modernize_structures = *arg_matches
+        .get_one::<bool>("MODERNIZE_STRUCTURES")
+        .expect("option modernize-structures must always exist");
+    let group_by_section = *arg_matches
+        .get_one::<bool>("GROUP_BY_SECTION")
+        .expect("option group-by-section must always exist");
+    let create_memory_segments = *arg_matches
+        .get_one::<bool>("CREATE_MEMORY_SEGMENTS")
+        .expect("option create-memory-segments must always exist");
+    let check_alignment = *arg_matches
+        .get_one::<bool>("CHECK_ALIGNMENT")
+        .expect("option check-alignment must always exist");
+
+    // fold flat objects that describe struct members into TYPEDEF_STRUCTURE + INSTANCE
+    if modernize_structures {
+        let mut log_msgs = Vec::new();
+        let modernize_summary =
+            modernize::modernize_structures(a2l_file, debugdata, &mut log_msgs)?;
+        summary.add_operation(
+            "modernize_structures",
+            &[
+                ("instances_created", modernize_summary.instances_created),
+                ("objects_folded", modernize_summary.objects_folded),
+            ],
+            &log_msgs,
+        );
+        for msg in log_msgs {
+            cond_print!(verbose, now, msg);
+        }
+        cond_print!(
+            verbose,
+            now,
+            format!(
+                "Folded {} objects into {} INSTANCEs",
+                modernize_summary.objects_folded, modernize_summary.instances_created
+            )
+        );
+    }
+
+    // organize the updated and inserted objects by the elf sections that contain them
+    if group_by_section {
+        let mut log_msgs = Vec::new();
+        let added = insert::create_section_groups(a2l_file, debugdata, &mut log_msgs);
+        summary.add_operation("group_by_section", &[("added", added)], &log_msgs);
+        for msg in log_msgs {
+            ext_println!(verbose, now, msg);
+        }
+        cond_print!(
+            verbose,
+            now,
+            format!("Added {added} objects to the GROUPs of their elf sections")
+        );
+    }
+
+    // describe the memory of the ECU in MOD_PAR based on the program headers and sections
+    if create_memory_segments {
+        let config = arg_matches
+            .get_one::<OsString>("MEMORY_SEGMENT_CONFIG")
+            .map(|filename| memory_segments::SegmentConfig::load(filename))
+            .transpose()?;
+        let mut log_msgs = Vec::new();
+        let created = memory_segments::create_memory_segments(
+            a2l_file,
+            debugdata,
+            config.as_ref(),
             &mut log_msgs,
-            strict,
         );
-        let a2l_file = match a2lresult {
-            Ok(a2l_file) => {
-                for msg in log_msgs {
-                    cond_print!(verbose, now, msg.to_string());
-                }
-                a2l_file
-            }
-            Err(
-                ref error @ A2lError::ParserError {
-                    parser_error:
-                        a2lfile::ParserError::InvalidMultiplicityNotPresent { ref block, .. },
-                },
-            ) if block == "A2L_FILE" => {
-                // parse error in the outermost block "A2L_FILE" could indicate that this is an a2l fragment containing only the content of a MODULE
-                if let Ok(module) = a2lfile::load_fragment_file(input_filename) {
-                    // successfully loaded a module, now upgrade it to a full file
-                    let mut a2l_file = a2lfile::new();
-                    a2l_file.project.module[0] = module;
-                    a2l_file.project.module[0].get_layout_mut().start_offset = 1;
-                    a2l_file
-                } else {
-                    return Err(error.to_string());
-                }
-            }
-            Err(error) => {
-                return Err(error.to_string());
-            }
-        };
+        summary.add_operation(
+            "create_memory_segments",
+            &[("segments", created)],
+            &log_msgs,
+        );
+        for msg in log_msgs {
+            ext_println!(verbose, now, msg);
+        }
+        cond_print!(
+            verbose,
+            now,
+            format!("Created or updated {created} MEMORY_SEGMENTs")
+        );
+    }
+
+    // compare the addresses with the alignment of the data types
+    let mut check_problems = 0;
+    if check_alignment {
+        let mut log_msgs = Vec::new();
+        let misaligned = alignment::check_alignment(a2l_file, debugdata, &mut log_msgs);
+        summary.add_operation("check_alignment", &[("problems", misaligned)], &log_msgs);
+        check_problems += misaligned;
+        for msg in &log_msgs {
+            ext_println!(verbose, now, format!("    {}", msg));
+        }
+        ext_println!(
+            verbose,
+            now,
+            format!("Alignment check complete. {misaligned} misaligned objects found.")
+        );
+    }
+
+    Ok(check_problems)
+}
+
+// check the byte order, then shift, translate and extend the addresses. Returns the number of byte order conflicts
+fn edit_addresses(
+    arg_matches: &ArgMatches,
+    a2l_file: &mut A2lFile,
+    debugdata: Option<&DebugData>,
+    summary: &mut summary::FileSummary,
+    verbose: u8,
+    now: Instant,
+) -> Result<usize, RunError> {
+    let check_byte_order = *arg_matches
+        .get_one::<bool>("CHECK_BYTE_ORDER")
+        .expect("option check-byte-order must always exist");
+
+    // compare the byte order of the objects with MOD_COMMON and with the elf file
+    let mut check_problems = 0;
+    if check_byte_order {
+        let mut log_msgs = Vec::new();
+        let elf_little_endian = debugdata.map(DebugData::is_little_endian);
+        let conflicts = check::check_byte_order(a2l_file, elf_little_endian, &mut log_msgs);
+        summary.add_operation("check_byte_order", &[("problems", conflicts)], &log_msgs);
+        check_problems += conflicts;
+        for msg in &log_msgs {
+            ext_println!(verbose, now, format!("    {}", msg));
+        }
+        ext_println!(
+            verbose,
+            now,
+            format!("Byte order check complete. {conflicts} conflicts found.")
+        );
+    }
 
+    // shift all addresses, e.g. for software that is linked to a different memory bank
+    if let Some(offset) = arg_matches.get_one::<i64>("ADDRESS_OFFSET") {
+        let mut log_msgs = Vec::new();
+        let count = address_offset::apply_address_offset(a2l_file, *offset, &mut log_msgs);
+        summary.add_operation(
+            "address_offset",
+            &[("shifted", count), ("problems", log_msgs.len())],
+            &log_msgs,
+        );
+        for msg in &log_msgs {
+            ext_println!(verbose, now, msg);
+        }
         cond_print!(
             verbose,
             now,
-            format!("Input \"{}\" loaded", input_filename.to_string_lossy())
+            format!(
+                "Shifted {count} addresses by {}",
+                address_offset::format_address_offset(*offset)
+            )
+        );
+    }
+
+    // translate addresses from one memory window to another, e.g. from the calibration flash to its mirror in RAM
+    if let Some(values) = arg_matches.get_many::<String>("ADDRESS_MAP") {
+        let windows = values
+            .map(|text| text.parse::<address_offset::AddressWindow>())
+            .collect::<Result<Vec<_>, _>>()?;
+        let count = address_offset::apply_address_translation(a2l_file, &windows);
+        summary.add_operation("address_map", &[("translated", count)], &[]);
+        cond_print!(verbose, now, format!("Translated {count} addresses"));
+    }
+
+    // assign the ECU_ADDRESS_EXTENSIONs according to the memory areas of the (updated) addresses
+    if let Some(values) = arg_matches.get_many::<String>("ADDRESS_EXTENSION") {
+        let rules = values
+            .map(|text| text.parse::<address_extension::AddressExtensionRule>())
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut log_msgs = Vec::new();
+        let updated = address_extension::apply_address_extensions(
+            a2l_file,
+            &rules,
+            |name| debugdata.and_then(|d| d.section_range(name)),
+            &mut log_msgs,
+        );
+        summary.add_operation(
+            "address_extension",
+            &[("updated", updated), ("problems", log_msgs.len())],
+            &log_msgs,
+        );
+        for msg in &log_msgs {
+            ext_println!(verbose, now, msg);
+        }
+        cond_print!(
+            verbose,
+            now,
+            format!("Set the ECU_ADDRESS_EXTENSION of {updated} objects")
         );
-        Ok((input_filename, a2l_file))
-    } else if arg_matches.contains_id("CREATE") {
-        // dummy file name
-        let input_filename = OsStr::new("<newly created>");
-        // a minimal a2l file needs only a PROJECT containing a MODULE
-        let mut project = a2lfile::Project::new(
-            "new_project".to_string(),
-            "description of project".to_string(),
-        );
-        project.module = vec![a2lfile::Module::new(
-            "new_module".to_string(),
-            String::new(),
-        )];
-        let mut a2l_file = a2lfile::A2lFile::new(project);
-        // only one line break for PROJECT (after ASAP2_VERSION) instead of the default 2
-        a2l_file.project.get_layout_mut().start_offset = 1;
-        // only one line break for MODULE [0] instead of the default 2
-        a2l_file.project.module[0].get_layout_mut().start_offset = 1;
-        // also set ASAP2_VERSION 1.71
-        a2l_file.asap2_version = Some(a2lfile::Asap2Version::new(1, 71));
-        Ok((input_filename, a2l_file))
-    } else {
-        // shouldn't be able to get here, the clap config requires either INPUT or CREATE
-        Err("impossible: no input filename and no --create".to_string())
     }
+
+    Ok(check_problems)
 }
 
-// set up the entire command line handling.
-// fortunately clap makes this painless
-fn get_args() -> ArgMatches {
-    Command::new("a2ltool")
-    .version(env!("CARGO_PKG_VERSION"))
-    .about("Reads, writes and modifies A2L files")
-    .arg(Arg::new("INPUT")
-        .help("Input A2L file")
-        .index(1)
-        .value_parser(ValueParser::os_string())
-    )
-    .arg(Arg::new("CREATE")
-        .help("Create a new A2L file instead of loading an existing one")
-        .long("create")
+// create virtual measurements from formulas that reference other measurements
+fn create_virtual_measurements(
+    arg_matches: &ArgMatches,
+    a2l_file: &mut A2lFile,
+    summary: &mut summary::FileSummary,
+    verbose: u8,
+    now: Instant,
+) -> Result<(), RunError> {
+    // create virtual measurements, which may reference measurements inserted above
+    if let Some(values) = arg_matches.get_many::<String>("VIRTUAL_MEASUREMENT") {
+        let values: Vec<&String> = values.collect();
+        let mut log_msgs = Vec::new();
+        for args in values.chunks(3) {
+            let references: Vec<&str> = args[2].split(',').map(str::trim).collect();
+            virtual_measurement::create_virtual_measurement(
+                a2l_file,
+                args[0],
+                args[1],
+                &references,
+            )?;
+            log_msgs.push(format!("Created virtual MEASUREMENT {}", args[0]));
+        }
+        summary.add_operation(
+            "virtual_measurement",
+            &[("measurements_created", log_msgs.len())],
+            &log_msgs,
+        );
+        for msg in log_msgs {
+            cond_print!(verbose, now, msg);
+        }
+    }
+    Ok(())
+}
+
+// remove unknown IF_DATA and the IF_DATA of specific protocols / vendors
+fn clean_up_ifdata(
+    arg_matches: &ArgMatches,
+    a2l_file: &mut A2lFile,
+    summary: &mut summary::FileSummary,
+    verbose: u8,
+    now: Instant,
+) {
+    let ifdata_cleanup = *arg_matches
+        .get_one::<bool>("IFDATA_CLEANUP")
+        .expect("option ifdata-cleanup must always exist");
+
+    // remove unknown IF_DATA
+    if ifdata_cleanup {
+        a2l_file.ifdata_cleanup();
+        summary.add_operation("ifdata_cleanup", &[], &[]);
+        cond_print!(verbose, now, "Unknown ifdata removal is done");
+    }
+
+    // remove IF_DATA of specific protocols / vendors
+    if let Some(names) = arg_matches.get_many::<String>("REMOVE_IFDATA") {
+        let names: Vec<&str> = names.map(String::as_str).collect();
+        let count = remove_ifdata::remove_ifdata(a2l_file, &names);
+        summary.add_operation("remove_ifdata", &[("removed", count)], &[]);
+        cond_print!(
+            verbose,
+            now,
+            format!(
+                "Removed {count} IF_DATA blocks of type {}",
+                names.join(", ")
+            )
+        );
+    }
+}
+
+// convert and rename objects, and take their descriptions and other metadata from DCM or CSV files
+fn edit_objects(
+    arg_matches: &ArgMatches,
+    a2l_file: &mut A2lFile,
+    summary: &mut summary::FileSummary,
+    verbose: u8,
+    now: Instant,
+) -> Result<(), RunError> {
+    // convert objects between MEASUREMENT and CHARACTERISTIC
+    let to_characteristic = arg_matches.get_many::<String>("CONVERT_TO_CHARACTERISTIC");
+    let to_measurement = arg_matches.get_many::<String>("CONVERT_TO_MEASUREMENT");
+    if to_characteristic.is_some() || to_measurement.is_some() {
+        let mut log_msgs = Vec::new();
+        let mut characteristic_count = 0;
+        let mut measurement_count = 0;
+        for regex in to_characteristic.into_iter().flatten() {
+            characteristic_count +=
+                convert::convert_to_characteristic(a2l_file, regex, &mut log_msgs)?;
+        }
+        for regex in to_measurement.into_iter().flatten() {
+            measurement_count += convert::convert_to_measurement(a2l_file, regex, &mut log_msgs)?;
+        }
+        summary.add_operation(
+            "convert",
+            &[
+                ("to_characteristic", characteristic_count),
+                ("to_measurement", measurement_count),
+                ("not_converted", log_msgs.len()),
+            ],
+            &log_msgs,
+        );
+        for msg in log_msgs {
+            ext_println!(verbose, now, msg);
+        }
+        cond_print!(
+            verbose,
+            now,
+            format!("Converted {characteristic_count} MEASUREMENTs to CHARACTERISTICs and {measurement_count} CHARACTERISTICs to MEASUREMENTs")
+        );
+    }
+
+    // rename objects and update all references to them
+    if let Some(expressions) = arg_matches.get_many::<String>("RENAME") {
+        let mut count = 0;
+        for expression in expressions {
+            count += rename::rename_with_expression(a2l_file, expression)?;
+        }
+        summary.add_operation("rename", &[("renamed", count)], &[]);
+        cond_print!(verbose, now, format!("Renamed {count} items"));
+    }
+
+    // rename objects according to a mapping file
+    if let Some(rename_map_filename) = arg_matches.get_one::<OsString>("RENAME_MAP") {
+        let text = std::fs::read_to_string(rename_map_filename).map_err(|err| {
+            RunError::new(
+                EXIT_IO_ERROR,
+                format!(
+                    "Error: could not read the rename map \"{}\": {err}",
+                    rename_map_filename.to_string_lossy()
+                ),
+            )
+        })?;
+        let rename_map = rename::parse_rename_map(&text)?;
+        let mut log_msgs = Vec::new();
+        let count = rename::rename_from_map(a2l_file, &rename_map, &mut log_msgs);
+        summary.add_operation(
+            "rename_map",
+            &[("renamed", count), ("problems", log_msgs.len())],
+            &log_msgs,
+        );
+        for msg in log_msgs {
+            ext_println!(verbose, now, msg);
+        }
+        cond_print!(
+            verbose,
+            now,
+            format!("Renamed {count} items using the rename map")
+        );
+    }
+
+    // place all names in a namespace by adding a prefix and / or suffix
+    let prefix = arg_matches.get_one::<String>("PREFIX_ALL");
+    let suffix = arg_matches.get_one::<String>("SUFFIX_ALL");
+    if prefix.is_some() || suffix.is_some() {
+        let count = rename::add_prefix_suffix(
+            a2l_file,
+            prefix.map_or("", String::as_str),
+            suffix.map_or("", String::as_str),
+        )?;
+        summary.add_operation("prefix_suffix", &[("renamed", count)], &[]);
+        cond_print!(
+            verbose,
+            now,
+            format!("Added the prefix / suffix to {count} items")
+        );
+    }
+
+    // generate the DISPLAY_IDENTIFIERs from the (possibly renamed) object names
+    if let Some(template) = arg_matches.get_one::<String>("DISPLAY_IDENTIFIER") {
+        let rule = display_identifier::DisplayIdentifierRule::new(
+            template,
+            arg_matches
+                .get_one::<String>("DISPLAY_IDENTIFIER_STRIP")
+                .map(String::as_str),
+            arg_matches
+                .get_one::<usize>("DISPLAY_IDENTIFIER_MAX_LENGTH")
+                .copied(),
+        )?;
+        let only_missing = *arg_matches
+            .get_one::<bool>("DISPLAY_IDENTIFIER_ONLY_MISSING")
+            .expect("option display-identifier-only-missing must always exist");
+        let mut log_msgs = Vec::new();
+        let count = display_identifier::update_display_identifiers(
+            a2l_file,
+            &rule,
+            only_missing,
+            &mut log_msgs,
+        );
+        summary.add_operation(
+            "display_identifier",
+            &[("updated", count), ("problems", log_msgs.len())],
+            &log_msgs,
+        );
+        for msg in log_msgs {
+            ext_println!(verbose, now, msg);
+        }
+        cond_print!(
+            verbose,
+            now,
+            format!("Set the DISPLAY_IDENTIFIER of {count} items")
+        );
+    }
+
+    // take descriptions and display names from a DCM file
+    if let Some(dcm_filename) = arg_matches.get_one::<OsString>("IMPORT_DCM_METADATA") {
+        let text = std::fs::read(dcm_filename).map_err(|err| {
+            RunError::new(
+                EXIT_IO_ERROR,
+                format!(
+                    "Error: could not read \"{}\": {err}",
+                    dcm_filename.to_string_lossy()
+                ),
+            )
+        })?;
+        let metadata = dcm::parse_dcm_metadata(&String::from_utf8_lossy(&text))?;
+        let mut log_msgs = Vec::new();
+        let count = dcm::apply_dcm_metadata(a2l_file, &metadata, &mut log_msgs);
+        summary.add_operation(
+            "import_dcm_metadata",
+            &[("updated", count), ("problems", log_msgs.len())],
+            &log_msgs,
+        );
+        for msg in log_msgs {
+            ext_println!(verbose, now, msg);
+        }
+        cond_print!(
+            verbose,
+            now,
+            format!("Updated the descriptions of {count} objects from the DCM file")
+        );
+    }
+
+    // take units, descriptions, formats, limits and conversions from a CSV file
+    if let Some(metadata_filename) = arg_matches.get_one::<OsString>("APPLY_METADATA") {
+        let text = std::fs::read(metadata_filename).map_err(|err| {
+            RunError::new(
+                EXIT_IO_ERROR,
+                format!(
+                    "Error: could not read \"{}\": {err}",
+                    metadata_filename.to_string_lossy()
+                ),
+            )
+        })?;
+        let rows = metadata::parse_metadata_csv(&String::from_utf8_lossy(&text))?;
+        let mut log_msgs = Vec::new();
+        let count = metadata::apply_metadata(a2l_file, &rows, &mut log_msgs);
+        summary.add_operation(
+            "apply_metadata",
+            &[("updated", count), ("problems", log_msgs.len())],
+            &log_msgs,
+        );
+        for msg in log_msgs {
+            ext_println!(verbose, now, msg);
+        }
+        cond_print!(
+            verbose,
+            now,
+            format!("Applied the metadata from the CSV file to {count} objects")
+        );
+    }
+
+    Ok(())
+}
+
+// edit MOD_PAR, the variant coding and the versions of the release
+fn edit_mod_par(
+    arg_matches: &ArgMatches,
+    a2l_file: &mut A2lFile,
+    summary: &mut summary::FileSummary,
+    verbose: u8,
+    now: Instant,
+) -> Result<(), RunError> {
+    // set the EPK and its address in MOD_PAR
+    if let Some(epk_string) = arg_matches.get_one::<String>("SET_EPK") {
+        epk::set_epk(a2l_file, epk_string);
+        summary.add_operation("set_epk", &[], &[]);
+        cond_print!(verbose, now, format!("EPK set to \"{epk_string}\""));
+    }
+    if let Some(addr_epk) = arg_matches.get_one::<u64>("SET_ADDR_EPK") {
+        let address = u32::try_from(*addr_epk)
+            .map_err(|_| format!("Error: ADDR_EPK 0x{addr_epk:X} does not fit into 32 bits"))?;
+        epk::set_addr_epk(a2l_file, address);
+        summary.add_operation("set_addr_epk", &[], &[]);
+        cond_print!(verbose, now, format!("ADDR_EPK set to 0x{address:08X}"));
+    }
+
+    // create or update entries of MOD_PAR
+    if let Some(assignments) = arg_matches.get_many::<String>("SET_MODPAR") {
+        let mut count = 0;
+        for assignment in assignments {
+            let (key, value) = modpar::parse_modpar_assignment(assignment)?;
+            modpar::set_modpar_value(a2l_file, key, value)?;
+            count += 1;
+        }
+        summary.add_operation("set_modpar", &[("entries", count)], &[]);
+        cond_print!(verbose, now, format!("Set {count} MOD_PAR entries"));
+    }
+
+    // edit the variant coding: criterion values and forbidden combinations
+    if let Some(definitions) = arg_matches.get_many::<String>("SET_VAR_CRITERION") {
+        let mut log_msgs = Vec::new();
+        let mut count = 0;
+        let mut removed = 0;
+        for definition in definitions {
+            let (name, values) = variant_coding::parse_criterion_values(definition)?;
+            removed += variant_coding::set_criterion_values(a2l_file, name, &values, &mut log_msgs);
+            count += 1;
+        }
+        summary.add_operation(
+            "set_var_criterion",
+            &[("criteria", count), ("removed_combinations", removed)],
+            &log_msgs,
+        );
+        for msg in log_msgs {
+            ext_println!(verbose, now, msg);
+        }
+        cond_print!(
+            verbose,
+            now,
+            format!("Set the values of {count} variant criteria, removed {removed} forbidden combinations")
+        );
+    }
+    if let Some(combinations) = arg_matches.get_many::<String>("REMOVE_VAR_FORBIDDEN_COMB") {
+        let mut count = 0;
+        for text in combinations {
+            let combination = variant_coding::parse_combination(text)?;
+            count += variant_coding::remove_forbidden_combination(a2l_file, &combination);
+        }
+        summary.add_operation("remove_var_forbidden_comb", &[("combinations", count)], &[]);
+        cond_print!(
+            verbose,
+            now,
+            format!("Removed {count} forbidden variant combinations")
+        );
+    }
+    if let Some(combinations) = arg_matches.get_many::<String>("ADD_VAR_FORBIDDEN_COMB") {
+        let mut count = 0;
+        for text in combinations {
+            let combination = variant_coding::parse_combination(text)?;
+            count += variant_coding::add_forbidden_combination(a2l_file, &combination)?;
+        }
+        summary.add_operation("add_var_forbidden_comb", &[("combinations", count)], &[]);
+        cond_print!(
+            verbose,
+            now,
+            format!("Added {count} forbidden variant combinations")
+        );
+    }
+
+    // insert the calibration concept: CALIBRATION_METHODs and the calibration RAM
+    if let Some(config_filename) = arg_matches.get_one::<OsString>("CALIBRATION_CONFIG") {
+        let config = calibration_method::CalibrationConfig::load(config_filename)?;
+        let mut log_msgs = Vec::new();
+        let count = calibration_method::apply_calibration_config(a2l_file, &config, &mut log_msgs);
+        summary.add_operation("calibration_config", &[("entries", count)], &log_msgs);
+        for msg in log_msgs {
+            ext_println!(verbose, now, msg);
+        }
+        cond_print!(
+            verbose,
+            now,
+            format!("Inserted {count} calibration entries into MOD_PAR")
+        );
+    }
+
+    // stamp the version and the project number of the release
+    if let Some(version) = arg_matches.get_one::<String>("SET_VERSION") {
+        modpar::set_version(a2l_file, version)?;
+        summary.add_operation("set_version", &[], &[]);
+        cond_print!(verbose, now, format!("VERSION set to \"{version}\""));
+    }
+    if let Some(project_no) = arg_matches.get_one::<String>("SET_PROJECT_NO") {
+        modpar::set_project_no(a2l_file, project_no)?;
+        summary.add_operation("set_project_no", &[], &[]);
+        cond_print!(verbose, now, format!("PROJECT_NO set to {project_no}"));
+    }
+    let function_version = arg_matches.get_one::<String>("SET_FUNCTION_VERSION");
+    let function_version_map = arg_matches.get_one::<OsString>("FUNCTION_VERSION_MAP");
+    if function_version.is_some() || function_version_map.is_some() {
+        let version_map = if let Some(map_filename) = function_version_map {
+            let text = std::fs::read_to_string(map_filename).map_err(|err| {
+                RunError::new(
+                    EXIT_IO_ERROR,
+                    format!(
+                        "Error: could not read the function version map \"{}\": {err}",
+                        map_filename.to_string_lossy()
+                    ),
+                )
+            })?;
+            modpar::parse_function_version_map(&text)?
+        } else {
+            Vec::new()
+        };
+        let mut log_msgs = Vec::new();
+        let count = modpar::set_function_versions(
+            a2l_file,
+            &version_map,
+            function_version.map(String::as_str),
+            &mut log_msgs,
+        )?;
+        summary.add_operation("set_function_version", &[("functions", count)], &log_msgs);
+        for msg in log_msgs {
+            ext_println!(verbose, now, msg);
+        }
+        cond_print!(
+            verbose,
+            now,
+            format!("FUNCTION_VERSION set in {count} functions")
+        );
+    }
+
+    Ok(())
+}
+
+// run the check rules and transformations of plugins in the order in which they were given.
+// Returns the number of problems found by the check rules
+fn run_plugins(
+    arg_matches: &ArgMatches,
+    a2l_file: &mut A2lFile,
+    summary: &mut summary::FileSummary,
+    verbose: u8,
+    now: Instant,
+) -> Result<usize, RunError> {
+    let mut check_problems = 0;
+    if let Some(steps) = arg_matches.get_many::<String>("PLUGIN_STEP") {
+        let mut plugins = Vec::new();
+        for filename in arg_matches
+            .get_many::<OsString>("PLUGIN")
+            .into_iter()
+            .flatten()
+        {
+            // SAFETY: plugins are libraries that the user explicitly asked to load
+            plugins.push(unsafe { plugin::LoadedPlugin::load(filename) }?);
+        }
+        for step in steps {
+            let mut log_msgs = Vec::new();
+            if let Some(plugin) = plugins.iter().find(|p| p.checks().contains(step)) {
+                plugin.run_check(step, a2l_file, &mut log_msgs)?;
+                summary.add_operation(step, &[("problems", log_msgs.len())], &log_msgs);
+                check_problems += log_msgs.len();
+                for msg in &log_msgs {
+                    ext_println!(verbose, now, format!("    {}", msg));
+                }
+                ext_println!(
+                    verbose,
+                    now,
+                    format!(
+                        "Check {step} of plugin {} complete. {} problems found.",
+                        plugin.name(),
+                        log_msgs.len()
+                    )
+                );
+            } else if let Some(plugin) = plugins.iter().find(|p| p.transformations().contains(step))
+            {
+                plugin.run_transformation(step, a2l_file, &mut log_msgs)?;
+                summary.add_operation(step, &[], &log_msgs);
+                for msg in log_msgs {
+                    cond_print!(verbose, now, msg);
+                }
+                cond_print!(
+                    verbose,
+                    now,
+                    format!("Transformation {step} of plugin {} complete", plugin.name())
+                );
+            } else {
+                return Err(RunError::new(
+                    EXIT_ERROR,
+                    format!("Error: none of the plugins has a check rule or transformation named {step}"),
+                ));
+            }
+        }
+    }
+
+    Ok(check_problems)
+}
+
+// check the EPK and calculate or verify the checksums of the MEMORY_SEGMENTs using a hex file
+fn verify_memory_image(
+    arg_matches: &ArgMatches,
+    a2l_file: &mut A2lFile,
+    summary: &mut summary::FileSummary,
+    verbose: u8,
+    now: Instant,
+) -> Result<(), RunError> {
+    // check that the EPK in the hex file matches the a2l file
+    if let Some(hex_filename) = arg_matches.get_one::<OsString>("VERIFY_EPK") {
+        let image = hexfile::HexImage::load(hex_filename)
+            .map_err(|err| RunError::new(EXIT_IO_ERROR, err))?;
+        let msg = epk::verify_epk(a2l_file, &image)
+            .map_err(|err| RunError::new(EXIT_VERIFY_FAILED, err))?;
+        summary.add_operation("verify_epk", &[], &[]);
+        cond_print!(verbose, now, msg);
+    }
+
+    // calculate or verify the checksums of the MEMORY_SEGMENTs
+    if let Some(hex_filename) = arg_matches.get_one::<OsString>("CHECKSUM") {
+        let image = hexfile::HexImage::load(hex_filename)
+            .map_err(|err| RunError::new(EXIT_IO_ERROR, err))?;
+        let checksum_type = arg_matches
+            .get_one::<checksum::ChecksumType>("CHECKSUM_TYPE")
+            .copied()
+            .unwrap_or(checksum::ChecksumType::Crc32);
+        let verify_only = *arg_matches
+            .get_one::<bool>("CHECKSUM_VERIFY")
+            .expect("option checksum-verify must always exist");
+        let mut log_msgs = Vec::new();
+        let result =
+            checksum::update_checksums(a2l_file, &image, checksum_type, verify_only, &mut log_msgs);
+        summary.add_operation(
+            "checksum",
+            &[
+                ("calculated", result.calculated),
+                ("mismatched", result.mismatched),
+            ],
+            &log_msgs,
+        );
+        for msg in &log_msgs {
+            ext_println!(verbose, now, msg);
+        }
+        if verify_only && result.mismatched > 0 {
+            return Err(RunError::new(
+                EXIT_VERIFY_FAILED,
+                format!(
+                    "Error: {} of the MEMORY_SEGMENT checksums do not match the memory image",
+                    result.mismatched
+                ),
+            ));
+        }
+        cond_print!(
+            verbose,
+            now,
+            format!(
+                "Calculated the checksums of {} MEMORY_SEGMENTs",
+                result.calculated
+            )
+        );
+    }
+
+    Ok(())
+}
+
+// write other files based on the a2l file, and convert values
+fn export_files(
+    arg_matches: &ArgMatches,
+    a2l_file: &A2lFile,
+    summary: &mut summary::FileSummary,
+    verbose: u8,
+    now: Instant,
+) -> Result<(), RunError> {
+    // write struct definitions for the TYPEDEF_STRUCTUREs
+    if let Some(structs_filename) = arg_matches.get_one::<OsString>("EXPORT_STRUCTS") {
+        let language = if arg_matches
+            .get_one::<String>("EXPORT_STRUCTS_LANG")
+            .is_some_and(|lang| lang == "rust")
+        {
+            codegen::StructLanguage::Rust
+        } else {
+            codegen::StructLanguage::C
+        };
+        let mut log_msgs = Vec::<String>::new();
+        let text = codegen::generate_structs(a2l_file, language, &mut log_msgs);
+        std::fs::write(structs_filename, text).map_err(|err| {
+            RunError::new(
+                EXIT_IO_ERROR,
+                format!(
+                    "Error: could not write the struct definitions to \"{}\": {err}",
+                    structs_filename.to_string_lossy()
+                ),
+            )
+        })?;
+        for msg in &log_msgs {
+            cond_print!(verbose, now, msg);
+        }
+        summary.add_operation("export_structs", &[], &log_msgs);
+        summary.add_written_file(structs_filename);
+        cond_print!(
+            verbose,
+            now,
+            format!(
+                "Struct definitions written to \"{}\"",
+                structs_filename.to_string_lossy()
+            )
+        );
+    }
+
+    // write a report of all objects sorted by address
+    if let Some(memory_map_filename) = arg_matches.get_one::<OsString>("MEMORY_MAP") {
+        let html = arg_matches
+            .get_one::<String>("MEMORY_MAP_FORMAT")
+            .is_some_and(|format| format == "html");
+        let report = memmap::memory_map_report(a2l_file, html);
+        std::fs::write(memory_map_filename, report).map_err(|err| {
+            RunError::new(
+                EXIT_IO_ERROR,
+                format!(
+                    "Error: could not write the memory map to \"{}\": {err}",
+                    memory_map_filename.to_string_lossy()
+                ),
+            )
+        })?;
+        summary.add_operation("memory_map", &[], &[]);
+        summary.add_written_file(memory_map_filename);
+        cond_print!(
+            verbose,
+            now,
+            format!(
+                "Memory map written to \"{}\"",
+                memory_map_filename.to_string_lossy()
+            )
+        );
+    }
+
+    // write all calibration objects to a DCM file
+    if let Some(dcm_filename) = arg_matches.get_one::<OsString>("EXPORT_DCM") {
+        std::fs::write(dcm_filename, dcm::export_dcm(a2l_file)).map_err(|err| {
+            RunError::new(
+                EXIT_IO_ERROR,
+                format!(
+                    "Error: could not write the DCM file \"{}\": {err}",
+                    dcm_filename.to_string_lossy()
+                ),
+            )
+        })?;
+        summary.add_operation("export_dcm", &[], &[]);
+        summary.add_written_file(dcm_filename);
+        cond_print!(
+            verbose,
+            now,
+            format!("DCM file written to \"{}\"", dcm_filename.to_string_lossy())
+        );
+    }
+
+    // decode the values of all calibration objects from a hex file
+    if let Some(hex_filename) = arg_matches.get_one::<OsString>("DUMP_VALUES") {
+        let image = hexfile::HexImage::load(hex_filename)
+            .map_err(|err| RunError::new(EXIT_IO_ERROR, err))?;
+        let mut log_msgs = Vec::new();
+        let values = calvalues::read_calibration_values(a2l_file, &image, &mut log_msgs);
+        let json = arg_matches
+            .get_one::<String>("DUMP_VALUES_FORMAT")
+            .is_some_and(|format| format == "json");
+        if json {
+            ext_println!(verbose, now, calvalues::values_to_json(&values));
+        } else {
+            for value in &values {
+                ext_println!(verbose, now, value.to_string());
+            }
+        }
+        summary.add_operation(
+            "dump_values",
+            &[("values", values.len()), ("problems", log_msgs.len())],
+            &log_msgs,
+        );
+        // with json output the messages are only shown in verbose mode, so that the output remains valid json
+        for msg in log_msgs {
+            if json {
+                cond_print!(verbose, now, msg);
+            } else {
+                ext_println!(verbose, now, msg);
+            }
+        }
+    }
+
+    // convert raw values to physical values and back
+    if let Some(name) = arg_matches.get_one::<String>("CONVERT") {
+        let lines = compu::convert_values(
+            &a2l_file.project.module[0],
+            name,
+            &string_values(arg_matches, "RAW_VALUE"),
+            &string_values(arg_matches, "PHYS_VALUE"),
+        )?;
+        summary.add_operation("convert", &[("values", lines.len())], &[]);
+        for line in lines {
+            ext_println!(verbose, now, line);
+        }
+    }
+
+    // write calibration values from a DCM or CDFx file into a hex file
+    if let Some(hex_filename) = arg_matches.get_one::<OsString>("PATCH_HEX") {
+        let cal_filename = arg_matches
+            .get_one::<OsString>("CALIBRATION_DATA")
+            .expect("required by clap");
+        let hex_output = arg_matches
+            .get_one::<OsString>("HEX_OUTPUT")
+            .expect("required by clap");
+        let mut image = hexfile::HexImage::load(hex_filename)
+            .map_err(|err| RunError::new(EXIT_IO_ERROR, err))?;
+        let values = calvalues::load_calibration_file(cal_filename)?;
+        let mut log_msgs = Vec::new();
+        let count =
+            calvalues::write_calibration_values(a2l_file, &mut image, &values, &mut log_msgs);
+        image
+            .save(hex_output)
+            .map_err(|err| RunError::new(EXIT_IO_ERROR, err))?;
+        summary.add_operation(
+            "patch_hex",
+            &[("written", count), ("problems", log_msgs.len())],
+            &log_msgs,
+        );
+        summary.add_written_file(hex_output);
+        for msg in log_msgs {
+            ext_println!(verbose, now, msg);
+        }
+        cond_print!(
+            verbose,
+            now,
+            format!(
+                "Wrote {count} of {} values to \"{}\"",
+                values.len(),
+                hex_output.to_string_lossy()
+            )
+        );
+    }
+
+    Ok(())
+}
+
+// remove confidential information before the file is shared
+fn anonymize_file(
+    arg_matches: &ArgMatches,
+    a2l_file: &mut A2lFile,
+    summary: &mut summary::FileSummary,
+    verbose: u8,
+    now: Instant,
+) -> Result<(), RunError> {
+    let count = anonymize::anonymize(a2l_file);
+    summary.add_operation("anonymize", &[("removed", count)], &[]);
+    cond_print!(
+        verbose,
+        now,
+        format!("Removed {count} descriptions, annotations and identifiers")
+    );
+    if let Some(map_filename) = arg_matches.get_one::<OsString>("ANONYMIZE_NAMES") {
+        let mapping = anonymize::anonymize_names(a2l_file)?;
+        std::fs::write(map_filename, rename::format_rename_map(&mapping)).map_err(|err| {
+            RunError::new(
+                EXIT_IO_ERROR,
+                format!(
+                    "Error: could not write the rename map \"{}\": {err}",
+                    map_filename.to_string_lossy()
+                ),
+            )
+        })?;
+        summary.add_operation("anonymize_names", &[("renamed", mapping.len())], &[]);
+        summary.add_written_file(map_filename);
+        cond_print!(
+            verbose,
+            now,
+            format!(
+                "Renamed {} items, the original names are in \"{}\"",
+                mapping.len(),
+                map_filename.to_string_lossy()
+            )
+        );
+    }
+    Ok(())
+}
+
+// write the a2l file, preceded by the include files if the output is split
+#[allow(clippy::too_many_arguments)]
+fn write_output(
+    arg_matches: &ArgMatches,
+    mut a2l_file: A2lFile,
+    input_path: Option<&OsStr>,
+    out_filename: &OsStr,
+    input_comments: Option<&comments::Comments>,
+    format_profile: Option<&format::FormatProfile>,
+    progress: &progress::Progress,
+    summary: &mut summary::FileSummary,
+    verbose: u8,
+    now: Instant,
+) -> Result<(), RunError> {
+    let audit = *arg_matches
+        .get_one::<bool>("AUDIT")
+        .expect("option audit must always exist");
+    let backup = *arg_matches
+        .get_one::<bool>("BACKUP")
+        .expect("option backup must always exist");
+
+    progress.phase(&format!("Writing \"{}\"", out_filename.to_string_lossy()));
+    a2l_file.sort_new_items();
+    let banner = if audit {
+        let command_line: Vec<String> = std::env::args_os()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        let mut input_files = Vec::new();
+        if let Some(input_path) = input_path {
+            input_files.push(("input", input_path));
+        }
+        for (role, arg) in [("merge", "MERGEMODULE"), ("merge_project", "MERGEPROJECT")] {
+            if let Some(files) = arg_matches.get_many::<OsString>(arg) {
+                input_files.extend(files.map(|file| (role, file.as_os_str())));
+            }
+        }
+        if let Some(elffile) = arg_matches.get_one::<OsString>("ELFFILE") {
+            input_files.push(("elf", elffile.as_os_str()));
+        }
+        summary.audit_text(&command_line.join(" "), &input_files)
+    } else {
+        format!("a2ltool {}", env!("CARGO_PKG_VERSION"))
+    };
+    let banner = &*banner;
+    if let Some(split_by) = arg_matches.get_one::<String>("SPLIT_BY") {
+        let split_by = if split_by == "group" {
+            split::SplitBy::Group
+        } else {
+            split::SplitBy::Type
+        };
+        let out_path = std::path::Path::new(out_filename);
+        let basename = out_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let include_files = split::split_includes(&mut a2l_file, split_by, &basename);
+        for include_file in &include_files {
+            let inc_path = out_path.with_file_name(&include_file.filename);
+            let text = if let Some(profile) = format_profile {
+                format::format_text(&include_file.text, profile)
+            } else {
+                include_file.text.clone()
+            };
+            output::write_atomic(inc_path.as_os_str(), |file| file.write_all(text.as_bytes()))
+                .map_err(|err| RunError::new(EXIT_IO_ERROR, err))?;
+            summary.add_written_file(inc_path.as_os_str());
+        }
+        summary.add_operation("split", &[("include_files", include_files.len())], &[]);
+        cond_print!(
+            verbose,
+            now,
+            format!(
+                "Split the output into {} include files",
+                include_files.len()
+            )
+        );
+    }
+    if backup {
+        if let Some(backup_path) = output::backup(out_filename, std::time::SystemTime::now())
+            .map_err(|err| RunError::new(EXIT_IO_ERROR, err))?
+        {
+            summary.add_written_file(backup_path.as_os_str());
+            cond_print!(
+                verbose,
+                now,
+                format!(
+                    "Backup of the previous output written to \"{}\"",
+                    backup_path.to_string_lossy()
+                )
+            );
+        }
+    }
+    // the output is written to a temporary file first, so that a failure does not destroy the previous file
+    output::write_atomic(out_filename, |file| {
+        let mut text = output::write_to_string(a2l_file);
+        if let Some(input_comments) = input_comments {
+            text = input_comments.insert(&text);
+        }
+        // the banner is placed on the first line if it is empty, like in A2lFile::write()
+        let separator = if text.starts_with('\n') { "" } else { "\n" };
+        let mut text = format!("/* {banner} */{separator}{text}");
+        if let Some(profile) = format_profile {
+            text = format::format_text(&text, profile);
+        }
+        file.write_all(text.as_bytes())
+    })
+    .map_err(|err| RunError::new(EXIT_IO_ERROR, err))?;
+    progress.finish();
+    summary.add_operation("write", &[], &[]);
+    summary.add_written_file(out_filename);
+    Ok(())
+}
+
+// total number of MEASUREMENTs, CHARACTERISTICs, AXIS_PTS, BLOBs and INSTANCEs in all modules
+fn count_objects(a2l_file: &a2lfile::A2lFile) -> usize {
+    a2l_file
+        .project
+        .module
+        .iter()
+        .map(|module| {
+            module.measurement.len()
+                + module.characteristic.len()
+                + module.axis_pts.len()
+                + module.blob.len()
+                + module.instance.len()
+        })
+        .sum()
+}
+
+// all files on the command line that are read by the operations, except for the input file and the elf file.
+// Output files are not included, because they are written on every run
+fn other_input_files(arg_matches: &ArgMatches) -> Vec<&OsStr> {
+    const EXCLUDED: [&str; 7] = [
+        "INPUT",
+        "ELFFILE",
+        "OUTPUT",
+        "OUTPUT_PATTERN",
+        "LOGFILE",
+        "SUMMARY_JSON",
+        "UPDATE_CACHE",
+    ];
+    arg_matches
+        .ids()
+        .filter(|id| !EXCLUDED.contains(&id.as_str()))
+        .filter_map(|id| {
+            arg_matches
+                .try_get_many::<OsString>(id.as_str())
+                .ok()
+                .flatten()
+        })
+        .flatten()
+        .map(OsString::as_os_str)
+        .filter(|path| std::path::Path::new(path).is_file())
+        .collect()
+}
+
+// load or create an a2l file, depending on the command line
+// return the file name (a dummy value if it is created) as well as the a2l data
+fn load_or_create_a2l<'a>(
+    arg_matches: &ArgMatches,
+    input_filename: Option<&'a OsStr>,
+    strict: bool,
+    verbose: u8,
+    now: Instant,
+    summary: &mut summary::FileSummary,
+) -> Result<(&'a OsStr, a2lfile::A2lFile), String> {
+    if let Some(input_filename) = input_filename {
+        // an additional A2ML specification can be supplied by the user to parse vendor specific IF_DATA
+        let additional_a2ml = arg_matches
+            .get_one::<OsString>("A2ML_FILE")
+            .map(|a2ml_filename| a2ml::load_a2ml_file(a2ml_filename))
+            .transpose()?;
+        let include_dirs: Vec<std::path::PathBuf> = arg_matches
+            .get_many::<OsString>("INCLUDE_DIR")
+            .map(|dirs| dirs.map(std::path::PathBuf::from).collect())
+            .unwrap_or_default();
+        let mut log_msgs = Vec::<A2lError>::new();
+        let a2l_file = load::load_a2l_with_include_dirs(
+            input_filename,
+            additional_a2ml.as_deref(),
+            &include_dirs,
+            strict,
+            &mut log_msgs,
+        )?;
+        let log_msgs: Vec<String> = log_msgs.iter().map(ToString::to_string).collect();
+        summary.add_operation("load", &[("warnings", log_msgs.len())], &log_msgs);
+        for msg in log_msgs {
+            cond_print!(verbose, now, msg);
+        }
+
+        cond_print!(
+            verbose,
+            now,
+            format!("Input \"{}\" loaded", input_filename.to_string_lossy())
+        );
+        Ok((input_filename, a2l_file))
+    } else if arg_matches.contains_id("CREATE") {
+        // dummy file name
+        let input_filename = OsStr::new("<newly created>");
+        summary.add_operation("create", &[], &[]);
+        Ok((input_filename, load::new_a2l()))
+    } else {
+        // shouldn't be able to get here, the clap config requires either INPUT or CREATE
+        Err("impossible: no input filename and no --create".to_string())
+    }
+}
+
+fn get_args(args: Vec<OsString>) -> ArgMatches {
+    build_command().get_matches_from(args)
+}
+
+// set up the entire command line handling.
+// fortunately clap makes this painless
+fn build_command() -> Command {
+    Command::new("a2ltool")
+    .version(env!("CARGO_PKG_VERSION"))
+    .about("Reads, writes and modifies A2L files")
+    .after_help("Arguments can also be read from a response file given as @FILE. It contains one argument per line; lines starting with '#' are ignored.")
+    .arg(Arg::new("INPUT")
+        .help("Input A2L file. A file inside a ZIP archive is given as ARCHIVE.zip!path/FILE.a2l, or just ARCHIVE.zip if it contains only one a2l file.")
+        .index(1)
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+    )
+    .arg(Arg::new("CREATE")
+        .help("Create a new A2L file instead of loading an existing one")
+        .long("create")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+    )
+    .arg(Arg::new("ELFFILE")
+        .help("Elf file containing symbols and address information")
+        .short('e')
+        .long("elffile")
+        .number_of_values(1)
+        .value_name("ELFFILE")
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+    )
+    .arg(Arg::new("CHECK")
+        .help("Perform additional consistency checks")
+        .long("check")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+    )
+    .arg(Arg::new("CHECK_FORMULA_INVERSE")
+        .help("Verify that the FORMULA_INV of each COMPU_METHOD is the inverse of its FORMULA, using sample values between the limits of the objects that use it.\nThe arg --check must be present.")
+        .long("check-formula-inverse")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+        .requires("CHECK")
+    )
+    .arg(Arg::new("CHECK_RULES")
+        .help("Read additional rules for the consistency check from a rules file.\nEach line of the file contains one rule, e.g. \"NAMING MEASUREMENT ^M_\" requires all MEASUREMENT names to match the regex ^M_, and \"ALIAS _alias$\" allows objects whose name matches the regex to share their address with other objects.\nThe arg --check must be present.")
+        .long("check-rules")
+        .number_of_values(1)
+        .value_name("RULES_FILE")
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+        .requires("CHECK")
+    )
+    .arg(Arg::new("VALIDATE_IFDATA")
+        .help("Check all IF_DATA blocks that do not match the A2ML specification, and report which element violates the specification.\nThe A2ML of the file, the A2ML given with --a2ml and the built-in A2ML are used.\nInvalid blocks are counted as problems of the consistency check.")
+        .long("validate-ifdata")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+    )
+    .arg(Arg::new("CHECK_ALIGNMENT")
+        .help("Check that the address of each object is aligned according to its data type in the debug info, e.g. a uint32 must be placed at a multiple of 4.\nMisaligned objects are counted as problems of the consistency check.")
+        .long("check-alignment")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+        .requires("ELFFILE")
+    )
+    .arg(Arg::new("CHECK_BYTE_ORDER")
+        .help("Check that the BYTE_ORDER of each object matches the byte order of the target, which is taken from the elf file if it is given, or from MOD_COMMON.\nConflicts are counted as problems of the consistency check.")
+        .long("check-byte-order")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+    )
+    .arg(Arg::new("ADDRESS_OFFSET")
+        .help("Add an offset to all addresses: ECU_ADDRESS, the addresses of CHARACTERISTICs, AXIS_PTS, BLOBs and INSTANCEs, MEMORY_SEGMENTs, ADDR_EPK and the addresses in CANAPE_EXT, CCP and XCP IF_DATA.\nThe offset is a decimal or hex number and can be negative, e.g. --address-offset 0x20000 or --address-offset -0x20000")
+        .long("address-offset")
+        .number_of_values(1)
+        .value_name("OFFSET")
+        .allow_hyphen_values(true)
+        .value_parser(|text: &str| address_offset::parse_address_offset(text))
+    )
+    .arg(Arg::new("ADDRESS_MAP")
+        .help("Translate all object addresses inside a memory window to another window, e.g. --address-map 0x08010000-0x0801FFFF=0x20010000 for calibration flash that is mirrored into RAM. The END address is included.\nThe translation is applied after an update. The option can be repeated, and the table can be stored in a response file. MEMORY_SEGMENTs and ADDR_EPK are not changed.")
+        .long("address-map")
+        .number_of_values(1)
+        .value_name("START-END=TARGET")
+        .action(clap::ArgAction::Append)
+    )
+    .arg(Arg::new("ADDRESS_EXTENSION")
+        .help("Assign an ECU_ADDRESS_EXTENSION to all objects in a memory area. The area is either an address range, e.g. 0x70000000-0x7000FFFF=1, or an elf section, e.g. .data=1.\nThe option can be repeated to build a rule set, which can also be stored in a response file. The first matching rule is used; objects outside of all areas are not changed.")
+        .long("address-extension")
+        .number_of_values(1)
+        .value_name("AREA=EXT")
+        .action(clap::ArgAction::Append)
+    )
+    .arg(Arg::new("MODULE")
+        .help("Restrict the XCP and CCP settings, the address update, the insertion of new items and the removal of IF_DATA to the MODULE with the given name.\nWithout this option these operations apply to all modules, or only to the first module.")
+        .long("module")
+        .number_of_values(1)
+        .value_name("NAME")
+    )
+    .arg(Arg::new("CREATE_XCP")
+        .help("Create a complete XCP IF_DATA block using the given transport layer (can, flx, tcp or udp).\nModules that already contain XCP settings are not changed.\nThe transport layer parameters can be set with the --xcp-can-*, --xcp-flx-* and --xcp-eth-* options.")
+        .long("create-xcp")
+        .number_of_values(1)
+        .value_name("TRANSPORT")
+        .value_parser(["can", "flx", "tcp", "udp"])
+    )
+    .arg(Arg::new("XCP_CONFIG")
+        .help("Read the parameters of the XCP IF_DATA created by --create-xcp from a config file.\nEach line contains one setting in the form KEY VALUE.\nKeys: T1 .. T7, MAX_CTO, MAX_DTO, BYTE_ORDER, DAQ_CONFIG_TYPE, MAX_DAQ, MAX_EVENT_CHANNEL, MIN_DAQ, MAX_ODT_ENTRY_SIZE_DAQ")
+        .long("xcp-config")
+        .number_of_values(1)
+        .value_name("CONFIG_FILE")
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+        .requires("CREATE_XCP")
+    )
+    .arg(Arg::new("XCP_PARAM")
+        .help("Set a parameter of the XCP IF_DATA created by --create-xcp, e.g. --xcp-param T1=500.\nThe same keys as in the config file are supported. Values given here override the config file.")
+        .long("xcp-param")
+        .number_of_values(1)
+        .value_name("KEY=VALUE")
+        .action(clap::ArgAction::Append)
+        .requires("CREATE_XCP")
+    )
+    .arg(Arg::new("XCP_CAN_ID_MASTER")
+        .help("Set the CAN id used by the master (CAN_ID_MASTER) in the XCP_ON_CAN settings.\nThe XCP IF_DATA will be created if it does not exist.\nExtended CAN ids are marked by setting bit 31, e.g. 0x80000123")
+        .long("xcp-can-id-master")
+        .number_of_values(1)
+        .value_name("CAN_ID")
+        .value_parser(CanIdValueParser)
+    )
+    .arg(Arg::new("XCP_CAN_ID_SLAVE")
+        .help("Set the CAN id used by the slave (CAN_ID_SLAVE) in the XCP_ON_CAN settings.\nThe XCP IF_DATA will be created if it does not exist.")
+        .long("xcp-can-id-slave")
+        .number_of_values(1)
+        .value_name("CAN_ID")
+        .value_parser(CanIdValueParser)
+    )
+    .arg(Arg::new("XCP_CAN_BAUDRATE")
+        .help("Set the baudrate in Hz in the XCP_ON_CAN settings.\nThe XCP IF_DATA will be created if it does not exist.")
+        .long("xcp-can-baudrate")
+        .number_of_values(1)
+        .value_name("BAUDRATE")
+        .value_parser(clap::value_parser!(u32))
+    )
+    .arg(Arg::new("XCP_CAN_SAMPLE_POINT")
+        .help("Set the sample point in percent of the bit time in the XCP_ON_CAN settings.\nThe XCP IF_DATA will be created if it does not exist.")
+        .long("xcp-can-sample-point")
+        .number_of_values(1)
+        .value_name("PERCENT")
+        .value_parser(clap::value_parser!(u8).range(1..=100))
+    )
+    .arg(Arg::new("XCP_ADD_EVENT")
+        .help("Add a DAQ event to the XCP settings. The cycle time can be given in ns, us, ms or s; 0 means the event is not cyclic.\nExample: --xcp-add-event task_10ms 10ms")
+        .long("xcp-add-event")
+        .number_of_values(2)
+        .value_names(["NAME", "CYCLE"])
+        .action(clap::ArgAction::Append)
+    )
+    .arg(Arg::new("XCP_RENAME_EVENT")
+        .help("Rename a DAQ event in the XCP settings.\nExample: --xcp-rename-event old_name new_name")
+        .long("xcp-rename-event")
+        .number_of_values(2)
+        .value_names(["NAME", "NEW_NAME"])
+        .action(clap::ArgAction::Append)
+    )
+    .arg(Arg::new("XCP_RETIME_EVENT")
+        .help("Change the cycle time of a DAQ event in the XCP settings.\nExample: --xcp-retime-event task_fast 5ms")
+        .long("xcp-retime-event")
+        .number_of_values(2)
+        .value_names(["NAME", "CYCLE"])
+        .action(clap::ArgAction::Append)
+    )
+    .arg(Arg::new("XCP_EVENT_CONFIG")
+        .help("Replace the DAQ events in the XCP settings with the events of a YAML config file, which is typically shared with the code generation of the firmware.\nEach event has a name, a channel number, an optional cycle time and an optional priority. Existing events are matched by channel number.\nThe config is applied before --xcp-add-event, --xcp-rename-event and --xcp-retime-event.")
+        .long("xcp-event-config")
+        .number_of_values(1)
+        .value_name("YAML_FILE")
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+    )
+    .arg(Arg::new("XCP_PROTOCOL_PARAM")
+        .help("Change a parameter of the PROTOCOL_LAYER in the existing XCP settings. Supported keys: T1 .. T7, MAX_CTO, MAX_DTO.\nThe new values are validated against the XCP version and the transport layer.\nExample: --xcp-protocol-param T1=500")
+        .long("xcp-protocol-param")
+        .number_of_values(1)
+        .value_name("KEY=VALUE")
+        .action(clap::ArgAction::Append)
+    )
+    .arg(Arg::new("CHECK_MAX_REFRESH")
+        .help("Check that the MAX_REFRESH of each object matches the cycle time of a DAQ event in the XCP settings.\nMismatched objects are counted as problems of the consistency check.")
+        .long("check-max-refresh")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+    )
+    .arg(Arg::new("FIX_MAX_REFRESH")
+        .help("Replace each MAX_REFRESH that does not match the cycle time of a DAQ event by the cycle time of the closest DAQ event")
+        .long("fix-max-refresh")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+    )
+    .arg(Arg::new("SET_SEED_KEY")
+        .help("Set the name of the seed & key DLL in the existing XCP and CCP settings")
+        .long("set-seed-key")
+        .number_of_values(1)
+        .value_name("DLL")
+    )
+    .arg(Arg::new("CLEAR_SEED_KEY")
+        .help("Remove the seed & key DLL from the existing XCP and CCP settings")
+        .long("clear-seed-key")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+        .conflicts_with("SET_SEED_KEY")
+    )
+    .arg(Arg::new("SEED_KEY_TRANSPORT")
+        .help("Limit --set-seed-key or --clear-seed-key to the XCP settings of one transport layer (can, flx, tcp or udp).\nThis allows a transport layer to use a different seed & key DLL than the default.")
+        .long("seed-key-transport")
+        .number_of_values(1)
+        .value_name("TRANSPORT")
+        .value_parser(["can", "flx", "tcp", "udp"])
+    )
+    .arg(Arg::new("XCP_FLX_FIBEX_FILE")
+        .help("Set the name of the FIBEX file in the XCP_ON_FLX settings.\nThe XCP IF_DATA will be created if it does not exist.")
+        .long("xcp-flx-fibex-file")
+        .number_of_values(1)
+        .value_name("FILENAME")
+    )
+    .arg(Arg::new("XCP_FLX_CLUSTER_ID")
+        .help("Set the cluster id in the XCP_ON_FLX settings.\nThe XCP IF_DATA will be created if it does not exist.")
+        .long("xcp-flx-cluster-id")
+        .number_of_values(1)
+        .value_name("CLUSTER_ID")
+    )
+    .arg(Arg::new("XCP_FLX_NAX")
+        .help("Set the node address (NAX) of the slave in the XCP_ON_FLX settings.\nThe XCP IF_DATA will be created if it does not exist.")
+        .long("xcp-flx-nax")
+        .number_of_values(1)
+        .value_name("NAX")
+        .value_parser(clap::value_parser!(u8))
+    )
+    .arg(Arg::new("XCP_FLX_T1")
+        .help("Set the timeout T1_FLX in ms in the XCP_ON_FLX settings.\nThe XCP IF_DATA will be created if it does not exist.")
+        .long("xcp-flx-t1")
+        .number_of_values(1)
+        .value_name("MS")
+        .value_parser(clap::value_parser!(u16))
+    )
+    .arg(Arg::new("XCP_ETH_PROTOCOL")
+        .help("Select the transport protocol (tcp or udp) of XCP on Ethernet.\nAn existing XCP_ON_TCP_IP or XCP_ON_UDP_IP block is converted if necessary.")
+        .long("xcp-eth-protocol")
+        .number_of_values(1)
+        .value_name("PROTOCOL")
+        .value_parser(["tcp", "udp"])
+    )
+    .arg(Arg::new("XCP_ETH_ADDRESS")
+        .help("Set the address of the slave in the XCP on Ethernet settings.\nThe value can be an IPv4 address, an IPv6 address or a host name.\nAn XCP_ON_UDP_IP block will be created if no XCP on Ethernet settings exist.")
+        .long("xcp-eth-address")
+        .number_of_values(1)
+        .value_name("ADDRESS")
+    )
+    .arg(Arg::new("XCP_ETH_PORT")
+        .help("Set the port of the slave in the XCP on Ethernet settings.\nAn XCP_ON_UDP_IP block will be created if no XCP on Ethernet settings exist.")
+        .long("xcp-eth-port")
+        .number_of_values(1)
+        .value_name("PORT")
+        .value_parser(clap::value_parser!(u16))
+    )
+    .arg(Arg::new("CLEANUP")
+        .help("Remove empty or unreferenced items")
+        .short('c')
+        .long("cleanup")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+    )
+    .arg(Arg::new("INCLUDE_DIR")
+        .help("Search for the files of /include directives in this directory, if they are not found relative to the including file.\nEnvironment variables in /include directives, e.g. $(PROJECT_ROOT) or ${PROJECT_ROOT}, are always expanded.")
+        .short('I')
+        .long("include-dir")
+        .number_of_values(1)
+        .value_name("DIR")
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::DirPath)
+        .action(clap::ArgAction::Append)
+    )
+    .arg(Arg::new("MERGEMODULE")
+        .help("Merge another a2l file on the MODULE level.\nThe input file and the merge file must each contain exactly one MODULE.\nThe contents will be merged so that there is one merged MODULE in the output.")
+        .short('m')
+        .long("merge")
+        .number_of_values(1)
+        .value_name("A2LFILE")
+        .number_of_values(1)
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+        .action(clap::ArgAction::Append)
+    )
+    .arg(Arg::new("MERGEPROJECT")
+        .help("Merge another a2l file on the PROJECT level.\nIf the input file contains m MODULES and the merge file contains n MODULES, then there will be m + n MODULEs in the output.")
+        .short('p')
+        .long("merge-project")
+        .number_of_values(1)
+        .value_name("A2LFILE")
+        .number_of_values(1)
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+        .action(clap::ArgAction::Append)
+    )
+    .arg(Arg::new("MERGEINCLUDES")
+        .help("Merge the content of all included files. The output file will contain no /include commands.")
+        .short('i')
+        .long("merge-includes")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+    )
+    .arg(Arg::new("SPLIT_BY")
+        .help("Write the objects into separate include files next to the output file, and only keep the /include commands in the output file.\nWith \"type\" there are include files for the support objects, the measurements and the characteristics; with \"group\" there is one include file per root GROUP.")
+        .long("split-by")
+        .number_of_values(1)
+        .value_parser(["group", "type"])
+        .value_name("SPLIT")
+    )
+    .arg(Arg::new("UPDATE")
+        .help("Update the addresses of all objects in the A2L file based on the elf file.\nObjects that cannot be found in the elf file will be deleted.\nThe arg --elffile must be present.")
+        .short('u')
+        .long("update")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+        .requires("ELFFILE")
+    )
+    .arg(Arg::new("UPDATE_VAR_ADDRESS")
+        .help("Update the VAR_ADDRESS lists of the variant coding. The symbol of each variant is the symbol of the CHARACTERISTIC followed by SUFFIX.\nSUFFIX can contain placeholders for the criteria, e.g. _{Gear}_{Engine}, and {index} for the number of the variant.\nWithout placeholders SUFFIX separates the criterion values, e.g. _ finds the symbol name_Manual_Petrol.\nThe arg --elffile must be present.")
+        .long("update-var-address")
+        .number_of_values(1)
+        .value_name("SUFFIX")
+        .requires("ELFFILE")
+    )
+    .arg(Arg::new("STORE_BUILD_ID")
+        .help("Store the GNU build-id of the elf file in MOD_PAR as SYSTEM_CONSTANT \"BUILD_ID\", to record which build the addresses belong to.\nThe arg --elffile must be present.")
+        .long("store-build-id")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+        .requires("ELFFILE")
+        .conflicts_with("VERIFY_BUILD_ID")
+    )
+    .arg(Arg::new("VERIFY_BUILD_ID")
+        .help("Verify that the SYSTEM_CONSTANT \"BUILD_ID\" matches the build-id of the elf file.\nThe run fails with exit code 8 if it is missing or different")
+        .long("verify-build-id")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+        .requires("ELFFILE")
+    )
+    .arg(Arg::new("BUILD_ID_SYMBOL")
+        .help("Use the content of this symbol in the elf file as the build-id instead of the GNU build-id, e.g. a version string")
+        .long("build-id-symbol")
+        .number_of_values(1)
+        .value_name("SYMBOL")
+    )
+    .arg(Arg::new("SAFE_UPDATE")
+        .help("Update the addresses of all objects in the A2L file based on the elf file.\nObjects that cannot be found in the elf file will be preserved; their adresses will be set to zero.\nThe arg --elffile must be present.")
+        .long("update-preserve")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+        .requires("ELFFILE")
+    )
+    .arg(Arg::new("UPDATE_CACHE")
+        .help("Store the state of the input, output and elf files in the given cache file.\nIf all input files and the command line are unchanged and the output file has not been modified since the last run, then the run is skipped.\nIf the input file is the unchanged output of the last run, then the update is skipped, or only the objects whose symbols changed in the elf file are updated.")
+        .long("update-cache")
+        .number_of_values(1)
+        .value_name("CACHEFILE")
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+        .requires("UPDATE_ARGGROUP")
+    )
+    .arg(Arg::new("MAX_UPDATE_FAILURES")
+        .help("Exit with an error if more than COUNT objects could not be found in the elf file during the update.\nThe output file is not written in this case.")
+        .long("max-update-failures")
+        .number_of_values(1)
+        .value_name("COUNT")
+        .value_parser(clap::value_parser!(u32))
+        .requires("UPDATE_ARGGROUP")
+    )
+    .arg(Arg::new("ADDRESS_DELTA_WARN")
+        .help("Warn about all objects whose address was changed by the update by more than BYTES bytes.\nA large change usually means that the object was matched to the wrong variable. These objects are listed separately in the summary.")
+        .long("address-delta-warn")
+        .number_of_values(1)
+        .value_name("BYTES")
+        .value_parser(clap::value_parser!(u64))
+        .requires("UPDATE_ARGGROUP")
+    )
+    .arg(Arg::new("ENABLE_STRUCTURES")
+        .help("Enable the the use of INSTANCE, TYPEDEF_STRUCTURE & co. for all operations. Requires a2l version 1.7.1")
+        .short('t')
+        .long("enable-structures")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+        .requires("ELFFILE")
+    )
+    .arg(Arg::new("MODERNIZE_STRUCTURES")
+        .help("Fold MEASUREMENTs and CHARACTERISTICs that describe the members of the same struct variable into an INSTANCE of a new TYPEDEF_STRUCTURE.\nThe objects are grouped using their SYMBOL_LINK and checked against the debug info. Requires a2l version 1.7.1")
+        .long("modernize-structures")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+        .requires("ELFFILE")
+    )
+    .arg(Arg::new("INSTANCE_POINTERS")
+        .help("How to update INSTANCEs of variables with nested pointers, e.g. pointers to pointers or arrays of pointers:\nskip them with a warning, follow up to N nested levels (follow:N), or represent all levels with TYPEDEFs (typedef, default).\nNested levels can only be represented if --enable-structures is set.")
+        .long("instance-pointers")
+        .number_of_values(1)
+        .value_name("POLICY")
+        .value_parser(|policy: &str| policy.parse::<update::InstancePointerPolicy>())
+        .requires("UPDATE_ARGGROUP")
+    )
+    .arg(Arg::new("SYMBOL_CASE_INSENSITIVE")
+        .help("Match the names of objects and SYMBOL_LINKs to the variables in the debug info without regard to case, if there is no exact match.\nIf a name matches several variables or struct members that differ only by case, either the first of them in alphabetical order is used (first, default), or the name is not resolved (reject).")
+        .long("symbol-case-insensitive")
+        .num_args(0..=1)
+        .default_missing_value("first")
+        .value_name("TIE_BREAK")
+        .value_parser(|tie_break: &str| tie_break.parse::<CaseTieBreak>())
+        .requires("ELFFILE")
+    )
+    .arg(Arg::new("IGNORE_LEADING_UNDERSCORE")
+        .help("Match the names of objects and SYMBOL_LINKs to variables in the debug info whose names differ by a leading underscore, e.g. EngSpd and _EngSpd, if there is no exact match.\nThe SYMBOL_LINK is set to the name in the debug info.")
+        .long("ignore-leading-underscore")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+        .requires("ELFFILE")
+    )
+    .arg(Arg::new("DUPLICATE_SYMBOL_POLICY")
+        .help("Select how a name is resolved if there are several variables with this name in the debug info, e.g. static variables in different files, and the SYMBOL_LINK does not select one of them with {CompileUnit:file_c}.\nThe name is either not resolved (error), or the first variable in the debug info (first, default) or the variable from the file whose name comes first in alphabetical order (by-file) is used.")
+        .long("duplicate-symbol-policy")
+        .number_of_values(1)
+        .value_name("POLICY")
+        .value_parser(|policy: &str| policy.parse::<DuplicateSymbolPolicy>())
+        .requires("ELFFILE")
+    )
+    .arg(Arg::new("A2LVERSION")
+        .help("Convert the input file to the given version (e.g. \"1.5.1\", \"1.6.0\", etc.). This is a lossy operation, which deletes incompatible information.")
+        .short('a')
+        .long("a2lversion")
+        .number_of_values(1)
+        .value_name("A2L_VERSION")
+        .value_parser(A2lVersionParser)
+    )
+    .arg(Arg::new("OUTPUT")
+        .help("Write to the given output file. If this flag is not present, no output will be written.")
+        .short('o')
+        .long("output")
+        .number_of_values(1)
+        .value_name("A2LFILE")
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+    )
+    .arg(Arg::new("PLUGIN")
+        .help("Load a plugin from a dynamic library. The check rules and transformations of the plugin can be run with --plugin-step.\nThis option can be given multiple times.")
+        .long("plugin")
+        .number_of_values(1)
+        .value_name("LIBRARY")
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+        .action(clap::ArgAction::Append)
+    )
+    .arg(Arg::new("PLUGIN_STEP")
+        .help("Run a check rule or transformation of a plugin. The steps are run in the order in which they are given, after the built-in modifications of the file, but before --checksum, --sort and --anonymize, so that these also apply to the result of the plugins.\nProblems found by check rules are counted as problems of the consistency check.")
+        .long("plugin-step")
+        .number_of_values(1)
+        .value_name("NAME")
+        .action(clap::ArgAction::Append)
+        .requires("PLUGIN")
+    )
+    .arg(Arg::new("BATCH")
+        .help("Process all A2L files matching the pattern instead of a single input file.\nThe wildcards * and ? can be used in the file name. Each file is processed independently with the same options.")
+        .long("batch")
+        .number_of_values(1)
+        .value_name("PATTERN")
+        .action(clap::ArgAction::Append)
+    )
+    .arg(Arg::new("WATCH")
+        .help("Keep running after all operations are complete, and run them again each time the elf file or one of the files given with --merge or --merge-project changes.\nThe output file is written after each run. Stop with Ctrl+C.")
+        .long("watch")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+        .requires("ELFFILE")
+    )
+    .arg(Arg::new("OUTPUT_PATTERN")
+        .help("Output file name for each file in batch mode. {stem}, {name} and {ext} are replaced by the parts of the input file name.\nRelative names are placed in the directory of the input file.")
+        .long("output-pattern")
+        .number_of_values(1)
+        .value_name("PATTERN")
+        .requires("BATCH")
+        .conflicts_with("OUTPUT")
+    )
+    .arg(Arg::new("STRICT")
+        .help("Parse all input in strict mode. An error wil be reported if the file has any inconsistency.")
+        .short('s')
+        .long("strict")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+    )
+    .arg(Arg::new("VERBOSE")
+        .help("Display additional information")
+        .short('v')
+        .long("verbose")
+        .number_of_values(0)
+        .action(clap::ArgAction::Count)
+    )
+    .arg(Arg::new("QUIET")
+        .help("Do not print anything on stdout. Errors are printed on stderr.")
+        .short('q')
+        .long("quiet")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+        .conflicts_with("VERBOSE")
+    )
+    .arg(Arg::new("LOGFILE")
+        .help("Write all messages to a log file with timestamps.\nThe log contains the messages of every verbosity level, independent of --verbose.")
+        .long("logfile")
+        .number_of_values(1)
+        .value_name("LOGFILE")
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+    )
+    .arg(Arg::new("SUMMARY_JSON")
+        .help("Write a summary of all operations, their results and warnings to a JSON file")
+        .long("summary-json")
+        .number_of_values(1)
+        .value_name("JSONFILE")
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+    )
+    .arg(Arg::new("PROGRESS")
+        .help("Show the progress of long running steps, like loading the elf file or writing the output, on stderr")
+        .long("progress")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+    )
+    .arg(Arg::new("DEBUGPRINT")
+        .help("Display internal data for debugging")
+        .long("debug-print")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+    )
+    .arg(Arg::new("FLATTEN_TYPEDEFS")
+        .help("Expand every INSTANCE into equivalent MEASUREMENTs, CHARACTERISTICs, AXIS_PTS and BLOBs, and remove the TYPEDEFs that are no longer used.\nThis allows the file to be used with tools that don't support TYPEDEF_* and INSTANCE.")
+        .long("flatten-typedefs")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+    )
+    .arg(Arg::new("CONVERT_TO_CHARACTERISTIC")
+        .help("Convert all MEASUREMENTs whose name matches the regex into CHARACTERISTICs.\nRECORD_LAYOUTs are created as needed, and the references in GROUPs and FUNCTIONs are updated.\nThis option can be given multiple times.")
+        .long("convert-to-characteristic")
+        .number_of_values(1)
+        .value_name("REGEX")
+        .action(clap::ArgAction::Append)
+    )
+    .arg(Arg::new("CONVERT_TO_MEASUREMENT")
+        .help("Convert all CHARACTERISTICs of type VALUE or VAL_BLK whose name matches the regex into MEASUREMENTs.\nRECORD_LAYOUTs that are no longer used are deleted, and the references in GROUPs and FUNCTIONs are updated.\nThis option can be given multiple times.")
+        .long("convert-to-measurement")
+        .number_of_values(1)
+        .value_name("REGEX")
+        .action(clap::ArgAction::Append)
+    )
+    .arg(Arg::new("RENAME")
+        .help("Rename objects using a substitution expression like s/OldPrefix_/NewPrefix_/ and update all references to them.\nThe pattern is a regex that is applied to the names of all objects of all types. Add the flag g to replace all matches in a name.\nThis option can be given multiple times.")
+        .long("rename")
+        .number_of_values(1)
+        .value_name("EXPRESSION")
+        .action(clap::ArgAction::Append)
+    )
+    .arg(Arg::new("RENAME_MAP")
+        .help("Rename objects according to a mapping file, and update all references to them.\nEach line of the file contains an old and a new name, separated by a comma: old_name,new_name\nEntries that don't match anything or that would cause name collisions are reported.")
+        .long("rename-map")
+        .number_of_values(1)
+        .value_name("MAPFILE")
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+    )
+    .arg(Arg::new("PREFIX_ALL")
+        .help("Add a prefix to the names of all objects of all types, and update all references to them")
+        .long("prefix-all")
+        .number_of_values(1)
+        .value_name("PREFIX")
+    )
+    .arg(Arg::new("SUFFIX_ALL")
+        .help("Add a suffix to the names of all objects of all types, and update all references to them")
+        .long("suffix-all")
+        .number_of_values(1)
+        .value_name("SUFFIX")
+    )
+    .arg(Arg::new("DISPLAY_IDENTIFIER")
+        .help("Set the DISPLAY_IDENTIFIER of all MEASUREMENTs, CHARACTERISTICs, AXIS_PTS, BLOBs and INSTANCEs using a template.\nThe placeholders {name} (object name) and {symbol} (symbol name of the SYMBOL_LINK) are replaced.\nThis is done after renaming, inserting and updating, so that the DISPLAY_IDENTIFIERs match the final names.")
+        .long("display-identifier")
+        .number_of_values(1)
+        .value_name("TEMPLATE")
+    )
+    .arg(Arg::new("DISPLAY_IDENTIFIER_STRIP")
+        .help("Remove a prefix matching the REGEX from the names before they are inserted into the display identifier template")
+        .long("display-identifier-strip")
+        .number_of_values(1)
+        .value_name("REGEX")
+        .requires("DISPLAY_IDENTIFIER")
+    )
+    .arg(Arg::new("DISPLAY_IDENTIFIER_MAX_LENGTH")
+        .help("Truncate generated DISPLAY_IDENTIFIERs to at most LENGTH characters")
+        .long("display-identifier-max-length")
+        .number_of_values(1)
+        .value_name("LENGTH")
+        .value_parser(clap::value_parser!(usize))
+        .requires("DISPLAY_IDENTIFIER")
+    )
+    .arg(Arg::new("DISPLAY_IDENTIFIER_ONLY_MISSING")
+        .help("Only create DISPLAY_IDENTIFIERs for objects that don't have one, and keep all existing DISPLAY_IDENTIFIERs")
+        .long("display-identifier-only-missing")
         .number_of_values(0)
         .action(clap::ArgAction::SetTrue)
+        .requires("DISPLAY_IDENTIFIER")
     )
-    .arg(Arg::new("ELFFILE")
-        .help("Elf file containing symbols and address information")
-        .short('e')
-        .long("elffile")
+    .arg(Arg::new("SET_EPK")
+        .help("Set the EPK string in MOD_PAR. MOD_PAR is created if it does not exist")
+        .long("epk")
         .number_of_values(1)
-        .value_name("ELFFILE")
+        .value_name("EPK")
+    )
+    .arg(Arg::new("SET_ADDR_EPK")
+        .help("Set the address of the EPK in MOD_PAR. Any existing ADDR_EPK entries are replaced")
+        .long("addr-epk")
+        .number_of_values(1)
+        .value_name("ADDRESS")
+        .value_parser(AddressValueParser)
+    )
+    .arg(Arg::new("SET_MODPAR")
+        .help("Create or update an entry of MOD_PAR, e.g. --set-modpar CPU_TYPE=TC397\nValid entries are CPU_TYPE, CUSTOMER, CUSTOMER_NO, ECU, ECU_CALIBRATION_OFFSET, EPK, NO_OF_INTERFACES, PHONE_NO, SUPPLIER, USER and VERSION.\nThis option can be given multiple times.")
+        .long("set-modpar")
+        .number_of_values(1)
+        .value_name("KEY=VALUE")
+        .action(clap::ArgAction::Append)
+    )
+    .arg(Arg::new("SET_VAR_CRITERION")
+        .help("Set the values of a VAR_CRITERION, e.g. --set-var-criterion Gear=Manual,Automatic\nThe criterion is created if it does not exist. Forbidden combinations that use a removed value are deleted.\nThis option can be given multiple times.")
+        .long("set-var-criterion")
+        .number_of_values(1)
+        .value_name("NAME=VALUE,...")
+        .action(clap::ArgAction::Append)
+    )
+    .arg(Arg::new("ADD_VAR_FORBIDDEN_COMB")
+        .help("Add a forbidden combination of variant criterion values, e.g. --add-var-forbidden-comb Gear=Manual,Engine=Electric\nThis option can be given multiple times.")
+        .long("add-var-forbidden-comb")
+        .number_of_values(1)
+        .value_name("CRITERION=VALUE,...")
+        .action(clap::ArgAction::Append)
+    )
+    .arg(Arg::new("REMOVE_VAR_FORBIDDEN_COMB")
+        .help("Remove a forbidden combination of variant criterion values, e.g. --remove-var-forbidden-comb Gear=Manual,Engine=Electric\nThis option can be given multiple times.")
+        .long("remove-var-forbidden-comb")
+        .number_of_values(1)
+        .value_name("CRITERION=VALUE,...")
+        .action(clap::ArgAction::Append)
+    )
+    .arg(Arg::new("CALIBRATION_CONFIG")
+        .help("Insert CALIBRATION_METHODs and the calibration RAM into MOD_PAR, as described by a config file.\nExample config:\n  METHOD InCircuit2 1\n  HANDLE 0x70000000 0x8000\n  HANDLE_TEXT \"working page\"\n  RAM_SEGMENT CalRam 0x70000000 0x8000")
+        .long("calibration-config")
+        .number_of_values(1)
+        .value_name("CONFIG_FILE")
         .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
     )
-    .arg(Arg::new("CHECK")
-        .help("Perform additional consistency checks")
-        .long("check")
-        .number_of_values(0)
-        .action(clap::ArgAction::SetTrue)
+    .arg(Arg::new("SET_VERSION")
+        .help("Set the VERSION in the HEADER and in the MOD_PAR of every module, e.g. --set-version \"2.3.0 build 17\"")
+        .long("set-version")
+        .number_of_values(1)
+        .value_name("VERSION")
     )
-    .arg(Arg::new("CLEANUP")
-        .help("Remove empty or unreferenced items")
-        .short('c')
-        .long("cleanup")
-        .number_of_values(0)
-        .action(clap::ArgAction::SetTrue)
+    .arg(Arg::new("SET_PROJECT_NO")
+        .help("Set the PROJECT_NO in the HEADER. The project number must be a valid a2l identifier, e.g. B17")
+        .long("set-project-no")
+        .number_of_values(1)
+        .value_name("PROJECT_NO")
     )
-    .arg(Arg::new("MERGEMODULE")
-        .help("Merge another a2l file on the MODULE level.\nThe input file and the merge file must each contain exactly one MODULE.\nThe contents will be merged so that there is one merged MODULE in the output.")
-        .short('m')
-        .long("merge")
+    .arg(Arg::new("SET_FUNCTION_VERSION")
+        .help("Set the FUNCTION_VERSION of all FUNCTIONs, e.g. --set-function-version 2.3.0\nWith --function-version-map this version is only used for the functions that are not in the map.")
+        .long("set-function-version")
         .number_of_values(1)
-        .value_name("A2LFILE")
+        .value_name("VERSION")
+    )
+    .arg(Arg::new("FUNCTION_VERSION_MAP")
+        .help("Set the FUNCTION_VERSION of FUNCTIONs according to a mapping file.\nEach line of the file contains a function name and a version, separated by a comma: function,version")
+        .long("function-version-map")
         .number_of_values(1)
+        .value_name("MAPFILE")
         .value_parser(ValueParser::os_string())
-        .action(clap::ArgAction::Append)
+        .value_hint(ValueHint::FilePath)
     )
-    .arg(Arg::new("MERGEPROJECT")
-        .help("Merge another a2l file on the PROJECT level.\nIf the input file contains m MODULES and the merge file contains n MODULES, then there will be m + n MODULEs in the output.")
-        .short('p')
-        .long("merge-project")
+    .arg(Arg::new("VERIFY_EPK")
+        .help("Verify that the EPK string is stored at ADDR_EPK in the given Intel HEX or S-record file.\nThe run fails with exit code 8 if the content does not match")
+        .long("verify-epk")
         .number_of_values(1)
-        .value_name("A2LFILE")
+        .value_name("HEXFILE")
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+    )
+    .arg(Arg::new("CHECKSUM")
+        .help("Calculate the checksum of each MEMORY_SEGMENT over the content of the given Intel HEX or S-record file, and store it in MOD_PAR as SYSTEM_CONSTANT \"CHECKSUM.<segment>\".\nThe checksum type is taken from the XCP CHECKSUM block of the segment, or from --checksum-type.")
+        .long("checksum")
         .number_of_values(1)
+        .value_name("HEXFILE")
         .value_parser(ValueParser::os_string())
-        .action(clap::ArgAction::Append)
+        .value_hint(ValueHint::FilePath)
     )
-    .arg(Arg::new("MERGEINCLUDES")
-        .help("Merge the content of all included files. The output file will contain no /include commands.")
-        .short('i')
-        .long("merge-includes")
-        .number_of_values(0)
-        .action(clap::ArgAction::SetTrue)
+    .arg(Arg::new("CHECKSUM_TYPE")
+        .help("The checksum type for MEMORY_SEGMENTs without an XCP CHECKSUM block: add11, add12, add14, add22, add24, add44, crc16, crc16-ccitt or crc32 (default)")
+        .long("checksum-type")
+        .number_of_values(1)
+        .value_name("TYPE")
+        .value_parser(|value: &str| value.parse::<checksum::ChecksumType>())
+        .requires("CHECKSUM")
     )
-    .arg(Arg::new("UPDATE")
-        .help("Update the addresses of all objects in the A2L file based on the elf file.\nObjects that cannot be found in the elf file will be deleted.\nThe arg --elffile must be present.")
-        .short('u')
-        .long("update")
+    .arg(Arg::new("CHECKSUM_VERIFY")
+        .help("Only compare the stored checksums with the content of the file given with --checksum, without changing them.\nThe run fails with exit code 8 if a checksum is missing or does not match")
+        .long("checksum-verify")
         .number_of_values(0)
         .action(clap::ArgAction::SetTrue)
-        .requires("ELFFILE")
+        .requires("CHECKSUM")
     )
-    .arg(Arg::new("SAFE_UPDATE")
-        .help("Update the addresses of all objects in the A2L file based on the elf file.\nObjects that cannot be found in the elf file will be preserved; their adresses will be set to zero.\nThe arg --elffile must be present.")
-        .long("update-preserve")
-        .number_of_values(0)
-        .action(clap::ArgAction::SetTrue)
-        .requires("ELFFILE")
+    .arg(Arg::new("FORMAT")
+        .help("Format the output file according to a style profile. The profile is one of the built-in profiles default, sorted, compact and tabs, or a JSON file.\nA profile file can set \"indent\" (number of spaces or \"tab\"), \"sort\" (true/false), \"hex_case\" (upper/lower), \"max_blank_lines\" and \"line_ending\" (lf/crlf).")
+        .long("format")
+        .number_of_values(1)
+        .value_name("PROFILE")
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+        .requires("OUTPUT")
     )
-    .arg(Arg::new("ENABLE_STRUCTURES")
-        .help("Enable the the use of INSTANCE, TYPEDEF_STRUCTURE & co. for all operations. Requires a2l version 1.7.1")
-        .short('t')
-        .long("enable-structures")
+    .arg(Arg::new("ANONYMIZE")
+        .help("Remove confidential information before the file is shared: all descriptions, annotations and comments, the project identifiers, and the supplier and customer information in MOD_PAR")
+        .long("anonymize")
         .number_of_values(0)
         .action(clap::ArgAction::SetTrue)
-        .requires("ELFFILE")
-    )
-    .arg(Arg::new("A2LVERSION")
-        .help("Convert the input file to the given version (e.g. \"1.5.1\", \"1.6.0\", etc.). This is a lossy operation, which deletes incompatible information.")
-        .short('a')
-        .long("a2lversion")
-        .number_of_values(1)
-        .value_name("A2L_VERSION")
-        .value_parser(A2lVersionParser)
     )
-    .arg(Arg::new("OUTPUT")
-        .help("Write to the given output file. If this flag is not present, no output will be written.")
-        .short('o')
-        .long("output")
+    .arg(Arg::new("ANONYMIZE_NAMES")
+        .help("Also rename all items to generated names like OBJ_1. The original names are written to MAPFILE, which restores them when it is used with --rename-map")
+        .long("anonymize-names")
         .number_of_values(1)
-        .value_name("A2LFILE")
+        .value_name("MAPFILE")
         .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+        .requires("ANONYMIZE")
     )
-    .arg(Arg::new("STRICT")
-        .help("Parse all input in strict mode. An error wil be reported if the file has any inconsistency.")
-        .short('s')
-        .long("strict")
+    .arg(Arg::new("STRIP_COMMENTS")
+        .help("Do not copy the comments of the input file to the output file")
+        .long("strip-comments")
         .number_of_values(0)
         .action(clap::ArgAction::SetTrue)
     )
-    .arg(Arg::new("VERBOSE")
-        .help("Display additional information")
-        .short('v')
-        .long("verbose")
+    .arg(Arg::new("BACKUP")
+        .help("Keep a copy of the previous output file as <OUTPUT>.<timestamp>.bak before it is overwritten")
+        .long("backup")
         .number_of_values(0)
-        .action(clap::ArgAction::Count)
+        .action(clap::ArgAction::SetTrue)
     )
-    .arg(Arg::new("DEBUGPRINT")
-        .help("Display internal data for debugging")
-        .long("debug-print")
+    .arg(Arg::new("AUDIT")
+        .help("Document in the banner comment of the output file how it was produced: the command line, the input, merge and elf files with their CRC32, and all operations that were performed")
+        .long("audit")
         .number_of_values(0)
         .action(clap::ArgAction::SetTrue)
     )
@@ -668,17 +3514,281 @@ fn get_args() -> ArgMatches {
         .number_of_values(0)
         .action(clap::ArgAction::SetTrue)
     )
+    .arg(Arg::new("A2ML_FILE")
+        .help("Read an additional A2ML specification from a file. It is used together with the built-in A2ML to parse the IF_DATA of the input file.\nItems of its IF_DATA taggedunion override built-in items with the same tag.")
+        .long("a2ml")
+        .number_of_values(1)
+        .value_name("A2ML_FILE")
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+        .conflicts_with("CREATE")
+    )
+    .arg(Arg::new("EXTRACT_A2ML")
+        .help("Write the A2ML block of the input file to a separate file")
+        .long("extract-a2ml")
+        .number_of_values(1)
+        .value_name("A2ML_FILE")
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+    )
+    .arg(Arg::new("REPLACE_A2ML")
+        .help("Replace the A2ML block in all modules with the content of the given file.\nThe file may contain either the bare A2ML or a complete /begin A2ML ... /end A2ML block.")
+        .long("replace-a2ml")
+        .number_of_values(1)
+        .value_name("A2ML_FILE")
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+    )
+    .arg(Arg::new("FORMAT_A2ML")
+        .help("Reformat the A2ML block in all modules, with one declaration or member per line and indentation for each nesting level")
+        .long("format-a2ml")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+    )
+    .arg(Arg::new("SORT_A2ML")
+        .help("Sort the members of each taggedstruct and taggedunion in the A2ML by their tags while reformatting it")
+        .long("sort-a2ml")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+        .requires("FORMAT_A2ML")
+    )
     .arg(Arg::new("IFDATA_CLEANUP")
         .help("Remove all IF_DATA blocks that cannot be parsed according to A2ML")
         .long("ifdata-cleanup")
         .action(clap::ArgAction::SetTrue)
     )
+    .arg(Arg::new("REMOVE_IFDATA")
+        .help("Remove all IF_DATA blocks with the given name, e.g. CANAPE_EXT or ASAP1B_CCP.\nAll other IF_DATA blocks are left unchanged. This option can be given multiple times.")
+        .long("remove-ifdata")
+        .number_of_values(1)
+        .value_name("NAME")
+        .action(clap::ArgAction::Append)
+    )
     .arg(Arg::new("SHOW_XCP")
         .help("Display the XCP settings in the a2l file, if they exist")
         .long("show-xcp")
         .number_of_values(0)
         .action(clap::ArgAction::SetTrue)
     )
+    .arg(Arg::new("SHOW_CCP")
+        .help("Display the CCP settings in the a2l file, if they exist")
+        .long("show-ccp")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+    )
+    .arg(Arg::new("SHOW_VARIANTS")
+        .help("Display the variant coding in the a2l file: VAR_CRITERIONs, VAR_FORBIDDEN_COMBs and VAR_CHARACTERISTICs")
+        .long("show-variants")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+    )
+    .arg(Arg::new("DECODE_IFDATA")
+        .help("Display the content of the IF_DATA blocks as a tree, decoded according to the A2ML specification.\nIf NAME is given, only the IF_DATA of the object NAME or the IF_DATA blocks with the tag NAME are shown.")
+        .long("decode-ifdata")
+        .num_args(0..=1)
+        .default_missing_value("")
+        .value_name("NAME")
+    )
+    .arg(Arg::new("SEARCH")
+        .help("Search for MEASUREMENTs, CHARACTERISTICs, AXIS_PTS, BLOBs and INSTANCEs whose name matches the regex.\nThe address, data type, conversion and groups of each matching object are displayed.")
+        .long("search")
+        .number_of_values(1)
+        .value_name("REGEX")
+    )
+    .arg(Arg::new("LIST")
+        .help("List all objects of the given type, one line per object")
+        .long("list")
+        .number_of_values(1)
+        .value_name("TYPE")
+        .value_parser(query::LIST_TYPES)
+    )
+    .arg(Arg::new("LIST_FILTER")
+        .help("Only list the objects whose name matches the regex")
+        .long("list-filter")
+        .number_of_values(1)
+        .value_name("REGEX")
+        .requires("LIST")
+    )
+    .arg(Arg::new("EXPAND_GROUP")
+        .help("List the names of all measurements and characteristics that are reachable from a GROUP, including all of its SUB_GROUPs")
+        .long("expand-group")
+        .number_of_values(1)
+        .value_name("GROUP")
+    )
+    .arg(Arg::new("EXPAND_GROUP_OUTPUT")
+        .help("Write the names found by --expand-group to a label list (LAB file) instead of displaying them")
+        .long("expand-group-output")
+        .number_of_values(1)
+        .value_name("LAB_FILE")
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+        .requires("EXPAND_GROUP")
+    )
+    .arg(Arg::new("INFO")
+        .help("Display the definition of an object together with everything it references:\nCOMPU_METHOD, conversion tables, RECORD_LAYOUT and axes.\nIf an elf file is given, the symbol of the object is displayed as well.")
+        .long("info")
+        .number_of_values(1)
+        .value_name("NAME")
+    )
+    .arg(Arg::new("STATISTICS")
+        .help("Display statistics about the file: the number of objects of each type, the size of the calibration data,\nthe data types in use, the IF_DATA sizes and the structure of the include files")
+        .long("statistics")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+    )
+    .arg(Arg::new("CCP_CAN_ID_SEND")
+        .help("Set the CAN id used to send commands to the ECU (CRO) in the CCP TP_BLOB.\nThe ASAP1B_CCP IF_DATA will be created if it does not exist.")
+        .long("ccp-can-id-send")
+        .number_of_values(1)
+        .value_name("CAN_ID")
+        .value_parser(CanIdValueParser)
+    )
+    .arg(Arg::new("CCP_CAN_ID_RECEIVE")
+        .help("Set the CAN id used by the ECU to respond (DTO) in the CCP TP_BLOB.\nThe ASAP1B_CCP IF_DATA will be created if it does not exist.")
+        .long("ccp-can-id-receive")
+        .number_of_values(1)
+        .value_name("CAN_ID")
+        .value_parser(CanIdValueParser)
+    )
+    .arg(Arg::new("CCP_STATION_ADDRESS")
+        .help("Set the station address in the CCP TP_BLOB.\nThe ASAP1B_CCP IF_DATA will be created if it does not exist.")
+        .long("ccp-station-address")
+        .number_of_values(1)
+        .value_name("ADDRESS")
+        .value_parser(clap::value_parser!(u16))
+    )
+    .arg(Arg::new("SHOW_XCP_FORMAT")
+        .help("Select the output format of --show-xcp: text (default) or json")
+        .long("show-xcp-format")
+        .number_of_values(1)
+        .value_name("FORMAT")
+        .value_parser(["text", "json"])
+        .requires("SHOW_XCP")
+    )
+    .arg(Arg::new("MEMORY_MAP")
+        .help("Write a report of all objects sorted by address and grouped by MEMORY_SEGMENT.\nGaps between the objects and overlapping objects are highlighted.")
+        .long("memory-map")
+        .number_of_values(1)
+        .value_name("REPORTFILE")
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+    )
+    .arg(Arg::new("MEMORY_MAP_FORMAT")
+        .help("Select the format of the memory map report: text (default) or html")
+        .long("memory-map-format")
+        .number_of_values(1)
+        .value_name("FORMAT")
+        .value_parser(["text", "html"])
+        .requires("MEMORY_MAP")
+    )
+    .arg(Arg::new("EXPORT_STRUCTS")
+        .help("Write struct definitions that mirror the memory layout of all TYPEDEF_STRUCTUREs to a C header or a Rust source file.\nThe offsets of the components, their array dimensions and their base types are taken from the a2l file.")
+        .long("export-structs")
+        .number_of_values(1)
+        .value_name("SOURCEFILE")
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+    )
+    .arg(Arg::new("EXPORT_STRUCTS_LANG")
+        .help("Select the language of the struct definitions: c (default) or rust")
+        .long("export-structs-lang")
+        .number_of_values(1)
+        .value_name("LANGUAGE")
+        .value_parser(["c", "rust"])
+        .requires("EXPORT_STRUCTS")
+    )
+    .arg(Arg::new("EXPORT_DCM")
+        .help("Write all CHARACTERISTICs and AXIS_PTS to a DCM file, with their descriptions, display names and units.\nAll values in the file are set to zero.")
+        .long("export-dcm")
+        .number_of_values(1)
+        .value_name("DCMFILE")
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+    )
+    .arg(Arg::new("IMPORT_DCM_METADATA")
+        .help("Set the descriptions (LANGNAME) and display names (DISPLAYNAME) of CHARACTERISTICs and AXIS_PTS from a DCM file")
+        .long("import-dcm-metadata")
+        .number_of_values(1)
+        .value_name("DCMFILE")
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+    )
+    .arg(Arg::new("APPLY_METADATA")
+        .help("Set the units, descriptions, formats, limits and COMPU_METHODs of MEASUREMENTs, CHARACTERISTICs and AXIS_PTS from a CSV file.\nThe header line names the columns: name or regex, unit, description, format, lower_limit, upper_limit and conversion.\nRows that don't match any object are reported.")
+        .long("apply-metadata")
+        .number_of_values(1)
+        .value_name("CSVFILE")
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+    )
+    .arg(Arg::new("DUMP_VALUES")
+        .help("Read an Intel HEX or S-record file and display the physical values of all CHARACTERISTICs and AXIS_PTS")
+        .long("dump-values")
+        .number_of_values(1)
+        .value_name("HEXFILE")
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+    )
+    .arg(Arg::new("DUMP_VALUES_FORMAT")
+        .help("Select the format of the values: text (default) or json")
+        .long("dump-values-format")
+        .number_of_values(1)
+        .value_name("FORMAT")
+        .value_parser(["text", "json"])
+        .requires("DUMP_VALUES")
+    )
+    .arg(Arg::new("CONVERT")
+        .help("Convert values with the COMPU_METHOD of the named object. The name can be a COMPU_METHOD, MEASUREMENT, CHARACTERISTIC or AXIS_PTS.\nThe values are given with --raw-value and --phys-value")
+        .long("convert")
+        .number_of_values(1)
+        .value_name("NAME")
+    )
+    .arg(Arg::new("RAW_VALUE")
+        .help("A raw value to convert to a physical value with --convert. This option can be given multiple times.")
+        .long("raw-value")
+        .number_of_values(1)
+        .value_name("VALUE")
+        .allow_hyphen_values(true)
+        .action(clap::ArgAction::Append)
+        .requires("CONVERT")
+    )
+    .arg(Arg::new("PHYS_VALUE")
+        .help("A physical value to convert to a raw value with --convert. Text values of verbal conversion tables are also accepted. This option can be given multiple times.")
+        .long("phys-value")
+        .number_of_values(1)
+        .value_name("VALUE")
+        .allow_hyphen_values(true)
+        .action(clap::ArgAction::Append)
+        .requires("CONVERT")
+    )
+    .arg(Arg::new("PATCH_HEX")
+        .help("Read an Intel HEX or S-record file, write the values from a calibration data file into it, and save the result.\nThe values are converted to raw values using the record layouts and COMPU_METHODs of the a2l file.")
+        .long("patch-hex")
+        .number_of_values(1)
+        .value_name("HEXFILE")
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+        .requires("CALIBRATION_DATA")
+        .requires("HEX_OUTPUT")
+    )
+    .arg(Arg::new("CALIBRATION_DATA")
+        .help("The calibration data file in DCM or CDFx format, whose values are written by --patch-hex")
+        .long("calibration-data")
+        .number_of_values(1)
+        .value_name("CALFILE")
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+        .requires("PATCH_HEX")
+    )
+    .arg(Arg::new("HEX_OUTPUT")
+        .help("The output file of --patch-hex. It is written in the same format as the input hex file")
+        .long("hex-output")
+        .number_of_values(1)
+        .value_name("HEXFILE")
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+        .requires("PATCH_HEX")
+    )
     .arg(Arg::new("INSERT_CHARACTERISTIC")
         .help("Insert a CHARACTERISTIC based on a variable in the elf file. The variable name can be complex, e.g. var.element[0].subelement")
         .short('C')
@@ -762,9 +3872,47 @@ fn get_args() -> ArgMatches {
         .requires("INSERT_ARGGROUP")
         .value_name("GROUP")
     )
+    .arg(Arg::new("GROUP_BY")
+        .help("When inserting items, also put them into one group per source file or per directory, based on the debug info, or into nested groups following the struct hierarchy.")
+        .long("group-by")
+        .number_of_values(1)
+        .value_parser(["source-file", "directory", "struct"])
+        .requires("INSERT_ARGGROUP")
+        .value_name("GROUPING")
+    )
+    .arg(Arg::new("GROUP_BY_SECTION")
+        .help("After updating and inserting, add each MEASUREMENT, CHARACTERISTIC and AXIS_PTS to a GROUP named after the elf section that contains it, e.g. \"cal_engine\" for the section .cal_engine.")
+        .long("group-by-section")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+        .requires("ELFFILE")
+    )
+    .arg(Arg::new("CREATE_MEMORY_SEGMENTS")
+        .help("Create MEMORY_SEGMENTs in MOD_PAR from the program headers of the elf file.\nCode is classified as PRG_CODE in FLASH, writable data as VARIABLES in RAM and constant data as PRG_DATA in FLASH.\nExisting MEMORY_SEGMENTs with the same name are updated.")
+        .long("create-memory-segments")
+        .number_of_values(0)
+        .action(clap::ArgAction::SetTrue)
+        .requires("ELFFILE")
+    )
+    .arg(Arg::new("MEMORY_SEGMENT_CONFIG")
+        .help("Read the names and types of the MEMORY_SEGMENTs from a config file instead of using the program headers.\nEach line contains one segment, e.g. \"\\.cal_.* CALIBRATION PRG_DATA FLASH\" creates the segment CALIBRATION spanning all sections matching the regex.\nThe types can be omitted or set to AUTO to derive them from the section flags.")
+        .long("memory-segment-config")
+        .number_of_values(1)
+        .value_name("CONFIG_FILE")
+        .value_parser(ValueParser::os_string())
+        .value_hint(ValueHint::FilePath)
+        .requires("CREATE_MEMORY_SEGMENTS")
+    )
+    .arg(Arg::new("VIRTUAL_MEASUREMENT")
+        .help("Create a VIRTUAL MEASUREMENT that is calculated from other measurements using a FORMULA.\nThe referenced measurements are given as a comma separated list and are named X1, X2, etc. in the formula.\nExample: --virtual-measurement distance \"X1*X2\" speed,time")
+        .long("virtual-measurement")
+        .number_of_values(3)
+        .value_names(["NAME", "FORMULA", "REFERENCES"])
+        .action(clap::ArgAction::Append)
+    )
     .group(
         ArgGroup::new("INPUT_ARGGROUP")
-            .args(["INPUT", "CREATE"])
+            .args(["INPUT", "CREATE", "BATCH"])
             .multiple(false)
             .required(true)
      )
@@ -780,41 +3928,132 @@ fn get_args() -> ArgMatches {
                 "INSERT_MEASUREMENT_SECTION", "INSERT_MEASUREMENT_SECTION", ])
             .multiple(true)
     )
+    .subcommand(Command::new("shell")
+        .about("Load an A2L file and modify it interactively")
+        .arg(Arg::new("INPUT")
+            .help("Input A2L file")
+            .index(1)
+            .required(true)
+            .value_parser(ValueParser::os_string())
+            .value_hint(ValueHint::FilePath)
+        )
+        .arg(Arg::new("STRICT")
+            .help("Parse all input in strict mode. An error will be reported if the file has any inconsistency.")
+            .short('s')
+            .long("strict")
+            .number_of_values(0)
+            .action(clap::ArgAction::SetTrue)
+        )
+    )
+    .subcommand(Command::new("server")
+        .about("Keep a2l and elf files in memory and perform operations on them, which are requested with JSON-RPC on a local TCP port")
+        .long_about("Keep a2l and elf files in memory and perform operations on them, which are requested with JSON-RPC 2.0 on a local TCP port.\nEach request and each response is one line of JSON. The methods are check, update, query, diff, write, clear_cache and shutdown.\nThe first request of each connection must be authenticate, with the token that the server prints when it starts.\nFiles are only loaded again if they change on disk.")
+        .arg(Arg::new("PORT")
+            .help("The port on 127.0.0.1 where the server listens for connections")
+            .long("port")
+            .number_of_values(1)
+            .value_name("PORT")
+            .default_value("7878")
+            .value_parser(clap::value_parser!(u16))
+        )
+        .arg(Arg::new("TOKEN_FILE")
+            .help("Write the access token to this file instead of printing it. On unix the file can only be read by the current user.")
+            .long("token-file")
+            .number_of_values(1)
+            .value_name("FILE")
+            .value_parser(ValueParser::os_string())
+            .value_hint(ValueHint::FilePath)
+        )
+        .arg(Arg::new("OUTPUT_DIR")
+            .help("Output files of update and write requests are created in this directory. Paths outside of it are rejected.")
+            .long("output-dir")
+            .number_of_values(1)
+            .value_name("DIR")
+            .default_value(".")
+            .value_parser(ValueParser::os_string())
+            .value_hint(ValueHint::DirPath)
+        )
+        .arg(Arg::new("STRICT")
+            .help("Parse all input in strict mode. An error will be reported if the file has any inconsistency.")
+            .short('s')
+            .long("strict")
+            .number_of_values(0)
+            .action(clap::ArgAction::SetTrue)
+        )
+    )
+    .subcommand(Command::new("completions")
+        .about("Print a shell completion script for a2ltool")
+        .arg(Arg::new("SHELL")
+            .help("The shell for which the completion script is generated")
+            .index(1)
+            .required(true)
+            .value_parser(clap::value_parser!(clap_complete::Shell))
+        )
+    )
+    .args_conflicts_with_subcommands(true)
+    .subcommand_negates_reqs(true)
     .next_line_help(false)
-    .get_matches()
 }
 
-fn range_args_to_ranges(args: Option<ValuesRef<u64>>) -> Vec<(u64, u64)> {
-    if let Some(values) = args {
-        let rangevals: Vec<u64> = values.copied().collect();
-        let mut addr_ranges: Vec<(u64, u64)> = Vec::new();
-        for idx in (1..rangevals.len()).step_by(2) {
-            addr_ranges.push((rangevals[idx - 1], rangevals[idx]));
+// replace each argument "@file" with the arguments listed in the file.
+// The file contains one argument per line. Empty lines and lines starting with '#' are ignored.
+// This makes it possible to use more arguments than the command line length limit of the OS allows.
+fn expand_response_files(
+    args: impl IntoIterator<Item = OsString>,
+    depth: usize,
+) -> Result<Vec<OsString>, String> {
+    // response files may reference other response files, but a loop must not cause infinite recursion
+    const MAX_DEPTH: usize = 10;
+
+    let mut expanded = Vec::new();
+    for arg in args {
+        let Some(filename) = arg.to_str().and_then(|arg| arg.strip_prefix('@')) else {
+            expanded.push(arg);
+            continue;
+        };
+        if depth >= MAX_DEPTH {
+            return Err(format!(
+                "Error: response files are nested too deeply at \"{filename}\""
+            ));
         }
-        addr_ranges
-    } else {
-        Vec::new()
+        let text = std::fs::read_to_string(filename)
+            .map_err(|err| format!("Error: could not read response file \"{filename}\": {err}"))?;
+        let file_args = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                // quotes around an argument are optional, e.g. for file names containing spaces
+                let line = line
+                    .strip_prefix('"')
+                    .and_then(|line| line.strip_suffix('"'))
+                    .unwrap_or(line);
+                OsString::from(line)
+            })
+            .collect::<Vec<_>>();
+        expanded.extend(expand_response_files(file_args, depth + 1)?);
     }
+    Ok(expanded)
 }
 
-fn section_args_to_ranges(
-    args: Option<ValuesRef<String>>,
-    debug_data: &DebugData,
-    verbose: u8,
-) -> Vec<(u64, u64)> {
-    if let Some(values) = args {
-        let mut addr_ranges: Vec<(u64, u64)> = Vec::new();
-        for section in values {
-            if let Some(range) = debug_data.sections.get(section).copied() {
-                addr_ranges.push(range);
-            } else if verbose > 0 {
-                println!("Cannot insert items from non-existent section {section}!");
-            }
-        }
-        addr_ranges
-    } else {
-        Vec::new()
-    }
+// the values of an argument that can be given several times, or an empty list if it is not present
+fn string_values<'a>(arg_matches: &'a ArgMatches, id: &str) -> Vec<&'a str> {
+    arg_matches
+        .get_many::<String>(id)
+        .map(|values| values.map(String::as_str).collect())
+        .unwrap_or_default()
+}
+
+// address ranges that are given as pairs of start and end address
+fn range_values(arg_matches: &ArgMatches, id: &str) -> Vec<(u64, u64)> {
+    let values: Vec<u64> = arg_matches
+        .get_many::<u64>(id)
+        .map(|values| values.copied().collect())
+        .unwrap_or_default();
+    values
+        .chunks_exact(2)
+        .map(|pair| (pair[0], pair[1]))
+        .collect()
 }
 
 #[derive(Clone)]
@@ -853,6 +4092,38 @@ impl clap::builder::TypedValueParser for AddressValueParser {
     }
 }
 
+// CAN ids are given as hex values, like addresses, but they must fit into 32 bits
+#[derive(Clone)]
+struct CanIdValueParser;
+
+impl clap::builder::TypedValueParser for CanIdValueParser {
+    type Value = u64;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        let can_id = AddressValueParser.parse_ref(cmd, arg, value)?;
+        if can_id > u64::from(u32::MAX) {
+            let mut err = clap::Error::new(clap::error::ErrorKind::ValueValidation).with_cmd(cmd);
+            if let Some(arg) = arg {
+                err.insert(
+                    clap::error::ContextKind::InvalidArg,
+                    clap::error::ContextValue::String(arg.to_string()),
+                );
+            }
+            err.insert(
+                clap::error::ContextKind::InvalidValue,
+                clap::error::ContextValue::String(value.to_string_lossy().to_string()),
+            );
+            return Err(err);
+        }
+        Ok(can_id)
+    }
+}
+
 #[derive(Clone, Copy)]
 struct A2lVersionParser;
 
@@ -893,32 +4164,48 @@ impl clap::builder::TypedValueParser for A2lVersionParser {
     }
 }
 
-impl From<&A2lFile> for A2lVersion {
-    fn from(a2l_file: &A2lFile) -> Self {
-        if let Some(asap2_version) = &a2l_file.asap2_version {
-            match (asap2_version.version_no, asap2_version.upgrade_no) {
-                (1, 51) => A2lVersion::V1_5_1,
-                (1, 60) => A2lVersion::V1_6_0,
-                (1, 61) => A2lVersion::V1_6_1,
-                (1, 70) => A2lVersion::V1_7_0,
-                (1, 71) => A2lVersion::V1_7_1,
-                _ => A2lVersion::V1_5_0,
-            }
-        } else {
-            A2lVersion::V1_5_0
-        }
-    }
-}
+#[cfg(test)]
+mod test {
+    use super::*;
 
-impl Display for A2lVersion {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            A2lVersion::V1_5_0 => f.write_str("1.5.0"),
-            A2lVersion::V1_5_1 => f.write_str("1.5.1"),
-            A2lVersion::V1_6_0 => f.write_str("1.6.0"),
-            A2lVersion::V1_6_1 => f.write_str("1.6.1"),
-            A2lVersion::V1_7_0 => f.write_str("1.7.0"),
-            A2lVersion::V1_7_1 => f.write_str("1.7.1"),
-        }
+    #[test]
+    fn test_expand_response_files() {
+        let response_filename = std::env::temp_dir().join("a2ltool_test_response_file.txt");
+        std::fs::write(
+            &response_filename,
+            "# update the addresses\n--update\n\n  --measurement  \n\"my var\"\n",
+        )
+        .unwrap();
+        let args = vec![
+            OsString::from("a2ltool"),
+            OsString::from("input.a2l"),
+            OsString::from(format!("@{}", response_filename.display())),
+            OsString::from("-v"),
+        ];
+        let expanded = expand_response_files(args, 0).unwrap();
+        assert_eq!(
+            expanded,
+            [
+                "a2ltool",
+                "input.a2l",
+                "--update",
+                "--measurement",
+                "my var",
+                "-v"
+            ]
+        );
+
+        // a response file that includes itself
+        std::fs::write(
+            &response_filename,
+            format!("@{}", response_filename.display()),
+        )
+        .unwrap();
+        let args = vec![OsString::from(format!("@{}", response_filename.display()))];
+        assert!(expand_response_files(args, 0).is_err());
+        std::fs::remove_file(&response_filename).unwrap();
+
+        let args = vec![OsString::from("@does_not_exist.txt")];
+        assert!(expand_response_files(args, 0).is_err());
     }
 }
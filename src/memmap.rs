@@ -0,0 +1,501 @@
+//! Memory map report: all objects of an a2l file laid out by address
+
+use a2lfile::{
+    A2lFile, AxisPts, Characteristic, CharacteristicType, DataType, DataTypeSize, Measurement,
+    Module, RecordLayout,
+};
+use std::fmt::Write;
+
+/// One object in the memory map
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryMapEntry {
+    pub address: u32,
+    /// size in bytes; 0 if the size could not be determined
+    pub size: u32,
+    pub name: String,
+    /// the block type, e.g. "MEASUREMENT"
+    pub blocktype: &'static str,
+}
+
+/// All objects whose address lies inside one MEMORY_SEGMENT
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryMapSegment {
+    /// name of the MEMORY_SEGMENT, or None for the objects that are not inside any segment
+    pub name: Option<String>,
+    pub address: u32,
+    pub size: u32,
+    /// the objects in the segment, sorted by address
+    pub entries: Vec<MemoryMapEntry>,
+}
+
+// one line of the report
+enum MapRow<'a> {
+    Object {
+        entry: &'a MemoryMapEntry,
+        overlap: bool,
+    },
+    Gap {
+        address: u32,
+        size: u64,
+    },
+}
+
+/// Build the memory map of a module.
+///
+/// The objects are assigned to the MEMORY_SEGMENT that contains their start address.
+/// Objects outside of all segments are collected in an additional segment without a name, which is always last.
+/// The sizes of CHARACTERISTICs and AXIS_PTS are calculated from their RECORD_LAYOUTs.
+#[must_use]
+pub fn build_memory_map(module: &Module) -> Vec<MemoryMapSegment> {
+    let mut segments: Vec<MemoryMapSegment> = module
+        .mod_par
+        .iter()
+        .flat_map(|mod_par| &mod_par.memory_segment)
+        .map(|memory_segment| MemoryMapSegment {
+            name: Some(memory_segment.name.clone()),
+            address: memory_segment.address,
+            size: memory_segment.size,
+            entries: Vec::new(),
+        })
+        .collect();
+    let mut outside = MemoryMapSegment {
+        name: None,
+        address: 0,
+        size: 0,
+        entries: Vec::new(),
+    };
+
+    for entry in module_entries(module) {
+        let address = u64::from(entry.address);
+        let segment = segments.iter_mut().find(|segment| {
+            let start = u64::from(segment.address);
+            start <= address && address < start + u64::from(segment.size)
+        });
+        match segment {
+            Some(segment) => segment.entries.push(entry),
+            None => outside.entries.push(entry),
+        }
+    }
+
+    if !outside.entries.is_empty() {
+        segments.push(outside);
+    }
+    for segment in &mut segments {
+        segment
+            .entries
+            .sort_by(|a, b| (a.address, &a.name).cmp(&(b.address, &b.name)));
+    }
+    segments
+}
+
+/// Generate a memory map report for all modules of the a2l file, either as plain text or as an HTML page.
+///
+/// Unused space inside MEMORY_SEGMENTs is listed as a gap, and objects that overlap a previous object are highlighted.
+#[must_use]
+pub fn memory_map_report(a2l_file: &A2lFile, html: bool) -> String {
+    let mut report = String::new();
+    if html {
+        let _ = writeln!(
+            report,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">"
+        );
+        let _ = writeln!(report, "<title>Memory map</title>\n<style>");
+        let _ = writeln!(
+            report,
+            "table {{ border-collapse: collapse; margin-bottom: 2em; }}"
+        );
+        let _ = writeln!(
+            report,
+            "td, th {{ border: 1px solid #ccc; padding: 2px 8px; font-family: monospace; }}"
+        );
+        let _ = writeln!(report, "tr.gap {{ background-color: #eee; color: #666; }}");
+        let _ = writeln!(report, "tr.overlap {{ background-color: #fcc; }}");
+        let _ = writeln!(report, "</style>\n</head>\n<body>");
+    }
+    for module in &a2l_file.project.module {
+        let segments = build_memory_map(module);
+        if html {
+            let _ = writeln!(report, "<h1>Module {}</h1>", html_escape(&module.name));
+        } else {
+            let _ = writeln!(report, "Module {}", module.name);
+        }
+        for segment in &segments {
+            if html {
+                write_segment_html(&mut report, segment);
+            } else {
+                write_segment_text(&mut report, segment);
+            }
+        }
+    }
+    if html {
+        let _ = writeln!(report, "</body>\n</html>");
+    }
+    report
+}
+
+fn write_segment_text(report: &mut String, segment: &MemoryMapSegment) {
+    let (rows, used) = segment_rows(segment);
+    let _ = writeln!(report);
+    let _ = writeln!(report, "{}", segment_title(segment, used));
+    for row in rows {
+        match row {
+            MapRow::Object { entry, overlap } => {
+                let marker = if overlap { "  OVERLAP" } else { "" };
+                let _ = writeln!(
+                    report,
+                    "  0x{:08X}  {:>8}  {:<15} {}{marker}",
+                    entry.address, entry.size, entry.blocktype, entry.name
+                );
+            }
+            MapRow::Gap { address, size } => {
+                let _ = writeln!(report, "  0x{address:08X}  {size:>8}  <gap>");
+            }
+        }
+    }
+}
+
+fn write_segment_html(report: &mut String, segment: &MemoryMapSegment) {
+    let (rows, used) = segment_rows(segment);
+    let _ = writeln!(
+        report,
+        "<h2>{}</h2>",
+        html_escape(&segment_title(segment, used))
+    );
+    let _ = writeln!(report, "<table>");
+    let _ = writeln!(
+        report,
+        "<tr><th>Address</th><th>Size</th><th>Type</th><th>Name</th></tr>"
+    );
+    for row in rows {
+        match row {
+            MapRow::Object { entry, overlap } => {
+                let class = if overlap { " class=\"overlap\"" } else { "" };
+                let _ = writeln!(
+                    report,
+                    "<tr{class}><td>0x{:08X}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    entry.address,
+                    entry.size,
+                    entry.blocktype,
+                    html_escape(&entry.name)
+                );
+            }
+            MapRow::Gap { address, size } => {
+                let _ = writeln!(
+                    report,
+                    "<tr class=\"gap\"><td>0x{address:08X}</td><td>{size}</td><td></td><td>unused</td></tr>"
+                );
+            }
+        }
+    }
+    let _ = writeln!(report, "</table>");
+}
+
+fn segment_title(segment: &MemoryMapSegment, used: u64) -> String {
+    if let Some(name) = &segment.name {
+        let end = u64::from(segment.address) + u64::from(segment.size);
+        let percent = if segment.size > 0 {
+            used as f64 * 100.0 / f64::from(segment.size)
+        } else {
+            0.0
+        };
+        format!(
+            "MEMORY_SEGMENT {name}: 0x{:08X} - 0x{end:08X}, {} bytes, {used} bytes used ({percent:.1}%)",
+            segment.address, segment.size
+        )
+    } else {
+        format!("Outside of all MEMORY_SEGMENTs: {used} bytes used")
+    }
+}
+
+// interleave the objects of a segment with the gaps between them, and mark overlapping objects.
+// Also returns the number of bytes that are used by at least one object
+fn segment_rows(segment: &MemoryMapSegment) -> (Vec<MapRow<'_>>, u64) {
+    let mut rows = Vec::new();
+    let mut used = 0;
+    // gaps are only meaningful inside of a MEMORY_SEGMENT
+    let show_gaps = segment.name.is_some();
+    let mut covered_end = u64::from(segment.address);
+    let mut first = true;
+    for entry in &segment.entries {
+        let start = u64::from(entry.address);
+        let end = start + u64::from(entry.size);
+        if first && !show_gaps {
+            covered_end = start;
+        }
+        first = false;
+        if start > covered_end && show_gaps {
+            rows.push(MapRow::Gap {
+                address: covered_end as u32,
+                size: start - covered_end,
+            });
+        }
+        let overlap = start < covered_end;
+        used += end.saturating_sub(start.max(covered_end));
+        covered_end = covered_end.max(end);
+        rows.push(MapRow::Object { entry, overlap });
+    }
+    let segment_end = u64::from(segment.address) + u64::from(segment.size);
+    if show_gaps && covered_end < segment_end {
+        rows.push(MapRow::Gap {
+            address: covered_end as u32,
+            size: segment_end - covered_end,
+        });
+    }
+    (rows, used)
+}
+
+// collect all objects of the module that have an address
+fn module_entries(module: &Module) -> Vec<MemoryMapEntry> {
+    let mut entries = Vec::new();
+    for measurement in &module.measurement {
+        if let Some(ecu_address) = &measurement.ecu_address {
+            entries.push(MemoryMapEntry {
+                address: ecu_address.address,
+                size: measurement_size(measurement),
+                name: measurement.name.clone(),
+                blocktype: "MEASUREMENT",
+            });
+        }
+    }
+    for characteristic in &module.characteristic {
+        entries.push(MemoryMapEntry {
+            address: characteristic.address,
+            size: characteristic_size(module, characteristic),
+            name: characteristic.name.clone(),
+            blocktype: "CHARACTERISTIC",
+        });
+    }
+    for axis_pts in &module.axis_pts {
+        entries.push(MemoryMapEntry {
+            address: axis_pts.address,
+            size: axis_pts_size(module, axis_pts),
+            name: axis_pts.name.clone(),
+            blocktype: "AXIS_PTS",
+        });
+    }
+    for blob in &module.blob {
+        entries.push(MemoryMapEntry {
+            address: blob.start_address,
+            size: blob.size,
+            name: blob.name.clone(),
+            blocktype: "BLOB",
+        });
+    }
+    for instance in &module.instance {
+        let count = instance
+            .matrix_dim
+            .as_ref()
+            .map_or(1, |matrix_dim| dim_product(&matrix_dim.dim_list));
+        entries.push(MemoryMapEntry {
+            address: instance.start_address,
+            size: typedef_size(module, &instance.type_ref) * count,
+            name: instance.name.clone(),
+            blocktype: "INSTANCE",
+        });
+    }
+    entries
+}
+
+pub(crate) fn datatype_size(datatype: DataType) -> u32 {
+    match datatype {
+        DataType::Ubyte | DataType::Sbyte => 1,
+        DataType::Uword | DataType::Sword | DataType::Float16Ieee => 2,
+        DataType::Ulong | DataType::Slong | DataType::Float32Ieee => 4,
+        DataType::AUint64 | DataType::AInt64 | DataType::Float64Ieee => 8,
+    }
+}
+
+fn dim_product(dim_list: &[u16]) -> u32 {
+    dim_list.iter().map(|dim| u32::from(*dim)).product()
+}
+
+pub(crate) fn measurement_size(measurement: &Measurement) -> u32 {
+    let count = if let Some(matrix_dim) = &measurement.matrix_dim {
+        dim_product(&matrix_dim.dim_list)
+    } else if let Some(array_size) = &measurement.array_size {
+        u32::from(array_size.number)
+    } else {
+        1
+    };
+    datatype_size(measurement.datatype) * count
+}
+
+pub(crate) fn characteristic_size(module: &Module, characteristic: &Characteristic) -> u32 {
+    let block_count = if let Some(matrix_dim) = &characteristic.matrix_dim {
+        dim_product(&matrix_dim.dim_list)
+    } else if let Some(number) = &characteristic.number {
+        u32::from(number.number)
+    } else {
+        1
+    };
+    if characteristic.characteristic_type == CharacteristicType::Ascii {
+        return block_count;
+    }
+    let Some(record_layout) = find_record_layout(module, &characteristic.deposit) else {
+        return 0;
+    };
+    let axis_points: Vec<u32> = characteristic
+        .axis_descr
+        .iter()
+        .map(|axis_descr| u32::from(axis_descr.max_axis_points))
+        .collect();
+    let value_count = match characteristic.characteristic_type {
+        CharacteristicType::Value => 1,
+        CharacteristicType::ValBlk => block_count,
+        _ => axis_points.iter().product(),
+    };
+    record_layout_size(record_layout, &axis_points, value_count)
+}
+
+pub(crate) fn axis_pts_size(module: &Module, axis_pts: &AxisPts) -> u32 {
+    find_record_layout(module, &axis_pts.deposit_record).map_or(0, |record_layout| {
+        record_layout_size(record_layout, &[u32::from(axis_pts.max_axis_points)], 0)
+    })
+}
+
+fn typedef_size(module: &Module, type_ref: &str) -> u32 {
+    if let Some(typedef) = module.typedef_structure.iter().find(|t| t.name == type_ref) {
+        typedef.total_size
+    } else if let Some(typedef) = module.typedef_blob.iter().find(|t| t.name == type_ref) {
+        typedef.size
+    } else if let Some(typedef) = module
+        .typedef_measurement
+        .iter()
+        .find(|t| t.name == type_ref)
+    {
+        let count = typedef
+            .matrix_dim
+            .as_ref()
+            .map_or(1, |matrix_dim| dim_product(&matrix_dim.dim_list));
+        datatype_size(typedef.datatype) * count
+    } else {
+        0
+    }
+}
+
+fn find_record_layout<'a>(module: &'a Module, name: &str) -> Option<&'a RecordLayout> {
+    module.record_layout.iter().find(|rl| rl.name == name)
+}
+
+// the size of the data described by a record layout. Alignment is not taken into account
+fn record_layout_size(record_layout: &RecordLayout, axis_points: &[u32], value_count: u32) -> u32 {
+    let mut size = 0;
+    if let Some(fnc_values) = &record_layout.fnc_values {
+        size += datatype_size(fnc_values.datatype) * value_count;
+    }
+    let axis_pts_dims = [
+        &record_layout.axis_pts_x,
+        &record_layout.axis_pts_y,
+        &record_layout.axis_pts_z,
+        &record_layout.axis_pts_4,
+        &record_layout.axis_pts_5,
+    ];
+    for (idx, axis_pts_dim) in axis_pts_dims.iter().enumerate() {
+        if let Some(axis_pts_dim) = axis_pts_dim {
+            size +=
+                datatype_size(axis_pts_dim.datatype) * axis_points.get(idx).copied().unwrap_or(1);
+        }
+    }
+    let no_axis_pts_dims = [
+        &record_layout.no_axis_pts_x,
+        &record_layout.no_axis_pts_y,
+        &record_layout.no_axis_pts_z,
+        &record_layout.no_axis_pts_4,
+        &record_layout.no_axis_pts_5,
+    ];
+    for no_axis_pts_dim in no_axis_pts_dims.into_iter().flatten() {
+        size += datatype_size(no_axis_pts_dim.datatype);
+    }
+    for reserved in &record_layout.reserved {
+        size += match reserved.data_size {
+            DataTypeSize::Byte => 1,
+            DataTypeSize::Word => 2,
+            DataTypeSize::Long => 4,
+        };
+    }
+    size
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_memory_map() {
+        let a2l_text = r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin MOD_PAR ""
+                    /begin MEMORY_SEGMENT cal "" DATA FLASH INTERN 0x1000 0x100 -1 -1 -1 -1 -1
+                    /end MEMORY_SEGMENT
+                /end MOD_PAR
+                /begin MEASUREMENT m1 "" ULONG NO_COMPU_METHOD 0 0 0 255
+                    ECU_ADDRESS 0x5000
+                /end MEASUREMENT
+                /begin CHARACTERISTIC c1 "" VALUE 0x1000 RL_UWORD 0 NO_COMPU_METHOD 0 255
+                /end CHARACTERISTIC
+                /begin CHARACTERISTIC c2 "" VAL_BLK 0x1010 RL_UWORD 0 NO_COMPU_METHOD 0 255
+                    MATRIX_DIM 4
+                /end CHARACTERISTIC
+                /begin CHARACTERISTIC c3 "" CURVE 0x1014 RL_CURVE 0 NO_COMPU_METHOD 0 255
+                    /begin AXIS_DESCR STD_AXIS NO_INPUT_QUANTITY NO_COMPU_METHOD 3 0 255
+                    /end AXIS_DESCR
+                /end CHARACTERISTIC
+                /begin RECORD_LAYOUT RL_UWORD
+                    FNC_VALUES 1 UWORD COLUMN_DIR DIRECT
+                /end RECORD_LAYOUT
+                /begin RECORD_LAYOUT RL_CURVE
+                    NO_AXIS_PTS_X 1 UBYTE
+                    AXIS_PTS_X 2 UBYTE INDEX_INCR DIRECT
+                    FNC_VALUES 3 UWORD COLUMN_DIR DIRECT
+                /end RECORD_LAYOUT
+            /end MODULE
+        /end PROJECT"#;
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(a2l_text, None, &mut log_msgs, false).unwrap();
+
+        let segments = build_memory_map(&a2l_file.project.module[0]);
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].name.as_deref(), Some("cal"));
+        assert_eq!(segments[0].entries.len(), 3);
+        assert_eq!(segments[0].entries[0].size, 2);
+        assert_eq!(segments[0].entries[1].size, 8);
+        // 1 byte NO_AXIS_PTS_X + 3 bytes AXIS_PTS_X + 3 * 2 bytes FNC_VALUES
+        assert_eq!(segments[0].entries[2].size, 10);
+        assert_eq!(segments[1].name, None);
+        assert_eq!(segments[1].entries[0].name, "m1");
+
+        // c2 (0x1010 - 0x1017) overlaps c3 (0x1014 - 0x101D)
+        let (rows, used) = segment_rows(&segments[0]);
+        assert_eq!(used, 2 + 14);
+        assert!(matches!(
+            rows[1],
+            MapRow::Gap {
+                address: 0x1002,
+                size: 14
+            }
+        ));
+        assert!(matches!(rows[3], MapRow::Object { overlap: true, .. }));
+        assert!(matches!(
+            rows[4],
+            MapRow::Gap {
+                address: 0x101E,
+                ..
+            }
+        ));
+
+        let text = memory_map_report(&a2l_file, false);
+        assert!(text.contains("MEMORY_SEGMENT cal"));
+        assert!(text.contains("OVERLAP"));
+        let html = memory_map_report(&a2l_file, true);
+        assert!(html.contains("<tr class=\"overlap\">"));
+    }
+}
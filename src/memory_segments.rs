@@ -0,0 +1,343 @@
+//! Creation of MEMORY_SEGMENTs from the program headers and sections of an elf file
+
+use crate::dwarf::{DebugData, MemoryFlags};
+use crate::modpar::get_or_create_mod_par;
+use crate::query::compile_regex;
+use a2lfile::{A2lFile, MemoryAttribute, MemorySegment, MemoryType, PrgType};
+use regex::Regex;
+use std::ffi::OsStr;
+
+/// a rule that combines all matching elf sections into one MEMORY_SEGMENT
+#[derive(Debug)]
+struct SegmentRule {
+    section_regex: Regex,
+    name: String,
+    // None: derived from the section flags
+    prg_type: Option<PrgType>,
+    memory_type: Option<MemoryType>,
+}
+
+/// user configurable names and types of the MEMORY_SEGMENTs created by [`create_memory_segments`]
+#[derive(Debug, Default)]
+pub struct SegmentConfig {
+    rules: Vec<SegmentRule>,
+}
+
+impl SegmentConfig {
+    /// load the segment rules from a config file.
+    /// The file contains one rule per line, lines starting with '#' are comments.
+    /// Each rule has the form `<SECTION_REGEX> <NAME> [<PRG_TYPE> [<MEMORY_TYPE>]]`, e.g. `\.cal_.* CALIBRATION DATA FLASH`.
+    /// If the types are missing or `AUTO`, they are derived from the flags of the matching sections.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the file cannot be read or contains an invalid rule
+    pub fn load(filename: &OsStr) -> Result<Self, String> {
+        let text = std::fs::read_to_string(filename).map_err(|err| {
+            format!(
+                "Error: could not read memory segment config {}: {err}",
+                filename.to_string_lossy()
+            )
+        })?;
+        Self::parse(&text).map_err(|err| {
+            format!(
+                "Error: invalid memory segment config {}: {err}",
+                filename.to_string_lossy()
+            )
+        })
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut config = SegmentConfig::default();
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() < 2 || parts.len() > 4 {
+                return Err(format!(
+                    "line {}: expected <SECTION_REGEX> <NAME> [<PRG_TYPE> [<MEMORY_TYPE>]]",
+                    idx + 1
+                ));
+            }
+            let section_regex =
+                compile_regex(parts[0]).map_err(|err| format!("line {}: {err}", idx + 1))?;
+            if !crate::rename::is_valid_identifier(parts[1]) {
+                return Err(format!(
+                    "line {}: {} is not a valid MEMORY_SEGMENT name",
+                    idx + 1,
+                    parts[1]
+                ));
+            }
+            let prg_type = match parts.get(2) {
+                None => None,
+                Some(text) => parse_prg_type(text)
+                    .ok_or_else(|| format!("line {}: unknown PRG_TYPE {text}", idx + 1))?,
+            };
+            let memory_type = match parts.get(3) {
+                None => None,
+                Some(text) => parse_memory_type(text)
+                    .ok_or_else(|| format!("line {}: unknown MEMORY_TYPE {text}", idx + 1))?,
+            };
+            config.rules.push(SegmentRule {
+                section_regex,
+                name: parts[1].to_string(),
+                prg_type,
+                memory_type,
+            });
+        }
+        Ok(config)
+    }
+}
+
+// the outer option is None if the text is invalid; the inner option is None for AUTO
+fn parse_prg_type(text: &str) -> Option<Option<PrgType>> {
+    let prg_type = match text.to_ascii_uppercase().as_str() {
+        "AUTO" => return Some(None),
+        "CODE" | "PRG_CODE" => PrgType::Code,
+        "DATA" | "PRG_DATA" => PrgType::Data,
+        "RESERVED" | "PRG_RESERVED" => PrgType::Reserved,
+        "OFFLINE_DATA" => PrgType::OfflineData,
+        "VARIABLES" => PrgType::Variables,
+        "SERAM" => PrgType::Seram,
+        "CALIBRATION_VARIABLES" => PrgType::CalibrationVariables,
+        "EXCLUDE_FROM_FLASH" => PrgType::ExcludeFromFlash,
+        _ => return None,
+    };
+    Some(Some(prg_type))
+}
+
+fn parse_memory_type(text: &str) -> Option<Option<MemoryType>> {
+    let memory_type = match text.to_ascii_uppercase().as_str() {
+        "AUTO" => return Some(None),
+        "EEPROM" => MemoryType::Eeprom,
+        "EPROM" => MemoryType::Eprom,
+        "FLASH" => MemoryType::Flash,
+        "RAM" => MemoryType::Ram,
+        "ROM" => MemoryType::Rom,
+        "REGISTER" => MemoryType::Register,
+        "NOT_IN_ECU" => MemoryType::NotInEcu,
+        _ => return None,
+    };
+    Some(Some(memory_type))
+}
+
+// code is placed in flash, writable data in RAM, and everything else is constant data in flash
+fn classify(flags: MemoryFlags) -> (PrgType, MemoryType) {
+    if flags.executable {
+        (PrgType::Code, MemoryType::Flash)
+    } else if flags.writable {
+        (PrgType::Variables, MemoryType::Ram)
+    } else {
+        (PrgType::Data, MemoryType::Flash)
+    }
+}
+
+/// Create a MEMORY_SEGMENT in the MOD_PAR of each module for the memory regions of the elf file.
+///
+/// Without a config there is one segment for each loadable program header, named after the first section it contains.
+/// With a config, each rule creates one segment that spans all sections matching its regex, and
+/// sections without a matching rule are ignored.
+/// Existing segments with the same name are updated.
+///
+/// Returns the number of segments that were created or updated.
+pub fn create_memory_segments(
+    a2l_file: &mut A2lFile,
+    debug_data: &DebugData,
+    config: Option<&SegmentConfig>,
+    log_msgs: &mut Vec<String>,
+) -> usize {
+    let segments = if let Some(config) = config {
+        segments_from_config(debug_data, config, log_msgs)
+    } else {
+        segments_from_program_headers(debug_data)
+    };
+
+    let mut count = 0;
+    for module in &mut a2l_file.project.module {
+        let mod_par = get_or_create_mod_par(module);
+        for (name, description, start, end, prg_type, memory_type) in &segments {
+            let (Ok(address), Ok(size)) = (u32::try_from(*start), u32::try_from(end - start))
+            else {
+                log_msgs.push(format!(
+                    "MEMORY_SEGMENT {name} at 0x{start:X} cannot be represented with 32-bit addresses"
+                ));
+                continue;
+            };
+            if let Some(segment) = mod_par
+                .memory_segment
+                .iter_mut()
+                .find(|segment| &segment.name == name)
+            {
+                segment.prg_type = *prg_type;
+                segment.memory_type = *memory_type;
+                segment.address = address;
+                segment.size = size;
+            } else {
+                mod_par.memory_segment.push(MemorySegment::new(
+                    name.clone(),
+                    description.clone(),
+                    *prg_type,
+                    *memory_type,
+                    MemoryAttribute::Intern,
+                    address,
+                    size,
+                    [-1; 5],
+                ));
+            }
+            count += 1;
+        }
+    }
+    count
+}
+
+type SegmentInfo = (String, String, u64, u64, PrgType, MemoryType);
+
+fn segments_from_program_headers(debug_data: &DebugData) -> Vec<SegmentInfo> {
+    let mut sections: Vec<(&String, u64)> = debug_data
+        .sections
+        .iter()
+        .map(|(name, (start, _))| (name, *start))
+        .collect();
+    sections.sort_by_key(|(name, start)| (*start, *name));
+
+    let mut load_segments = debug_data.load_segments.clone();
+    load_segments.sort_by_key(|(start, _, _)| *start);
+    let mut segments: Vec<SegmentInfo> = Vec::new();
+    for (idx, (start, end, flags)) in load_segments.into_iter().enumerate() {
+        let contained: Vec<&str> = sections
+            .iter()
+            .filter(|(_, section_start)| start <= *section_start && *section_start < end)
+            .map(|(name, _)| name.as_str())
+            .collect();
+        let mut name = contained
+            .first()
+            .map(|section| make_segment_name(section))
+            .unwrap_or_else(|| format!("SEGMENT_{idx}"));
+        // segment names must be unique
+        if segments.iter().any(|segment| segment.0 == name) {
+            name = format!("{name}_{idx}");
+        }
+        let (prg_type, memory_type) = classify(flags);
+        segments.push((
+            name,
+            format!("sections {}", contained.join(" ")),
+            start,
+            end,
+            prg_type,
+            memory_type,
+        ));
+    }
+    segments
+}
+
+fn segments_from_config(
+    debug_data: &DebugData,
+    config: &SegmentConfig,
+    log_msgs: &mut Vec<String>,
+) -> Vec<SegmentInfo> {
+    let mut section_names: Vec<&String> = debug_data.sections.keys().collect();
+    section_names.sort_by_key(|name| debug_data.sections[*name]);
+    let mut segments = Vec::new();
+    for rule in &config.rules {
+        let matching: Vec<&str> = section_names
+            .iter()
+            .filter(|name| rule.section_regex.is_match(name))
+            .map(|name| name.as_str())
+            .collect();
+        if matching.is_empty() {
+            log_msgs.push(format!(
+                "No elf section matches the rule for MEMORY_SEGMENT {}",
+                rule.name
+            ));
+            continue;
+        }
+        let start = matching
+            .iter()
+            .map(|name| debug_data.sections[*name].0)
+            .min()
+            .unwrap_or_default();
+        let end = matching
+            .iter()
+            .map(|name| debug_data.sections[*name].1)
+            .max()
+            .unwrap_or_default();
+        // the section flags are combined: the segment is writable or executable if any of its sections are
+        let flags = matching
+            .iter()
+            .filter_map(|name| debug_data.section_flags.get(*name))
+            .fold(MemoryFlags::default(), |acc, flags| MemoryFlags {
+                writable: acc.writable || flags.writable,
+                executable: acc.executable || flags.executable,
+            });
+        let (auto_prg_type, auto_memory_type) = classify(flags);
+        segments.push((
+            rule.name.clone(),
+            format!("sections {}", matching.join(" ")),
+            start,
+            end,
+            rule.prg_type.unwrap_or(auto_prg_type),
+            rule.memory_type.unwrap_or(auto_memory_type),
+        ));
+    }
+    segments
+}
+
+// derive a valid segment name from the name of an elf section: ".text" becomes "text"
+fn make_segment_name(section_name: &str) -> String {
+    let mut name: String = section_name
+        .trim_start_matches('.')
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_create_memory_segments() {
+        assert!(SegmentConfig::parse(r"\.text").is_err());
+        assert!(SegmentConfig::parse(r"\.text CODE_SEG PROGRAM").is_err());
+        assert!(SegmentConfig::parse(r"\.text 1CODE").is_err());
+
+        let debug_data = DebugData::load(
+            std::ffi::OsStr::new("tests/elffiles/update_test.elf"),
+            false,
+        )
+        .unwrap();
+        let mut a2l_file = crate::load::new_a2l();
+        let mut log_msgs = Vec::new();
+        let count = create_memory_segments(&mut a2l_file, &debug_data, None, &mut log_msgs);
+        assert_eq!(count, 2);
+        let mod_par = a2l_file.project.module[0].mod_par.as_ref().unwrap();
+        assert_eq!(mod_par.memory_segment[0].name, "text");
+        assert_eq!(mod_par.memory_segment[0].prg_type, PrgType::Code);
+        assert_eq!(mod_par.memory_segment[1].name, "data");
+        assert_eq!(mod_par.memory_segment[1].prg_type, PrgType::Variables);
+        assert_eq!(mod_par.memory_segment[1].memory_type, MemoryType::Ram);
+
+        let config = SegmentConfig::parse(
+            "# comment\n\\.text code PRG_CODE\n\\.(data|bss) variables AUTO FLASH\n\\.missing none\n",
+        )
+        .unwrap();
+        let count =
+            create_memory_segments(&mut a2l_file, &debug_data, Some(&config), &mut log_msgs);
+        assert_eq!(count, 2);
+        assert_eq!(log_msgs.len(), 1);
+        let mod_par = a2l_file.project.module[0].mod_par.as_ref().unwrap();
+        assert_eq!(mod_par.memory_segment.len(), 4);
+        let variables = &mod_par.memory_segment[3];
+        assert_eq!(variables.name, "variables");
+        assert_eq!(variables.prg_type, PrgType::Variables);
+        assert_eq!(variables.memory_type, MemoryType::Flash);
+        assert_eq!(variables.long_identifier, "sections .data .bss");
+        assert_eq!(variables.size, 0x134);
+    }
+}
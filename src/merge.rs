@@ -0,0 +1,72 @@
+//! Merging of a2l files
+
+use a2lfile::{A2lError, A2lFile};
+use std::ffi::OsStr;
+
+/// Merge the content of another a2l file into the modules of `a2l_file`.
+///
+/// Objects are merged at the module level: if both files contain an object with the same name,
+/// then the object in `a2l_file` is kept. The merge file may also be an a2l fragment.
+///
+/// # Errors
+///
+/// Returns an error message if the merge file cannot be loaded
+pub fn merge_module_file(
+    a2l_file: &mut A2lFile,
+    merge_filename: &OsStr,
+    strict: bool,
+) -> Result<(), String> {
+    let mut merge_log_msgs = Vec::<A2lError>::new();
+    let mergeresult = a2lfile::load(merge_filename, None, &mut merge_log_msgs, strict);
+    if let Ok(mut merge_a2l) = mergeresult {
+        a2l_file.merge_modules(&mut merge_a2l);
+        Ok(())
+    } else if let Ok(mut other_module) = a2lfile::load_fragment_file(merge_filename) {
+        a2l_file.project.module[0].merge(&mut other_module);
+        Ok(())
+    } else {
+        Err(format!(
+            "Failed to load \"{}\" for merging: {}\n",
+            merge_filename.to_string_lossy(),
+            mergeresult.unwrap_err()
+        ))
+    }
+}
+
+/// Merge another a2l file at the project level.
+///
+/// All modules of the merge file are added to the project of `a2l_file`.
+///
+/// # Errors
+///
+/// Returns an error message if the merge file cannot be loaded
+pub fn merge_project_file(
+    a2l_file: &mut A2lFile,
+    merge_filename: &OsStr,
+    strict: bool,
+) -> Result<(), String> {
+    let mut merge_log_msgs = Vec::<A2lError>::new();
+    let merge_a2l = a2lfile::load(merge_filename, None, &mut merge_log_msgs, strict)
+        .map_err(|a2lerr| a2lerr.to_string())?;
+
+    a2l_file.project.module.extend(merge_a2l.project.module);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_merge() {
+        let mut a2l_file = crate::load::new_a2l();
+        merge_module_file(&mut a2l_file, OsStr::new("tests/update_test1.a2l"), false).unwrap();
+        assert_eq!(a2l_file.project.module.len(), 1);
+        assert!(!a2l_file.project.module[0].instance.is_empty());
+
+        merge_project_file(&mut a2l_file, OsStr::new("tests/update_test1.a2l"), false).unwrap();
+        assert_eq!(a2l_file.project.module.len(), 2);
+
+        assert!(merge_module_file(&mut a2l_file, OsStr::new("does_not_exist.a2l"), false).is_err());
+    }
+}
@@ -0,0 +1,402 @@
+//! Application of object metadata (units, descriptions, formats, limits, conversions) from a CSV file
+
+use crate::query::compile_regex;
+use crate::A2lVersion;
+use a2lfile::{A2lFile, Format, Module, PhysUnit};
+use regex::Regex;
+
+/// Selects the objects that a row of the metadata file applies to
+#[derive(Debug, Clone)]
+pub enum MetadataSelector {
+    /// the object with exactly this name
+    Name(String),
+    /// all objects whose name fully matches the regex
+    Regex(Regex),
+}
+
+/// One row of a metadata file. Fields that are `None` are not changed.
+#[derive(Debug, Clone)]
+pub struct MetadataRow {
+    /// line number in the file
+    pub line: usize,
+    pub selector: MetadataSelector,
+    /// PHYS_UNIT
+    pub unit: Option<String>,
+    /// the long identifier
+    pub description: Option<String>,
+    /// FORMAT
+    pub format: Option<String>,
+    pub lower_limit: Option<f64>,
+    pub upper_limit: Option<f64>,
+    /// name of the COMPU_METHOD
+    pub conversion: Option<String>,
+}
+
+/// Parse a metadata file in CSV format.
+///
+/// The first line is a header that names the columns. The columns `name` or `regex` select the objects,
+/// and the columns `unit`, `description`, `format`, `lower_limit`, `upper_limit` and `conversion` contain
+/// the new values. Unknown columns are ignored, and empty cells leave the value unchanged.
+/// The fields may be separated by commas, semicolons or tabs, and may be quoted with `"`.
+///
+/// # Errors
+///
+/// Returns an error message if the header does not contain a `name` or `regex` column, or if a line is invalid
+pub fn parse_metadata_csv(text: &str) -> Result<Vec<MetadataRow>, String> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+    let Some((_, header_line)) = lines.next() else {
+        return Ok(Vec::new());
+    };
+    let header_line = header_line.trim_start_matches('\u{feff}');
+    let delimiter = [',', ';', '\t']
+        .into_iter()
+        .max_by_key(|delim| header_line.matches(*delim).count())
+        .unwrap_or(',');
+    let header: Vec<String> = split_csv_line(header_line, delimiter)
+        .ok_or_else(|| format!("Error: invalid header line in metadata file: \"{header_line}\""))?
+        .iter()
+        .map(|column| column.trim().to_ascii_lowercase())
+        .collect();
+    let column = |name: &str| header.iter().position(|col| col == name);
+    let name_col = column("name");
+    let regex_col = column("regex");
+    if name_col.is_none() && regex_col.is_none() {
+        return Err(
+            "Error: the header of the metadata file must contain a column \"name\" or \"regex\""
+                .to_string(),
+        );
+    }
+    let unit_col = column("unit");
+    let description_col = column("description");
+    let format_col = column("format");
+    let lower_limit_col = column("lower_limit");
+    let upper_limit_col = column("upper_limit");
+    let conversion_col = column("conversion");
+
+    let mut rows = Vec::new();
+    for (idx, line) in lines {
+        let line_num = idx + 1;
+        let fields = split_csv_line(line, delimiter).ok_or_else(|| {
+            format!("Error: unterminated quote on line {line_num} of the metadata file")
+        })?;
+        let get = |col: Option<usize>| {
+            col.and_then(|c| fields.get(c))
+                .map(|field| field.trim())
+                .filter(|field| !field.is_empty())
+        };
+        let selector = if let Some(name) = get(name_col) {
+            MetadataSelector::Name(name.to_string())
+        } else if let Some(regex) = get(regex_col) {
+            MetadataSelector::Regex(compile_regex(regex)?)
+        } else {
+            return Err(format!(
+                "Error: line {line_num} of the metadata file contains neither a name nor a regex"
+            ));
+        };
+        let parse_limit = |col: Option<usize>| {
+            get(col)
+                .map(|value| {
+                    value.parse::<f64>().map_err(|_| {
+                        format!("Error: invalid limit \"{value}\" on line {line_num} of the metadata file")
+                    })
+                })
+                .transpose()
+        };
+        let format = get(format_col).map(str::to_string);
+        if let Some(format) = &format {
+            if !is_valid_format(format) {
+                return Err(format!(
+                    "Error: invalid format \"{format}\" on line {line_num} of the metadata file; expected e.g. \"%8.3\""
+                ));
+            }
+        }
+        rows.push(MetadataRow {
+            line: line_num,
+            selector,
+            unit: get(unit_col).map(str::to_string),
+            description: get(description_col).map(str::to_string),
+            format,
+            lower_limit: parse_limit(lower_limit_col)?,
+            upper_limit: parse_limit(upper_limit_col)?,
+            conversion: get(conversion_col).map(str::to_string),
+        });
+    }
+    Ok(rows)
+}
+
+// split a line of a CSV file into fields. Quoted fields may contain the delimiter, and "" inside a quoted field is a literal quote
+fn split_csv_line(line: &str, delimiter: char) -> Option<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    field.push('"');
+                    chars.next();
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                field.push(c);
+            }
+        } else if c == '"' && field.trim().is_empty() {
+            field.clear();
+            in_quotes = true;
+        } else if c == delimiter {
+            fields.push(std::mem::take(&mut field));
+        } else {
+            field.push(c);
+        }
+    }
+    if in_quotes {
+        return None;
+    }
+    fields.push(field);
+    Some(fields)
+}
+
+// a FORMAT has the form %Length.Layout, e.g. %8.3
+fn is_valid_format(format: &str) -> bool {
+    let Some(rest) = format.strip_prefix('%') else {
+        return false;
+    };
+    let (length, layout) = rest.split_once('.').unwrap_or((rest, "0"));
+    length.chars().all(|c| c.is_ascii_digit())
+        && !layout.is_empty()
+        && layout.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Write the metadata into the matching MEASUREMENTs, CHARACTERISTICs and AXIS_PTS.
+///
+/// Rows that don't match any object, and values that cannot be applied (e.g. a reference to a COMPU_METHOD
+/// that does not exist), are reported in `log_msgs`. If several rows match an object, they are applied in order.
+/// Returns the number of objects that were updated.
+pub fn apply_metadata(
+    a2l_file: &mut A2lFile,
+    rows: &[MetadataRow],
+    log_msgs: &mut Vec<String>,
+) -> usize {
+    let version = A2lVersion::from(&*a2l_file);
+    let mut updated = std::collections::HashSet::new();
+    for row in rows {
+        let mut matched = false;
+        for module in &mut a2l_file.project.module {
+            let names = matching_names(module, &row.selector);
+            for name in names {
+                matched = true;
+                if apply_row(module, &name, row, version, log_msgs) {
+                    updated.insert((module.name.clone(), name));
+                }
+            }
+        }
+        if !matched {
+            let selector = match &row.selector {
+                MetadataSelector::Name(name) => name.clone(),
+                MetadataSelector::Regex(regex) => format!("regex {}", regex.as_str()),
+            };
+            log_msgs.push(format!(
+                "Line {}: {selector} does not match any MEASUREMENT, CHARACTERISTIC or AXIS_PTS",
+                row.line
+            ));
+        }
+    }
+    updated.len()
+}
+
+// get the names of all objects in the module that are selected by the row
+fn matching_names(module: &Module, selector: &MetadataSelector) -> Vec<String> {
+    let is_match = |name: &str| match selector {
+        MetadataSelector::Name(selected) => name == selected,
+        MetadataSelector::Regex(regex) => regex.is_match(name),
+    };
+    module
+        .measurement
+        .iter()
+        .map(|m| &m.name)
+        .chain(module.characteristic.iter().map(|c| &c.name))
+        .chain(module.axis_pts.iter().map(|a| &a.name))
+        .filter(|name| is_match(name))
+        .cloned()
+        .collect()
+}
+
+// apply one row of metadata to the object with the given name. Returns true if anything was changed
+fn apply_row(
+    module: &mut Module,
+    name: &str,
+    row: &MetadataRow,
+    version: A2lVersion,
+    log_msgs: &mut Vec<String>,
+) -> bool {
+    let mut conversion_ok = true;
+    if let Some(conversion) = &row.conversion {
+        if conversion != "NO_COMPU_METHOD"
+            && !module.compu_method.iter().any(|cm| &cm.name == conversion)
+        {
+            log_msgs.push(format!(
+                "Line {}: the COMPU_METHOD {conversion} for {name} does not exist",
+                row.line
+            ));
+            conversion_ok = false;
+        }
+    }
+    let mut unit_ok = true;
+    if row.unit.is_some() && version < A2lVersion::V1_6_0 {
+        log_msgs.push(format!(
+            "Line {}: PHYS_UNIT of {name} requires file version 1.6.0, but the current version is {version}",
+            row.line
+        ));
+        unit_ok = false;
+    }
+
+    let fields = if let Some(m) = module.measurement.iter_mut().find(|m| m.name == name) {
+        (
+            &mut m.long_identifier,
+            &mut m.phys_unit,
+            &mut m.format,
+            &mut m.lower_limit,
+            &mut m.upper_limit,
+            &mut m.conversion,
+        )
+    } else if let Some(c) = module.characteristic.iter_mut().find(|c| c.name == name) {
+        (
+            &mut c.long_identifier,
+            &mut c.phys_unit,
+            &mut c.format,
+            &mut c.lower_limit,
+            &mut c.upper_limit,
+            &mut c.conversion,
+        )
+    } else if let Some(a) = module.axis_pts.iter_mut().find(|a| a.name == name) {
+        (
+            &mut a.long_identifier,
+            &mut a.phys_unit,
+            &mut a.format,
+            &mut a.lower_limit,
+            &mut a.upper_limit,
+            &mut a.conversion,
+        )
+    } else {
+        return false;
+    };
+    let (long_identifier, phys_unit, format, lower_limit, upper_limit, conversion) = fields;
+
+    let mut changed = false;
+    if let Some(description) = &row.description {
+        changed |= set_value(long_identifier, description.clone());
+    }
+    if let (Some(unit), true) = (&row.unit, unit_ok) {
+        if phys_unit.as_ref().is_none_or(|pu| &pu.unit != unit) {
+            *phys_unit = Some(PhysUnit::new(unit.clone()));
+            changed = true;
+        }
+    }
+    if let Some(format_string) = &row.format {
+        if format
+            .as_ref()
+            .is_none_or(|fmt| &fmt.format_string != format_string)
+        {
+            *format = Some(Format::new(format_string.clone()));
+            changed = true;
+        }
+    }
+    if let Some(ll) = row.lower_limit {
+        changed |= set_value(lower_limit, ll);
+    }
+    if let Some(ul) = row.upper_limit {
+        changed |= set_value(upper_limit, ul);
+    }
+    if let (Some(new_conversion), true) = (&row.conversion, conversion_ok) {
+        changed |= set_value(conversion, new_conversion.clone());
+    }
+    if *lower_limit > *upper_limit {
+        log_msgs.push(format!(
+            "Line {}: the lower limit {} of {name} is greater than the upper limit {}",
+            row.line, *lower_limit, *upper_limit
+        ));
+    }
+    changed
+}
+
+fn set_value<T: PartialEq>(target: &mut T, value: T) -> bool {
+    if *target == value {
+        false
+    } else {
+        *target = value;
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_apply_metadata() {
+        assert!(parse_metadata_csv("unit,description\nrpm,speed").is_err());
+        assert!(parse_metadata_csv("name,lower_limit\nx,low").is_err());
+        assert!(parse_metadata_csv("name,format\nx,8.3").is_err());
+        assert!(parse_metadata_csv("name,description\nx,\"unterminated").is_err());
+
+        let csv = "\
+name;regex;unit;description;format;lower_limit;upper_limit;conversion;owner
+engine_speed;;rpm;\"Engine speed; filtered\";%8.1;0;8000;;powertrain
+;c_.*;;calibration value;;;;CM;
+unknown;;;;;;;;
+c_gain;;;;;;;missing_cm;
+";
+        let rows = parse_metadata_csv(csv).unwrap();
+        assert_eq!(rows.len(), 4);
+        assert_eq!(
+            rows[0].description.as_deref(),
+            Some("Engine speed; filtered")
+        );
+        assert_eq!(rows[0].upper_limit, Some(8000.0));
+        assert!(matches!(rows[1].selector, MetadataSelector::Regex(_)));
+
+        let mut load_msgs = Vec::new();
+        let mut a2l_file = a2lfile::load_from_string(
+            r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin MEASUREMENT engine_speed "" UWORD NO_COMPU_METHOD 0 0 0 65535
+                /end MEASUREMENT
+                /begin CHARACTERISTIC c_gain "" VALUE 0x1000 RL 0 NO_COMPU_METHOD 0 100
+                /end CHARACTERISTIC
+                /begin CHARACTERISTIC c_offset "" VALUE 0x1004 RL 0 NO_COMPU_METHOD 0 100
+                /end CHARACTERISTIC
+                /begin COMPU_METHOD CM "" IDENTICAL "%4.2" "" /end COMPU_METHOD
+                /begin RECORD_LAYOUT RL FNC_VALUES 1 ULONG ROW_DIR DIRECT /end RECORD_LAYOUT
+            /end MODULE
+        /end PROJECT"#,
+            None,
+            &mut load_msgs,
+            true,
+        )
+        .unwrap();
+        let mut log_msgs = Vec::new();
+        let count = apply_metadata(&mut a2l_file, &rows, &mut log_msgs);
+        assert_eq!(count, 3);
+        assert_eq!(log_msgs.len(), 2);
+        assert!(log_msgs[0].starts_with("Line 4: unknown does not match"));
+        assert!(log_msgs[1].contains("missing_cm"));
+
+        let module = &a2l_file.project.module[0];
+        let engine_speed = &module.measurement[0];
+        assert_eq!(engine_speed.long_identifier, "Engine speed; filtered");
+        assert_eq!(engine_speed.phys_unit.as_ref().unwrap().unit, "rpm");
+        assert_eq!(engine_speed.format.as_ref().unwrap().format_string, "%8.1");
+        assert_eq!(engine_speed.upper_limit, 8000.0);
+        for characteristic in &module.characteristic {
+            assert_eq!(characteristic.long_identifier, "calibration value");
+            assert_eq!(characteristic.conversion, "CM");
+        }
+    }
+}
@@ -0,0 +1,509 @@
+//! Conversion of flat objects that describe the members of a struct variable into TYPEDEF_STRUCTURE + INSTANCE form
+
+use crate::dwarf::{DebugData, TypeInfo};
+use crate::rename::{apply_renames, plan_renames, NameKind};
+use crate::symbol::find_symbol;
+use crate::update::make_symbol_link_string;
+use crate::update::typedef::make_symbol_type_string;
+use crate::A2lVersion;
+use a2lfile::{
+    A2lFile, A2lObject, Characteristic, Instance, Measurement, Module, StructureComponent,
+    SymbolLink, SymbolTypeLink, TypedefCharacteristic, TypedefMeasurement, TypedefStructure,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// The result of [`modernize_structures`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ModernizeSummary {
+    /// number of INSTANCEs that were created
+    pub instances_created: usize,
+    /// number of MEASUREMENTs and CHARACTERISTICs that were folded into the new INSTANCEs
+    pub objects_folded: usize,
+}
+
+// a flat object that describes one member of a struct variable
+enum FlatObject {
+    Characteristic(Box<Characteristic>),
+    Measurement(Box<Measurement>),
+}
+
+// the members of a struct variable that are described by flat objects, arranged by their path
+#[derive(Default)]
+struct MemberTree {
+    // name of the member -> index of the flat object, or nested members
+    leaves: BTreeMap<String, usize>,
+    nodes: BTreeMap<String, MemberTree>,
+}
+
+/// Fold MEASUREMENTs and CHARACTERISTICs that describe the members of the same struct variable into
+/// an INSTANCE of a new TYPEDEF_STRUCTURE.
+///
+/// The objects are grouped by the variable name in their SYMBOL_LINK, e.g. `params.gain` and `params.offset`
+/// both belong to `params`. A struct is only folded if it is described by at least two objects,
+/// if all of them are either MEASUREMENTs or CHARACTERISTICs, and if the debug info confirms the
+/// member names and addresses. Array elements are never folded.
+///
+/// Each object is turned into a TYPEDEF_CHARACTERISTIC or TYPEDEF_MEASUREMENT with the same settings,
+/// and all references to the object are changed to the name of the INSTANCE member (e.g. `params.gain`).
+///
+/// # Errors
+///
+/// Returns an error message if the file version is older than 1.7.1, which introduced TYPEDEF_STRUCTURE
+pub fn modernize_structures(
+    a2l_file: &mut A2lFile,
+    debug_data: &DebugData,
+    log_msgs: &mut Vec<String>,
+) -> Result<ModernizeSummary, String> {
+    let version = A2lVersion::from(&*a2l_file);
+    if version < A2lVersion::V1_7_1 {
+        return Err(format!("Error: The conversion to TYPEDEF_STRUCTURE and INSTANCE requires file version 1.7.1, but the current version is {version}"));
+    }
+
+    let mut summary = ModernizeSummary::default();
+    for module in &mut a2l_file.project.module {
+        // group the objects by the struct variable they belong to
+        let mut candidates: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+        let objects = module
+            .characteristic
+            .iter()
+            .map(|chr| (&chr.name, &chr.symbol_link))
+            .chain(
+                module
+                    .measurement
+                    .iter()
+                    .map(|meas| (&meas.name, &meas.symbol_link)),
+            );
+        for (name, symbol_link) in objects {
+            let symbol = symbol_link
+                .as_ref()
+                .filter(|sl| sl.offset == 0)
+                .map_or(name.as_str(), |sl| sl.symbol_name.as_str());
+            if let Some((root, path)) = symbol.split_once('.') {
+                if !root.contains(['[', '{']) && !path.contains(['[', '{']) {
+                    candidates
+                        .entry(root.to_string())
+                        .or_default()
+                        .push((name.clone(), path.to_string()));
+                }
+            }
+        }
+
+        for (root, members) in candidates {
+            if members.len() < 2 {
+                continue;
+            }
+            match fold_struct(module, debug_data, &root, &members) {
+                Ok(()) => {
+                    log_msgs.push(format!(
+                        "Folded {} objects into INSTANCE {root}",
+                        members.len()
+                    ));
+                    summary.instances_created += 1;
+                    summary.objects_folded += members.len();
+                }
+                Err(errmsg) => {
+                    log_msgs.push(format!("Struct variable {root} was not folded: {errmsg}"));
+                }
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+// replace the objects that describe the members of one struct variable by an INSTANCE
+fn fold_struct(
+    module: &mut Module,
+    debug_data: &DebugData,
+    root: &str,
+    members: &[(String, String)],
+) -> Result<(), String> {
+    if is_object_name(module, root) {
+        return Err(format!("an object called {root} already exists"));
+    }
+    let sym_info = find_symbol(root, debug_data)?;
+    let root_type = sym_info.typeinfo.get_reference(&debug_data.types);
+    if root_type.get_members().is_none() {
+        return Err(format!("its data type {root_type} is not a struct"));
+    }
+
+    let object_names: HashSet<&str> = members.iter().map(|(name, _)| name.as_str()).collect();
+    let mut objects = Vec::new();
+    let mut tree = MemberTree::default();
+    for (name, path) in members {
+        let object = if let Some(chr) = module.characteristic.iter().find(|chr| chr.name == *name) {
+            FlatObject::Characteristic(Box::new(chr.clone()))
+        } else if let Some(meas) = module.measurement.iter().find(|meas| meas.name == *name) {
+            FlatObject::Measurement(Box::new(meas.clone()))
+        } else {
+            continue;
+        };
+        let (offset, _) = get_member_type(root_type, path, debug_data)
+            .ok_or_else(|| format!("{path} is not a member of {root_type}"))?;
+        let address = match &object {
+            FlatObject::Characteristic(chr) => u64::from(chr.address),
+            FlatObject::Measurement(meas) => meas
+                .ecu_address
+                .as_ref()
+                .map_or(0, |ecu_address| u64::from(ecu_address.address)),
+        };
+        if address != sym_info.address + offset {
+            return Err(format!(
+                "the address of {name} does not match the debug info; the file should be updated first"
+            ));
+        }
+        tree.insert(path, objects.len())?;
+        objects.push(object);
+    }
+
+    let is_calib = matches!(objects[0], FlatObject::Characteristic(_));
+    if objects
+        .iter()
+        .any(|obj| matches!(obj, FlatObject::Characteristic(_)) != is_calib)
+    {
+        return Err("it is described by both MEASUREMENTs and CHARACTERISTICs".to_string());
+    }
+    let extensions: HashSet<Option<i16>> = objects
+        .iter()
+        .map(|obj| {
+            let ext = match obj {
+                FlatObject::Characteristic(chr) => &chr.ecu_address_extension,
+                FlatObject::Measurement(meas) => &meas.ecu_address_extension,
+            };
+            ext.as_ref().map(|ext| ext.extension)
+        })
+        .collect();
+    if extensions.len() > 1 {
+        return Err("the objects use different ECU_ADDRESS_EXTENSIONs".to_string());
+    }
+
+    // all checks passed: create the typedefs and the INSTANCE
+    let mut used_names = typedef_names(module);
+    let typedef_name = create_typedefs(
+        module,
+        &mut used_names,
+        root_type,
+        &tree,
+        &mut objects,
+        debug_data,
+    );
+    let mut instance = Instance::new(
+        root.to_string(),
+        format!("instance for symbol {}", sym_info.name),
+        typedef_name,
+        sym_info.address as u32,
+    );
+    let symbol_link_text = make_symbol_link_string(&sym_info, debug_data);
+    instance.symbol_link = Some(SymbolLink::new(symbol_link_text, 0));
+    if let Some(extension) = extensions.into_iter().next().flatten() {
+        instance.ecu_address_extension = Some(a2lfile::EcuAddressExtension::new(extension));
+    }
+    // set the address of the new instance to be written as hex
+    instance.get_layout_mut().item_location.3 = (0, true);
+
+    // redirect all references to the members of the INSTANCE, then remove the flat objects
+    let new_names: HashMap<&str, String> = members
+        .iter()
+        .map(|(name, path)| (name.as_str(), format!("{root}.{path}")))
+        .collect();
+    let plan = plan_renames(module, |kind, name| {
+        if kind == NameKind::Object {
+            new_names.get(name).cloned()
+        } else {
+            None
+        }
+    });
+    apply_renames(module, &plan, false);
+    let renamed: HashSet<&String> = new_names.values().collect();
+    module
+        .characteristic
+        .retain(|chr| !renamed.contains(&chr.name) && !object_names.contains(chr.name.as_str()));
+    module
+        .measurement
+        .retain(|meas| !renamed.contains(&meas.name) && !object_names.contains(meas.name.as_str()));
+    module.instance.push(instance);
+
+    Ok(())
+}
+
+impl MemberTree {
+    fn insert(&mut self, path: &str, index: usize) -> Result<(), String> {
+        let conflict = || format!("the member {path} is described more than once");
+        if let Some((first, rest)) = path.split_once('.') {
+            if self.leaves.contains_key(first) {
+                return Err(conflict());
+            }
+            self.nodes
+                .entry(first.to_string())
+                .or_default()
+                .insert(rest, index)
+                .map_err(|_| conflict())
+        } else if self.nodes.contains_key(path) || self.leaves.contains_key(path) {
+            Err(conflict())
+        } else {
+            self.leaves.insert(path.to_string(), index);
+            Ok(())
+        }
+    }
+}
+
+// follow a path of member names through the struct types and return the offset and type of the last member
+fn get_member_type<'a>(
+    typeinfo: &'a TypeInfo,
+    path: &str,
+    debug_data: &'a DebugData,
+) -> Option<(u64, &'a TypeInfo)> {
+    let mut offset = 0;
+    let mut cur_type = typeinfo;
+    for component in path.split('.') {
+        let (member_type, member_offset) = cur_type
+            .get_reference(&debug_data.types)
+            .get_members()?
+            .get(component)?;
+        offset += member_offset;
+        cur_type = member_type.get_reference(&debug_data.types);
+    }
+    Some((offset, cur_type))
+}
+
+// create the TYPEDEF_STRUCTURE for one level of the member tree, and all TYPEDEFs it depends on
+fn create_typedefs(
+    module: &mut Module,
+    used_names: &mut HashSet<String>,
+    typeinfo: &TypeInfo,
+    tree: &MemberTree,
+    objects: &mut [FlatObject],
+    debug_data: &DebugData,
+) -> String {
+    let struct_name = make_unique_name(used_names, typeinfo.name.as_deref().unwrap_or("Structure"));
+    let mut td_struct = TypedefStructure::new(
+        struct_name.clone(),
+        String::new(),
+        typeinfo.get_size() as u32,
+    );
+    // display item .2 (size) in hex by default
+    td_struct.get_layout_mut().item_location.2 = (1, true);
+    td_struct.symbol_type_link =
+        make_symbol_type_string(debug_data, typeinfo).map(SymbolTypeLink::new);
+
+    let members = typeinfo
+        .get_members()
+        .expect("the member tree only contains struct types");
+    // the STRUCTURE_COMPONENTs are created in the order of the members in the debug info
+    for (member_name, (member_type, offset)) in members {
+        let member_type = member_type.get_reference(&debug_data.types);
+        let component_type = if let Some(subtree) = tree.nodes.get(&**member_name) {
+            create_typedefs(
+                module,
+                used_names,
+                member_type,
+                subtree,
+                objects,
+                debug_data,
+            )
+        } else if let Some(index) = tree.leaves.get(&**member_name) {
+            let name = make_unique_name(used_names, &format!("{struct_name}_{member_name}"));
+            match &mut objects[*index] {
+                FlatObject::Characteristic(chr) => {
+                    module
+                        .typedef_characteristic
+                        .push(make_typedef_characteristic(&name, chr));
+                }
+                FlatObject::Measurement(meas) => {
+                    module
+                        .typedef_measurement
+                        .push(make_typedef_measurement(&name, meas));
+                }
+            }
+            name
+        } else {
+            continue;
+        };
+        let mut component =
+            StructureComponent::new(member_name.to_string(), component_type, *offset as u32);
+        let layout = component.get_layout_mut();
+        layout.start_offset = 1; // only one newline before this block -- i.e. no empty lines
+        layout.item_location.2 = (1, false); // offset is placed on a new line, not displayd as hex
+        component.symbol_type_link = Some(SymbolTypeLink::new(member_name.to_string()));
+        td_struct.structure_component.push(component);
+    }
+    module.typedef_structure.push(td_struct);
+
+    struct_name
+}
+
+fn make_typedef_characteristic(name: &str, chr: &mut Characteristic) -> TypedefCharacteristic {
+    let mut td_char = TypedefCharacteristic::new(
+        name.to_string(),
+        std::mem::take(&mut chr.long_identifier),
+        chr.characteristic_type,
+        chr.deposit.clone(),
+        chr.max_diff,
+        chr.conversion.clone(),
+        chr.lower_limit,
+        chr.upper_limit,
+    );
+    td_char.axis_descr = std::mem::take(&mut chr.axis_descr);
+    td_char.bit_mask = chr.bit_mask.take();
+    td_char.byte_order = chr.byte_order.take();
+    td_char.discrete = chr.discrete.take();
+    td_char.encoding = chr.encoding.take();
+    td_char.extended_limits = chr.extended_limits.take();
+    td_char.format = chr.format.take();
+    td_char.matrix_dim = chr.matrix_dim.take();
+    td_char.number = chr.number.take();
+    td_char.phys_unit = chr.phys_unit.take();
+    td_char.step_size = chr.step_size.take();
+    td_char
+}
+
+fn make_typedef_measurement(name: &str, meas: &mut Measurement) -> TypedefMeasurement {
+    let mut td_meas = TypedefMeasurement::new(
+        name.to_string(),
+        std::mem::take(&mut meas.long_identifier),
+        meas.datatype,
+        meas.conversion.clone(),
+        meas.resolution,
+        meas.accuracy,
+        meas.lower_limit,
+        meas.upper_limit,
+    );
+    td_meas.address_type = meas.address_type.take();
+    td_meas.bit_mask = meas.bit_mask.take();
+    td_meas.bit_operation = meas.bit_operation.take();
+    td_meas.byte_order = meas.byte_order.take();
+    td_meas.discrete = meas.discrete.take();
+    td_meas.error_mask = meas.error_mask.take();
+    td_meas.format = meas.format.take();
+    td_meas.layout = meas.layout.take();
+    td_meas.matrix_dim = meas.matrix_dim.take();
+    td_meas.phys_unit = meas.phys_unit.take();
+    td_meas
+}
+
+// the names of all TYPEDEF_* blocks share one namespace
+fn typedef_names(module: &Module) -> HashSet<String> {
+    let mut names = HashSet::new();
+    names.extend(module.typedef_axis.iter().map(|td| td.name.clone()));
+    names.extend(module.typedef_blob.iter().map(|td| td.name.clone()));
+    names.extend(
+        module
+            .typedef_characteristic
+            .iter()
+            .map(|td| td.name.clone()),
+    );
+    names.extend(module.typedef_measurement.iter().map(|td| td.name.clone()));
+    names.extend(module.typedef_structure.iter().map(|td| td.name.clone()));
+    names
+}
+
+// make the name unique by appending _Copy<x>, like the TYPEDEFs created during the update
+fn make_unique_name(used_names: &mut HashSet<String>, name: &str) -> String {
+    let mut newname = name.to_string();
+    let mut copycount = 0;
+    while used_names.contains(&newname) {
+        copycount += 1;
+        newname = format!("{name}_Copy{copycount}");
+    }
+    used_names.insert(newname.clone());
+    newname
+}
+
+fn is_object_name(module: &Module, name: &str) -> bool {
+    module.axis_pts.iter().any(|item| item.name == name)
+        || module.blob.iter().any(|item| item.name == name)
+        || module.characteristic.iter().any(|item| item.name == name)
+        || module.instance.iter().any(|item| item.name == name)
+        || module.measurement.iter().any(|item| item.name == name)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_modernize_structures() {
+        let debug_data = crate::DebugData::load(
+            std::ffi::OsStr::new("tests/elffiles/update_test.elf"),
+            false,
+        )
+        .unwrap();
+        let sym_info = find_symbol("struct_b", &debug_data).unwrap();
+        let (s1_offset, _) = get_member_type(sym_info.typeinfo, "s1.val_i32", &debug_data).unwrap();
+        let (s2_offset, _) = get_member_type(sym_info.typeinfo, "s2.val_f32", &debug_data).unwrap();
+        let a2l_text = format!(
+            r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin CHARACTERISTIC b_s1_i32 "" VALUE {:#x} RL 0 CM 0 100
+                    SYMBOL_LINK "struct_b.s1.val_i32" 0
+                /end CHARACTERISTIC
+                /begin CHARACTERISTIC b_s2_f32 "" VALUE {:#x} RL 0 NO_COMPU_METHOD 0 100
+                    SYMBOL_LINK "struct_b.s2.val_f32" 0
+                /end CHARACTERISTIC
+                /begin MEASUREMENT val_u8 "" UBYTE NO_COMPU_METHOD 0 0 0 255
+                /end MEASUREMENT
+                /begin COMPU_METHOD CM "" RAT_FUNC "%4.2" "" COEFFS 0 1 0 0 0 1 /end COMPU_METHOD
+                /begin RECORD_LAYOUT RL FNC_VALUES 1 ULONG ROW_DIR DIRECT /end RECORD_LAYOUT
+                /begin GROUP grp "" ROOT
+                    /begin REF_CHARACTERISTIC b_s1_i32 b_s2_f32 /end REF_CHARACTERISTIC
+                /end GROUP
+            /end MODULE
+        /end PROJECT"#,
+            sym_info.address + s1_offset,
+            sym_info.address + s2_offset
+        );
+        let mut load_msgs = Vec::new();
+        let mut a2l_file =
+            a2lfile::load_from_string(&a2l_text, None, &mut load_msgs, false).unwrap();
+
+        let mut log_msgs = Vec::new();
+        let summary = modernize_structures(&mut a2l_file, &debug_data, &mut log_msgs).unwrap();
+        assert_eq!(summary.instances_created, 1);
+        assert_eq!(summary.objects_folded, 2);
+        let module = &a2l_file.project.module[0];
+        assert!(module.characteristic.is_empty());
+        assert_eq!(module.measurement.len(), 1);
+        assert_eq!(module.instance[0].name, "struct_b");
+        assert_eq!(module.instance[0].type_ref, "StructB");
+        // StructB, StructA for s1 and a second StructA for s2
+        assert_eq!(module.typedef_structure.len(), 3);
+        let td_struct_b = module
+            .typedef_structure
+            .iter()
+            .find(|td| td.name == "StructB")
+            .unwrap();
+        assert_eq!(
+            td_struct_b.symbol_type_link.as_ref().unwrap().symbol_type,
+            "StructB"
+        );
+        let sc_s1 = &td_struct_b.structure_component[0];
+        assert_eq!(
+            sc_s1.symbol_type_link.as_ref().unwrap().symbol_type,
+            sc_s1.component_name
+        );
+        let td_char = module
+            .typedef_characteristic
+            .iter()
+            .find(|td| td.conversion == "CM")
+            .unwrap();
+        assert_eq!(td_char.record_layout, "RL");
+        assert_eq!(
+            module.group[0]
+                .ref_characteristic
+                .as_ref()
+                .unwrap()
+                .identifier_list,
+            vec!["struct_b.s1.val_i32", "struct_b.s2.val_f32"]
+        );
+
+        // running it again does nothing
+        let summary = modernize_structures(&mut a2l_file, &debug_data, &mut log_msgs).unwrap();
+        assert_eq!(summary.instances_created, 0);
+
+        let mut a2l_file = a2lfile::new();
+        a2l_file.asap2_version = Some(a2lfile::Asap2Version::new(1, 61));
+        assert!(modernize_structures(&mut a2l_file, &debug_data, &mut log_msgs).is_err());
+    }
+}
@@ -0,0 +1,331 @@
+//! Editing of the entries of MOD_PAR and HEADER, and of the FUNCTION_VERSIONs
+
+use a2lfile::{
+    A2lFile, CpuType, Customer, CustomerNo, Ecu, EcuCalibrationOffset, Epk, FunctionVersion,
+    Header, ModPar, Module, NoOfInterfaces, PhoneNo, ProjectNo, Supplier, User, Version,
+};
+
+/// The MOD_PAR entries that can be set with [`set_modpar_value`]
+pub const MODPAR_KEYS: &[&str] = &[
+    "CPU_TYPE",
+    "CUSTOMER",
+    "CUSTOMER_NO",
+    "ECU",
+    "ECU_CALIBRATION_OFFSET",
+    "EPK",
+    "NO_OF_INTERFACES",
+    "PHONE_NO",
+    "SUPPLIER",
+    "USER",
+    "VERSION",
+];
+
+/// Split an assignment of the form `KEY=value`. The key is not case sensitive.
+///
+/// # Errors
+///
+/// Returns an error message if there is no `=` or if the key is not one of [`MODPAR_KEYS`]
+pub fn parse_modpar_assignment(assignment: &str) -> Result<(&'static str, &str), String> {
+    let Some((key, value)) = assignment.split_once('=') else {
+        return Err(format!(
+            "Error: \"{assignment}\" is not a valid MOD_PAR assignment, expected KEY=value"
+        ));
+    };
+    let key = key.trim();
+    let Some(known_key) = MODPAR_KEYS
+        .iter()
+        .find(|known| known.eq_ignore_ascii_case(key))
+    else {
+        return Err(format!(
+            "Error: unknown MOD_PAR entry \"{key}\". Valid entries are: {}",
+            MODPAR_KEYS.join(", ")
+        ));
+    };
+    Ok((known_key, value))
+}
+
+/// Create or update an entry of MOD_PAR in every module. MOD_PAR is created if it does not exist.
+///
+/// # Errors
+///
+/// Returns an error message if the key is unknown or the value is not valid for the entry
+pub fn set_modpar_value(a2l_file: &mut A2lFile, key: &str, value: &str) -> Result<(), String> {
+    if value.chars().any(char::is_control) {
+        return Err(format!(
+            "Error: the value of {key} must not contain control characters"
+        ));
+    }
+    for module in &mut a2l_file.project.module {
+        let mod_par = get_or_create_mod_par(module);
+        match key {
+            "CPU_TYPE" => mod_par.cpu_type = Some(CpuType::new(value.to_string())),
+            "CUSTOMER" => mod_par.customer = Some(Customer::new(value.to_string())),
+            "CUSTOMER_NO" => mod_par.customer_no = Some(CustomerNo::new(value.to_string())),
+            "ECU" => mod_par.ecu = Some(Ecu::new(value.to_string())),
+            "ECU_CALIBRATION_OFFSET" => {
+                let offset = parse_number::<i32>(key, value)?;
+                mod_par.ecu_calibration_offset = Some(EcuCalibrationOffset::new(offset));
+            }
+            "EPK" => mod_par.epk = Some(Epk::new(value.to_string())),
+            "NO_OF_INTERFACES" => {
+                let num = parse_number::<u16>(key, value)?;
+                mod_par.no_of_interfaces = Some(NoOfInterfaces::new(num));
+            }
+            "PHONE_NO" => mod_par.phone_no = Some(PhoneNo::new(value.to_string())),
+            "SUPPLIER" => mod_par.supplier = Some(Supplier::new(value.to_string())),
+            "USER" => mod_par.user = Some(User::new(value.to_string())),
+            "VERSION" => mod_par.version = Some(Version::new(value.to_string())),
+            _ => return Err(format!("Error: unknown MOD_PAR entry \"{key}\"")),
+        }
+    }
+    Ok(())
+}
+
+/// Set the VERSION in the HEADER of the PROJECT and in the MOD_PAR of every module.
+/// HEADER and MOD_PAR are created if they do not exist.
+///
+/// # Errors
+///
+/// Returns an error message if the version contains control characters
+pub fn set_version(a2l_file: &mut A2lFile, version: &str) -> Result<(), String> {
+    set_modpar_value(a2l_file, "VERSION", version)?;
+    get_or_create_header(a2l_file).version = Some(Version::new(version.to_string()));
+    Ok(())
+}
+
+/// Set the PROJECT_NO in the HEADER of the PROJECT. HEADER is created if it does not exist.
+///
+/// # Errors
+///
+/// Returns an error message if the project number is not a valid a2l identifier
+pub fn set_project_no(a2l_file: &mut A2lFile, project_no: &str) -> Result<(), String> {
+    if !crate::rename::is_valid_identifier(project_no) {
+        return Err(format!(
+            "Error: \"{project_no}\" is not a valid PROJECT_NO. It must be an identifier, which can't start with a digit"
+        ));
+    }
+    get_or_create_header(a2l_file).project_no = Some(ProjectNo::new(project_no.to_string()));
+    Ok(())
+}
+
+/// Parse a function version map, which contains one entry per line in the form `function,version`.
+///
+/// The function name may also be separated from the version by a semicolon or a tab. Empty lines and lines starting with '#' are ignored.
+///
+/// # Errors
+///
+/// Returns an error message if a line is invalid, or if a function occurs more than once
+pub fn parse_function_version_map(text: &str) -> Result<Vec<(String, String)>, String> {
+    let mut version_map: Vec<(String, String)> = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((function, version)) = line.split_once([',', ';', '\t']) else {
+            return Err(format!(
+                "Error: invalid line {} in function version map: \"{line}\"; expected \"function,version\"",
+                idx + 1
+            ));
+        };
+        let function = function.trim().trim_matches('"');
+        let version = version.trim().trim_matches('"');
+        if function.is_empty() || version.chars().any(char::is_control) {
+            return Err(format!(
+                "Error: invalid line {} in function version map: \"{line}\"",
+                idx + 1
+            ));
+        }
+        if version_map.iter().any(|(name, _)| name == function) {
+            return Err(format!(
+                "Error: {function} occurs more than once in the function version map (line {})",
+                idx + 1
+            ));
+        }
+        version_map.push((function.to_string(), version.to_string()));
+    }
+    Ok(version_map)
+}
+
+/// Set the FUNCTION_VERSION of the FUNCTIONs in every module.
+///
+/// Functions that are listed in `version_map` get the version from the map, all other functions get `default_version`,
+/// if it is given. Entries of the map that don't match any function are reported in `log_msgs`.
+///
+/// Returns the number of functions whose FUNCTION_VERSION was set.
+///
+/// # Errors
+///
+/// Returns an error message if the default version contains control characters
+pub fn set_function_versions(
+    a2l_file: &mut A2lFile,
+    version_map: &[(String, String)],
+    default_version: Option<&str>,
+    log_msgs: &mut Vec<String>,
+) -> Result<usize, String> {
+    if default_version.is_some_and(|version| version.chars().any(char::is_control)) {
+        return Err("Error: the FUNCTION_VERSION must not contain control characters".to_string());
+    }
+    let mut count = 0;
+    let mut used = vec![false; version_map.len()];
+    for module in &mut a2l_file.project.module {
+        for function in &mut module.function {
+            let mapped = version_map
+                .iter()
+                .position(|(name, _)| *name == function.name);
+            let version = if let Some(pos) = mapped {
+                used[pos] = true;
+                &version_map[pos].1
+            } else if let Some(default_version) = default_version {
+                default_version
+            } else {
+                continue;
+            };
+            function.function_version = Some(FunctionVersion::new(version.to_string()));
+            count += 1;
+        }
+    }
+    for ((name, _), _) in version_map.iter().zip(used).filter(|(_, used)| !used) {
+        log_msgs.push(format!(
+            "The function version map contains {name}, but there is no FUNCTION with this name"
+        ));
+    }
+    Ok(count)
+}
+
+fn get_or_create_header(a2l_file: &mut A2lFile) -> &mut Header {
+    a2l_file
+        .project
+        .header
+        .get_or_insert_with(|| Header::new(String::new()))
+}
+
+// parse a decimal or hex number
+fn parse_number<T: TryFrom<i64>>(key: &str, value: &str) -> Result<T, String> {
+    let value = value.trim();
+    let parsed = if let Some(hexval) = value.strip_prefix("0x") {
+        i64::from_str_radix(hexval, 16).ok()
+    } else {
+        value.parse::<i64>().ok()
+    };
+    parsed
+        .and_then(|num| T::try_from(num).ok())
+        .ok_or_else(|| format!("Error: \"{value}\" is not a valid number for {key}"))
+}
+
+pub(crate) fn get_or_create_mod_par(module: &mut Module) -> &mut ModPar {
+    module
+        .mod_par
+        .get_or_insert_with(|| ModPar::new(String::new()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_set_modpar() {
+        let mut a2l_file = crate::load::new_a2l();
+        let (key, value) = parse_modpar_assignment("cpu_type=TC397").unwrap();
+        assert_eq!(key, "CPU_TYPE");
+        set_modpar_value(&mut a2l_file, key, value).unwrap();
+        set_modpar_value(&mut a2l_file, "NO_OF_INTERFACES", "2").unwrap();
+        set_modpar_value(&mut a2l_file, "VERSION", "1.0").unwrap();
+        set_modpar_value(&mut a2l_file, "VERSION", "1.1").unwrap();
+        let mod_par = a2l_file.project.module[0].mod_par.as_ref().unwrap();
+        assert_eq!(mod_par.cpu_type.as_ref().unwrap().cpu, "TC397");
+        assert_eq!(mod_par.no_of_interfaces.as_ref().unwrap().num, 2);
+        assert_eq!(mod_par.version.as_ref().unwrap().version_identifier, "1.1");
+
+        assert!(parse_modpar_assignment("CPU_TYPE").is_err());
+        assert!(parse_modpar_assignment("COLOR=blue").is_err());
+        assert!(set_modpar_value(&mut a2l_file, "NO_OF_INTERFACES", "70000").is_err());
+        assert!(set_modpar_value(&mut a2l_file, "ECU", "a\nb").is_err());
+    }
+
+    #[test]
+    fn test_set_version() {
+        let mut a2l_file = crate::load::new_a2l();
+        set_version(&mut a2l_file, "2.3.0-build.17").unwrap();
+        set_project_no(&mut a2l_file, "B17").unwrap();
+        assert!(set_project_no(&mut a2l_file, "17").is_err());
+        assert!(set_version(&mut a2l_file, "a\tb").is_err());
+
+        let header = a2l_file.project.header.as_ref().unwrap();
+        assert_eq!(
+            header.version.as_ref().unwrap().version_identifier,
+            "2.3.0-build.17"
+        );
+        assert_eq!(header.project_no.as_ref().unwrap().project_number, "B17");
+        let mod_par = a2l_file.project.module[0].mod_par.as_ref().unwrap();
+        assert_eq!(
+            mod_par.version.as_ref().unwrap().version_identifier,
+            "2.3.0-build.17"
+        );
+
+        // the result can be loaded again
+        let text = a2l_file.write_to_string();
+        let mut log_msgs = Vec::new();
+        assert!(a2lfile::load_from_string(&text, None, &mut log_msgs, true).is_ok());
+    }
+
+    #[test]
+    fn test_set_function_versions() {
+        let text = r#"ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+          /begin MODULE m ""
+            /begin FUNCTION Engine "" FUNCTION_VERSION "0.1" /end FUNCTION
+            /begin FUNCTION Brakes "" /end FUNCTION
+            /begin FUNCTION Lights "" /end FUNCTION
+          /end MODULE
+        /end PROJECT"#;
+        let mut log_msgs = Vec::new();
+        let mut a2l_file = a2lfile::load_from_string(text, None, &mut log_msgs, true).unwrap();
+
+        let version_map = parse_function_version_map(
+            "# component versions\nEngine,2.1.0\nBrakes;\"1.4, hotfix\"\n\nWipers\t3.0\n",
+        )
+        .unwrap();
+        assert_eq!(version_map.len(), 3);
+        assert_eq!(
+            version_map[1],
+            ("Brakes".to_string(), "1.4, hotfix".to_string())
+        );
+        assert!(parse_function_version_map("Engine").is_err());
+        assert!(parse_function_version_map("Engine,1\nEngine,2").is_err());
+
+        // only the functions in the map are changed
+        let mut log_msgs = Vec::new();
+        let count =
+            set_function_versions(&mut a2l_file, &version_map, None, &mut log_msgs).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(log_msgs.len(), 1);
+        let functions = &a2l_file.project.module[0].function;
+        assert_eq!(
+            functions[0]
+                .function_version
+                .as_ref()
+                .unwrap()
+                .version_identifier,
+            "2.1.0"
+        );
+        assert!(functions[2].function_version.is_none());
+
+        // the default version is used for all other functions
+        let mut log_msgs = Vec::new();
+        let count =
+            set_function_versions(&mut a2l_file, &version_map[..1], Some("1.0"), &mut log_msgs)
+                .unwrap();
+        assert_eq!(count, 3);
+        let functions = &a2l_file.project.module[0].function;
+        assert_eq!(
+            functions[1]
+                .function_version
+                .as_ref()
+                .unwrap()
+                .version_identifier,
+            "1.0"
+        );
+        assert!(set_function_versions(&mut a2l_file, &[], Some("a\nb"), &mut log_msgs).is_err());
+    }
+}
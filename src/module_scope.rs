@@ -0,0 +1,84 @@
+//! Restriction of operations to a single MODULE of a file with several modules
+
+use a2lfile::{A2lFile, Module};
+
+/// The modules that were set aside while operations are restricted to one selected module
+#[derive(Debug)]
+pub struct ModuleScope {
+    // original position of the selected module
+    position: usize,
+    other_modules: Vec<Module>,
+}
+
+impl ModuleScope {
+    /// Remove all modules except for the module with the given name from the file.
+    ///
+    /// Operations that process all modules or only the first module then only see the selected module.
+    /// The other modules must be put back with [`ModuleScope::restore`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the file does not contain a module with the given name
+    pub fn select(a2l_file: &mut A2lFile, name: &str) -> Result<Self, String> {
+        let modules = &mut a2l_file.project.module;
+        let Some(position) = modules.iter().position(|module| module.name == name) else {
+            let names: Vec<&str> = modules.iter().map(|module| module.name.as_str()).collect();
+            return Err(format!(
+                "Error: there is no MODULE {name}. The file contains the modules {}",
+                names.join(", ")
+            ));
+        };
+        let selected = modules.remove(position);
+        let other_modules = std::mem::replace(modules, vec![selected]);
+        Ok(Self {
+            position,
+            other_modules,
+        })
+    }
+
+    /// Put the other modules back into the file, in their original order
+    pub fn restore(self, a2l_file: &mut A2lFile) {
+        let Self {
+            position,
+            mut other_modules,
+        } = self;
+        let mut selected = std::mem::take(&mut a2l_file.project.module);
+        // modules that were added in the meantime are placed after the selected module
+        other_modules.splice(position..position, selected.drain(..));
+        a2l_file.project.module = other_modules;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_module_scope() {
+        let mut a2l_file = a2lfile::new();
+        a2l_file.project.module[0].name = "first".to_string();
+        for name in ["second", "third"] {
+            a2l_file
+                .project
+                .module
+                .push(Module::new(name.to_string(), String::new()));
+        }
+
+        let scope = ModuleScope::select(&mut a2l_file, "second").unwrap();
+        assert_eq!(a2l_file.project.module.len(), 1);
+        assert_eq!(a2l_file.project.module[0].name, "second");
+        a2l_file.project.module[0].long_identifier = "selected".to_string();
+        scope.restore(&mut a2l_file);
+
+        let names: Vec<&str> = a2l_file
+            .project
+            .module
+            .iter()
+            .map(|module| module.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["first", "second", "third"]);
+        assert_eq!(a2l_file.project.module[1].long_identifier, "selected");
+
+        assert!(ModuleScope::select(&mut a2l_file, "missing").is_err());
+    }
+}
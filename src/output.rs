@@ -0,0 +1,442 @@
+use a2lfile::{A2lFile, A2lObject, BlockInfo, Module, Project};
+use std::ffi::{OsStr, OsString};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// parallel serialization only pays off if every thread has enough objects to write
+const MIN_CHUNK_ITEMS: usize = 2000;
+
+// Write a file through a temporary file in the same directory, which is renamed to the target once it is complete.
+// If writing fails, e.g. because the disk is full or the process crashes, the previous file remains intact.
+// The data is flushed to the disk before the rename, so that a power loss can't leave an empty file behind.
+// This matters most when the output overwrites the input.
+pub(crate) fn write_atomic<F>(filename: &OsStr, write: F) -> Result<(), String>
+where
+    F: FnOnce(&mut File) -> std::io::Result<()>,
+{
+    let target = Path::new(filename);
+    let mut tmp_name = target.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(format!(".a2ltool-{}.tmp", std::process::id()));
+    let tmp_path = target.with_file_name(tmp_name);
+
+    let result = File::create(&tmp_path)
+        .and_then(|mut file| {
+            write(&mut file)?;
+            file.sync_all()
+        })
+        .and_then(|()| std::fs::rename(&tmp_path, target))
+        .map_err(|err| {
+            format!(
+                "Error: could not write \"{}\": {err}",
+                filename.to_string_lossy()
+            )
+        });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+// Copy an existing file to <name>.<timestamp>.bak before it is overwritten.
+// Returns the name of the backup, or None if there was no file to back up
+pub(crate) fn backup(filename: &OsStr, time: SystemTime) -> Result<Option<PathBuf>, String> {
+    let target = Path::new(filename);
+    if !target.is_file() {
+        return Ok(None);
+    }
+    let mut backup_name = OsString::from(filename);
+    backup_name.push(format!(".{}.bak", crate::logging::file_timestamp(time)));
+    let backup_path = PathBuf::from(backup_name);
+    std::fs::copy(target, &backup_path).map_err(|err| {
+        format!(
+            "Error: could not create the backup \"{}\": {err}",
+            backup_path.to_string_lossy()
+        )
+    })?;
+    Ok(Some(backup_path))
+}
+
+// The writer of a2lfile serializes the whole file on a single thread, which takes about as long as loading it.
+// The text of each object in a MODULE only depends on the object itself, so the objects of a module can be
+// distributed to several temporary modules that are serialized in parallel. The texts of these modules are
+// then joined in the order in which the writer sorts the objects, which gives exactly the same output.
+// The file is consumed, so that each thread can also drop the objects it has written.
+pub(crate) fn write_to_string(a2l_file: A2lFile) -> String {
+    let thread_count = std::thread::available_parallelism().map_or(4, usize::from);
+    write_to_string_chunked(a2l_file, thread_count, MIN_CHUNK_ITEMS)
+}
+
+fn write_to_string_chunked(
+    mut a2l_file: A2lFile,
+    max_chunks: usize,
+    min_chunk_items: usize,
+) -> String {
+    let module_keys: Vec<Vec<ItemKey>> = a2l_file.project.module.iter().map(item_keys).collect();
+    let item_count: usize = module_keys.iter().map(Vec::len).sum();
+    // objects from include files are written as a single /include per file, which can't be split
+    let has_includes = module_keys.iter().flatten().any(|key| key.included);
+    if max_chunks < 2
+        || item_count < 2 * min_chunk_items
+        || has_includes
+        || !a2l_file.project.module.iter_mut().all(only_listed_items)
+    {
+        return a2l_file.write_to_string();
+    }
+
+    // write the file without the content of the modules; the content is inserted into this skeleton
+    let contents: Vec<Module> = a2l_file
+        .project
+        .module
+        .iter_mut()
+        .map(|module| {
+            let shell = empty_module(module);
+            std::mem::replace(module, shell)
+        })
+        .collect();
+    let skeleton = a2l_file.write_to_string();
+    let Some(insert_positions) = module_insert_positions(&skeleton, &a2l_file.project.module)
+    else {
+        // the skeleton can't be matched to the modules, e.g. because a string contains "/end MODULE"
+        a2l_file.project.module = contents;
+        return a2l_file.write_to_string();
+    };
+
+    let module_texts: Vec<String> = a2l_file
+        .project
+        .module
+        .iter()
+        .zip(contents)
+        .zip(module_keys)
+        .map(|((shell, content), keys)| {
+            let chunk_count = max_chunks.min(keys.len() / min_chunk_items).max(1);
+            write_module_chunked(content, shell, &keys, chunk_count)
+        })
+        .collect();
+
+    let mut text =
+        String::with_capacity(skeleton.len() + module_texts.iter().map(String::len).sum::<usize>());
+    let mut skeleton_pos = 0;
+    for (insert_pos, module_idx) in insert_positions {
+        text.push_str(&skeleton[skeleton_pos..insert_pos]);
+        text.push_str(&module_texts[module_idx]);
+        skeleton_pos = insert_pos;
+    }
+    text.push_str(&skeleton[skeleton_pos..]);
+    text
+}
+
+// serialize the objects of a module in chunks on several threads and return the text of all objects
+fn write_module_chunked(
+    content: Module,
+    shell: &Module,
+    keys: &[ItemKey],
+    chunk_count: usize,
+) -> String {
+    // assign contiguous ranges of the sorted objects to the chunks
+    let mut order: Vec<usize> = (0..keys.len()).collect();
+    order.sort_by(|a, b| keys[*a].cmp_position(&keys[*b]));
+    let mut assignment = vec![0; keys.len()];
+    for (position, item_idx) in order.into_iter().enumerate() {
+        assignment[item_idx] = position * chunk_count / keys.len();
+    }
+
+    // the text of a chunk is the text of an empty module with the objects inserted in front of "/end MODULE"
+    let empty_text = write_module(shell.clone());
+    let header_len = empty_text[..empty_text.rfind("/end MODULE").unwrap_or(0)]
+        .trim_end()
+        .len();
+    let footer_len = empty_text.len() - header_len;
+
+    let chunks = split_module(content, shell, &assignment, chunk_count);
+    let chunk_texts: Vec<String> = std::thread::scope(|scope| {
+        let handles: Vec<_> = chunks
+            .into_iter()
+            .map(|chunk| scope.spawn(move || write_module(chunk)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("serialization thread panicked"))
+            .collect()
+    });
+
+    let mut text = String::with_capacity(chunk_texts.iter().map(String::len).sum());
+    for chunk_text in &chunk_texts {
+        text.push_str(&chunk_text[header_len..chunk_text.len() - footer_len]);
+    }
+    text
+}
+
+// write a module inside an otherwise empty file
+fn write_module(module: Module) -> String {
+    let mut file = A2lFile::new(Project::new(String::new(), String::new()));
+    file.project.module.push(module);
+    file.write_to_string()
+}
+
+fn empty_module(module: &Module) -> Module {
+    let mut shell = Module::new(module.name.clone(), module.long_identifier.clone());
+    *shell.get_layout_mut() = module.get_layout().clone();
+    shell
+}
+
+// Check that module_items! handles everything in the module, by moving the objects out and back in.
+// Content that is not listed there, e.g. because a newer version of a2lfile added a field, would be
+// lost when the module is split, so such a file must be written by a2lfile itself.
+fn only_listed_items(module: &mut Module) -> bool {
+    let mut content = empty_module(module);
+    move_items(module, &mut content);
+    let only_listed = *module == empty_module(module);
+    move_items(&mut content, module);
+    only_listed
+}
+
+// Find the positions in the skeleton where the content of each module must be inserted: in front of the
+// whitespace before "/end MODULE". The modules are written in the order of their position in the input file.
+fn module_insert_positions(skeleton: &str, modules: &[Module]) -> Option<Vec<(usize, usize)>> {
+    if skeleton.matches("/end MODULE").count() != modules.len() {
+        return None;
+    }
+    let mut order: Vec<usize> = (0..modules.len()).collect();
+    order.sort_by_key(|idx| {
+        let layout = modules[*idx].get_layout();
+        (layout.uid == 0, layout.uid, layout.line)
+    });
+    let mut positions = Vec::with_capacity(modules.len());
+    let mut search_pos = 0;
+    for module_idx in order {
+        let end_pos = search_pos + skeleton[search_pos..].find("/end MODULE")?;
+        positions.push((skeleton[..end_pos].trim_end().len(), module_idx));
+        search_pos = end_pos + "/end MODULE".len();
+    }
+    Some(positions)
+}
+
+// the information that the writer of a2lfile uses to sort the objects of a module
+struct ItemKey {
+    tag: &'static str,
+    uid: u32,
+    line: u32,
+    included: bool,
+}
+
+impl ItemKey {
+    fn new<T>(tag: &'static str, layout: &BlockInfo<T>) -> Self {
+        Self {
+            tag,
+            uid: layout.uid,
+            line: layout.line,
+            included: layout.incfile.is_some(),
+        }
+    }
+
+    // same order as in the writer: objects with a uid (i.e. loaded from a file) come first,
+    // new objects without uid or line are sorted by their tag
+    fn cmp_position(&self, other: &Self) -> std::cmp::Ordering {
+        (self.uid == 0, self.uid, self.line, self.tag).cmp(&(
+            other.uid == 0,
+            other.uid,
+            other.line,
+            other.tag,
+        ))
+    }
+}
+
+// Generate the functions that handle all objects of a module. Optional objects are treated as lists
+// with at most one element. All functions visit the objects in the same order.
+macro_rules! module_items {
+    (lists: [$($list:ident: $list_tag:literal),*], options: [$($opt:ident: $opt_tag:literal),*]) => {
+        // move all objects from one module to another
+        fn move_items(from: &mut Module, to: &mut Module) {
+            $(to.$list = std::mem::take(&mut from.$list);)*
+            $(to.$opt = from.$opt.take();)*
+        }
+
+        fn item_keys(module: &Module) -> Vec<ItemKey> {
+            let mut keys = Vec::new();
+            $(keys.extend(module.$list.iter().map(|item| ItemKey::new($list_tag, item.get_layout())));)*
+            $(keys.extend(module.$opt.iter().map(|item| ItemKey::new($opt_tag, item.get_layout())));)*
+            keys
+        }
+
+        // move the objects of the module into the chunks given by the assignment
+        fn split_module(
+            mut content: Module,
+            shell: &Module,
+            assignment: &[usize],
+            chunk_count: usize,
+        ) -> Vec<Module> {
+            let mut chunks = vec![shell.clone(); chunk_count];
+            let mut pos = 0;
+            $(
+                let list = std::mem::take(&mut content.$list);
+                let len = list.len();
+                let parts = split_list(list, &assignment[pos..pos + len], chunk_count);
+                for (chunk, part) in chunks.iter_mut().zip(parts) {
+                    chunk.$list = part;
+                }
+                pos += len;
+            )*
+            let mut option_assignment = assignment[pos..].iter();
+            $(
+                if let Some(item) = content.$opt.take() {
+                    let chunk_idx = option_assignment.next().expect("one chunk per object");
+                    chunks[*chunk_idx].$opt = Some(item);
+                }
+            )*
+            chunks
+        }
+    };
+}
+
+// The objects are large, and copying them costs about as much time as writing them. In a loaded file the
+// objects of each chunk are usually contiguous, so the list can be cut into pieces and the first piece keeps
+// the allocation.
+fn split_list<T>(mut list: Vec<T>, assignment: &[usize], chunk_count: usize) -> Vec<Vec<T>> {
+    if assignment.is_sorted() {
+        let mut parts: Vec<Vec<T>> = (1..chunk_count)
+            .rev()
+            .map(|chunk_idx| {
+                let start = assignment.partition_point(|idx| *idx < chunk_idx);
+                list.split_off(start)
+            })
+            .collect();
+        parts.push(list);
+        parts.reverse();
+        parts
+    } else {
+        let mut parts: Vec<Vec<T>> = (0..chunk_count).map(|_| Vec::new()).collect();
+        for (item, chunk_idx) in list.into_iter().zip(assignment) {
+            parts[*chunk_idx].push(item);
+        }
+        parts
+    }
+}
+
+module_items!(
+    lists: [
+        axis_pts: "AXIS_PTS",
+        blob: "BLOB",
+        characteristic: "CHARACTERISTIC",
+        compu_method: "COMPU_METHOD",
+        compu_tab: "COMPU_TAB",
+        compu_vtab: "COMPU_VTAB",
+        compu_vtab_range: "COMPU_VTAB_RANGE",
+        frame: "FRAME",
+        function: "FUNCTION",
+        group: "GROUP",
+        if_data: "IF_DATA",
+        instance: "INSTANCE",
+        measurement: "MEASUREMENT",
+        record_layout: "RECORD_LAYOUT",
+        transformer: "TRANSFORMER",
+        typedef_axis: "TYPEDEF_AXIS",
+        typedef_blob: "TYPEDEF_BLOB",
+        typedef_characteristic: "TYPEDEF_CHARACTERISTIC",
+        typedef_measurement: "TYPEDEF_MEASUREMENT",
+        typedef_structure: "TYPEDEF_STRUCTURE",
+        unit: "UNIT",
+        user_rights: "USER_RIGHTS"
+    ],
+    options: [
+        a2ml: "A2ML",
+        mod_common: "MOD_COMMON",
+        mod_par: "MOD_PAR",
+        variant_coding: "VARIANT_CODING"
+    ]
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Write;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_write_atomic() {
+        let dir = std::env::temp_dir().join(format!("a2ltool_output_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let target = dir.join("out.a2l");
+        std::fs::write(&target, "old").unwrap();
+
+        let time = UNIX_EPOCH + Duration::from_secs(951_782_400 + 3661);
+        let backup_path = backup(target.as_os_str(), time).unwrap().unwrap();
+        assert_eq!(backup_path, dir.join("out.a2l.20000229_010101.bak"));
+        assert!(backup(dir.join("missing.a2l").as_os_str(), time)
+            .unwrap()
+            .is_none());
+
+        // a failed write leaves the previous file untouched and removes the temporary file
+        let result = write_atomic(target.as_os_str(), |file| {
+            file.write_all(b"partial")?;
+            Err(std::io::Error::other("failed"))
+        });
+        assert!(result.is_err());
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "old");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+
+        write_atomic(target.as_os_str(), |file| file.write_all(b"new")).unwrap();
+        let content = std::fs::read_to_string(&target).unwrap();
+        let backup_content = std::fs::read_to_string(&backup_path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(content, "new");
+        assert_eq!(backup_content, "old");
+    }
+
+    #[test]
+    fn test_write_to_string_chunked() {
+        let mut log_msgs = Vec::new();
+        let mut a2l_file =
+            a2lfile::load("tests/update_test1.a2l", None, &mut log_msgs, false).unwrap();
+        // new objects have no position in the file and are sorted by their tag
+        a2l_file.project.module[0]
+            .measurement
+            .push(a2lfile::Measurement::new(
+                "new_measurement".to_string(),
+                String::new(),
+                a2lfile::DataType::Ubyte,
+                "NO_COMPU_METHOD".to_string(),
+                0,
+                0.0,
+                0.0,
+                255.0,
+            ));
+        a2l_file.project.module[0]
+            .compu_vtab
+            .push(a2lfile::CompuVtab::new(
+                "new_vtab".to_string(),
+                String::new(),
+                a2lfile::ConversionType::TabVerb,
+                0,
+            ));
+        let mut second_module = a2l_file.project.module[0].clone();
+        second_module.name = "second".to_string();
+        a2l_file.project.module.push(second_module);
+        let expected = a2l_file.write_to_string();
+        let mut module = a2l_file.project.module[0].clone();
+        assert!(only_listed_items(&mut module));
+        assert_eq!(module, a2l_file.project.module[0]);
+
+        for chunk_count in [2, 3, 7] {
+            assert_eq!(
+                write_to_string_chunked(a2l_file.clone(), chunk_count, 1),
+                expected
+            );
+        }
+        // small files are written without splitting
+        assert_eq!(
+            write_to_string_chunked(a2l_file.clone(), 4, 1_000_000),
+            expected
+        );
+
+        // the order of the lists does not matter, since the writer sorts the objects by their position
+        a2l_file.project.module[0].measurement.reverse();
+        a2l_file.project.module[0].characteristic.reverse();
+        assert_eq!(write_to_string_chunked(a2l_file.clone(), 3, 1), expected);
+
+        // if the modules can't be found in the skeleton, the file is written on a single thread
+        a2l_file.project.long_identifier = "/end MODULE".to_string();
+        let expected = a2l_file.write_to_string();
+        assert_eq!(write_to_string_chunked(a2l_file, 3, 1), expected);
+    }
+}
@@ -0,0 +1,503 @@
+//! Plugins with additional check rules and transformations
+//!
+//! A plugin is a dynamic library that exports the function `a2ltool_plugin_v1`, which returns a pointer to a
+//! [`PluginApi`]. The API only uses C types, so plugins don't need to be built with the same compiler as a2ltool.
+//! The a2l file is passed to the plugin as text, and a transformation returns the text of the modified file.
+//!
+//! Plugins written in Rust implement the trait [`Plugin`] and export it with [`export_plugin!`](crate::export_plugin):
+//!
+//! ```ignore
+//! struct NamingRules;
+//!
+//! impl a2ltool::plugin::Plugin for NamingRules {
+//!     fn name(&self) -> &str {
+//!         "naming_rules"
+//!     }
+//!
+//!     fn checks(&self) -> Vec<String> {
+//!         vec!["measurement_prefix".to_string()]
+//!     }
+//!
+//!     fn check(&self, _name: &str, a2l_file: &a2lfile::A2lFile, problems: &mut Vec<String>) -> Result<(), String> {
+//!         for module in &a2l_file.project.module {
+//!             for measurement in module.measurement.iter().filter(|m| !m.name.starts_with("m_")) {
+//!                 problems.push(format!("MEASUREMENT {} does not start with m_", measurement.name));
+//!             }
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! a2ltool::export_plugin!(NamingRules);
+//! ```
+//!
+//! The crate of the plugin must have the crate-type `cdylib`.
+
+use a2lfile::A2lFile;
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::ptr;
+
+/// The version of the plugin API. It changes whenever [`PluginApi`] changes in an incompatible way.
+pub const PLUGIN_API_VERSION: u32 = 1;
+
+/// The name of the function that every plugin exports. It takes no arguments and returns `*const PluginApi`.
+pub const PLUGIN_ENTRY_POINT: &str = "a2ltool_plugin_v1";
+
+/// The functions and properties of a plugin. All strings are nul-terminated UTF-8.
+#[repr(C)]
+pub struct PluginApi {
+    /// must be [`PLUGIN_API_VERSION`]
+    pub api_version: u32,
+    /// the name of the plugin
+    pub name: *const c_char,
+    /// the names of the check rules of the plugin, separated by newlines
+    pub checks: *const c_char,
+    /// the names of the transformations of the plugin, separated by newlines
+    pub transformations: *const c_char,
+    /// passed unchanged as the first argument of `run_step`
+    pub context: *const c_void,
+    /// Run the check rule or transformation `step` on the a2l file `a2l_text`.
+    ///
+    /// On success 0 is returned. A transformation sets `*a2l_out` to the text of the modified file, or leaves it
+    /// NULL if nothing was changed. `*messages` is set to the problems found by a check rule, or to the log messages
+    /// of a transformation, separated by newlines; it is NULL if there are none.
+    /// On failure -1 is returned and `*messages` is set to the error message.
+    pub run_step: unsafe extern "C" fn(
+        context: *const c_void,
+        step: *const c_char,
+        a2l_text: *const c_char,
+        a2l_out: *mut *mut c_char,
+        messages: *mut *mut c_char,
+    ) -> c_int,
+    /// release a string that was returned by `run_step`
+    pub free_string: unsafe extern "C" fn(text: *mut c_char),
+}
+
+/// Check rules and transformations that can be provided by a plugin
+pub trait Plugin: Send + Sync {
+    /// the name of the plugin
+    fn name(&self) -> &str;
+
+    /// the names of the check rules
+    fn checks(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// run the check rule `name`, and add a message for each problem to `problems`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the check could not be performed
+    fn check(
+        &self,
+        name: &str,
+        a2l_file: &A2lFile,
+        problems: &mut Vec<String>,
+    ) -> Result<(), String> {
+        let _ = (a2l_file, problems);
+        Err(format!(
+            "the plugin {} has no check rule {name}",
+            self.name()
+        ))
+    }
+
+    /// the names of the transformations
+    fn transformations(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// run the transformation `name`, which modifies `a2l_file`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the transformation failed
+    fn transform(
+        &self,
+        name: &str,
+        a2l_file: &mut A2lFile,
+        log_msgs: &mut Vec<String>,
+    ) -> Result<(), String> {
+        let _ = (a2l_file, log_msgs);
+        Err(format!(
+            "the plugin {} has no transformation {name}",
+            self.name()
+        ))
+    }
+}
+
+/// Export a value that implements [`Plugin`] from a dynamic library, so that it can be loaded by a2ltool.
+#[macro_export]
+macro_rules! export_plugin {
+    ($plugin:expr) => {
+        #[no_mangle]
+        pub extern "C" fn a2ltool_plugin_v1() -> *const $crate::plugin::PluginApi {
+            static EXPORTED: ::std::sync::OnceLock<$crate::plugin::ExportedPlugin> =
+                ::std::sync::OnceLock::new();
+            EXPORTED
+                .get_or_init(|| $crate::plugin::ExportedPlugin::new(Box::new($plugin)))
+                .api()
+        }
+    };
+}
+
+/// The [`PluginApi`] of a [`Plugin`], together with the data it points to. Created by [`export_plugin!`](crate::export_plugin).
+pub struct ExportedPlugin {
+    // the context of the api points to the inner box, which does not move when the ExportedPlugin is moved
+    _plugin: Box<Box<dyn Plugin>>,
+    _strings: [CString; 3],
+    api: PluginApi,
+}
+
+// SAFETY: the pointers in the api refer to the plugin and the strings, which are owned by the ExportedPlugin and
+// are never modified. The plugin itself is Send + Sync.
+unsafe impl Send for ExportedPlugin {}
+unsafe impl Sync for ExportedPlugin {}
+
+impl ExportedPlugin {
+    #[must_use]
+    pub fn new(plugin: Box<dyn Plugin>) -> Self {
+        let plugin = Box::new(plugin);
+        let strings = [
+            to_cstring(plugin.name()),
+            to_cstring(&plugin.checks().join("\n")),
+            to_cstring(&plugin.transformations().join("\n")),
+        ];
+        let api = PluginApi {
+            api_version: PLUGIN_API_VERSION,
+            name: strings[0].as_ptr(),
+            checks: strings[1].as_ptr(),
+            transformations: strings[2].as_ptr(),
+            context: ptr::from_ref::<Box<dyn Plugin>>(&plugin).cast(),
+            run_step: exported_run_step,
+            free_string: exported_free_string,
+        };
+        Self {
+            _plugin: plugin,
+            _strings: strings,
+            api,
+        }
+    }
+
+    #[must_use]
+    pub fn api(&self) -> *const PluginApi {
+        &self.api
+    }
+}
+
+unsafe extern "C" fn exported_run_step(
+    context: *const c_void,
+    step: *const c_char,
+    a2l_text: *const c_char,
+    a2l_out: *mut *mut c_char,
+    messages: *mut *mut c_char,
+) -> c_int {
+    let plugin = &*context.cast::<Box<dyn Plugin>>();
+    let step = CStr::from_ptr(step).to_string_lossy();
+    let a2l_text = CStr::from_ptr(a2l_text).to_string_lossy();
+    *a2l_out = ptr::null_mut();
+    *messages = ptr::null_mut();
+
+    let run = || -> Result<(Option<String>, Vec<String>), String> {
+        let mut a2l_file = load_text(&a2l_text)?;
+        let mut log_msgs = Vec::new();
+        if plugin.checks().iter().any(|name| *name == step) {
+            plugin.check(&step, &a2l_file, &mut log_msgs)?;
+            Ok((None, log_msgs))
+        } else {
+            plugin.transform(&step, &mut a2l_file, &mut log_msgs)?;
+            Ok((Some(a2l_file.write_to_string()), log_msgs))
+        }
+    };
+    // a panic must not unwind into the caller
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(run))
+        .unwrap_or_else(|_| Err(format!("the plugin {} panicked", plugin.name())));
+
+    match result {
+        Ok((text, log_msgs)) => {
+            if let Some(text) = text {
+                *a2l_out = to_cstring(&text).into_raw();
+            }
+            if !log_msgs.is_empty() {
+                *messages = to_cstring(&log_msgs.join("\n")).into_raw();
+            }
+            0
+        }
+        Err(errmsg) => {
+            *messages = to_cstring(&errmsg).into_raw();
+            -1
+        }
+    }
+}
+
+unsafe extern "C" fn exported_free_string(text: *mut c_char) {
+    if !text.is_null() {
+        drop(CString::from_raw(text));
+    }
+}
+
+/// A plugin that was loaded from a dynamic library.
+/// The library remains loaded until the program exits.
+pub struct LoadedPlugin {
+    api: &'static PluginApi,
+    name: String,
+    checks: Vec<String>,
+    transformations: Vec<String>,
+}
+
+impl LoadedPlugin {
+    /// Load a plugin from a dynamic library.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the library cannot be loaded, it does not export [`PLUGIN_ENTRY_POINT`],
+    /// or it was built for a different version of the plugin API
+    ///
+    /// # Safety
+    ///
+    /// Loading a library runs its initialization code, and the functions of the plugin are called without any checks.
+    /// Only trusted libraries that were built as a2ltool plugins may be loaded.
+    #[cfg(any(unix, windows))]
+    pub unsafe fn load(filename: &std::ffi::OsStr) -> Result<Self, String> {
+        let load_error = |err: libloading::Error| {
+            format!(
+                "Error: could not load plugin \"{}\": {err}",
+                filename.to_string_lossy()
+            )
+        };
+        let library = libloading::Library::new(filename).map_err(load_error)?;
+        let api = {
+            let entry_point: libloading::Symbol<unsafe extern "C" fn() -> *const PluginApi> =
+                library
+                    .get(PLUGIN_ENTRY_POINT.as_bytes())
+                    .map_err(load_error)?;
+            entry_point()
+        };
+        // the api points into the library, so it must never be unloaded
+        std::mem::forget(library);
+        if api.is_null() {
+            return Err(format!(
+                "Error: the plugin \"{}\" did not provide its API",
+                filename.to_string_lossy()
+            ));
+        }
+        Self::from_api(&*api)
+    }
+
+    // check the version of the api and read the names of the plugin and its steps
+    unsafe fn from_api(api: &'static PluginApi) -> Result<Self, String> {
+        if api.api_version != PLUGIN_API_VERSION {
+            return Err(format!(
+                "Error: the plugin uses version {} of the plugin API, but version {PLUGIN_API_VERSION} is required",
+                api.api_version
+            ));
+        }
+        let names = |text: *const c_char| -> Vec<String> {
+            CStr::from_ptr(text)
+                .to_string_lossy()
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+        Ok(Self {
+            api,
+            name: CStr::from_ptr(api.name).to_string_lossy().into_owned(),
+            checks: names(api.checks),
+            transformations: names(api.transformations),
+        })
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// the names of the check rules of the plugin
+    #[must_use]
+    pub fn checks(&self) -> &[String] {
+        &self.checks
+    }
+
+    /// the names of the transformations of the plugin
+    #[must_use]
+    pub fn transformations(&self) -> &[String] {
+        &self.transformations
+    }
+
+    /// Run the check rule `name` and add the problems it finds to `problems`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the plugin failed
+    pub fn run_check(
+        &self,
+        name: &str,
+        a2l_file: &A2lFile,
+        problems: &mut Vec<String>,
+    ) -> Result<(), String> {
+        let (_, messages) = self.run_step(name, a2l_file)?;
+        problems.extend(messages);
+        Ok(())
+    }
+
+    /// Run the transformation `name`, which replaces the content of `a2l_file`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the plugin failed, or if the file it returned cannot be loaded
+    pub fn run_transformation(
+        &self,
+        name: &str,
+        a2l_file: &mut A2lFile,
+        log_msgs: &mut Vec<String>,
+    ) -> Result<(), String> {
+        let (text, messages) = self.run_step(name, a2l_file)?;
+        log_msgs.extend(messages);
+        if let Some(text) = text {
+            *a2l_file = load_text(&text).map_err(|err| {
+                format!(
+                    "Error: the file returned by {} of plugin {} is invalid: {err}",
+                    name, self.name
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    // call the plugin and convert the strings it returns
+    fn run_step(
+        &self,
+        name: &str,
+        a2l_file: &A2lFile,
+    ) -> Result<(Option<String>, Vec<String>), String> {
+        let step = to_cstring(name);
+        let a2l_text = to_cstring(&a2l_file.write_to_string());
+        let mut a2l_out = ptr::null_mut();
+        let mut messages = ptr::null_mut();
+        // SAFETY: the api was provided by a plugin, which was loaded by the unsafe function load()
+        let (status, text, messages) = unsafe {
+            let status = (self.api.run_step)(
+                self.api.context,
+                step.as_ptr(),
+                a2l_text.as_ptr(),
+                &mut a2l_out,
+                &mut messages,
+            );
+            let text = self.take_string(a2l_out);
+            let messages = self.take_string(messages);
+            (status, text, messages)
+        };
+        let messages = messages.unwrap_or_default();
+        if status == 0 {
+            Ok((text, messages.lines().map(str::to_string).collect()))
+        } else {
+            Err(format!(
+                "Error: {name} of plugin {} failed: {messages}",
+                self.name
+            ))
+        }
+    }
+
+    // copy a string returned by the plugin and release it
+    unsafe fn take_string(&self, text: *mut c_char) -> Option<String> {
+        if text.is_null() {
+            None
+        } else {
+            let result = CStr::from_ptr(text).to_string_lossy().into_owned();
+            (self.api.free_string)(text);
+            Some(result)
+        }
+    }
+}
+
+// the a2l file is passed as text between a2ltool and the plugins
+fn load_text(a2l_text: &str) -> Result<A2lFile, String> {
+    let mut log_msgs = Vec::new();
+    a2lfile::load_from_string(
+        a2l_text,
+        Some(crate::ifdata::A2MLVECTOR_TEXT.to_string()),
+        &mut log_msgs,
+        false,
+    )
+    .map_err(|err| err.to_string())
+}
+
+// interior nul bytes cannot be represented in a C string
+fn to_cstring(text: &str) -> CString {
+    CString::new(text.replace('\0', " ")).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct TestPlugin;
+
+    impl Plugin for TestPlugin {
+        fn name(&self) -> &str {
+            "test_plugin"
+        }
+
+        fn checks(&self) -> Vec<String> {
+            vec!["has_measurements".to_string()]
+        }
+
+        fn check(
+            &self,
+            _name: &str,
+            a2l_file: &A2lFile,
+            problems: &mut Vec<String>,
+        ) -> Result<(), String> {
+            if a2l_file.project.module[0].measurement.is_empty() {
+                problems.push("the module has no MEASUREMENTs".to_string());
+            }
+            Ok(())
+        }
+
+        fn transformations(&self) -> Vec<String> {
+            vec!["rename_project".to_string(), "fail".to_string()]
+        }
+
+        fn transform(
+            &self,
+            name: &str,
+            a2l_file: &mut A2lFile,
+            log_msgs: &mut Vec<String>,
+        ) -> Result<(), String> {
+            if name == "fail" {
+                return Err("failed on purpose".to_string());
+            }
+            a2l_file.project.name = "renamed".to_string();
+            log_msgs.push("project renamed".to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_plugin_api() {
+        // the ExportedPlugin must live as long as the LoadedPlugin that uses its api
+        let exported: &'static ExportedPlugin =
+            Box::leak(Box::new(ExportedPlugin::new(Box::new(TestPlugin))));
+        let plugin = unsafe { LoadedPlugin::from_api(&*exported.api()) }.unwrap();
+        assert_eq!(plugin.name(), "test_plugin");
+        assert_eq!(plugin.checks(), ["has_measurements"]);
+        assert_eq!(plugin.transformations(), ["rename_project", "fail"]);
+
+        let mut a2l_file = crate::load::new_a2l();
+        let mut problems = Vec::new();
+        plugin
+            .run_check("has_measurements", &a2l_file, &mut problems)
+            .unwrap();
+        assert_eq!(problems, ["the module has no MEASUREMENTs"]);
+
+        let mut log_msgs = Vec::new();
+        plugin
+            .run_transformation("rename_project", &mut a2l_file, &mut log_msgs)
+            .unwrap();
+        assert_eq!(a2l_file.project.name, "renamed");
+        assert_eq!(log_msgs, ["project renamed"]);
+
+        let result = plugin.run_transformation("fail", &mut a2l_file, &mut log_msgs);
+        assert!(result.unwrap_err().contains("failed on purpose"));
+    }
+}
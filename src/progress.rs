@@ -0,0 +1,134 @@
+use std::io::{IsTerminal, Write};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+// when stderr is not a terminal, a line is printed at this interval while a phase is running
+const PLAIN_INTERVAL: Duration = Duration::from_secs(10);
+// redraw interval of the status line on a terminal
+const TTY_INTERVAL: Duration = Duration::from_millis(200);
+
+struct ProgressState {
+    phase: Option<String>,
+    phase_start: Instant,
+    last_output: Instant,
+}
+
+// Progress reporting for long running phases, e.g. loading the elf file or writing the output.
+// All output goes to stderr, so that it does not interfere with the normal output on stdout.
+// On a terminal a status line with the elapsed time is continuously updated,
+// otherwise a line is printed at the start and end of each phase and every few seconds in between.
+pub(crate) struct Progress {
+    state: Option<Arc<Mutex<ProgressState>>>,
+    is_tty: bool,
+    // dropping the sender stops the ticker thread
+    stop_sender: Option<mpsc::Sender<()>>,
+    ticker: Option<JoinHandle<()>>,
+}
+
+impl Progress {
+    pub(crate) fn new(enabled: bool) -> Self {
+        if !enabled {
+            return Self {
+                state: None,
+                is_tty: false,
+                stop_sender: None,
+                ticker: None,
+            };
+        }
+        let is_tty = std::io::stderr().is_terminal();
+        let state = Arc::new(Mutex::new(ProgressState {
+            phase: None,
+            phase_start: Instant::now(),
+            last_output: Instant::now(),
+        }));
+        let ticker_state = state.clone();
+        let (stop_sender, stop_receiver) = mpsc::channel();
+        let ticker =
+            std::thread::spawn(move || ticker_thread(&ticker_state, &stop_receiver, is_tty));
+        Self {
+            state: Some(state),
+            is_tty,
+            stop_sender: Some(stop_sender),
+            ticker: Some(ticker),
+        }
+    }
+
+    // start a new phase. A phase that is still running is finished first
+    pub(crate) fn phase(&self, name: &str) {
+        self.finish();
+        if let Some(state) = &self.state {
+            let mut state = state.lock().unwrap();
+            state.phase = Some(name.to_string());
+            state.phase_start = Instant::now();
+            state.last_output = Instant::now();
+            if self.is_tty {
+                draw_status_line(&state);
+            } else {
+                eprintln!("{name} ...");
+            }
+        }
+    }
+
+    // finish the current phase, if there is one
+    pub(crate) fn finish(&self) {
+        if let Some(state) = &self.state {
+            let mut state = state.lock().unwrap();
+            if let Some(phase) = state.phase.take() {
+                let elapsed = state.phase_start.elapsed().as_secs_f64();
+                if self.is_tty {
+                    eprintln!("\r\x1b[2K{phase}: done ({elapsed:.1}s)");
+                } else {
+                    eprintln!("{phase}: done ({elapsed:.1}s)");
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Progress {
+    // a phase that is still running was not completed, e.g. because an error occurred.
+    // It is not reported as done; only the status line is removed from the terminal
+    fn drop(&mut self) {
+        if let Some(state) = &self.state {
+            let mut state = state.lock().unwrap();
+            if state.phase.take().is_some() && self.is_tty {
+                eprint!("\r\x1b[2K");
+                let _ = std::io::stderr().flush();
+            }
+        }
+        self.stop_sender = None;
+        if let Some(ticker) = self.ticker.take() {
+            let _ = ticker.join();
+        }
+    }
+}
+
+// periodically show that the current phase is still running
+fn ticker_thread(state: &Mutex<ProgressState>, stop_receiver: &mpsc::Receiver<()>, is_tty: bool) {
+    while let Err(RecvTimeoutError::Timeout) = stop_receiver.recv_timeout(TTY_INTERVAL) {
+        let mut state = state.lock().unwrap();
+        if state.phase.is_some() {
+            if is_tty {
+                draw_status_line(&state);
+            } else if state.last_output.elapsed() >= PLAIN_INTERVAL {
+                let phase = state.phase.as_deref().unwrap_or_default();
+                eprintln!(
+                    "{phase}: still running ({:.0}s)",
+                    state.phase_start.elapsed().as_secs_f64()
+                );
+                state.last_output = Instant::now();
+            }
+        }
+    }
+}
+
+fn draw_status_line(state: &ProgressState) {
+    const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+    let elapsed = state.phase_start.elapsed();
+    let spinner = SPINNER[(elapsed.as_millis() / TTY_INTERVAL.as_millis()) as usize % 4];
+    let phase = state.phase.as_deref().unwrap_or_default();
+    eprint!("\r\x1b[2K{spinner} {phase} ({:.1}s)", elapsed.as_secs_f64());
+    let _ = std::io::stderr().flush();
+}
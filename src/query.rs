@@ -0,0 +1,922 @@
+//! Queries that find objects in an a2l file and describe them
+
+#[cfg(feature = "elf")]
+use crate::DebugData;
+use a2lfile::{A2lFile, A2lObject, Module};
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::Display;
+
+/// Summary information about one object (MEASUREMENT, CHARACTERISTIC, AXIS_PTS, BLOB or INSTANCE)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectInfo {
+    /// the block type, e.g. "MEASUREMENT"
+    pub blocktype: &'static str,
+    pub name: String,
+    /// name of the module that contains the object
+    pub module: String,
+    pub address: Option<u32>,
+    /// the data type of the object; for INSTANCEs this is the name of the referenced TYPEDEF
+    pub datatype: Option<String>,
+    pub conversion: Option<String>,
+    /// all GROUPs that reference the object
+    pub groups: Vec<String>,
+    /// the include file that contains the object, or None if it is part of the main file
+    pub incfile: Option<String>,
+    pub line: u32,
+}
+
+/// Find all objects whose name matches the regex.
+///
+/// The regex must match the complete name. The result is sorted by module and name.
+///
+/// # Errors
+///
+/// Returns an error message if the regex is invalid
+pub fn search(a2l_file: &A2lFile, regex: &str) -> Result<Vec<ObjectInfo>, String> {
+    let regex = compile_regex(regex)?;
+    let mut result = Vec::new();
+    for module in &a2l_file.project.module {
+        let group_membership = build_group_membership(module);
+        let mut objects = module_objects(module, &group_membership);
+        objects.retain(|object| regex.is_match(&object.name));
+        objects.sort_by(|a, b| a.name.cmp(&b.name));
+        result.extend(objects);
+    }
+    Ok(result)
+}
+
+/// Object types that can be listed with [`list`]
+pub const LIST_TYPES: [&str; 7] = [
+    "measurements",
+    "characteristics",
+    "axis_pts",
+    "instances",
+    "groups",
+    "functions",
+    "compu_methods",
+];
+
+/// List all objects of one type, optionally filtered by a regex that must match the complete name.
+///
+/// The type is one of the names in [`LIST_TYPES`]. The result is sorted by module and name.
+///
+/// # Errors
+///
+/// Returns an error message if the type is unknown or the regex is invalid
+pub fn list(
+    a2l_file: &A2lFile,
+    list_type: &str,
+    regex: Option<&str>,
+) -> Result<Vec<ObjectInfo>, String> {
+    let regex = regex.map(compile_regex).transpose()?;
+    let mut result = Vec::new();
+    for module in &a2l_file.project.module {
+        let group_membership = build_group_membership(module);
+        let mut objects = match list_type {
+            "measurements" | "characteristics" | "axis_pts" | "instances" => {
+                let blocktype = match list_type {
+                    "measurements" => "MEASUREMENT",
+                    "characteristics" => "CHARACTERISTIC",
+                    "axis_pts" => "AXIS_PTS",
+                    _ => "INSTANCE",
+                };
+                let mut objects = module_objects(module, &group_membership);
+                objects.retain(|object| object.blocktype == blocktype);
+                objects
+            }
+            "groups" => module_groups(module),
+            "functions" => module_functions(module),
+            "compu_methods" => module_compu_methods(module),
+            _ => {
+                return Err(format!(
+                    "Error: cannot list unknown object type \"{list_type}\""
+                ))
+            }
+        };
+        if let Some(regex) = &regex {
+            objects.retain(|object| regex.is_match(&object.name));
+        }
+        objects.sort_by(|a, b| a.name.cmp(&b.name));
+        result.extend(objects);
+    }
+    Ok(result)
+}
+
+/// The differences between the objects of two files, see [`diff`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObjectDiff {
+    /// the objects that only exist in the new file
+    pub added: Vec<String>,
+    /// the objects that only exist in the old file
+    pub removed: Vec<String>,
+    /// the objects whose address, data type or conversion changed: the name, the old and the new info
+    pub changed: Vec<(String, ObjectInfo, ObjectInfo)>,
+}
+
+/// Compare the objects of two files, i.e. everything that is found by [`search`].
+///
+/// Objects are named "BLOCKTYPE module.name", and they are compared by address, data type and conversion.
+/// All lists are sorted by this name.
+#[must_use]
+pub fn diff(old_file: &A2lFile, new_file: &A2lFile) -> ObjectDiff {
+    let old_objects = diff_objects(old_file);
+    let new_objects = diff_objects(new_file);
+
+    let mut result = ObjectDiff::default();
+    for (key, info) in &new_objects {
+        match old_objects.get(key) {
+            None => result.added.push(key.clone()),
+            Some(old_info)
+                if (&old_info.address, &old_info.datatype, &old_info.conversion)
+                    != (&info.address, &info.datatype, &info.conversion) =>
+            {
+                result
+                    .changed
+                    .push((key.clone(), old_info.clone(), info.clone()));
+            }
+            Some(_) => {}
+        }
+    }
+    result.removed = old_objects
+        .keys()
+        .filter(|key| !new_objects.contains_key(*key))
+        .cloned()
+        .collect();
+    result
+}
+
+impl ObjectDiff {
+    /// Convert the differences to JSON: `{"added": [...], "removed": [...], "changed": [...]}`.
+    /// Each entry of "changed" contains the name of the object, and its old and new address, data type and conversion.
+    #[must_use]
+    pub fn to_json(&self) -> serde_json::Value {
+        let properties = |info: &ObjectInfo| {
+            serde_json::json!({
+                "address": info.address,
+                "datatype": info.datatype,
+                "conversion": info.conversion,
+            })
+        };
+        let changed: Vec<serde_json::Value> = self
+            .changed
+            .iter()
+            .map(|(key, old_info, new_info)| {
+                serde_json::json!({
+                    "object": key,
+                    "old": properties(old_info),
+                    "new": properties(new_info),
+                })
+            })
+            .collect();
+        serde_json::json!({"added": self.added, "removed": self.removed, "changed": changed})
+    }
+}
+
+// all objects of a file, keyed by "BLOCKTYPE module.name"
+fn diff_objects(a2l_file: &A2lFile) -> BTreeMap<String, ObjectInfo> {
+    search(a2l_file, ".*")
+        .unwrap_or_default()
+        .into_iter()
+        .map(|info| {
+            (
+                format!("{} {}.{}", info.blocktype, info.module, info.name),
+                info,
+            )
+        })
+        .collect()
+}
+
+/// The names of all objects that are reachable from a GROUP, either directly or through its SUB_GROUPs
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct GroupContents {
+    /// the names in REF_MEASUREMENT, sorted
+    pub measurements: Vec<String>,
+    /// the names in REF_CHARACTERISTIC, sorted; these can also be AXIS_PTS, BLOBs and INSTANCEs
+    pub characteristics: Vec<String>,
+}
+
+/// Collect the measurements and characteristics of a GROUP and of all of its SUB_GROUPs, recursively.
+///
+/// If a group with this name exists in several modules, the contents of all of them are combined.
+///
+/// # Errors
+///
+/// Returns an error message if there is no GROUP with the given name
+pub fn expand_group(a2l_file: &A2lFile, name: &str) -> Result<GroupContents, String> {
+    let mut found = false;
+    let mut measurements = BTreeSet::new();
+    let mut characteristics = BTreeSet::new();
+    for module in &a2l_file.project.module {
+        let groups: HashMap<&str, &a2lfile::Group> = module
+            .group
+            .iter()
+            .map(|group| (group.name.as_str(), group))
+            .collect();
+        if !groups.contains_key(name) {
+            continue;
+        }
+        found = true;
+
+        // the group tree could contain cycles, so every group is only visited once
+        let mut visited = HashSet::new();
+        let mut pending = vec![name];
+        while let Some(group_name) = pending.pop() {
+            let Some(group) = groups.get(group_name) else {
+                continue;
+            };
+            if !visited.insert(group_name) {
+                continue;
+            }
+            if let Some(ref_measurement) = &group.ref_measurement {
+                measurements.extend(ref_measurement.identifier_list.iter().cloned());
+            }
+            if let Some(ref_characteristic) = &group.ref_characteristic {
+                characteristics.extend(ref_characteristic.identifier_list.iter().cloned());
+            }
+            if let Some(sub_group) = &group.sub_group {
+                pending.extend(sub_group.identifier_list.iter().map(String::as_str));
+            }
+        }
+    }
+    if !found {
+        return Err(format!("Error: there is no GROUP named {name}"));
+    }
+    Ok(GroupContents {
+        measurements: measurements.into_iter().collect(),
+        characteristics: characteristics.into_iter().collect(),
+    })
+}
+
+impl GroupContents {
+    /// Format the names as a label list (LAB file), with the measurements in the section [RAMCELL]
+    /// and the characteristics in the section [LABEL]
+    #[must_use]
+    pub fn to_lab(&self) -> String {
+        let mut text = String::new();
+        if !self.measurements.is_empty() {
+            text.push_str("[RAMCELL]\n");
+            for name in &self.measurements {
+                text.push_str(name);
+                text.push('\n');
+            }
+        }
+        if !self.characteristics.is_empty() {
+            text.push_str("[LABEL]\n");
+            for name in &self.characteristics {
+                text.push_str(name);
+                text.push('\n');
+            }
+        }
+        text
+    }
+}
+
+/// Describe an object in detail.
+///
+/// The result contains the a2l text of the MEASUREMENT, CHARACTERISTIC, AXIS_PTS, BLOB or INSTANCE
+/// with the given name, followed by the a2l text of everything it references:
+/// the COMPU_METHOD and its conversion tables, the RECORD_LAYOUT, the axes and the TYPEDEF of an INSTANCE.
+///
+/// # Errors
+///
+/// Returns an error message if there is no object with the given name
+pub fn object_details(a2l_file: &A2lFile, name: &str) -> Result<String, String> {
+    let mut sections = Vec::new();
+    for module in &a2l_file.project.module {
+        if let Some(measurement) = module.measurement.iter().find(|m| m.name == name) {
+            let text = fragment_text(|fm| fm.measurement.push(measurement.clone()));
+            sections.push(format!("MEASUREMENT in module {}:\n{text}", module.name));
+            conversion_details(module, &measurement.conversion, &mut sections);
+        }
+        if let Some(characteristic) = module.characteristic.iter().find(|c| c.name == name) {
+            let text = fragment_text(|fm| fm.characteristic.push(characteristic.clone()));
+            sections.push(format!("CHARACTERISTIC in module {}:\n{text}", module.name));
+            conversion_details(module, &characteristic.conversion, &mut sections);
+            record_layout_details(module, &characteristic.deposit, &mut sections);
+            for (idx, axis_descr) in characteristic.axis_descr.iter().enumerate() {
+                let axis_num = idx + 1;
+                sections.push(format!(
+                    "Axis {axis_num}: {:?}, input quantity {}, up to {} points",
+                    axis_descr.attribute, axis_descr.input_quantity, axis_descr.max_axis_points
+                ));
+                conversion_details(module, &axis_descr.conversion, &mut sections);
+                if let Some(axis_pts_ref) = &axis_descr.axis_pts_ref {
+                    axis_pts_details(module, &axis_pts_ref.axis_points, &mut sections);
+                }
+                if let Some(curve_axis_ref) = &axis_descr.curve_axis_ref {
+                    sections.push(format!(
+                        "Axis {axis_num} uses the curve {} as its axis",
+                        curve_axis_ref.curve_axis
+                    ));
+                }
+            }
+        }
+        if module.axis_pts.iter().any(|axis_pts| axis_pts.name == name) {
+            axis_pts_details(module, name, &mut sections);
+        }
+        if let Some(blob) = module.blob.iter().find(|b| b.name == name) {
+            let text = fragment_text(|fm| fm.blob.push(blob.clone()));
+            sections.push(format!("BLOB in module {}:\n{text}", module.name));
+        }
+        if let Some(instance) = module.instance.iter().find(|i| i.name == name) {
+            let text = fragment_text(|fm| fm.instance.push(instance.clone()));
+            sections.push(format!("INSTANCE in module {}:\n{text}", module.name));
+            typedef_details(module, &instance.type_ref, &mut sections);
+        }
+    }
+
+    if sections.is_empty() {
+        Err(format!("Error: there is no object named \"{name}\""))
+    } else {
+        Ok(sections.join("\n\n"))
+    }
+}
+
+/// Describe the symbol in the debug data that an object refers to.
+///
+/// The symbol is identified in the same way as during an address update, i.e. using the SYMBOL_LINK,
+/// the CANAPE_EXT IF_DATA or the object name.
+///
+/// # Errors
+///
+/// Returns an error message if there is no object with the given name, or its symbol was not found
+#[cfg(feature = "elf")]
+pub fn symbol_details(
+    a2l_file: &A2lFile,
+    name: &str,
+    debug_data: &DebugData,
+) -> Result<String, String> {
+    let module = a2l_file.project.module.iter();
+    let symbol_info = module
+        .filter_map(|module| {
+            let result = if let Some(m) = module.measurement.iter().find(|m| m.name == name) {
+                crate::update::get_symbol_info(name, &m.symbol_link, &m.if_data, debug_data)
+            } else if let Some(c) = module.characteristic.iter().find(|c| c.name == name) {
+                crate::update::get_symbol_info(name, &c.symbol_link, &c.if_data, debug_data)
+            } else if let Some(a) = module.axis_pts.iter().find(|a| a.name == name) {
+                crate::update::get_symbol_info(name, &a.symbol_link, &a.if_data, debug_data)
+            } else if let Some(b) = module.blob.iter().find(|b| b.name == name) {
+                crate::update::get_symbol_info(name, &b.symbol_link, &b.if_data, debug_data)
+            } else if let Some(i) = module.instance.iter().find(|i| i.name == name) {
+                crate::update::get_symbol_info(name, &i.symbol_link, &i.if_data, debug_data)
+            } else {
+                return None;
+            };
+            Some(result)
+        })
+        .next()
+        .ok_or_else(|| format!("Error: there is no object named \"{name}\""))?;
+
+    match symbol_info {
+        Ok(sym_info) => Ok(format!(
+            "Symbol {}: address 0x{:08X}, size {} bytes, type {}",
+            sym_info.name,
+            sym_info.address,
+            sym_info.typeinfo.get_size(),
+            sym_info.typeinfo
+        )),
+        Err(errors) => Err(format!(
+            "Error: no symbol found for {name}: {}",
+            errors.join(", ")
+        )),
+    }
+}
+
+// the COMPU_METHOD and the conversion tables it references
+fn conversion_details(module: &Module, conversion: &str, sections: &mut Vec<String>) {
+    if conversion == "NO_COMPU_METHOD" {
+        return;
+    }
+    let Some(compu_method) = module.compu_method.iter().find(|cm| cm.name == conversion) else {
+        sections.push(format!("COMPU_METHOD {conversion} does not exist"));
+        return;
+    };
+    let table_names: Vec<&str> = compu_method
+        .compu_tab_ref
+        .iter()
+        .map(|compu_tab_ref| compu_tab_ref.conversion_table.as_str())
+        .chain(
+            compu_method
+                .status_string_ref
+                .iter()
+                .map(|status_string_ref| status_string_ref.conversion_table.as_str()),
+        )
+        .collect();
+    let text = fragment_text(|fm| {
+        fm.compu_method.push(compu_method.clone());
+        for table in table_names {
+            fm.compu_tab
+                .extend(module.compu_tab.iter().filter(|t| t.name == table).cloned());
+            fm.compu_vtab.extend(
+                module
+                    .compu_vtab
+                    .iter()
+                    .filter(|t| t.name == table)
+                    .cloned(),
+            );
+            fm.compu_vtab_range.extend(
+                module
+                    .compu_vtab_range
+                    .iter()
+                    .filter(|t| t.name == table)
+                    .cloned(),
+            );
+        }
+    });
+    sections.push(format!("Conversion:\n{text}"));
+}
+
+fn record_layout_details(module: &Module, record_layout_name: &str, sections: &mut Vec<String>) {
+    if let Some(record_layout) = module
+        .record_layout
+        .iter()
+        .find(|rl| rl.name == record_layout_name)
+    {
+        let text = fragment_text(|fm| fm.record_layout.push(record_layout.clone()));
+        sections.push(format!("Record layout:\n{text}"));
+    } else {
+        sections.push(format!("RECORD_LAYOUT {record_layout_name} does not exist"));
+    }
+}
+
+fn axis_pts_details(module: &Module, name: &str, sections: &mut Vec<String>) {
+    if let Some(axis_pts) = module.axis_pts.iter().find(|a| a.name == name) {
+        let text = fragment_text(|fm| fm.axis_pts.push(axis_pts.clone()));
+        sections.push(format!("AXIS_PTS in module {}:\n{text}", module.name));
+        conversion_details(module, &axis_pts.conversion, sections);
+        record_layout_details(module, &axis_pts.deposit_record, sections);
+    } else {
+        sections.push(format!("AXIS_PTS {name} does not exist"));
+    }
+}
+
+// an INSTANCE can reference any kind of TYPEDEF
+fn typedef_details(module: &Module, type_ref: &str, sections: &mut Vec<String>) {
+    let text = fragment_text(|fm| {
+        fm.typedef_structure.extend(
+            module
+                .typedef_structure
+                .iter()
+                .filter(|t| t.name == type_ref)
+                .cloned(),
+        );
+        fm.typedef_measurement.extend(
+            module
+                .typedef_measurement
+                .iter()
+                .filter(|t| t.name == type_ref)
+                .cloned(),
+        );
+        fm.typedef_characteristic.extend(
+            module
+                .typedef_characteristic
+                .iter()
+                .filter(|t| t.name == type_ref)
+                .cloned(),
+        );
+        fm.typedef_axis.extend(
+            module
+                .typedef_axis
+                .iter()
+                .filter(|t| t.name == type_ref)
+                .cloned(),
+        );
+        fm.typedef_blob.extend(
+            module
+                .typedef_blob
+                .iter()
+                .filter(|t| t.name == type_ref)
+                .cloned(),
+        );
+    });
+    if text.trim().is_empty() {
+        sections.push(format!("TYPEDEF {type_ref} does not exist"));
+    } else {
+        sections.push(format!("Type:\n{text}"));
+    }
+}
+
+// write the objects that were added to an empty module and return only the text of the module content
+pub(crate) fn fragment_text(fill: impl FnOnce(&mut Module)) -> String {
+    let mut fragment = crate::load::new_a2l();
+    fill(&mut fragment.project.module[0]);
+    let text = fragment.write_to_string();
+    let body: Vec<&str> = text
+        .lines()
+        .skip_while(|line| !line.trim_start().starts_with("/begin MODULE"))
+        .skip(1)
+        .take_while(|line| !line.trim_start().starts_with("/end MODULE"))
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    body.join("\n")
+}
+
+// the regex must match the complete name, not just a part of it
+pub(crate) fn compile_regex(regex: &str) -> Result<Regex, String> {
+    Regex::new(&format!("^(?:{regex})$"))
+        .map_err(|err| format!("Error: invalid regex \"{regex}\": {err}"))
+}
+
+// map each object name to the names of the groups that reference it
+fn build_group_membership(module: &Module) -> HashMap<&str, Vec<String>> {
+    let mut membership: HashMap<&str, Vec<String>> = HashMap::new();
+    for group in &module.group {
+        let ref_meas = group
+            .ref_measurement
+            .iter()
+            .flat_map(|ref_meas| &ref_meas.identifier_list);
+        let ref_char = group
+            .ref_characteristic
+            .iter()
+            .flat_map(|ref_char| &ref_char.identifier_list);
+        for name in ref_meas.chain(ref_char) {
+            membership
+                .entry(name.as_str())
+                .or_default()
+                .push(group.name.clone());
+        }
+    }
+    membership
+}
+
+// get the data type of the values of a CHARACTERISTIC or AXIS_PTS from its RECORD_LAYOUT
+fn record_layout_datatype(module: &Module, record_layout_name: &str) -> Option<String> {
+    let record_layout = module
+        .record_layout
+        .iter()
+        .find(|rl| rl.name == record_layout_name)?;
+    if let Some(fnc_values) = &record_layout.fnc_values {
+        Some(fnc_values.datatype.to_string())
+    } else {
+        record_layout
+            .axis_pts_x
+            .as_ref()
+            .map(|axis_pts_x| axis_pts_x.datatype.to_string())
+    }
+}
+
+fn object_info<T, L>(
+    item: &T,
+    blocktype: &'static str,
+    name: &str,
+    module: &Module,
+    group_membership: &HashMap<&str, Vec<String>>,
+) -> ObjectInfo
+where
+    T: A2lObject<L>,
+{
+    ObjectInfo {
+        blocktype,
+        name: name.to_string(),
+        module: module.name.clone(),
+        address: None,
+        datatype: None,
+        conversion: None,
+        groups: group_membership.get(name).cloned().unwrap_or_default(),
+        incfile: item.get_layout().incfile.clone(),
+        line: item.get_line(),
+    }
+}
+
+// collect the information about all objects of a module
+fn module_objects(
+    module: &Module,
+    group_membership: &HashMap<&str, Vec<String>>,
+) -> Vec<ObjectInfo> {
+    let mut objects = Vec::new();
+    for measurement in &module.measurement {
+        let mut info = object_info(
+            measurement,
+            "MEASUREMENT",
+            &measurement.name,
+            module,
+            group_membership,
+        );
+        info.address = measurement.ecu_address.as_ref().map(|addr| addr.address);
+        info.datatype = Some(measurement.datatype.to_string());
+        info.conversion = Some(measurement.conversion.clone());
+        objects.push(info);
+    }
+    for characteristic in &module.characteristic {
+        let mut info = object_info(
+            characteristic,
+            "CHARACTERISTIC",
+            &characteristic.name,
+            module,
+            group_membership,
+        );
+        info.address = Some(characteristic.address);
+        info.datatype = record_layout_datatype(module, &characteristic.deposit);
+        info.conversion = Some(characteristic.conversion.clone());
+        objects.push(info);
+    }
+    for axis_pts in &module.axis_pts {
+        let mut info = object_info(
+            axis_pts,
+            "AXIS_PTS",
+            &axis_pts.name,
+            module,
+            group_membership,
+        );
+        info.address = Some(axis_pts.address);
+        info.datatype = record_layout_datatype(module, &axis_pts.deposit_record);
+        info.conversion = Some(axis_pts.conversion.clone());
+        objects.push(info);
+    }
+    for blob in &module.blob {
+        let mut info = object_info(blob, "BLOB", &blob.name, module, group_membership);
+        info.address = Some(blob.start_address);
+        objects.push(info);
+    }
+    for instance in &module.instance {
+        let mut info = object_info(
+            instance,
+            "INSTANCE",
+            &instance.name,
+            module,
+            group_membership,
+        );
+        info.address = Some(instance.start_address);
+        info.datatype = Some(instance.type_ref.clone());
+        objects.push(info);
+    }
+    objects
+}
+
+// GROUPs have no address or data type. The groups of a GROUP are the groups that list it as a SUB_GROUP
+fn module_groups(module: &Module) -> Vec<ObjectInfo> {
+    let mut parents: HashMap<&str, Vec<String>> = HashMap::new();
+    for group in &module.group {
+        for sub_group in group
+            .sub_group
+            .iter()
+            .flat_map(|sub_group| &sub_group.identifier_list)
+        {
+            parents
+                .entry(sub_group.as_str())
+                .or_default()
+                .push(group.name.clone());
+        }
+    }
+    module
+        .group
+        .iter()
+        .map(|group| object_info(group, "GROUP", &group.name, module, &parents))
+        .collect()
+}
+
+fn module_functions(module: &Module) -> Vec<ObjectInfo> {
+    let empty = HashMap::new();
+    module
+        .function
+        .iter()
+        .map(|function| object_info(function, "FUNCTION", &function.name, module, &empty))
+        .collect()
+}
+
+// the "data type" of a COMPU_METHOD is its conversion type, e.g. RAT_FUNC
+fn module_compu_methods(module: &Module) -> Vec<ObjectInfo> {
+    let empty = HashMap::new();
+    module
+        .compu_method
+        .iter()
+        .map(|compu_method| {
+            let mut info = object_info(
+                compu_method,
+                "COMPU_METHOD",
+                &compu_method.name,
+                module,
+                &empty,
+            );
+            info.datatype = Some(compu_method.conversion_type.to_string());
+            info
+        })
+        .collect()
+}
+
+impl Display for ObjectInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:<15} {}", self.blocktype, self.name)?;
+        if let Some(address) = self.address {
+            write!(f, "  address: 0x{address:08X}")?;
+        }
+        if let Some(datatype) = &self.datatype {
+            write!(f, "  type: {datatype}")?;
+        }
+        if let Some(conversion) = &self.conversion {
+            write!(f, "  conversion: {conversion}")?;
+        }
+        if !self.groups.is_empty() {
+            write!(f, "  groups: {}", self.groups.join(", "))?;
+        }
+        match &self.incfile {
+            Some(incfile) => write!(f, "  ({incfile}:{})", self.line),
+            None => write!(f, "  (line {})", self.line),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_search() {
+        let a2l_text = r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin MEASUREMENT speed "" UWORD NO_COMPU_METHOD 0 0 0 255
+                    ECU_ADDRESS 0x1234
+                /end MEASUREMENT
+                /begin MEASUREMENT temperature "" SBYTE NO_COMPU_METHOD 0 0 0 255
+                /end MEASUREMENT
+                /begin CHARACTERISTIC speed_limit "" VALUE 0x2000 RL_UBYTE 0 NO_COMPU_METHOD 0 255
+                /end CHARACTERISTIC
+                /begin RECORD_LAYOUT RL_UBYTE
+                    FNC_VALUES 1 UBYTE COLUMN_DIR DIRECT
+                /end RECORD_LAYOUT
+                /begin GROUP grp ""
+                    /begin REF_MEASUREMENT speed /end REF_MEASUREMENT
+                /end GROUP
+            /end MODULE
+        /end PROJECT"#;
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(a2l_text, None, &mut log_msgs, false).unwrap();
+
+        let result = search(&a2l_file, "speed.*").unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].name, "speed");
+        assert_eq!(result[0].address, Some(0x1234));
+        assert_eq!(result[0].groups, vec!["grp".to_string()]);
+        assert_eq!(result[1].name, "speed_limit");
+        assert_eq!(result[1].datatype.as_deref(), Some("UBYTE"));
+        assert!(result[0].to_string().contains("0x00001234"));
+
+        // the regex must match the whole name
+        assert!(search(&a2l_file, "speed").unwrap().len() == 1);
+        assert!(search(&a2l_file, "(").is_err());
+    }
+
+    #[test]
+    fn test_list() {
+        let a2l_text = r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin MEASUREMENT speed "" UWORD NO_COMPU_METHOD 0 0 0 255
+                /end MEASUREMENT
+                /begin MEASUREMENT temperature "" SBYTE cm_temp 0 0 0 255
+                /end MEASUREMENT
+                /begin COMPU_METHOD cm_temp "" LINEAR "%6.2" "degC"
+                    COEFFS_LINEAR 1 -40
+                /end COMPU_METHOD
+                /begin GROUP root "" ROOT
+                    /begin SUB_GROUP sub /end SUB_GROUP
+                /end GROUP
+                /begin GROUP sub ""
+                /end GROUP
+                /begin FUNCTION f1 ""
+                /end FUNCTION
+            /end MODULE
+        /end PROJECT"#;
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(a2l_text, None, &mut log_msgs, false).unwrap();
+
+        let result = list(&a2l_file, "measurements", None).unwrap();
+        assert_eq!(result.len(), 2);
+        let result = list(&a2l_file, "measurements", Some("temp.*")).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].name, "temperature");
+
+        let result = list(&a2l_file, "groups", None).unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[1].name, "sub");
+        assert_eq!(result[1].groups, vec!["root".to_string()]);
+
+        let result = list(&a2l_file, "compu_methods", None).unwrap();
+        assert_eq!(result[0].datatype.as_deref(), Some("LINEAR"));
+        assert_eq!(list(&a2l_file, "functions", None).unwrap().len(), 1);
+        assert!(list(&a2l_file, "characteristics", None).unwrap().is_empty());
+        assert!(list(&a2l_file, "blobs", None).is_err());
+    }
+
+    #[test]
+    fn test_object_details() {
+        let a2l_text = r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin CHARACTERISTIC curve "" CURVE 0x2000 RL_CURVE 0 cm_tab 0 255
+                    /begin AXIS_DESCR COM_AXIS speed NO_COMPU_METHOD 8 0 255
+                        AXIS_PTS_REF axis
+                    /end AXIS_DESCR
+                /end CHARACTERISTIC
+                /begin AXIS_PTS axis "" 0x3000 speed RL_AXIS 0 NO_COMPU_METHOD 8 0 255
+                /end AXIS_PTS
+                /begin COMPU_METHOD cm_tab "" TAB_VERB "%6.2" ""
+                    COMPU_TAB_REF vtab
+                /end COMPU_METHOD
+                /begin COMPU_VTAB vtab "" TAB_VERB 2
+                    0 "off"
+                    1 "on"
+                /end COMPU_VTAB
+                /begin RECORD_LAYOUT RL_CURVE
+                    FNC_VALUES 1 UBYTE COLUMN_DIR DIRECT
+                /end RECORD_LAYOUT
+                /begin RECORD_LAYOUT RL_AXIS
+                    AXIS_PTS_X 1 UWORD INDEX_INCR DIRECT
+                /end RECORD_LAYOUT
+            /end MODULE
+        /end PROJECT"#;
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(a2l_text, None, &mut log_msgs, false).unwrap();
+
+        let details = object_details(&a2l_file, "curve").unwrap();
+        assert!(details.contains("/begin CHARACTERISTIC curve"));
+        assert!(details.contains("/begin COMPU_METHOD cm_tab"));
+        assert!(details.contains("\"on\""));
+        assert!(details.contains("/begin RECORD_LAYOUT RL_CURVE"));
+        assert!(details.contains("/begin AXIS_PTS axis"));
+        assert!(details.contains("/begin RECORD_LAYOUT RL_AXIS"));
+        assert!(object_details(&a2l_file, "missing").is_err());
+    }
+
+    #[test]
+    fn test_expand_group() {
+        let a2l_text = r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin GROUP top "" ROOT
+                    /begin REF_MEASUREMENT speed /end REF_MEASUREMENT
+                    /begin SUB_GROUP engine chassis /end SUB_GROUP
+                /end GROUP
+                /begin GROUP engine ""
+                    /begin REF_MEASUREMENT rpm speed /end REF_MEASUREMENT
+                    /begin REF_CHARACTERISTIC idle_rpm /end REF_CHARACTERISTIC
+                    /begin SUB_GROUP top /end SUB_GROUP
+                /end GROUP
+                /begin GROUP chassis ""
+                    /begin REF_CHARACTERISTIC axle_ratio /end REF_CHARACTERISTIC
+                /end GROUP
+                /begin GROUP other ""
+                    /begin REF_MEASUREMENT temperature /end REF_MEASUREMENT
+                /end GROUP
+            /end MODULE
+        /end PROJECT"#;
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(a2l_text, None, &mut log_msgs, false).unwrap();
+
+        // the cycle top -> engine -> top is only followed once
+        let contents = expand_group(&a2l_file, "top").unwrap();
+        assert_eq!(contents.measurements, vec!["rpm", "speed"]);
+        assert_eq!(contents.characteristics, vec!["axle_ratio", "idle_rpm"]);
+        assert_eq!(
+            contents.to_lab(),
+            "[RAMCELL]\nrpm\nspeed\n[LABEL]\naxle_ratio\nidle_rpm\n"
+        );
+
+        let contents = expand_group(&a2l_file, "chassis").unwrap();
+        assert!(contents.measurements.is_empty());
+        assert_eq!(contents.to_lab(), "[LABEL]\naxle_ratio\n");
+
+        assert!(expand_group(&a2l_file, "missing").is_err());
+    }
+
+    #[test]
+    fn test_diff() {
+        let a2l_text = r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin MEASUREMENT speed "" UWORD NO_COMPU_METHOD 0 0 0 255
+                    ECU_ADDRESS 0x1234
+                /end MEASUREMENT
+                /begin MEASUREMENT temperature "" SBYTE NO_COMPU_METHOD 0 0 0 255
+                /end MEASUREMENT
+            /end MODULE
+        /end PROJECT"#;
+        let new_text = a2l_text
+            .replace("0x1234", "0x1238")
+            .replace("temperature", "pressure");
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(a2l_text, None, &mut log_msgs, false).unwrap();
+        let new_file = a2lfile::load_from_string(&new_text, None, &mut log_msgs, false).unwrap();
+
+        assert_eq!(diff(&a2l_file, &a2l_file), ObjectDiff::default());
+        let result = diff(&a2l_file, &new_file);
+        assert_eq!(result.added, vec!["MEASUREMENT m.pressure".to_string()]);
+        assert_eq!(
+            result.removed,
+            vec!["MEASUREMENT m.temperature".to_string()]
+        );
+        assert_eq!(result.changed.len(), 1);
+        assert_eq!(result.changed[0].0, "MEASUREMENT m.speed");
+
+        let json = result.to_json();
+        assert_eq!(json["changed"][0]["old"]["address"], 0x1234);
+        assert_eq!(json["changed"][0]["new"]["address"], 0x1238);
+    }
+}
@@ -0,0 +1,109 @@
+//! Removal of IF_DATA blocks by name
+
+use a2lfile::{A2lFile, GenericIfData, IfData};
+
+/// remove all IF_DATA blocks whose tag (e.g. XCP, CANAPE_EXT, ASAP1B_CCP) is one of the given names
+/// returns the number of removed IF_DATA blocks
+pub fn remove_ifdata(a2l_file: &mut A2lFile, names: &[&str]) -> usize {
+    let mut count = 0;
+
+    for module in &mut a2l_file.project.module {
+        count += remove_ifdata_from_list(&mut module.if_data, names);
+
+        if let Some(mod_par) = &mut module.mod_par {
+            for memory_layout in &mut mod_par.memory_layout {
+                count += remove_ifdata_from_list(&mut memory_layout.if_data, names);
+            }
+            for memory_segment in &mut mod_par.memory_segment {
+                count += remove_ifdata_from_list(&mut memory_segment.if_data, names);
+            }
+        }
+
+        for axis_pts in &mut module.axis_pts {
+            count += remove_ifdata_from_list(&mut axis_pts.if_data, names);
+        }
+        for blob in &mut module.blob {
+            count += remove_ifdata_from_list(&mut blob.if_data, names);
+        }
+        for characteristic in &mut module.characteristic {
+            count += remove_ifdata_from_list(&mut characteristic.if_data, names);
+        }
+        for frame in &mut module.frame {
+            count += remove_ifdata_from_list(&mut frame.if_data, names);
+        }
+        for function in &mut module.function {
+            count += remove_ifdata_from_list(&mut function.if_data, names);
+        }
+        for group in &mut module.group {
+            count += remove_ifdata_from_list(&mut group.if_data, names);
+        }
+        for instance in &mut module.instance {
+            count += remove_ifdata_from_list(&mut instance.if_data, names);
+        }
+        for measurement in &mut module.measurement {
+            count += remove_ifdata_from_list(&mut measurement.if_data, names);
+        }
+    }
+
+    count
+}
+
+fn remove_ifdata_from_list(ifdata_list: &mut Vec<IfData>, names: &[&str]) -> usize {
+    let len_before = ifdata_list.len();
+    ifdata_list.retain(|ifdata| !ifdata_tag(ifdata).is_some_and(|tag| names.contains(&tag)));
+    len_before - ifdata_list.len()
+}
+
+/// get the tag of the IF_DATA, which identifies the protocol or vendor.
+/// By convention the content of every IF_DATA is wrapped in a taggedunion.
+/// This is true both for IF_DATA that was parsed according to an A2ML specification,
+/// and for unknown IF_DATA, as long as its first element is an identifier.
+pub fn ifdata_tag(ifdata: &IfData) -> Option<&str> {
+    if let Some(GenericIfData::Block { items, .. }) = &ifdata.ifdata_items {
+        if let Some(GenericIfData::TaggedUnion(taggedunion)) = items.first() {
+            return taggedunion.keys().next().map(String::as_str);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_remove_ifdata() {
+        let a2l_text = r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin IF_DATA CANAPE_EXT 100 /end IF_DATA
+                /begin IF_DATA VENDOR_EXT 1 2 3 /end IF_DATA
+                /begin IF_DATA XCP /begin PROTOCOL_LAYER 0x0100 1 2 3 4 5 6 7 8 8 BYTE_ORDER_MSB_LAST ADDRESS_GRANULARITY_BYTE /end PROTOCOL_LAYER /end IF_DATA
+                /begin MEASUREMENT meas "" UBYTE NO_COMPU_METHOD 0 0 0 255
+                    /begin IF_DATA VENDOR_EXT 4 /end IF_DATA
+                    /begin IF_DATA CANAPE_EXT 100 /end IF_DATA
+                /end MEASUREMENT
+            /end MODULE
+        /end PROJECT"#;
+        let mut log_msgs = Vec::new();
+        let mut a2l_file = a2lfile::load_from_string(
+            a2l_text,
+            Some(crate::ifdata::A2MLVECTOR_TEXT.to_string()),
+            &mut log_msgs,
+            false,
+        )
+        .unwrap();
+        let module = &a2l_file.project.module[0];
+        assert_eq!(ifdata_tag(&module.if_data[0]), Some("CANAPE_EXT"));
+        assert_eq!(ifdata_tag(&module.if_data[1]), Some("VENDOR_EXT"));
+        assert_eq!(ifdata_tag(&module.if_data[2]), Some("XCP"));
+
+        let count = remove_ifdata(&mut a2l_file, &["VENDOR_EXT", "CANAPE_EXT"]);
+        assert_eq!(count, 4);
+        let module = &a2l_file.project.module[0];
+        assert_eq!(module.if_data.len(), 1);
+        assert_eq!(ifdata_tag(&module.if_data[0]), Some("XCP"));
+        assert!(module.measurement[0].if_data.is_empty());
+    }
+}
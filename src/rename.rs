@@ -0,0 +1,835 @@
+//! Renaming of objects, including all references to them
+
+use crate::A2lVersion;
+use a2lfile::{A2lFile, AxisDescr, Module, SymbolLink};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+
+/// The different kinds of names in a module. Each kind has its own namespace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum NameKind {
+    /// MEASUREMENT, CHARACTERISTIC, AXIS_PTS, BLOB and INSTANCE share one namespace
+    Object,
+    CompuMethod,
+    /// COMPU_TAB, COMPU_VTAB and COMPU_VTAB_RANGE
+    ConversionTable,
+    RecordLayout,
+    Unit,
+    Group,
+    Function,
+    /// all TYPEDEF_* blocks
+    Typedef,
+    Frame,
+    Transformer,
+    MemorySegment,
+}
+
+/// A set of renames for one module, which has been checked for conflicts
+#[derive(Debug, Clone, Default)]
+pub struct RenamePlan {
+    tables: HashMap<NameKind, HashMap<String, String>>,
+    /// descriptions of the renames that were rejected, because the new name is not a valid identifier,
+    /// or because it would collide with another name
+    pub rejected: Vec<String>,
+}
+
+impl RenamePlan {
+    /// the number of accepted renames
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.tables.values().map(HashMap::len).sum()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// get the new name of an item, if it will be renamed
+    #[must_use]
+    pub fn get(&self, kind: NameKind, name: &str) -> Option<&str> {
+        self.tables
+            .get(&kind)
+            .and_then(|table| table.get(name))
+            .map(String::as_str)
+    }
+}
+
+/// Determine the new names of the items in a module.
+///
+/// The function `new_name` is called for every named item in the module and returns the new name, or None if
+/// the item should keep its name. A rename is rejected if the new name is not a valid identifier, if the
+/// new name already exists, or if several items would receive the same name.
+pub fn plan_renames(
+    module: &Module,
+    mut new_name: impl FnMut(NameKind, &str) -> Option<String>,
+) -> RenamePlan {
+    let names = module_names(module);
+    let mut plan = RenamePlan::default();
+
+    let mut proposals: HashMap<NameKind, HashMap<String, String>> = HashMap::new();
+    for (kind, name) in &names {
+        if let Some(newname) = new_name(*kind, name) {
+            if newname == *name {
+                continue;
+            }
+            if is_valid_identifier(&newname) {
+                proposals
+                    .entry(*kind)
+                    .or_default()
+                    .insert((*name).to_string(), newname);
+            } else {
+                plan.rejected.push(format!(
+                    "{kind} {name}: \"{newname}\" is not a valid identifier"
+                ));
+            }
+        }
+    }
+
+    // each rejected rename means that the old name remains in use, which can cause further collisions
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (kind, table) in &mut proposals {
+            let mut targets: HashMap<&str, Vec<&str>> = HashMap::new();
+            for (old, new) in table.iter() {
+                targets.entry(new.as_str()).or_default().push(old.as_str());
+            }
+            let kept: HashSet<&str> = names
+                .iter()
+                .filter(|(k, name)| k == kind && !table.contains_key(*name))
+                .map(|(_, name)| *name)
+                .collect();
+
+            let mut reject: Vec<(Vec<String>, String)> = Vec::new();
+            for (new, olds) in &targets {
+                if olds.len() > 1 {
+                    let mut olds: Vec<String> = olds.iter().map(ToString::to_string).collect();
+                    olds.sort_unstable();
+                    let reason = format!(
+                        "{kind}s {} would all be renamed to \"{new}\"",
+                        olds.join(", ")
+                    );
+                    reject.push((olds, reason));
+                } else if kept.contains(new) {
+                    let reason = format!("{kind} {}: \"{new}\" already exists", olds[0]);
+                    reject.push((vec![olds[0].to_string()], reason));
+                }
+            }
+            for (olds, reason) in reject {
+                for old in olds {
+                    table.remove(&old);
+                }
+                plan.rejected.push(reason);
+                changed = true;
+            }
+        }
+    }
+    plan.rejected.sort();
+    plan.tables = proposals;
+    plan
+}
+
+/// Rename the items of a module according to the plan, and update all references to them.
+///
+/// If `add_symbol_links` is set, then a SYMBOL_LINK containing the old name is added to renamed objects
+/// that don't have one, so that the addresses can still be updated from the debug info.
+pub fn apply_renames(module: &mut Module, plan: &RenamePlan, add_symbol_links: bool) {
+    let empty = HashMap::new();
+    let table = |kind: NameKind| plan.tables.get(&kind).unwrap_or(&empty);
+    let objects = table(NameKind::Object);
+    let compu_methods = table(NameKind::CompuMethod);
+    let conversion_tables = table(NameKind::ConversionTable);
+    let record_layouts = table(NameKind::RecordLayout);
+    let units = table(NameKind::Unit);
+    let groups = table(NameKind::Group);
+    let functions = table(NameKind::Function);
+    let typedefs = table(NameKind::Typedef);
+    let frames = table(NameKind::Frame);
+    let transformers = table(NameKind::Transformer);
+    let memory_segments = table(NameKind::MemorySegment);
+
+    // objects keep the link to their original symbol
+    let rename_object = |name: &mut String, symbol_link: &mut Option<SymbolLink>| {
+        if let Some(newname) = objects.get(name) {
+            if add_symbol_links && symbol_link.is_none() {
+                *symbol_link = Some(SymbolLink::new(name.clone(), 0));
+            }
+            *name = newname.clone();
+        }
+    };
+
+    for measurement in &mut module.measurement {
+        rename_object(&mut measurement.name, &mut measurement.symbol_link);
+        rename(&mut measurement.conversion, compu_methods);
+        if let Some(var_virtual) = &mut measurement.var_virtual {
+            rename_list(&mut var_virtual.measuring_channel_list, objects);
+        }
+        if let Some(function_list) = &mut measurement.function_list {
+            rename_list(&mut function_list.name_list, functions);
+        }
+        if let Some(ref_memory_segment) = &mut measurement.ref_memory_segment {
+            rename(&mut ref_memory_segment.name, memory_segments);
+        }
+    }
+    for characteristic in &mut module.characteristic {
+        rename_object(&mut characteristic.name, &mut characteristic.symbol_link);
+        rename(&mut characteristic.deposit, record_layouts);
+        rename(&mut characteristic.conversion, compu_methods);
+        rename_axis_descr(&mut characteristic.axis_descr, objects, compu_methods);
+        if let Some(comparison_quantity) = &mut characteristic.comparison_quantity {
+            rename(&mut comparison_quantity.name, objects);
+        }
+        if let Some(dependent_characteristic) = &mut characteristic.dependent_characteristic {
+            rename_list(&mut dependent_characteristic.characteristic_list, objects);
+        }
+        if let Some(virtual_characteristic) = &mut characteristic.virtual_characteristic {
+            rename_list(&mut virtual_characteristic.characteristic_list, objects);
+        }
+        if let Some(map_list) = &mut characteristic.map_list {
+            rename_list(&mut map_list.name_list, objects);
+        }
+        if let Some(function_list) = &mut characteristic.function_list {
+            rename_list(&mut function_list.name_list, functions);
+        }
+        if let Some(ref_memory_segment) = &mut characteristic.ref_memory_segment {
+            rename(&mut ref_memory_segment.name, memory_segments);
+        }
+    }
+    for axis_pts in &mut module.axis_pts {
+        rename_object(&mut axis_pts.name, &mut axis_pts.symbol_link);
+        rename(&mut axis_pts.input_quantity, objects);
+        rename(&mut axis_pts.deposit_record, record_layouts);
+        rename(&mut axis_pts.conversion, compu_methods);
+        if let Some(function_list) = &mut axis_pts.function_list {
+            rename_list(&mut function_list.name_list, functions);
+        }
+        if let Some(ref_memory_segment) = &mut axis_pts.ref_memory_segment {
+            rename(&mut ref_memory_segment.name, memory_segments);
+        }
+    }
+    for blob in &mut module.blob {
+        rename_object(&mut blob.name, &mut blob.symbol_link);
+    }
+    for instance in &mut module.instance {
+        rename_object(&mut instance.name, &mut instance.symbol_link);
+        rename(&mut instance.type_ref, typedefs);
+    }
+
+    for compu_method in &mut module.compu_method {
+        rename(&mut compu_method.name, compu_methods);
+        if let Some(compu_tab_ref) = &mut compu_method.compu_tab_ref {
+            rename(&mut compu_tab_ref.conversion_table, conversion_tables);
+        }
+        if let Some(status_string_ref) = &mut compu_method.status_string_ref {
+            rename(&mut status_string_ref.conversion_table, conversion_tables);
+        }
+        if let Some(ref_unit) = &mut compu_method.ref_unit {
+            rename(&mut ref_unit.unit, units);
+        }
+    }
+    for compu_tab in &mut module.compu_tab {
+        rename(&mut compu_tab.name, conversion_tables);
+    }
+    for compu_vtab in &mut module.compu_vtab {
+        rename(&mut compu_vtab.name, conversion_tables);
+    }
+    for compu_vtab_range in &mut module.compu_vtab_range {
+        rename(&mut compu_vtab_range.name, conversion_tables);
+    }
+    for record_layout in &mut module.record_layout {
+        rename(&mut record_layout.name, record_layouts);
+    }
+    if let Some(s_rec_layout) = module
+        .mod_common
+        .as_mut()
+        .and_then(|mod_common| mod_common.s_rec_layout.as_mut())
+    {
+        rename(&mut s_rec_layout.name, record_layouts);
+    }
+    for unit in &mut module.unit {
+        rename(&mut unit.name, units);
+        if let Some(ref_unit) = &mut unit.ref_unit {
+            rename(&mut ref_unit.unit, units);
+        }
+    }
+
+    for group in &mut module.group {
+        rename(&mut group.name, groups);
+        if let Some(sub_group) = &mut group.sub_group {
+            rename_list(&mut sub_group.identifier_list, groups);
+        }
+        if let Some(ref_measurement) = &mut group.ref_measurement {
+            rename_list(&mut ref_measurement.identifier_list, objects);
+        }
+        if let Some(ref_characteristic) = &mut group.ref_characteristic {
+            rename_list(&mut ref_characteristic.identifier_list, objects);
+        }
+        if let Some(function_list) = &mut group.function_list {
+            rename_list(&mut function_list.name_list, functions);
+        }
+    }
+    for function in &mut module.function {
+        rename(&mut function.name, functions);
+        if let Some(sub_function) = &mut function.sub_function {
+            rename_list(&mut sub_function.identifier_list, functions);
+        }
+        if let Some(in_measurement) = &mut function.in_measurement {
+            rename_list(&mut in_measurement.identifier_list, objects);
+        }
+        if let Some(loc_measurement) = &mut function.loc_measurement {
+            rename_list(&mut loc_measurement.identifier_list, objects);
+        }
+        if let Some(out_measurement) = &mut function.out_measurement {
+            rename_list(&mut out_measurement.identifier_list, objects);
+        }
+        if let Some(def_characteristic) = &mut function.def_characteristic {
+            rename_list(&mut def_characteristic.identifier_list, objects);
+        }
+        if let Some(ref_characteristic) = &mut function.ref_characteristic {
+            rename_list(&mut ref_characteristic.identifier_list, objects);
+        }
+    }
+    for user_rights in &mut module.user_rights {
+        for ref_group in &mut user_rights.ref_group {
+            rename_list(&mut ref_group.identifier_list, groups);
+        }
+    }
+
+    for typedef_axis in &mut module.typedef_axis {
+        rename(&mut typedef_axis.name, typedefs);
+        rename(&mut typedef_axis.input_quantity, objects);
+        rename(&mut typedef_axis.record_layout, record_layouts);
+        rename(&mut typedef_axis.conversion, compu_methods);
+    }
+    for typedef_blob in &mut module.typedef_blob {
+        rename(&mut typedef_blob.name, typedefs);
+    }
+    for typedef_characteristic in &mut module.typedef_characteristic {
+        rename(&mut typedef_characteristic.name, typedefs);
+        rename(&mut typedef_characteristic.record_layout, record_layouts);
+        rename(&mut typedef_characteristic.conversion, compu_methods);
+        rename_axis_descr(
+            &mut typedef_characteristic.axis_descr,
+            objects,
+            compu_methods,
+        );
+    }
+    for typedef_measurement in &mut module.typedef_measurement {
+        rename(&mut typedef_measurement.name, typedefs);
+        rename(&mut typedef_measurement.conversion, compu_methods);
+    }
+    for typedef_structure in &mut module.typedef_structure {
+        rename(&mut typedef_structure.name, typedefs);
+        for structure_component in &mut typedef_structure.structure_component {
+            rename(&mut structure_component.component_type, typedefs);
+        }
+    }
+
+    for frame in &mut module.frame {
+        rename(&mut frame.name, frames);
+        if let Some(frame_measurement) = &mut frame.frame_measurement {
+            rename_list(&mut frame_measurement.identifier_list, objects);
+        }
+    }
+    for transformer in &mut module.transformer {
+        rename(&mut transformer.name, transformers);
+        rename(&mut transformer.inverse_transformer, transformers);
+        if let Some(transformer_in_objects) = &mut transformer.transformer_in_objects {
+            rename_list(&mut transformer_in_objects.identifier_list, objects);
+        }
+        if let Some(transformer_out_objects) = &mut transformer.transformer_out_objects {
+            rename_list(&mut transformer_out_objects.identifier_list, objects);
+        }
+    }
+    if let Some(mod_par) = &mut module.mod_par {
+        for memory_segment in &mut mod_par.memory_segment {
+            rename(&mut memory_segment.name, memory_segments);
+        }
+    }
+    if let Some(variant_coding) = &mut module.variant_coding {
+        for var_characteristic in &mut variant_coding.var_characteristic {
+            rename(&mut var_characteristic.name, objects);
+        }
+        for var_criterion in &mut variant_coding.var_criterion {
+            if let Some(var_measurement) = &mut var_criterion.var_measurement {
+                rename(&mut var_measurement.name, objects);
+            }
+            if let Some(var_selection_characteristic) =
+                &mut var_criterion.var_selection_characteristic
+            {
+                rename(&mut var_selection_characteristic.name, objects);
+            }
+        }
+    }
+}
+
+/// Rename items in all modules of the file, and update all references to them.
+///
+/// The function `new_name` is called for every named item and returns its new name, or None if it should not be renamed.
+/// Objects that are renamed keep a SYMBOL_LINK to their original symbol, if the file version supports it.
+///
+/// # Errors
+///
+/// If any rename would create an invalid identifier or a name collision, then no changes are made
+/// and an error message listing all problems is returned
+pub fn rename_all(
+    a2l_file: &mut A2lFile,
+    mut new_name: impl FnMut(NameKind, &str) -> Option<String>,
+) -> Result<usize, String> {
+    let plans: Vec<RenamePlan> = a2l_file
+        .project
+        .module
+        .iter()
+        .map(|module| plan_renames(module, &mut new_name))
+        .collect();
+    let rejected: Vec<&str> = plans
+        .iter()
+        .flat_map(|plan| &plan.rejected)
+        .map(String::as_str)
+        .collect();
+    if !rejected.is_empty() {
+        return Err(format!(
+            "Error: no objects were renamed, because some new names are invalid or collide:\n  {}",
+            rejected.join("\n  ")
+        ));
+    }
+
+    let add_symbol_links = A2lVersion::from(&*a2l_file) >= A2lVersion::V1_6_0;
+    let mut count = 0;
+    for (module, plan) in a2l_file.project.module.iter_mut().zip(&plans) {
+        apply_renames(module, plan, add_symbol_links);
+        count += plan.len();
+    }
+    Ok(count)
+}
+
+/// Rename items using a sed-style substitution expression like `s/OldPrefix_/NewPrefix_/`.
+///
+/// The pattern is a regex, and the replacement may refer to capture groups as `\1` or `$1`.
+/// Only the first match in each name is replaced, unless the flag `g` is given, e.g. `s/a/b/g`.
+/// Any character can be used as the delimiter instead of `/`.
+///
+/// # Errors
+///
+/// Returns an error message if the expression is invalid, or if the renames would cause collisions
+pub fn rename_with_expression(a2l_file: &mut A2lFile, expression: &str) -> Result<usize, String> {
+    let (regex, replacement, global) = parse_substitution(expression)?;
+    rename_all(a2l_file, |_, name| {
+        if !regex.is_match(name) {
+            return None;
+        }
+        let newname = if global {
+            regex.replace_all(name, replacement.as_str())
+        } else {
+            regex.replace(name, replacement.as_str())
+        };
+        Some(newname.into_owned())
+    })
+}
+
+/// Add a prefix and a suffix to the names of all items in the file, and update all references.
+///
+/// This places all items in their own "namespace", so that they can be merged into another file without conflicts.
+///
+/// # Errors
+///
+/// Returns an error message if any new name is too long or would collide with an existing name
+pub fn add_prefix_suffix(
+    a2l_file: &mut A2lFile,
+    prefix: &str,
+    suffix: &str,
+) -> Result<usize, String> {
+    rename_all(a2l_file, |_, name| Some(format!("{prefix}{name}{suffix}")))
+}
+
+/// Rename items according to a list of (old name, new name) pairs, and update all references.
+///
+/// Unlike [`rename_all`], renames that would cause a collision are skipped, while all other renames are applied.
+/// Entries that did not match any item and skipped renames are reported in `log_msgs`.
+pub fn rename_from_map(
+    a2l_file: &mut A2lFile,
+    rename_map: &[(String, String)],
+    log_msgs: &mut Vec<String>,
+) -> usize {
+    let map: HashMap<&str, &str> = rename_map
+        .iter()
+        .map(|(old, new)| (old.as_str(), new.as_str()))
+        .collect();
+    let mut matched = HashSet::new();
+    let add_symbol_links = A2lVersion::from(&*a2l_file) >= A2lVersion::V1_6_0;
+    let mut count = 0;
+    for module in &mut a2l_file.project.module {
+        let plan = plan_renames(module, |_, name| {
+            let newname = map.get(name)?;
+            matched.insert(name.to_string());
+            Some((*newname).to_string())
+        });
+        for reason in &plan.rejected {
+            log_msgs.push(format!("Rename skipped: {reason}"));
+        }
+        apply_renames(module, &plan, add_symbol_links);
+        count += plan.len();
+    }
+    for (old, _) in rename_map {
+        if !matched.contains(old) {
+            log_msgs.push(format!("Rename map entry {old} does not match any item"));
+        }
+    }
+    count
+}
+
+/// Parse a rename map, which contains one pair of names per line in the form `old_name,new_name`.
+///
+/// The names may also be separated by a semicolon or a tab. Empty lines and lines starting with '#' are ignored.
+///
+/// # Errors
+///
+/// Returns an error message if a line is invalid, or if an old name occurs more than once
+pub fn parse_rename_map(text: &str) -> Result<Vec<(String, String)>, String> {
+    let mut rename_map = Vec::new();
+    let mut seen = HashSet::new();
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line
+            .split([',', ';', '\t'])
+            .map(|field| field.trim().trim_matches('"'))
+            .collect();
+        let [old, new] = fields.as_slice() else {
+            return Err(format!(
+                "Error: invalid line {} in rename map: \"{line}\"; expected \"old_name,new_name\"",
+                idx + 1
+            ));
+        };
+        if !seen.insert(*old) {
+            return Err(format!(
+                "Error: {old} is renamed more than once in the rename map (line {})",
+                idx + 1
+            ));
+        }
+        rename_map.push(((*old).to_string(), (*new).to_string()));
+    }
+    Ok(rename_map)
+}
+
+/// Format a list of (old name, new name) pairs as a rename map, which can be read by [`parse_rename_map`]
+#[must_use]
+pub fn format_rename_map(rename_map: &[(String, String)]) -> String {
+    rename_map
+        .iter()
+        .map(|(old, new)| format!("{old},{new}\n"))
+        .collect()
+}
+
+// split an expression s/pattern/replacement/flags into its components
+fn parse_substitution(expression: &str) -> Result<(Regex, String, bool), String> {
+    let invalid = || {
+        format!(
+            "Error: invalid rename expression \"{expression}\", expected s/PATTERN/REPLACEMENT/"
+        )
+    };
+    let rest = expression.strip_prefix('s').ok_or_else(invalid)?;
+    let delimiter = rest.chars().next().ok_or_else(invalid)?;
+    let parts: Vec<&str> = rest[delimiter.len_utf8()..].split(delimiter).collect();
+    let [pattern, replacement, flags] = parts.as_slice() else {
+        return Err(invalid());
+    };
+    if pattern.is_empty() || flags.chars().any(|flag| flag != 'g') {
+        return Err(invalid());
+    }
+    let regex = Regex::new(pattern)
+        .map_err(|err| format!("Error: invalid regex \"{pattern}\" in rename expression: {err}"))?;
+    // sed uses \1 to refer to capture groups, while the regex crate uses ${1}
+    let backref = Regex::new(r"\\(\d)").unwrap();
+    let replacement = backref.replace_all(replacement, "$${$1}").into_owned();
+    Ok((regex, replacement, flags.contains('g')))
+}
+
+fn rename(name: &mut String, table: &HashMap<String, String>) {
+    if let Some(newname) = table.get(name) {
+        name.clone_from(newname);
+    }
+}
+
+fn rename_list(names: &mut [String], table: &HashMap<String, String>) {
+    for name in names {
+        rename(name, table);
+    }
+}
+
+fn rename_axis_descr(
+    axis_descr_list: &mut [AxisDescr],
+    objects: &HashMap<String, String>,
+    compu_methods: &HashMap<String, String>,
+) {
+    for axis_descr in axis_descr_list {
+        rename(&mut axis_descr.input_quantity, objects);
+        rename(&mut axis_descr.conversion, compu_methods);
+        if let Some(axis_pts_ref) = &mut axis_descr.axis_pts_ref {
+            rename(&mut axis_pts_ref.axis_points, objects);
+        }
+        if let Some(curve_axis_ref) = &mut axis_descr.curve_axis_ref {
+            rename(&mut curve_axis_ref.curve_axis, objects);
+        }
+    }
+}
+
+// all named items of the module, together with the namespace they belong to
+fn module_names<'a>(module: &'a Module) -> Vec<(NameKind, &'a str)> {
+    let mut names = Vec::new();
+    let mut add = |kind: NameKind, iter: &mut dyn Iterator<Item = &'a String>| {
+        names.extend(iter.map(|name| (kind, name.as_str())));
+    };
+    add(
+        NameKind::Object,
+        &mut module.measurement.iter().map(|m| &m.name),
+    );
+    add(
+        NameKind::Object,
+        &mut module.characteristic.iter().map(|c| &c.name),
+    );
+    add(
+        NameKind::Object,
+        &mut module.axis_pts.iter().map(|a| &a.name),
+    );
+    add(NameKind::Object, &mut module.blob.iter().map(|b| &b.name));
+    add(
+        NameKind::Object,
+        &mut module.instance.iter().map(|i| &i.name),
+    );
+    add(
+        NameKind::CompuMethod,
+        &mut module.compu_method.iter().map(|c| &c.name),
+    );
+    add(
+        NameKind::ConversionTable,
+        &mut module.compu_tab.iter().map(|c| &c.name),
+    );
+    add(
+        NameKind::ConversionTable,
+        &mut module.compu_vtab.iter().map(|c| &c.name),
+    );
+    add(
+        NameKind::ConversionTable,
+        &mut module.compu_vtab_range.iter().map(|c| &c.name),
+    );
+    add(
+        NameKind::RecordLayout,
+        &mut module.record_layout.iter().map(|r| &r.name),
+    );
+    add(NameKind::Unit, &mut module.unit.iter().map(|u| &u.name));
+    add(NameKind::Group, &mut module.group.iter().map(|g| &g.name));
+    add(
+        NameKind::Function,
+        &mut module.function.iter().map(|f| &f.name),
+    );
+    add(
+        NameKind::Typedef,
+        &mut module.typedef_axis.iter().map(|t| &t.name),
+    );
+    add(
+        NameKind::Typedef,
+        &mut module.typedef_blob.iter().map(|t| &t.name),
+    );
+    add(
+        NameKind::Typedef,
+        &mut module.typedef_characteristic.iter().map(|t| &t.name),
+    );
+    add(
+        NameKind::Typedef,
+        &mut module.typedef_measurement.iter().map(|t| &t.name),
+    );
+    add(
+        NameKind::Typedef,
+        &mut module.typedef_structure.iter().map(|t| &t.name),
+    );
+    add(NameKind::Frame, &mut module.frame.iter().map(|f| &f.name));
+    add(
+        NameKind::Transformer,
+        &mut module.transformer.iter().map(|t| &t.name),
+    );
+    if let Some(mod_par) = &module.mod_par {
+        add(
+            NameKind::MemorySegment,
+            &mut mod_par.memory_segment.iter().map(|m| &m.name),
+        );
+    }
+    names
+}
+
+// a2l identifiers consist of letters, digits, '_', '.', '[' and ']', must not start with a digit,
+// and may be at most 1024 characters long
+pub(crate) fn is_valid_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 1024
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '[' | ']'))
+}
+
+impl Display for NameKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            NameKind::Object => "object",
+            NameKind::CompuMethod => "COMPU_METHOD",
+            NameKind::ConversionTable => "conversion table",
+            NameKind::RecordLayout => "RECORD_LAYOUT",
+            NameKind::Unit => "UNIT",
+            NameKind::Group => "GROUP",
+            NameKind::Function => "FUNCTION",
+            NameKind::Typedef => "TYPEDEF",
+            NameKind::Frame => "FRAME",
+            NameKind::Transformer => "TRANSFORMER",
+            NameKind::MemorySegment => "MEMORY_SEGMENT",
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static A2L_TEXT: &str = r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin MEASUREMENT Old_speed "" UWORD Old_cm 0 0 0 255
+                /end MEASUREMENT
+                /begin MEASUREMENT Other "" UWORD NO_COMPU_METHOD 0 0 0 255
+                /end MEASUREMENT
+                /begin CHARACTERISTIC Old_curve "" CURVE 0x2000 Old_rl 0 Old_cm 0 255
+                    /begin AXIS_DESCR COM_AXIS Old_speed Old_cm 8 0 255
+                        AXIS_PTS_REF Old_axis
+                    /end AXIS_DESCR
+                /end CHARACTERISTIC
+                /begin AXIS_PTS Old_axis "" 0x3000 Old_speed Old_rl 0 Old_cm 8 0 255
+                    SYMBOL_LINK "axis_symbol" 0
+                /end AXIS_PTS
+                /begin COMPU_METHOD Old_cm "" IDENTICAL "%6.2" ""
+                /end COMPU_METHOD
+                /begin RECORD_LAYOUT Old_rl
+                    FNC_VALUES 1 UBYTE COLUMN_DIR DIRECT
+                /end RECORD_LAYOUT
+                /begin GROUP Old_group "" ROOT
+                    /begin REF_MEASUREMENT Old_speed Other /end REF_MEASUREMENT
+                    /begin REF_CHARACTERISTIC Old_curve /end REF_CHARACTERISTIC
+                /end GROUP
+            /end MODULE
+        /end PROJECT"#;
+
+    #[test]
+    fn test_rename_with_expression() {
+        let mut log_msgs = Vec::new();
+        let mut a2l_file = a2lfile::load_from_string(A2L_TEXT, None, &mut log_msgs, false).unwrap();
+        let count = rename_with_expression(&mut a2l_file, "s/^Old_/New_/").unwrap();
+        assert_eq!(count, 6);
+
+        let module = &a2l_file.project.module[0];
+        assert_eq!(module.measurement[0].name, "New_speed");
+        assert_eq!(module.measurement[0].conversion, "New_cm");
+        // the original name is kept as the symbol name
+        let symbol_link = module.measurement[0].symbol_link.as_ref().unwrap();
+        assert_eq!(symbol_link.symbol_name, "Old_speed");
+        // an existing SYMBOL_LINK is not changed
+        let symbol_link = module.axis_pts[0].symbol_link.as_ref().unwrap();
+        assert_eq!(symbol_link.symbol_name, "axis_symbol");
+        let characteristic = &module.characteristic[0];
+        assert_eq!(characteristic.deposit, "New_rl");
+        assert_eq!(characteristic.axis_descr[0].input_quantity, "New_speed");
+        let axis_pts_ref = characteristic.axis_descr[0].axis_pts_ref.as_ref().unwrap();
+        assert_eq!(axis_pts_ref.axis_points, "New_axis");
+        let ref_measurement = module.group[0].ref_measurement.as_ref().unwrap();
+        assert_eq!(ref_measurement.identifier_list, vec!["New_speed", "Other"]);
+        assert_eq!(module.group[0].name, "New_group");
+
+        let mut log_msgs = Vec::new();
+        a2l_file.check(&mut log_msgs);
+        assert!(log_msgs.is_empty(), "{log_msgs:?}");
+    }
+
+    #[test]
+    fn test_rename_collisions() {
+        let mut log_msgs = Vec::new();
+        let mut a2l_file = a2lfile::load_from_string(A2L_TEXT, None, &mut log_msgs, false).unwrap();
+        // Old_speed -> Other collides with the existing measurement Other
+        let result = rename_with_expression(&mut a2l_file, "s/Old_speed/Other/");
+        assert!(result.unwrap_err().contains("\"Other\" already exists"));
+        // all names would be the same
+        let result = rename_with_expression(&mut a2l_file, "s/.*/X/");
+        assert!(result.is_err());
+        let result = rename_with_expression(&mut a2l_file, "s/Old/1/");
+        assert!(result.unwrap_err().contains("not a valid identifier"));
+        assert_eq!(a2l_file.project.module[0].measurement[0].name, "Old_speed");
+
+        // renaming two objects so that they swap their names is allowed
+        let result = rename_all(&mut a2l_file, |_, name| match name {
+            "Old_speed" => Some("Other".to_string()),
+            "Other" => Some("Old_speed".to_string()),
+            _ => None,
+        });
+        assert_eq!(result, Ok(2));
+    }
+
+    #[test]
+    fn test_add_prefix_suffix() {
+        let mut log_msgs = Vec::new();
+        let mut a2l_file = a2lfile::load_from_string(A2L_TEXT, None, &mut log_msgs, false).unwrap();
+        let count = add_prefix_suffix(&mut a2l_file, "ecu1_", "").unwrap();
+        assert_eq!(count, 7);
+        let module = &a2l_file.project.module[0];
+        assert_eq!(module.measurement[1].name, "ecu1_Other");
+        assert_eq!(module.record_layout[0].name, "ecu1_Old_rl");
+        let ref_characteristic = module.group[0].ref_characteristic.as_ref().unwrap();
+        assert_eq!(ref_characteristic.identifier_list, vec!["ecu1_Old_curve"]);
+
+        let count = add_prefix_suffix(&mut a2l_file, "", "_x").unwrap();
+        assert_eq!(count, 7);
+        assert_eq!(
+            a2l_file.project.module[0].measurement[1].name,
+            "ecu1_Other_x"
+        );
+
+        // identifiers can be at most 1024 characters long
+        let long_suffix = "x".repeat(1020);
+        let result = add_prefix_suffix(&mut a2l_file, "", &long_suffix);
+        assert!(result.unwrap_err().contains("not a valid identifier"));
+    }
+
+    #[test]
+    fn test_rename_from_map() {
+        let mut log_msgs = Vec::new();
+        let mut a2l_file = a2lfile::load_from_string(A2L_TEXT, None, &mut log_msgs, false).unwrap();
+        let map_text = "# old,new\nOld_speed,VehicleSpeed\nOld_curve;Other\n\nMissing,Anything\n";
+        let rename_map = parse_rename_map(map_text).unwrap();
+        assert_eq!(rename_map.len(), 3);
+
+        let mut log_msgs = Vec::new();
+        let count = rename_from_map(&mut a2l_file, &rename_map, &mut log_msgs);
+        assert_eq!(count, 1);
+        assert_eq!(log_msgs.len(), 2);
+        assert!(log_msgs[0].contains("\"Other\" already exists"));
+        assert!(log_msgs[1].contains("Missing"));
+        let module = &a2l_file.project.module[0];
+        assert_eq!(module.measurement[0].name, "VehicleSpeed");
+        assert_eq!(module.characteristic[0].name, "Old_curve");
+
+        assert!(parse_rename_map("a,b,c").is_err());
+        assert!(parse_rename_map("a,b\na,c").is_err());
+    }
+
+    #[test]
+    fn test_parse_substitution() {
+        let (regex, replacement, global) = parse_substitution(r"s|(\w+)_x|\1_y|g").unwrap();
+        assert_eq!(regex.as_str(), r"(\w+)_x");
+        assert_eq!(replacement, "${1}_y");
+        assert!(global);
+        assert!(parse_substitution("s/a/b").is_err());
+        assert!(parse_substitution("x/a/b/").is_err());
+        assert!(parse_substitution("s/a/b/i").is_err());
+    }
+}
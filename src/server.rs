@@ -0,0 +1,570 @@
+use crate::watch::{file_state, FileState};
+use a2lfile::{A2lError, A2lFile};
+use a2ltool::{load, query, update, DebugData};
+use serde_json::{json, Map, Value};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+// JSON-RPC 2.0 error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+// the operation was valid, but it failed, e.g. because a file could not be loaded
+const OPERATION_FAILED: i64 = -32000;
+// the connection did not authenticate with the token of the server
+const NOT_AUTHENTICATED: i64 = -32001;
+
+// connections that don't send a request for this long are closed
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+// a valid JSON-RPC request
+struct Request {
+    id: Option<Value>,
+    method: String,
+    params: Map<String, Value>,
+}
+
+// a parsed file, together with the modification time and size of the file when it was loaded
+struct CachedFile<T> {
+    state: FileState,
+    data: T,
+}
+
+// an error response: the JSON-RPC error code and a message
+type RpcError = (i64, String);
+
+// The server keeps all a2l and elf files that were used by a request in memory. A file is only
+// loaded again if its modification time or size changes.
+pub(crate) struct Server {
+    a2l_files: HashMap<PathBuf, CachedFile<A2lFile>>,
+    elf_files: HashMap<PathBuf, CachedFile<DebugData>>,
+    strict: bool,
+    shutdown: bool,
+    // every connection must authenticate with this token before it can send requests
+    token: String,
+    // output files can only be written inside this directory
+    output_dir: PathBuf,
+    idle_timeout: Duration,
+}
+
+impl Server {
+    pub(crate) fn new(strict: bool, token: String, output_dir: PathBuf) -> Self {
+        Self {
+            a2l_files: HashMap::new(),
+            elf_files: HashMap::new(),
+            strict,
+            shutdown: false,
+            token,
+            output_dir,
+            idle_timeout: IDLE_TIMEOUT,
+        }
+    }
+
+    // handle one line of input, which contains a JSON-RPC request, and return the response.
+    // Notifications, i.e. requests without an id, don't get a response.
+    // If the line is not a valid request, the error response is returned as Err, and the connection must be closed.
+    pub(crate) fn handle_request(&mut self, line: &str) -> Result<Option<String>, String> {
+        let request = parse_request(line)?;
+        let result = match request.method.as_str() {
+            "check" => self.check(&request.params),
+            "update" => self.update(&request.params),
+            "query" => self.query(&request.params),
+            "diff" => self.diff(&request.params),
+            "write" => self.write(&request.params),
+            "clear_cache" => {
+                self.a2l_files.clear();
+                self.elf_files.clear();
+                Ok(json!({}))
+            }
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(json!({}))
+            }
+            method => Err((METHOD_NOT_FOUND, format!("unknown method \"{method}\""))),
+        };
+
+        Ok(request.id.map(|id| match result {
+            Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}).to_string(),
+            Err(error) => error_response(&id, error),
+        }))
+    }
+
+    // check the first request of a connection, which must be {"method": "authenticate", "params": {"token": TOKEN}}.
+    // The response is returned as Ok if the token is correct; otherwise the connection must be closed.
+    pub(crate) fn authenticate(&self, line: &str) -> Result<String, String> {
+        let request = parse_request(line)?;
+        let id = request.id.unwrap_or(Value::Null);
+        if request.method != "authenticate" {
+            return Err(error_response(
+                &id,
+                (
+                    NOT_AUTHENTICATED,
+                    "the first request must be \"authenticate\"".to_string(),
+                ),
+            ));
+        }
+        let token = get_str(&request.params, "token").unwrap_or_default();
+        if !constant_time_eq(token.as_bytes(), self.token.as_bytes()) {
+            return Err(error_response(
+                &id,
+                (NOT_AUTHENTICATED, "invalid token".to_string()),
+            ));
+        }
+        Ok(json!({"jsonrpc": "2.0", "id": id, "result": {}}).to_string())
+    }
+
+    // consistency check: {"a2l": FILE} -> {"problems": [...]}
+    fn check(&mut self, params: &Map<String, Value>) -> Result<Value, RpcError> {
+        let a2l_file = self.a2l_file(get_path(params, "a2l")?)?;
+        let mut log_msgs = Vec::new();
+        a2l_file.check(&mut log_msgs);
+        Ok(json!({"problems": log_msgs}))
+    }
+
+    // address update: {"a2l": FILE, "elf": FILE, "preserve": bool, "output": FILE}
+    // The update is performed on a copy, so that the cached file remains unchanged. The result is
+    // only written if an output file is given.
+    fn update(&mut self, params: &Map<String, Value>) -> Result<Value, RpcError> {
+        let preserve = get_bool(params, "preserve")?;
+        let output = get_optional_path(params, "output")?
+            .map(|output| self.output_path(&output))
+            .transpose()?;
+        let elf_path = get_path(params, "elf")?;
+        self.load_elf(&elf_path)?;
+        let mut a2l_file = self.a2l_file(get_path(params, "a2l")?)?.clone();
+        let debug_data = &self.elf_files[&elf_path].data;
+
+        let mut log_msgs = Vec::new();
+        let summary =
+            update::update_addresses(&mut a2l_file, debug_data, &mut log_msgs, preserve, false);
+        if let Some(output) = &output {
+            write_a2l(&mut a2l_file, output)?;
+        }
+        Ok(json!({
+            "measurement_updated": summary.measurement_updated,
+            "measurement_not_found": summary.measurement_not_updated,
+            "characteristic_updated": summary.characteristic_updated,
+            "characteristic_not_found": summary.characteristic_not_updated,
+            "axis_pts_updated": summary.axis_pts_updated,
+            "axis_pts_not_found": summary.axis_pts_not_updated,
+            "blob_updated": summary.blob_updated,
+            "blob_not_found": summary.blob_not_updated,
+            "instance_updated": summary.instance_updated,
+            "instance_not_found": summary.instance_not_updated,
+            "messages": log_msgs,
+        }))
+    }
+
+    // describe one object: {"a2l": FILE, "name": NAME, "elf": FILE} -> {"details": TEXT, "symbol": TEXT}
+    // The symbol is only described if an elf file is given
+    fn query(&mut self, params: &Map<String, Value>) -> Result<Value, RpcError> {
+        let name = get_str(params, "name")?;
+        let elf_path = get_optional_path(params, "elf")?;
+        if let Some(elf_path) = &elf_path {
+            self.load_elf(elf_path)?;
+        }
+        let a2l_path = get_path(params, "a2l")?;
+        self.a2l_file(a2l_path.clone())?;
+        let a2l_file = &self.a2l_files[&a2l_path].data;
+
+        let details =
+            query::object_details(a2l_file, name).map_err(|err| (OPERATION_FAILED, err))?;
+        let symbol = elf_path.map(|elf_path| {
+            let debug_data = &self.elf_files[&elf_path].data;
+            query::symbol_details(a2l_file, name, debug_data).unwrap_or_else(|err| err)
+        });
+        Ok(json!({"details": details, "symbol": symbol}))
+    }
+
+    // compare the objects of two files: {"a2l": FILE, "other": FILE} -> {"added": [...], "removed": [...], "changed": [...]}
+    // Objects are compared by address, data type and conversion.
+    fn diff(&mut self, params: &Map<String, Value>) -> Result<Value, RpcError> {
+        let path = get_path(params, "a2l")?;
+        let other_path = get_path(params, "other")?;
+        // both files are loaded into the cache first, so that they can be borrowed at the same time
+        self.a2l_file(path.clone())?;
+        self.a2l_file(other_path.clone())?;
+        let diff = query::diff(
+            &self.a2l_files[&path].data,
+            &self.a2l_files[&other_path].data,
+        );
+        Ok(diff.to_json())
+    }
+
+    // write a file: {"a2l": FILE, "output": FILE, "sort": bool}
+    fn write(&mut self, params: &Map<String, Value>) -> Result<Value, RpcError> {
+        let output = self.output_path(&get_path(params, "output")?)?;
+        let sort = get_bool(params, "sort")?;
+        let mut a2l_file = self.a2l_file(get_path(params, "a2l")?)?.clone();
+        if sort {
+            a2l_file.sort();
+        }
+        write_a2l(&mut a2l_file, &output)?;
+        Ok(json!({"output": output.to_string_lossy()}))
+    }
+
+    // Get the full path of an output file. Clients can only write files inside the output directory,
+    // so the path must be relative, and it must not leave the output directory with ".." or a symlink.
+    fn output_path(&self, output: &Path) -> Result<PathBuf, RpcError> {
+        let invalid = || {
+            (
+                INVALID_PARAMS,
+                format!(
+                    "the output file {} is not inside the output directory {}",
+                    output.display(),
+                    self.output_dir.display()
+                ),
+            )
+        };
+        if !output
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(invalid());
+        }
+        let path = self.output_dir.join(output);
+        let (Some(parent), Some(file_name)) = (path.parent(), path.file_name()) else {
+            return Err(invalid());
+        };
+        let output_dir = self
+            .output_dir
+            .canonicalize()
+            .map_err(|err| (OPERATION_FAILED, err.to_string()))?;
+        let parent = parent
+            .canonicalize()
+            .map_err(|err| (OPERATION_FAILED, format!("{}: {err}", parent.display())))?;
+        if !parent.starts_with(&output_dir) {
+            return Err(invalid());
+        }
+        Ok(parent.join(file_name))
+    }
+
+    // get an a2l file from the cache, or load it if it is not cached or has changed
+    fn a2l_file(&mut self, path: PathBuf) -> Result<&A2lFile, RpcError> {
+        let state = file_state(&path);
+        if self
+            .a2l_files
+            .get(&path)
+            .is_none_or(|cached| cached.state != state)
+        {
+            let mut log_msgs = Vec::<A2lError>::new();
+            let data = load::load_a2l(path.as_os_str(), None, self.strict, &mut log_msgs)
+                .map_err(|err| (OPERATION_FAILED, err))?;
+            self.a2l_files
+                .insert(path.clone(), CachedFile { state, data });
+        }
+        Ok(&self.a2l_files[&path].data)
+    }
+
+    // make sure that the current version of an elf file is in the cache
+    fn load_elf(&mut self, path: &Path) -> Result<(), RpcError> {
+        let state = file_state(path);
+        if self
+            .elf_files
+            .get(path)
+            .is_none_or(|cached| cached.state != state)
+        {
+            let data =
+                DebugData::load(path.as_os_str(), false).map_err(|err| (OPERATION_FAILED, err))?;
+            self.elf_files
+                .insert(path.to_path_buf(), CachedFile { state, data });
+        }
+        Ok(())
+    }
+}
+
+// Listen for connections on the local port and handle the requests of each connection.
+// Every line sent by the client is one request, and every response is sent as one line.
+// Connections are handled one at a time, so the requests never run concurrently. A connection that
+// does not send anything for IDLE_TIMEOUT is closed, so that an idle client can't block all others.
+//
+// Any program on the computer can connect to the port, including web pages in a browser. Therefore the
+// first request of each connection must contain the token that is created when the server starts, and
+// the connection is closed as soon as it sends something that is not a JSON-RPC request.
+pub(crate) fn run_server(
+    port: u16,
+    strict: bool,
+    token_file: Option<&Path>,
+    output_dir: PathBuf,
+) -> Result<(), String> {
+    let token = new_token();
+    if let Some(token_file) = token_file {
+        write_token_file(token_file, &token).map_err(|err| {
+            format!(
+                "Error: could not write the token file {}: {err}",
+                token_file.display()
+            )
+        })?;
+    }
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .map_err(|err| format!("Error: could not listen on port {port}: {err}"))?;
+    println!("a2ltool server listening on 127.0.0.1:{port}");
+    if token_file.is_none() {
+        println!("token: {token}");
+    }
+    let mut server = Server::new(strict, token, output_dir);
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(err) = handle_connection(&mut server, stream) {
+                    println!("Connection closed: {err}");
+                }
+            }
+            Err(err) => println!("Connection failed: {err}"),
+        }
+        if server.shutdown {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn handle_connection(server: &mut Server, stream: TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(server.idle_timeout))?;
+    let mut writer = stream.try_clone()?;
+    let mut authenticated = false;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let result = if authenticated {
+            server.handle_request(&line)
+        } else {
+            server.authenticate(&line).map(Some)
+        };
+        match result {
+            Ok(response) => {
+                authenticated = true;
+                if let Some(response) = response {
+                    writeln!(writer, "{response}")?;
+                    writer.flush()?;
+                }
+            }
+            Err(response) => {
+                writeln!(writer, "{response}")?;
+                writer.flush()?;
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "invalid request",
+                ));
+            }
+        }
+        if server.shutdown {
+            break;
+        }
+    }
+    Ok(())
+}
+
+// compare the token without returning early at the first difference, so that the response time
+// does not reveal how many leading characters of a guess are correct
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// create a token that can't be guessed.
+// The keys of RandomState are initialized from the random number generator of the operating system.
+fn new_token() -> String {
+    let time = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    (0..2u8)
+        .map(|idx| {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_u128(time);
+            hasher.write_u8(idx);
+            format!("{:016x}", hasher.finish())
+        })
+        .collect()
+}
+
+// write the token to a file that can only be read by the current user
+fn write_token_file(path: &Path, token: &str) -> std::io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path)?;
+    writeln!(file, "{token}")
+}
+
+// parse one line of input. If it is not a valid JSON-RPC request, the error response is returned
+fn parse_request(line: &str) -> Result<Request, String> {
+    let request: Value = serde_json::from_str(line).map_err(|err| {
+        error_response(&Value::Null, (PARSE_ERROR, format!("invalid JSON: {err}")))
+    })?;
+    let id = request.get("id").cloned();
+    let response_id = id.clone().unwrap_or(Value::Null);
+    let Some(method) = request.get("method").and_then(Value::as_str) else {
+        return Err(error_response(
+            &response_id,
+            (INVALID_REQUEST, "the request has no method".to_string()),
+        ));
+    };
+    let params = match request.get("params") {
+        None => Map::new(),
+        Some(Value::Object(params)) => params.clone(),
+        Some(_) => {
+            return Err(error_response(
+                &response_id,
+                (INVALID_PARAMS, "params must be an object".to_string()),
+            ))
+        }
+    };
+    Ok(Request {
+        id,
+        method: method.to_string(),
+        params,
+    })
+}
+
+fn error_response(id: &Value, (code, message): RpcError) -> String {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}}).to_string()
+}
+
+fn get_str<'a>(params: &'a Map<String, Value>, name: &str) -> Result<&'a str, RpcError> {
+    params
+        .get(name)
+        .and_then(Value::as_str)
+        .ok_or_else(|| (INVALID_PARAMS, format!("parameter \"{name}\" is required")))
+}
+
+fn get_path(params: &Map<String, Value>, name: &str) -> Result<PathBuf, RpcError> {
+    get_str(params, name).map(PathBuf::from)
+}
+
+fn get_optional_path(params: &Map<String, Value>, name: &str) -> Result<Option<PathBuf>, RpcError> {
+    match params.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(path)) => Ok(Some(PathBuf::from(path))),
+        Some(_) => Err((
+            INVALID_PARAMS,
+            format!("parameter \"{name}\" must be a string"),
+        )),
+    }
+}
+
+fn get_bool(params: &Map<String, Value>, name: &str) -> Result<bool, RpcError> {
+    match params.get(name) {
+        None | Some(Value::Null) => Ok(false),
+        Some(Value::Bool(value)) => Ok(*value),
+        Some(_) => Err((
+            INVALID_PARAMS,
+            format!("parameter \"{name}\" must be a boolean"),
+        )),
+    }
+}
+
+fn write_a2l(a2l_file: &mut A2lFile, output: &Path) -> Result<(), RpcError> {
+    a2l_file.sort_new_items();
+    let banner = format!("a2ltool {}", env!("CARGO_PKG_VERSION"));
+    a2l_file
+        .write(output.as_os_str(), Some(&banner))
+        .map_err(|err| (OPERATION_FAILED, err.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn request(server: &mut Server, line: &str) -> Value {
+        let response = server.handle_request(line).unwrap().unwrap();
+        serde_json::from_str(&response).unwrap()
+    }
+
+    #[test]
+    fn test_server_requests() {
+        let mut server = Server::new(false, "secret".to_string(), PathBuf::from("."));
+
+        let response = request(
+            &mut server,
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "check", "params": {"a2l": "tests/update_test1.a2l"}}"#,
+        );
+        assert_eq!(response["id"], 1);
+        assert!(response["result"]["problems"].is_array());
+        assert_eq!(server.a2l_files.len(), 1);
+
+        // the second request uses the cached file
+        let response = request(
+            &mut server,
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "diff", "params": {"a2l": "tests/update_test1.a2l", "other": "tests/update_test1.a2l"}}"#,
+        );
+        assert_eq!(response["result"]["added"], json!([]));
+        assert_eq!(response["result"]["changed"], json!([]));
+        assert_eq!(server.a2l_files.len(), 1);
+
+        let response = request(
+            &mut server,
+            r#"{"jsonrpc": "2.0", "id": 3, "method": "check", "params": {}}"#,
+        );
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+
+        let response = request(
+            &mut server,
+            r#"{"jsonrpc": "2.0", "id": 4, "method": "unknown"}"#,
+        );
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+
+        // output files outside of the output directory are rejected
+        for output in ["/tmp/out.a2l", "../out.a2l", "tests/../../out.a2l"] {
+            let line = json!({"jsonrpc": "2.0", "id": 5, "method": "write", "params": {"a2l": "tests/update_test1.a2l", "output": output}});
+            let response = request(&mut server, &line.to_string());
+            assert_eq!(response["error"]["code"], INVALID_PARAMS);
+        }
+
+        // invalid input is an error, and the connection is closed
+        let response = server.handle_request("POST / HTTP/1.1").unwrap_err();
+        let response: Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+
+        // notifications don't get a response
+        assert!(server
+            .handle_request(r#"{"jsonrpc": "2.0", "method": "clear_cache"}"#)
+            .unwrap()
+            .is_none());
+        assert!(server.a2l_files.is_empty());
+    }
+
+    #[test]
+    fn test_authenticate() {
+        let server = Server::new(false, new_token(), PathBuf::from("."));
+        assert_eq!(server.token.len(), 32);
+        assert_ne!(server.token, new_token());
+
+        let line = json!({"jsonrpc": "2.0", "id": 1, "method": "authenticate", "params": {"token": server.token}});
+        assert!(server.authenticate(&line.to_string()).is_ok());
+        let line = r#"{"jsonrpc": "2.0", "id": 1, "method": "authenticate", "params": {"token": "guess"}}"#;
+        assert!(server.authenticate(line).is_err());
+        let line = r#"{"jsonrpc": "2.0", "id": 1, "method": "shutdown"}"#;
+        assert!(server.authenticate(line).is_err());
+
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"abcd"));
+        assert!(!constant_time_eq(b"", b"abc"));
+    }
+
+    #[test]
+    fn test_idle_connection_is_closed() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let _client = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let mut server = Server::new(false, new_token(), PathBuf::from("."));
+        server.idle_timeout = Duration::from_millis(100);
+        // the client never sends anything, so the connection ends with a timeout instead of blocking forever
+        let err = handle_connection(&mut server, stream).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+        ));
+    }
+}
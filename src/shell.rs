@@ -0,0 +1,501 @@
+use a2lfile::{A2lError, A2lFile, A2lObjectName, Module};
+use a2ltool::{load, update, DebugData};
+use regex::Regex;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::ffi::{OsStr, OsString};
+
+const HELP_TEXT: &str = "Commands:
+  list [BLOCKTYPE] [REGEX]   list the names of all objects, optionally filtered by block type and name
+  show NAME                  show the a2l text of an object
+  remove REGEX               remove all MEASUREMENTs, CHARACTERISTICs, AXIS_PTS, BLOBs and INSTANCEs whose name matches
+  update ELFFILE [preserve]  update the addresses using the debug info of an elf file
+  check                      perform a consistency check
+  sort                       sort all elements of the file
+  write [FILE]               write the file; without FILE the input file is overwritten
+  help                       show this help
+  quit                       leave the shell";
+
+const COMMANDS: [&str; 9] = [
+    "list", "show", "remove", "update", "check", "sort", "write", "help", "quit",
+];
+
+// the block types that can be listed and shown. Only the first five can be removed
+const BLOCK_TYPES: [&str; 9] = [
+    "MEASUREMENT",
+    "CHARACTERISTIC",
+    "AXIS_PTS",
+    "BLOB",
+    "INSTANCE",
+    "COMPU_METHOD",
+    "FUNCTION",
+    "GROUP",
+    "RECORD_LAYOUT",
+];
+
+// call $func with the item list of the given block type in the module
+macro_rules! with_block_list {
+    ($module:expr, $blocktype:expr, $func:expr) => {
+        match $blocktype {
+            "MEASUREMENT" => $func(&mut $module.measurement),
+            "CHARACTERISTIC" => $func(&mut $module.characteristic),
+            "AXIS_PTS" => $func(&mut $module.axis_pts),
+            "BLOB" => $func(&mut $module.blob),
+            "INSTANCE" => $func(&mut $module.instance),
+            "COMPU_METHOD" => $func(&mut $module.compu_method),
+            "FUNCTION" => $func(&mut $module.function),
+            "GROUP" => $func(&mut $module.group),
+            "RECORD_LAYOUT" => $func(&mut $module.record_layout),
+            _ => unreachable!(),
+        }
+    };
+}
+
+pub(crate) struct Shell {
+    a2l_file: A2lFile,
+    filename: OsString,
+    modified: bool,
+}
+
+impl Shell {
+    pub(crate) fn new(a2l_file: A2lFile, filename: &OsStr) -> Self {
+        Self {
+            a2l_file,
+            filename: filename.to_os_string(),
+            modified: false,
+        }
+    }
+
+    // execute one command line and return the text that should be displayed
+    // Ok(None) is returned when the user wants to leave the shell
+    pub(crate) fn execute(&mut self, line: &str) -> Result<Option<String>, String> {
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else {
+            return Ok(Some(String::new()));
+        };
+        let args: Vec<&str> = words.collect();
+
+        let output = match (command, args.as_slice()) {
+            ("help", []) => HELP_TEXT.to_string(),
+            ("list", args) => self.list(args)?,
+            ("show", [name]) => self.show(name)?,
+            ("remove", [regex]) => self.remove(regex)?,
+            ("update", [elffile]) => self.update(elffile, false)?,
+            ("update", [elffile, "preserve"]) => self.update(elffile, true)?,
+            ("check", []) => {
+                let mut log_msgs = Vec::new();
+                self.a2l_file.check(&mut log_msgs);
+                if log_msgs.is_empty() {
+                    "Consistency check complete. No problems found.".to_string()
+                } else {
+                    log_msgs.join("\n")
+                }
+            }
+            ("sort", []) => {
+                self.a2l_file.sort();
+                self.modified = true;
+                "All objects have been sorted".to_string()
+            }
+            ("write", []) => self.write(&self.filename.clone())?,
+            ("write", [filename]) => self.write(OsStr::new(filename))?,
+            ("quit" | "exit", []) => {
+                if self.modified {
+                    println!("Warning: unsaved changes have been discarded");
+                }
+                return Ok(None);
+            }
+            _ => {
+                return Err(format!(
+                    "Error: invalid command \"{line}\". Type \"help\" for a list of commands."
+                ))
+            }
+        };
+        Ok(Some(output))
+    }
+
+    // the names of all objects that can be listed and shown, sorted and without duplicates
+    fn object_names(&mut self) -> Vec<String> {
+        let mut names = Vec::new();
+        for module in &mut self.a2l_file.project.module {
+            for blocktype in BLOCK_TYPES {
+                names.extend(with_block_list!(module, blocktype, get_names));
+            }
+        }
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    fn list(&mut self, args: &[&str]) -> Result<String, String> {
+        let (blocktypes, regex): (Vec<&str>, Option<&str>) = match args {
+            [] => (BLOCK_TYPES.to_vec(), None),
+            [arg] => {
+                let upper = arg.to_ascii_uppercase();
+                if let Some(blocktype) = BLOCK_TYPES.iter().find(|bt| **bt == upper) {
+                    (vec![blocktype], None)
+                } else {
+                    (BLOCK_TYPES.to_vec(), Some(arg))
+                }
+            }
+            [blocktype, regex] => {
+                let upper = blocktype.to_ascii_uppercase();
+                let Some(blocktype) = BLOCK_TYPES.iter().find(|bt| **bt == upper) else {
+                    return Err(format!("Error: unknown block type {blocktype}"));
+                };
+                (vec![blocktype], Some(regex))
+            }
+            _ => return Err("Error: too many arguments for list".to_string()),
+        };
+        let regex = regex.map(compile_regex).transpose()?;
+
+        let mut lines = Vec::new();
+        for module in &mut self.a2l_file.project.module {
+            for blocktype in &blocktypes {
+                let names = with_block_list!(module, *blocktype, get_names);
+                for name in names {
+                    if regex.as_ref().is_none_or(|regex| regex.is_match(&name)) {
+                        lines.push(format!("{blocktype:<15} {name}"));
+                    }
+                }
+            }
+        }
+        lines.push(format!("{} objects", lines.len()));
+        Ok(lines.join("\n"))
+    }
+
+    fn show(&mut self, name: &str) -> Result<String, String> {
+        let mut fragment = load::new_a2l();
+        let mut found = false;
+        for module in &mut self.a2l_file.project.module {
+            let target = &mut fragment.project.module[0];
+            for blocktype in BLOCK_TYPES {
+                found |= match blocktype {
+                    "MEASUREMENT" => copy_named(&module.measurement, &mut target.measurement, name),
+                    "CHARACTERISTIC" => {
+                        copy_named(&module.characteristic, &mut target.characteristic, name)
+                    }
+                    "AXIS_PTS" => copy_named(&module.axis_pts, &mut target.axis_pts, name),
+                    "BLOB" => copy_named(&module.blob, &mut target.blob, name),
+                    "INSTANCE" => copy_named(&module.instance, &mut target.instance, name),
+                    "COMPU_METHOD" => {
+                        copy_named(&module.compu_method, &mut target.compu_method, name)
+                    }
+                    "FUNCTION" => copy_named(&module.function, &mut target.function, name),
+                    "GROUP" => copy_named(&module.group, &mut target.group, name),
+                    _ => copy_named(&module.record_layout, &mut target.record_layout, name),
+                };
+            }
+        }
+        if !found {
+            return Err(format!("Error: no object named {name}"));
+        }
+
+        // write the fragment and keep only the content of the module
+        let text = fragment.write_to_string();
+        let body: Vec<&str> = text
+            .lines()
+            .skip_while(|line| !line.trim_start().starts_with("/begin MODULE"))
+            .skip(1)
+            .take_while(|line| !line.trim_start().starts_with("/end MODULE"))
+            .collect();
+        Ok(body.join("\n"))
+    }
+
+    fn remove(&mut self, regex: &str) -> Result<String, String> {
+        let regex = compile_regex(regex)?;
+        let mut count = 0;
+        for module in &mut self.a2l_file.project.module {
+            count += remove_matching(module, &regex);
+        }
+        if count > 0 {
+            self.modified = true;
+        }
+        Ok(format!("Removed {count} objects"))
+    }
+
+    fn update(&mut self, elffile: &str, preserve_unknown: bool) -> Result<String, String> {
+        let debug_data = DebugData::load(OsStr::new(elffile), false)?;
+        let mut log_msgs = Vec::new();
+        let summary = update::update_addresses(
+            &mut self.a2l_file,
+            &debug_data,
+            &mut log_msgs,
+            preserve_unknown,
+            false,
+        );
+        self.modified = true;
+        log_msgs.push(format!(
+            "Addresses updated: {} MEASUREMENTs, {} CHARACTERISTICs, {} AXIS_PTS, {} BLOBs, {} INSTANCEs",
+            summary.measurement_updated,
+            summary.characteristic_updated,
+            summary.axis_pts_updated,
+            summary.blob_updated,
+            summary.instance_updated
+        ));
+        Ok(log_msgs.join("\n"))
+    }
+
+    fn write(&mut self, filename: &OsStr) -> Result<String, String> {
+        self.a2l_file.sort_new_items();
+        let banner = format!("a2ltool {}", env!("CARGO_PKG_VERSION"));
+        self.a2l_file
+            .write(filename, Some(&banner))
+            .map_err(|err| err.to_string())?;
+        self.modified = false;
+        Ok(format!(
+            "Output written to \"{}\"",
+            filename.to_string_lossy()
+        ))
+    }
+}
+
+// line editing support for the shell: TAB completes commands, block types and object names
+struct ShellHelper {
+    // the names of all objects in the file, sorted
+    names: Vec<String>,
+}
+
+impl ShellHelper {
+    // find the start of the word before the cursor and the possible completions of this word
+    fn candidates(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        let before = &line[..pos];
+        let start = before
+            .rfind(|c: char| c.is_ascii_whitespace())
+            .map_or(0, |idx| idx + 1);
+        let word = &before[start..];
+        let previous_words: Vec<&str> = before[..start].split_whitespace().collect();
+        let candidates = match previous_words.as_slice() {
+            [] => COMMANDS
+                .iter()
+                .filter(|command| command.starts_with(word))
+                .map(ToString::to_string)
+                .collect(),
+            ["list"] => {
+                let upper = word.to_ascii_uppercase();
+                BLOCK_TYPES
+                    .iter()
+                    .filter(|blocktype| blocktype.starts_with(&upper))
+                    .map(|blocktype| blocktype.to_ascii_lowercase())
+                    .chain(self.names_with_prefix(word))
+                    .collect()
+            }
+            ["list", _] | ["show" | "remove"] => self.names_with_prefix(word).collect(),
+            _ => Vec::new(),
+        };
+        (start, candidates)
+    }
+
+    fn names_with_prefix<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = String> + 'a {
+        let first = self.names.partition_point(|name| name.as_str() < prefix);
+        self.names[first..]
+            .iter()
+            .take_while(move |name| name.starts_with(prefix))
+            .cloned()
+    }
+}
+
+impl Completer for ShellHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.candidates(line, pos))
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper {}
+
+impl Validator for ShellHelper {}
+
+impl Helper for ShellHelper {}
+
+// load the file and run the shell, reading commands until "quit" or end of input
+// The previous commands can be recalled with the arrow keys, and TAB completes names
+pub(crate) fn run_shell(filename: &OsStr, strict: bool) -> Result<(), String> {
+    let mut log_msgs = Vec::<A2lError>::new();
+    let a2l_file = load::load_a2l(filename, None, strict, &mut log_msgs)?;
+    for msg in log_msgs {
+        println!("{msg}");
+    }
+    let mut shell = Shell::new(a2l_file, filename);
+    println!(
+        "Loaded \"{}\". Type \"help\" for a list of commands.",
+        filename.to_string_lossy()
+    );
+
+    let mut editor = Editor::<ShellHelper, DefaultHistory>::new().map_err(|err| err.to_string())?;
+    editor.set_helper(Some(ShellHelper {
+        names: shell.object_names(),
+    }));
+    loop {
+        let line = match editor.readline("a2ltool> ") {
+            Ok(line) => line,
+            // Ctrl-C discards the current line
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => {
+                println!();
+                return Ok(());
+            }
+            Err(err) => return Err(format!("Error: could not read the input: {err}")),
+        };
+        if !line.trim().is_empty() {
+            let _ = editor.add_history_entry(line.as_str());
+        }
+        match shell.execute(&line) {
+            Ok(Some(output)) => {
+                if !output.is_empty() {
+                    println!("{output}");
+                }
+            }
+            Ok(None) => return Ok(()),
+            Err(err) => println!("{err}"),
+        }
+        // objects can only disappear or be renamed by these commands
+        if matches!(line.split_whitespace().next(), Some("remove" | "update")) {
+            if let Some(helper) = editor.helper_mut() {
+                helper.names = shell.object_names();
+            }
+        }
+    }
+}
+
+fn compile_regex(regex: &str) -> Result<Regex, String> {
+    // the regex must match the complete name
+    Regex::new(&format!("^(?:{regex})$"))
+        .map_err(|err| format!("Error: invalid regex \"{regex}\": {err}"))
+}
+
+fn get_names<T: A2lObjectName>(list: &[T]) -> Vec<String> {
+    list.iter()
+        .map(|item| item.get_name().to_string())
+        .collect()
+}
+
+fn copy_named<T: A2lObjectName + Clone>(list: &[T], target: &mut Vec<T>, name: &str) -> bool {
+    let items: Vec<T> = list
+        .iter()
+        .filter(|item| item.get_name() == name)
+        .cloned()
+        .collect();
+    let found = !items.is_empty();
+    target.extend(items);
+    found
+}
+
+fn remove_from_list<T: A2lObjectName>(list: &mut Vec<T>, regex: &Regex) -> usize {
+    let len_before = list.len();
+    list.retain(|item| !regex.is_match(item.get_name()));
+    len_before - list.len()
+}
+
+fn remove_matching(module: &mut Module, regex: &Regex) -> usize {
+    let mut count = 0;
+    for blocktype in &BLOCK_TYPES[0..5] {
+        count += with_block_list!(module, *blocktype, |list| remove_from_list(list, regex));
+    }
+    count
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_shell_commands() {
+        let mut log_msgs = Vec::new();
+        let a2l_file = load::load_a2l(
+            OsStr::new("tests/update_test1.a2l"),
+            None,
+            false,
+            &mut log_msgs,
+        )
+        .unwrap();
+        let instance_count = a2l_file.project.module[0].instance.len();
+        let instance_name = a2l_file.project.module[0].instance[0].name.clone();
+        let mut shell = Shell::new(a2l_file, OsStr::new("tests/update_test1.a2l"));
+
+        let output = shell.execute("list instance").unwrap().unwrap();
+        assert!(output.ends_with(&format!("{instance_count} objects")));
+
+        let output = shell
+            .execute(&format!("show {instance_name}"))
+            .unwrap()
+            .unwrap();
+        assert!(output.contains("/begin INSTANCE"));
+        assert!(shell.execute("show does_not_exist").is_err());
+
+        let output = shell
+            .execute(&format!("remove {instance_name}"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(output, "Removed 1 objects");
+        assert_eq!(
+            shell.a2l_file.project.module[0].instance.len(),
+            instance_count - 1
+        );
+
+        assert!(shell.execute("unknown command").is_err());
+        assert!(shell.execute("remove (").is_err());
+        assert!(shell.execute("quit").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_completer() {
+        let helper = ShellHelper {
+            names: vec![
+                "speed".to_string(),
+                "speed_limit".to_string(),
+                "temperature".to_string(),
+            ],
+        };
+        let history = DefaultHistory::new();
+        let ctx = Context::new(&history);
+        let complete = |line: &str| helper.complete(line, line.len(), &ctx).unwrap();
+
+        assert_eq!(complete("sh"), (0, vec!["show".to_string()]));
+        assert_eq!(
+            complete("show spe"),
+            (5, vec!["speed".to_string(), "speed_limit".to_string()])
+        );
+        assert_eq!(
+            complete("remove  temp"),
+            (8, vec!["temperature".to_string()])
+        );
+        assert_eq!(complete("list me"), (5, vec!["measurement".to_string()]));
+        assert_eq!(
+            complete("list measurement t"),
+            (17, vec!["temperature".to_string()])
+        );
+        // only the first argument of show is an object name
+        assert_eq!(complete("show speed s"), (11, Vec::new()));
+        assert_eq!(complete("show x"), (5, Vec::new()));
+
+        // the names are taken from the loaded file
+        let mut log_msgs = Vec::new();
+        let a2l_file = load::load_a2l(
+            OsStr::new("tests/update_test1.a2l"),
+            None,
+            false,
+            &mut log_msgs,
+        )
+        .unwrap();
+        let instance_name = a2l_file.project.module[0].instance[0].name.clone();
+        let mut shell = Shell::new(a2l_file, OsStr::new("tests/update_test1.a2l"));
+        let names = shell.object_names();
+        assert!(names.contains(&instance_name));
+        assert!(names.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
@@ -0,0 +1,385 @@
+//! Split the content of an a2l file into several include files
+
+use a2lfile::{A2lFile, A2lObject, A2lObjectName, Module};
+use std::collections::{HashMap, HashSet};
+
+/// How the objects of a module are distributed to include files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitBy {
+    /// one include file per root GROUP, containing the objects referenced by the group and its sub-groups
+    Group,
+    /// one include file each for the support objects, the measurements and the characteristics
+    Type,
+}
+
+/// An include file created by [`split_includes`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncludeFile {
+    /// file name of the include file, relative to the main file
+    pub filename: String,
+    /// the a2l text of all objects in the include file
+    pub text: String,
+}
+
+/// Move objects from the modules of the a2l file into include files.
+///
+/// The moved objects remain part of `a2l_file`, but they are marked so that the main file only contains
+/// `/include "filename"` directives for them. The content of each include file is returned and must be
+/// written next to the main file.
+/// All existing includes are merged first, so that each object is written to exactly one file.
+///
+/// With [`SplitBy::Type`] the files are named `{basename}_support.a2l` (COMPU_METHODs, COMPU_TABs,
+/// RECORD_LAYOUTs, UNITs and TYPEDEFs), `{basename}_measurements.a2l` and `{basename}_characteristics.a2l`
+/// (CHARACTERISTICs, AXIS_PTS, BLOBs and INSTANCEs).
+/// With [`SplitBy::Group`] there is one file `{basename}_{group}.a2l` per root GROUP. Objects that are
+/// referenced by several groups are placed in the file of the first group; objects without a group, the
+/// support objects and the GROUPs themselves stay in the main file.
+/// If the file contains several modules, the module name is added to each file name.
+pub fn split_includes(
+    a2l_file: &mut A2lFile,
+    split_by: SplitBy,
+    basename: &str,
+) -> Vec<IncludeFile> {
+    a2l_file.merge_includes();
+    let multi_module = a2l_file.project.module.len() > 1;
+    let mut include_files = Vec::new();
+    for module in &mut a2l_file.project.module {
+        let prefix = if multi_module {
+            format!("{basename}_{}", module.name)
+        } else {
+            basename.to_string()
+        };
+        match split_by {
+            SplitBy::Type => split_by_type(module, &prefix, &mut include_files),
+            SplitBy::Group => split_by_group(module, &prefix, &mut include_files),
+        }
+    }
+    include_files
+}
+
+fn split_by_type(module: &mut Module, prefix: &str, include_files: &mut Vec<IncludeFile>) {
+    let all = |_: &str| true;
+
+    let filename = make_include_filename(prefix, "support");
+    let mut include = Module::new(module.name.clone(), String::new());
+    move_items(
+        &mut module.compu_method,
+        &mut include.compu_method,
+        &filename,
+        all,
+    );
+    move_items(
+        &mut module.compu_tab,
+        &mut include.compu_tab,
+        &filename,
+        all,
+    );
+    move_items(
+        &mut module.compu_vtab,
+        &mut include.compu_vtab,
+        &filename,
+        all,
+    );
+    move_items(
+        &mut module.compu_vtab_range,
+        &mut include.compu_vtab_range,
+        &filename,
+        all,
+    );
+    move_items(
+        &mut module.record_layout,
+        &mut include.record_layout,
+        &filename,
+        all,
+    );
+    move_items(&mut module.unit, &mut include.unit, &filename, all);
+    move_items(
+        &mut module.typedef_axis,
+        &mut include.typedef_axis,
+        &filename,
+        all,
+    );
+    move_items(
+        &mut module.typedef_blob,
+        &mut include.typedef_blob,
+        &filename,
+        all,
+    );
+    move_items(
+        &mut module.typedef_characteristic,
+        &mut include.typedef_characteristic,
+        &filename,
+        all,
+    );
+    move_items(
+        &mut module.typedef_measurement,
+        &mut include.typedef_measurement,
+        &filename,
+        all,
+    );
+    move_items(
+        &mut module.typedef_structure,
+        &mut include.typedef_structure,
+        &filename,
+        all,
+    );
+    add_include_file(include, filename, include_files);
+
+    let filename = make_include_filename(prefix, "measurements");
+    let mut include = Module::new(module.name.clone(), String::new());
+    move_items(
+        &mut module.measurement,
+        &mut include.measurement,
+        &filename,
+        all,
+    );
+    add_include_file(include, filename, include_files);
+
+    let filename = make_include_filename(prefix, "characteristics");
+    let mut include = Module::new(module.name.clone(), String::new());
+    move_items(
+        &mut module.characteristic,
+        &mut include.characteristic,
+        &filename,
+        all,
+    );
+    move_items(&mut module.axis_pts, &mut include.axis_pts, &filename, all);
+    move_items(&mut module.blob, &mut include.blob, &filename, all);
+    move_items(&mut module.instance, &mut include.instance, &filename, all);
+    add_include_file(include, filename, include_files);
+}
+
+fn split_by_group(module: &mut Module, prefix: &str, include_files: &mut Vec<IncludeFile>) {
+    let owners = group_owners(module);
+    let mut root_groups: Vec<&String> = Vec::new();
+    for root_group in owners.values() {
+        if !root_groups.contains(&root_group) {
+            root_groups.push(root_group);
+        }
+    }
+    // keep the order of the groups in the file
+    root_groups.sort_by_key(|name| module.group.iter().position(|grp| &grp.name == *name));
+
+    for root_group in root_groups {
+        let filename = make_include_filename(prefix, root_group);
+        let selected = |name: &str| owners.get(name) == Some(root_group);
+        let mut include = Module::new(module.name.clone(), String::new());
+        move_items(
+            &mut module.measurement,
+            &mut include.measurement,
+            &filename,
+            selected,
+        );
+        move_items(
+            &mut module.characteristic,
+            &mut include.characteristic,
+            &filename,
+            selected,
+        );
+        move_items(
+            &mut module.axis_pts,
+            &mut include.axis_pts,
+            &filename,
+            selected,
+        );
+        move_items(&mut module.blob, &mut include.blob, &filename, selected);
+        move_items(
+            &mut module.instance,
+            &mut include.instance,
+            &filename,
+            selected,
+        );
+        add_include_file(include, filename, include_files);
+    }
+}
+
+// map the name of each object to the root group that contains it, either directly or through its sub-groups
+fn group_owners(module: &Module) -> HashMap<String, String> {
+    let groups: HashMap<&str, &a2lfile::Group> = module
+        .group
+        .iter()
+        .map(|grp| (grp.name.as_str(), grp))
+        .collect();
+    let sub_groups: HashSet<&str> = module
+        .group
+        .iter()
+        .filter_map(|grp| grp.sub_group.as_ref())
+        .flat_map(|sub_group| &sub_group.identifier_list)
+        .map(String::as_str)
+        .collect();
+
+    let mut owners = HashMap::new();
+    for root in module
+        .group
+        .iter()
+        .filter(|grp| grp.root.is_some() || !sub_groups.contains(grp.name.as_str()))
+    {
+        let mut visited = HashSet::new();
+        let mut pending = vec![root.name.as_str()];
+        while let Some(group_name) = pending.pop() {
+            let Some(group) = groups.get(group_name) else {
+                continue;
+            };
+            if !visited.insert(group_name) {
+                continue;
+            }
+            let ref_meas = group
+                .ref_measurement
+                .iter()
+                .flat_map(|ref_meas| &ref_meas.identifier_list);
+            let ref_char = group
+                .ref_characteristic
+                .iter()
+                .flat_map(|ref_char| &ref_char.identifier_list);
+            for name in ref_meas.chain(ref_char) {
+                owners
+                    .entry(name.clone())
+                    .or_insert_with(|| root.name.clone());
+            }
+            if let Some(sub_group) = &group.sub_group {
+                pending.extend(sub_group.identifier_list.iter().rev().map(String::as_str));
+            }
+        }
+    }
+    owners
+}
+
+// copy the selected items into the include module, and mark the originals so that they are written as /include
+fn move_items<T, L>(
+    items: &mut [T],
+    include_items: &mut Vec<T>,
+    filename: &str,
+    selected: impl Fn(&str) -> bool,
+) where
+    T: A2lObject<L> + A2lObjectName + Clone,
+{
+    for item in items.iter_mut().filter(|item| selected(item.get_name())) {
+        include_items.push(item.clone());
+        item.get_layout_mut().incfile = Some(filename.to_string());
+    }
+}
+
+fn add_include_file(include: Module, filename: String, include_files: &mut Vec<IncludeFile>) {
+    let text = module_items_text(include);
+    if !text.is_empty() {
+        include_files.push(IncludeFile { filename, text });
+    }
+}
+
+// write the items of a module without the enclosing MODULE block
+fn module_items_text(module: Module) -> String {
+    let mut a2l_file = a2lfile::new();
+    a2l_file.project.module[0] = module;
+    let text = a2l_file.write_to_string();
+    let start = text
+        .find("/begin MODULE")
+        .and_then(|pos| text[pos..].find('\n').map(|newline| pos + newline + 1))
+        .unwrap_or(text.len());
+    let end = text.rfind("/end MODULE").unwrap_or(text.len()).max(start);
+    let items_text = text[start..end].trim_start_matches('\n').trim_end();
+    if items_text.is_empty() {
+        return String::new();
+    }
+
+    // remove the indentation of the module level
+    let indent_len = items_text.len() - items_text.trim_start().len();
+    let indent = &items_text[..indent_len];
+    let mut result = String::with_capacity(items_text.len());
+    for line in items_text.lines() {
+        result.push_str(line.strip_prefix(indent).unwrap_or(line));
+        result.push('\n');
+    }
+    result
+}
+
+// build the name of an include file from characters that are valid in file names on all systems
+fn make_include_filename(prefix: &str, part: &str) -> String {
+    let part: String = part
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{prefix}_{part}.a2l")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_split_includes() {
+        let text = r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin COMPU_METHOD cm "" IDENTICAL "%4.2" "unit"
+                /end COMPU_METHOD
+                /begin MEASUREMENT engine_speed "" UWORD cm 0 0 0 10000
+                /end MEASUREMENT
+                /begin MEASUREMENT gear "" UBYTE NO_COMPU_METHOD 0 0 0 10
+                /end MEASUREMENT
+                /begin MEASUREMENT ungrouped "" UBYTE NO_COMPU_METHOD 0 0 0 10
+                /end MEASUREMENT
+                /begin CHARACTERISTIC ignition_map "" VALUE 0x1000 RL 0 NO_COMPU_METHOD 0 100
+                /end CHARACTERISTIC
+                /begin RECORD_LAYOUT RL
+                    FNC_VALUES 1 UBYTE ROW_DIR DIRECT
+                /end RECORD_LAYOUT
+                /begin GROUP engine ""
+                    ROOT
+                    /begin REF_CHARACTERISTIC ignition_map
+                    /end REF_CHARACTERISTIC
+                    /begin SUB_GROUP engine.sensors
+                    /end SUB_GROUP
+                /end GROUP
+                /begin GROUP engine.sensors ""
+                    /begin REF_MEASUREMENT engine_speed
+                    /end REF_MEASUREMENT
+                /end GROUP
+                /begin GROUP transmission ""
+                    ROOT
+                    /begin REF_MEASUREMENT gear engine_speed
+                    /end REF_MEASUREMENT
+                /end GROUP
+            /end MODULE
+        /end PROJECT"#;
+        let mut load_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(text, None, &mut load_msgs, true).unwrap();
+
+        let mut by_type = a2l_file.clone();
+        let include_files = split_includes(&mut by_type, SplitBy::Type, "out");
+        assert_eq!(include_files.len(), 3);
+        assert_eq!(include_files[0].filename, "out_support.a2l");
+        assert!(include_files[0].text.starts_with("/begin COMPU_METHOD cm"));
+        assert!(include_files[0].text.contains("/begin RECORD_LAYOUT RL"));
+        assert_eq!(include_files[1].filename, "out_measurements.a2l");
+        assert_eq!(
+            include_files[1].text.matches("/begin MEASUREMENT").count(),
+            3
+        );
+        assert_eq!(include_files[2].filename, "out_characteristics.a2l");
+        let main_text = by_type.write_to_string();
+        assert!(main_text.contains("/include \"out_measurements.a2l\""));
+        assert!(!main_text.contains("/begin MEASUREMENT"));
+        assert!(main_text.contains("/begin GROUP engine"));
+
+        let mut by_group = a2l_file.clone();
+        let include_files = split_includes(&mut by_group, SplitBy::Group, "out");
+        assert_eq!(include_files.len(), 2);
+        assert_eq!(include_files[0].filename, "out_engine.a2l");
+        assert!(include_files[0].text.contains("engine_speed"));
+        assert!(include_files[0].text.contains("ignition_map"));
+        assert_eq!(include_files[1].filename, "out_transmission.a2l");
+        assert!(include_files[1].text.contains("gear"));
+        assert!(!include_files[1].text.contains("engine_speed"));
+        let main_text = by_group.write_to_string();
+        assert!(main_text.contains("/begin MEASUREMENT ungrouped"));
+        assert!(main_text.contains("/begin COMPU_METHOD cm"));
+        assert!(!main_text.contains("/begin MEASUREMENT gear"));
+    }
+}
@@ -0,0 +1,328 @@
+//! Statistics about the content of an a2l file
+
+use crate::load::include_tree;
+use crate::memmap;
+use crate::query::fragment_text;
+use crate::remove_ifdata::ifdata_tag;
+use a2lfile::{A2lFile, IfData, Module};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::ffi::OsStr;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+/// Statistics about an a2l file, see [`collect_statistics`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Statistics {
+    /// number of objects of each block type, in the order of the a2l specification
+    pub object_counts: Vec<(&'static str, usize)>,
+    /// number of IF_DATA blocks and their total size in bytes for each IF_DATA tag
+    pub ifdata: BTreeMap<String, (usize, usize)>,
+    /// number of distinct include files; None if the file was not loaded from disk
+    pub include_files: Option<usize>,
+    /// maximum nesting depth of the include files; 0 if there are no includes
+    pub include_depth: Option<usize>,
+    /// total size of all CHARACTERISTICs and AXIS_PTS in bytes
+    pub calibration_bytes: u64,
+    /// number of MEASUREMENTs and CHARACTERISTICs for each data type
+    pub datatypes: BTreeMap<String, (usize, usize)>,
+}
+
+/// Collect statistics about the objects in an a2l file.
+///
+/// If the name of the file is given, its include structure is analyzed by reading
+/// the file and all files included by it. Included files are found in the same way as
+/// by [`crate::load::load_a2l_with_include_dirs`], using the `include_dirs`.
+#[must_use]
+pub fn collect_statistics(
+    a2l_file: &A2lFile,
+    filename: Option<&OsStr>,
+    include_dirs: &[PathBuf],
+) -> Statistics {
+    let mut stats = Statistics::default();
+    let modules = &a2l_file.project.module;
+    let count = |get_len: fn(&Module) -> usize| modules.iter().map(get_len).sum::<usize>();
+    stats.object_counts = vec![
+        ("MODULE", modules.len()),
+        ("MEASUREMENT", count(|m| m.measurement.len())),
+        ("CHARACTERISTIC", count(|m| m.characteristic.len())),
+        ("AXIS_PTS", count(|m| m.axis_pts.len())),
+        ("BLOB", count(|m| m.blob.len())),
+        ("INSTANCE", count(|m| m.instance.len())),
+        ("GROUP", count(|m| m.group.len())),
+        ("FUNCTION", count(|m| m.function.len())),
+        ("COMPU_METHOD", count(|m| m.compu_method.len())),
+        (
+            "COMPU_(V)TAB",
+            count(|m| m.compu_tab.len() + m.compu_vtab.len() + m.compu_vtab_range.len()),
+        ),
+        ("RECORD_LAYOUT", count(|m| m.record_layout.len())),
+        (
+            "TYPEDEF",
+            count(|m| {
+                m.typedef_axis.len()
+                    + m.typedef_blob.len()
+                    + m.typedef_characteristic.len()
+                    + m.typedef_measurement.len()
+                    + m.typedef_structure.len()
+            }),
+        ),
+        ("UNIT", count(|m| m.unit.len())),
+        ("FRAME", count(|m| m.frame.len())),
+        ("TRANSFORMER", count(|m| m.transformer.len())),
+    ];
+
+    for module in modules {
+        for measurement in &module.measurement {
+            stats
+                .datatypes
+                .entry(measurement.datatype.to_string())
+                .or_default()
+                .0 += 1;
+        }
+        for characteristic in &module.characteristic {
+            let datatype = module
+                .record_layout
+                .iter()
+                .find(|rl| rl.name == characteristic.deposit)
+                .and_then(|rl| rl.fnc_values.as_ref())
+                .map_or_else(|| "unknown".to_string(), |fnc| fnc.datatype.to_string());
+            stats.datatypes.entry(datatype).or_default().1 += 1;
+            stats.calibration_bytes +=
+                u64::from(memmap::characteristic_size(module, characteristic));
+        }
+        for axis_pts in &module.axis_pts {
+            stats.calibration_bytes += u64::from(memmap::axis_pts_size(module, axis_pts));
+        }
+        collect_ifdata_sizes(module, &mut stats.ifdata);
+    }
+
+    if let Some(filename) = filename {
+        let tree = include_tree(Path::new(filename), include_dirs);
+        // the main file itself is not an include file
+        stats.include_files = Some(tree.len().saturating_sub(1));
+        stats.include_depth = Some(include_depth(
+            &tree,
+            Path::new(filename),
+            &mut HashSet::new(),
+        ));
+    }
+
+    stats
+}
+
+impl Statistics {
+    /// Convert the statistics to JSON.
+    ///
+    /// The object counts are given as `{"MEASUREMENT": 2, ...}`, the data types as
+    /// `{"UWORD": {"measurements": 2, "characteristics": 0}, ...}` and the IF_DATA as `{"XCP": {"blocks": 1, "bytes": 100}, ...}`.
+    #[must_use]
+    pub fn to_json(&self) -> Value {
+        let objects: Map<String, Value> = self
+            .object_counts
+            .iter()
+            .map(|(blocktype, count)| ((*blocktype).to_string(), json!(count)))
+            .collect();
+        let datatypes: Map<String, Value> = self
+            .datatypes
+            .iter()
+            .map(|(datatype, (meas_count, char_count))| {
+                (
+                    datatype.clone(),
+                    json!({"measurements": meas_count, "characteristics": char_count}),
+                )
+            })
+            .collect();
+        let ifdata: Map<String, Value> = self
+            .ifdata
+            .iter()
+            .map(|(tag, (count, size))| (tag.clone(), json!({"blocks": count, "bytes": size})))
+            .collect();
+        json!({
+            "objects": objects,
+            "calibration_bytes": self.calibration_bytes,
+            "datatypes": datatypes,
+            "ifdata": ifdata,
+            "include_files": self.include_files,
+            "include_depth": self.include_depth,
+        })
+    }
+}
+
+// count the IF_DATA blocks of each tag, and measure the size of their a2l text
+fn collect_ifdata_sizes(module: &Module, ifdata_stats: &mut BTreeMap<String, (usize, usize)>) {
+    let mut all_ifdata: Vec<&IfData> = module.if_data.iter().collect();
+    if let Some(mod_par) = &module.mod_par {
+        for memory_layout in &mod_par.memory_layout {
+            all_ifdata.extend(&memory_layout.if_data);
+        }
+        for memory_segment in &mod_par.memory_segment {
+            all_ifdata.extend(&memory_segment.if_data);
+        }
+    }
+    for axis_pts in &module.axis_pts {
+        all_ifdata.extend(&axis_pts.if_data);
+    }
+    for blob in &module.blob {
+        all_ifdata.extend(&blob.if_data);
+    }
+    for characteristic in &module.characteristic {
+        all_ifdata.extend(&characteristic.if_data);
+    }
+    for frame in &module.frame {
+        all_ifdata.extend(&frame.if_data);
+    }
+    for function in &module.function {
+        all_ifdata.extend(&function.if_data);
+    }
+    for group in &module.group {
+        all_ifdata.extend(&group.if_data);
+    }
+    for instance in &module.instance {
+        all_ifdata.extend(&instance.if_data);
+    }
+    for measurement in &module.measurement {
+        all_ifdata.extend(&measurement.if_data);
+    }
+
+    let mut by_tag: BTreeMap<&str, Vec<IfData>> = BTreeMap::new();
+    for ifdata in all_ifdata {
+        let tag = ifdata_tag(ifdata).unwrap_or("<unknown>");
+        by_tag.entry(tag).or_default().push(ifdata.clone());
+    }
+    for (tag, ifdata_list) in by_tag {
+        let count = ifdata_list.len();
+        let text = fragment_text(|fm| fm.if_data = ifdata_list);
+        let entry = ifdata_stats.entry(tag.to_string()).or_default();
+        entry.0 += count;
+        entry.1 += text.len();
+    }
+}
+
+// follow the includes of a file in the include tree and return the maximum nesting depth.
+// Every file that was visited is added to visited, which also prevents endless recursion
+fn include_depth(
+    tree: &[(PathBuf, Vec<(String, PathBuf)>)],
+    file: &Path,
+    visited: &mut HashSet<PathBuf>,
+) -> usize {
+    if !visited.insert(file.to_path_buf()) {
+        return 0;
+    }
+    let Some((_, includes)) = tree.iter().find(|(tree_file, _)| tree_file == file) else {
+        return 0;
+    };
+    includes
+        .iter()
+        .map(|(_, incfile)| 1 + include_depth(tree, incfile, visited))
+        .max()
+        .unwrap_or(0)
+}
+
+impl Display for Statistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Objects:")?;
+        for (blocktype, count) in &self.object_counts {
+            writeln!(f, "  {blocktype:<16} {count:>8}")?;
+        }
+        writeln!(f, "Calibration data: {} bytes", self.calibration_bytes)?;
+        if let (Some(files), Some(depth)) = (self.include_files, self.include_depth) {
+            writeln!(f, "Include files: {files}, maximum nesting depth {depth}")?;
+        }
+        writeln!(f, "Data types:          MEASUREMENT  CHARACTERISTIC")?;
+        for (datatype, (meas_count, char_count)) in &self.datatypes {
+            writeln!(f, "  {datatype:<16} {meas_count:>12}  {char_count:>14}")?;
+        }
+        write!(f, "IF_DATA:")?;
+        if self.ifdata.is_empty() {
+            write!(f, " none")?;
+        }
+        for (tag, (count, size)) in &self.ifdata {
+            write!(f, "\n  {tag:<16} {count:>8} blocks, {size:>10} bytes")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_statistics() {
+        let a2l_text = r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin MEASUREMENT m1 "" UWORD NO_COMPU_METHOD 0 0 0 255
+                    /begin IF_DATA CANAPE_EXT 100 /end IF_DATA
+                /end MEASUREMENT
+                /begin MEASUREMENT m2 "" UWORD NO_COMPU_METHOD 0 0 0 255
+                /end MEASUREMENT
+                /begin CHARACTERISTIC c1 "" VAL_BLK 0x2000 RL_ULONG 0 NO_COMPU_METHOD 0 255
+                    NUMBER 3
+                /end CHARACTERISTIC
+                /begin RECORD_LAYOUT RL_ULONG
+                    FNC_VALUES 1 ULONG COLUMN_DIR DIRECT
+                /end RECORD_LAYOUT
+            /end MODULE
+        /end PROJECT"#;
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(a2l_text, None, &mut log_msgs, false).unwrap();
+
+        let stats = collect_statistics(&a2l_file, None, &[]);
+        assert!(stats.object_counts.contains(&("MEASUREMENT", 2)));
+        assert!(stats.object_counts.contains(&("CHARACTERISTIC", 1)));
+        assert_eq!(stats.calibration_bytes, 12);
+        assert_eq!(stats.datatypes["UWORD"], (2, 0));
+        assert_eq!(stats.datatypes["ULONG"], (0, 1));
+        assert_eq!(stats.ifdata["CANAPE_EXT"].0, 1);
+        assert!(stats.ifdata["CANAPE_EXT"].1 > 0);
+        assert!(stats.to_string().contains("Calibration data: 12 bytes"));
+    }
+
+    #[test]
+    fn test_include_statistics() {
+        let dir = std::env::temp_dir().join(format!("a2ltool_statistics_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("a2l")).unwrap();
+        std::fs::create_dir_all(dir.join("common")).unwrap();
+        let main_file = dir.join("a2l/main.a2l");
+        std::fs::write(
+            &main_file,
+            r#"ASAP2_VERSION 1 71
+            /begin PROJECT p ""
+              /begin MODULE m ""
+                /include "shared.a2l"
+                /* /include "old.a2l" */
+              /end MODULE
+            /end PROJECT"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("common/shared.a2l"),
+            "/include \"$(A2LTOOL_TEST_STATISTICS_ROOT)/common/leaf.a2l\"",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("common/leaf.a2l"),
+            "/begin MEASUREMENT m1 \"\" UBYTE NO_COMPU_METHOD 0 0 0 255 /end MEASUREMENT",
+        )
+        .unwrap();
+        std::env::set_var("A2LTOOL_TEST_STATISTICS_ROOT", &dir);
+
+        let a2l_file = a2lfile::new();
+        // shared.a2l is only found in the include directory, and it refers to leaf.a2l through a variable
+        let without_dirs = collect_statistics(&a2l_file, Some(main_file.as_os_str()), &[]);
+        let with_dirs = collect_statistics(
+            &a2l_file,
+            Some(main_file.as_os_str()),
+            &[dir.join("common")],
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(without_dirs.include_files, Some(0));
+        assert_eq!(without_dirs.include_depth, Some(0));
+        assert_eq!(with_dirs.include_files, Some(2));
+        assert_eq!(with_dirs.include_depth, Some(2));
+    }
+}
@@ -0,0 +1,182 @@
+use a2ltool::checksum::{calculate_checksum, ChecksumType};
+use serde_json::{json, Map, Value};
+use std::ffi::OsStr;
+
+// one operation that was performed on a file, e.g. "update" or "check"
+struct Operation {
+    name: String,
+    counts: Map<String, Value>,
+    warnings: Vec<String>,
+}
+
+// everything that was done while processing one input file
+pub(crate) struct FileSummary {
+    input: Option<String>,
+    operations: Vec<Operation>,
+    files_written: Vec<String>,
+    error: Option<String>,
+}
+
+impl FileSummary {
+    pub(crate) fn new(input: Option<&OsStr>) -> Self {
+        Self {
+            input: input.map(|input| input.to_string_lossy().into_owned()),
+            operations: Vec::new(),
+            files_written: Vec::new(),
+            error: None,
+        }
+    }
+
+    pub(crate) fn add_operation(
+        &mut self,
+        name: &str,
+        counts: &[(&str, usize)],
+        warnings: &[String],
+    ) {
+        self.operations.push(Operation {
+            name: name.to_string(),
+            counts: counts
+                .iter()
+                .map(|(key, count)| ((*key).to_string(), Value::from(*count)))
+                .collect(),
+            warnings: warnings.to_vec(),
+        });
+    }
+
+    pub(crate) fn add_written_file(&mut self, filename: &OsStr) {
+        self.files_written
+            .push(filename.to_string_lossy().into_owned());
+    }
+
+    pub(crate) fn set_result<E: std::fmt::Display>(&mut self, result: &Result<(), E>) {
+        self.error = result
+            .as_ref()
+            .err()
+            .map(|err| err.to_string().trim_end().to_string());
+    }
+
+    // The audit trail documents how the output file was produced: the command line, the input files
+    // with their CRC32 and the operations that were performed. It is written into the banner comment.
+    pub(crate) fn audit_text(&self, command_line: &str, input_files: &[(&str, &OsStr)]) -> String {
+        let mut lines = vec![
+            format!("a2ltool {}", env!("CARGO_PKG_VERSION")),
+            format!("  command line: {command_line}"),
+        ];
+        for (role, filename) in input_files {
+            // an input file inside an archive is identified by the archive
+            let path = a2ltool::zip::split_archive_path(filename)
+                .map_or_else(|| filename.into(), |(archive, _)| archive);
+            let crc = std::fs::read(path).map_or_else(
+                |_| "unreadable".to_string(),
+                |data| {
+                    format!(
+                        "CRC32 0x{:08X}",
+                        calculate_checksum(ChecksumType::Crc32, &data, false)
+                    )
+                },
+            );
+            lines.push(format!("  {role}: {} ({crc})", filename.to_string_lossy()));
+        }
+        for operation in &self.operations {
+            let counts: Vec<String> = operation
+                .counts
+                .iter()
+                .map(|(key, count)| format!("{key}={count}"))
+                .collect();
+            if counts.is_empty() {
+                lines.push(format!("  operation: {}", operation.name));
+            } else {
+                lines.push(format!(
+                    "  operation: {} ({})",
+                    operation.name,
+                    counts.join(", ")
+                ));
+            }
+        }
+        // the text is placed inside a comment, which must not be terminated early
+        lines.join("\n").replace("*/", "* /")
+    }
+
+    fn to_json(&self) -> Value {
+        let operations: Vec<Value> = self
+            .operations
+            .iter()
+            .map(|operation| {
+                json!({
+                    "operation": operation.name,
+                    "counts": operation.counts,
+                    "warnings": operation.warnings,
+                })
+            })
+            .collect();
+        json!({
+            "input": self.input,
+            "success": self.error.is_none(),
+            "error": self.error,
+            "operations": operations,
+            "files_written": self.files_written,
+        })
+    }
+}
+
+// write the summary of all processed files as JSON
+pub(crate) fn write_summary(filename: &OsStr, files: &[FileSummary]) -> Result<(), String> {
+    let summary = json!({
+        "a2ltool_version": env!("CARGO_PKG_VERSION"),
+        "success": files.iter().all(|file| file.error.is_none()),
+        "files": files.iter().map(FileSummary::to_json).collect::<Vec<_>>(),
+    });
+    let text = serde_json::to_string_pretty(&summary).map_err(|err| err.to_string())?;
+    std::fs::write(filename, text).map_err(|err| {
+        format!(
+            "Error: could not write the summary to \"{}\": {err}",
+            filename.to_string_lossy()
+        )
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_file_summary() {
+        let mut summary = FileSummary::new(Some(OsStr::new("input.a2l")));
+        summary.add_operation("check", &[("problems", 1)], &["problem".to_string()]);
+        summary.add_written_file(OsStr::new("output.a2l"));
+        summary.set_result(&Ok::<(), String>(()));
+        let json = summary.to_json();
+        assert_eq!(json["success"], true);
+        assert_eq!(json["operations"][0]["operation"], "check");
+        assert_eq!(json["operations"][0]["counts"]["problems"], 1);
+        assert_eq!(json["operations"][0]["warnings"][0], "problem");
+        assert_eq!(json["files_written"][0], "output.a2l");
+
+        summary.set_result(&Err("Error: failed\n".to_string()));
+        assert_eq!(summary.to_json()["error"], "Error: failed");
+    }
+
+    #[test]
+    fn test_audit_text() {
+        let mut summary = FileSummary::new(Some(OsStr::new("input.a2l")));
+        summary.add_operation("load", &[], &[]);
+        summary.add_operation("remove", &[("removed", 3)], &[]);
+        let text = summary.audit_text(
+            "a2ltool in.a2l --remove \"Cal_*/x\"",
+            &[
+                ("input", OsStr::new("tests/update_test1.a2l")),
+                ("elf", OsStr::new("does_not_exist.elf")),
+            ],
+        );
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines[0].starts_with("a2ltool "));
+        assert_eq!(
+            lines[1],
+            "  command line: a2ltool in.a2l --remove \"Cal_* /x\""
+        );
+        assert!(lines[2].starts_with("  input: tests/update_test1.a2l (CRC32 0x"));
+        assert_eq!(lines[3], "  elf: does_not_exist.elf (unreadable)");
+        assert_eq!(lines[4], "  operation: load");
+        assert_eq!(lines[5], "  operation: remove (removed=3)");
+    }
+}
@@ -1,5 +1,6 @@
 use crate::dwarf::{make_simple_unit_name, DebugData, TypeInfo};
-use crate::dwarf::{DwarfDataType, VarInfo};
+use crate::dwarf::{CaseFoldedNames, DuplicateSymbolPolicy, DwarfDataType, VarInfo};
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub(crate) struct SymbolInfo<'dbg> {
@@ -7,8 +8,9 @@ pub(crate) struct SymbolInfo<'dbg> {
     pub(crate) address: u64,
     pub(crate) typeinfo: &'dbg TypeInfo,
     pub(crate) unit_idx: usize,
-    pub(crate) function_name: &'dbg Option<String>,
-    pub(crate) namespaces: &'dbg [String],
+    pub(crate) decl_file: Option<usize>,
+    pub(crate) function_name: &'dbg Option<Arc<str>>,
+    pub(crate) namespaces: &'dbg [Arc<str>],
     pub(crate) is_unique: bool,
 }
 
@@ -55,11 +57,137 @@ pub(crate) fn find_symbol<'a>(
                 }
             }
 
+            // some toolchains prefix the names of all C symbols with '_', so the name in the a2l file may
+            // differ from the name in the debug info by a leading underscore
+            let underscore_variant = debug_data
+                .ignore_leading_underscore
+                .then(|| toggle_leading_underscore(components[0]));
+            let mut alternatives = vec![components.clone()];
+            if let Some(variant) = &underscore_variant {
+                let mut components_variant = components.clone();
+                components_variant[0] = variant;
+                if let Ok(sym_info) =
+                    find_symbol_from_components(&components_variant, &additional_spec, debug_data)
+                {
+                    return Ok(SymbolInfo {
+                        name: join_symbol_components(&components_variant),
+                        ..sym_info
+                    });
+                }
+                alternatives.push(components_variant);
+            }
+
+            // finally, try to match the name without regard to case, if this is enabled
+            if let Some(case_folded) = &debug_data.case_folded {
+                for alternative in &alternatives {
+                    let matched = match_components_case(alternative, debug_data, case_folded)?;
+                    if matched != *alternative {
+                        if let Ok(sym_info) =
+                            find_symbol_from_components(&matched, &additional_spec, debug_data)
+                        {
+                            return Ok(SymbolInfo {
+                                name: join_symbol_components(&matched),
+                                ..sym_info
+                            });
+                        }
+                    }
+                }
+            }
+
             Err(find_err)
         }
     }
 }
 
+// get the spelling in the debug info of each component of a symbol name, when the names are compared without case.
+// Components that can't be matched are returned unchanged
+fn match_components_case<'a>(
+    components: &[&'a str],
+    debug_data: &'a DebugData,
+    case_folded: &'a CaseFoldedNames,
+) -> Result<Vec<&'a str>, String> {
+    let varname = if debug_data.variables.contains_key(components[0]) {
+        Some(components[0])
+    } else {
+        let candidates = case_folded
+            .variables
+            .get(&components[0].to_ascii_lowercase())
+            .map(|names| names.iter().map(String::as_str).collect())
+            .unwrap_or_default();
+        case_folded.select(components[0], candidates, "variables")?
+    };
+    let Some(varname) = varname else {
+        return Ok(components.to_vec());
+    };
+    let mut matched = vec![varname];
+    let mut typeinfo = debug_data
+        .variables
+        .get(varname)
+        .and_then(|varinfo_list| debug_data.types.get(&varinfo_list[0].typeref));
+
+    let mut idx = 1;
+    while idx < components.len() {
+        let component = components[idx];
+        match typeinfo.map(|t| &t.datatype) {
+            Some(
+                DwarfDataType::Struct { members, .. }
+                | DwarfDataType::Union { members, .. }
+                | DwarfDataType::Class { members, .. },
+            ) => {
+                let member_name = if members.contains_key(component) {
+                    Some(component)
+                } else {
+                    let candidates = members
+                        .keys()
+                        .filter(|name| name.eq_ignore_ascii_case(component))
+                        .map(|name| &**name)
+                        .collect();
+                    case_folded.select(component, candidates, "members")?
+                };
+                let Some(member_name) = member_name else {
+                    break;
+                };
+                matched.push(member_name);
+                typeinfo = members
+                    .get(member_name)
+                    .map(|(membertype, _)| membertype.get_reference(&debug_data.types));
+                idx += 1;
+            }
+            Some(DwarfDataType::Array { dim, arraytype, .. }) => {
+                // array indices are copied, they are not affected by case
+                let end = (idx + dim.len()).min(components.len());
+                matched.extend_from_slice(&components[idx..end]);
+                typeinfo = Some(arraytype);
+                idx = end;
+            }
+            _ => break,
+        }
+    }
+    matched.extend_from_slice(&components[idx..]);
+
+    Ok(matched)
+}
+
+// add a leading underscore to a variable name, or remove it if it is present: "EngSpd" <-> "_EngSpd"
+fn toggle_leading_underscore(name: &str) -> String {
+    match name.strip_prefix('_') {
+        Some(stripped) if !stripped.is_empty() => stripped.to_string(),
+        _ => format!("_{name}"),
+    }
+}
+
+// the reverse of split_symbol_components: join the components to form a symbol name
+fn join_symbol_components(components: &[&str]) -> String {
+    let mut name = components[0].to_string();
+    for component in &components[1..] {
+        if !component.starts_with('[') {
+            name.push('.');
+        }
+        name.push_str(component);
+    }
+    name
+}
+
 fn find_symbol_from_components<'a>(
     components: &[&str],
     additional_spec: &Option<AdditionalSpec>,
@@ -69,7 +197,7 @@ fn find_symbol_from_components<'a>(
     if let Some(varinfo_list) = debug_data.variables.get(components[0]) {
         // somtimes there are several variables with the same name in different files or functions
         // select the best one of them based on the additional_data
-        let varinfo = select_varinfo(varinfo_list, additional_spec, debug_data);
+        let varinfo = select_varinfo(components[0], varinfo_list, additional_spec, debug_data)?;
         let is_unique = varinfo_list.len() == 1;
 
         // we also need the type in order to resolve struct members, etc.
@@ -81,6 +209,7 @@ fn find_symbol_from_components<'a>(
                     address: addr,
                     typeinfo,
                     unit_idx: varinfo.unit_idx,
+                    decl_file: varinfo.decl_file,
                     function_name: &varinfo.function,
                     namespaces: &varinfo.namespaces,
                     is_unique,
@@ -100,6 +229,7 @@ fn find_symbol_from_components<'a>(
                         dbginfo_offset: 0,
                     },
                     unit_idx: varinfo.unit_idx,
+                    decl_file: varinfo.decl_file,
                     namespaces: &varinfo.namespaces,
                     function_name: &None,
                     is_unique,
@@ -118,25 +248,65 @@ fn find_symbol_from_components<'a>(
 }
 
 fn select_varinfo<'a>(
+    varname: &str,
     varinfo_list: &'a [VarInfo],
     additional_spec: &Option<AdditionalSpec>,
     debug_data: &DebugData,
-) -> &'a VarInfo {
+) -> Result<&'a VarInfo, String> {
+    if varinfo_list.len() == 1 {
+        return Ok(&varinfo_list[0]);
+    }
     if let Some(additional_spec) = additional_spec {
         let unit = &additional_spec.simple_unit_name;
         let func = &additional_spec.function_name;
         let ns = &additional_spec.namespaces;
         for vi in varinfo_list {
             if (unit.is_none() || *unit == make_simple_unit_name(debug_data, vi.unit_idx))
-                && (func.is_none() || *func == vi.function)
-                && *ns == vi.namespaces
+                && (func.is_none() || func.as_deref() == vi.function.as_deref())
+                && ns
+                    .iter()
+                    .map(String::as_str)
+                    .eq(vi.namespaces.iter().map(|ns| &**ns))
             {
-                return vi;
+                return Ok(vi);
             }
         }
         // spec was NOT matched. In this case we simply continue as if the spec didin't exist
     }
-    &varinfo_list[0]
+    match debug_data.duplicate_symbol_policy {
+        DuplicateSymbolPolicy::First => Ok(&varinfo_list[0]),
+        DuplicateSymbolPolicy::ByFile => {
+            // the order of the variables depends on the link order, but the order of the file names does not
+            let varinfo = varinfo_list
+                .iter()
+                .min_by_key(|vi| {
+                    (
+                        make_simple_unit_name(debug_data, vi.unit_idx),
+                        vi.function.as_deref(),
+                    )
+                })
+                .unwrap();
+            Ok(varinfo)
+        }
+        DuplicateSymbolPolicy::Error => {
+            let locations: Vec<String> = varinfo_list
+                .iter()
+                .map(|vi| {
+                    let unit_name = make_simple_unit_name(debug_data, vi.unit_idx)
+                        .unwrap_or_else(|| "<unknown>".to_string());
+                    if let Some(func) = &vi.function {
+                        format!("{unit_name} (function {func})")
+                    } else {
+                        unit_name
+                    }
+                })
+                .collect();
+            Err(format!(
+                "Symbol \"{varname}\" exists in several places: {}. Add {{CompileUnit:...}} to the SYMBOL_LINK to select one of them",
+                locations.join(", ")
+            ))
+        }
+    }
 }
 
 // split up a string of the form
@@ -159,7 +329,8 @@ fn get_additional_spec(varname_ext: &str) -> (&str, Option<AdditionalSpec>) {
                 } else if let Some(nsname) = component.strip_prefix("Namespace:") {
                     add_spec.namespaces.push(nsname.to_string());
                 } else if let Some(name) = component.strip_prefix("CompileUnit:") {
-                    add_spec.simple_unit_name = Some(name.to_string());
+                    // accept both "file.c" and the simple unit name "file_c"
+                    add_spec.simple_unit_name = Some(name.replace('.', "_"));
                     // CompileUnit:... is the last interesting entry - skip the final {Namespace:Global}
                     break;
                 }
@@ -323,6 +494,7 @@ fn get_index(idxstr: &str) -> Option<usize> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::dwarf::CaseTieBreak;
     use indexmap::IndexMap;
     use std::collections::HashMap;
 
@@ -351,7 +523,16 @@ mod test {
             variables: IndexMap::new(),
             demangled_names: HashMap::new(),
             unit_names: Vec::new(),
+            source_files: Vec::new(),
             sections: HashMap::new(),
+            section_flags: HashMap::new(),
+            load_segments: Vec::new(),
+            symbol_sizes: HashMap::new(),
+            fixed_point: HashMap::new(),
+            little_endian: true,
+            case_folded: None,
+            ignore_leading_underscore: false,
+            duplicate_symbol_policy: DuplicateSymbolPolicy::First,
         };
         // global variable: uint32_t my_array[2]
         dbgdata.variables.insert(
@@ -361,6 +542,7 @@ mod test {
                 typeref: 1,
                 unit_idx: 0,
                 function: None,
+                decl_file: None,
                 namespaces: vec![],
             }],
         );
@@ -411,15 +593,24 @@ mod test {
             variables: IndexMap::new(),
             demangled_names: HashMap::new(),
             unit_names: Vec::new(),
+            source_files: Vec::new(),
             sections: HashMap::new(),
+            section_flags: HashMap::new(),
+            load_segments: Vec::new(),
+            symbol_sizes: HashMap::new(),
+            fixed_point: HashMap::new(),
+            little_endian: true,
+            case_folded: None,
+            ignore_leading_underscore: false,
+            duplicate_symbol_policy: DuplicateSymbolPolicy::First,
         };
         // global variable defined in C like this:
         // struct {
         //        uint32_t array_item[2];
         // } my_struct;
-        let mut structmembers: IndexMap<String, (TypeInfo, u64)> = IndexMap::new();
+        let mut structmembers: IndexMap<Arc<str>, (TypeInfo, u64)> = IndexMap::new();
         structmembers.insert(
-            "array_item".to_string(),
+            "array_item".into(),
             (
                 TypeInfo {
                     datatype: DwarfDataType::Array {
@@ -447,6 +638,7 @@ mod test {
                 typeref: 2,
                 unit_idx: 0,
                 function: None,
+                decl_file: None,
                 namespaces: vec![],
             }],
         );
@@ -475,6 +667,186 @@ mod test {
         assert!(result3.is_err());
     }
 
+    #[test]
+    fn test_find_symbol_case_insensitive() {
+        let mut dbgdata = DebugData {
+            types: HashMap::new(),
+            typenames: HashMap::new(),
+            variables: IndexMap::new(),
+            demangled_names: HashMap::new(),
+            unit_names: Vec::new(),
+            source_files: Vec::new(),
+            sections: HashMap::new(),
+            section_flags: HashMap::new(),
+            load_segments: Vec::new(),
+            symbol_sizes: HashMap::new(),
+            fixed_point: HashMap::new(),
+            little_endian: true,
+            case_folded: None,
+            ignore_leading_underscore: false,
+            duplicate_symbol_policy: DuplicateSymbolPolicy::First,
+        };
+        let uint8 = TypeInfo {
+            datatype: DwarfDataType::Uint8,
+            name: None,
+            unit_idx: usize::MAX,
+            dbginfo_offset: 0,
+        };
+        // struct { uint8_t Array_Item[2]; uint8_t value; uint8_t Value; } My_Struct;
+        let mut structmembers: IndexMap<Arc<str>, (TypeInfo, u64)> = IndexMap::new();
+        structmembers.insert(
+            "Array_Item".into(),
+            (
+                TypeInfo {
+                    datatype: DwarfDataType::Array {
+                        arraytype: Box::new(uint8.clone()),
+                        dim: vec![2],
+                        size: 2,
+                        stride: 1,
+                    },
+                    ..uint8.clone()
+                },
+                0,
+            ),
+        );
+        structmembers.insert("value".into(), (uint8.clone(), 2));
+        structmembers.insert("Value".into(), (uint8.clone(), 3));
+        dbgdata.types.insert(
+            1,
+            TypeInfo {
+                datatype: DwarfDataType::Struct {
+                    members: structmembers,
+                    size: 4,
+                },
+                name: Some("Struct_t".into()),
+                unit_idx: 0,
+                dbginfo_offset: 1,
+            },
+        );
+        dbgdata.types.insert(2, uint8);
+        for (name, typeref, address) in [
+            ("My_Struct", 1, 0x1000),
+            ("Speed", 2, 0x2000),
+            ("speed", 2, 0x3000),
+        ] {
+            dbgdata.variables.insert(
+                name.to_string(),
+                vec![crate::dwarf::VarInfo {
+                    address,
+                    typeref,
+                    unit_idx: 0,
+                    function: None,
+                    decl_file: None,
+                    namespaces: vec![],
+                }],
+            );
+        }
+
+        // without case-insensitive matching, the names must match exactly
+        assert!(find_symbol("MY_STRUCT.ARRAY_ITEM[1]", &dbgdata).is_err());
+
+        let mut log_msgs = Vec::new();
+        dbgdata.set_case_insensitive(CaseTieBreak::First, &mut log_msgs);
+        assert_eq!(log_msgs.len(), 2);
+        assert!(log_msgs[0].contains("Value, value of Struct_t"));
+        assert!(log_msgs[1].contains("Speed, speed"));
+
+        let sym_info = find_symbol("MY_STRUCT.ARRAY_ITEM[1]", &dbgdata).unwrap();
+        assert_eq!(sym_info.name, "My_Struct.Array_Item[1]");
+        assert_eq!(sym_info.address, 0x1001);
+        let sym_info = find_symbol("my_struct.array_item._1_", &dbgdata).unwrap();
+        assert_eq!(sym_info.name, "My_Struct.Array_Item._1_");
+        // exact matches are preferred over the tie-break
+        let sym_info = find_symbol("speed", &dbgdata).unwrap();
+        assert_eq!(sym_info.address, 0x3000);
+        let sym_info = find_symbol("SPEED", &dbgdata).unwrap();
+        assert_eq!(sym_info.name, "Speed");
+        let sym_info = find_symbol("My_Struct.VALUE", &dbgdata).unwrap();
+        assert_eq!(sym_info.name, "My_Struct.Value");
+
+        dbgdata.set_case_insensitive(CaseTieBreak::Reject, &mut Vec::new());
+        assert!(find_symbol("SPEED", &dbgdata).is_err());
+        assert!(find_symbol("My_Struct.VALUE", &dbgdata).is_err());
+        assert!(find_symbol("My_Struct.value", &dbgdata).is_ok());
+        assert!(find_symbol("MY_STRUCT.ARRAY_ITEM[0]", &dbgdata).is_ok());
+    }
+
+    #[test]
+    fn test_find_symbol_leading_underscore() {
+        let mut dbgdata = DebugData {
+            types: HashMap::new(),
+            typenames: HashMap::new(),
+            variables: IndexMap::new(),
+            demangled_names: HashMap::new(),
+            unit_names: Vec::new(),
+            source_files: Vec::new(),
+            sections: HashMap::new(),
+            section_flags: HashMap::new(),
+            load_segments: Vec::new(),
+            symbol_sizes: HashMap::new(),
+            fixed_point: HashMap::new(),
+            little_endian: true,
+            case_folded: None,
+            ignore_leading_underscore: false,
+            duplicate_symbol_policy: DuplicateSymbolPolicy::First,
+        };
+        let mut structmembers: IndexMap<Arc<str>, (TypeInfo, u64)> = IndexMap::new();
+        structmembers.insert(
+            "value".into(),
+            (
+                TypeInfo {
+                    datatype: DwarfDataType::Uint16,
+                    name: None,
+                    unit_idx: usize::MAX,
+                    dbginfo_offset: 0,
+                },
+                2,
+            ),
+        );
+        dbgdata.types.insert(
+            1,
+            TypeInfo {
+                datatype: DwarfDataType::Struct {
+                    members: structmembers,
+                    size: 4,
+                },
+                name: None,
+                unit_idx: 0,
+                dbginfo_offset: 1,
+            },
+        );
+        for (name, address) in [("_EngSpd", 0x1000), ("Other", 0x2000)] {
+            dbgdata.variables.insert(
+                name.to_string(),
+                vec![crate::dwarf::VarInfo {
+                    address,
+                    typeref: 1,
+                    unit_idx: 0,
+                    function: None,
+                    decl_file: None,
+                    namespaces: vec![],
+                }],
+            );
+        }
+
+        assert!(find_symbol("EngSpd.value", &dbgdata).is_err());
+
+        dbgdata.set_ignore_leading_underscore(true);
+        let sym_info = find_symbol("EngSpd.value", &dbgdata).unwrap();
+        assert_eq!(sym_info.name, "_EngSpd.value");
+        assert_eq!(sym_info.address, 0x1002);
+        let sym_info = find_symbol("_Other", &dbgdata).unwrap();
+        assert_eq!(sym_info.name, "Other");
+        // only a single leading underscore is added or removed
+        assert!(find_symbol("__Other", &dbgdata).is_err());
+
+        // both differences together are only resolved if case-insensitive matching is enabled
+        assert!(find_symbol("ENGSPD", &dbgdata).is_err());
+        dbgdata.set_case_insensitive(CaseTieBreak::First, &mut Vec::new());
+        let sym_info = find_symbol("ENGSPD.VALUE", &dbgdata).unwrap();
+        assert_eq!(sym_info.name, "_EngSpd.value");
+    }
+
     #[test]
     fn test_select_varinfo() {
         let mut debug_data = DebugData {
@@ -483,7 +855,16 @@ mod test {
             variables: IndexMap::new(),
             demangled_names: HashMap::new(),
             unit_names: Vec::new(),
+            source_files: Vec::new(),
             sections: HashMap::new(),
+            section_flags: HashMap::new(),
+            load_segments: Vec::new(),
+            symbol_sizes: HashMap::new(),
+            fixed_point: HashMap::new(),
+            little_endian: true,
+            case_folded: None,
+            ignore_leading_underscore: false,
+            duplicate_symbol_policy: DuplicateSymbolPolicy::First,
         };
         debug_data.types.insert(
             0,
@@ -501,43 +882,64 @@ mod test {
                     address: 0,
                     typeref: 0,
                     unit_idx: 0,
-                    function: Some("func_a".to_string()),
+                    function: Some("func_a".into()),
+                    decl_file: None,
                     namespaces: vec![],
                 },
                 VarInfo {
                     address: 1000,
                     typeref: 0,
                     unit_idx: 1,
-                    function: Some("func_b".to_string()),
+                    function: Some("func_b".into()),
+                    decl_file: None,
                     namespaces: vec![],
                 },
                 VarInfo {
                     address: 2000,
                     typeref: 0,
                     unit_idx: 1,
-                    function: Some("func_c".to_string()),
+                    function: Some("func_c".into()),
+                    decl_file: None,
                     namespaces: vec![],
                 },
             ],
         );
         debug_data.unit_names.push(Some("file1.c".to_string()));
         debug_data.unit_names.push(Some("file2.c".to_string()));
-        let varinfo_list = debug_data.variables.get("var").unwrap();
+        let varinfo_list = debug_data.variables.swap_remove("var").unwrap();
         let (base, additional_spec) =
             get_additional_spec("var{Function:func_a}{CompileUnit:file1_c}{Namespace:Global}");
         assert_eq!(base, "var");
-        let varinfo = select_varinfo(varinfo_list, &additional_spec, &debug_data);
+        let varinfo = select_varinfo("var", &varinfo_list, &additional_spec, &debug_data).unwrap();
         assert_eq!(varinfo.address, 0);
         let (base, additional_spec) =
             get_additional_spec("var{Function:func_b}{CompileUnit:file2_c}{Namespace:Global}");
         assert_eq!(base, "var");
-        let varinfo = select_varinfo(varinfo_list, &additional_spec, &debug_data);
+        let varinfo = select_varinfo("var", &varinfo_list, &additional_spec, &debug_data).unwrap();
         assert_eq!(varinfo.address, 1000);
         let (base, additional_spec) =
             get_additional_spec("var{Function:func_c}{CompileUnit:file2_c}{Namespace:Global}");
         assert_eq!(base, "var");
-        let varinfo = select_varinfo(varinfo_list, &additional_spec, &debug_data);
+        let varinfo = select_varinfo("var", &varinfo_list, &additional_spec, &debug_data).unwrap();
         assert_eq!(varinfo.address, 2000);
+
+        // without a matching spec the result depends on the duplicate symbol policy
+        let (_, additional_spec) = get_additional_spec("var{CompileUnit:file3.c}");
+        let varinfo = select_varinfo("var", &varinfo_list, &additional_spec, &debug_data).unwrap();
+        assert_eq!(varinfo.address, 0);
+        debug_data.set_duplicate_symbol_policy(DuplicateSymbolPolicy::Error);
+        let result = select_varinfo("var", &varinfo_list, &None, &debug_data);
+        assert!(result.is_err());
+        let (_, additional_spec) = get_additional_spec("var{CompileUnit:file2.c}");
+        let varinfo = select_varinfo("var", &varinfo_list, &additional_spec, &debug_data).unwrap();
+        assert_eq!(varinfo.address, 1000);
+
+        // by-file does not depend on the order of the variables in the debug info
+        debug_data.set_duplicate_symbol_policy(DuplicateSymbolPolicy::ByFile);
+        let mut reversed = varinfo_list.clone();
+        reversed.reverse();
+        let varinfo = select_varinfo("var", &reversed, &None, &debug_data).unwrap();
+        assert_eq!(varinfo.address, 0);
     }
 
     #[test]
@@ -551,5 +953,11 @@ mod test {
         assert_eq!(add_spec.function_name, Some("func".to_string()));
         assert_eq!(add_spec.namespaces, vec!["Foo", "Bar"]);
         assert_eq!(add_spec.simple_unit_name, Some("file_c".to_string()));
+
+        let (_, add_spec) = get_additional_spec("varname{CompileUnit:file.c}");
+        assert_eq!(
+            add_spec.unwrap().simple_unit_name,
+            Some("file_c".to_string())
+        );
     }
 }
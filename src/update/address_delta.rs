@@ -0,0 +1,119 @@
+use a2lfile::{A2lFile, Module};
+use std::collections::HashMap;
+
+/// The addresses of all objects before the update.
+/// After the update it is used to find objects whose address changed by more than a threshold,
+/// which usually means that the object was matched to the wrong variable.
+pub struct AddressSnapshot {
+    // (module index, block type, name) -> address
+    addresses: HashMap<(usize, &'static str, String), u32>,
+}
+
+impl AddressSnapshot {
+    /// record the addresses of all MEASUREMENTs, CHARACTERISTICs, AXIS_PTS, BLOBs and INSTANCEs
+    #[must_use]
+    pub fn new(a2l_file: &A2lFile) -> Self {
+        let mut addresses = HashMap::new();
+        for (module_idx, module) in a2l_file.project.module.iter().enumerate() {
+            for (blocktype, name, address) in module_addresses(module) {
+                addresses.insert((module_idx, blocktype, name.to_string()), address);
+            }
+        }
+        Self { addresses }
+    }
+
+    /// get a message for each object whose address changed by more than `threshold` bytes.
+    /// Objects that had no address before or that have no address now, i.e. the address is zero, are ignored.
+    #[must_use]
+    pub fn large_changes(&self, a2l_file: &A2lFile, threshold: u64) -> Vec<String> {
+        let mut messages = Vec::new();
+        for (module_idx, module) in a2l_file.project.module.iter().enumerate() {
+            for (blocktype, name, new_address) in module_addresses(module) {
+                let key = (module_idx, blocktype, name.to_string());
+                if let Some(&old_address) = self.addresses.get(&key) {
+                    let delta = u64::from(old_address.abs_diff(new_address));
+                    if old_address != 0 && new_address != 0 && delta > threshold {
+                        messages.push(format!(
+                            "Warning: the address of {blocktype} {name} changed by {delta} bytes from 0x{old_address:08X} to 0x{new_address:08X}"
+                        ));
+                    }
+                }
+            }
+        }
+        messages
+    }
+}
+
+// the block type, name and address of each object in the module that has an address
+fn module_addresses(module: &Module) -> Vec<(&'static str, &str, u32)> {
+    let mut result = Vec::new();
+    for measurement in &module.measurement {
+        if let Some(ecu_address) = &measurement.ecu_address {
+            result.push(("MEASUREMENT", &*measurement.name, ecu_address.address));
+        }
+    }
+    for characteristic in &module.characteristic {
+        result.push((
+            "CHARACTERISTIC",
+            &*characteristic.name,
+            characteristic.address,
+        ));
+    }
+    for axis_pts in &module.axis_pts {
+        result.push(("AXIS_PTS", &*axis_pts.name, axis_pts.address));
+    }
+    for blob in &module.blob {
+        result.push(("BLOB", &*blob.name, blob.start_address));
+    }
+    for instance in &module.instance {
+        result.push(("INSTANCE", &*instance.name, instance.start_address));
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_large_changes() {
+        let a2l_text = r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin MEASUREMENT near "" UBYTE NO_COMPU_METHOD 0 0 0 255
+                    ECU_ADDRESS 0x1000
+                /end MEASUREMENT
+                /begin MEASUREMENT far "" UBYTE NO_COMPU_METHOD 0 0 0 255
+                    ECU_ADDRESS 0x1000
+                /end MEASUREMENT
+                /begin MEASUREMENT lost "" UBYTE NO_COMPU_METHOD 0 0 0 255
+                    ECU_ADDRESS 0x1000
+                /end MEASUREMENT
+                /begin BLOB b "" 0x2000 4
+                /end BLOB
+            /end MODULE
+        /end PROJECT"#;
+        let mut log_msgs = Vec::new();
+        let mut a2l_file = a2lfile::load_from_string(a2l_text, None, &mut log_msgs, false).unwrap();
+        let snapshot = AddressSnapshot::new(&a2l_file);
+
+        // addresses after the update: near moved a little, far moved to a different region, lost was not found
+        let module = &mut a2l_file.project.module[0];
+        for (idx, address) in [0x1100, 0x8000_1000, 0].into_iter().enumerate() {
+            module.measurement[idx]
+                .ecu_address
+                .as_mut()
+                .unwrap()
+                .address = address;
+        }
+        module.blob[0].start_address = 0x1000;
+
+        let messages = snapshot.large_changes(&a2l_file, 0x1000);
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].contains("MEASUREMENT far"));
+
+        let messages = snapshot.large_changes(&a2l_file, 0xFF);
+        assert_eq!(messages.len(), 3);
+    }
+}
@@ -18,15 +18,20 @@ use super::{make_symbol_link_string, UpdateInfo};
 pub(crate) fn update_module_axis_pts(
     info: &mut UpdateInfo,
     compu_method_index: &HashMap<String, usize>,
-) -> (u32, u32) {
+) -> (u32, u32, u32) {
     let mut enum_convlist = HashMap::<String, &TypeInfo>::new();
     let mut removed_items = HashSet::<String>::new();
     let mut axis_pts_list = Vec::new();
     let mut axis_pts_updated: u32 = 0;
     let mut axis_pts_not_updated: u32 = 0;
+    let mut changed_dims = HashMap::<String, u16>::new();
 
     std::mem::swap(&mut info.module.axis_pts, &mut axis_pts_list);
     for mut axis_pts in axis_pts_list {
+        if info.skip_unchanged(&axis_pts.name) {
+            info.module.axis_pts.push(axis_pts);
+            continue;
+        }
         match update_axis_pts_address(&mut axis_pts, info.debug_data, info.version) {
             Ok(typeinfo) => {
                 // the variable used for the axis should be a 1-dimensional array, or a struct containing a 1-dimensional array
@@ -40,19 +45,26 @@ pub(crate) fn update_module_axis_pts(
                     match &inner_typeinfo.datatype {
                         DwarfDataType::Array { dim, arraytype, .. } => {
                             // update max_axis_points to match the size of the array
-                            if !dim.is_empty() {
-                                axis_pts.max_axis_points = dim[0] as u16;
+                            if let Some(new_dim) = dim.first() {
+                                let new_dim = u16::try_from(*new_dim).unwrap_or(u16::MAX);
+                                if new_dim != axis_pts.max_axis_points {
+                                    info.log_msgs.push(format!(
+                                        "The max_axis_points of AXIS_PTS {} on line {} changed from {} to {new_dim}",
+                                        axis_pts.name,
+                                        axis_pts.get_line(),
+                                        axis_pts.max_axis_points
+                                    ));
+                                    axis_pts.max_axis_points = new_dim;
+                                    changed_dims.insert(axis_pts.name.clone(), new_dim);
+                                }
                             }
                             if let DwarfDataType::Enum { enumerators, .. } = &arraytype.datatype {
                                 // an array of enums? it could be done...
                                 if axis_pts.conversion == "NO_COMPU_METHOD" {
-                                    axis_pts.conversion = arraytype
-                                        .name
-                                        .clone()
-                                        .unwrap_or_else(|| {
-                                            format!("{}_compu_method", axis_pts.name)
-                                        })
-                                        .clone();
+                                    axis_pts.conversion = arraytype.name.as_deref().map_or_else(
+                                        || format!("{}_compu_method", axis_pts.name),
+                                        ToString::to_string,
+                                    );
                                 }
                                 cond_create_enum_conversion(
                                     info.module,
@@ -113,8 +125,57 @@ pub(crate) fn update_module_axis_pts(
     // update COMPU_VTABs and COMPU_VTAB_RANGEs based on the data types used in MEASUREMENTs etc.
     update_enum_compu_methods(info.module, &enum_convlist);
     cleanup_removed_axis_pts(info.module, &removed_items);
+    update_axis_descr_dims(info.module, &changed_dims, info.log_msgs);
+
+    (
+        axis_pts_updated,
+        axis_pts_not_updated,
+        changed_dims.len() as u32,
+    )
+}
 
-    (axis_pts_updated, axis_pts_not_updated)
+// the max_axis_points of an AXIS_DESCR that references an AXIS_PTS must match the AXIS_PTS
+fn update_axis_descr_dims(
+    module: &mut Module,
+    changed_dims: &HashMap<String, u16>,
+    log_msgs: &mut Vec<String>,
+) {
+    if changed_dims.is_empty() {
+        return;
+    }
+
+    let characteristics = module.characteristic.iter_mut().map(|item| {
+        (
+            "CHARACTERISTIC",
+            &item.name,
+            item.get_line(),
+            &mut item.axis_descr,
+        )
+    });
+    let typedefs = module.typedef_characteristic.iter_mut().map(|item| {
+        (
+            "TYPEDEF_CHARACTERISTIC",
+            &item.name,
+            item.get_line(),
+            &mut item.axis_descr,
+        )
+    });
+    for (blocktype, name, line, axis_descr_list) in characteristics.chain(typedefs) {
+        for axis_descr in axis_descr_list {
+            let Some(axis_pts_ref) = &axis_descr.axis_pts_ref else {
+                continue;
+            };
+            if let Some(new_dim) = changed_dims.get(&axis_pts_ref.axis_points) {
+                if axis_descr.max_axis_points != *new_dim {
+                    log_msgs.push(format!(
+                        "The max_axis_points of the axis {} in {blocktype} {name} on line {line} changed from {} to {new_dim}",
+                        axis_pts_ref.axis_points, axis_descr.max_axis_points
+                    ));
+                    axis_descr.max_axis_points = *new_dim;
+                }
+            }
+        }
+    }
 }
 
 // update the address of an AXIS_PTS object
@@ -4,34 +4,55 @@ use std::collections::HashSet;
 
 use super::ifdata_update::{update_ifdata, zero_if_data};
 use super::{
-    cleanup_item_list, get_symbol_info, log_update_errors, make_symbol_link_string, set_symbol_link,
+    cleanup_item_list, get_symbol_info, log_update_errors, make_symbol_link_string,
+    set_symbol_link, UpdateInfo,
 };
 
-pub(crate) fn update_module_blobs(
-    module: &mut Module,
-    debug_data: &DebugData,
-    log_msgs: &mut Vec<String>,
-    preserve_unknown: bool,
-) -> (u32, u32) {
+pub(crate) fn update_module_blobs(info: &mut UpdateInfo) -> (u32, u32, u32) {
     let mut removed_items = HashSet::<String>::new();
     let mut blob_list = Vec::new();
     let mut blob_updated: u32 = 0;
     let mut blob_not_updated: u32 = 0;
-    std::mem::swap(&mut module.blob, &mut blob_list);
+    let mut blob_resized: u32 = 0;
+    std::mem::swap(&mut info.module.blob, &mut blob_list);
     for mut blob in blob_list {
-        match update_blob_address(&mut blob, debug_data) {
-            Ok(typeinfo) => {
-                blob.size = typeinfo.get_size() as u32;
-                module.blob.push(blob);
+        if info.skip_unchanged(&blob.name) {
+            info.module.blob.push(blob);
+            continue;
+        }
+        match update_blob_address(&mut blob, info.debug_data) {
+            Ok((symbol_name, typeinfo)) => {
+                // the size of the type is unknown for incomplete types, e.g. "extern uint8_t buffer[];"
+                // In this case the size of the symbol in the symbol table is used
+                let mut size = typeinfo.get_size();
+                if size == 0 {
+                    size = info
+                        .debug_data
+                        .symbol_sizes
+                        .get(&symbol_name)
+                        .copied()
+                        .unwrap_or(0);
+                }
+                if size != 0 && size != u64::from(blob.size) {
+                    info.log_msgs.push(format!(
+                        "The size of BLOB {} on line {} changed from {} to {size} bytes",
+                        blob.name,
+                        blob.get_line(),
+                        blob.size
+                    ));
+                    blob.size = size as u32;
+                    blob_resized += 1;
+                }
+                info.module.blob.push(blob);
                 blob_updated += 1;
             }
             Err(errmsgs) => {
-                log_update_errors(log_msgs, errmsgs, "BLOB", blob.get_line());
+                log_update_errors(info.log_msgs, errmsgs, "BLOB", blob.get_line());
 
-                if preserve_unknown {
+                if info.preserve_unknown {
                     blob.start_address = 0;
                     zero_if_data(&mut blob.if_data);
-                    module.blob.push(blob);
+                    info.module.blob.push(blob);
                 } else {
                     // item is removed implicitly, because it is not added back to the list
                     removed_items.insert(blob.name.clone());
@@ -40,16 +61,17 @@ pub(crate) fn update_module_blobs(
             }
         }
     }
-    cleanup_removed_blobs(module, &removed_items);
+    cleanup_removed_blobs(info.module, &removed_items);
 
-    (blob_updated, blob_not_updated)
+    (blob_updated, blob_not_updated, blob_resized)
 }
 
 // update the address of a BLOB object
+// returns the name of the symbol and its type
 fn update_blob_address<'a>(
     blob: &mut Blob,
     debug_data: &'a DebugData,
-) -> Result<&'a TypeInfo, Vec<String>> {
+) -> Result<(String, &'a TypeInfo), Vec<String>> {
     match get_symbol_info(&blob.name, &blob.symbol_link, &blob.if_data, debug_data) {
         Ok(sym_info) => {
             // make sure a valid SYMBOL_LINK exists
@@ -63,7 +85,7 @@ fn update_blob_address<'a>(
                 sym_info.address,
             );
 
-            Ok(sym_info.typeinfo)
+            Ok((sym_info.name, sym_info.typeinfo))
         }
         Err(errmsgs) => Err(errmsgs),
     }
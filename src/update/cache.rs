@@ -0,0 +1,446 @@
+//! A cache file that allows an address update to be skipped if nothing has changed since the last update
+
+use crate::dwarf::DebugData;
+use crate::update::{get_symbol_info, make_symbol_link_string};
+use a2lfile::{A2lFile, IfData, SymbolLink};
+use fxhash::FxHasher64;
+use serde_json::{json, Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::Read;
+
+/// For each module: the hash of the debug information of each object, by object name
+pub type ObjectHashes = HashMap<String, HashMap<String, u64>>;
+
+/// The input and output of an update with a specific elf file
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateCache {
+    /// hash of the a2l file that was loaded
+    pub input_hash: u64,
+    /// hash of the a2l file as it was written after the update
+    pub a2l_hash: u64,
+    /// hash of the elf file that was used for the update
+    pub elf_hash: u64,
+    /// hash of all other files that were read, e.g. files that were merged
+    pub inputs_hash: u64,
+    /// the settings of the run, e.g. the a2ltool version and the command line
+    pub settings: String,
+    /// hashes of the debug information of the objects in the written a2l file, see [`object_hashes`]
+    pub object_hashes: ObjectHashes,
+}
+
+/// The work that can be skipped in the current run, see [`UpdateCache::compare`]
+#[derive(Debug, Default, PartialEq)]
+pub struct CacheStatus {
+    /// the input is unchanged and the output of the last run has not been modified, so nothing needs to be done
+    pub up_to_date: bool,
+    /// the input is the output of the last run and the elf file is unchanged, so the update can be skipped
+    pub skip_update: bool,
+    /// the object hashes of the last run, if the input is its output
+    pub object_hashes: Option<ObjectHashes>,
+}
+
+impl UpdateCache {
+    /// Describe the current run by the hashes of its input files.
+    /// The hash of the output and the object hashes are only known once the output has been written
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if one of the files cannot be read
+    pub fn current<'a>(
+        input: &OsStr,
+        elffile: &OsStr,
+        other_inputs: impl IntoIterator<Item = &'a OsStr>,
+        settings: String,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            input_hash: hash_file(input)?,
+            a2l_hash: 0,
+            elf_hash: hash_file(elffile)?,
+            inputs_hash: hash_files(other_inputs)?,
+            settings,
+            object_hashes: ObjectHashes::new(),
+        })
+    }
+
+    /// Compare the current run with the last run, which is described by `cached`.
+    /// `output_hash` is the hash of the existing output file, if there is one.
+    /// Nothing can be skipped if the other input files or the settings have changed
+    #[must_use]
+    pub fn compare(&self, cached: Option<UpdateCache>, output_hash: Option<u64>) -> CacheStatus {
+        let Some(cached) = cached.filter(|cached| {
+            cached.inputs_hash == self.inputs_hash && cached.settings == self.settings
+        }) else {
+            return CacheStatus::default();
+        };
+        let elf_unchanged = cached.elf_hash == self.elf_hash;
+        let input_is_output = self.input_hash == cached.a2l_hash;
+        CacheStatus {
+            up_to_date: elf_unchanged
+                && self.input_hash == cached.input_hash
+                && output_hash == Some(cached.a2l_hash),
+            skip_update: elf_unchanged && input_is_output,
+            // the object hashes only describe the objects in the output of the last run
+            object_hashes: input_is_output.then_some(cached.object_hashes),
+        }
+    }
+
+    /// Load a cache file. Returns None if the file does not exist or is not a valid cache file
+    #[must_use]
+    pub fn load(filename: &OsStr) -> Option<Self> {
+        let text = std::fs::read_to_string(filename).ok()?;
+        let value: Value = serde_json::from_str(&text).ok()?;
+        let get_hash = |name| u64::from_str_radix(value.get(name)?.as_str()?, 16).ok();
+        Some(Self {
+            input_hash: get_hash("input_hash")?,
+            a2l_hash: get_hash("a2l_hash")?,
+            elf_hash: get_hash("elf_hash")?,
+            inputs_hash: get_hash("inputs_hash")?,
+            settings: value.get("settings")?.as_str()?.to_string(),
+            // cache files written by older versions don't contain the object hashes
+            object_hashes: value
+                .get("objects")
+                .and_then(Value::as_object)
+                .map(|modules| {
+                    modules
+                        .iter()
+                        .filter_map(|(module_name, objects)| {
+                            let objects = objects
+                                .as_object()?
+                                .iter()
+                                .filter_map(|(name, hash)| {
+                                    let hash = u64::from_str_radix(hash.as_str()?, 16).ok()?;
+                                    Some((name.clone(), hash))
+                                })
+                                .collect();
+                            Some((module_name.clone(), objects))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Save the cache file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the file cannot be written
+    pub fn save(&self, filename: &OsStr) -> Result<(), String> {
+        let objects: Map<String, Value> = self
+            .object_hashes
+            .iter()
+            .map(|(module_name, objects)| {
+                let objects: Map<String, Value> = objects
+                    .iter()
+                    .map(|(name, hash)| (name.clone(), Value::from(format!("{hash:016x}"))))
+                    .collect();
+                (module_name.clone(), Value::Object(objects))
+            })
+            .collect();
+        let value = json!({
+            "input_hash": format!("{:016x}", self.input_hash),
+            "a2l_hash": format!("{:016x}", self.a2l_hash),
+            "elf_hash": format!("{:016x}", self.elf_hash),
+            "inputs_hash": format!("{:016x}", self.inputs_hash),
+            "settings": self.settings,
+            "objects": objects,
+        });
+        std::fs::write(filename, format!("{value:#}\n")).map_err(|err| {
+            format!(
+                "Error: could not write the update cache \"{}\": {err}",
+                filename.to_string_lossy()
+            )
+        })
+    }
+}
+
+/// Calculate the hash of the content of a file
+///
+/// # Errors
+///
+/// Returns an error message if the file cannot be read
+pub fn hash_file(filename: &OsStr) -> Result<u64, String> {
+    let error = |err: std::io::Error| {
+        format!(
+            "Error: could not read \"{}\": {err}",
+            filename.to_string_lossy()
+        )
+    };
+    let mut file = File::open(filename).map_err(error)?;
+    let mut hasher = FxHasher64::default();
+    let mut buffer = vec![0u8; 1 << 16];
+    loop {
+        // the buffer is always filled completely, so that the hash does not depend on the sizes of the reads
+        let mut len = 0;
+        while len < buffer.len() {
+            match file.read(&mut buffer[len..]) {
+                Ok(0) => break,
+                Ok(count) => len += count,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(error(err)),
+            }
+        }
+        hasher.write(&buffer[..len]);
+        if len < buffer.len() {
+            break;
+        }
+    }
+    Ok(hasher.finish())
+}
+
+/// Calculate a combined hash of the content of several files
+///
+/// # Errors
+///
+/// Returns an error message if one of the files cannot be read
+pub fn hash_files<'a>(filenames: impl IntoIterator<Item = &'a OsStr>) -> Result<u64, String> {
+    let mut hasher = FxHasher64::default();
+    for filename in filenames {
+        hasher.write_u64(hash_file(filename)?);
+    }
+    Ok(hasher.finish())
+}
+
+/// Calculate a hash of the debug information of each MEASUREMENT, CHARACTERISTIC, AXIS_PTS and BLOB,
+/// consisting of the symbol, its address, its data type and its size.
+///
+/// Objects whose symbol cannot be found are not included.
+/// The hashes do not depend on the layout of the elf file, so they stay the same when an unrelated part
+/// of the program changes.
+#[must_use]
+pub fn object_hashes(a2l_file: &A2lFile, debug_data: &DebugData) -> ObjectHashes {
+    let mut hashes = ObjectHashes::new();
+    for module in &a2l_file.project.module {
+        let mut module_hashes = HashMap::new();
+        let mut insert = |name: &String, symbol_link: &Option<SymbolLink>, if_data: &[IfData]| {
+            if let Some(hash) = symbol_hash(name, symbol_link, if_data, debug_data) {
+                module_hashes.insert(name.clone(), hash);
+            }
+        };
+        for measurement in &module.measurement {
+            if measurement.var_virtual.is_none() {
+                insert(
+                    &measurement.name,
+                    &measurement.symbol_link,
+                    &measurement.if_data,
+                );
+            }
+        }
+        for characteristic in &module.characteristic {
+            if characteristic.virtual_characteristic.is_none() {
+                insert(
+                    &characteristic.name,
+                    &characteristic.symbol_link,
+                    &characteristic.if_data,
+                );
+            }
+        }
+        for axis_pts in &module.axis_pts {
+            insert(&axis_pts.name, &axis_pts.symbol_link, &axis_pts.if_data);
+        }
+        for blob in &module.blob {
+            insert(&blob.name, &blob.symbol_link, &blob.if_data);
+        }
+        hashes.insert(module.name.clone(), module_hashes);
+    }
+    hashes
+}
+
+/// Find the objects in each module whose debug information is the same in `cached` and `current`
+#[must_use]
+pub fn unchanged_objects(
+    cached: &ObjectHashes,
+    current: &ObjectHashes,
+) -> HashMap<String, HashSet<String>> {
+    current
+        .iter()
+        .filter_map(|(module_name, current_objects)| {
+            let cached_objects = cached.get(module_name)?;
+            let unchanged = current_objects
+                .iter()
+                .filter(|(name, hash)| cached_objects.get(*name) == Some(*hash))
+                .map(|(name, _)| name.clone())
+                .collect();
+            Some((module_name.clone(), unchanged))
+        })
+        .collect()
+}
+
+// hash everything about a symbol that is used by the update
+fn symbol_hash(
+    name: &str,
+    symbol_link: &Option<SymbolLink>,
+    if_data: &[IfData],
+    debug_data: &DebugData,
+) -> Option<u64> {
+    let sym_info = get_symbol_info(name, symbol_link, if_data, debug_data).ok()?;
+    let mut hasher = FxHasher64::default();
+    make_symbol_link_string(&sym_info, debug_data).hash(&mut hasher);
+    sym_info.address.hash(&mut hasher);
+    sym_info
+        .typeinfo
+        .hash_content(&debug_data.types, &mut hasher);
+    debug_data
+        .symbol_sizes
+        .get(&sym_info.name)
+        .hash(&mut hasher);
+    // the scaling of fixed-point types is stored separately from the types
+    let typeinfo = sym_info.typeinfo;
+    let target = typeinfo
+        .get_pointer(&debug_data.types)
+        .map_or(typeinfo, |(_, t)| t);
+    let element = target.get_arraytype().unwrap_or(target);
+    for t in [typeinfo, target, element] {
+        debug_data
+            .fixed_point_factor(t)
+            .map(f64::to_bits)
+            .hash(&mut hasher);
+    }
+    Some(hasher.finish())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_update_cache() {
+        let cache_file = std::env::temp_dir().join(format!("a2ltool_cache_{}", std::process::id()));
+        let cache = UpdateCache {
+            input_hash: hash_file(OsStr::new("tests/update_test1.a2l")).unwrap(),
+            a2l_hash: hash_file(OsStr::new("tests/update_test2.a2l")).unwrap(),
+            elf_hash: hash_file(OsStr::new("tests/elffiles/update_test.elf")).unwrap(),
+            inputs_hash: hash_files([OsStr::new("tests/update_test1.a2l")]).unwrap(),
+            settings: "update".to_string(),
+            object_hashes: HashMap::from([(
+                "Module".to_string(),
+                HashMap::from([("Measurement".to_string(), 0x1234)]),
+            )]),
+        };
+        assert_ne!(cache.input_hash, cache.a2l_hash);
+        assert_ne!(cache.input_hash, cache.inputs_hash);
+        cache.save(cache_file.as_os_str()).unwrap();
+        let loaded = UpdateCache::load(cache_file.as_os_str());
+        std::fs::remove_file(&cache_file).unwrap();
+        assert_eq!(loaded, Some(cache));
+
+        assert!(UpdateCache::load(OsStr::new("does_not_exist.json")).is_none());
+        assert!(hash_file(OsStr::new("does_not_exist.a2l")).is_err());
+        assert!(hash_files([OsStr::new("does_not_exist.a2l")]).is_err());
+    }
+
+    #[test]
+    fn test_compare() {
+        let current = UpdateCache::current(
+            OsStr::new("tests/update_test1.a2l"),
+            OsStr::new("tests/elffiles/update_test.elf"),
+            [OsStr::new("tests/update_test2.a2l")],
+            "update".to_string(),
+        )
+        .unwrap();
+        assert_eq!(current.compare(None, None), CacheStatus::default());
+
+        // the input file was updated in place
+        let cached = UpdateCache {
+            input_hash: 1,
+            a2l_hash: current.input_hash,
+            ..current.clone()
+        };
+        let status = current.compare(Some(cached.clone()), Some(current.input_hash));
+        assert!(!status.up_to_date);
+        assert!(status.skip_update);
+        assert!(status.object_hashes.is_some());
+
+        // the input and output are unchanged
+        let cached = UpdateCache {
+            a2l_hash: 2,
+            ..current.clone()
+        };
+        let status = current.compare(Some(cached.clone()), Some(2));
+        assert!(status.up_to_date);
+        assert!(!status.skip_update);
+        assert!(status.object_hashes.is_none());
+        assert!(!current.compare(Some(cached.clone()), Some(3)).up_to_date);
+
+        // a changed elf file only allows the unchanged objects to be skipped
+        let cached = UpdateCache {
+            a2l_hash: current.input_hash,
+            elf_hash: 3,
+            ..current.clone()
+        };
+        let status = current.compare(Some(cached), Some(current.input_hash));
+        assert!(!status.up_to_date);
+        assert!(!status.skip_update);
+        assert!(status.object_hashes.is_some());
+
+        // with different settings, nothing can be skipped
+        let cached = UpdateCache {
+            settings: "insert".to_string(),
+            ..current.clone()
+        };
+        assert_eq!(
+            current.compare(Some(cached), Some(0)),
+            CacheStatus::default()
+        );
+    }
+
+    #[test]
+    fn test_unchanged_objects() {
+        let a2l_text = r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin MEASUREMENT var_uint16 "" UBYTE NO_COMPU_METHOD 0 0 0 255 /end MEASUREMENT
+                /begin MEASUREMENT enum_var1 "" UBYTE NO_COMPU_METHOD 0 0 0 255 /end MEASUREMENT
+                /begin CHARACTERISTIC var_fl32 "" VALUE 0 RL 0 NO_COMPU_METHOD 0 255 /end CHARACTERISTIC
+                /begin BLOB var_sint32 "" 0 1 /end BLOB
+                /begin RECORD_LAYOUT RL FNC_VALUES 1 UBYTE ROW_DIR DIRECT /end RECORD_LAYOUT
+            /end MODULE
+        /end PROJECT"#;
+        let mut log_msgs = Vec::new();
+        let mut a2l_file = a2lfile::load_from_string(a2l_text, None, &mut log_msgs, false).unwrap();
+        let debug_data =
+            DebugData::load(OsStr::new("tests/elffiles/debugdata_gcc.elf"), false).unwrap();
+        crate::update::update_addresses(&mut a2l_file, &debug_data, &mut log_msgs, false, false);
+        let cached = object_hashes(&a2l_file, &debug_data);
+        assert_eq!(cached["m"].len(), 4);
+
+        // the elf file is different, but the debug information of the variables is the same
+        let elf_file = OsStr::new("tests/elffiles/debugdata_gcc_dwz.elf");
+        assert_ne!(
+            hash_file(elf_file).unwrap(),
+            hash_file(OsStr::new("tests/elffiles/debugdata_gcc.elf")).unwrap()
+        );
+        let relinked_debug_data = DebugData::load(elf_file, false).unwrap();
+        let current = object_hashes(&a2l_file, &relinked_debug_data);
+        assert_eq!(cached, current);
+        let unchanged = unchanged_objects(&cached, &current);
+        assert_eq!(unchanged["m"].len(), 4);
+
+        let updated_a2l = a2l_file.clone();
+        let summary = crate::update::update_addresses_incremental(
+            &mut a2l_file,
+            &relinked_debug_data,
+            &mut log_msgs,
+            false,
+            false,
+            crate::update::InstancePointerPolicy::default(),
+            &unchanged,
+            &mut |_| {},
+        );
+        assert_eq!(summary.unchanged, 4);
+        assert_eq!(summary.measurement_updated, 0);
+        assert_eq!(summary.characteristic_updated, 0);
+        assert_eq!(summary.blob_updated, 0);
+        assert_eq!(a2l_file, updated_a2l);
+
+        // the variables are at different addresses in an elf file from a different compiler
+        let other_debug_data =
+            DebugData::load(OsStr::new("tests/elffiles/debugdata_clang.elf"), false).unwrap();
+        let other = object_hashes(&a2l_file, &other_debug_data);
+        assert!(unchanged_objects(&cached, &other)["m"].is_empty());
+    }
+}
@@ -1,13 +1,17 @@
 use crate::dwarf::DwarfDataType;
 use crate::dwarf::{DebugData, TypeInfo};
 use crate::A2lVersion;
-use a2lfile::{A2lObject, AxisDescr, Characteristic, CharacteristicType, Module, RecordLayout};
+use a2lfile::{
+    A2lObject, AxisDescr, Characteristic, CharacteristicType, Module, Number, RecordLayout,
+};
 use std::collections::HashMap;
 use std::collections::HashSet;
 
 use crate::update::{
     adjust_limits, cleanup_item_list,
     enums::{cond_create_enum_conversion, update_enum_compu_methods},
+    find_compu_method,
+    fixed_point::cond_create_fixed_point_conversion,
     get_fnc_values_memberid, get_inner_type, get_symbol_info,
     ifdata_update::{update_ifdata, zero_if_data},
     log_update_errors, make_symbol_link_string, set_bitmask, set_matrix_dim, set_symbol_link,
@@ -34,6 +38,17 @@ pub(crate) fn update_module_characteristics(
 
     std::mem::swap(&mut info.module.characteristic, &mut characteristic_list);
     for mut characteristic in characteristic_list {
+        // the AXIS_DESCRs depend on the referenced AXIS_PTS, which must also be unchanged
+        let axis_pts_unchanged = characteristic.axis_descr.iter().all(|axis_descr| {
+            axis_descr
+                .axis_pts_ref
+                .as_ref()
+                .is_none_or(|axis_pts_ref| info.unchanged.contains(&axis_pts_ref.axis_points))
+        });
+        if axis_pts_unchanged && info.skip_unchanged(&characteristic.name) {
+            info.module.characteristic.push(characteristic);
+            continue;
+        }
         if characteristic.virtual_characteristic.is_none() {
             // only update the address if the CHARACTERISTIC is not a VIRTUAL_CHARACTERISTIC
             match update_characteristic_address(&mut characteristic, info.debug_data, info.version)
@@ -47,7 +62,8 @@ pub(crate) fn update_module_characteristics(
                         typeinfo,
                         &mut enum_convlist,
                         &axis_pts_dim,
-                        info.version >= A2lVersion::V1_7_0,
+                        info.debug_data,
+                        info.version,
                         compu_method_index,
                     );
 
@@ -95,26 +111,38 @@ fn update_characteristic_information<'enumlist, 'typeinfo: 'enumlist>(
     typeinfo: &'typeinfo TypeInfo,
     enum_convlist: &'enumlist mut HashMap<String, &'typeinfo TypeInfo>,
     axis_pts_dim: &HashMap<String, u16>,
-    use_new_matrix_dim: bool,
+    debug_data: &DebugData,
+    version: A2lVersion,
     compu_method_index: &HashMap<String, usize>,
 ) {
+    let use_new_matrix_dim = version >= A2lVersion::V1_7_0;
     let member_id = get_fnc_values_memberid(module, recordlayout_info, &characteristic.deposit);
     if let Some(inner_typeinfo) = get_inner_type(typeinfo, member_id) {
         if let DwarfDataType::Enum { enumerators, .. } = &inner_typeinfo.datatype {
-            let enum_name = inner_typeinfo
-                .name
-                .clone()
-                .unwrap_or_else(|| format!("{}_compu_method", characteristic.name));
+            let enum_name = inner_typeinfo.name.as_deref().map_or_else(
+                || format!("{}_compu_method", characteristic.name),
+                ToString::to_string,
+            );
             if characteristic.conversion == "NO_COMPU_METHOD" {
                 characteristic.conversion = enum_name;
             }
             cond_create_enum_conversion(module, &characteristic.conversion, enumerators);
             enum_convlist.insert(characteristic.conversion.clone(), inner_typeinfo);
+        } else if characteristic.conversion == "NO_COMPU_METHOD" {
+            let value_type = inner_typeinfo.get_arraytype().unwrap_or(inner_typeinfo);
+            if let Some(name) = cond_create_fixed_point_conversion(
+                module,
+                debug_data,
+                value_type,
+                &characteristic.name,
+                version,
+            ) {
+                characteristic.conversion = name;
+            }
         }
 
-        let opt_compu_method = compu_method_index
-            .get(&characteristic.conversion)
-            .and_then(|idx| module.compu_method.get(*idx));
+        let opt_compu_method =
+            find_compu_method(module, compu_method_index, &characteristic.conversion);
         let (ll, ul) = adjust_limits(
             inner_typeinfo,
             characteristic.lower_limit,
@@ -138,6 +166,17 @@ fn update_characteristic_information<'enumlist, 'typeinfo: 'enumlist>(
         characteristic.characteristic_type = CharacteristicType::Value;
     }
 
+    // a string is an array of characters: NUMBER contains the length of the string
+    if characteristic.characteristic_type == CharacteristicType::Ascii {
+        if let DwarfDataType::Array { dim, .. } = &typeinfo.datatype {
+            if dim.len() == 1 {
+                let number = characteristic.number.get_or_insert(Number::new(0));
+                number.number = u16::try_from(dim[0]).unwrap_or(u16::MAX);
+                characteristic.matrix_dim = None;
+            }
+        }
+    }
+
     // if the characteristic does not have any axes, update MATRIX_DIM and switch between types VALUE and VAL_BLK as needed
     if characteristic.characteristic_type == CharacteristicType::Value
         || characteristic.characteristic_type == CharacteristicType::ValBlk
@@ -155,7 +194,19 @@ fn update_characteristic_information<'enumlist, 'typeinfo: 'enumlist>(
             // change ValBlk -> Value
             characteristic.characteristic_type = CharacteristicType::Value;
         }
-        characteristic.number = None;
+        // NUMBER is deprecated for VAL_BLK, but files before version 1.7 may use it instead of MATRIX_DIM.
+        // In these files it is kept and contains the total number of values
+        match (&characteristic.matrix_dim, &mut characteristic.number) {
+            (Some(matrix_dim), Some(number)) if !use_new_matrix_dim => {
+                let count: u64 = matrix_dim
+                    .dim_list
+                    .iter()
+                    .map(|dim| u64::from(*dim))
+                    .product();
+                number.number = u16::try_from(count).unwrap_or(u16::MAX);
+            }
+            _ => characteristic.number = None,
+        }
     } else {
         characteristic.matrix_dim = None;
     }
@@ -4,12 +4,13 @@ use a2lfile::{
     ValueTriplesStruct,
 };
 use std::collections::HashMap;
+use std::sync::Arc;
 
 // create a COMPU_METHOD and a COMPU_VTAB for the typename of an enum
 pub(crate) fn cond_create_enum_conversion(
     module: &mut Module,
     typename: &str,
-    enumerators: &[(String, i64)],
+    enumerators: &[(Arc<str>, i64)],
 ) {
     let compu_method_find = module
         .compu_method
@@ -42,7 +43,7 @@ pub(crate) fn cond_create_enum_conversion(
             for (name, value) in enumerators {
                 new_compu_vtab
                     .value_pairs
-                    .push(ValuePairsStruct::new(*value as f64, name.to_owned()));
+                    .push(ValuePairsStruct::new(*value as f64, name.to_string()));
             }
             module.compu_vtab.push(new_compu_vtab);
         }
@@ -103,7 +104,7 @@ pub(crate) fn update_enum_compu_methods(
             // overwrite the current compu_vtab entries with the values from the enum
             for (idx, (name, value)) in enumerators.iter().enumerate() {
                 compu_vtab.value_pairs[idx].in_val = *value as f64;
-                compu_vtab.value_pairs[idx].out_val = name.clone();
+                compu_vtab.value_pairs[idx].out_val = name.to_string();
             }
         }
     }
@@ -137,7 +138,7 @@ pub(crate) fn update_enum_compu_methods(
             for (idx, (name, value)) in enumerators.iter().enumerate() {
                 compu_vtab_range.value_triples[idx].in_val_min = *value as f64;
                 compu_vtab_range.value_triples[idx].in_val_max = *value as f64;
-                compu_vtab_range.value_triples[idx].out_val = name.clone();
+                compu_vtab_range.value_triples[idx].out_val = name.to_string();
             }
         }
     }
@@ -0,0 +1,144 @@
+use crate::dwarf::{DebugData, TypeInfo};
+use crate::A2lVersion;
+use a2lfile::{Coeffs, CoeffsLinear, CompuMethod, ConversionType, Module};
+
+// if the type is a fixed-point type, create a COMPU_METHOD for it and return its name
+// An existing COMPU_METHOD with the same name is reused without changes
+pub(crate) fn cond_create_fixed_point_conversion(
+    module: &mut Module,
+    debug_data: &DebugData,
+    typeinfo: &TypeInfo,
+    item_name: &str,
+    version: A2lVersion,
+) -> Option<String> {
+    let factor = debug_data.fixed_point_factor(typeinfo)?;
+    let name = typeinfo
+        .name
+        .as_deref()
+        .map_or_else(|| format!("{item_name}_compu_method"), ToString::to_string);
+    if !module.compu_method.iter().any(|item| item.name == name) {
+        // enough decimal places to display the resolution of the type
+        let decimals = (-factor.log10()).ceil().clamp(0.0, 15.0) as u32;
+        if version >= A2lVersion::V1_6_0 {
+            // LINEAR: PHYS = a * INT + b
+            let mut compu_method = CompuMethod::new(
+                name.clone(),
+                format!("Conversion for fixed-point type {name}"),
+                ConversionType::Linear,
+                format!("%.{decimals}"),
+                String::new(),
+            );
+            compu_method.coeffs_linear = Some(CoeffsLinear::new(factor, 0.0));
+            module.compu_method.push(compu_method);
+        } else {
+            // RAT_FUNC: INT = (a * PHYS^2 + b * PHYS + c) / (d * PHYS^2 + e * PHYS + f)
+            let mut compu_method = CompuMethod::new(
+                name.clone(),
+                format!("Conversion for fixed-point type {name}"),
+                ConversionType::RatFunc,
+                format!("%.{decimals}"),
+                String::new(),
+            );
+            compu_method.coeffs = Some(Coeffs::new(0.0, 1.0 / factor, 0.0, 0.0, 0.0, 1.0));
+            module.compu_method.push(compu_method);
+        }
+    }
+    Some(name)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dwarf::{DebugData, DuplicateSymbolPolicy, DwarfDataType, TypeInfo, VarInfo};
+    use indexmap::IndexMap;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_fixed_point_conversion() {
+        // global variables: _q15 q_gain; int16_t raw_value
+        let mut debug_data = DebugData {
+            types: HashMap::new(),
+            typenames: HashMap::new(),
+            variables: IndexMap::new(),
+            demangled_names: HashMap::new(),
+            unit_names: Vec::new(),
+            source_files: Vec::new(),
+            sections: HashMap::new(),
+            section_flags: HashMap::new(),
+            load_segments: Vec::new(),
+            symbol_sizes: HashMap::new(),
+            fixed_point: HashMap::new(),
+            little_endian: true,
+            case_folded: None,
+            ignore_leading_underscore: false,
+            duplicate_symbol_policy: DuplicateSymbolPolicy::First,
+        };
+        for (name, address, typeref) in [("q_gain", 0x1000, 1), ("raw_value", 0x1002, 2)] {
+            debug_data.variables.insert(
+                name.to_string(),
+                vec![VarInfo {
+                    address,
+                    typeref,
+                    unit_idx: 0,
+                    function: None,
+                    decl_file: None,
+                    namespaces: vec![],
+                }],
+            );
+        }
+        for (offset, name) in [(1, "_q15"), (2, "int16_t")] {
+            debug_data.types.insert(
+                offset,
+                TypeInfo {
+                    datatype: DwarfDataType::Sint16,
+                    name: Some(name.into()),
+                    unit_idx: 0,
+                    dbginfo_offset: offset,
+                },
+            );
+        }
+        debug_data.fixed_point.insert(1, 2f64.powi(-15));
+
+        for (version_line, conversion_type) in [
+            ("ASAP2_VERSION 1 71", a2lfile::ConversionType::Linear),
+            ("ASAP2_VERSION 1 51", a2lfile::ConversionType::RatFunc),
+        ] {
+            let mut load_msgs = Vec::new();
+            let mut a2l_file = a2lfile::load_from_string(
+                &format!(
+                    r#"{version_line}
+                /begin PROJECT p ""
+                    /begin MODULE m ""
+                        /begin MEASUREMENT q_gain "" SWORD NO_COMPU_METHOD 0 0 0 0
+                            ECU_ADDRESS 0
+                        /end MEASUREMENT
+                        /begin MEASUREMENT raw_value "" SWORD NO_COMPU_METHOD 0 0 0 0
+                            ECU_ADDRESS 0
+                        /end MEASUREMENT
+                    /end MODULE
+                /end PROJECT"#
+                ),
+                None,
+                &mut load_msgs,
+                true,
+            )
+            .unwrap();
+            let mut log_msgs = Vec::new();
+            crate::update::update_addresses(
+                &mut a2l_file,
+                &debug_data,
+                &mut log_msgs,
+                false,
+                false,
+            );
+
+            let module = &a2l_file.project.module[0];
+            assert_eq!(module.measurement[0].conversion, "_q15");
+            assert_eq!(module.measurement[0].lower_limit, -1.0);
+            assert!((module.measurement[0].upper_limit - 1.0).abs() < 0.001);
+            assert_eq!(module.measurement[1].conversion, "NO_COMPU_METHOD");
+            assert_eq!(module.compu_method.len(), 1);
+            assert_eq!(module.compu_method[0].conversion_type, conversion_type);
+            assert_eq!(module.compu_method[0].format, "%.5");
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use crate::dwarf::{DebugData, TypeInfo};
 use a2lfile::{A2lObject, Instance, Module};
 use std::collections::HashSet;
+use std::str::FromStr;
 
 use crate::update::{
     cleanup_removed_axis_pts, cleanup_removed_blobs, cleanup_removed_characteristics,
@@ -11,6 +12,42 @@ use crate::update::{
 
 use super::{make_symbol_link_string, set_address_type, set_matrix_dim, UpdateInfo};
 
+/// How INSTANCEs of variables with nested pointers are handled during the update.
+///
+/// An INSTANCE can only represent one pointer level with its ADDRESS_TYPE. Further levels occur
+/// in variables like `T** var` or `T* var[4]`, and can only be represented by TYPEDEF_*s that
+/// have their own ADDRESS_TYPE.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InstancePointerPolicy {
+    /// do not update the data type of the INSTANCE and log a warning
+    Skip,
+    /// represent up to the given number of nested pointer levels as separate TYPEDEF_* levels;
+    /// the data type of INSTANCEs with deeper nesting is not updated
+    Follow(u32),
+    /// represent all nested pointer levels as separate TYPEDEF_* levels
+    #[default]
+    Typedef,
+}
+
+impl FromStr for InstancePointerPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(Self::Skip),
+            "typedef" => Ok(Self::Typedef),
+            "follow" => Ok(Self::Follow(1)),
+            _ => s
+                .strip_prefix("follow:")
+                .and_then(|levels| levels.parse().ok())
+                .map(Self::Follow)
+                .ok_or_else(|| {
+                    format!("invalid pointer policy \"{s}\", expected skip, typedef or follow:N")
+                }),
+        }
+    }
+}
+
 pub(crate) fn update_module_instances<'dbg>(
     info: &mut UpdateInfo<'_, 'dbg, '_>,
     nameset: &TypedefNames,
@@ -25,6 +62,7 @@ pub(crate) fn update_module_instances<'dbg>(
         match update_instance_address(&mut instance, info.debug_data) {
             Ok((typedef_ref, typeinfo)) => {
                 if nameset.contains(&typedef_ref) {
+                    let typedef_ref = nameset.shared_name(&typedef_ref);
                     // Each INSTANCE can have:
                     // - an ADDRESS_TYPE, which means that it is a pointer to some data
                     // - a MATRIX_DIM, meaning this instance is an array of some data
@@ -34,6 +72,28 @@ pub(crate) fn update_module_instances<'dbg>(
                     // Therefore the typeinfo should be transformed to the base data type by first unwrapping
                     // one pointer (if any), and then getting an array element type (if any)
                     // More complicted constructions like pointers to pointers, arrays of pointers, etc. can not be represented directly
+                    let nested_levels = count_nested_pointers(typeinfo, info.debug_data);
+                    let max_levels = match info.pointer_policy {
+                        InstancePointerPolicy::Skip => 0,
+                        InstancePointerPolicy::Follow(levels) => levels,
+                        InstancePointerPolicy::Typedef => u32::MAX,
+                    };
+                    if nested_levels > max_levels {
+                        info.log_msgs.push(format!(
+                            "Warning: INSTANCE {} on line {} has {nested_levels} nested pointer level(s), which are not represented by TYPEDEFs. Its data type was not updated.",
+                            instance.name,
+                            instance.get_line()
+                        ));
+                        // keep the existing TYPEDEF, without associating it with the nested type
+                        typedef_types
+                            .entry(typedef_ref)
+                            .or_default()
+                            .push((None, TypedefReferrer::Instance(info.module.instance.len())));
+                        info.module.instance.push(instance);
+                        instance_not_updated += 1;
+                        continue;
+                    }
+
                     set_address_type(&mut instance.address_type, typeinfo);
                     let basetype = typeinfo
                         .get_pointer(&info.debug_data.types)
@@ -64,7 +124,7 @@ pub(crate) fn update_module_instances<'dbg>(
                     instance.start_address = 0;
                     zero_if_data(&mut instance.if_data);
                     typedef_types
-                        .entry(instance.type_ref.clone())
+                        .entry(nameset.shared_name(&instance.type_ref))
                         .or_default()
                         .push((None, TypedefReferrer::Instance(info.module.instance.len())));
                     info.module.instance.push(instance);
@@ -81,6 +141,20 @@ pub(crate) fn update_module_instances<'dbg>(
     (instance_updated, instance_not_updated, typedef_types)
 }
 
+// count the pointer levels that remain after the INSTANCE has consumed one pointer level and the array dimensions
+fn count_nested_pointers(typeinfo: &TypeInfo, debug_data: &DebugData) -> u32 {
+    let mut levels = 0;
+    let typeinfo = typeinfo
+        .get_pointer(&debug_data.types)
+        .map_or(typeinfo, |(_, t)| t);
+    let mut cur_typeinfo = typeinfo.get_arraytype().unwrap_or(typeinfo);
+    while let Some((_, pt_type)) = cur_typeinfo.get_pointer(&debug_data.types) {
+        levels += 1;
+        cur_typeinfo = pt_type.get_arraytype().unwrap_or(pt_type);
+    }
+    levels
+}
+
 // update the address of an INSTANCE object
 fn update_instance_address<'a>(
     instance: &mut Instance,
@@ -8,6 +8,8 @@ use std::collections::HashSet;
 use crate::update::{
     adjust_limits, cleanup_item_list,
     enums::{cond_create_enum_conversion, update_enum_compu_methods},
+    find_compu_method,
+    fixed_point::cond_create_fixed_point_conversion,
     get_a2l_datatype, get_symbol_info,
     ifdata_update::{update_ifdata, zero_if_data},
     log_update_errors, set_bitmask, set_matrix_dim, set_measurement_ecu_address, set_symbol_link,
@@ -27,6 +29,10 @@ pub(crate) fn update_module_measurements(
 
     std::mem::swap(&mut info.module.measurement, &mut measurement_list);
     for mut measurement in measurement_list {
+        if info.skip_unchanged(&measurement.name) {
+            info.module.measurement.push(measurement);
+            continue;
+        }
         if measurement.var_virtual.is_none() {
             // only MEASUREMENTS that are not VIRTUAL can be updated
             match update_measurement_address(&mut measurement, info.debug_data, info.version) {
@@ -38,7 +44,7 @@ pub(crate) fn update_module_measurements(
                         &mut measurement,
                         typeinfo,
                         &mut enum_convlist,
-                        info.version >= A2lVersion::V1_7_0,
+                        info.version,
                         compu_method_index,
                     );
 
@@ -85,7 +91,7 @@ pub(crate) fn update_content<'enumlist, 'typeinfo: 'enumlist>(
     measurement: &mut Measurement,
     typeinfo: &'typeinfo TypeInfo,
     enum_convlist: &'enumlist mut HashMap<String, &'typeinfo TypeInfo>,
-    use_new_matrix_dim: bool,
+    version: A2lVersion,
     compu_method_index: &HashMap<String, usize>,
 ) {
     // handle pointers - only allowed for version 1.7.0+ (the caller should take care of this precondition)
@@ -95,24 +101,36 @@ pub(crate) fn update_content<'enumlist, 'typeinfo: 'enumlist>(
         .map_or(typeinfo, |(_, t)| t);
 
     // handle arrays and unwrap the typeinfo
-    set_matrix_dim(&mut measurement.matrix_dim, typeinfo, use_new_matrix_dim);
+    set_matrix_dim(
+        &mut measurement.matrix_dim,
+        typeinfo,
+        version >= A2lVersion::V1_7_0,
+    );
     measurement.array_size = None;
     let typeinfo = typeinfo.get_arraytype().unwrap_or(typeinfo);
 
     if let DwarfDataType::Enum { enumerators, .. } = &typeinfo.datatype {
         if measurement.conversion == "NO_COMPU_METHOD" {
-            measurement.conversion = typeinfo
-                .name
-                .clone()
-                .unwrap_or_else(|| format!("{}_compu_method", measurement.name));
+            measurement.conversion = typeinfo.name.as_deref().map_or_else(
+                || format!("{}_compu_method", measurement.name),
+                ToString::to_string,
+            );
         }
         cond_create_enum_conversion(module, &measurement.conversion, enumerators);
         enum_convlist.insert(measurement.conversion.clone(), typeinfo);
+    } else if measurement.conversion == "NO_COMPU_METHOD" {
+        if let Some(name) = cond_create_fixed_point_conversion(
+            module,
+            debug_data,
+            typeinfo,
+            &measurement.name,
+            version,
+        ) {
+            measurement.conversion = name;
+        }
     }
 
-    let opt_compu_method = compu_method_index
-        .get(&measurement.conversion)
-        .and_then(|idx| module.compu_method.get(*idx));
+    let opt_compu_method = find_compu_method(module, compu_method_index, &measurement.conversion);
     let (ll, ul) = adjust_limits(
         typeinfo,
         measurement.lower_limit,
@@ -1,15 +1,22 @@
+//! Update the addresses and data types of all objects based on the debug info
+
 use crate::dwarf::{make_simple_unit_name, DebugData, TypeInfo};
+use crate::intern::StringInterner;
 use crate::{ifdata, A2lVersion};
 use a2lfile::{
     A2lFile, A2lObject, AddrType, AddressType, BitMask, CompuMethod, EcuAddress, IfData, MatrixDim,
     Module, SymbolLink,
 };
 use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
+mod address_delta;
 mod axis_pts;
 mod blob;
+pub mod cache;
 mod characteristic;
 pub mod enums;
+pub(crate) mod fixed_point;
 mod ifdata_update;
 mod instance;
 mod measurement;
@@ -19,39 +26,49 @@ pub(crate) mod typedef;
 use crate::datatype::{get_a2l_datatype, get_type_limits};
 use crate::dwarf::DwarfDataType;
 use crate::symbol::{find_symbol, SymbolInfo};
+pub use address_delta::AddressSnapshot;
 use axis_pts::*;
 use blob::{cleanup_removed_blobs, update_module_blobs};
 use characteristic::*;
 use instance::update_module_instances;
+pub use instance::InstancePointerPolicy;
 use measurement::*;
 use record_layout::*;
 use typedef::update_module_typedefs;
 
-pub(crate) struct UpdateSumary {
-    pub(crate) measurement_updated: u32,
-    pub(crate) measurement_not_updated: u32,
-    pub(crate) characteristic_updated: u32,
-    pub(crate) characteristic_not_updated: u32,
-    pub(crate) axis_pts_updated: u32,
-    pub(crate) axis_pts_not_updated: u32,
-    pub(crate) blob_updated: u32,
-    pub(crate) blob_not_updated: u32,
-    pub(crate) instance_updated: u32,
-    pub(crate) instance_not_updated: u32,
+/// number of objects of each type that were updated or could not be found in the debug info
+pub struct UpdateSumary {
+    pub measurement_updated: u32,
+    pub measurement_not_updated: u32,
+    pub characteristic_updated: u32,
+    pub characteristic_not_updated: u32,
+    pub axis_pts_updated: u32,
+    pub axis_pts_not_updated: u32,
+    /// number of updated AXIS_PTS whose max_axis_points was changed
+    pub axis_pts_dim_changed: u32,
+    pub blob_updated: u32,
+    pub blob_not_updated: u32,
+    /// number of updated BLOBs whose size was changed
+    pub blob_size_changed: u32,
+    pub instance_updated: u32,
+    pub instance_not_updated: u32,
+    /// number of objects that were skipped because their debug information has not changed
+    pub unchanged: u32,
 }
 
 #[derive(Debug, Clone)]
 pub(crate) enum TypedefReferrer {
     Instance(usize),
-    StructureComponent(String, String),
+    StructureComponent(Arc<str>, Arc<str>),
 }
 
+// the names in TypedefNames are shared with the keys of TypedefsRefInfo, which has one entry for each TYPEDEF
 pub(crate) struct TypedefNames {
-    axis: HashSet<String>,
-    blob: HashSet<String>,
-    characteristic: HashSet<String>,
-    measurement: HashSet<String>,
-    structure: HashSet<String>,
+    axis: StringInterner,
+    blob: StringInterner,
+    characteristic: StringInterner,
+    measurement: StringInterner,
+    structure: StringInterner,
 }
 
 pub(crate) struct UpdateInfo<'a2l, 'dbg, 'log> {
@@ -59,34 +76,99 @@ pub(crate) struct UpdateInfo<'a2l, 'dbg, 'log> {
     pub(crate) debug_data: &'dbg DebugData,
     pub(crate) log_msgs: &'log mut Vec<String>,
     pub(crate) preserve_unknown: bool,
+    pub(crate) pointer_policy: InstancePointerPolicy,
     pub(crate) version: A2lVersion,
     pub(crate) reclayout_info: RecordLayoutInfo,
+    // objects that were updated with identical debug information before, and can be skipped
+    pub(crate) unchanged: &'dbg HashSet<String>,
+    pub(crate) unchanged_count: u32,
+}
+
+type TypedefsRefInfo<'a> = HashMap<Arc<str>, Vec<(Option<&'a TypeInfo>, TypedefReferrer)>>;
+
+/// perform an address update.
+/// This update can be destructive (any object that cannot be updated will be discarded)
+/// or non-destructive (addresses of invalid objects will be set to zero).
+pub fn update_addresses(
+    a2l_file: &mut A2lFile,
+    debug_data: &DebugData,
+    log_msgs: &mut Vec<String>,
+    preserve_unknown: bool,
+    enable_structures: bool,
+) -> UpdateSumary {
+    update_addresses_with_progress(
+        a2l_file,
+        debug_data,
+        log_msgs,
+        preserve_unknown,
+        enable_structures,
+        InstancePointerPolicy::default(),
+        &mut |_| {},
+    )
 }
 
-type TypedefsRefInfo<'a> = HashMap<String, Vec<(Option<&'a TypeInfo>, TypedefReferrer)>>;
+/// perform an address update, see [`update_addresses`].
+/// The callback `progress` is called with a description of each step before the step starts.
+/// INSTANCEs of variables with nested pointers are handled according to `pointer_policy`.
+pub fn update_addresses_with_progress(
+    a2l_file: &mut A2lFile,
+    debug_data: &DebugData,
+    log_msgs: &mut Vec<String>,
+    preserve_unknown: bool,
+    enable_structures: bool,
+    pointer_policy: InstancePointerPolicy,
+    progress: &mut dyn FnMut(&str),
+) -> UpdateSumary {
+    update_addresses_incremental(
+        a2l_file,
+        debug_data,
+        log_msgs,
+        preserve_unknown,
+        enable_structures,
+        pointer_policy,
+        &HashMap::new(),
+        progress,
+    )
+}
 
-// perform an address update.
-// This update can be destructive (any object that cannot be updated will be discarded)
-// or non-destructive (addresses of invalid objects will be set to zero).
-pub(crate) fn update_addresses(
+/// perform an address update, see [`update_addresses_with_progress`].
+/// The MEASUREMENTs, CHARACTERISTICs, AXIS_PTS and BLOBs listed in `unchanged` for each module are
+/// kept as they are. This is only correct if they are the result of a previous update, and their
+/// debug information is still the same, see [`cache::unchanged_objects`].
+#[allow(clippy::too_many_arguments)]
+pub fn update_addresses_incremental(
     a2l_file: &mut A2lFile,
     debug_data: &DebugData,
     log_msgs: &mut Vec<String>,
     preserve_unknown: bool,
     enable_structures: bool,
+    pointer_policy: InstancePointerPolicy,
+    unchanged: &HashMap<String, HashSet<String>>,
+    progress: &mut dyn FnMut(&str),
 ) -> UpdateSumary {
     let version = A2lVersion::from(&*a2l_file);
+    // nested pointer levels can only be represented if the TYPEDEFs are updated
+    let pointer_policy = if enable_structures {
+        pointer_policy
+    } else {
+        InstancePointerPolicy::Skip
+    };
 
     let mut summary = UpdateSumary::new();
+    let no_unchanged_objects = HashSet::new();
     for module in &mut a2l_file.project.module {
         let reclayout_info = RecordLayoutInfo::build(module);
+        let unchanged = unchanged.get(&module.name).unwrap_or(&no_unchanged_objects);
         let mut info = UpdateInfo {
             module,
             debug_data,
             log_msgs,
             preserve_unknown,
+            pointer_policy,
             version,
             reclayout_info,
+            unchanged,
+            unchanged_count: 0,
         };
 
         let compu_method_index = info
@@ -98,35 +180,52 @@ pub(crate) fn update_addresses(
             .collect::<HashMap<_, _>>();
 
         // update all AXIS_PTS
-        let (updated, not_updated) = update_module_axis_pts(&mut info, &compu_method_index);
+        progress(&format!("Updating {} AXIS_PTS", info.module.axis_pts.len()));
+        let (updated, not_updated, dim_changed) =
+            update_module_axis_pts(&mut info, &compu_method_index);
         summary.measurement_updated += updated;
         summary.measurement_not_updated += not_updated;
+        summary.axis_pts_dim_changed += dim_changed;
 
         // update all MEASUREMENTs
+        progress(&format!(
+            "Updating {} MEASUREMENTs",
+            info.module.measurement.len()
+        ));
         let (updated, not_updated) = update_module_measurements(&mut info, &compu_method_index);
         summary.measurement_updated += updated;
         summary.measurement_not_updated += not_updated;
 
         // update all CHARACTERISTICs
+        progress(&format!(
+            "Updating {} CHARACTERISTICs",
+            info.module.characteristic.len()
+        ));
         let (updated, not_updated) = update_module_characteristics(&mut info, &compu_method_index);
         summary.characteristic_updated += updated;
         summary.characteristic_not_updated += not_updated;
 
         // update all BLOBs
-        let (updated, not_updated) =
-            update_module_blobs(info.module, debug_data, info.log_msgs, preserve_unknown);
+        progress(&format!("Updating {} BLOBs", info.module.blob.len()));
+        let (updated, not_updated, size_changed) = update_module_blobs(&mut info);
         summary.blob_updated += updated;
         summary.blob_not_updated += not_updated;
+        summary.blob_size_changed += size_changed;
 
         let typedef_names = TypedefNames::new(info.module);
 
         // update all INSTANCEs
+        progress(&format!(
+            "Updating {} INSTANCEs",
+            info.module.instance.len()
+        ));
         let (updated, not_updated, typedef_ref_info) =
             update_module_instances(&mut info, &typedef_names);
         summary.instance_updated += updated;
         summary.instance_not_updated += not_updated;
 
         if enable_structures {
+            progress("Updating TYPEDEFs");
             update_module_typedefs(
                 &mut info,
                 typedef_ref_info,
@@ -134,13 +233,14 @@ pub(crate) fn update_addresses(
                 &compu_method_index,
             );
         }
+        summary.unchanged += info.unchanged_count;
     }
 
     summary
 }
 
 // try to get the symbol name used in the elf file, and find its address and type
-fn get_symbol_info<'a>(
+pub(crate) fn get_symbol_info<'a>(
     name: &str,
     opt_symbol_link: &Option<SymbolLink>,
     ifdata_vec: &[IfData],
@@ -340,6 +440,21 @@ fn get_symbol_name_from_ifdata(ifdata_vec: &[IfData]) -> Option<String> {
 // generate adjusted min and max limits based on the datatype.
 // since the updater code has no knowledge how the data is handled in the application it
 // is only possible to shrink existing limits, but not expand them
+// find a COMPU_METHOD by name. COMPU_METHODs that were created during the update are not in the index
+fn find_compu_method<'a>(
+    module: &'a Module,
+    compu_method_index: &HashMap<String, usize>,
+    name: &str,
+) -> Option<&'a CompuMethod> {
+    if let Some(idx) = compu_method_index.get(name) {
+        module.compu_method.get(*idx)
+    } else if name == "NO_COMPU_METHOD" {
+        None
+    } else {
+        module.compu_method.iter().find(|item| item.name == name)
+    }
+}
+
 fn adjust_limits(
     typeinfo: &TypeInfo,
     old_lower_limit: f64,
@@ -435,45 +550,126 @@ impl UpdateSumary {
         Self {
             axis_pts_not_updated: 0,
             axis_pts_updated: 0,
+            axis_pts_dim_changed: 0,
             blob_not_updated: 0,
             blob_updated: 0,
+            blob_size_changed: 0,
             characteristic_not_updated: 0,
             characteristic_updated: 0,
             measurement_not_updated: 0,
             measurement_updated: 0,
             instance_not_updated: 0,
             instance_updated: 0,
+            unchanged: 0,
+        }
+    }
+
+    /// total number of objects that could not be found in the debug info
+    pub fn failures(&self) -> u32 {
+        self.characteristic_not_updated
+            + self.measurement_not_updated
+            + self.axis_pts_not_updated
+            + self.blob_not_updated
+            + self.instance_not_updated
+    }
+
+    /// all counters with their names, e.g. for a machine readable report
+    pub fn counts(&self) -> Vec<(&'static str, usize)> {
+        vec![
+            (
+                "characteristic_updated",
+                self.characteristic_updated as usize,
+            ),
+            (
+                "characteristic_not_found",
+                self.characteristic_not_updated as usize,
+            ),
+            ("measurement_updated", self.measurement_updated as usize),
+            (
+                "measurement_not_found",
+                self.measurement_not_updated as usize,
+            ),
+            ("axis_pts_updated", self.axis_pts_updated as usize),
+            ("axis_pts_not_found", self.axis_pts_not_updated as usize),
+            ("axis_pts_dim_changed", self.axis_pts_dim_changed as usize),
+            ("blob_updated", self.blob_updated as usize),
+            ("blob_not_found", self.blob_not_updated as usize),
+            ("blob_size_changed", self.blob_size_changed as usize),
+            ("instance_updated", self.instance_updated as usize),
+            ("instance_not_found", self.instance_not_updated as usize),
+            ("unchanged", self.unchanged as usize),
+        ]
+    }
+}
+
+impl std::fmt::Display for UpdateSumary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "   characteristic: {} updated, {} not found",
+            self.characteristic_updated, self.characteristic_not_updated
+        )?;
+        writeln!(
+            f,
+            "   measurement: {} updated, {} not found",
+            self.measurement_updated, self.measurement_not_updated
+        )?;
+        writeln!(
+            f,
+            "   axis_pts: {} updated, {} not found, {} resized",
+            self.axis_pts_updated, self.axis_pts_not_updated, self.axis_pts_dim_changed
+        )?;
+        writeln!(
+            f,
+            "   blob: {} updated, {} not found, {} resized",
+            self.blob_updated, self.blob_not_updated, self.blob_size_changed
+        )?;
+        write!(
+            f,
+            "   instance: {} updated, {} not found",
+            self.instance_updated, self.instance_not_updated
+        )?;
+        if self.unchanged > 0 {
+            write!(
+                f,
+                "\n   {} objects were skipped, because their symbols are unchanged since the last update",
+                self.unchanged
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl UpdateInfo<'_, '_, '_> {
+    // check if an object can be skipped, because it was updated with identical debug information before
+    pub(crate) fn skip_unchanged(&mut self, name: &str) -> bool {
+        let skip = self.unchanged.contains(name);
+        if skip {
+            self.unchanged_count += 1;
         }
+        skip
     }
 }
 
 impl TypedefNames {
     pub(crate) fn new(module: &Module) -> Self {
         Self {
-            axis: module
-                .typedef_axis
-                .iter()
-                .map(|item| item.name.clone())
-                .collect(),
-            blob: module
-                .typedef_blob
-                .iter()
-                .map(|item| item.name.clone())
-                .collect(),
+            axis: module.typedef_axis.iter().map(|item| &item.name).collect(),
+            blob: module.typedef_blob.iter().map(|item| &item.name).collect(),
             characteristic: module
                 .typedef_characteristic
                 .iter()
-                .map(|item| item.name.clone())
+                .map(|item| &item.name)
                 .collect(),
             measurement: module
                 .typedef_measurement
                 .iter()
-                .map(|item| item.name.clone())
+                .map(|item| &item.name)
                 .collect(),
             structure: module
                 .typedef_structure
                 .iter()
-                .map(|item| item.name.clone())
+                .map(|item| &item.name)
                 .collect(),
         }
     }
@@ -485,11 +681,22 @@ impl TypedefNames {
             || self.blob.contains(name)
             || self.axis.contains(name)
     }
+
+    // get the shared copy of a TYPEDEF name, or a new copy if the name is not a known TYPEDEF
+    pub(crate) fn shared_name(&self, name: &str) -> Arc<str> {
+        self.structure
+            .get(name)
+            .or_else(|| self.measurement.get(name))
+            .or_else(|| self.characteristic.get(name))
+            .or_else(|| self.blob.get(name))
+            .or_else(|| self.axis.get(name))
+            .unwrap_or_else(|| Arc::from(name))
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use super::adjust_limits;
+    use super::{adjust_limits, InstancePointerPolicy};
     use crate::dwarf::{DwarfDataType, TypeInfo};
     use a2lfile::{Coeffs, CoeffsLinear, CompuMethod, ConversionType};
 
@@ -534,4 +741,155 @@ mod test {
         assert_eq!(lower, 0.0);
         assert_eq!(upper, 10200.0);
     }
+
+    #[test]
+    fn test_update_blob_size() {
+        let a2l_text = r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin BLOB TEST_structarr "" 0 1 /end BLOB
+            /end MODULE
+        /end PROJECT"#;
+        let mut log_msgs = Vec::new();
+        let mut a2l_file = a2lfile::load_from_string(a2l_text, None, &mut log_msgs, false).unwrap();
+        let debug_data = crate::DebugData::load(
+            std::ffi::OsStr::new("tests/elffiles/update_test.elf"),
+            false,
+        )
+        .unwrap();
+        let mut log_msgs = Vec::new();
+        let summary =
+            super::update_addresses(&mut a2l_file, &debug_data, &mut log_msgs, false, false);
+        assert_eq!(summary.blob_updated, 1);
+        assert_eq!(summary.blob_size_changed, 1);
+        let blob = &a2l_file.project.module[0].blob[0];
+        assert_ne!(blob.start_address, 0);
+        assert!(blob.size > 1);
+        assert!(log_msgs[0].contains("changed from 1 to"));
+    }
+
+    #[test]
+    fn test_update_val_blk_number() {
+        let a2l_text = r#"
+        ASAP2_VERSION 1 61
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin CHARACTERISTIC TEST_structarr "" VAL_BLK 0 RL 0 NO_COMPU_METHOD 0 255
+                    NUMBER 3
+                /end CHARACTERISTIC
+                /begin RECORD_LAYOUT RL FNC_VALUES 1 UBYTE ROW_DIR DIRECT /end RECORD_LAYOUT
+            /end MODULE
+        /end PROJECT"#;
+        let mut log_msgs = Vec::new();
+        let mut a2l_file = a2lfile::load_from_string(a2l_text, None, &mut log_msgs, false).unwrap();
+        let debug_data = crate::DebugData::load(
+            std::ffi::OsStr::new("tests/elffiles/update_test.elf"),
+            false,
+        )
+        .unwrap();
+        let mut log_msgs = Vec::new();
+        super::update_addresses(&mut a2l_file, &debug_data, &mut log_msgs, false, false);
+        let characteristic = &a2l_file.project.module[0].characteristic[0];
+        // TEST_structarr has 10 elements
+        assert_eq!(characteristic.number.as_ref().unwrap().number, 10);
+        assert_eq!(
+            characteristic.matrix_dim.as_ref().unwrap().dim_list,
+            vec![10, 1, 1]
+        );
+    }
+
+    #[test]
+    fn test_update_axis_pts_dim() {
+        let a2l_text = r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin AXIS_PTS TEST_structarr "" 0 NO_INPUT_QUANTITY RL_AXIS 0 NO_COMPU_METHOD 5 0 255
+                /end AXIS_PTS
+                /begin CHARACTERISTIC val_u8 "" CURVE 0 RL 0 NO_COMPU_METHOD 0 255
+                    /begin AXIS_DESCR COM_AXIS NO_INPUT_QUANTITY NO_COMPU_METHOD 5 0 255
+                        AXIS_PTS_REF TEST_structarr
+                    /end AXIS_DESCR
+                /end CHARACTERISTIC
+                /begin RECORD_LAYOUT RL FNC_VALUES 1 UBYTE ROW_DIR DIRECT /end RECORD_LAYOUT
+                /begin RECORD_LAYOUT RL_AXIS AXIS_PTS_X 1 UBYTE INDEX_INCR DIRECT /end RECORD_LAYOUT
+            /end MODULE
+        /end PROJECT"#;
+        let mut log_msgs = Vec::new();
+        let mut a2l_file = a2lfile::load_from_string(a2l_text, None, &mut log_msgs, false).unwrap();
+        let debug_data = crate::DebugData::load(
+            std::ffi::OsStr::new("tests/elffiles/update_test.elf"),
+            false,
+        )
+        .unwrap();
+        let mut log_msgs = Vec::new();
+        let summary =
+            super::update_addresses(&mut a2l_file, &debug_data, &mut log_msgs, false, false);
+        assert_eq!(summary.axis_pts_dim_changed, 1);
+        let module = &a2l_file.project.module[0];
+        assert_eq!(module.axis_pts[0].max_axis_points, 10);
+        assert_eq!(module.characteristic[0].axis_descr[0].max_axis_points, 10);
+        assert!(log_msgs[0].contains("AXIS_PTS TEST_structarr on line 5 changed from 5 to 10"));
+        assert!(log_msgs[1].contains("in CHARACTERISTIC val_u8 on line 7 changed from 5 to 10"));
+    }
+
+    #[test]
+    fn test_update_instance_pointer_policy() {
+        let debug_data = crate::DebugData::load(
+            std::ffi::OsStr::new("tests/elffiles/update_test.elf"),
+            false,
+        )
+        .unwrap();
+        let update = |policy| {
+            let mut log_msgs = Vec::new();
+            let mut a2l_file =
+                a2lfile::load("tests/update_test1.a2l", None, &mut log_msgs, false).unwrap();
+            let mut log_msgs = Vec::new();
+            let summary = super::update_addresses_with_progress(
+                &mut a2l_file,
+                &debug_data,
+                &mut log_msgs,
+                false,
+                true,
+                policy,
+                &mut |_| {},
+            );
+            (a2l_file, summary, log_msgs)
+        };
+
+        let (a2l_skip, summary_skip, log_msgs) = update(InstancePointerPolicy::Skip);
+        // TEST_structptr_ptr, TEST_structptr_arr, TEST_structptr_arr_ptr and TEST_structarr_ptr_arr
+        assert_eq!(
+            log_msgs
+                .iter()
+                .filter(|msg| msg.contains("nested pointer level"))
+                .count(),
+            4
+        );
+        let instance = a2l_skip.project.module[0]
+            .instance
+            .iter()
+            .find(|inst| inst.name == "TEST_structptr_ptr")
+            .unwrap();
+        assert_eq!(instance.type_ref, "LongPointer_TestStruct");
+        assert_ne!(instance.start_address, 0);
+
+        let (_, summary_typedef, _) = update(InstancePointerPolicy::Typedef);
+        assert_eq!(
+            summary_typedef.instance_updated,
+            summary_skip.instance_updated + 4
+        );
+        let (_, summary_follow, _) = update(InstancePointerPolicy::Follow(1));
+        assert_eq!(
+            summary_follow.instance_updated,
+            summary_typedef.instance_updated
+        );
+
+        assert_eq!(
+            "follow:2".parse::<InstancePointerPolicy>(),
+            Ok(InstancePointerPolicy::Follow(2))
+        );
+        assert!("follow:x".parse::<InstancePointerPolicy>().is_err());
+    }
 }
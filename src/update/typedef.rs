@@ -1,4 +1,5 @@
 use crate::dwarf::{make_simple_unit_name, DebugData, DwarfDataType, TypeInfo};
+use crate::intern::StringInterner;
 use crate::update::enums::{cond_create_enum_conversion, update_enum_compu_methods};
 use crate::update::{
     adjust_limits, get_a2l_datatype, get_fnc_values_memberid, get_inner_type, set_address_type,
@@ -15,6 +16,7 @@ use indexmap::{IndexMap, IndexSet};
 use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::fmt::Write;
+use std::sync::Arc;
 
 type FxIndexMap<K, V> = IndexMap<K, V, FxBuildHasher>;
 
@@ -99,7 +101,7 @@ pub(crate) fn create_new_typedefs<'a>(
     let mut typedef_ref_info: TypedefsRefInfo = HashMap::new();
 
     for (typeinfo, instance_idx) in create_list {
-        let name = module.instance[*instance_idx].name.clone();
+        let name = typedef_names.shared_name(&module.instance[*instance_idx].name);
         typedef_ref_info
             .entry(name)
             .or_default()
@@ -156,7 +158,7 @@ impl<'dbg, 'a2l, 'rl, 'log, 'cm> TypedefUpdater<'dbg, 'a2l, 'rl, 'log, 'cm> {
 
     /// process the TYPEDEFs in self.module
     fn process_typedefs(mut self, preserve_unknown: bool, create_only: bool) {
-        self.typedef_names.structure = HashSet::new();
+        self.typedef_names.structure = StringInterner::new();
 
         self.calc_structure_category();
         self.build_structure_hash();
@@ -310,7 +312,7 @@ impl<'dbg, 'a2l, 'rl, 'log, 'cm> TypedefUpdater<'dbg, 'a2l, 'rl, 'log, 'cm> {
                 // come from an INSTANCE
                 if let Some(instance_typeinfo) = self
                     .typedef_ref_info
-                    .get(&td_struct.name)
+                    .get(td_struct.name.as_str())
                     .and_then(|info_vec| {
                         info_vec
                             .iter()
@@ -360,7 +362,7 @@ impl<'dbg, 'a2l, 'rl, 'log, 'cm> TypedefUpdater<'dbg, 'a2l, 'rl, 'log, 'cm> {
                 }
             } else if let Some(unnamed_typeinfo) = self
                 .typedef_ref_info
-                .get(&td_struct.name)
+                .get(td_struct.name.as_str())
                 .and_then(|info_vec| {
                     info_vec
                         .iter()
@@ -526,13 +528,14 @@ impl<'dbg, 'a2l, 'rl, 'log, 'cm> TypedefUpdater<'dbg, 'a2l, 'rl, 'log, 'cm> {
         component_typeinfo: &'dbg TypeInfo,
     ) {
         self.typedef_ref_info
-            .entry(sc.component_type.clone())
+            .entry(self.typedef_names.shared_name(&sc.component_type))
             .or_default()
             .push((
                 Some(component_typeinfo),
                 TypedefReferrer::StructureComponent(
-                    self.typedef_structs[struct_idx].name.clone(),
-                    sc.component_name.clone(),
+                    self.typedef_names
+                        .shared_name(&self.typedef_structs[struct_idx].name),
+                    Arc::from(sc.component_name.as_str()),
                 ),
             ));
         if let Some((old_typeinfo, old_quality)) = self.typedef_map.swap_remove(&sc.component_type)
@@ -598,7 +601,7 @@ impl<'dbg, 'a2l, 'rl, 'log, 'cm> TypedefUpdater<'dbg, 'a2l, 'rl, 'log, 'cm> {
             // try to ensure the distinct type whose name matches the typedef name is first in the list
             for idx in 1..dtypes.len() {
                 if let Some(name) = dtypes[0].name.as_deref() {
-                    if name == refname {
+                    if name == &**refname {
                         dtypes.swap(0, idx);
                         break;
                     }
@@ -610,14 +613,14 @@ impl<'dbg, 'a2l, 'rl, 'log, 'cm> TypedefUpdater<'dbg, 'a2l, 'rl, 'log, 'cm> {
                 false
             } else if self.typedef_names.structure.contains(refname) {
                 // existing TYPEDEF_STRUCTURE
-                *self.is_calib_struct.get(refname).unwrap_or(&false)
+                *self.is_calib_struct.get(&**refname).unwrap_or(&false)
             } else if self.typedef_names.contains(refname) {
                 // TYPEDEF_AXIS / TYPEDEF_BLOB / TYPEDEF_CHARACTERISTIC
                 true
             } else {
                 // nonexistent TYPEDEF, use the "magic" refname to determine if a TYPEDEF_CHARWYCTERISTIC should be created
                 // '|' is not allowed in names, so this name should only occur when it is used as a flag in the insert code.
-                refname == FLAG_CREATE_CALIB
+                &**refname == FLAG_CREATE_CALIB
             };
 
             for typeinfo in dtypes {
@@ -678,14 +681,14 @@ impl<'dbg, 'a2l, 'rl, 'log, 'cm> TypedefUpdater<'dbg, 'a2l, 'rl, 'log, 'cm> {
         while self.typedef_names.contains(&newname) {
             if is_calib {
                 if is_calibration_typeinfo(typeinfo)
-                    && self.typedef_names.characteristic.contains::<str>(&newname)
+                    && self.typedef_names.characteristic.contains(&newname)
                 {
                     // there is an existing matching TYPEDEF_CHARACTERISTIC called <newname>
                     should_create = false;
                     break;
                 }
             } else if is_measurement_typeinfo(typeinfo, &self.debug_data.types)
-                && self.typedef_names.measurement.contains::<str>(&newname)
+                && self.typedef_names.measurement.contains(&newname)
             {
                 // there is an existing matching TYPEDEF_MEASUREMENT called <newname>
                 should_create = false;
@@ -739,7 +742,7 @@ impl<'dbg, 'a2l, 'rl, 'log, 'cm> TypedefUpdater<'dbg, 'a2l, 'rl, 'log, 'cm> {
             .insert(name.clone());
         self.typedef_map
             .insert(name.clone(), (typeinfo, TypeQuality::Exact));
-        self.typedef_names.characteristic.insert(name.clone());
+        self.typedef_names.characteristic.insert(&name);
 
         self.log_msgs
             .push(format!("creating TYPEDEF_CHARACTERISTIC \"{name}\""));
@@ -792,7 +795,7 @@ impl<'dbg, 'a2l, 'rl, 'log, 'cm> TypedefUpdater<'dbg, 'a2l, 'rl, 'log, 'cm> {
             .insert(name.clone());
         self.typedef_map
             .insert(name.clone(), (typeinfo, TypeQuality::Exact));
-        self.typedef_names.blob.insert(name.clone());
+        self.typedef_names.blob.insert(&name);
 
         self.log_msgs
             .push(format!("creating TYPEDEF_BLOB \"{name}\""));
@@ -818,7 +821,7 @@ impl<'dbg, 'a2l, 'rl, 'log, 'cm> TypedefUpdater<'dbg, 'a2l, 'rl, 'log, 'cm> {
             .insert(name.clone());
         self.typedef_map
             .insert(name.clone(), (typeinfo, TypeQuality::Exact));
-        self.typedef_names.measurement.insert(name.clone());
+        self.typedef_names.measurement.insert(&name);
 
         self.log_msgs
             .push(format!("creating TYPEDEF_MEASUREMENT \"{name}\""));
@@ -852,7 +855,7 @@ impl<'dbg, 'a2l, 'rl, 'log, 'cm> TypedefUpdater<'dbg, 'a2l, 'rl, 'log, 'cm> {
             .insert(name.clone());
         self.typedef_map
             .insert(name.clone(), (typeinfo, TypeQuality::Exact));
-        self.typedef_names.structure.insert(name.clone());
+        self.typedef_names.structure.insert(&name);
         self.is_calib_struct.insert(name.clone(), is_calib);
 
         self.log_msgs
@@ -943,7 +946,7 @@ impl<'dbg, 'a2l, 'rl, 'log, 'cm> TypedefUpdater<'dbg, 'a2l, 'rl, 'log, 'cm> {
                 && newtype.compare(reftype.as_ref().unwrap(), &self.debug_data.types)
             {
                 self.typedef_ref_info
-                    .entry(newname.to_string())
+                    .entry(self.typedef_names.shared_name(newname))
                     .or_default()
                     .push((Some(newtype), referrer.clone()));
 
@@ -952,11 +955,11 @@ impl<'dbg, 'a2l, 'rl, 'log, 'cm> TypedefUpdater<'dbg, 'a2l, 'rl, 'log, 'cm> {
                         self.module.instance[*instance_idx].type_ref = newname.to_string();
                     }
                     TypedefReferrer::StructureComponent(st_name, cmp_name) => {
-                        if let Some(td_struct) = self.typedef_structs.get_mut(st_name) {
+                        if let Some(td_struct) = self.typedef_structs.get_mut(&**st_name) {
                             if let Some(component) = td_struct
                                 .structure_component
                                 .iter_mut()
-                                .find(|cmp| cmp.component_name == *cmp_name)
+                                .find(|cmp| *cmp.component_name == **cmp_name)
                             {
                                 component.component_type = newname.to_string();
                             }
@@ -987,7 +990,7 @@ impl<'dbg, 'a2l, 'rl, 'log, 'cm> TypedefUpdater<'dbg, 'a2l, 'rl, 'log, 'cm> {
                 set_address_type(&mut td_blob.address_type, blob_type);
 
                 // update all instances referring to this blob
-                if let Some(blob_info) = self.typedef_ref_info.get(&td_blob.name).cloned() {
+                if let Some(blob_info) = self.typedef_ref_info.get(td_blob.name.as_str()).cloned() {
                     let name = td_blob.name.clone();
                     self.update_typedef_referrers(&blob_info, blob_type, &name);
                 }
@@ -1015,7 +1018,7 @@ impl<'dbg, 'a2l, 'rl, 'log, 'cm> TypedefUpdater<'dbg, 'a2l, 'rl, 'log, 'cm> {
                 ));
                 self.update_typedef_characteristic(td_char, char_type, &mut enum_convlist);
                 // update all instances referring to this characteristic
-                if let Some(char_info) = self.typedef_ref_info.get(&td_char.name).cloned() {
+                if let Some(char_info) = self.typedef_ref_info.get(td_char.name.as_str()).cloned() {
                     self.update_typedef_referrers(&char_info, char_type, &td_char.name);
                 }
             }
@@ -1044,10 +1047,10 @@ impl<'dbg, 'a2l, 'rl, 'log, 'cm> TypedefUpdater<'dbg, 'a2l, 'rl, 'log, 'cm> {
         if let Some(inner_typeinfo) = get_inner_type(char_type, member_id) {
             if let DwarfDataType::Enum { enumerators, .. } = &inner_typeinfo.datatype {
                 // the values of this struct are of type enum
-                let enum_name = inner_typeinfo
-                    .name
-                    .clone()
-                    .unwrap_or_else(|| format!("{}_compu_method", td_char.name));
+                let enum_name = inner_typeinfo.name.as_deref().map_or_else(
+                    || format!("{}_compu_method", td_char.name),
+                    ToString::to_string,
+                );
                 if td_char.conversion == "NO_COMPU_METHOD" {
                     td_char.conversion = enum_name;
                 }
@@ -1143,7 +1146,7 @@ impl<'dbg, 'a2l, 'rl, 'log, 'cm> TypedefUpdater<'dbg, 'a2l, 'rl, 'log, 'cm> {
 
                 self.update_typedef_measurement(td_meas, meas_type, &mut enum_convlist);
                 // update all instances referring to this characteristic
-                if let Some(meas_info) = self.typedef_ref_info.get(&td_meas.name).cloned() {
+                if let Some(meas_info) = self.typedef_ref_info.get(td_meas.name.as_str()).cloned() {
                     self.update_typedef_referrers(&meas_info, meas_type, &td_meas.name);
                 }
             }
@@ -1167,10 +1170,10 @@ impl<'dbg, 'a2l, 'rl, 'log, 'cm> TypedefUpdater<'dbg, 'a2l, 'rl, 'log, 'cm> {
         set_bitmask(&mut td_meas.bit_mask, meas_type);
         if let DwarfDataType::Enum { enumerators, .. } = &meas_type.datatype {
             if td_meas.conversion == "NO_COMPU_METHOD" {
-                td_meas.conversion = meas_type
-                    .name
-                    .clone()
-                    .unwrap_or_else(|| format!("{}_compu_method", td_meas.name));
+                td_meas.conversion = meas_type.name.as_deref().map_or_else(
+                    || format!("{}_compu_method", td_meas.name),
+                    ToString::to_string,
+                );
             }
             cond_create_enum_conversion(self.module, &td_meas.conversion, enumerators);
             enum_convlist.insert(td_meas.conversion.clone(), meas_type);
@@ -1205,7 +1208,9 @@ impl<'dbg, 'a2l, 'rl, 'log, 'cm> TypedefUpdater<'dbg, 'a2l, 'rl, 'log, 'cm> {
 
                 self.update_typedef_structure(td_struct, struct_type, &mut enum_convlist);
                 // update all instances referring to this structure
-                if let Some(struct_info) = self.typedef_ref_info.get(&td_struct.name).cloned() {
+                if let Some(struct_info) =
+                    self.typedef_ref_info.get(td_struct.name.as_str()).cloned()
+                {
                     self.update_typedef_referrers(&struct_info, struct_type, &td_struct.name);
                 }
             }
@@ -1324,7 +1329,7 @@ impl<'dbg, 'a2l, 'rl, 'log, 'cm> TypedefUpdater<'dbg, 'a2l, 'rl, 'log, 'cm> {
     fn update_typedef_struct_content(
         &mut self,
         td_struct: &mut TypedefStructure,
-        members: &'dbg IndexMap<String, (TypeInfo, u64)>,
+        members: &'dbg IndexMap<Arc<str>, (TypeInfo, u64)>,
         enum_convlist: &mut HashMap<String, &'dbg TypeInfo>,
         is_calib: bool,
     ) {
@@ -1337,7 +1342,7 @@ impl<'dbg, 'a2l, 'rl, 'log, 'cm> TypedefUpdater<'dbg, 'a2l, 'rl, 'log, 'cm> {
             let cur_type = typeinfo_ref.get_reference(&self.debug_data.types);
             let mut sc = if let Some(sc) = structure_components
                 .iter()
-                .find(|sc| &sc.component_name == cur_member_name)
+                .find(|sc| *sc.component_name == **cur_member_name)
             {
                 sc.clone()
             } else {
@@ -1363,16 +1368,17 @@ impl<'dbg, 'a2l, 'rl, 'log, 'cm> TypedefUpdater<'dbg, 'a2l, 'rl, 'log, 'cm> {
                 if !matches!(&final_typeinfo.datatype, DwarfDataType::FuncPtr(_))
                     && (is_calib || !matches!(&final_typeinfo.datatype, DwarfDataType::Other(_)))
                 {
-                    sc.component_name = cur_member_name.clone();
+                    sc.component_name = cur_member_name.to_string();
                     // set ADDRESS_TYPE if cur_member_typeinfo is a pointer, or delete it
                     set_address_type(&mut sc.address_type, cur_type);
                     // update, set or delete MATRIX_DIM
                     set_matrix_dim(&mut sc.matrix_dim, cur_type_nopointer, true);
                     // update or create the SYMBOL_TYPE_LINK of the STRUCTURE_COMPONENT
                     if let Some(symbol_type_link) = &mut sc.symbol_type_link {
-                        symbol_type_link.symbol_type = cur_member_name.clone();
+                        symbol_type_link.symbol_type = cur_member_name.to_string();
                     } else {
-                        sc.symbol_type_link = Some(SymbolTypeLink::new(cur_member_name.clone()));
+                        sc.symbol_type_link =
+                            Some(SymbolTypeLink::new(cur_member_name.to_string()));
                     }
 
                     sc.address_offset = *cur_member_offset as u32;
@@ -1382,13 +1388,13 @@ impl<'dbg, 'a2l, 'rl, 'log, 'cm> TypedefUpdater<'dbg, 'a2l, 'rl, 'log, 'cm> {
                         sc.component_type = typedef_name;
 
                         self.typedef_ref_info
-                            .entry(sc.component_type.clone())
+                            .entry(self.typedef_names.shared_name(&sc.component_type))
                             .or_default()
                             .push((
                                 Some(cur_type_unwrapped),
                                 TypedefReferrer::StructureComponent(
-                                    td_struct.name.clone(),
-                                    sc.component_name.clone(),
+                                    self.typedef_names.shared_name(&td_struct.name),
+                                    Arc::from(sc.component_name.as_str()),
                                 ),
                             ));
                         td_struct.structure_component.push(sc);
@@ -1400,30 +1406,13 @@ impl<'dbg, 'a2l, 'rl, 'log, 'cm> TypedefUpdater<'dbg, 'a2l, 'rl, 'log, 'cm> {
 
     /// update the `SYMBOL_TYPE_LINK` of a `TYPEDEF_STRUCTURE`
     fn update_symbol_type_link(&self, td_struct: &mut TypedefStructure, typeinfo: &TypeInfo) {
-        if let Some(name) = &typeinfo.name {
-            // the type has a name
+        if let Some(symbol_type) = make_symbol_type_string(self.debug_data, typeinfo) {
             if let Some(stl) = &mut td_struct.symbol_type_link {
                 // a SYMBOL_TYPE_LINK already exists and can be updated
-                if is_type_discriminant_needed(self.debug_data, name)
-                    && self.debug_data.unit_names[typeinfo.unit_idx].is_some()
-                {
-                    // Vector generates 'SYMBOL_TYPE_LINK "SomeSymbol{CompileUnit:Some_File_c}{Namespace:Global}"'
-                    // This can remove some ambiguity when multiple files or namespaces define SomeSymbol, but
-                    // it's not perfect, since the path is stripped rom the file name.
-                    if let Some(simple_unit_name) =
-                        make_simple_unit_name(self.debug_data, typeinfo.unit_idx)
-                    {
-                        // this could fail with advanced DWARF encoding, i.e. when partial units are in use
-                        stl.symbol_type =
-                            format!("{name}{{CompileUnit:{simple_unit_name}}}{{Namespace:Global}}");
-                    }
-                } else {
-                    // SYMBOL_TYPE_LINK contains the bare symbol name, which can be directly replaced
-                    stl.symbol_type = name.clone();
-                }
+                stl.symbol_type = symbol_type;
             } else {
                 // SYMBOL_TYPE_LINK did not exist and needs to be created
-                td_struct.symbol_type_link = Some(SymbolTypeLink::new(name.clone()));
+                td_struct.symbol_type_link = Some(SymbolTypeLink::new(symbol_type));
             }
         } else {
             // the type is unnamed - possible, but very unlikely here
@@ -1441,20 +1430,23 @@ impl<'dbg, 'a2l, 'rl, 'log, 'cm> TypedefUpdater<'dbg, 'a2l, 'rl, 'log, 'cm> {
             updated = false;
             let mut idx = 0;
             while idx < self.typedef_structs.len() {
-                let opt_info_vec = self.typedef_ref_info.get(&self.typedef_structs[idx].name);
+                let opt_info_vec = self
+                    .typedef_ref_info
+                    .get(self.typedef_structs[idx].name.as_str());
                 if opt_info_vec.is_none() || opt_info_vec.unwrap().is_empty() {
                     for sc in &self.typedef_structs[idx].structure_component {
-                        if let Some(target_info) = self.typedef_ref_info.get_mut(&sc.component_type)
+                        if let Some(target_info) =
+                            self.typedef_ref_info.get_mut(sc.component_type.as_str())
                         {
                             target_info.retain(|(_, referrer)| {
                                 if let TypedefReferrer::StructureComponent(s, _) = referrer {
-                                    *s != self.typedef_structs[idx].name
+                                    **s != *self.typedef_structs[idx].name
                                 } else {
                                     true
                                 }
                             });
                             if target_info.is_empty() {
-                                self.typedef_ref_info.remove(&sc.component_type);
+                                self.typedef_ref_info.remove(sc.component_type.as_str());
                             }
                         }
                     }
@@ -1474,7 +1466,7 @@ impl<'dbg, 'a2l, 'rl, 'log, 'cm> TypedefUpdater<'dbg, 'a2l, 'rl, 'log, 'cm> {
         while idx < self.module.typedef_characteristic.len() {
             let opt_info_vec = self
                 .typedef_ref_info
-                .get(&self.module.typedef_characteristic[idx].name);
+                .get(self.module.typedef_characteristic[idx].name.as_str());
             if opt_info_vec.is_none() || opt_info_vec.unwrap().is_empty() {
                 self.log_msgs.push(format!(
                     "removing unused TYPEDEF_CHARACTERISTIC {}",
@@ -1491,7 +1483,7 @@ impl<'dbg, 'a2l, 'rl, 'log, 'cm> TypedefUpdater<'dbg, 'a2l, 'rl, 'log, 'cm> {
         while idx < self.module.typedef_measurement.len() {
             let opt_info_vec = self
                 .typedef_ref_info
-                .get(&self.module.typedef_measurement[idx].name);
+                .get(self.module.typedef_measurement[idx].name.as_str());
             if opt_info_vec.is_none() || opt_info_vec.unwrap().is_empty() {
                 self.log_msgs.push(format!(
                     "removing unused TYPEDEF_MEASUREMENT {}",
@@ -1505,6 +1497,30 @@ impl<'dbg, 'a2l, 'rl, 'log, 'cm> TypedefUpdater<'dbg, 'a2l, 'rl, 'log, 'cm> {
     }
 }
 
+/// make the content of a `SYMBOL_TYPE_LINK` for a named type
+///
+/// If several different types share the name, the compile unit is added to the name, so that
+/// the link can be resolved again by `get_typeinfo_from_symbol_link`
+pub(crate) fn make_symbol_type_string(
+    debug_data: &DebugData,
+    typeinfo: &TypeInfo,
+) -> Option<String> {
+    let name = typeinfo.name.as_ref()?;
+    if is_type_discriminant_needed(debug_data, name) {
+        // Vector generates 'SYMBOL_TYPE_LINK "SomeSymbol{CompileUnit:Some_File_c}{Namespace:Global}"'
+        // This can remove some ambiguity when multiple files or namespaces define SomeSymbol, but
+        // it's not perfect, since the path is stripped rom the file name.
+        // This could fail with advanced DWARF encoding, i.e. when partial units are in use
+        if let Some(simple_unit_name) = make_simple_unit_name(debug_data, typeinfo.unit_idx) {
+            return Some(format!(
+                "{name}{{CompileUnit:{simple_unit_name}}}{{Namespace:Global}}"
+            ));
+        }
+    }
+    // SYMBOL_TYPE_LINK contains the bare type name
+    Some(name.to_string())
+}
+
 /// take the type name from a `SYMBOL_TYPE_LINK` and try to find a matching type in the `debug_data`
 fn get_typeinfo_from_symbol_link<'dbg>(
     debug_data: &'dbg DebugData,
@@ -1561,11 +1577,11 @@ fn get_typeinfo_from_symbol_link<'dbg>(
 fn get_structure_component_typeinfo<'dbg>(
     debug_data: &'dbg DebugData,
     structure_component: &StructureComponent,
-    members: &'dbg IndexMap<String, (TypeInfo, u64)>,
+    members: &'dbg IndexMap<Arc<str>, (TypeInfo, u64)>,
 ) -> Option<&'dbg TypeInfo> {
     let symtypelink = structure_component.symbol_type_link.as_ref()?;
     // get the member type info - this is a TypeRef when referring to another struct / etc.
-    let typeinfo_ref = &members.get(&symtypelink.symbol_type)?.0;
+    let typeinfo_ref = &members.get(symtypelink.symbol_type.as_str())?.0;
     // dereference the TypeRef (if any)
     let full_typeinfo = typeinfo_ref.get_reference(&debug_data.types);
     // follow the pointer (if any)
@@ -1646,8 +1662,10 @@ fn is_measurement_typeinfo(typeinfo: &TypeInfo, types: &HashMap<usize, TypeInfo>
 /// if there are multiple types with the same name, do we need to use the Vector naming
 /// extension to distingush between them?
 /// A qualifier {`CompileUnit`:...} is not needed if all of the types are actually identical.
-fn is_type_discriminant_needed(debug_data: &DebugData, name: &String) -> bool {
-    let type_offsets = debug_data.typenames.get(name).unwrap();
+fn is_type_discriminant_needed(debug_data: &DebugData, name: &str) -> bool {
+    let Some(type_offsets) = debug_data.typenames.get(name) else {
+        return false;
+    };
     if type_offsets.len() < 2 {
         return false;
     }
@@ -1711,7 +1729,7 @@ fn make_typedef_name(debug_data: &DebugData, typeinfo: &TypeInfo, is_calib: bool
             let basename: Cow<str> = if let Some(pt_type) = debug_data.types.get(&pt_dbg_offset.0) {
                 make_typedef_name(debug_data, pt_type, is_calib).into()
             } else if let Some(pt_name) = &typeinfo.name {
-                pt_name.into()
+                (**pt_name).into()
             } else {
                 "unknown".into()
             };
@@ -1735,7 +1753,7 @@ fn make_typedef_name(debug_data: &DebugData, typeinfo: &TypeInfo, is_calib: bool
             .to_string(),
         DwarfDataType::Class { .. } => {
             // there is no such thing as an unnamed class
-            typeinfo.name.clone().unwrap()
+            typeinfo.name.as_deref().unwrap().to_string()
         }
         DwarfDataType::Union { .. } => typeinfo
             .name
@@ -1826,9 +1844,13 @@ fn fully_unwrap_typeinfo<'dbg>(
 #[cfg(test)]
 mod test {
     use super::{update_module_typedefs, TypedefUpdater};
+    use crate::intern::StringInterner;
     use crate::{
         dwarf::{DebugData, TypeInfo},
-        update::{get_symbol_info, RecordLayoutInfo, TypedefNames, TypedefReferrer, UpdateInfo},
+        update::{
+            get_symbol_info, InstancePointerPolicy, RecordLayoutInfo, TypedefNames,
+            TypedefReferrer, UpdateInfo,
+        },
         A2lVersion,
     };
     use a2lfile::A2lFile;
@@ -1865,7 +1887,7 @@ mod test {
             &dummy_cm_index,
         );
 
-        tdu.typedef_names.structure = HashSet::new();
+        tdu.typedef_names.structure = StringInterner::new();
         tdu.calc_structure_category();
 
         assert!(!(*tdu.is_calib_struct.get("StructA").unwrap()));
@@ -1901,7 +1923,7 @@ mod test {
             &dummy_cm_index,
         );
 
-        tdu.typedef_names.structure = HashSet::new();
+        tdu.typedef_names.structure = StringInterner::new();
         tdu.build_structure_hash();
 
         assert!(tdu.typedef_structs.contains_key("StructA"));
@@ -1939,7 +1961,7 @@ mod test {
             &dummy_cm_index,
         );
 
-        tdu.typedef_names.structure = HashSet::new();
+        tdu.typedef_names.structure = StringInterner::new();
         tdu.calc_structure_category();
         tdu.build_structure_hash();
 
@@ -1989,7 +2011,7 @@ mod test {
         // the typedef_ref_info for INSTANCEs is normally collected by the INSTANCE update function
         let mut typedef_ref_info: HashMap<_, Vec<_>> = HashMap::new();
         typedef_ref_info
-            .entry("StructB".to_string())
+            .entry("StructB".into())
             .or_default()
             .push((Some(structb_typeinfo), TypedefReferrer::Instance(0)));
 
@@ -2005,7 +2027,7 @@ mod test {
             &dummy_cm_index,
         );
 
-        tdu.typedef_names.structure = HashSet::new();
+        tdu.typedef_names.structure = StringInterner::new();
         tdu.calc_structure_category();
         tdu.build_structure_hash();
 
@@ -2039,7 +2061,7 @@ mod test {
         );
         let mut enum_convlist = HashMap::<String, &TypeInfo>::new();
 
-        tdu.typedef_names.structure = HashSet::new();
+        tdu.typedef_names.structure = StringInterner::new();
         tdu.calc_structure_category();
         tdu.build_structure_hash();
         tdu.process_structure_components(false);
@@ -2114,7 +2136,7 @@ mod test {
         );
         let mut enum_convlist = HashMap::<String, &TypeInfo>::new();
 
-        tdu.typedef_names.structure = HashSet::new();
+        tdu.typedef_names.structure = StringInterner::new();
         tdu.calc_structure_category();
         tdu.build_structure_hash();
         tdu.process_structure_components(false);
@@ -2140,7 +2162,7 @@ mod test {
         let (mut a2l, debug_data, names, reclayout) =
             test_setup("tests/update_test3.a2l", "tests/elffiles/update_test.elf");
 
-        let mut typedef_ref_info: HashMap<String, Vec<_>> = HashMap::new();
+        let mut typedef_ref_info: HashMap<_, Vec<_>> = HashMap::new();
         for (idx, inst) in a2l.project.module[0].instance.iter().enumerate() {
             if let Ok(sym_info) =
                 get_symbol_info(&inst.name, &inst.symbol_link, &inst.if_data, &debug_data)
@@ -2151,7 +2173,7 @@ mod test {
                     .map_or(sym_info.typeinfo, |(_, t)| t);
                 let typeinfo = typeinfo.get_arraytype().unwrap_or(typeinfo);
                 typedef_ref_info
-                    .entry(inst.type_ref.clone())
+                    .entry(names.shared_name(&inst.type_ref))
                     .or_default()
                     .push((Some(typeinfo), TypedefReferrer::Instance(idx)));
             }
@@ -2164,8 +2186,11 @@ mod test {
             debug_data: &debug_data,
             log_msgs: &mut log_msgs,
             preserve_unknown: false,
+            pointer_policy: InstancePointerPolicy::default(),
             version,
             reclayout_info: reclayout,
+            unchanged: &HashSet::new(),
+            unchanged_count: 0,
         };
         update_module_typedefs(&mut info, typedef_ref_info, names, &HashMap::new());
 
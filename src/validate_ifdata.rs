@@ -0,0 +1,700 @@
+//! Strict validation of IF_DATA blocks against the applicable A2ML specification
+//!
+//! a2lfile keeps IF_DATA that does not match any A2ML specification as generic data, but it does
+//! not report why the data did not match. Here the A2ML is parsed again, and the content of each
+//! such IF_DATA is checked element by element, in the same way that the a2lfile parser reads it.
+
+use crate::a2ml::tokenize;
+use a2lfile::{A2lFile, A2lObject, GenericIfData, IfData, Module};
+use std::collections::HashMap;
+
+// the type tree of an A2ML specification
+#[derive(Debug, Clone)]
+enum A2mlType {
+    None,
+    Integer(&'static str, i128, i128),
+    Float(&'static str),
+    String,
+    Array(Box<A2mlType>, usize),
+    Enum(Vec<String>),
+    Struct(Vec<A2mlType>),
+    Sequence(Box<A2mlType>),
+    TaggedStruct(Vec<A2mlTaggedItem>),
+    TaggedUnion(Vec<A2mlTaggedItem>),
+}
+
+#[derive(Debug, Clone)]
+struct A2mlTaggedItem {
+    tag: String,
+    is_block: bool,
+    item: A2mlType,
+}
+
+// the content of an IF_DATA block, as a flat list of tokens
+#[derive(Debug, Clone, PartialEq)]
+enum IfDataToken {
+    Begin,
+    End,
+    Identifier(String),
+    String(String),
+    Integer(i64),
+    Float(f64),
+}
+
+// a violation of the specification, found at the token with index pos
+#[derive(Debug)]
+struct Violation {
+    pos: usize,
+    line: u32,
+    path: String,
+    message: String,
+}
+
+/// Validate all IF_DATA blocks that could not be parsed according to the A2ML specification.
+///
+/// The specification that applies to a block is selected by its tag: the additional specification
+/// is used first, then the A2ML of the module and finally the built-in specification.
+/// For each invalid block a message reports the element that violates the specification,
+/// together with its line number and the tag path that leads to it.
+///
+/// # Errors
+///
+/// Returns an error message if the additional A2ML specification cannot be parsed
+pub fn validate_ifdata(
+    a2l_file: &A2lFile,
+    additional_a2ml: Option<&str>,
+    log_msgs: &mut Vec<String>,
+) -> Result<(), String> {
+    let builtin_text = if let Some(additional_a2ml) = additional_a2ml {
+        crate::a2ml::merge_a2ml_spec(crate::ifdata::A2MLVECTOR_TEXT, additional_a2ml)?
+    } else {
+        crate::ifdata::A2MLVECTOR_TEXT.to_string()
+    };
+    let builtin_spec = parse_a2ml(&builtin_text)?;
+
+    for module in &a2l_file.project.module {
+        let module_spec = match module.a2ml.as_ref().map(|a2ml| parse_a2ml(&a2ml.a2ml_text)) {
+            Some(Ok(spec)) => Some(spec),
+            Some(Err(err)) => {
+                log_msgs.push(format!(
+                    "In MODULE {}: the A2ML could not be parsed: {err}",
+                    module.name
+                ));
+                None
+            }
+            None => None,
+        };
+        let mut specs = vec![&builtin_spec];
+        if let Some(module_spec) = &module_spec {
+            // the A2ML of the module takes precedence over the built-in A2ML, but not over an additional A2ML
+            if additional_a2ml.is_some() {
+                specs.push(module_spec);
+            } else {
+                specs.insert(0, module_spec);
+            }
+        }
+
+        for (owner, ifdata_list) in ifdata_owners(module) {
+            for ifdata in ifdata_list.iter().filter(|ifdata| !ifdata.ifdata_valid) {
+                if let Some(msg) = validate_block(ifdata, &owner, &specs) {
+                    log_msgs.push(msg);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// all IF_DATA lists of a module, together with a description of the element that contains them
+pub(crate) fn ifdata_owners(module: &Module) -> Vec<(String, &Vec<IfData>)> {
+    let mut ifdata_lists = vec![(format!("MODULE {}", module.name), &module.if_data)];
+    if let Some(mod_par) = &module.mod_par {
+        for memory_layout in &mod_par.memory_layout {
+            ifdata_lists.push(("MEMORY_LAYOUT".to_string(), &memory_layout.if_data));
+        }
+        for memory_segment in &mod_par.memory_segment {
+            let owner = format!("MEMORY_SEGMENT {}", memory_segment.name);
+            ifdata_lists.push((owner, &memory_segment.if_data));
+        }
+    }
+    for axis_pts in &module.axis_pts {
+        ifdata_lists.push((format!("AXIS_PTS {}", axis_pts.name), &axis_pts.if_data));
+    }
+    for blob in &module.blob {
+        ifdata_lists.push((format!("BLOB {}", blob.name), &blob.if_data));
+    }
+    for characteristic in &module.characteristic {
+        let owner = format!("CHARACTERISTIC {}", characteristic.name);
+        ifdata_lists.push((owner, &characteristic.if_data));
+    }
+    for frame in &module.frame {
+        ifdata_lists.push((format!("FRAME {}", frame.name), &frame.if_data));
+    }
+    for function in &module.function {
+        ifdata_lists.push((format!("FUNCTION {}", function.name), &function.if_data));
+    }
+    for group in &module.group {
+        ifdata_lists.push((format!("GROUP {}", group.name), &group.if_data));
+    }
+    for instance in &module.instance {
+        ifdata_lists.push((format!("INSTANCE {}", instance.name), &instance.if_data));
+    }
+    for measurement in &module.measurement {
+        let owner = format!("MEASUREMENT {}", measurement.name);
+        ifdata_lists.push((owner, &measurement.if_data));
+    }
+    ifdata_lists
+}
+
+// check one IF_DATA block that could not be parsed, and describe the violation
+fn validate_block(ifdata: &IfData, owner: &str, specs: &[&A2mlType]) -> Option<String> {
+    let ifdata_items = ifdata.ifdata_items.as_ref()?;
+    let line = ifdata.get_line();
+    let mut tokens = Vec::new();
+    let mut token_line = line;
+    flatten_ifdata(ifdata_items, &mut token_line, &mut tokens);
+
+    let tag = crate::remove_ifdata::ifdata_tag(ifdata).unwrap_or("");
+    let Some(spec) = specs.iter().find(|spec| {
+        matches!(spec, A2mlType::TaggedUnion(items) if items.iter().any(|item| item.tag == tag))
+    }) else {
+        return Some(format!(
+            "In IF_DATA {tag} of {owner} on line {line}: no A2ML specification defines the tag {tag}"
+        ));
+    };
+
+    let mut validator = Validator {
+        tokens: &tokens,
+        pos: 0,
+        unknown_tag: None,
+        sequence_error: None,
+    };
+    match validator.validate(spec) {
+        Ok(()) => Some(format!(
+            "In IF_DATA {tag} of {owner} on line {line}: the content does not match the A2ML specification"
+        )),
+        Err(violation) => Some(format!(
+            "In IF_DATA {tag} of {owner} on line {}: {}: {}",
+            violation.line, violation.path, violation.message
+        )),
+    }
+}
+
+// IF_DATA that did not match any specification is stored as a generic tree of identifiers, strings,
+// numbers and tagged items. Turning it back into a list of tokens allows it to be parsed again.
+// The line numbers of the items are stored as offsets relative to the previous token.
+fn flatten_ifdata(data: &GenericIfData, line: &mut u32, tokens: &mut Vec<(IfDataToken, u32)>) {
+    match data {
+        GenericIfData::Block { items, .. }
+        | GenericIfData::Struct(_, _, items)
+        | GenericIfData::Sequence(items)
+        | GenericIfData::Array(items) => {
+            for item in items {
+                flatten_ifdata(item, line, tokens);
+            }
+        }
+        GenericIfData::TaggedStruct(taggeditems) | GenericIfData::TaggedUnion(taggeditems) => {
+            // the items are stored in a HashMap; the uid restores the original order
+            let mut sorted: Vec<_> = taggeditems.values().flatten().collect();
+            sorted.sort_by_key(|item| item.uid);
+            for item in sorted {
+                *line = item.line;
+                if item.is_block {
+                    tokens.push((IfDataToken::Begin, *line));
+                }
+                tokens.push((IfDataToken::Identifier(item.tag.clone()), *line));
+                flatten_ifdata(&item.data, line, tokens);
+                if item.is_block {
+                    *line += item.end_offset;
+                    tokens.push((IfDataToken::End, *line));
+                    tokens.push((IfDataToken::Identifier(item.tag.clone()), *line));
+                }
+            }
+        }
+        GenericIfData::EnumItem(offset, ident) => {
+            *line += offset;
+            tokens.push((IfDataToken::Identifier(ident.clone()), *line));
+        }
+        GenericIfData::String(offset, text) => {
+            *line += offset;
+            tokens.push((IfDataToken::String(text.clone()), *line));
+        }
+        GenericIfData::Long(offset, (value, is_hex)) => {
+            *line += offset;
+            // hex values are read as u32 and stored as i32
+            let value = if *is_hex {
+                i64::from(*value as u32)
+            } else {
+                i64::from(*value)
+            };
+            tokens.push((IfDataToken::Integer(value), *line));
+        }
+        GenericIfData::Float(offset, value) => {
+            *line += offset;
+            tokens.push((IfDataToken::Float(f64::from(*value)), *line));
+        }
+        // the generic parser does not create any other items
+        _ => {}
+    }
+}
+
+// follows the rules of the a2lfile parser: tagged items and sequences are read greedily,
+// and an error inside of a tagged item is final
+struct Validator<'a> {
+    tokens: &'a [(IfDataToken, u32)],
+    pos: usize,
+    // position and valid tags of the last taggedstruct or taggedunion that stopped at an unknown tag
+    unknown_tag: Option<(usize, Vec<String>)>,
+    // the furthest error that ended a sequence
+    sequence_error: Option<Violation>,
+}
+
+impl Validator<'_> {
+    fn validate(&mut self, spec: &A2mlType) -> Result<(), Violation> {
+        let result = self.item(spec, "IF_DATA").and_then(|()| {
+            if self.pos < self.tokens.len() {
+                Err(self.violation("IF_DATA", "/end IF_DATA"))
+            } else {
+                Ok(())
+            }
+        });
+        // an error that ended a sequence is reported instead if it was found further into the data
+        match (result, self.sequence_error.take()) {
+            (Err(violation), Some(seq_violation)) if seq_violation.pos > violation.pos => {
+                Err(seq_violation)
+            }
+            (result, _) => result,
+        }
+    }
+
+    fn item(&mut self, spec: &A2mlType, path: &str) -> Result<(), Violation> {
+        match spec {
+            A2mlType::None => {}
+            A2mlType::Integer(name, min, max) => {
+                let value = match self.tokens.get(self.pos) {
+                    Some((IfDataToken::Integer(value), _)) => Some(i128::from(*value)),
+                    // integers that don't fit into an i32 are stored as float values by the generic parser
+                    Some((IfDataToken::Float(value), _)) if value.fract() == 0.0 => {
+                        Some(*value as i128)
+                    }
+                    _ => None,
+                };
+                match value {
+                    Some(value) if (*min..=*max).contains(&value) => self.pos += 1,
+                    Some(_) => {
+                        return Err(
+                            self.violation(path, &format!("{name} in the range {min} to {max}"))
+                        )
+                    }
+                    None => return Err(self.violation(path, name)),
+                }
+            }
+            A2mlType::Float(name) => match self.tokens.get(self.pos) {
+                Some((IfDataToken::Integer(_) | IfDataToken::Float(_), _)) => self.pos += 1,
+                _ => return Err(self.violation(path, name)),
+            },
+            A2mlType::String => match self.tokens.get(self.pos) {
+                Some((IfDataToken::String(_), _)) => self.pos += 1,
+                _ => return Err(self.violation(path, "a string")),
+            },
+            A2mlType::Array(element, dim) => {
+                for _ in 0..*dim {
+                    self.item(element, path)?;
+                }
+            }
+            A2mlType::Enum(values) => match self.tokens.get(self.pos) {
+                Some((IfDataToken::Identifier(ident), _)) if values.contains(ident) => {
+                    self.pos += 1;
+                }
+                _ => {
+                    let expected = format!("one of the enum values {}", values.join(", "));
+                    return Err(self.violation(path, &expected));
+                }
+            },
+            A2mlType::Struct(members) => {
+                for member in members {
+                    self.item(member, path)?;
+                }
+            }
+            A2mlType::Sequence(element) => loop {
+                let checkpoint = self.pos;
+                if let Err(violation) = self.item(element, path) {
+                    self.pos = checkpoint;
+                    if self
+                        .sequence_error
+                        .as_ref()
+                        .is_none_or(|seq_violation| violation.pos > seq_violation.pos)
+                    {
+                        self.sequence_error = Some(violation);
+                    }
+                    break;
+                }
+                if self.pos == checkpoint {
+                    break;
+                }
+            },
+            A2mlType::TaggedStruct(items) => while self.tagged_item(items, path)? {},
+            A2mlType::TaggedUnion(items) => {
+                self.tagged_item(items, path)?;
+            }
+        }
+        Ok(())
+    }
+
+    // read one tagged item, if the next tokens are a tag that is valid here
+    fn tagged_item(&mut self, items: &[A2mlTaggedItem], path: &str) -> Result<bool, Violation> {
+        let (is_block, tag_pos) = match self.tokens.get(self.pos) {
+            Some((IfDataToken::Begin, _)) => (true, self.pos + 1),
+            _ => (false, self.pos),
+        };
+        let Some((IfDataToken::Identifier(tag), _)) = self.tokens.get(tag_pos) else {
+            return Ok(false);
+        };
+        let Some(item) = items
+            .iter()
+            .find(|item| item.tag == *tag && item.is_block == is_block)
+        else {
+            let tags = items
+                .iter()
+                .map(|item| {
+                    if item.is_block {
+                        format!("/begin {}", item.tag)
+                    } else {
+                        item.tag.clone()
+                    }
+                })
+                .collect();
+            self.unknown_tag = Some((self.pos, tags));
+            return Ok(false);
+        };
+
+        self.pos = tag_pos + 1;
+        let path = format!("{path}/{tag}");
+        self.item(&item.item, &path)?;
+        if is_block {
+            if self.tokens.get(self.pos).map(|(token, _)| token) != Some(&IfDataToken::End) {
+                return Err(self.violation(&path, &format!("/end {tag}")));
+            }
+            self.pos += 2;
+        }
+        Ok(true)
+    }
+
+    fn violation(&self, path: &str, expected: &str) -> Violation {
+        let (found, line) = match self.tokens.get(self.pos) {
+            Some((token, line)) => (describe_token(token), *line),
+            None => (
+                "/end IF_DATA".to_string(),
+                self.tokens.last().map_or(0, |(_, line)| *line),
+            ),
+        };
+        let mut message = format!("expected {expected}, found {found}");
+        if let Some((_, tags)) = self
+            .unknown_tag
+            .as_ref()
+            .filter(|(tag_pos, _)| *tag_pos == self.pos)
+        {
+            message = format!(
+                "{message}, which is not one of the valid tags {}",
+                tags.join(", ")
+            );
+        }
+        Violation {
+            pos: self.pos,
+            line,
+            path: path.to_string(),
+            message,
+        }
+    }
+}
+
+fn describe_token(token: &IfDataToken) -> String {
+    match token {
+        IfDataToken::Begin => "/begin".to_string(),
+        IfDataToken::End => "/end".to_string(),
+        IfDataToken::Identifier(ident) => format!("identifier {ident}"),
+        IfDataToken::String(text) => format!("string \"{text}\""),
+        IfDataToken::Integer(value) => format!("number {value}"),
+        IfDataToken::Float(value) => format!("number {value}"),
+    }
+}
+
+// parse an A2ML specification and return the type of the block "IF_DATA"
+fn parse_a2ml(text: &str) -> Result<A2mlType, String> {
+    let tokens: Vec<&str> = tokenize(text).into_iter().map(|(_, tok)| tok).collect();
+    let mut parser = A2mlParser {
+        tokens: &tokens,
+        pos: 0,
+        types: HashMap::new(),
+    };
+    let mut ifdata_spec = None;
+    while parser.pos < tokens.len() {
+        if parser.peek() == "block" {
+            parser.pos += 1;
+            let tag = parser.tag()?;
+            let spec = parser.tagged_def()?;
+            if tag == "IF_DATA" {
+                ifdata_spec = Some(spec);
+            }
+        } else {
+            parser.member()?;
+        }
+        parser.expect(";")?;
+    }
+    ifdata_spec.ok_or_else(|| "the A2ML does not contain a block \"IF_DATA\"".to_string())
+}
+
+struct A2mlParser<'a> {
+    tokens: &'a [&'a str],
+    pos: usize,
+    // named enums, structs, taggedstructs and taggedunions, e.g. "struct Vendor_Info"
+    types: HashMap<String, A2mlType>,
+}
+
+impl A2mlParser<'_> {
+    fn peek(&self) -> &str {
+        self.tokens.get(self.pos).copied().unwrap_or("")
+    }
+
+    fn next(&mut self) -> Result<&str, String> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .copied()
+            .ok_or_else(|| "unexpected end of the A2ML".to_string())?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        let token = self.next()?;
+        if token == expected {
+            Ok(())
+        } else {
+            Err(format!(
+                "expected \"{expected}\" in the A2ML, found \"{token}\""
+            ))
+        }
+    }
+
+    fn tag(&mut self) -> Result<String, String> {
+        let token = self.next()?;
+        token
+            .strip_prefix('"')
+            .and_then(|tag| tag.strip_suffix('"'))
+            .map(ToString::to_string)
+            .ok_or_else(|| format!("expected a tag in the A2ML, found \"{token}\""))
+    }
+
+    fn constant(&mut self) -> Result<i64, String> {
+        let negative = self.peek() == "-";
+        if negative {
+            self.pos += 1;
+        }
+        let token = self.next()?;
+        let value = if let Some(hex) = token.strip_prefix("0x").or(token.strip_prefix("0X")) {
+            i64::from_str_radix(hex, 16).ok()
+        } else {
+            token.parse().ok()
+        };
+        let value =
+            value.ok_or_else(|| format!("expected a number in the A2ML, found \"{token}\""))?;
+        Ok(if negative { -value } else { value })
+    }
+
+    // member = type_name [ array_specifier ]
+    fn member(&mut self) -> Result<A2mlType, String> {
+        let mut spec = self.type_name()?;
+        while self.peek() == "[" {
+            self.pos += 1;
+            let dim = usize::try_from(self.constant()?).map_err(|err| err.to_string())?;
+            self.expect("]")?;
+            // arrays of char are strings
+            spec = if matches!(spec, A2mlType::Integer("char", ..)) {
+                A2mlType::String
+            } else {
+                A2mlType::Array(Box::new(spec), dim)
+            };
+        }
+        Ok(spec)
+    }
+
+    // taggedstruct_definition = tag [ member ] | tag "(" member ")*"
+    fn tagged_def(&mut self) -> Result<A2mlType, String> {
+        match self.peek() {
+            ";" | ")" => Ok(A2mlType::None),
+            "(" => {
+                self.pos += 1;
+                let member = self.member()?;
+                self.expect(")")?;
+                self.expect("*")?;
+                Ok(A2mlType::Sequence(Box::new(member)))
+            }
+            _ => self.member(),
+        }
+    }
+
+    fn type_name(&mut self) -> Result<A2mlType, String> {
+        let keyword = self.next()?.to_string();
+        let spec = match keyword.as_str() {
+            "char" => A2mlType::Integer("char", -128, 127),
+            "int" => A2mlType::Integer("int", -32768, 32767),
+            "long" => A2mlType::Integer("long", i32::MIN.into(), i32::MAX.into()),
+            "int64" => A2mlType::Integer("int64", i64::MIN.into(), i64::MAX.into()),
+            "uchar" => A2mlType::Integer("uchar", 0, 255),
+            "uint" => A2mlType::Integer("uint", 0, 65535),
+            "ulong" => A2mlType::Integer("ulong", 0, u32::MAX.into()),
+            "uint64" => A2mlType::Integer("uint64", 0, u64::MAX.into()),
+            "float" => A2mlType::Float("float"),
+            "double" => A2mlType::Float("double"),
+            "enum" | "struct" | "taggedstruct" | "taggedunion" => {
+                let name = match self.peek() {
+                    "{" => None,
+                    _ => Some(format!("{keyword} {}", self.next()?)),
+                };
+                if self.peek() == "{" {
+                    self.pos += 1;
+                    let spec = self.type_body(&keyword)?;
+                    if let Some(name) = name {
+                        self.types.insert(name, spec.clone());
+                    }
+                    spec
+                } else {
+                    // reference to a previously defined type
+                    let name = name.unwrap_or_default();
+                    self.types
+                        .get(&name)
+                        .cloned()
+                        .ok_or_else(|| format!("{name} was referenced but not defined"))?
+                }
+            }
+            _ => return Err(format!("unexpected \"{keyword}\" in the A2ML")),
+        };
+        Ok(spec)
+    }
+
+    // parse the content of the braces of an enum, struct, taggedstruct or taggedunion
+    fn type_body(&mut self, keyword: &str) -> Result<A2mlType, String> {
+        let spec = match keyword {
+            "enum" => {
+                let mut values = Vec::new();
+                loop {
+                    values.push(self.tag()?);
+                    if self.peek() == "=" {
+                        self.pos += 1;
+                        self.constant()?;
+                    }
+                    if self.next()? == "}" {
+                        break;
+                    }
+                }
+                return Ok(A2mlType::Enum(values));
+            }
+            "struct" => {
+                let mut members = Vec::new();
+                while self.peek() != "}" {
+                    members.push(self.member()?);
+                    self.expect(";")?;
+                }
+                A2mlType::Struct(members)
+            }
+            _ => {
+                let mut items = Vec::new();
+                while self.peek() != "}" {
+                    let repeat = self.peek() == "(";
+                    if repeat {
+                        self.pos += 1;
+                    }
+                    let is_block = self.peek() == "block";
+                    if is_block {
+                        self.pos += 1;
+                    }
+                    let tag = self.tag()?;
+                    let item = self.tagged_def()?;
+                    if repeat {
+                        self.expect(")")?;
+                        self.expect("*")?;
+                    }
+                    self.expect(";")?;
+                    items.push(A2mlTaggedItem {
+                        tag,
+                        is_block,
+                        item,
+                    });
+                }
+                if keyword == "taggedstruct" {
+                    A2mlType::TaggedStruct(items)
+                } else {
+                    A2mlType::TaggedUnion(items)
+                }
+            }
+        };
+        self.expect("}")?;
+        Ok(spec)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_validate_ifdata() {
+        let a2l_text = r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin A2ML
+                    struct Vendor_Info {
+                        uint;
+                        enum { "ON" = 1, "OFF" = 0 };
+                    };
+                    block "IF_DATA" taggedunion {
+                        "VENDOR_EXT" taggedstruct {
+                            block "INFO" struct Vendor_Info;
+                            ("CHANNEL" char[10])*;
+                        };
+                    };
+                /end A2ML
+                /begin IF_DATA VENDOR_EXT
+                    CHANNEL "a"
+                    /begin INFO 1 ON /end INFO
+                /end IF_DATA
+                /begin IF_DATA VENDOR_EXT
+                    CHANNEL "a"
+                    /begin INFO 70000 ON /end INFO
+                /end IF_DATA
+                /begin IF_DATA VENDOR_EXT
+                    /begin INFO 1
+                        MAYBE
+                    /end INFO
+                /end IF_DATA
+                /begin IF_DATA VENDOR_EXT CHANNEL "a" UNKNOWN 1 /end IF_DATA
+                /begin IF_DATA OTHER_EXT 1 /end IF_DATA
+                /begin IF_DATA XCP /begin PROTOCOL_LAYER 0x0100 1 2 3 /end PROTOCOL_LAYER /end IF_DATA
+            /end MODULE
+        /end PROJECT"#;
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(
+            a2l_text,
+            Some(crate::ifdata::A2MLVECTOR_TEXT.to_string()),
+            &mut log_msgs,
+            false,
+        )
+        .unwrap();
+        assert!(a2l_file.project.module[0].if_data[0].ifdata_valid);
+
+        let mut log_msgs = Vec::new();
+        validate_ifdata(&a2l_file, None, &mut log_msgs).unwrap();
+        assert_eq!(log_msgs.len(), 5);
+        assert!(log_msgs[0].contains("on line 23: IF_DATA/VENDOR_EXT/INFO: expected uint in the range 0 to 65535, found number 70000"));
+        assert!(log_msgs[1].contains("on line 27: IF_DATA/VENDOR_EXT/INFO: expected one of the enum values ON, OFF, found identifier MAYBE"));
+        assert!(log_msgs[2].contains("expected /end IF_DATA, found identifier UNKNOWN, which is not one of the valid tags /begin INFO, CHANNEL"));
+        assert!(log_msgs[3].contains("no A2ML specification defines the tag OTHER_EXT"));
+        assert!(log_msgs[4].contains("IF_DATA/XCP/PROTOCOL_LAYER: expected uint, found /end"));
+
+        assert!(parse_a2ml("struct { uint; };").is_err());
+        assert!(parse_a2ml("block \"IF_DATA\" struct Undefined;").is_err());
+    }
+}
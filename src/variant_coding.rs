@@ -0,0 +1,677 @@
+//! Display and editing of the variant coding: VAR_CRITERION, VAR_FORBIDDEN_COMB and VAR_CHARACTERISTIC
+
+use crate::rename::is_valid_identifier;
+#[cfg(feature = "elf")]
+use crate::symbol::find_symbol;
+#[cfg(feature = "elf")]
+use crate::DebugData;
+#[cfg(feature = "elf")]
+use a2lfile::VarAddress;
+use a2lfile::{
+    A2lFile, A2lObject, CombinationStruct, VarCriterion, VarForbiddenComb, VariantCoding,
+};
+use std::ffi::OsStr;
+use std::fmt::Write;
+
+/// print the variant coding of all modules
+pub fn show_variants(a2l_file: &A2lFile, filename: &OsStr) {
+    let multi_module = a2l_file.project.module.len() > 1;
+
+    println!("Variant coding in {}:", filename.to_string_lossy());
+
+    for module in &a2l_file.project.module {
+        if multi_module {
+            println!("Variant coding for module {}", module.name);
+        }
+
+        if let Some(variant_coding) = &module.variant_coding {
+            print!("{}", format_variant_coding(variant_coding));
+        } else {
+            println!("No variant coding found in module {}", module.name);
+        }
+    }
+    println!();
+}
+
+// describe the variant coding of a module as text
+fn format_variant_coding(variant_coding: &VariantCoding) -> String {
+    let mut text = String::new();
+    let separator = variant_coding
+        .var_separator
+        .as_ref()
+        .map_or(".", |var_separator| var_separator.separator.as_str());
+    let _ = writeln!(text, "  separator: \"{separator}\"");
+    if variant_coding.var_naming.is_some() {
+        let _ = writeln!(text, "  naming: NUMERIC");
+    }
+
+    let _ = writeln!(text, "  criteria:");
+    for var_criterion in &variant_coding.var_criterion {
+        let _ = write!(
+            text,
+            "    {}: {}",
+            var_criterion.name,
+            var_criterion.value_list.join(", ")
+        );
+        if let Some(var_measurement) = &var_criterion.var_measurement {
+            let _ = write!(text, " (measurement {})", var_measurement.name);
+        }
+        if let Some(var_selection_characteristic) = &var_criterion.var_selection_characteristic {
+            let _ = write!(text, " (selected by {})", var_selection_characteristic.name);
+        }
+        text.push('\n');
+    }
+
+    if !variant_coding.var_forbidden_comb.is_empty() {
+        let _ = writeln!(text, "  forbidden combinations:");
+        for var_forbidden_comb in &variant_coding.var_forbidden_comb {
+            let _ = writeln!(
+                text,
+                "    {}",
+                format_combination(&var_forbidden_comb.combination)
+            );
+        }
+    }
+
+    if !variant_coding.var_characteristic.is_empty() {
+        let _ = writeln!(text, "  variant characteristics:");
+        for var_characteristic in &variant_coding.var_characteristic {
+            let _ = write!(
+                text,
+                "    {}: {}",
+                var_characteristic.name,
+                var_characteristic.criterion_name_list.join(", ")
+            );
+            if let Some(var_address) = &var_characteristic.var_address {
+                let addresses: Vec<String> = var_address
+                    .address_list
+                    .iter()
+                    .map(|address| format!("0x{address:08X}"))
+                    .collect();
+                let _ = write!(text, " (addresses {})", addresses.join(", "));
+            }
+            text.push('\n');
+        }
+    }
+
+    text
+}
+
+fn format_combination(combination: &[CombinationStruct]) -> String {
+    let items: Vec<String> = combination
+        .iter()
+        .map(|item| format!("{}={}", item.criterion_name, item.criterion_value))
+        .collect();
+    items.join(",")
+}
+
+/// Parse a criterion definition of the form `NAME=VALUE1,VALUE2,...`
+///
+/// # Errors
+///
+/// Returns an error message if the text is not a valid criterion definition
+pub fn parse_criterion_values(text: &str) -> Result<(&str, Vec<&str>), String> {
+    let Some((name, values)) = text.split_once('=') else {
+        return Err(format!(
+            "Error: \"{text}\" is not a valid criterion definition. Expected NAME=VALUE1,VALUE2,..."
+        ));
+    };
+    let name = name.trim();
+    let values: Vec<&str> = values.split(',').map(str::trim).collect();
+    for identifier in std::iter::once(name).chain(values.iter().copied()) {
+        if !is_valid_identifier(identifier) {
+            return Err(format!(
+                "Error: \"{identifier}\" in \"{text}\" is not a valid identifier"
+            ));
+        }
+    }
+    for (idx, value) in values.iter().enumerate() {
+        if values[..idx].contains(value) {
+            return Err(format!(
+                "Error: the value {value} occurs more than once in \"{text}\""
+            ));
+        }
+    }
+    Ok((name, values))
+}
+
+/// Parse a combination of criterion values of the form `CRITERION1=VALUE1,CRITERION2=VALUE2,...`
+///
+/// # Errors
+///
+/// Returns an error message if the text is not a valid combination
+pub fn parse_combination(text: &str) -> Result<Vec<(&str, &str)>, String> {
+    let mut combination: Vec<(&str, &str)> = Vec::new();
+    for item in text.split(',') {
+        let Some((criterion, value)) = item.split_once('=') else {
+            return Err(format!(
+                "Error: \"{text}\" is not a valid combination. Expected CRITERION1=VALUE1,CRITERION2=VALUE2,..."
+            ));
+        };
+        let (criterion, value) = (criterion.trim(), value.trim());
+        if combination.iter().any(|(name, _)| *name == criterion) {
+            return Err(format!(
+                "Error: the criterion {criterion} occurs more than once in \"{text}\""
+            ));
+        }
+        combination.push((criterion, value));
+    }
+    Ok(combination)
+}
+
+/// Set the values of a VAR_CRITERION in every module. The criterion and VARIANT_CODING are created if they do not exist.
+///
+/// Forbidden combinations that refer to a removed value are deleted. A message is logged for each
+/// VAR_CHARACTERISTIC with a VAR_ADDRESS that depends on the criterion, since the number of its addresses
+/// must match the number of variants.
+///
+/// Returns the number of deleted forbidden combinations.
+pub fn set_criterion_values(
+    a2l_file: &mut A2lFile,
+    name: &str,
+    values: &[&str],
+    log_msgs: &mut Vec<String>,
+) -> usize {
+    let mut removed = 0;
+    for module in &mut a2l_file.project.module {
+        let variant_coding = module.variant_coding.get_or_insert_with(VariantCoding::new);
+        let value_list: Vec<String> = values.iter().map(|value| (*value).to_string()).collect();
+        if let Some(var_criterion) = variant_coding
+            .var_criterion
+            .iter_mut()
+            .find(|var_criterion| var_criterion.name == name)
+        {
+            if var_criterion.value_list == value_list {
+                continue;
+            }
+            var_criterion.value_list = value_list;
+        } else {
+            let mut var_criterion = VarCriterion::new(name.to_string(), String::new());
+            var_criterion.value_list = value_list;
+            variant_coding.var_criterion.push(var_criterion);
+        }
+
+        let count = variant_coding.var_forbidden_comb.len();
+        variant_coding
+            .var_forbidden_comb
+            .retain(|var_forbidden_comb| {
+                var_forbidden_comb.combination.iter().all(|item| {
+                    item.criterion_name != name || values.contains(&item.criterion_value.as_str())
+                })
+            });
+        removed += count - variant_coding.var_forbidden_comb.len();
+
+        for var_characteristic in &variant_coding.var_characteristic {
+            if var_characteristic.var_address.is_some()
+                && var_characteristic
+                    .criterion_name_list
+                    .iter()
+                    .any(|criterion| criterion == name)
+            {
+                log_msgs.push(format!(
+                    "The VAR_ADDRESS of VAR_CHARACTERISTIC {} depends on the changed criterion {name} and must be updated",
+                    var_characteristic.name
+                ));
+            }
+        }
+    }
+    removed
+}
+
+/// Add a forbidden combination of criterion values to every module that defines all of its criteria.
+///
+/// Returns the number of modules the combination was added to. A combination that is already forbidden is not added again.
+///
+/// # Errors
+///
+/// Returns an error message if a criterion or value does not exist in a module that defines the other criteria,
+/// or if no module defines all criteria of the combination
+pub fn add_forbidden_combination(
+    a2l_file: &mut A2lFile,
+    combination: &[(&str, &str)],
+) -> Result<usize, String> {
+    let mut count = 0;
+    let mut found = false;
+    for module in &mut a2l_file.project.module {
+        let Some(variant_coding) = &mut module.variant_coding else {
+            continue;
+        };
+        let mut known = 0;
+        for (criterion, value) in combination {
+            if let Some(var_criterion) = variant_coding
+                .var_criterion
+                .iter()
+                .find(|var_criterion| var_criterion.name == *criterion)
+            {
+                if !var_criterion.value_list.iter().any(|v| v == value) {
+                    return Err(format!(
+                        "Error: {value} is not a value of the criterion {criterion} in module {}",
+                        module.name
+                    ));
+                }
+                known += 1;
+            }
+        }
+        if known < combination.len() {
+            continue;
+        }
+        found = true;
+
+        if !variant_coding
+            .var_forbidden_comb
+            .iter()
+            .any(|var_forbidden_comb| is_same_combination(var_forbidden_comb, combination))
+        {
+            let mut var_forbidden_comb = VarForbiddenComb::new();
+            var_forbidden_comb.combination = combination
+                .iter()
+                .map(|(criterion, value)| {
+                    CombinationStruct::new((*criterion).to_string(), (*value).to_string())
+                })
+                .collect();
+            variant_coding.var_forbidden_comb.push(var_forbidden_comb);
+            count += 1;
+        }
+    }
+    if !found {
+        let criteria: Vec<&str> = combination
+            .iter()
+            .map(|(criterion, _)| *criterion)
+            .collect();
+        return Err(format!(
+            "Error: no module defines all of the criteria {}",
+            criteria.join(", ")
+        ));
+    }
+    Ok(count)
+}
+
+/// Remove a forbidden combination of criterion values from every module. The order of the criteria does not matter.
+///
+/// Returns the number of removed combinations.
+pub fn remove_forbidden_combination(a2l_file: &mut A2lFile, combination: &[(&str, &str)]) -> usize {
+    let mut count = 0;
+    for module in &mut a2l_file.project.module {
+        if let Some(variant_coding) = &mut module.variant_coding {
+            let before = variant_coding.var_forbidden_comb.len();
+            variant_coding
+                .var_forbidden_comb
+                .retain(|var_forbidden_comb| !is_same_combination(var_forbidden_comb, combination));
+            count += before - variant_coding.var_forbidden_comb.len();
+        }
+    }
+    count
+}
+
+fn is_same_combination(
+    var_forbidden_comb: &VarForbiddenComb,
+    combination: &[(&str, &str)],
+) -> bool {
+    var_forbidden_comb.combination.len() == combination.len()
+        && combination.iter().all(|(criterion, value)| {
+            var_forbidden_comb
+                .combination
+                .iter()
+                .any(|item| item.criterion_name == *criterion && item.criterion_value == *value)
+        })
+}
+
+/// Update the VAR_ADDRESS lists of all VAR_CHARACTERISTICs from the symbols of the variants in the elf file.
+///
+/// The symbol of each variant is the symbol of the CHARACTERISTIC followed by a suffix. If `suffix` contains
+/// placeholders like `{Gear}`, they are replaced by the value of the criterion, and `{index}` is replaced by the
+/// number of the variant. Otherwise the suffix is used as a separator, e.g. `_` gives the symbol `name_Manual_Petrol`.
+/// The addresses are listed in the order of the variants, where the value of the last criterion changes fastest,
+/// and forbidden combinations are skipped.
+///
+/// Returns the number of updated VAR_CHARACTERISTICs. VAR_CHARACTERISTICs are not changed if any of their symbols
+/// is missing; the reason is added to `log_msgs`.
+#[cfg(feature = "elf")]
+pub fn update_var_addresses(
+    a2l_file: &mut A2lFile,
+    debug_data: &DebugData,
+    suffix: &str,
+    log_msgs: &mut Vec<String>,
+) -> usize {
+    let mut count = 0;
+    for module in &mut a2l_file.project.module {
+        let Some(variant_coding) = &module.variant_coding else {
+            continue;
+        };
+        let mut updates = Vec::new();
+        for (idx, var_characteristic) in variant_coding.var_characteristic.iter().enumerate() {
+            let Some(characteristic) = module
+                .characteristic
+                .iter()
+                .find(|characteristic| characteristic.name == var_characteristic.name)
+            else {
+                log_msgs.push(format!(
+                    "Error updating the VAR_ADDRESS of {}: there is no CHARACTERISTIC with this name",
+                    var_characteristic.name
+                ));
+                continue;
+            };
+            // additional specifications like {Function:name} are not part of the symbol name
+            let symbol_name = characteristic
+                .symbol_link
+                .as_ref()
+                .map_or(&characteristic.name, |symbol_link| &symbol_link.symbol_name);
+            let symbol_name = symbol_name.split('{').next().unwrap_or_default();
+
+            let result =
+                variant_combinations(variant_coding, &var_characteristic.criterion_name_list)
+                    .and_then(|combinations| {
+                        combinations
+                            .iter()
+                            .enumerate()
+                            .map(|(index, combination)| {
+                                let variant_symbol = format!(
+                                    "{symbol_name}{}",
+                                    variant_suffix(suffix, combination, index)?
+                                );
+                                find_symbol(&variant_symbol, debug_data)
+                                    .map(|sym_info| sym_info.address as u32)
+                            })
+                            .collect::<Result<Vec<u32>, String>>()
+                    });
+            match result {
+                Ok(addresses) => updates.push((idx, addresses)),
+                Err(errmsg) => log_msgs.push(format!(
+                    "Error updating the VAR_ADDRESS of {}: {errmsg}",
+                    var_characteristic.name
+                )),
+            }
+        }
+
+        let Some(variant_coding) = &mut module.variant_coding else {
+            continue;
+        };
+        for (idx, addresses) in updates {
+            let var_characteristic = &mut variant_coding.var_characteristic[idx];
+            let var_address = var_characteristic
+                .var_address
+                .get_or_insert_with(VarAddress::new);
+            // addresses are conventionally written as hex numbers
+            var_address.get_layout_mut().item_location.0 =
+                addresses.iter().map(|_| (0, true)).collect();
+            var_address.address_list = addresses;
+            count += 1;
+        }
+    }
+    count
+}
+
+// list all valid combinations of the values of the given criteria. The value of the last criterion changes fastest.
+// A combination is invalid if it contains all entries of a forbidden combination
+#[cfg(feature = "elf")]
+pub(crate) fn variant_combinations<'a>(
+    variant_coding: &'a VariantCoding,
+    criterion_names: &[String],
+) -> Result<Vec<Vec<(&'a str, &'a str)>>, String> {
+    let mut combinations: Vec<Vec<(&str, &str)>> = vec![Vec::new()];
+    for criterion_name in criterion_names {
+        let Some(var_criterion) = variant_coding
+            .var_criterion
+            .iter()
+            .find(|var_criterion| var_criterion.name == *criterion_name)
+        else {
+            return Err(format!("the criterion {criterion_name} does not exist"));
+        };
+        combinations = combinations
+            .into_iter()
+            .flat_map(|combination| {
+                var_criterion.value_list.iter().map(move |value| {
+                    let mut combination = combination.clone();
+                    combination.push((var_criterion.name.as_str(), value.as_str()));
+                    combination
+                })
+            })
+            .collect();
+    }
+    combinations.retain(|combination| {
+        !variant_coding
+            .var_forbidden_comb
+            .iter()
+            .any(|var_forbidden_comb| {
+                var_forbidden_comb.combination.iter().all(|item| {
+                    combination
+                        .contains(&(item.criterion_name.as_str(), item.criterion_value.as_str()))
+                })
+            })
+    });
+    Ok(combinations)
+}
+
+// build the symbol suffix of one variant, either from a template with placeholders or from a separator
+#[cfg(feature = "elf")]
+fn variant_suffix(
+    suffix: &str,
+    combination: &[(&str, &str)],
+    index: usize,
+) -> Result<String, String> {
+    if !suffix.contains('{') {
+        let values: Vec<&str> = combination.iter().map(|(_, value)| *value).collect();
+        return Ok(format!("{suffix}{}", values.join(suffix)));
+    }
+
+    let mut result = String::new();
+    let mut remaining = suffix;
+    while let Some(start) = remaining.find('{') {
+        let Some(end) = remaining[start..].find('}') else {
+            return Err(format!(
+                "the suffix \"{suffix}\" contains an unterminated placeholder"
+            ));
+        };
+        result.push_str(&remaining[..start]);
+        let placeholder = &remaining[start + 1..start + end];
+        if placeholder == "index" {
+            result.push_str(&index.to_string());
+        } else if let Some((_, value)) = combination
+            .iter()
+            .find(|(criterion, _)| *criterion == placeholder)
+        {
+            result.push_str(value);
+        } else {
+            return Err(format!(
+                "the placeholder {{{placeholder}}} is not a criterion of the characteristic"
+            ));
+        }
+        remaining = &remaining[start + end + 1..];
+    }
+    result.push_str(remaining);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TEXT: &str = r#"ASAP2_VERSION 1 71
+    /begin PROJECT p ""
+      /begin MODULE m ""
+        /begin CHARACTERISTIC c "" VALUE 0x1000 rl 0 NO_COMPU_METHOD 0 100 /end CHARACTERISTIC
+        /begin RECORD_LAYOUT rl FNC_VALUES 1 UBYTE ROW_DIR DIRECT /end RECORD_LAYOUT
+        /begin VARIANT_CODING
+          VAR_SEPARATOR "."
+          /begin VAR_CRITERION Gear "" Manual Automatic /end VAR_CRITERION
+          /begin VAR_CRITERION Engine "" Petrol Diesel Electric /end VAR_CRITERION
+          /begin VAR_FORBIDDEN_COMB Engine Electric Gear Manual /end VAR_FORBIDDEN_COMB
+          /begin VAR_CHARACTERISTIC c Gear Engine
+            /begin VAR_ADDRESS 0x2000 0x2001 0x2002 0x2003 0x2004 /end VAR_ADDRESS
+          /end VAR_CHARACTERISTIC
+        /end VARIANT_CODING
+      /end MODULE
+    /end PROJECT"#;
+
+    #[test]
+    fn test_format_variant_coding() {
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(TEXT, None, &mut log_msgs, true).unwrap();
+        let variant_coding = a2l_file.project.module[0].variant_coding.as_ref().unwrap();
+        let text = format_variant_coding(variant_coding);
+        assert!(text.contains("    Gear: Manual, Automatic\n"));
+        assert!(text.contains("    Engine: Petrol, Diesel, Electric\n"));
+        assert!(text.contains("  forbidden combinations:\n    Engine=Electric,Gear=Manual\n"));
+        assert!(text.contains("    c: Gear, Engine (addresses 0x00002000,"));
+    }
+
+    #[test]
+    fn test_parse() {
+        let (name, values) = parse_criterion_values("Gear=Manual, Automatic").unwrap();
+        assert_eq!(name, "Gear");
+        assert_eq!(values, vec!["Manual", "Automatic"]);
+        assert!(parse_criterion_values("Gear").is_err());
+        assert!(parse_criterion_values("Gear=Manual,Manual").is_err());
+        assert!(parse_criterion_values("Gear=1st").is_err());
+
+        let combination = parse_combination("Gear=Manual,Engine=Diesel").unwrap();
+        assert_eq!(combination, vec![("Gear", "Manual"), ("Engine", "Diesel")]);
+        assert!(parse_combination("Gear=Manual,Gear=Automatic").is_err());
+        assert!(parse_combination("Gear").is_err());
+    }
+
+    #[test]
+    fn test_edit_variants() {
+        let mut log_msgs = Vec::new();
+        let mut a2l_file = a2lfile::load_from_string(TEXT, None, &mut log_msgs, true).unwrap();
+
+        // forbidden combinations can be given in any order
+        let count =
+            add_forbidden_combination(&mut a2l_file, &[("Gear", "Manual"), ("Engine", "Electric")]);
+        assert_eq!(count, Ok(0));
+        let count = add_forbidden_combination(
+            &mut a2l_file,
+            &[("Gear", "Automatic"), ("Engine", "Diesel")],
+        );
+        assert_eq!(count, Ok(1));
+        assert!(add_forbidden_combination(&mut a2l_file, &[("Gear", "Sequential")]).is_err());
+        assert!(add_forbidden_combination(&mut a2l_file, &[("Color", "Red")]).is_err());
+
+        let count = remove_forbidden_combination(
+            &mut a2l_file,
+            &[("Engine", "Diesel"), ("Gear", "Automatic")],
+        );
+        assert_eq!(count, 1);
+
+        // removing the value Electric also removes the forbidden combination that uses it
+        let mut log_msgs = Vec::new();
+        let removed = set_criterion_values(
+            &mut a2l_file,
+            "Engine",
+            &["Petrol", "Diesel"],
+            &mut log_msgs,
+        );
+        assert_eq!(removed, 1);
+        assert_eq!(log_msgs.len(), 1);
+        let variant_coding = a2l_file.project.module[0].variant_coding.as_ref().unwrap();
+        assert!(variant_coding.var_forbidden_comb.is_empty());
+        assert_eq!(
+            variant_coding.var_criterion[1].value_list,
+            vec!["Petrol", "Diesel"]
+        );
+
+        // new criteria are created
+        let mut log_msgs = Vec::new();
+        set_criterion_values(&mut a2l_file, "Market", &["EU", "US"], &mut log_msgs);
+        assert!(log_msgs.is_empty());
+        let variant_coding = a2l_file.project.module[0].variant_coding.as_ref().unwrap();
+        assert_eq!(variant_coding.var_criterion.len(), 3);
+
+        // the edited variant coding can be written and loaded again
+        let text = a2l_file.write_to_string();
+        let mut log_msgs = Vec::new();
+        let reloaded = a2lfile::load_from_string(&text, None, &mut log_msgs, true).unwrap();
+        let variant_coding = reloaded.project.module[0].variant_coding.as_ref().unwrap();
+        assert_eq!(variant_coding.var_criterion[2].value_list, vec!["EU", "US"]);
+    }
+
+    #[cfg(feature = "elf")]
+    #[test]
+    fn test_variant_combinations() {
+        let mut log_msgs = Vec::new();
+        let a2l_file = a2lfile::load_from_string(TEXT, None, &mut log_msgs, true).unwrap();
+        let variant_coding = a2l_file.project.module[0].variant_coding.as_ref().unwrap();
+        let criteria = vec!["Gear".to_string(), "Engine".to_string()];
+        let combinations = variant_combinations(variant_coding, &criteria).unwrap();
+        // 2 * 3 combinations, one of them is forbidden
+        assert_eq!(combinations.len(), 5);
+        assert_eq!(
+            combinations[0],
+            vec![("Gear", "Manual"), ("Engine", "Petrol")]
+        );
+        assert_eq!(
+            combinations[2],
+            vec![("Gear", "Automatic"), ("Engine", "Petrol")]
+        );
+        assert!(variant_combinations(variant_coding, &["Color".to_string()]).is_err());
+
+        assert_eq!(
+            variant_suffix("_", &combinations[0], 0).unwrap(),
+            "_Manual_Petrol"
+        );
+        assert_eq!(
+            variant_suffix("_{Engine}_v{index}", &combinations[1], 1).unwrap(),
+            "_Diesel_v1"
+        );
+        assert!(variant_suffix("_{Color}", &combinations[0], 0).is_err());
+    }
+
+    #[cfg(feature = "elf")]
+    #[test]
+    fn test_update_var_addresses() {
+        let debug_data = crate::DebugData::load(
+            std::ffi::OsStr::new("tests/elffiles/update_test.elf"),
+            false,
+        )
+        .unwrap();
+        let text = r#"ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+          /begin MODULE m ""
+            /begin CHARACTERISTIC val "" VALUE 0 rl 0 NO_COMPU_METHOD 0 100 /end CHARACTERISTIC
+            /begin CHARACTERISTIC other "" VALUE 0 rl 0 NO_COMPU_METHOD 0 100 /end CHARACTERISTIC
+            /begin RECORD_LAYOUT rl FNC_VALUES 1 UBYTE ROW_DIR DIRECT /end RECORD_LAYOUT
+            /begin VARIANT_CODING
+              /begin VAR_CRITERION Type "" u8 u16 i32 /end VAR_CRITERION
+              /begin VAR_CHARACTERISTIC val Type /end VAR_CHARACTERISTIC
+              /begin VAR_CHARACTERISTIC other Type
+                /begin VAR_ADDRESS 0x1 0x2 0x3 /end VAR_ADDRESS
+              /end VAR_CHARACTERISTIC
+            /end VARIANT_CODING
+          /end MODULE
+        /end PROJECT"#;
+        let mut log_msgs = Vec::new();
+        let mut a2l_file = a2lfile::load_from_string(text, None, &mut log_msgs, true).unwrap();
+
+        let mut log_msgs = Vec::new();
+        let count = update_var_addresses(&mut a2l_file, &debug_data, "_", &mut log_msgs);
+        assert_eq!(count, 1);
+        // the symbols of "other" do not exist, so its addresses are unchanged
+        assert_eq!(log_msgs.len(), 1);
+        let expected: Vec<u32> = ["val_u8", "val_u16", "val_i32"]
+            .iter()
+            .map(|name| find_symbol(name, &debug_data).unwrap().address as u32)
+            .collect();
+        let variant_coding = a2l_file.project.module[0].variant_coding.as_ref().unwrap();
+        assert_eq!(
+            variant_coding.var_characteristic[0]
+                .var_address
+                .as_ref()
+                .unwrap()
+                .address_list,
+            expected
+        );
+        assert_eq!(
+            variant_coding.var_characteristic[1]
+                .var_address
+                .as_ref()
+                .unwrap()
+                .address_list,
+            vec![1, 2, 3]
+        );
+
+        // the same addresses are found with a template
+        let mut log_msgs = Vec::new();
+        let count = update_var_addresses(&mut a2l_file, &debug_data, "_{Type}", &mut log_msgs);
+        assert_eq!(count, 1);
+    }
+}
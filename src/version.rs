@@ -1,9 +1,13 @@
+//! Conversion of a2l files to older versions of the standard
+
 use crate::A2lVersion;
 use a2lfile::{
     A2lFile, CharacteristicType, Coeffs, CoeffsLinear, ConversionType, DataType, MatrixDim,
     MemoryType,
 };
 
+/// convert the a2l file to the given version.
+/// Objects and attributes that do not exist in the new version are removed.
 pub fn convert(a2l_file: &mut A2lFile, new_version: A2lVersion) {
     match new_version {
         A2lVersion::V1_5_0 => {
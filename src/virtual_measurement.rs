@@ -0,0 +1,129 @@
+//! Creation of VIRTUAL MEASUREMENTs, which are calculated from other measurements
+
+use a2lfile::{
+    A2lFile, CompuMethod, ConversionType, DataType, EcuAddress, Formula, Measurement, Virtual,
+};
+
+/// Create a virtual MEASUREMENT in the first module, which is calculated from the referenced measurements.
+///
+/// The formula refers to the referenced measurements as X1, X2, etc. in the order in which they are given.
+/// A COMPU_METHOD of the type FORM is created for the formula.
+///
+/// # Errors
+///
+/// Returns an error message if the name is invalid or already in use, or if a referenced measurement does not exist
+pub fn create_virtual_measurement(
+    a2l_file: &mut A2lFile,
+    name: &str,
+    formula: &str,
+    references: &[&str],
+) -> Result<(), String> {
+    let module = &mut a2l_file.project.module[0];
+    if !crate::rename::is_valid_identifier(name) {
+        return Err(format!("Error: \"{name}\" is not a valid identifier"));
+    }
+    let name_in_use = module.measurement.iter().any(|meas| meas.name == name)
+        || module.characteristic.iter().any(|chr| chr.name == name)
+        || module.axis_pts.iter().any(|axis| axis.name == name)
+        || module.blob.iter().any(|blob| blob.name == name)
+        || module.instance.iter().any(|inst| inst.name == name);
+    if name_in_use {
+        return Err(format!("Error: the name {name} is already in use"));
+    }
+    if formula.trim().is_empty() || formula.chars().any(char::is_control) {
+        return Err(format!("Error: invalid formula \"{formula}\" for {name}"));
+    }
+    if references.is_empty() {
+        return Err(format!(
+            "Error: the virtual measurement {name} must reference at least one measurement"
+        ));
+    }
+    for reference in references {
+        if !module
+            .measurement
+            .iter()
+            .any(|meas| meas.name == *reference)
+        {
+            return Err(format!(
+                "Error: the referenced MEASUREMENT {reference} does not exist"
+            ));
+        }
+    }
+    let conversion = format!("{name}_FORMULA");
+    if module.compu_method.iter().any(|cm| cm.name == conversion) {
+        return Err(format!("Error: COMPU_METHOD {conversion} already exists"));
+    }
+
+    let mut compu_method = CompuMethod::new(
+        conversion.clone(),
+        format!("formula for virtual measurement {name}"),
+        ConversionType::Form,
+        "%8.3".to_string(),
+        String::new(),
+    );
+    compu_method.formula = Some(Formula::new(formula.to_string()));
+    module.compu_method.push(compu_method);
+
+    let mut measurement = Measurement::new(
+        name.to_string(),
+        String::new(),
+        DataType::Float64Ieee,
+        conversion,
+        0,
+        0.0,
+        f64::MIN,
+        f64::MAX,
+    );
+    // virtual measurements are not read from the ECU memory
+    measurement.ecu_address = Some(EcuAddress::new(0));
+    let mut var_virtual = Virtual::new();
+    var_virtual.measuring_channel_list = references.iter().map(ToString::to_string).collect();
+    measurement.var_virtual = Some(var_virtual);
+    module.measurement.push(measurement);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_create_virtual_measurement() {
+        let a2l_text = r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin MEASUREMENT speed "" UWORD NO_COMPU_METHOD 0 0 0 65535
+                    ECU_ADDRESS 0x1000
+                /end MEASUREMENT
+                /begin MEASUREMENT time "" UWORD NO_COMPU_METHOD 0 0 0 65535
+                    ECU_ADDRESS 0x1002
+                /end MEASUREMENT
+            /end MODULE
+        /end PROJECT"#;
+        let mut log_msgs = Vec::new();
+        let mut a2l_file = a2lfile::load_from_string(a2l_text, None, &mut log_msgs, false).unwrap();
+
+        create_virtual_measurement(&mut a2l_file, "distance", "X1*X2", &["speed", "time"]).unwrap();
+        let module = &a2l_file.project.module[0];
+        let measurement = module.measurement.iter().find(|m| m.name == "distance");
+        let measurement = measurement.unwrap();
+        assert_eq!(measurement.conversion, "distance_FORMULA");
+        assert_eq!(
+            measurement
+                .var_virtual
+                .as_ref()
+                .unwrap()
+                .measuring_channel_list,
+            vec!["speed".to_string(), "time".to_string()]
+        );
+        let compu_method = &module.compu_method[0];
+        assert_eq!(compu_method.conversion_type, ConversionType::Form);
+        assert_eq!(compu_method.formula.as_ref().unwrap().fx, "X1*X2");
+
+        assert!(create_virtual_measurement(&mut a2l_file, "distance", "X1", &["speed"]).is_err());
+        assert!(create_virtual_measurement(&mut a2l_file, "accel", "X1", &["missing"]).is_err());
+        assert!(create_virtual_measurement(&mut a2l_file, "accel", "X1", &[]).is_err());
+    }
+}
@@ -0,0 +1,246 @@
+//! API for use from JavaScript when a2ltool is compiled to WebAssembly
+//!
+//! Build with `cargo build --lib --release --target wasm32-unknown-unknown --no-default-features`.
+//! The wrapper wasm/a2ltool.js loads the resulting module and provides the functions `check()`, `merge()`,
+//! `statistics()` and `diff()`.
+//!
+//! Since there is no file system, all a2l files are passed as text. The exported functions take the
+//! text from buffers allocated with `a2ltool_alloc`, and store their result (or an error message) in a
+//! buffer that can be read using `a2ltool_result_ptr` and `a2ltool_result_len`.
+
+use crate::{ifdata, query, statistics};
+use a2lfile::A2lFile;
+
+/// Load an a2l file from a string. The built-in A2ML specification is used to parse the IF_DATA.
+///
+/// # Errors
+///
+/// Returns an error message if the text cannot be parsed
+pub fn load_text(a2l_text: &str) -> Result<A2lFile, String> {
+    let mut log_msgs = Vec::new();
+    a2lfile::load_from_string(
+        a2l_text,
+        Some(ifdata::A2MLVECTOR_TEXT.to_string()),
+        &mut log_msgs,
+        false,
+    )
+    .map_err(|err| err.to_string())
+}
+
+/// Perform a consistency check of an a2l file given as text.
+/// The result is a JSON array of strings, which contains one entry for each problem.
+///
+/// # Errors
+///
+/// Returns an error message if the text cannot be parsed
+pub fn check_text(a2l_text: &str) -> Result<String, String> {
+    let a2l_file = load_text(a2l_text)?;
+    let mut log_msgs = Vec::new();
+    a2l_file.check(&mut log_msgs);
+    Ok(serde_json::Value::from(log_msgs).to_string())
+}
+
+/// Merge the modules of two a2l files given as text, and return the text of the merged file.
+///
+/// # Errors
+///
+/// Returns an error message if either text cannot be parsed
+pub fn merge_text(a2l_text: &str, merge_text: &str) -> Result<String, String> {
+    let mut a2l_file = load_text(a2l_text)?;
+    let mut merge_file = load_text(merge_text)?;
+    a2l_file.merge_modules(&mut merge_file);
+    Ok(a2l_file.write_to_string())
+}
+
+/// Collect statistics about an a2l file given as text, see [`statistics::collect_statistics`].
+/// The result is the JSON form of the statistics; there is no information about include files.
+///
+/// # Errors
+///
+/// Returns an error message if the text cannot be parsed
+pub fn statistics_text(a2l_text: &str) -> Result<String, String> {
+    let a2l_file = load_text(a2l_text)?;
+    Ok(statistics::collect_statistics(&a2l_file, None, &[])
+        .to_json()
+        .to_string())
+}
+
+/// Compare the objects of two a2l files given as text, see [`query::diff`].
+/// The result is a JSON object containing the lists "added", "removed" and "changed".
+///
+/// # Errors
+///
+/// Returns an error message if either text cannot be parsed
+pub fn diff_text(a2l_text: &str, other_text: &str) -> Result<String, String> {
+    let a2l_file = load_text(a2l_text)?;
+    let other_file = load_text(other_text)?;
+    Ok(query::diff(&a2l_file, &other_file).to_json().to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+mod exports {
+    use std::cell::RefCell;
+
+    thread_local! {
+        static RESULT: RefCell<String> = const { RefCell::new(String::new()) };
+    }
+
+    // store the result of an operation and return 0 on success or -1 on failure
+    fn set_result(result: Result<String, String>) -> i32 {
+        let (text, status) = match result {
+            Ok(text) => (text, 0),
+            Err(text) => (text, -1),
+        };
+        RESULT.with(|result| *result.borrow_mut() = text);
+        status
+    }
+
+    unsafe fn get_str<'a>(ptr: *const u8, len: usize) -> Result<&'a str, String> {
+        std::str::from_utf8(std::slice::from_raw_parts(ptr, len))
+            .map_err(|_| "the input is not valid UTF-8".to_string())
+    }
+
+    /// allocate a buffer of `len` bytes in the wasm memory
+    #[no_mangle]
+    pub extern "C" fn a2ltool_alloc(len: usize) -> *mut u8 {
+        let mut buffer = Vec::<u8>::with_capacity(len);
+        let ptr = buffer.as_mut_ptr();
+        std::mem::forget(buffer);
+        ptr
+    }
+
+    /// release a buffer that was allocated with `a2ltool_alloc`
+    ///
+    /// # Safety
+    ///
+    /// `ptr` and `len` must match a previous call of `a2ltool_alloc`
+    #[no_mangle]
+    pub unsafe extern "C" fn a2ltool_free(ptr: *mut u8, len: usize) {
+        drop(Vec::from_raw_parts(ptr, 0, len));
+    }
+
+    /// pointer to the result of the last operation
+    #[no_mangle]
+    pub extern "C" fn a2ltool_result_ptr() -> *const u8 {
+        RESULT.with(|result| result.borrow().as_ptr())
+    }
+
+    /// length in bytes of the result of the last operation
+    #[no_mangle]
+    pub extern "C" fn a2ltool_result_len() -> usize {
+        RESULT.with(|result| result.borrow().len())
+    }
+
+    /// consistency check, see [`super::check_text`]
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to `len` initialized bytes
+    #[no_mangle]
+    pub unsafe extern "C" fn a2ltool_check(ptr: *const u8, len: usize) -> i32 {
+        set_result(get_str(ptr, len).and_then(super::check_text))
+    }
+
+    /// merge two files, see [`super::merge_text`]
+    ///
+    /// # Safety
+    ///
+    /// each pointer must point to the given number of initialized bytes
+    #[no_mangle]
+    pub unsafe extern "C" fn a2ltool_merge(
+        ptr: *const u8,
+        len: usize,
+        merge_ptr: *const u8,
+        merge_len: usize,
+    ) -> i32 {
+        set_result(
+            get_str(ptr, len)
+                .and_then(|a2l_text| super::merge_text(a2l_text, get_str(merge_ptr, merge_len)?)),
+        )
+    }
+
+    /// statistics, see [`super::statistics_text`]
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to `len` initialized bytes
+    #[no_mangle]
+    pub unsafe extern "C" fn a2ltool_statistics(ptr: *const u8, len: usize) -> i32 {
+        set_result(get_str(ptr, len).and_then(super::statistics_text))
+    }
+
+    /// compare two files, see [`super::diff_text`]
+    ///
+    /// # Safety
+    ///
+    /// each pointer must point to the given number of initialized bytes
+    #[no_mangle]
+    pub unsafe extern "C" fn a2ltool_diff(
+        ptr: *const u8,
+        len: usize,
+        other_ptr: *const u8,
+        other_len: usize,
+    ) -> i32 {
+        set_result(
+            get_str(ptr, len)
+                .and_then(|a2l_text| super::diff_text(a2l_text, get_str(other_ptr, other_len)?)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_check_merge_text() {
+        let a2l_text = r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin MEASUREMENT meas "" UBYTE NO_COMPU_METHOD 0 0 0 255
+                /end MEASUREMENT
+            /end MODULE
+        /end PROJECT"#;
+        let merge_a2l_text = a2l_text.replace("meas", "other_meas");
+
+        let result = check_text(a2l_text).unwrap();
+        let messages: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert!(messages.is_array());
+
+        let merged = merge_text(a2l_text, &merge_a2l_text).unwrap();
+        assert!(merged.contains("other_meas"));
+        assert!(load_text(&merged).is_ok());
+
+        assert!(check_text("not an a2l file").is_err());
+    }
+
+    #[test]
+    fn test_statistics_diff_text() {
+        let a2l_text = r#"
+        ASAP2_VERSION 1 71
+        /begin PROJECT p ""
+            /begin MODULE m ""
+                /begin MEASUREMENT meas "" UBYTE NO_COMPU_METHOD 0 0 0 255
+                /end MEASUREMENT
+            /end MODULE
+        /end PROJECT"#;
+        let other_a2l_text = a2l_text.replace("meas", "other_meas");
+
+        let result = statistics_text(a2l_text).unwrap();
+        let stats: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(stats["objects"]["MEASUREMENT"], 1);
+        assert_eq!(stats["datatypes"]["UBYTE"]["measurements"], 1);
+        assert!(stats["include_files"].is_null());
+
+        let result = diff_text(a2l_text, &other_a2l_text).unwrap();
+        let diff: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(
+            diff["added"],
+            serde_json::json!(["MEASUREMENT m.other_meas"])
+        );
+        assert_eq!(diff["removed"], serde_json::json!(["MEASUREMENT m.meas"]));
+
+        assert!(diff_text(a2l_text, "not an a2l file").is_err());
+    }
+}
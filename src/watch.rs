@@ -0,0 +1,97 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+// the files are checked for changes this often
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// the state of a file that is used to detect changes: modification time and size.
+// None if the file does not exist, e.g. while the linker is replacing it
+pub(crate) type FileState = Option<(SystemTime, u64)>;
+
+// watch a list of input files by polling their modification time and size
+pub(crate) struct FileWatcher {
+    files: Vec<(PathBuf, FileState)>,
+}
+
+impl FileWatcher {
+    pub(crate) fn new(files: Vec<PathBuf>) -> Self {
+        let files = files
+            .into_iter()
+            .map(|path| {
+                let state = file_state(&path);
+                (path, state)
+            })
+            .collect();
+        Self { files }
+    }
+
+    // block until at least one of the files has changed, and return the names of the changed files.
+    // A file that is still being written is not reported until it has stopped changing for one poll interval.
+    pub(crate) fn wait_for_change(&mut self) -> Vec<PathBuf> {
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let changed = self.changed_files();
+            if changed.is_empty() {
+                continue;
+            }
+            // wait until the files are stable
+            loop {
+                std::thread::sleep(POLL_INTERVAL);
+                if self.changed_files().is_empty() {
+                    break;
+                }
+            }
+            // the changed files must exist before processing can start; otherwise keep waiting
+            if self.files.iter().all(|(_, state)| state.is_some()) {
+                return changed;
+            }
+        }
+    }
+
+    // update the stored state of all files, and return the names of those that changed since the last call
+    fn changed_files(&mut self) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+        for (path, state) in &mut self.files {
+            let new_state = file_state(path);
+            if new_state != *state {
+                *state = new_state;
+                changed.push(path.clone());
+            }
+        }
+        changed
+    }
+}
+
+// get the current state of a file
+pub(crate) fn file_state(path: &Path) -> FileState {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_changed_files() {
+        let dir = std::env::temp_dir().join(format!("a2ltool_watch_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let elffile = dir.join("firmware.elf");
+        let mergefile = dir.join("merge.a2l");
+        std::fs::write(&elffile, "elf").unwrap();
+        std::fs::write(&mergefile, "a2l").unwrap();
+
+        let mut watcher = FileWatcher::new(vec![elffile.clone(), mergefile.clone()]);
+        assert!(watcher.changed_files().is_empty());
+
+        // the size changes, so the change is detected even if the timestamp resolution is coarse
+        std::fs::write(&elffile, "new elf").unwrap();
+        assert_eq!(watcher.changed_files(), vec![elffile.clone()]);
+        assert!(watcher.changed_files().is_empty());
+
+        std::fs::remove_file(&mergefile).unwrap();
+        assert_eq!(watcher.changed_files(), vec![mergefile.clone()]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
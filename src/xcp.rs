@@ -1,14 +1,121 @@
+//! Display, create and edit the XCP settings in the IF_DATA XCP
+
 use std::ffi::OsStr;
+use std::str::FromStr;
 
 use crate::ifdata::{
-    A2mlVector, Address2, Channel, Cmd, CycleRepetition, Daq2, EvServ, FlxSlotId, HostName,
-    InitialCmdBuffer, InitialResErrBuffer, Ipv6, LpduId, MaxFlxLenBuf, Offset, PoolBuffer, ResErr,
-    Stim2, TCP_IP_Parameters, UDP_IP_Parameters, Xcp, XcpOnCan, XcpOnFlx, XcpOnTcpIp, XcpOnUdpIp,
-    XcpPacket,
+    A2mlVector, Address2, AddressExtension, AddressGranularity, AnonEnum3, AnonEnum4, Baudrate,
+    ByteOrderMsb, CAN_Parameters, CanIdMaster, CanIdSlave, Channel, Cmd, CycleRepetition, Daq,
+    Daq2, EvServ, Event, FLX_Parameters, FlxSlotId, GranularityOdtEntrySizeDaq, HeaderNax,
+    HostName, IdentificationFieldType, InitialCmdBuffer, InitialResErrBuffer, Ipv6, LpduId,
+    MaxFlxLenBuf, Offset, OptimisationType, OverloadIndication, PacketAlignment, PoolBuffer,
+    ProtocolLayer, ResErr, SamplePoint, SeedAndKeyExternalFunction, Stim2, TCP_IP_Parameters,
+    UDP_IP_Parameters, Xcp, XcpOnCan, XcpOnFlx, XcpOnTcpIp, XcpOnUdpIp, XcpPacket, A2MLVECTOR_TEXT,
 };
-use a2lfile::A2lFile;
+use crate::remove_ifdata::ifdata_tag;
+use a2lfile::{A2lFile, A2lObject, A2ml, IfData, MaxRefresh, Module};
+use serde_json::{json, Map, Value};
+use yaml_rust2::{Yaml, YamlLoader};
+
+/// XCP on CAN settings that can be changed from the command line
+#[derive(Debug, Default)]
+pub struct XcpOnCanSettings {
+    pub can_id_master: Option<u32>,
+    pub can_id_slave: Option<u32>,
+    pub baudrate: Option<u32>,
+    pub sample_point: Option<u8>,
+}
+
+/// XCP on FlexRay settings that can be changed from the command line
+#[derive(Debug, Default)]
+pub struct XcpOnFlxSettings {
+    pub fibex_file: Option<String>,
+    pub cluster_id: Option<String>,
+    pub nax: Option<u8>,
+    pub t1: Option<u16>,
+}
+
+/// the protocol of an XCP on Ethernet connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XcpEthProtocol {
+    Tcp,
+    Udp,
+}
+
+/// XCP on TCP/IP and UDP/IP settings that can be changed from the command line
+#[derive(Debug, Default)]
+pub struct XcpOnEthSettings {
+    pub protocol: Option<XcpEthProtocol>,
+    pub address: Option<String>,
+    pub port: Option<u16>,
+}
+
+impl FromStr for XcpEthProtocol {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.to_ascii_lowercase().as_str() {
+            "tcp" => Ok(Self::Tcp),
+            "udp" => Ok(Self::Udp),
+            _ => Err(format!(
+                "Error: unknown protocol \"{text}\". Valid protocols are tcp and udp"
+            )),
+        }
+    }
+}
+
+/// the transport layers that are supported for newly created XCP settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XcpTransport {
+    Can,
+    Flx,
+    Tcp,
+    Udp,
+}
+
+impl FromStr for XcpTransport {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.to_ascii_lowercase().as_str() {
+            "can" => Ok(Self::Can),
+            "flx" => Ok(Self::Flx),
+            "tcp" => Ok(Self::Tcp),
+            "udp" => Ok(Self::Udp),
+            _ => Err(format!(
+                "Error: unknown transport layer \"{text}\". Valid transport layers are can, flx, tcp and udp"
+            )),
+        }
+    }
+}
+
+/// parameters of a newly created XCP IF_DATA block
+/// the default values can be overridden from a config file or on the command line
+#[derive(Debug, Clone)]
+pub struct XcpTemplate {
+    transport: XcpTransport,
+    timings: [u16; 7],
+    max_cto: u8,
+    max_dto: u16,
+    byte_order_msb_first: bool,
+    daq_dynamic: bool,
+    max_daq: u16,
+    max_event_channel: u16,
+    min_daq: u8,
+    max_odt_entry_size_daq: u8,
+}
+
+// default values used when a new XCP_ON_TCP_IP or XCP_ON_UDP_IP block is created
+const XCP_ON_ETH_VERSION: u16 = 0x0100;
+const XCP_ON_ETH_DEFAULT_PORT: u16 = 5555;
+
+/// print the XCP settings of all modules, either as text or as JSON
+pub fn show_settings(a2l_file: &A2lFile, filename: &OsStr, json: bool) {
+    if json {
+        show_settings_json(a2l_file, filename);
+        return;
+    }
 
-pub(crate) fn show_settings(a2l_file: &A2lFile, filename: &OsStr) {
     let multi_module = a2l_file.project.module.len() > 1;
 
     println!("XCP settings in {}:", filename.to_string_lossy());
@@ -35,7 +142,1351 @@ pub(crate) fn show_settings(a2l_file: &A2lFile, filename: &OsStr) {
     println!();
 }
 
+// print the XCP settings as a JSON document, so that they can be processed by other tools
+fn show_settings_json(a2l_file: &A2lFile, filename: &OsStr) {
+    let modules: Vec<Value> = a2l_file
+        .project
+        .module
+        .iter()
+        .map(|module| {
+            let xcp = module.if_data.iter().find_map(|ifdata| {
+                A2mlVector::load_from_ifdata(ifdata)
+                    .and_then(|decoded_ifdata| decoded_ifdata.xcp)
+                    .map(|xcp| xcp_to_json(&xcp))
+            });
+            json!({
+                "name": module.name,
+                "xcp": xcp,
+            })
+        })
+        .collect();
+    let output = json!({
+        "file": filename.to_string_lossy(),
+        "modules": modules,
+    });
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&output).unwrap_or_default()
+    );
+}
+
+fn xcp_to_json(xcp: &Xcp) -> Value {
+    let mut output = Map::new();
+    if let Some(protocol_layer) = &xcp.protocol_layer {
+        output.insert(
+            "protocol_layer".to_string(),
+            json!({
+                "version": protocol_layer.protocol_version,
+                "t1": protocol_layer.t1,
+                "t2": protocol_layer.t2,
+                "t3": protocol_layer.t3,
+                "t4": protocol_layer.t4,
+                "t5": protocol_layer.t5,
+                "t6": protocol_layer.t6,
+                "t7": protocol_layer.t7,
+                "max_cto": protocol_layer.max_cto,
+                "max_dto": protocol_layer.max_dto,
+                "byte_order": format!("{:?}", protocol_layer.byte_order_msb),
+                "address_granularity": format!("{:?}", protocol_layer.address_granularity),
+                "seed_and_key": protocol_layer.seed_and_key_external_function.as_ref().map(|seed_key| &seed_key.funcname),
+            }),
+        );
+    }
+    if let Some(daq) = &xcp.daq {
+        let daq_lists: Vec<Value> = daq
+            .daq_list
+            .iter()
+            .map(|daq_list| {
+                json!({
+                    "number": daq_list.daq_list_number,
+                    "type": daq_list.daq_list_type.as_ref().map(|daq_list_type| format!("{:?}", daq_list_type.anon_enum4)),
+                    "max_odt": daq_list.max_odt.as_ref().map(|max_odt| max_odt.item),
+                    "event_fixed": daq_list.event_fixed.as_ref().map(|event_fixed| event_fixed.item),
+                })
+            })
+            .collect();
+        let events: Vec<Value> = daq
+            .event
+            .iter()
+            .map(|event| {
+                json!({
+                    "name": event.event_channel_name,
+                    "short_name": event.event_channel_short_name,
+                    "number": event.event_channel_number,
+                    "direction": format!("{:?}", event.anon_enum4),
+                    "max_daq_list": event.max_daq_list,
+                    "time_cycle": event.time_cycle,
+                    "time_unit": event.time_unit,
+                    "priority": event.priority,
+                })
+            })
+            .collect();
+        output.insert(
+            "daq".to_string(),
+            json!({
+                "config_type": format!("{:?}", daq.anon_enum3),
+                "max_daq": daq.max_daq,
+                "max_event_channel": daq.max_event_channel,
+                "min_daq": daq.min_daq,
+                "daq_lists": daq_lists,
+                "events": events,
+            }),
+        );
+    }
+    if let Some(xcp_on_can) = &xcp.xcp_on_can {
+        let can_parameters = &xcp_on_can.can_parameters;
+        let can_fd = can_parameters.can_fd.as_ref().map(|can_fd| {
+            json!({
+                "data_baudrate": can_fd.can_fd_data_transfer_baudrate.as_ref().map(|baudrate| baudrate.value),
+                "max_dlc": can_fd.max_dlc.as_ref().map(|max_dlc| max_dlc.value),
+            })
+        });
+        output.insert(
+            "xcp_on_can".to_string(),
+            json!({
+                "can_id_master": can_parameters.can_id_master.as_ref().map(|id| id.value),
+                "can_id_slave": can_parameters.can_id_slave.as_ref().map(|id| id.value),
+                "baudrate": can_parameters.baudrate.as_ref().map(|baudrate| baudrate.value),
+                "sample_point": can_parameters.sample_point.as_ref().map(|sp| sp.value),
+                "can_fd": can_fd,
+            }),
+        );
+    }
+    if let Some(xcp_on_flx) = &xcp.xcp_on_flx {
+        let flx_parameters = &xcp_on_flx.flx_parameters;
+        let mut buffers = Vec::new();
+        if let Some(buffer) = &flx_parameters.initial_cmd_buffer {
+            buffers.push(json!({"type": "initial_cmd", "id": buffer.flx_buf}));
+        }
+        if let Some(buffer) = &flx_parameters.initial_res_err_buffer {
+            buffers.push(json!({"type": "initial_res_err", "id": buffer.flx_buf}));
+        }
+        for buffer in &flx_parameters.pool_buffer {
+            buffers.push(json!({"type": "pool", "id": buffer.flx_buf}));
+        }
+        output.insert(
+            "xcp_on_flx".to_string(),
+            json!({
+                "fibex_file": flx_parameters.fibex_file,
+                "cluster_id": flx_parameters.cluster_id,
+                "nax": flx_parameters.nax,
+                "t1": flx_parameters.t1,
+                "header_nax": format!("{:?}", flx_parameters.header_nax),
+                "packet_alignment": format!("{:?}", flx_parameters.packet_alignment),
+                "buffers": buffers,
+            }),
+        );
+    }
+    if let Some(xcp_on_tcp_ip) = &xcp.xcp_on_tcp_ip {
+        let TCP_IP_Parameters {
+            host_name,
+            address,
+            ipv6,
+            port,
+            ..
+        } = &xcp_on_tcp_ip.tcp_ip_parameters;
+        output.insert(
+            "xcp_on_tcp_ip".to_string(),
+            xcp_on_ip_to_json(host_name, address, ipv6, *port),
+        );
+    }
+    if let Some(xcp_on_udp_ip) = &xcp.xcp_on_udp_ip {
+        let UDP_IP_Parameters {
+            host_name,
+            address,
+            ipv6,
+            port,
+            ..
+        } = &xcp_on_udp_ip.udp_ip_parameters;
+        output.insert(
+            "xcp_on_udp_ip".to_string(),
+            xcp_on_ip_to_json(host_name, address, ipv6, *port),
+        );
+    }
+    Value::Object(output)
+}
+
+fn xcp_on_ip_to_json(
+    host_name: &Option<HostName>,
+    address: &Option<Address2>,
+    ipv6: &Option<Ipv6>,
+    port: u16,
+) -> Value {
+    json!({
+        "host_name": host_name.as_ref().map(|host_name| &host_name.hostname),
+        "address": address.as_ref().map(|address| &address.address_v4),
+        "ipv6": ipv6.as_ref().map(|ipv6| &ipv6.address_v6),
+        "port": port,
+    })
+}
+
+impl XcpTemplate {
+    /// create a template with default values that are suitable for the transport layer
+    #[must_use]
+    pub fn new(transport: XcpTransport) -> Self {
+        let (max_cto, max_dto) = match transport {
+            XcpTransport::Can => (8, 8),
+            XcpTransport::Flx => (254, 254),
+            XcpTransport::Tcp | XcpTransport::Udp => (255, 1460),
+        };
+        Self {
+            transport,
+            timings: [1000, 2000, 0, 2000, 1000, 0, 0],
+            max_cto,
+            max_dto,
+            byte_order_msb_first: false,
+            daq_dynamic: true,
+            max_daq: 0,
+            max_event_channel: 0,
+            min_daq: 0,
+            max_odt_entry_size_daq: (max_dto - 1).min(255) as u8,
+        }
+    }
+
+    /// load settings from a config file
+    /// The file contains one setting per line in the form "KEY VALUE"; lines starting with '#' are comments
+    pub fn load_config(&mut self, filename: &OsStr) -> Result<(), String> {
+        let text = std::fs::read_to_string(filename).map_err(|err| {
+            format!(
+                "Error: could not read XCP config file {}: {err}",
+                filename.to_string_lossy()
+            )
+        })?;
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            self.set(key, value.trim()).map_err(|err| {
+                format!(
+                    "Error in XCP config file {}, line {}: {err}",
+                    filename.to_string_lossy(),
+                    idx + 1
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    /// change a single setting, given as "KEY=VALUE"
+    pub fn set_from_arg(&mut self, arg: &str) -> Result<(), String> {
+        let Some((key, value)) = arg.split_once('=') else {
+            return Err(format!(
+                "Error: invalid XCP parameter \"{arg}\", expected KEY=VALUE"
+            ));
+        };
+        self.set(key.trim(), value.trim())
+            .map_err(|err| format!("Error: invalid XCP parameter \"{arg}\": {err}"))
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "T1" | "T2" | "T3" | "T4" | "T5" | "T6" | "T7" => {
+                let idx = (key.as_bytes()[1] - b'1') as usize;
+                self.timings[idx] = parse_number(value)?;
+            }
+            "MAX_CTO" => self.max_cto = parse_number(value)?,
+            "MAX_DTO" => self.max_dto = parse_number(value)?,
+            "BYTE_ORDER" => match value {
+                "MSB_FIRST" => self.byte_order_msb_first = true,
+                "MSB_LAST" => self.byte_order_msb_first = false,
+                _ => return Err(format!("invalid byte order {value}")),
+            },
+            "DAQ_CONFIG_TYPE" => match value {
+                "DYNAMIC" => self.daq_dynamic = true,
+                "STATIC" => self.daq_dynamic = false,
+                _ => return Err(format!("invalid DAQ config type {value}")),
+            },
+            "MAX_DAQ" => self.max_daq = parse_number(value)?,
+            "MAX_EVENT_CHANNEL" => self.max_event_channel = parse_number(value)?,
+            "MIN_DAQ" => self.min_daq = parse_number(value)?,
+            "MAX_ODT_ENTRY_SIZE_DAQ" => self.max_odt_entry_size_daq = parse_number(value)?,
+            _ => return Err(format!("unknown setting {key}")),
+        }
+        Ok(())
+    }
+
+    fn build(&self) -> Xcp {
+        let [t1, t2, t3, t4, t5, t6, t7] = self.timings;
+        let byte_order = if self.byte_order_msb_first {
+            ByteOrderMsb::ByteOrderMsbFirst
+        } else {
+            ByteOrderMsb::ByteOrderMsbLast
+        };
+        let mut xcp = Xcp::new();
+        xcp.protocol_layer = Some(ProtocolLayer::new(
+            0x0100,
+            t1,
+            t2,
+            t3,
+            t4,
+            t5,
+            t6,
+            t7,
+            self.max_cto,
+            self.max_dto,
+            byte_order,
+            AddressGranularity::AddressGranularityByte,
+        ));
+        xcp.daq = Some(new_daq(
+            self.daq_dynamic,
+            self.max_daq,
+            self.max_event_channel,
+            self.min_daq,
+            self.max_odt_entry_size_daq,
+        ));
+        match self.transport {
+            XcpTransport::Can => {
+                xcp.xcp_on_can = Some(XcpOnCan::new(CAN_Parameters::new(0x0100)));
+            }
+            XcpTransport::Flx => {
+                xcp.xcp_on_flx = Some(XcpOnFlx::new(new_flx_parameters()));
+            }
+            XcpTransport::Tcp => {
+                xcp.xcp_on_tcp_ip = Some(XcpOnTcpIp::new(TCP_IP_Parameters::new(
+                    XCP_ON_ETH_VERSION,
+                    XCP_ON_ETH_DEFAULT_PORT,
+                )));
+            }
+            XcpTransport::Udp => {
+                xcp.xcp_on_udp_ip = Some(XcpOnUdpIp::new(UDP_IP_Parameters::new(
+                    XCP_ON_ETH_VERSION,
+                    XCP_ON_ETH_DEFAULT_PORT,
+                )));
+            }
+        }
+        xcp
+    }
+}
+
+fn new_daq(
+    dynamic: bool,
+    max_daq: u16,
+    max_event_channel: u16,
+    min_daq: u8,
+    max_odt_entry_size_daq: u8,
+) -> Daq {
+    Daq::new(
+        if dynamic {
+            AnonEnum3::Dynamic
+        } else {
+            AnonEnum3::Static
+        },
+        max_daq,
+        max_event_channel,
+        min_daq,
+        OptimisationType::OptimisationTypeDefault,
+        AddressExtension::AddressExtensionFree,
+        IdentificationFieldType::IdentificationFieldTypeAbsolute,
+        GranularityOdtEntrySizeDaq::GranularityOdtEntrySizeDaqByte,
+        max_odt_entry_size_daq,
+        OverloadIndication::NoOverloadIndication,
+    )
+}
+
+// parse a decimal or hexadecimal (0x prefix) number
+fn parse_number<T: TryFrom<u64>>(value: &str) -> Result<T, String> {
+    let num = if let Some(hexval) = value.strip_prefix("0x") {
+        u64::from_str_radix(hexval, 16)
+    } else {
+        value.parse::<u64>()
+    };
+    num.ok()
+        .and_then(|num| T::try_from(num).ok())
+        .ok_or_else(|| format!("invalid value {value}"))
+}
+
+/// create a complete XCP IF_DATA block in each module that does not have one yet
+pub fn create_xcp(a2l_file: &mut A2lFile, template: &XcpTemplate, log_msgs: &mut Vec<String>) {
+    for module in &mut a2l_file.project.module {
+        if has_xcp(module) {
+            log_msgs.push(format!(
+                "Module {} already contains XCP settings, which were not changed",
+                module.name
+            ));
+        } else if let Err(errmsg) = edit_xcp(module, |xcp| *xcp = template.build()) {
+            log_msgs.push(errmsg);
+        }
+    }
+}
+
+/// change the timing parameters T1 - T7 and MAX_CTO / MAX_DTO in the default PROTOCOL_LAYER of all modules
+/// each parameter is given as KEY=VALUE. The resulting values are validated before they are stored.
+pub fn edit_protocol_layer(
+    a2l_file: &mut A2lFile,
+    params: &[&str],
+    log_msgs: &mut Vec<String>,
+) -> Result<(), String> {
+    let mut parsed_params = Vec::new();
+    for param in params {
+        let Some((key, value)) = param.split_once('=') else {
+            return Err(format!(
+                "Error: invalid protocol layer parameter \"{param}\", expected KEY=VALUE"
+            ));
+        };
+        let key = key.trim();
+        let value: u16 = parse_number(value.trim())
+            .map_err(|err| format!("Error: invalid protocol layer parameter \"{param}\": {err}"))?;
+        if !matches!(
+            key,
+            "T1" | "T2" | "T3" | "T4" | "T5" | "T6" | "T7" | "MAX_CTO" | "MAX_DTO"
+        ) {
+            return Err(format!("Error: unknown protocol layer parameter {key}"));
+        }
+        if key == "MAX_CTO" && value > u16::from(u8::MAX) {
+            return Err("Error: MAX_CTO must not be larger than 255".to_string());
+        }
+        parsed_params.push((key, value));
+    }
+
+    // validate the new settings of all modules first, so that an error leaves the whole file unchanged
+    let mut new_protocol_layers = Vec::new();
+    for (idx, module) in a2l_file.project.module.iter().enumerate() {
+        if !has_xcp(module) {
+            continue;
+        }
+        let Some(xcp) = decode_xcp(module) else {
+            return Err(undecodable_xcp_error(&module.name));
+        };
+        let Some(protocol_layer) = &xcp.protocol_layer else {
+            log_msgs.push(format!(
+                "The XCP settings in module {} do not contain a PROTOCOL_LAYER",
+                module.name
+            ));
+            continue;
+        };
+        let mut new_protocol_layer = protocol_layer.clone();
+        for (key, value) in &parsed_params {
+            match *key {
+                "T1" => new_protocol_layer.t1 = *value,
+                "T2" => new_protocol_layer.t2 = *value,
+                "T3" => new_protocol_layer.t3 = *value,
+                "T4" => new_protocol_layer.t4 = *value,
+                "T5" => new_protocol_layer.t5 = *value,
+                "T6" => new_protocol_layer.t6 = *value,
+                "T7" => new_protocol_layer.t7 = *value,
+                "MAX_CTO" => new_protocol_layer.max_cto = *value as u8,
+                _ => new_protocol_layer.max_dto = *value,
+            }
+        }
+        // CAN limits the packet size, unless CAN FD is used
+        let max_can_packet = xcp.xcp_on_can.as_ref().map(|xcp_on_can| {
+            if xcp_on_can.can_parameters.can_fd.is_some() {
+                64
+            } else {
+                8
+            }
+        });
+        validate_protocol_layer(&new_protocol_layer, max_can_packet).map_err(|errmsg| {
+            format!(
+                "Error: invalid protocol layer settings for module {}: {errmsg}",
+                module.name
+            )
+        })?;
+        new_protocol_layers.push((idx, new_protocol_layer));
+    }
+
+    for (idx, new_protocol_layer) in new_protocol_layers {
+        edit_xcp(&mut a2l_file.project.module[idx], |xcp| {
+            xcp.protocol_layer = Some(new_protocol_layer);
+        })?;
+    }
+
+    Ok(())
+}
+
+// check the protocol layer parameters against the requirements of the declared XCP version
+fn validate_protocol_layer(
+    protocol_layer: &ProtocolLayer,
+    max_can_packet: Option<u16>,
+) -> Result<(), String> {
+    let version = protocol_layer.protocol_version;
+    if version >> 8 != 1 {
+        return Err(format!(
+            "unsupported XCP protocol layer version 0x{version:04X}"
+        ));
+    }
+    if protocol_layer.max_cto < 8 {
+        return Err(format!(
+            "MAX_CTO is {}, but XCP requires at least 8",
+            protocol_layer.max_cto
+        ));
+    }
+    if protocol_layer.max_dto < 8 {
+        return Err(format!(
+            "MAX_DTO is {}, but XCP requires at least 8",
+            protocol_layer.max_dto
+        ));
+    }
+    if protocol_layer.t1 == 0 {
+        return Err("the timeout T1 must not be zero".to_string());
+    }
+    // before XCP 1.1 the timeout T7 was not defined and should be zero
+    if version < 0x0101 && protocol_layer.t7 != 0 {
+        return Err(format!(
+            "T7 is not supported by XCP version {}.{}",
+            version >> 8,
+            version & 0xff
+        ));
+    }
+    if let Some(max_packet) = max_can_packet {
+        if u16::from(protocol_layer.max_cto) > max_packet || protocol_layer.max_dto > max_packet {
+            return Err(format!(
+                "MAX_CTO and MAX_DTO must not be larger than {max_packet} for XCP on CAN"
+            ));
+        }
+    }
+    Ok(())
+}
+
+// an XCP IF_DATA that can't be decoded also counts, so that the edit functions report it instead of skipping the module
+fn has_xcp(module: &Module) -> bool {
+    module.if_data.iter().any(|ifdata| {
+        matches!(ifdata_tag(ifdata), Some("XCP" | "XCPplus"))
+            || A2mlVector::load_from_ifdata(ifdata).is_some_and(|decoded| decoded.xcp.is_some())
+    })
+}
+
+/// set (Some) or clear (None) the name of the seed & key DLL in the XCP settings of all modules
+/// Without a transport layer, the default PROTOCOL_LAYER and all transport layer specific PROTOCOL_LAYERs are changed.
+/// If a transport layer is given, then only its PROTOCOL_LAYER is changed; it is created as a copy of the default if necessary.
+pub fn edit_seed_key(
+    a2l_file: &mut A2lFile,
+    seed_key_dll: Option<&str>,
+    transport: Option<XcpTransport>,
+    log_msgs: &mut Vec<String>,
+) {
+    for module in &mut a2l_file.project.module {
+        if !has_xcp(module) {
+            continue;
+        }
+        let module_name = module.name.clone();
+        let result = edit_xcp(module, |xcp| {
+            if let Some(transport) = transport {
+                let default_protocol_layer = xcp.protocol_layer.clone();
+                let transport_protocol_layer = match transport {
+                    XcpTransport::Can => xcp.xcp_on_can.as_mut().map(|tl| &mut tl.protocol_layer),
+                    XcpTransport::Flx => xcp.xcp_on_flx.as_mut().map(|tl| &mut tl.protocol_layer),
+                    XcpTransport::Tcp => {
+                        xcp.xcp_on_tcp_ip.as_mut().map(|tl| &mut tl.protocol_layer)
+                    }
+                    XcpTransport::Udp => {
+                        xcp.xcp_on_udp_ip.as_mut().map(|tl| &mut tl.protocol_layer)
+                    }
+                };
+                match transport_protocol_layer {
+                    Some(protocol_layer) => {
+                        if protocol_layer.is_none() {
+                            *protocol_layer = default_protocol_layer;
+                        }
+                        if let Some(protocol_layer) = protocol_layer {
+                            set_seed_key(protocol_layer, seed_key_dll);
+                        } else {
+                            log_msgs.push(format!("The XCP settings in module {module_name} do not contain a PROTOCOL_LAYER"));
+                        }
+                    }
+                    None => log_msgs.push(format!(
+                        "The XCP settings in module {module_name} do not contain the transport layer {transport:?}"
+                    )),
+                }
+            } else {
+                let protocol_layers = [&mut xcp.protocol_layer]
+                    .into_iter()
+                    .chain(xcp.xcp_on_can.as_mut().map(|tl| &mut tl.protocol_layer))
+                    .chain(xcp.xcp_on_flx.as_mut().map(|tl| &mut tl.protocol_layer))
+                    .chain(xcp.xcp_on_tcp_ip.as_mut().map(|tl| &mut tl.protocol_layer))
+                    .chain(xcp.xcp_on_udp_ip.as_mut().map(|tl| &mut tl.protocol_layer));
+                for protocol_layer in protocol_layers.flatten() {
+                    set_seed_key(protocol_layer, seed_key_dll);
+                }
+            }
+        });
+        if let Err(errmsg) = result {
+            log_msgs.push(errmsg);
+        }
+    }
+}
+
+fn set_seed_key(protocol_layer: &mut ProtocolLayer, seed_key_dll: Option<&str>) {
+    protocol_layer.seed_and_key_external_function =
+        seed_key_dll.map(|dll| SeedAndKeyExternalFunction::new(dll.to_string()));
+}
+
+/// modifications of the DAQ events
+#[derive(Debug)]
+pub enum DaqEventEdit {
+    Add { name: String, cycle: (u8, u8) },
+    Rename { name: String, new_name: String },
+    Retime { name: String, cycle: (u8, u8) },
+}
+
+impl DaqEventEdit {
+    /// build the edits from lists of (name, value) pairs, as they are given on the command line:
+    /// (name, cycle time) to add events, (name, new name) to rename them, and (name, cycle time) to retime them
+    pub fn from_pairs(add: &[&str], rename: &[&str], retime: &[&str]) -> Result<Vec<Self>, String> {
+        let mut edits = Vec::new();
+        for pair in add.chunks_exact(2) {
+            edits.push(DaqEventEdit::Add {
+                name: pair[0].to_string(),
+                cycle: parse_cycle_time(pair[1])?,
+            });
+        }
+        for pair in rename.chunks_exact(2) {
+            edits.push(DaqEventEdit::Rename {
+                name: pair[0].to_string(),
+                new_name: pair[1].to_string(),
+            });
+        }
+        for pair in retime.chunks_exact(2) {
+            edits.push(DaqEventEdit::Retime {
+                name: pair[0].to_string(),
+                cycle: parse_cycle_time(pair[1])?,
+            });
+        }
+        Ok(edits)
+    }
+}
+
+// XCP time units: the value is the exponent of the unit, starting at 1ns
+const XCP_TIME_UNITS: [&str; 10] = [
+    "1ns", "10ns", "100ns", "1us", "10us", "100us", "1ms", "10ms", "100ms", "1s",
+];
+const XCP_TIME_UNIT_1MS: u8 = 6;
+
+/// parse a cycle time like "10ms", "500us" or "0" (not cyclic) into the XCP representation (TIME_CYCLE, TIME_UNIT)
+/// the unit given by the user is kept unless TIME_CYCLE would not fit into a byte
+pub fn parse_cycle_time(text: &str) -> Result<(u8, u8), String> {
+    let text = text.trim();
+    let (number, mut unit) = if let Some(number) = text.strip_suffix("ns") {
+        (number, 0)
+    } else if let Some(number) = text.strip_suffix("us") {
+        (number, 3)
+    } else if let Some(number) = text.strip_suffix("ms") {
+        (number, XCP_TIME_UNIT_1MS)
+    } else if let Some(number) = text.strip_suffix('s') {
+        (number, 9)
+    } else {
+        (text, XCP_TIME_UNIT_1MS)
+    };
+    let mut value: u64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("Error: invalid cycle time \"{text}\""))?;
+    if value == 0 {
+        return Ok((0, XCP_TIME_UNIT_1MS));
+    }
+
+    while value > u64::from(u8::MAX)
+        && value.is_multiple_of(10)
+        && (unit as usize) < XCP_TIME_UNITS.len() - 1
+    {
+        value /= 10;
+        unit += 1;
+    }
+    if value > u64::from(u8::MAX) {
+        return Err(format!(
+            "Error: the cycle time \"{text}\" cannot be represented in XCP"
+        ));
+    }
+    Ok((value as u8, unit))
+}
+
+fn format_cycle_time(time_cycle: u8, time_unit: u8) -> String {
+    if time_cycle == 0 {
+        "not cyclic".to_string()
+    } else if let Some(unit) = XCP_TIME_UNITS.get(time_unit as usize) {
+        format!("{time_cycle} * {unit}")
+    } else {
+        format!("{time_cycle} * (invalid unit {time_unit})")
+    }
+}
+
+/// add, rename, and retime the DAQ events in the XCP settings of all modules
+/// Modules without XCP settings are skipped. A DAQ section is created if events are added and it does not exist yet
+pub fn edit_daq_events(a2l_file: &mut A2lFile, edits: &[DaqEventEdit], log_msgs: &mut Vec<String>) {
+    for module in &mut a2l_file.project.module {
+        if !has_xcp(module) {
+            continue;
+        }
+        let module_name = module.name.clone();
+        let result = edit_xcp(module, |xcp| {
+            for edit in edits {
+                match edit {
+                    DaqEventEdit::Add { name, cycle } => {
+                        let daq = xcp.daq.get_or_insert_with(|| new_daq(true, 0, 0, 0, 0xff));
+                        if daq
+                            .event
+                            .iter()
+                            .any(|event| &event.event_channel_name == name)
+                        {
+                            log_msgs.push(format!(
+                                "Event {name} already exists in module {module_name}"
+                            ));
+                            continue;
+                        }
+                        let event_channel_number = daq
+                            .event
+                            .iter()
+                            .map(|event| event.event_channel_number + 1)
+                            .max()
+                            .unwrap_or(0);
+                        // the short name is limited to 8 characters
+                        let short_name: String = name.chars().take(8).collect();
+                        daq.event.push(Event::new(
+                            name.clone(),
+                            short_name,
+                            event_channel_number,
+                            AnonEnum4::Daq,
+                            0xff,
+                            cycle.0,
+                            cycle.1,
+                            0,
+                        ));
+                    }
+                    DaqEventEdit::Rename { name, new_name } => {
+                        if let Some(event) = find_daq_event(xcp, name) {
+                            event.event_channel_name.clone_from(new_name);
+                            event.event_channel_short_name = new_name.chars().take(8).collect();
+                        } else {
+                            log_msgs.push(format!(
+                                "Event {name} does not exist in module {module_name}"
+                            ));
+                        }
+                    }
+                    DaqEventEdit::Retime { name, cycle } => {
+                        if let Some(event) = find_daq_event(xcp, name) {
+                            event.time_cycle = cycle.0;
+                            event.time_unit = cycle.1;
+                        } else {
+                            log_msgs.push(format!(
+                                "Event {name} does not exist in module {module_name}"
+                            ));
+                        }
+                    }
+                }
+            }
+            if let Some(daq) = &mut xcp.daq {
+                update_max_event_channel(daq);
+            }
+        });
+        if let Err(errmsg) = result {
+            log_msgs.push(errmsg);
+        }
+    }
+}
+
+fn find_daq_event<'a>(xcp: &'a mut Xcp, name: &str) -> Option<&'a mut Event> {
+    xcp.daq
+        .iter_mut()
+        .flat_map(|daq| daq.event.iter_mut())
+        .find(|event| event.event_channel_name == name)
+}
+
+/// a DAQ event as defined in the event config of the firmware
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DaqEventDefinition {
+    pub name: String,
+    pub channel: u16,
+    pub cycle: (u8, u8),
+    pub priority: u8,
+}
+
+/// load the DAQ event definitions from a YAML file
+///
+/// The events are read from the list in the top level key `events`:
+/// ```yaml
+/// events:
+///   - name: task_10ms
+///     channel: 0
+///     cycle: 10ms
+///     priority: 1
+/// ```
+/// The cycle time is given like in `--xcp-add-event`, and the priority is optional.
+/// All other keys are ignored, so that the file can contain additional settings for the code generation.
+///
+/// # Errors
+///
+/// Returns an error message if the file cannot be read or if an event is incomplete or invalid
+pub fn load_daq_event_config(filename: &OsStr) -> Result<Vec<DaqEventDefinition>, String> {
+    let text = std::fs::read_to_string(filename).map_err(|err| {
+        format!(
+            "Error: could not read DAQ event config {}: {err}",
+            filename.to_string_lossy()
+        )
+    })?;
+    parse_daq_event_config(&text).map_err(|err| {
+        format!(
+            "Error in DAQ event config {}, {err}",
+            filename.to_string_lossy()
+        )
+    })
+}
+
+fn parse_daq_event_config(text: &str) -> Result<Vec<DaqEventDefinition>, String> {
+    let documents = YamlLoader::load_from_str(text).map_err(|err| err.to_string())?;
+    let Some(items) = documents
+        .first()
+        .and_then(|document| document["events"].as_vec())
+    else {
+        return Err("expected a list of events in the key \"events\"".to_string());
+    };
+
+    let mut events = Vec::new();
+    for (idx, item) in items.iter().enumerate() {
+        let Some(name) = yaml_scalar(&item["name"]) else {
+            return Err(format!("event {} has no name", idx + 1));
+        };
+        let channel = match &item["channel"] {
+            Yaml::Integer(channel) => u16::try_from(*channel)
+                .map_err(|_| format!("invalid channel {channel} of event {name}"))?,
+            Yaml::BadValue | Yaml::Null => return Err(format!("the event {name} has no channel")),
+            _ => return Err(format!("invalid channel of event {name}")),
+        };
+        let cycle = match &item["cycle"] {
+            Yaml::BadValue | Yaml::Null => (0, XCP_TIME_UNIT_1MS),
+            value => yaml_scalar(value)
+                .ok_or_else(|| "invalid cycle time".to_string())
+                .and_then(|cycle| parse_cycle_time(&cycle))
+                .map_err(|err| format!("event {name}: {err}"))?,
+        };
+        let priority = match &item["priority"] {
+            Yaml::BadValue | Yaml::Null => 0,
+            Yaml::Integer(priority) => u8::try_from(*priority)
+                .map_err(|_| format!("invalid priority {priority} of event {name}"))?,
+            _ => return Err(format!("invalid priority of event {name}")),
+        };
+        if events
+            .iter()
+            .any(|event: &DaqEventDefinition| event.channel == channel || event.name == name)
+        {
+            return Err(format!(
+                "the event {name} or its channel {channel} is defined twice"
+            ));
+        }
+        events.push(DaqEventDefinition {
+            name,
+            channel,
+            cycle,
+            priority,
+        });
+    }
+    Ok(events)
+}
+
+// get the text of a scalar value; the YAML loader converts unquoted numbers, e.g. a cycle time of 0
+fn yaml_scalar(value: &Yaml) -> Option<String> {
+    match value {
+        Yaml::String(text) | Yaml::Real(text) => Some(text.clone()),
+        Yaml::Integer(number) => Some(number.to_string()),
+        Yaml::Boolean(flag) => Some(flag.to_string()),
+        _ => None,
+    }
+}
+
+/// replace the DAQ events in the XCP settings of all modules with the given definitions
+///
+/// Existing events are matched by their channel number, so that their additional settings are kept.
+/// Events that are not part of the definitions are removed. Modules without XCP settings are skipped.
+/// Returns the number of events that were added, changed or removed.
+pub fn sync_daq_events(
+    a2l_file: &mut A2lFile,
+    definitions: &[DaqEventDefinition],
+    log_msgs: &mut Vec<String>,
+) -> usize {
+    let mut count = 0;
+    for module in &mut a2l_file.project.module {
+        if !has_xcp(module) {
+            continue;
+        }
+        let module_name = module.name.clone();
+        let result = edit_xcp(module, |xcp| {
+            if xcp.daq.is_none() && definitions.is_empty() {
+                return;
+            }
+            let daq = xcp.daq.get_or_insert_with(|| new_daq(true, 0, 0, 0, 0xff));
+            let mut old_events = std::mem::take(&mut daq.event);
+            for definition in definitions {
+                let short_name: String = definition.name.chars().take(8).collect();
+                if let Some(pos) = old_events
+                    .iter()
+                    .position(|event| event.event_channel_number == definition.channel)
+                {
+                    let mut event = old_events.remove(pos);
+                    if event.event_channel_name != definition.name
+                        || event.time_cycle != definition.cycle.0
+                        || event.time_unit != definition.cycle.1
+                        || event.priority != definition.priority
+                    {
+                        count += 1;
+                    }
+                    event.event_channel_name.clone_from(&definition.name);
+                    event.event_channel_short_name = short_name;
+                    event.time_cycle = definition.cycle.0;
+                    event.time_unit = definition.cycle.1;
+                    event.priority = definition.priority;
+                    daq.event.push(event);
+                } else {
+                    daq.event.push(Event::new(
+                        definition.name.clone(),
+                        short_name,
+                        definition.channel,
+                        AnonEnum4::Daq,
+                        0xff,
+                        definition.cycle.0,
+                        definition.cycle.1,
+                        definition.priority,
+                    ));
+                    count += 1;
+                }
+            }
+            for event in old_events {
+                log_msgs.push(format!(
+                    "Removed event {} (channel {}) from module {module_name}",
+                    event.event_channel_name, event.event_channel_number
+                ));
+                count += 1;
+            }
+            daq.event.sort_by_key(|event| event.event_channel_number);
+            update_max_event_channel(daq);
+        });
+        if let Err(errmsg) = result {
+            log_msgs.push(errmsg);
+        }
+    }
+    count
+}
+
+/// result of the comparison of MAX_REFRESH with the DAQ events
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MaxRefreshResult {
+    /// number of objects whose MAX_REFRESH does not match any cyclic DAQ event
+    pub mismatched: usize,
+    /// number of mismatched objects whose MAX_REFRESH was changed to the closest DAQ event
+    pub fixed: usize,
+}
+
+// the periods of the time based MAX_REFRESH scaling units 0 (1us) to 10 (1 day) in ns
+const MAX_REFRESH_UNITS_NS: [u64; 11] = [
+    1_000,
+    10_000,
+    100_000,
+    1_000_000,
+    10_000_000,
+    100_000_000,
+    1_000_000_000,
+    10_000_000_000,
+    60_000_000_000,
+    3_600_000_000_000,
+    86_400_000_000_000,
+];
+
+/// compare the MAX_REFRESH of all MEASUREMENTs, CHARACTERISTICs and AXIS_PTS with the cycle times of the DAQ events in the XCP settings
+///
+/// Only time based MAX_REFRESH values are checked, and modules without cyclic DAQ events are skipped.
+/// If `fix` is set, a mismatched MAX_REFRESH is replaced by the cycle time of the closest DAQ event.
+pub fn check_max_refresh(
+    a2l_file: &mut A2lFile,
+    fix: bool,
+    log_msgs: &mut Vec<String>,
+) -> MaxRefreshResult {
+    let mut result = MaxRefreshResult::default();
+    for module in &mut a2l_file.project.module {
+        let event_periods = cyclic_event_periods(module);
+        if event_periods.is_empty() {
+            continue;
+        }
+        for measurement in &mut module.measurement {
+            let line = measurement.get_line();
+            check_max_refresh_item(
+                "MEASUREMENT",
+                &measurement.name,
+                line,
+                &mut measurement.max_refresh,
+                &event_periods,
+                fix,
+                &mut result,
+                log_msgs,
+            );
+        }
+        for characteristic in &mut module.characteristic {
+            let line = characteristic.get_line();
+            check_max_refresh_item(
+                "CHARACTERISTIC",
+                &characteristic.name,
+                line,
+                &mut characteristic.max_refresh,
+                &event_periods,
+                fix,
+                &mut result,
+                log_msgs,
+            );
+        }
+        for axis_pts in &mut module.axis_pts {
+            let line = axis_pts.get_line();
+            check_max_refresh_item(
+                "AXIS_PTS",
+                &axis_pts.name,
+                line,
+                &mut axis_pts.max_refresh,
+                &event_periods,
+                fix,
+                &mut result,
+                log_msgs,
+            );
+        }
+    }
+    result
+}
+
+// get the cycle times of all cyclic DAQ events of the module in ns
+fn cyclic_event_periods(module: &Module) -> Vec<(String, u64)> {
+    module
+        .if_data
+        .iter()
+        .filter_map(|ifdata| A2mlVector::load_from_ifdata(ifdata)?.xcp?.daq)
+        .flat_map(|daq| daq.event)
+        .filter(|event| event.time_cycle > 0 && (event.time_unit as usize) < XCP_TIME_UNITS.len())
+        .map(|event| {
+            let period = u64::from(event.time_cycle) * 10u64.pow(u32::from(event.time_unit));
+            (event.event_channel_name, period)
+        })
+        .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn check_max_refresh_item(
+    blockname: &str,
+    name: &str,
+    line: u32,
+    max_refresh: &mut Option<MaxRefresh>,
+    event_periods: &[(String, u64)],
+    fix: bool,
+    result: &mut MaxRefreshResult,
+    log_msgs: &mut Vec<String>,
+) {
+    let Some(mr) = max_refresh else {
+        return;
+    };
+    // MAX_REFRESH values that are based on angles or events cannot be compared with the cycle times
+    let Some(unit_period) = MAX_REFRESH_UNITS_NS.get(mr.scaling_unit as usize) else {
+        return;
+    };
+    let period = unit_period * u64::from(mr.rate);
+    if event_periods
+        .iter()
+        .any(|(_, event_period)| *event_period == period)
+    {
+        return;
+    }
+    result.mismatched += 1;
+
+    // find the event whose cycle time is closest to the MAX_REFRESH, measured as a ratio
+    let distance = |event_period: u64| {
+        let (a, b) = (event_period.max(1) as f64, period.max(1) as f64);
+        (a / b).ln().abs()
+    };
+    let closest = event_periods
+        .iter()
+        .min_by(|(_, p1), (_, p2)| distance(*p1).total_cmp(&distance(*p2)))
+        .expect("event_periods is not empty");
+    let replacement = fix.then(|| max_refresh_from_period(closest.1)).flatten();
+    if let Some((scaling_unit, rate)) = replacement {
+        log_msgs.push(format!(
+            "{blockname} {name} on line {line}: MAX_REFRESH {} {} does not match any DAQ event; changed to {scaling_unit} {rate} (event {})",
+            mr.scaling_unit, mr.rate, closest.0
+        ));
+        mr.scaling_unit = scaling_unit;
+        mr.rate = rate;
+        result.fixed += 1;
+    } else {
+        log_msgs.push(format!(
+            "{blockname} {name} on line {line}: MAX_REFRESH {} {} does not match any DAQ event; the closest event is {} with a cycle time of {}",
+            mr.scaling_unit, mr.rate, closest.0, format_period(closest.1)
+        ));
+    }
+}
+
+// represent a period in ns as a MAX_REFRESH (scaling unit, rate), using the largest possible unit
+fn max_refresh_from_period(period: u64) -> Option<(u16, u32)> {
+    MAX_REFRESH_UNITS_NS
+        .iter()
+        .enumerate()
+        .rev()
+        .find(|(_, unit_period)| period.is_multiple_of(**unit_period))
+        .and_then(|(idx, unit_period)| {
+            Some((idx as u16, u32::try_from(period / unit_period).ok()?))
+        })
+}
+
+fn format_period(period: u64) -> String {
+    let mut value = period;
+    let mut unit = 0;
+    while value.is_multiple_of(10) && value > 0 && unit < XCP_TIME_UNITS.len() - 1 {
+        value /= 10;
+        unit += 1;
+    }
+    // shift the value back up to the closest named unit: ns, us, ms or s
+    while unit % 3 != 0 {
+        value *= 10;
+        unit -= 1;
+    }
+    format!("{value}{}", &XCP_TIME_UNITS[unit][1..])
+}
+
+/// set the XCP on CAN parameters in all modules
+/// the XCP IF_DATA and the XCP_ON_CAN block inside it are created if they don't exist yet
+///
+/// # Errors
+///
+/// Returns an error message if an existing XCP IF_DATA does not match the built-in A2ML specification
+pub fn edit_xcp_on_can(a2l_file: &mut A2lFile, settings: &XcpOnCanSettings) -> Result<(), String> {
+    for module in &mut a2l_file.project.module {
+        edit_xcp(module, |xcp| {
+            let xcp_on_can = xcp
+                .xcp_on_can
+                .get_or_insert_with(|| XcpOnCan::new(CAN_Parameters::new(0x0100)));
+            let can_parameters = &mut xcp_on_can.can_parameters;
+            // CAN ids are conventionally written as hex numbers
+            if let Some(can_id_master) = settings.can_id_master {
+                let mut item = CanIdMaster::new(can_id_master);
+                item.get_layout_mut().item_location.0 .1 = true;
+                can_parameters.can_id_master = Some(item);
+            }
+            if let Some(can_id_slave) = settings.can_id_slave {
+                let mut item = CanIdSlave::new(can_id_slave);
+                item.get_layout_mut().item_location.0 .1 = true;
+                can_parameters.can_id_slave = Some(item);
+            }
+            if let Some(baudrate) = settings.baudrate {
+                can_parameters.baudrate = Some(Baudrate::new(baudrate));
+            }
+            if let Some(sample_point) = settings.sample_point {
+                can_parameters.sample_point = Some(SamplePoint::new(sample_point));
+            }
+        })?;
+    }
+    Ok(())
+}
+
+/// set the XCP on FlexRay parameters in all modules
+/// the XCP IF_DATA and the XCP_ON_FLX block inside it are created if they don't exist yet
+///
+/// # Errors
+///
+/// Returns an error message if an existing XCP IF_DATA does not match the built-in A2ML specification
+pub fn edit_xcp_on_flx(a2l_file: &mut A2lFile, settings: &XcpOnFlxSettings) -> Result<(), String> {
+    for module in &mut a2l_file.project.module {
+        edit_xcp(module, |xcp| {
+            let xcp_on_flx = xcp
+                .xcp_on_flx
+                .get_or_insert_with(|| XcpOnFlx::new(new_flx_parameters()));
+            let flx_parameters = &mut xcp_on_flx.flx_parameters;
+            if let Some(fibex_file) = &settings.fibex_file {
+                flx_parameters.fibex_file.clone_from(fibex_file);
+            }
+            if let Some(cluster_id) = &settings.cluster_id {
+                flx_parameters.cluster_id.clone_from(cluster_id);
+            }
+            if let Some(nax) = settings.nax {
+                flx_parameters.nax = nax;
+            }
+            if let Some(t1) = settings.t1 {
+                flx_parameters.t1 = t1;
+            }
+        })?;
+    }
+    Ok(())
+}
+
+// the buffers can't be configured from the command line, so a new XCP_ON_FLX only contains the basic settings
+fn new_flx_parameters() -> FLX_Parameters {
+    FLX_Parameters::new(
+        0x0100,
+        1000,
+        String::new(),
+        String::new(),
+        0,
+        HeaderNax::HeaderNax,
+        PacketAlignment::PacketAlignment8,
+    )
+}
+
+/// set the XCP on Ethernet parameters in all modules
+/// If a protocol is given, then an existing block for the other protocol is converted. Otherwise all existing
+/// XCP_ON_TCP_IP and XCP_ON_UDP_IP blocks are modified, and a new XCP_ON_UDP_IP block is created if there are none.
+///
+/// # Errors
+///
+/// Returns an error message if an existing XCP IF_DATA does not match the built-in A2ML specification
+pub fn edit_xcp_on_eth(a2l_file: &mut A2lFile, settings: &XcpOnEthSettings) -> Result<(), String> {
+    for module in &mut a2l_file.project.module {
+        edit_xcp(module, |xcp| {
+            match settings.protocol {
+                Some(XcpEthProtocol::Tcp) => {
+                    if xcp.xcp_on_tcp_ip.is_none() {
+                        let mut tcp_ip_parameters =
+                            TCP_IP_Parameters::new(XCP_ON_ETH_VERSION, XCP_ON_ETH_DEFAULT_PORT);
+                        if let Some(xcp_on_udp_ip) = xcp.xcp_on_udp_ip.take() {
+                            let udp_ip_parameters = xcp_on_udp_ip.udp_ip_parameters;
+                            tcp_ip_parameters.port = udp_ip_parameters.port;
+                            tcp_ip_parameters.host_name = udp_ip_parameters.host_name;
+                            tcp_ip_parameters.address = udp_ip_parameters.address;
+                            tcp_ip_parameters.ipv6 = udp_ip_parameters.ipv6;
+                        }
+                        xcp.xcp_on_tcp_ip = Some(XcpOnTcpIp::new(tcp_ip_parameters));
+                    }
+                    xcp.xcp_on_udp_ip = None;
+                }
+                Some(XcpEthProtocol::Udp) => {
+                    if xcp.xcp_on_udp_ip.is_none() {
+                        let mut udp_ip_parameters =
+                            UDP_IP_Parameters::new(XCP_ON_ETH_VERSION, XCP_ON_ETH_DEFAULT_PORT);
+                        if let Some(xcp_on_tcp_ip) = xcp.xcp_on_tcp_ip.take() {
+                            let tcp_ip_parameters = xcp_on_tcp_ip.tcp_ip_parameters;
+                            udp_ip_parameters.port = tcp_ip_parameters.port;
+                            udp_ip_parameters.host_name = tcp_ip_parameters.host_name;
+                            udp_ip_parameters.address = tcp_ip_parameters.address;
+                            udp_ip_parameters.ipv6 = tcp_ip_parameters.ipv6;
+                        }
+                        xcp.xcp_on_udp_ip = Some(XcpOnUdpIp::new(udp_ip_parameters));
+                    }
+                    xcp.xcp_on_tcp_ip = None;
+                }
+                None => {
+                    if xcp.xcp_on_tcp_ip.is_none() && xcp.xcp_on_udp_ip.is_none() {
+                        xcp.xcp_on_udp_ip = Some(XcpOnUdpIp::new(UDP_IP_Parameters::new(
+                            XCP_ON_ETH_VERSION,
+                            XCP_ON_ETH_DEFAULT_PORT,
+                        )));
+                    }
+                }
+            }
+
+            if let Some(xcp_on_tcp_ip) = &mut xcp.xcp_on_tcp_ip {
+                let TCP_IP_Parameters {
+                    host_name,
+                    address,
+                    ipv6,
+                    port,
+                    ..
+                } = &mut xcp_on_tcp_ip.tcp_ip_parameters;
+                set_xcp_on_ip_common(host_name, address, ipv6, port, settings);
+            }
+            if let Some(xcp_on_udp_ip) = &mut xcp.xcp_on_udp_ip {
+                let UDP_IP_Parameters {
+                    host_name,
+                    address,
+                    ipv6,
+                    port,
+                    ..
+                } = &mut xcp_on_udp_ip.udp_ip_parameters;
+                set_xcp_on_ip_common(host_name, address, ipv6, port, settings);
+            }
+        })?;
+    }
+    Ok(())
+}
+
+// the address can be an IPv4 address, an IPv6 address or a host name. Only one of these should be present.
+fn set_xcp_on_ip_common(
+    host_name: &mut Option<HostName>,
+    address: &mut Option<Address2>,
+    ipv6: &mut Option<Ipv6>,
+    port: &mut u16,
+    settings: &XcpOnEthSettings,
+) {
+    if let Some(new_address) = &settings.address {
+        *host_name = None;
+        *address = None;
+        *ipv6 = None;
+        match new_address.parse::<std::net::IpAddr>() {
+            Ok(std::net::IpAddr::V4(_)) => *address = Some(Address2::new(new_address.clone())),
+            Ok(std::net::IpAddr::V6(_)) => *ipv6 = Some(Ipv6::new(new_address.clone())),
+            Err(_) => *host_name = Some(HostName::new(new_address.clone())),
+        }
+    }
+    if let Some(new_port) = settings.port {
+        *port = new_port;
+    }
+}
+
+// decode the XCP IF_DATA of a module, modify it and store the result
+// if the module doesn't have an XCP IF_DATA yet, then a new one is created.
+// An existing XCP IF_DATA that does not match the built-in A2ML specification can't be modified; this is an error
+fn edit_xcp<F>(module: &mut Module, edit_fn: F) -> Result<(), String>
+where
+    F: FnOnce(&mut Xcp),
+{
+    let existing = module.if_data.iter().enumerate().find_map(|(idx, ifdata)| {
+        A2mlVector::load_from_ifdata(ifdata)
+            .filter(|decoded_ifdata| decoded_ifdata.xcp.is_some())
+            .map(|decoded_ifdata| (idx, decoded_ifdata))
+    });
+    let (idx, mut decoded_ifdata) = if let Some(existing) = existing {
+        existing
+    } else {
+        if module
+            .if_data
+            .iter()
+            .any(|ifdata| matches!(ifdata_tag(ifdata), Some("XCP" | "XCPplus")))
+        {
+            return Err(undecodable_xcp_error(&module.name));
+        }
+        let mut decoded_ifdata = A2mlVector::new();
+        decoded_ifdata.xcp = Some(Xcp::new());
+        module.if_data.push(IfData::new());
+        (module.if_data.len() - 1, decoded_ifdata)
+    };
+
+    if let Some(xcp) = &mut decoded_ifdata.xcp {
+        edit_fn(xcp);
+    }
+    decoded_ifdata.store_to_ifdata(&mut module.if_data[idx]);
+
+    // other tools can only interpret the new IF_DATA if the file contains an A2ML specification
+    if module.a2ml.is_none() {
+        module.a2ml = Some(A2ml::new(A2MLVECTOR_TEXT.to_string()));
+    }
+    Ok(())
+}
+
+// read-only access to the decoded XCP settings of a module
+fn decode_xcp(module: &Module) -> Option<Xcp> {
+    module
+        .if_data
+        .iter()
+        .find_map(|ifdata| A2mlVector::load_from_ifdata(ifdata).and_then(|decoded| decoded.xcp))
+}
+
+fn undecodable_xcp_error(module_name: &str) -> String {
+    format!(
+        "Error: the XCP IF_DATA in module {module_name} does not match the built-in A2ML specification and can't be modified"
+    )
+}
+
+// MAX_EVENT_CHANNEL is the number of event channels, so it must be larger than the highest channel number
+fn update_max_event_channel(daq: &mut Daq) {
+    if let Some(max_channel) = daq
+        .event
+        .iter()
+        .map(|event| event.event_channel_number)
+        .max()
+    {
+        daq.max_event_channel = daq.max_event_channel.max(max_channel.saturating_add(1));
+    }
+}
+
 fn print_xcp(xcp: &Xcp) {
+    if let Some(protocol_layer) = &xcp.protocol_layer {
+        print_protocol_layer(protocol_layer);
+    }
+    print_seed_key(&xcp.protocol_layer, "  ");
+
+    if let Some(daq) = &xcp.daq {
+        print_daq(daq);
+    }
+
     if let Some(xcp_on_can) = &xcp.xcp_on_can {
         print_xcp_on_can(xcp_on_can);
     }
@@ -53,8 +1504,77 @@ fn print_xcp(xcp: &Xcp) {
     }
 }
 
+fn print_daq(daq: &Daq) {
+    println!(
+        "  DAQ: {:?}, max DAQ lists: {}, max event channels: {}, min DAQ: {}",
+        daq.anon_enum3, daq.max_daq, daq.max_event_channel, daq.min_daq
+    );
+    for daq_list in &daq.daq_list {
+        print!("    DAQ list {}", daq_list.daq_list_number);
+        if let Some(daq_list_type) = &daq_list.daq_list_type {
+            print!(", type: {:?}", daq_list_type.anon_enum4);
+        }
+        if let Some(max_odt) = &daq_list.max_odt {
+            print!(", max ODT: {}", max_odt.item);
+        }
+        if let Some(event_fixed) = &daq_list.event_fixed {
+            print!(", fixed event: {}", event_fixed.item);
+        }
+        println!();
+    }
+    for event in &daq.event {
+        println!(
+            "    Event {}: {} ({}), {:?}, cycle: {}, max DAQ lists: {}, priority: {}",
+            event.event_channel_number,
+            event.event_channel_name,
+            event.event_channel_short_name,
+            event.anon_enum4,
+            format_cycle_time(event.time_cycle, event.time_unit),
+            event.max_daq_list,
+            event.priority
+        );
+    }
+}
+
+fn print_protocol_layer(protocol_layer: &ProtocolLayer) {
+    println!(
+        "  Protocol layer: version {}.{}",
+        protocol_layer.protocol_version >> 8,
+        protocol_layer.protocol_version & 0xff
+    );
+    println!(
+        "    T1: {}, T2: {}, T3: {}, T4: {}, T5: {}, T6: {}, T7: {} (ms)",
+        protocol_layer.t1,
+        protocol_layer.t2,
+        protocol_layer.t3,
+        protocol_layer.t4,
+        protocol_layer.t5,
+        protocol_layer.t6,
+        protocol_layer.t7
+    );
+    println!(
+        "    MAX_CTO: {}, MAX_DTO: {}, {:?}, {:?}",
+        protocol_layer.max_cto,
+        protocol_layer.max_dto,
+        protocol_layer.byte_order_msb,
+        protocol_layer.address_granularity
+    );
+}
+
+// the seed & key DLL is printed with the indentation of the enclosing block
+fn print_seed_key(protocol_layer: &Option<ProtocolLayer>, indent: &str) {
+    if let Some(ProtocolLayer {
+        seed_and_key_external_function: Some(seed_key),
+        ..
+    }) = protocol_layer
+    {
+        println!("{indent}Seed & Key: {}", seed_key.funcname);
+    }
+}
+
 fn print_xcp_on_can(xcp_on_can: &XcpOnCan) {
     println!("  XCP on CAN:");
+    print_seed_key(&xcp_on_can.protocol_layer, "    ");
     if let Some(can_id_master) = &xcp_on_can.can_parameters.can_id_master {
         println!(
             "    CAN id master: 0x{:X}",
@@ -83,9 +1603,19 @@ fn print_xcp_on_can(xcp_on_can: &XcpOnCan) {
 
 fn print_xcp_on_flx(xcp_on_flx: &XcpOnFlx) {
     println!("  XCP on Flexray");
+    print_seed_key(&xcp_on_flx.protocol_layer, "    ");
     if !xcp_on_flx.flx_parameters.fibex_file.is_empty() {
         println!("    fibex file: {}", xcp_on_flx.flx_parameters.fibex_file);
     }
+    if !xcp_on_flx.flx_parameters.cluster_id.is_empty() {
+        println!("    cluster id: {}", xcp_on_flx.flx_parameters.cluster_id);
+    }
+    println!("    NAX: {}", xcp_on_flx.flx_parameters.nax);
+    println!("    T1: {} ms", xcp_on_flx.flx_parameters.t1);
+    println!(
+        "    header: {:?}, packet alignment: {:?}",
+        xcp_on_flx.flx_parameters.header_nax, xcp_on_flx.flx_parameters.packet_alignment
+    );
 
     if let Some(buffer) = &xcp_on_flx.flx_parameters.initial_cmd_buffer {
         let InitialCmdBuffer {
@@ -281,6 +1811,7 @@ fn print_xcp_on_tcp_ip(xcp_on_tcp_ip: &XcpOnTcpIp) {
         ..
     } = xcp_on_tcp_ip;
     println!("  XCP on TCP/IP");
+    print_seed_key(&xcp_on_tcp_ip.protocol_layer, "    ");
     print_xcp_on_ip_common(host_name, address, ipv6, *port);
 }
 
@@ -297,6 +1828,7 @@ fn print_xcp_on_udp_ip(xcp_on_udp_ip: &XcpOnUdpIp) {
         ..
     } = xcp_on_udp_ip;
     println!("  XCP on UDP/IP");
+    print_seed_key(&xcp_on_udp_ip.protocol_layer, "    ");
     print_xcp_on_ip_common(host_name, address, ipv6, *port);
 }
 
@@ -317,3 +1849,514 @@ fn print_xcp_on_ip_common(
     }
     println!("Port: {port}");
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_edit_xcp_on_can() {
+        let mut a2l_file = a2lfile::new();
+        let settings = XcpOnCanSettings {
+            can_id_master: Some(0x123),
+            can_id_slave: Some(0x8000_0456),
+            baudrate: Some(500_000),
+            sample_point: None,
+        };
+        edit_xcp_on_can(&mut a2l_file, &settings).unwrap();
+        assert!(a2l_file.project.module[0].a2ml.is_some());
+        assert_eq!(a2l_file.project.module[0].if_data.len(), 1);
+
+        // edit the existing block again; no additional IF_DATA should be created
+        let settings = XcpOnCanSettings {
+            sample_point: Some(75),
+            ..Default::default()
+        };
+        edit_xcp_on_can(&mut a2l_file, &settings).unwrap();
+        assert_eq!(a2l_file.project.module[0].if_data.len(), 1);
+
+        // write the file and load it again to make sure that the result is valid
+        let text = a2l_file.write_to_string();
+        let mut log_msgs = Vec::new();
+        let reloaded = a2lfile::load_from_string(&text, None, &mut log_msgs, true).unwrap();
+        let decoded = A2mlVector::load_from_ifdata(&reloaded.project.module[0].if_data[0]).unwrap();
+        let can_parameters = decoded.xcp.unwrap().xcp_on_can.unwrap().can_parameters;
+        assert_eq!(can_parameters.can_id_master.unwrap().value, 0x123);
+        assert_eq!(can_parameters.can_id_slave.unwrap().value, 0x8000_0456);
+        assert_eq!(can_parameters.baudrate.unwrap().value, 500_000);
+        assert_eq!(can_parameters.sample_point.unwrap().value, 75);
+
+        // an XCP IF_DATA that does not match the A2ML specification is not replaced or duplicated
+        let text = r#"ASAP2_VERSION 1 71
+/begin PROJECT p ""
+/begin MODULE m ""
+/begin IF_DATA XCP 1 2 3 /end IF_DATA
+/end MODULE
+/end PROJECT"#;
+        let mut a2l_file = a2lfile::load_from_string(text, None, &mut log_msgs, false).unwrap();
+        assert!(edit_xcp_on_can(&mut a2l_file, &settings).is_err());
+        assert_eq!(a2l_file.project.module[0].if_data.len(), 1);
+    }
+
+    #[test]
+    fn test_create_xcp() {
+        let mut a2l_file = a2lfile::new();
+        let mut template = XcpTemplate::new(XcpTransport::Can);
+        template.set_from_arg("T1=500").unwrap();
+        template.set_from_arg("MAX_EVENT_CHANNEL=0x10").unwrap();
+        template.set_from_arg("BYTE_ORDER=MSB_FIRST").unwrap();
+        assert!(template.set_from_arg("T8=1").is_err());
+        assert!(template.set_from_arg("MAX_CTO=300").is_err());
+
+        let mut log_msgs = Vec::new();
+        create_xcp(&mut a2l_file, &template, &mut log_msgs);
+        assert!(log_msgs.is_empty());
+        let text = a2l_file.write_to_string();
+        let mut load_msgs = Vec::new();
+        let reloaded = a2lfile::load_from_string(&text, None, &mut load_msgs, true).unwrap();
+        let decoded = A2mlVector::load_from_ifdata(&reloaded.project.module[0].if_data[0]).unwrap();
+        let xcp = decoded.xcp.unwrap();
+        let protocol_layer = xcp.protocol_layer.unwrap();
+        assert_eq!(protocol_layer.t1, 500);
+        assert_eq!(protocol_layer.max_cto, 8);
+        assert_eq!(
+            protocol_layer.byte_order_msb,
+            ByteOrderMsb::ByteOrderMsbFirst
+        );
+        assert_eq!(xcp.daq.unwrap().max_event_channel, 16);
+        assert!(xcp.xcp_on_can.is_some());
+
+        // the existing XCP settings are not replaced
+        create_xcp(&mut a2l_file, &template, &mut log_msgs);
+        assert_eq!(log_msgs.len(), 1);
+        assert_eq!(a2l_file.project.module[0].if_data.len(), 1);
+    }
+
+    #[test]
+    fn test_edit_xcp_on_flx() {
+        let mut a2l_file = a2lfile::new();
+        let settings = XcpOnFlxSettings {
+            fibex_file: Some("cluster.xml".to_string()),
+            cluster_id: Some("Cluster1".to_string()),
+            nax: Some(3),
+            t1: None,
+        };
+        edit_xcp_on_flx(&mut a2l_file, &settings).unwrap();
+
+        let text = a2l_file.write_to_string();
+        let mut log_msgs = Vec::new();
+        let reloaded = a2lfile::load_from_string(&text, None, &mut log_msgs, true).unwrap();
+        let decoded = A2mlVector::load_from_ifdata(&reloaded.project.module[0].if_data[0]).unwrap();
+        let flx_parameters = decoded.xcp.unwrap().xcp_on_flx.unwrap().flx_parameters;
+        assert_eq!(flx_parameters.fibex_file, "cluster.xml");
+        assert_eq!(flx_parameters.cluster_id, "Cluster1");
+        assert_eq!(flx_parameters.nax, 3);
+        assert_eq!(flx_parameters.t1, 1000);
+    }
+
+    #[test]
+    fn test_parse_cycle_time() {
+        assert_eq!(parse_cycle_time("10ms").unwrap(), (10, 6));
+        assert_eq!(parse_cycle_time("10").unwrap(), (10, 6));
+        assert_eq!(parse_cycle_time("1000ms").unwrap(), (100, 7));
+        assert_eq!(parse_cycle_time("250us").unwrap(), (250, 3));
+        assert_eq!(parse_cycle_time("0").unwrap(), (0, 6));
+        assert_eq!(parse_cycle_time("2s").unwrap(), (2, 9));
+        assert!(parse_cycle_time("257us").is_err());
+        assert!(parse_cycle_time("fast").is_err());
+    }
+
+    #[test]
+    fn test_daq_event_edits_from_pairs() {
+        let edits =
+            DaqEventEdit::from_pairs(&["task_1ms", "1ms"], &["a", "b"], &["c", "100ms"]).unwrap();
+        assert_eq!(edits.len(), 3);
+        assert!(
+            matches!(&edits[0], DaqEventEdit::Add { name, cycle: (1, 6) } if name == "task_1ms")
+        );
+        assert!(
+            matches!(&edits[1], DaqEventEdit::Rename { name, new_name } if name == "a" && new_name == "b")
+        );
+        assert!(matches!(
+            &edits[2],
+            DaqEventEdit::Retime {
+                cycle: (100, 6),
+                ..
+            }
+        ));
+        assert!(DaqEventEdit::from_pairs(&["task", "fast"], &[], &[]).is_err());
+
+        assert_eq!("CAN".parse::<XcpTransport>(), Ok(XcpTransport::Can));
+        assert_eq!("tcp".parse::<XcpEthProtocol>(), Ok(XcpEthProtocol::Tcp));
+        assert!("usb".parse::<XcpTransport>().is_err());
+    }
+
+    #[test]
+    fn test_edit_daq_events() {
+        let mut a2l_file = a2lfile::new();
+        let edits = vec![
+            DaqEventEdit::Add {
+                name: "task_10ms".to_string(),
+                cycle: (10, 6),
+            },
+            DaqEventEdit::Add {
+                name: "task_100ms".to_string(),
+                cycle: (100, 6),
+            },
+            DaqEventEdit::Rename {
+                name: "task_10ms".to_string(),
+                new_name: "fast_task".to_string(),
+            },
+            DaqEventEdit::Retime {
+                name: "task_100ms".to_string(),
+                cycle: (20, 7),
+            },
+            DaqEventEdit::Retime {
+                name: "unknown".to_string(),
+                cycle: (1, 6),
+            },
+        ];
+        let mut log_msgs = Vec::new();
+        // modules without XCP settings are not changed
+        edit_daq_events(&mut a2l_file, &edits, &mut log_msgs);
+        assert!(log_msgs.is_empty());
+        assert!(a2l_file.project.module[0].if_data.is_empty());
+
+        create_xcp(
+            &mut a2l_file,
+            &XcpTemplate::new(XcpTransport::Can),
+            &mut log_msgs,
+        );
+        edit_daq_events(&mut a2l_file, &edits, &mut log_msgs);
+        assert_eq!(log_msgs.len(), 1);
+
+        let decoded = A2mlVector::load_from_ifdata(&a2l_file.project.module[0].if_data[0]).unwrap();
+        let daq = decoded.xcp.unwrap().daq.unwrap();
+        assert_eq!(daq.max_event_channel, 2);
+        assert_eq!(daq.event[0].event_channel_name, "fast_task");
+        assert_eq!(daq.event[0].event_channel_number, 0);
+        assert_eq!(daq.event[1].event_channel_number, 1);
+        assert_eq!(daq.event[1].time_cycle, 20);
+        assert_eq!(daq.event[1].time_unit, 7);
+    }
+
+    #[test]
+    fn test_sync_daq_events() {
+        // the events must be in the key "events"
+        assert!(parse_daq_event_config("- name: a\n  channel: 0\n").is_err());
+        assert!(parse_daq_event_config("events:\n  - name: a\n").is_err());
+        assert!(parse_daq_event_config(
+            "events:\n  - name: a\n    channel: 0\n  - name: b\n    channel: 0\n"
+        )
+        .is_err());
+        assert!(
+            parse_daq_event_config("events:\n  - name: a\n    channel: 0\n    cycle: 1000s\n")
+                .is_err()
+        );
+        assert!(parse_daq_event_config("events: [{name: a, channel: 70000}]").is_err());
+        // flow style is valid YAML, and lists in other keys are not events
+        let definitions = parse_daq_event_config(
+            "codegen:\n  - name: ignored\nevents: [{name: a, channel: 2, cycle: 0}]",
+        )
+        .unwrap();
+        assert_eq!(definitions.len(), 1);
+        assert_eq!(definitions[0].channel, 2);
+        assert_eq!(definitions[0].cycle, (0, 6));
+
+        let definitions = parse_daq_event_config(
+            r#"
+# generated by the firmware build
+events:
+  - name: "task_10ms"   # fast task
+    channel: 0
+    cycle: 10ms
+    priority: 2
+    codegen:
+      name: ignored
+  - name: 'task #2'
+    channel: 1
+    cycle: 100ms
+  - channel: 3
+    name: background
+"#,
+        )
+        .unwrap();
+        assert_eq!(definitions.len(), 3);
+        assert_eq!(definitions[0].name, "task_10ms");
+        assert_eq!(definitions[0].priority, 2);
+        assert_eq!(definitions[1].name, "task #2");
+        assert_eq!(definitions[1].cycle, (100, 6));
+        assert_eq!(definitions[2].cycle, (0, 6));
+
+        let mut a2l_file = a2lfile::new();
+        let edits = vec![
+            DaqEventEdit::Add {
+                name: "old_10ms".to_string(),
+                cycle: (10, 6),
+            },
+            DaqEventEdit::Add {
+                name: "unused".to_string(),
+                cycle: (1, 6),
+            },
+            DaqEventEdit::Add {
+                name: "obsolete".to_string(),
+                cycle: (1, 6),
+            },
+        ];
+        let mut log_msgs = Vec::new();
+        create_xcp(
+            &mut a2l_file,
+            &XcpTemplate::new(XcpTransport::Can),
+            &mut log_msgs,
+        );
+        edit_daq_events(&mut a2l_file, &edits, &mut log_msgs);
+        // channel 0 is renamed, channel 1 is retimed, channel 2 is removed and channel 3 is added
+        let count = sync_daq_events(&mut a2l_file, &definitions, &mut log_msgs);
+        assert_eq!(count, 4);
+        assert_eq!(log_msgs.len(), 1);
+        // the second sync finds nothing to change
+        assert_eq!(
+            sync_daq_events(&mut a2l_file, &definitions, &mut log_msgs),
+            0
+        );
+
+        let decoded = A2mlVector::load_from_ifdata(&a2l_file.project.module[0].if_data[0]).unwrap();
+        let daq = decoded.xcp.unwrap().daq.unwrap();
+        assert_eq!(daq.event.len(), 3);
+        // the highest channel number is 3
+        assert_eq!(daq.max_event_channel, 4);
+        assert_eq!(daq.event[0].event_channel_name, "task_10ms");
+        assert_eq!(daq.event[0].priority, 2);
+        assert_eq!(daq.event[1].time_cycle, 100);
+        assert_eq!(daq.event[2].event_channel_number, 3);
+        assert_eq!(daq.event[2].event_channel_short_name, "backgrou");
+    }
+
+    #[test]
+    fn test_edit_seed_key() {
+        let mut a2l_file = a2lfile::new();
+        let mut log_msgs = Vec::new();
+        create_xcp(
+            &mut a2l_file,
+            &XcpTemplate::new(XcpTransport::Can),
+            &mut log_msgs,
+        );
+
+        edit_seed_key(&mut a2l_file, Some("default.dll"), None, &mut log_msgs);
+        edit_seed_key(
+            &mut a2l_file,
+            Some("can.dll"),
+            Some(XcpTransport::Can),
+            &mut log_msgs,
+        );
+        edit_seed_key(
+            &mut a2l_file,
+            Some("tcp.dll"),
+            Some(XcpTransport::Tcp),
+            &mut log_msgs,
+        );
+        // there is no TCP transport layer
+        assert_eq!(log_msgs.len(), 1);
+
+        let decoded = A2mlVector::load_from_ifdata(&a2l_file.project.module[0].if_data[0]).unwrap();
+        let xcp = decoded.xcp.unwrap();
+        let get_dll = |protocol_layer: &Option<ProtocolLayer>| {
+            protocol_layer
+                .as_ref()
+                .and_then(|pl| pl.seed_and_key_external_function.as_ref())
+                .map(|seed_key| seed_key.funcname.clone())
+        };
+        assert_eq!(get_dll(&xcp.protocol_layer).unwrap(), "default.dll");
+        assert_eq!(
+            get_dll(&xcp.xcp_on_can.unwrap().protocol_layer).unwrap(),
+            "can.dll"
+        );
+
+        // clearing without a transport layer removes all entries
+        edit_seed_key(&mut a2l_file, None, None, &mut log_msgs);
+        let decoded = A2mlVector::load_from_ifdata(&a2l_file.project.module[0].if_data[0]).unwrap();
+        let xcp = decoded.xcp.unwrap();
+        assert!(get_dll(&xcp.protocol_layer).is_none());
+        assert!(get_dll(&xcp.xcp_on_can.unwrap().protocol_layer).is_none());
+    }
+
+    #[test]
+    fn test_edit_protocol_layer() {
+        let mut a2l_file = a2lfile::new();
+        let mut log_msgs = Vec::new();
+        create_xcp(
+            &mut a2l_file,
+            &XcpTemplate::new(XcpTransport::Can),
+            &mut log_msgs,
+        );
+
+        edit_protocol_layer(&mut a2l_file, &["T1=250", "T4=0x100"], &mut log_msgs).unwrap();
+        let decoded = A2mlVector::load_from_ifdata(&a2l_file.project.module[0].if_data[0]).unwrap();
+        let protocol_layer = decoded.xcp.unwrap().protocol_layer.unwrap();
+        assert_eq!(protocol_layer.t1, 250);
+        assert_eq!(protocol_layer.t4, 256);
+
+        // classic CAN can't transport more than 8 bytes
+        assert!(edit_protocol_layer(&mut a2l_file, &["MAX_DTO=64"], &mut log_msgs).is_err());
+        // XCP 1.0 does not have T7
+        assert!(edit_protocol_layer(&mut a2l_file, &["T7=100"], &mut log_msgs).is_err());
+        assert!(edit_protocol_layer(&mut a2l_file, &["T1=0"], &mut log_msgs).is_err());
+        assert!(edit_protocol_layer(&mut a2l_file, &["T9=1"], &mut log_msgs).is_err());
+        assert!(log_msgs.is_empty());
+
+        // the failed edits did not change anything
+        let decoded = A2mlVector::load_from_ifdata(&a2l_file.project.module[0].if_data[0]).unwrap();
+        let protocol_layer = decoded.xcp.unwrap().protocol_layer.unwrap();
+        assert_eq!(protocol_layer.max_dto, 8);
+        assert_eq!(protocol_layer.t1, 250);
+
+        // MAX_DTO=64 is valid for XCP on TCP in the new first module, but not for classic CAN in the second module
+        let mut module = Module::new("tcp".to_string(), String::new());
+        edit_xcp(&mut module, |xcp| {
+            *xcp = XcpTemplate::new(XcpTransport::Tcp).build();
+        })
+        .unwrap();
+        a2l_file.project.module.insert(0, module);
+        assert!(edit_protocol_layer(&mut a2l_file, &["MAX_DTO=64"], &mut log_msgs).is_err());
+        let decoded = A2mlVector::load_from_ifdata(&a2l_file.project.module[0].if_data[0]).unwrap();
+        let protocol_layer = decoded.xcp.unwrap().protocol_layer.unwrap();
+        assert_eq!(protocol_layer.max_dto, 1460);
+    }
+
+    #[test]
+    fn test_xcp_to_json() {
+        let mut template = XcpTemplate::new(XcpTransport::Tcp);
+        template.set_from_arg("MAX_DTO=1000").unwrap();
+        let mut xcp = template.build();
+        if let Some(xcp_on_tcp_ip) = &mut xcp.xcp_on_tcp_ip {
+            xcp_on_tcp_ip.tcp_ip_parameters.address = Some(Address2::new("10.0.0.1".to_string()));
+        }
+        let json = xcp_to_json(&xcp);
+        assert_eq!(json["protocol_layer"]["max_dto"], 1000);
+        assert_eq!(json["xcp_on_tcp_ip"]["address"], "10.0.0.1");
+        assert_eq!(json["xcp_on_tcp_ip"]["port"], XCP_ON_ETH_DEFAULT_PORT);
+        assert!(json.get("xcp_on_can").is_none());
+        assert_eq!(json["daq"]["config_type"], "Dynamic");
+        assert!(json["daq"]["events"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_edit_xcp_on_eth() {
+        let mut a2l_file = a2lfile::new();
+        let settings = XcpOnEthSettings {
+            protocol: None,
+            address: Some("192.168.0.10".to_string()),
+            port: None,
+        };
+        edit_xcp_on_eth(&mut a2l_file, &settings).unwrap();
+        let decoded = A2mlVector::load_from_ifdata(&a2l_file.project.module[0].if_data[0]).unwrap();
+        let xcp = decoded.xcp.unwrap();
+        let udp_ip_parameters = xcp.xcp_on_udp_ip.unwrap().udp_ip_parameters;
+        assert_eq!(udp_ip_parameters.port, XCP_ON_ETH_DEFAULT_PORT);
+        assert_eq!(
+            udp_ip_parameters.address.unwrap().address_v4,
+            "192.168.0.10"
+        );
+
+        // switch to TCP: the address is kept, the port is changed
+        let settings = XcpOnEthSettings {
+            protocol: Some(XcpEthProtocol::Tcp),
+            address: None,
+            port: Some(1234),
+        };
+        edit_xcp_on_eth(&mut a2l_file, &settings).unwrap();
+        let decoded = A2mlVector::load_from_ifdata(&a2l_file.project.module[0].if_data[0]).unwrap();
+        let xcp = decoded.xcp.unwrap();
+        assert!(xcp.xcp_on_udp_ip.is_none());
+        let tcp_ip_parameters = xcp.xcp_on_tcp_ip.unwrap().tcp_ip_parameters;
+        assert_eq!(tcp_ip_parameters.port, 1234);
+        assert_eq!(
+            tcp_ip_parameters.address.unwrap().address_v4,
+            "192.168.0.10"
+        );
+
+        // a host name replaces the address
+        let settings = XcpOnEthSettings {
+            protocol: None,
+            address: Some("ecu.local".to_string()),
+            port: None,
+        };
+        edit_xcp_on_eth(&mut a2l_file, &settings).unwrap();
+        let decoded = A2mlVector::load_from_ifdata(&a2l_file.project.module[0].if_data[0]).unwrap();
+        let tcp_ip_parameters = decoded
+            .xcp
+            .unwrap()
+            .xcp_on_tcp_ip
+            .unwrap()
+            .tcp_ip_parameters;
+        assert!(tcp_ip_parameters.address.is_none());
+        assert_eq!(tcp_ip_parameters.host_name.unwrap().hostname, "ecu.local");
+    }
+
+    #[test]
+    fn test_check_max_refresh() {
+        let mut a2l_file = a2lfile::new();
+        let edits = vec![
+            DaqEventEdit::Add {
+                name: "task_10ms".to_string(),
+                cycle: (10, 6),
+            },
+            DaqEventEdit::Add {
+                name: "task_100ms".to_string(),
+                cycle: (100, 6),
+            },
+            DaqEventEdit::Add {
+                name: "trigger".to_string(),
+                cycle: (0, 6),
+            },
+        ];
+        let mut log_msgs = Vec::new();
+        create_xcp(
+            &mut a2l_file,
+            &XcpTemplate::new(XcpTransport::Can),
+            &mut log_msgs,
+        );
+        edit_daq_events(&mut a2l_file, &edits, &mut log_msgs);
+        let module = &mut a2l_file.project.module[0];
+        for (name, scaling_unit, rate) in [("m_10ms", 4, 1), ("m_80ms", 3, 80), ("m_angle", 103, 1)]
+        {
+            let mut measurement = a2lfile::Measurement::new(
+                name.to_string(),
+                String::new(),
+                a2lfile::DataType::Ubyte,
+                "NO_COMPU_METHOD".to_string(),
+                0,
+                0.0,
+                0.0,
+                255.0,
+            );
+            measurement.max_refresh = Some(MaxRefresh::new(scaling_unit, rate));
+            module.measurement.push(measurement);
+        }
+
+        let result = check_max_refresh(&mut a2l_file, false, &mut log_msgs);
+        assert_eq!(
+            result,
+            MaxRefreshResult {
+                mismatched: 1,
+                fixed: 0
+            }
+        );
+        assert_eq!(log_msgs.len(), 1);
+        assert!(log_msgs[0].contains("task_100ms with a cycle time of 100ms"));
+
+        let result = check_max_refresh(&mut a2l_file, true, &mut log_msgs);
+        assert_eq!(result.fixed, 1);
+        let max_refresh = a2l_file.project.module[0].measurement[1]
+            .max_refresh
+            .as_ref()
+            .unwrap();
+        assert_eq!((max_refresh.scaling_unit, max_refresh.rate), (5, 1));
+        let result = check_max_refresh(&mut a2l_file, false, &mut log_msgs);
+        assert_eq!(result.mismatched, 0);
+
+        assert_eq!(max_refresh_from_period(2_000_000), Some((3, 2)));
+        assert_eq!(max_refresh_from_period(500), None);
+        assert_eq!(format_period(250_000), "250us");
+    }
+}
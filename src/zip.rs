@@ -0,0 +1,262 @@
+//! Reading of ZIP archives, so that a2l files can be loaded directly from a delivery archive
+//!
+//! Only the features that are needed for deliveries are supported: stored and deflated entries
+//! without encryption and without ZIP64 extensions.
+
+use crate::checksum::{calculate_checksum, ChecksumType};
+use std::ffi::OsStr;
+use std::path::{Component, Path, PathBuf};
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIR_SIGNATURE: u32 = 0x0605_4b50;
+
+/// A ZIP archive that has been read into memory
+#[derive(Debug)]
+pub struct ZipArchive {
+    data: Vec<u8>,
+    entries: Vec<ZipEntry>,
+}
+
+#[derive(Debug)]
+struct ZipEntry {
+    name: String,
+    method: u16,
+    flags: u16,
+    crc32: u32,
+    compressed_size: usize,
+    uncompressed_size: usize,
+    local_header_offset: usize,
+}
+
+impl ZipArchive {
+    /// Read the archive and its central directory
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the file cannot be read or is not a valid ZIP archive
+    pub fn load(filename: &Path) -> Result<Self, String> {
+        let data = std::fs::read(filename).map_err(|err| {
+            format!(
+                "Error: could not read archive {}: {err}",
+                filename.to_string_lossy()
+            )
+        })?;
+        Self::from_bytes(data).map_err(|err| {
+            format!(
+                "Error: invalid archive {}: {err}",
+                filename.to_string_lossy()
+            )
+        })
+    }
+
+    fn from_bytes(data: Vec<u8>) -> Result<Self, String> {
+        // the end of central directory record is followed by a comment of up to 64k
+        let search_start = data.len().saturating_sub(22 + 0xFFFF);
+        let eocd = (search_start..data.len().saturating_sub(21))
+            .rev()
+            .find(|pos| read_u32(&data, *pos) == Some(END_OF_CENTRAL_DIR_SIGNATURE))
+            .ok_or("the end of the central directory was not found")?;
+        let entry_count = read_u16(&data, eocd + 10).ok_or("truncated archive")?;
+        let cd_offset = read_u32(&data, eocd + 16).ok_or("truncated archive")?;
+        if entry_count == 0xFFFF || cd_offset == 0xFFFF_FFFF {
+            return Err("ZIP64 archives are not supported".to_string());
+        }
+
+        let mut entries = Vec::with_capacity(usize::from(entry_count));
+        let mut pos = cd_offset as usize;
+        for _ in 0..entry_count {
+            if read_u32(&data, pos) != Some(CENTRAL_HEADER_SIGNATURE) {
+                return Err("invalid central directory".to_string());
+            }
+            let field16 = |offset| read_u16(&data, pos + offset).ok_or("truncated archive");
+            let field32 = |offset| read_u32(&data, pos + offset).ok_or("truncated archive");
+            let name_len = usize::from(field16(28)?);
+            let extra_len = usize::from(field16(30)?);
+            let comment_len = usize::from(field16(32)?);
+            let name_bytes = data
+                .get(pos + 46..pos + 46 + name_len)
+                .ok_or("truncated archive")?;
+            entries.push(ZipEntry {
+                // some tools write Windows path separators
+                name: String::from_utf8_lossy(name_bytes).replace('\\', "/"),
+                flags: field16(8)?,
+                method: field16(10)?,
+                crc32: field32(16)?,
+                compressed_size: field32(20)? as usize,
+                uncompressed_size: field32(24)? as usize,
+                local_header_offset: field32(42)? as usize,
+            });
+            pos += 46 + name_len + extra_len + comment_len;
+        }
+        Ok(Self { data, entries })
+    }
+
+    /// The names of all files in the archive; directories are not included
+    pub fn file_names(&self) -> impl Iterator<Item = &str> {
+        self.entries
+            .iter()
+            .filter(|entry| !entry.name.ends_with('/'))
+            .map(|entry| entry.name.as_str())
+    }
+
+    /// Read and decompress a file from the archive
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the file does not exist, uses an unsupported compression or is damaged
+    pub fn read(&self, name: &str) -> Result<Vec<u8>, String> {
+        let entry = self
+            .entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .ok_or_else(|| format!("Error: the archive does not contain {name}"))?;
+        if entry.flags & 1 != 0 {
+            return Err(format!("Error: {name} is encrypted"));
+        }
+        let offset = entry.local_header_offset;
+        if read_u32(&self.data, offset) != Some(LOCAL_HEADER_SIGNATURE) {
+            return Err(format!("Error: the archive entry of {name} is damaged"));
+        }
+        let truncated = || format!("Error: the archive entry of {name} is truncated");
+        let name_len = usize::from(read_u16(&self.data, offset + 26).ok_or_else(truncated)?);
+        let extra_len = usize::from(read_u16(&self.data, offset + 28).ok_or_else(truncated)?);
+        let start = offset + 30 + name_len + extra_len;
+        let compressed = self
+            .data
+            .get(start..start + entry.compressed_size)
+            .ok_or_else(truncated)?;
+        let content = match entry.method {
+            0 => compressed.to_vec(),
+            // the size in the header is not trusted: it is only used as the limit of the output
+            8 => miniz_oxide::inflate::decompress_to_vec_with_limit(
+                compressed,
+                entry.uncompressed_size,
+            )
+            .map_err(|err| format!("Error: could not decompress {name}: {err}"))?,
+            method => {
+                return Err(format!(
+                    "Error: {name} uses the unsupported compression method {method}"
+                ))
+            }
+        };
+        if content.len() != entry.uncompressed_size
+            || calculate_checksum(ChecksumType::Crc32, &content, false) != entry.crc32
+        {
+            return Err(format!("Error: the content of {name} is damaged"));
+        }
+        Ok(content)
+    }
+
+    /// Write all files of the archive into the directory
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if a file cannot be read from the archive or written, or if its name would place it
+    /// outside of the directory
+    pub fn extract_all(&self, directory: &Path) -> Result<(), String> {
+        for name in self.file_names() {
+            let relative = Path::new(name);
+            if !relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+            {
+                return Err(format!(
+                    "Error: the archive contains the unsafe path {name}"
+                ));
+            }
+            let target = directory.join(relative);
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent).map_err(|err| {
+                    format!(
+                        "Error: could not create {}: {err}",
+                        parent.to_string_lossy()
+                    )
+                })?;
+            }
+            std::fs::write(&target, self.read(name)?).map_err(|err| {
+                format!("Error: could not write {}: {err}", target.to_string_lossy())
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Split an input file name of the form `archive.zip!path/inner.a2l` into the archive and the inner file.
+/// A plain `archive.zip` is also recognized; in this case the inner file is None.
+#[must_use]
+pub fn split_archive_path(filename: &OsStr) -> Option<(PathBuf, Option<String>)> {
+    let text = filename.to_str()?;
+    let lower = text.to_ascii_lowercase();
+    if let Some(pos) = lower.find(".zip!") {
+        let inner = text[pos + 5..].replace('\\', "/");
+        Some((PathBuf::from(&text[..pos + 4]), Some(inner)))
+    } else if lower.ends_with(".zip") {
+        Some((PathBuf::from(text), None))
+    } else {
+        None
+    }
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Option<u16> {
+    let bytes = data.get(pos..pos + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Option<u32> {
+    let bytes = data.get(pos..pos + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_read_zip() {
+        // the archive contains the stored file "a.txt" and the deflated files "dir/b.a2l" and "dir/inc/extra.a2l"
+        let archive = ZipArchive::from_bytes(TEST_ZIP.to_vec()).unwrap();
+        let names: Vec<&str> = archive.file_names().collect();
+        assert_eq!(names, vec!["a.txt", "dir/b.a2l", "dir/inc/extra.a2l"]);
+        assert_eq!(archive.read("a.txt").unwrap(), b"hello\n");
+        let text = String::from_utf8(archive.read("dir/b.a2l").unwrap()).unwrap();
+        assert!(text.starts_with("ASAP2_VERSION 1 71"));
+        assert_eq!(text.matches("/begin MEASUREMENT").count(), 20);
+        let text = String::from_utf8(archive.read("dir/inc/extra.a2l").unwrap()).unwrap();
+        assert!(text.contains("ECU_ADDRESS 0x2000"));
+        assert!(archive.read("missing").is_err());
+        assert!(ZipArchive::from_bytes(b"not a zip file".to_vec()).is_err());
+
+        assert_eq!(
+            split_archive_path(OsStr::new("delivery.ZIP!a2l/ecu.a2l")),
+            Some((
+                PathBuf::from("delivery.ZIP"),
+                Some("a2l/ecu.a2l".to_string())
+            ))
+        );
+        assert_eq!(
+            split_archive_path(OsStr::new("delivery.zip")),
+            Some((PathBuf::from("delivery.zip"), None))
+        );
+        assert_eq!(split_archive_path(OsStr::new("ecu.a2l")), None);
+    }
+
+    #[test]
+    fn test_size_limit() {
+        // set the uncompressed size of dir/b.a2l in the central directory to 16 bytes.
+        // Decompression must stop at this limit instead of trusting the data
+        let mut data = TEST_ZIP.to_vec();
+        let name_pos = (46..data.len())
+            .find(|pos| {
+                read_u32(&data, pos - 46) == Some(CENTRAL_HEADER_SIGNATURE)
+                    && data[*pos..].starts_with(b"dir/b.a2l")
+            })
+            .unwrap();
+        data[name_pos - 46 + 24..name_pos - 46 + 28].copy_from_slice(&16u32.to_le_bytes());
+        let archive = ZipArchive::from_bytes(data).unwrap();
+        assert!(archive.read("dir/b.a2l").is_err());
+        assert!(archive.read("dir/inc/extra.a2l").is_ok());
+    }
+
+    const TEST_ZIP: &[u8] = include_bytes!("../tests/test_delivery.zip");
+}
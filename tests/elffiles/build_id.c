@@ -0,0 +1,11 @@
+// build_id.elf built with: gcc 12.2.0 (x86_64 linux)
+// gcc -g -O0 -nostdlib -static -Wl,--build-id=sha1 -Wl,-e,main build_id.c -o build_id.elf
+
+const char sw_version[] = "SW 1.2.3";
+unsigned int counter;
+
+int main(void)
+{
+    counter++;
+    return (int)sw_version[0];
+}
@@ -0,0 +1,77 @@
+// JavaScript wrapper for the WebAssembly build of a2ltool
+//
+// build the module with
+//   cargo build --lib --release --target wasm32-unknown-unknown --no-default-features
+// and load it with
+//   const a2ltool = await loadA2ltool(fetch("a2ltool.wasm"));
+
+export async function loadA2ltool(source) {
+    const { instance } = await WebAssembly.instantiateStreaming(source, {});
+    const wasm = instance.exports;
+    const encoder = new TextEncoder();
+    const decoder = new TextDecoder();
+
+    // copy a string into the wasm memory; returns [ptr, len]
+    function passString(text) {
+        const bytes = encoder.encode(text);
+        const ptr = wasm.a2ltool_alloc(bytes.length);
+        new Uint8Array(wasm.memory.buffer, ptr, bytes.length).set(bytes);
+        return [ptr, bytes.length];
+    }
+
+    function takeResult(status) {
+        const ptr = wasm.a2ltool_result_ptr();
+        const len = wasm.a2ltool_result_len();
+        const text = decoder.decode(new Uint8Array(wasm.memory.buffer, ptr, len));
+        if (status !== 0) {
+            throw new Error(text);
+        }
+        return text;
+    }
+
+    return {
+        // check an a2l file; returns an array of problem descriptions
+        check(a2lText) {
+            const [ptr, len] = passString(a2lText);
+            try {
+                return JSON.parse(takeResult(wasm.a2ltool_check(ptr, len)));
+            } finally {
+                wasm.a2ltool_free(ptr, len);
+            }
+        },
+
+        // merge the modules of two a2l files; returns the text of the merged file
+        merge(a2lText, mergeText) {
+            const [ptr, len] = passString(a2lText);
+            const [mergePtr, mergeLen] = passString(mergeText);
+            try {
+                return takeResult(wasm.a2ltool_merge(ptr, len, mergePtr, mergeLen));
+            } finally {
+                wasm.a2ltool_free(ptr, len);
+                wasm.a2ltool_free(mergePtr, mergeLen);
+            }
+        },
+
+        // count the objects, data types and IF_DATA blocks of an a2l file; returns an object
+        statistics(a2lText) {
+            const [ptr, len] = passString(a2lText);
+            try {
+                return JSON.parse(takeResult(wasm.a2ltool_statistics(ptr, len)));
+            } finally {
+                wasm.a2ltool_free(ptr, len);
+            }
+        },
+
+        // compare the objects of two a2l files; returns { added, removed, changed }
+        diff(a2lText, otherText) {
+            const [ptr, len] = passString(a2lText);
+            const [otherPtr, otherLen] = passString(otherText);
+            try {
+                return JSON.parse(takeResult(wasm.a2ltool_diff(ptr, len, otherPtr, otherLen)));
+            } finally {
+                wasm.a2ltool_free(ptr, len);
+                wasm.a2ltool_free(otherPtr, otherLen);
+            }
+        },
+    };
+}